@@ -0,0 +1,811 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::time::{Duration, Instant};
+
+use ffmpeg::format::context::Input as AvInput;
+use ffmpeg::media::Type as AvMediaType;
+
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::io::Reader;
+use crate::core::packet::Packet;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Callback that is invoked when the reader switches to a different variant.
+type VariantCallback = Box<dyn FnMut(&Variant) + Send>;
+
+/// Variant of an adaptive source, such as an HLS master playlist or a DASH manifest: one rung of
+/// its bitrate ladder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    /// Bit rate that the variant advertises, in bits per second.
+    pub bandwidth: u64,
+    /// Width and height of the video of the variant, if it has video.
+    pub resolution: Option<(u32, u32)>,
+    /// Index of the video stream of the variant, if it has one.
+    pub video_stream_index: Option<usize>,
+    /// Index of the audio stream of the variant, if it has one.
+    pub audio_stream_index: Option<usize>,
+    /// Indices of all streams that belong to the variant.
+    pub stream_indices: Vec<usize>,
+}
+
+impl Variant {
+    /// Index of the stream of which the keyframes mark segment boundaries.
+    fn key_stream_index(&self) -> Option<usize> {
+        self.video_stream_index
+            .or_else(|| self.stream_indices.first().copied())
+    }
+}
+
+/// Fixed selection of the variant of an adaptive source, for players that do not switch variants.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum VariantSelection {
+    /// Variant with the lowest bandwidth.
+    Lowest,
+    /// Variant with the highest bandwidth.
+    #[default]
+    Highest,
+    /// Variant with the highest bandwidth that does not exceed the given bit rate, or the variant
+    /// with the lowest bandwidth if none fits.
+    MaxBandwidth(u64),
+    /// Variant with the highest bandwidth of which the video is not higher than the given number
+    /// of lines, or the variant with the lowest bandwidth if none fits.
+    MaxHeight(u32),
+}
+
+impl VariantSelection {
+    /// Select a variant.
+    ///
+    /// # Arguments
+    ///
+    /// * `variants` - Variants in ascending order of bandwidth.
+    ///
+    /// # Return value
+    ///
+    /// Index of the selected variant, or `None` if there are no variants.
+    pub fn select(self, variants: &[Variant]) -> Option<usize> {
+        if variants.is_empty() {
+            return None;
+        }
+        let fits = |variant: &Variant| match self {
+            VariantSelection::Lowest => false,
+            VariantSelection::Highest => true,
+            VariantSelection::MaxBandwidth(bandwidth) => variant.bandwidth <= bandwidth,
+            VariantSelection::MaxHeight(height) => variant
+                .resolution
+                .map_or(true, |(_, variant_height)| variant_height <= height),
+        };
+        Some(variants.iter().rposition(fits).unwrap_or(0))
+    }
+}
+
+/// Policy that decides which variant of an adaptive source to play, based on the throughput
+/// measured while downloading its segments.
+///
+/// # Example
+///
+/// ```ignore
+/// // Only pick variants that take up at most 70% of the measured throughput.
+/// let policy = AbrPolicy::new().with_safety_factor(0.7);
+/// let mut reader = AdaptiveReaderBuilder::new(Reader::new(url).unwrap())
+///     .with_policy(policy)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AbrPolicy {
+    safety_factor: f64,
+    smoothing: f64,
+    min_samples: usize,
+    min_sample_duration: Duration,
+}
+
+impl AbrPolicy {
+    /// Create a policy with sensible defaults: throughput is measured per segment of at least two
+    /// seconds, and once two segments have been measured the highest variant that fits in 80% of
+    /// the average throughput is picked. The average weighs the last segment by 30%.
+    pub fn new() -> Self {
+        Self {
+            safety_factor: 0.8,
+            smoothing: 0.3,
+            min_samples: 2,
+            min_sample_duration: Duration::from_secs(2),
+        }
+    }
+
+    /// Set the fraction of the measured throughput that the bandwidth of a variant may take up.
+    ///
+    /// # Arguments
+    ///
+    /// * `safety_factor` - Fraction between `0.0` and `1.0`.
+    pub fn with_safety_factor(mut self, safety_factor: f64) -> Self {
+        self.safety_factor = safety_factor.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the weight of the last measurement in the average throughput. Higher values react
+    /// faster to changes in throughput, but switch variants more often.
+    ///
+    /// # Arguments
+    ///
+    /// * `smoothing` - Weight between `0.0` (exclusive) and `1.0`.
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(f64::EPSILON, 1.0);
+        self
+    }
+
+    /// Set the number of measurements to take before the first switch.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_samples` - Number of measurements.
+    pub fn with_min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples.max(1);
+        self
+    }
+
+    /// Set the minimum amount of media that a single measurement covers. Measurements end at
+    /// segment boundaries, so short segments are combined until they are at least this long.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_sample_duration` - Minimum duration of media per measurement.
+    pub fn with_min_sample_duration(mut self, min_sample_duration: Duration) -> Self {
+        self.min_sample_duration = min_sample_duration;
+        self
+    }
+}
+
+impl Default for AbrPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tracks download throughput and applies an [`AbrPolicy`].
+#[derive(Debug, Clone)]
+pub struct AbrController {
+    policy: AbrPolicy,
+    // Average throughput in bits per second.
+    estimate: Option<f64>,
+    samples: usize,
+}
+
+impl AbrController {
+    /// Create a new controller without any measurements.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Policy to apply.
+    pub fn new(policy: AbrPolicy) -> Self {
+        Self {
+            policy,
+            estimate: None,
+            samples: 0,
+        }
+    }
+
+    /// Average measured throughput in bits per second, if anything was measured yet.
+    pub fn throughput(&self) -> Option<u64> {
+        self.estimate.map(|estimate| estimate as u64)
+    }
+
+    /// Record how long it took to download an amount of data.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Number of bytes downloaded.
+    /// * `elapsed` - Time spent waiting for the data. Zero if all of it was already buffered.
+    pub fn record(&mut self, bytes: usize, elapsed: Duration) {
+        // Data that was already buffered says nothing about the network.
+        if elapsed.is_zero() {
+            return;
+        }
+        let sample = (bytes * 8) as f64 / elapsed.as_secs_f64();
+        self.estimate = Some(match self.estimate {
+            Some(estimate) => estimate + self.policy.smoothing * (sample - estimate),
+            None => sample,
+        });
+        self.samples += 1;
+    }
+
+    /// Select the variant to play.
+    ///
+    /// # Arguments
+    ///
+    /// * `bandwidths` - Bandwidths of the variants in bits per second, in ascending order.
+    /// * `current` - Index of the variant that is currently playing.
+    ///
+    /// # Return value
+    ///
+    /// Index of the variant to play, which is `current` if nothing needs to change.
+    pub fn select(&self, bandwidths: &[u64], current: usize) -> usize {
+        match self.estimate {
+            Some(estimate) if self.samples >= self.policy.min_samples => {
+                let budget = estimate * self.policy.safety_factor;
+                bandwidths
+                    .iter()
+                    .rposition(|&bandwidth| bandwidth as f64 <= budget)
+                    .unwrap_or(0)
+            }
+            _ => current,
+        }
+    }
+
+    /// Forget about previous measurements.
+    pub fn reset(&mut self) {
+        self.estimate = None;
+        self.samples = 0;
+    }
+}
+
+/// Builds an [`AdaptiveReader`].
+pub struct AdaptiveReaderBuilder {
+    reader: Reader,
+    policy: AbrPolicy,
+    initial_variant: usize,
+    on_variant_change: Option<VariantCallback>,
+}
+
+impl AdaptiveReaderBuilder {
+    /// Create a builder for an adaptive reader on top of a reader of an HLS master playlist or a DASH
+    /// manifest.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader of the source.
+    pub fn new(reader: Reader) -> Self {
+        Self {
+            reader,
+            policy: AbrPolicy::default(),
+            initial_variant: 0,
+            on_variant_change: None,
+        }
+    }
+
+    /// Set the policy that decides when to switch variants.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Policy to apply.
+    pub fn with_policy(mut self, policy: AbrPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the variant to start with. By default playback starts with the variant with the lowest
+    /// bandwidth, so that it starts quickly.
+    ///
+    /// # Arguments
+    ///
+    /// * `initial_variant` - Index into [`AdaptiveReader::variants`].
+    pub fn with_initial_variant(mut self, initial_variant: usize) -> Self {
+        self.initial_variant = initial_variant;
+        self
+    }
+
+    /// Invoke a callback whenever the reader switches to a different variant, e.g. to set up a
+    /// decoder for its streams. The callback runs on the thread that reads, so it should not block
+    /// for long.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - Callback that receives the variant that is played from now on.
+    pub fn with_on_variant_change(
+        mut self,
+        callback: impl FnMut(&Variant) + Send + 'static,
+    ) -> Self {
+        self.on_variant_change = Some(Box::new(callback));
+        self
+    }
+
+    /// Build an [`AdaptiveReader`].
+    pub fn build(self) -> Result<AdaptiveReader> {
+        let variants = input_variants(&self.reader.input);
+
+        if variants.is_empty() {
+            return Err(Error::InvalidConfiguration {
+                field: "reader",
+                reason: "source has no variants, it is not an HLS or DASH manifest".to_string(),
+            });
+        }
+        if self.initial_variant >= variants.len() {
+            return Err(Error::InvalidConfiguration {
+                field: "initial_variant",
+                reason: format!("source has only {} variants", variants.len()),
+            });
+        }
+
+        let mut reader = AdaptiveReader {
+            reader: self.reader,
+            variants,
+            controller: AbrController::new(self.policy),
+            min_sample_duration: self.policy.min_sample_duration,
+            current: self.initial_variant,
+            automatic: true,
+            on_variant_change: self.on_variant_change,
+            sample_start: None,
+            sample_bytes: 0,
+            sample_elapsed: Duration::ZERO,
+            pending_switch: None,
+        };
+        reader.apply_discard();
+        Ok(reader)
+    }
+}
+
+/// Reader of an adaptive source, such as an HLS master playlist or a DASH manifest, that plays one
+/// variant at a time and switches variants at segment boundaries based on the measured throughput.
+///
+/// Only the streams of the current variant are downloaded. When the reader switches, the demuxer
+/// continues with the segment of the new variant that covers the current position, so playback
+/// continues without a gap. The streams of the new variant have different indices, so decoders
+/// must be set up for them from the callback passed to
+/// [`AdaptiveReaderBuilder::with_on_variant_change`] or by checking
+/// [`AdaptiveReader::current_variant`].
+///
+/// # Example
+///
+/// ```ignore
+/// let reader = Reader::new(Url::parse("https://example.com/master.m3u8").unwrap()).unwrap();
+/// let mut reader = AdaptiveReaderBuilder::new(reader)
+///     .with_on_variant_change(|variant| println!("now playing {} bps", variant.bandwidth))
+///     .build()
+///     .unwrap();
+/// loop {
+///     let (stream_index, packet) = reader.read_any().unwrap();
+///     // Route the packet to the decoder of `stream_index`.
+/// }
+/// ```
+pub struct AdaptiveReader {
+    reader: Reader,
+    variants: Vec<Variant>,
+    controller: AbrController,
+    min_sample_duration: Duration,
+    current: usize,
+    // Whether the variant is selected by the controller, or fixed by the caller.
+    automatic: bool,
+    on_variant_change: Option<VariantCallback>,
+    // Position in seconds of the segment boundary at which the current measurement started.
+    sample_start: Option<f64>,
+    sample_bytes: usize,
+    sample_elapsed: Duration,
+    // Variant to switch to once the keyframe that triggered the switch has been returned.
+    pending_switch: Option<usize>,
+}
+
+impl AdaptiveReader {
+    /// Reads that return faster than this were served from data that the demuxer had already
+    /// buffered, and do not count as time spent waiting for the network.
+    const MIN_BLOCKING_READ: Duration = Duration::from_millis(1);
+
+    /// Variants of the source, in ascending order of bandwidth.
+    #[inline]
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    /// Variant that is currently played.
+    #[inline]
+    pub fn current_variant(&self) -> &Variant {
+        &self.variants[self.current]
+    }
+
+    /// Average measured throughput in bits per second, if anything was measured yet.
+    #[inline]
+    pub fn throughput(&self) -> Option<u64> {
+        self.controller.throughput()
+    }
+
+    /// Underlying reader, e.g. to get stream information for the streams of a variant.
+    #[inline]
+    pub fn reader(&self) -> &Reader {
+        &self.reader
+    }
+
+    /// Underlying reader, e.g. to seek.
+    #[inline]
+    pub fn reader_mut(&mut self) -> &mut Reader {
+        &mut self.reader
+    }
+
+    /// Fix the variant to play, or let the reader select variants based on throughput again.
+    ///
+    /// # Arguments
+    ///
+    /// * `variant` - Index into [`AdaptiveReader::variants`] of the variant to play, or `None` to
+    ///   switch automatically.
+    pub fn select_variant(&mut self, variant: Option<usize>) -> Result<()> {
+        match variant {
+            Some(index) if index >= self.variants.len() => Err(Error::InvalidConfiguration {
+                field: "variant",
+                reason: format!("source has only {} variants", self.variants.len()),
+            }),
+            Some(index) => {
+                self.automatic = false;
+                self.pending_switch = None;
+                if index != self.current {
+                    self.switch_to(index);
+                }
+                Ok(())
+            }
+            None => {
+                self.automatic = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// Read a single packet of the current variant.
+    ///
+    /// When the reader decides to switch variants at a segment boundary, the keyframe that starts
+    /// the segment is still returned, and the switch takes effect from the next packet on.
+    ///
+    /// # Return value
+    ///
+    /// Index of the stream the packet belongs to, and the packet.
+    pub fn read_any(&mut self) -> Result<(usize, Packet)> {
+        if let Some(next) = self.pending_switch.take() {
+            // The new variant starts with the segment that the last keyframe started.
+            self.switch_to(next);
+        }
+        loop {
+            let started = Instant::now();
+            let (stream_index, packet) = self.reader.read_any()?;
+            let elapsed = started.elapsed();
+
+            let variant = &self.variants[self.current];
+            // Packets that the demuxer buffered before the last switch.
+            if !variant.stream_indices.contains(&stream_index) {
+                continue;
+            }
+
+            if variant.key_stream_index() == Some(stream_index)
+                && packet.is_key()
+                && packet.pts().has_value()
+                && self.segment_boundary(packet.pts().as_secs_f64())
+            {
+                let bandwidths = self
+                    .variants
+                    .iter()
+                    .map(|variant| variant.bandwidth)
+                    .collect::<Vec<_>>();
+                let next = self.controller.select(&bandwidths, self.current);
+                if self.automatic && next != self.current {
+                    self.pending_switch = Some(next);
+                }
+            }
+
+            self.sample_bytes += packet.size();
+            if elapsed >= Self::MIN_BLOCKING_READ {
+                self.sample_elapsed += elapsed;
+            }
+            return Ok((stream_index, packet));
+        }
+    }
+
+    /// Handle a keyframe of the current variant, which may start a new segment. Finishes the
+    /// current measurement if it covers enough media.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position of the keyframe in seconds.
+    ///
+    /// # Return value
+    ///
+    /// Whether or not a measurement was finished.
+    fn segment_boundary(&mut self, position: f64) -> bool {
+        let start = *self.sample_start.get_or_insert(position);
+        // Positions jump backwards after seeking, in which case measuring starts over.
+        if position < start {
+            self.restart_sample(position);
+            return false;
+        }
+        if position - start < self.min_sample_duration.as_secs_f64() {
+            return false;
+        }
+        self.controller
+            .record(self.sample_bytes, self.sample_elapsed);
+        self.restart_sample(position);
+        true
+    }
+
+    fn restart_sample(&mut self, position: f64) {
+        self.sample_start = Some(position);
+        self.sample_bytes = 0;
+        self.sample_elapsed = Duration::ZERO;
+    }
+
+    fn switch_to(&mut self, index: usize) {
+        self.current = index;
+        self.apply_discard();
+        self.sample_start = None;
+        self.sample_bytes = 0;
+        self.sample_elapsed = Duration::ZERO;
+        if let Some(callback) = self.on_variant_change.as_mut() {
+            callback(&self.variants[self.current]);
+        }
+    }
+
+    /// Discard the streams of all variants except the current one, so that the demuxer stops
+    /// downloading their segments.
+    fn apply_discard(&mut self) {
+        discard_other_variants(&mut self.reader.input, &self.variants, self.current);
+    }
+}
+
+/// Enumerate the variants of an adaptive source, in ascending order of bandwidth. Sources that are
+/// not adaptive have no variants.
+///
+/// The HLS demuxer exposes each variant of a master playlist as a program. The DASH demuxer
+/// exposes each representation as a stream of a single program instead, so every video
+/// representation is combined with the audio representation with the highest bit rate.
+///
+/// # Arguments
+///
+/// * `input` - Input context.
+pub(crate) fn input_variants(input: &AvInput) -> Vec<Variant> {
+    let medium_of = |stream_index: usize| {
+        input
+            .stream(stream_index)
+            .map(|stream| stream.parameters().medium())
+    };
+    let variant = |bandwidth, stream_indices: Vec<usize>| {
+        let first_of = |medium| {
+            stream_indices
+                .iter()
+                .copied()
+                .find(|&stream_index| medium_of(stream_index) == Some(medium))
+        };
+        let video_stream_index = first_of(AvMediaType::Video);
+        let audio_stream_index = first_of(AvMediaType::Audio);
+        let resolution = video_stream_index
+            .and_then(|stream_index| input.stream(stream_index))
+            .map(|stream| ffi::codec_parameters_size(&stream.parameters()));
+        Variant {
+            bandwidth,
+            resolution,
+            video_stream_index,
+            audio_stream_index,
+            stream_indices,
+        }
+    };
+
+    let mut variants = if input.format().name() == "dash" {
+        // Bit rate of a representation as announced in the manifest.
+        let representations = |medium| {
+            input
+                .streams()
+                .filter(|stream| stream.parameters().medium() == medium)
+                .map(|stream| {
+                    let bandwidth = stream
+                        .metadata()
+                        .get("variant_bitrate")
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or(0);
+                    (bandwidth, stream.index())
+                })
+                .collect::<Vec<(u64, usize)>>()
+        };
+        let audio = representations(AvMediaType::Audio)
+            .into_iter()
+            .max_by_key(|&(bandwidth, _)| bandwidth);
+        let video = representations(AvMediaType::Video);
+        if video.is_empty() {
+            representations(AvMediaType::Audio)
+                .into_iter()
+                .map(|(bandwidth, stream_index)| variant(bandwidth, vec![stream_index]))
+                .collect::<Vec<_>>()
+        } else {
+            video
+                .into_iter()
+                .map(|(bandwidth, stream_index)| match audio {
+                    Some((audio_bandwidth, audio_stream_index)) => variant(
+                        bandwidth + audio_bandwidth,
+                        vec![stream_index, audio_stream_index],
+                    ),
+                    None => variant(bandwidth, vec![stream_index]),
+                })
+                .collect()
+        }
+    } else {
+        ffi::input_programs(input)
+            .into_iter()
+            .filter(|(_, stream_indices)| !stream_indices.is_empty())
+            .map(|(bandwidth, stream_indices)| variant(bandwidth, stream_indices))
+            .collect()
+    };
+    variants.sort_by_key(|variant| variant.bandwidth);
+    variants
+}
+
+/// Discard the streams of all variants except one, so that the demuxer stops downloading their
+/// segments. Streams that do not belong to any variant are left alone.
+///
+/// # Arguments
+///
+/// * `input` - Input context.
+/// * `variants` - Variants of the input.
+/// * `current` - Index of the variant to keep.
+pub(crate) fn discard_other_variants(input: &mut AvInput, variants: &[Variant], current: usize) {
+    let current = &variants[current].stream_indices;
+    for variant in variants {
+        for &stream_index in &variant.stream_indices {
+            ffi::set_input_stream_discard(input, stream_index, !current.contains(&stream_index));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ffmpeg::util::format::Pixel as AvPixel;
+
+    use super::*;
+    use crate::core::encode::{EncoderBuilder, Settings};
+    use crate::core::frame::RawFrame;
+    use crate::core::hls::HlsWriterBuilder;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn variant(bandwidth: u64, height: u32) -> Variant {
+        Variant {
+            bandwidth,
+            resolution: Some((height * 16 / 9, height)),
+            video_stream_index: Some(0),
+            audio_stream_index: None,
+            stream_indices: vec![0],
+        }
+    }
+
+    #[test]
+    fn test_variant_selection() {
+        let variants = [
+            variant(500_000, 360),
+            variant(1_500_000, 720),
+            variant(4_000_000, 1080),
+        ];
+        assert_eq!(VariantSelection::Lowest.select(&variants), Some(0));
+        assert_eq!(VariantSelection::Highest.select(&variants), Some(2));
+        assert_eq!(
+            VariantSelection::MaxBandwidth(2_000_000).select(&variants),
+            Some(1)
+        );
+        assert_eq!(
+            VariantSelection::MaxBandwidth(100).select(&variants),
+            Some(0)
+        );
+        assert_eq!(VariantSelection::MaxHeight(720).select(&variants), Some(1));
+        assert_eq!(VariantSelection::Highest.select(&[]), None);
+    }
+
+    #[test]
+    fn test_select_waits_for_min_samples() {
+        let mut controller = AbrController::new(AbrPolicy::new().with_min_samples(2));
+        let bandwidths = [500_000, 1_000_000, 3_000_000];
+
+        controller.record(1_000_000, secs(1));
+        assert_eq!(controller.throughput(), Some(8_000_000));
+        assert_eq!(controller.select(&bandwidths, 1), 1);
+
+        controller.record(1_000_000, secs(1));
+        assert_eq!(controller.select(&bandwidths, 1), 2);
+    }
+
+    #[test]
+    fn test_select_highest_variant_within_budget() {
+        let mut controller =
+            AbrController::new(AbrPolicy::new().with_min_samples(1).with_safety_factor(0.5));
+        let bandwidths = [500_000, 1_000_000, 3_000_000];
+
+        // 2.5 Mbps, of which 1.25 Mbps may be used.
+        controller.record(312_500, secs(1));
+        assert_eq!(controller.select(&bandwidths, 0), 1);
+    }
+
+    #[test]
+    fn test_select_lowest_variant_when_nothing_fits() {
+        let mut controller = AbrController::new(AbrPolicy::new().with_min_samples(1));
+        controller.record(1_000, secs(1));
+        assert_eq!(controller.select(&[500_000, 1_000_000], 1), 0);
+    }
+
+    #[test]
+    fn test_smoothing() {
+        let mut controller = AbrController::new(AbrPolicy::new().with_smoothing(0.5));
+        controller.record(1_000_000, secs(1));
+        controller.record(500_000, secs(1));
+        assert_eq!(controller.throughput(), Some(6_000_000));
+    }
+
+    #[test]
+    fn test_buffered_data_is_ignored() {
+        let mut controller = AbrController::new(AbrPolicy::new());
+        controller.record(1_000_000, Duration::ZERO);
+        assert_eq!(controller.throughput(), None);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut controller = AbrController::new(AbrPolicy::new().with_min_samples(1));
+        controller.record(1_000_000, secs(1));
+        controller.reset();
+        assert_eq!(controller.throughput(), None);
+        assert_eq!(controller.select(&[500_000, 1_000_000], 0), 0);
+    }
+
+    /// Write a variant of three seconds with segments of one second to an HLS playlist.
+    fn write_variant(playlist: &std::path::Path, width: u32, height: u32) {
+        let writer = HlsWriterBuilder::new(playlist)
+            .with_segment_duration(secs(1))
+            .build()
+            .unwrap();
+        let mut encoder = EncoderBuilder::from_writer(
+            writer,
+            Settings::preset_h264_yuv420p(width, height, false),
+        )
+        .with_gop_size(30)
+        .build()
+        .unwrap();
+        for index in 0..90 {
+            let mut frame = RawFrame::new(AvPixel::YUV420P, width, height);
+            frame.data_mut(0).fill(index as u8);
+            frame.set_pts(Some(index * 1_000_000 / 30));
+            encoder.encode_raw(frame).unwrap();
+        }
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_read_and_switch_variants() {
+        let directory = std::env::temp_dir().join(format!("abr-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        write_variant(&directory.join("low.m3u8"), 64, 48);
+        write_variant(&directory.join("high.m3u8"), 128, 96);
+        let master = directory.join("master.m3u8");
+        std::fs::write(
+            &master,
+            "#EXTM3U\n\
+             #EXT-X-STREAM-INF:BANDWIDTH=500000,RESOLUTION=64x48\nlow.m3u8\n\
+             #EXT-X-STREAM-INF:BANDWIDTH=1000000,RESOLUTION=128x96\nhigh.m3u8\n",
+        )
+        .unwrap();
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let mut reader = AdaptiveReaderBuilder::new(Reader::new(master.as_path()).unwrap())
+            .with_on_variant_change({
+                let changes = changes.clone();
+                move |variant| changes.lock().unwrap().push(variant.bandwidth)
+            })
+            .build()
+            .unwrap();
+        assert_eq!(reader.variants().len(), 2);
+        assert_eq!(reader.variants()[0].resolution, Some((64, 48)));
+        assert_eq!(reader.variants()[1].resolution, Some((128, 96)));
+        assert_eq!(reader.current_variant().bandwidth, 500_000);
+
+        // Only packets of the current variant come out.
+        let low = reader.variants()[0].stream_indices.clone();
+        let high = reader.variants()[1].stream_indices.clone();
+        for _ in 0..10 {
+            let (stream_index, _) = reader.read_any().unwrap();
+            assert!(low.contains(&stream_index));
+        }
+
+        reader.select_variant(Some(1)).unwrap();
+        assert_eq!(*changes.lock().unwrap(), vec![1_000_000]);
+        let mut packets = 0;
+        while let Ok((stream_index, _)) = reader.read_any() {
+            assert!(high.contains(&stream_index));
+            packets += 1;
+        }
+        assert!(packets > 0);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+/// Output quality levels that the decoder can fall back to under load, from best to worst.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum DecodeQuality {
+    /// Frames are decoded at the requested output size.
+    #[default]
+    Full,
+    /// Frames are scaled to half the requested output size.
+    Half,
+    /// Frames are scaled to a quarter of the requested output size.
+    Quarter,
+    /// Only keyframes are decoded, at a quarter of the requested output size.
+    KeyframesOnly,
+}
+
+impl DecodeQuality {
+    /// Factor by which the output width and height are divided at this quality.
+    pub fn size_divisor(self) -> u32 {
+        match self {
+            DecodeQuality::Full => 1,
+            DecodeQuality::Half => 2,
+            DecodeQuality::Quarter | DecodeQuality::KeyframesOnly => 4,
+        }
+    }
+
+    /// Whether or not non-keyframes are skipped at this quality.
+    pub fn keyframes_only(self) -> bool {
+        self == DecodeQuality::KeyframesOnly
+    }
+
+    fn lower(self) -> Self {
+        match self {
+            DecodeQuality::Full => DecodeQuality::Half,
+            DecodeQuality::Half => DecodeQuality::Quarter,
+            DecodeQuality::Quarter | DecodeQuality::KeyframesOnly => DecodeQuality::KeyframesOnly,
+        }
+    }
+
+    fn higher(self) -> Self {
+        match self {
+            DecodeQuality::Full | DecodeQuality::Half => DecodeQuality::Full,
+            DecodeQuality::Quarter => DecodeQuality::Half,
+            DecodeQuality::KeyframesOnly => DecodeQuality::Quarter,
+        }
+    }
+}
+
+/// Change of decode quality, reported so that a UI can indicate degraded quality.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QualityChange {
+    /// The decoder could not keep up and switched to a lower quality.
+    Degraded(DecodeQuality),
+    /// The decoder has headroom again and switched to a higher quality.
+    Restored(DecodeQuality),
+}
+
+/// Policy that decides when the decoder switches to a lower output quality because it misses its
+/// deadlines, and when it switches back.
+///
+/// # Example
+///
+/// ```ignore
+/// // Source is 30 fps, so each frame must be decoded within 33 ms.
+/// let policy = AdaptiveDecodePolicy::new(Duration::from_millis(33))
+///     .with_lowest_quality(DecodeQuality::Quarter);
+/// let mut decoder = DecoderBuilder::new(Path::new("video.mp4"))
+///     .with_adaptive_quality(policy)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AdaptiveDecodePolicy {
+    deadline: std::time::Duration,
+    window: usize,
+    miss_ratio: f32,
+    headroom: f32,
+    lowest_quality: DecodeQuality,
+}
+
+impl AdaptiveDecodePolicy {
+    /// Create a policy with the given deadline per frame and sensible defaults: quality is lowered
+    /// when half of the last 30 frames missed the deadline, and raised again when all of the last
+    /// 30 frames were decoded in less than half of the deadline.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - Time available to decode a single frame, usually the frame interval.
+    pub fn new(deadline: std::time::Duration) -> Self {
+        Self {
+            deadline,
+            window: 30,
+            miss_ratio: 0.5,
+            headroom: 0.5,
+            lowest_quality: DecodeQuality::KeyframesOnly,
+        }
+    }
+
+    /// Set the number of frames to measure before deciding to switch.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - Number of frames.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Set the fraction of frames in the window that must miss the deadline before the quality is
+    /// lowered.
+    ///
+    /// # Arguments
+    ///
+    /// * `miss_ratio` - Fraction between `0.0` and `1.0`.
+    pub fn with_miss_ratio(mut self, miss_ratio: f32) -> Self {
+        self.miss_ratio = miss_ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the fraction of the deadline that all frames in the window must stay under before the
+    /// quality is raised again.
+    ///
+    /// # Arguments
+    ///
+    /// * `headroom` - Fraction between `0.0` and `1.0`.
+    pub fn with_headroom(mut self, headroom: f32) -> Self {
+        self.headroom = headroom.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the lowest quality the decoder may fall back to.
+    ///
+    /// # Arguments
+    ///
+    /// * `lowest_quality` - Lowest allowed quality.
+    pub fn with_lowest_quality(mut self, lowest_quality: DecodeQuality) -> Self {
+        self.lowest_quality = lowest_quality;
+        self
+    }
+}
+
+/// Tracks decode timings and applies an [`AdaptiveDecodePolicy`].
+#[derive(Debug, Clone)]
+pub struct AdaptiveDecodeController {
+    policy: AdaptiveDecodePolicy,
+    quality: DecodeQuality,
+    timings: std::collections::VecDeque<std::time::Duration>,
+    // Set when leaving keyframes-only mode, until the next keyframe arrives.
+    awaiting_keyframe: bool,
+}
+
+impl AdaptiveDecodeController {
+    /// Create a new controller, starting at full quality.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Policy to apply.
+    pub fn new(policy: AdaptiveDecodePolicy) -> Self {
+        Self {
+            policy,
+            quality: DecodeQuality::Full,
+            timings: std::collections::VecDeque::with_capacity(policy.window),
+            awaiting_keyframe: false,
+        }
+    }
+
+    /// Current quality.
+    #[inline]
+    pub fn quality(&self) -> DecodeQuality {
+        self.quality
+    }
+
+    /// Record how long it took to decode a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `decode_time` - Time it took to decode the frame.
+    ///
+    /// # Return value
+    ///
+    /// The quality change to apply, if any.
+    pub fn record(&mut self, decode_time: std::time::Duration) -> Option<QualityChange> {
+        if self.timings.len() == self.policy.window {
+            self.timings.pop_front();
+        }
+        self.timings.push_back(decode_time);
+        if self.timings.len() < self.policy.window {
+            return None;
+        }
+
+        let deadline = self.policy.deadline;
+        let misses = self
+            .timings
+            .iter()
+            .filter(|&&timing| timing > deadline)
+            .count();
+        let has_headroom = self
+            .timings
+            .iter()
+            .all(|&timing| timing < deadline.mul_f32(self.policy.headroom));
+
+        let change = if misses as f32 >= self.policy.miss_ratio * self.policy.window as f32
+            && self.quality < self.policy.lowest_quality
+        {
+            self.quality = self.quality.lower().min(self.policy.lowest_quality);
+            QualityChange::Degraded(self.quality)
+        } else if has_headroom && self.quality > DecodeQuality::Full {
+            // Packets after the last decoded keyframe were skipped, so decoding can only resume at
+            // the next keyframe.
+            self.awaiting_keyframe |= self.quality.keyframes_only();
+            self.quality = self.quality.higher();
+            QualityChange::Restored(self.quality)
+        } else {
+            return None;
+        };
+
+        // Start measuring from scratch at the new quality.
+        self.timings.clear();
+        Some(change)
+    }
+
+    /// Whether or not a packet must be skipped at the current quality. Non-keyframes are skipped
+    /// in keyframes-only mode, and after leaving it until the next keyframe arrives.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_key` - Whether or not the packet is a keyframe.
+    pub fn skips_packet(&mut self, is_key: bool) -> bool {
+        if is_key {
+            self.awaiting_keyframe = false;
+            return false;
+        }
+        self.quality.keyframes_only() || self.awaiting_keyframe
+    }
+
+    /// Forget about previous measurements, e.g. after seeking. The quality is kept.
+    pub fn reset(&mut self) {
+        self.timings.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> std::time::Duration {
+        std::time::Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_degrade_and_restore() {
+        let policy = AdaptiveDecodePolicy::new(ms(33)).with_window(4);
+        let mut controller = AdaptiveDecodeController::new(policy);
+
+        assert_eq!(controller.record(ms(50)), None);
+        assert_eq!(controller.record(ms(10)), None);
+        assert_eq!(controller.record(ms(50)), None);
+        assert_eq!(
+            controller.record(ms(10)),
+            Some(QualityChange::Degraded(DecodeQuality::Half))
+        );
+
+        for _ in 0..3 {
+            assert_eq!(controller.record(ms(5)), None);
+        }
+        assert_eq!(
+            controller.record(ms(5)),
+            Some(QualityChange::Restored(DecodeQuality::Full))
+        );
+        assert_eq!(controller.quality(), DecodeQuality::Full);
+    }
+
+    #[test]
+    fn test_lowest_quality_is_respected() {
+        let policy = AdaptiveDecodePolicy::new(ms(33))
+            .with_window(1)
+            .with_lowest_quality(DecodeQuality::Half);
+        let mut controller = AdaptiveDecodeController::new(policy);
+        assert_eq!(
+            controller.record(ms(100)),
+            Some(QualityChange::Degraded(DecodeQuality::Half))
+        );
+        assert_eq!(controller.record(ms(100)), None);
+        assert_eq!(controller.quality(), DecodeQuality::Half);
+    }
+
+    #[test]
+    fn test_skips_packets_until_keyframe_after_keyframes_only() {
+        let policy = AdaptiveDecodePolicy::new(ms(33)).with_window(1);
+        let mut controller = AdaptiveDecodeController::new(policy);
+        for _ in 0..3 {
+            controller.record(ms(100));
+        }
+        assert_eq!(controller.quality(), DecodeQuality::KeyframesOnly);
+        assert!(controller.skips_packet(false));
+        assert!(!controller.skips_packet(true));
+
+        assert_eq!(
+            controller.record(ms(5)),
+            Some(QualityChange::Restored(DecodeQuality::Quarter))
+        );
+        assert!(controller.skips_packet(false));
+        assert!(controller.skips_packet(false));
+        assert!(!controller.skips_packet(true));
+        assert!(!controller.skips_packet(false));
+    }
+
+    #[test]
+    fn test_no_change_in_between() {
+        let policy = AdaptiveDecodePolicy::new(ms(33)).with_window(2);
+        let mut controller = AdaptiveDecodeController::new(policy);
+        // Within deadline, but not enough headroom to go up (and already at full quality).
+        assert_eq!(controller.record(ms(25)), None);
+        assert_eq!(controller.record(ms(25)), None);
+        assert_eq!(controller.quality(), DecodeQuality::Full);
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use futures_io::{AsyncRead, AsyncSeek};
+
+/// Adapter that turns an [`AsyncRead`] and [`AsyncSeek`] source into a blocking [`Read`] and
+/// [`Seek`] source, so that a [`Reader`](crate::core::io::Reader) can pull from async network
+/// clients.
+///
+/// Every read and seek blocks the current thread until the source is ready. The source must
+/// therefore be driven by a runtime on another thread, and the reader must not be used on a
+/// thread of an async runtime (use e.g. `spawn_blocking` instead).
+///
+/// Tokio sources can be adapted with `tokio_util::compat::TokioAsyncReadCompatExt::compat`.
+pub struct AsyncSource<S> {
+    source: S,
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> AsyncSource<S> {
+    /// Create a blocking adapter around an async source.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Async source to read from.
+    pub fn new(source: S) -> Self {
+        Self { source }
+    }
+
+    /// Get the async source back.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> Read for AsyncSource<S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        block_on(|cx| Pin::new(&mut self.source).poll_read(cx, buf))
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> Seek for AsyncSource<S> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        block_on(|cx| Pin::new(&mut self.source).poll_seek(cx, pos))
+    }
+}
+
+/// Wakes a parked thread.
+struct ThreadWaker(std::thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll until the result is ready, parking the current thread while waiting for a wake-up.
+///
+/// # Arguments
+///
+/// * `poll` - Function that polls the operation.
+fn block_on<T>(mut poll: impl FnMut(&mut Context<'_>) -> Poll<T>) -> T {
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(result) = poll(&mut cx) {
+            return result;
+        }
+        std::thread::park();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// In-memory async source that is only ready every other poll, and wakes from another thread.
+    struct SlowSource {
+        data: std::io::Cursor<Vec<u8>>,
+        ready: bool,
+    }
+
+    impl SlowSource {
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> bool {
+            self.ready = !self.ready;
+            if !self.ready {
+                let waker = cx.waker().clone();
+                std::thread::spawn(move || waker.wake());
+            }
+            self.ready
+        }
+    }
+
+    impl AsyncRead for SlowSource {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            if self.poll_ready(cx) {
+                Poll::Ready(self.data.read(buf))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    impl AsyncSeek for SlowSource {
+        fn poll_seek(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            pos: SeekFrom,
+        ) -> Poll<std::io::Result<u64>> {
+            if self.poll_ready(cx) {
+                Poll::Ready(self.data.seek(pos))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_source_reads_and_seeks() {
+        let mut source = AsyncSource::new(SlowSource {
+            data: std::io::Cursor::new(b"0123456789".to_vec()),
+            ready: false,
+        });
+        let mut buf = [0; 4];
+        assert_eq!(source.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"0123");
+        assert_eq!(source.seek(SeekFrom::End(-2)).unwrap(), 8);
+        let mut rest = Vec::new();
+        source.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"89");
+    }
+}
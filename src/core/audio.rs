@@ -0,0 +1,636 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::codec::decoder::Audio as AvAudioDecoder;
+use ffmpeg::codec::encoder::audio::Encoder as AvAudioEncoder;
+use ffmpeg::codec::flag::Flags as AvCodecFlags;
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::codec::Context as AvContext;
+use ffmpeg::format::flag::Flags as AvFormatFlags;
+use ffmpeg::media::Type as AvMediaType;
+use ffmpeg::util::error::EAGAIN;
+use ffmpeg::util::format::Sample as AvSampleFormat;
+use ffmpeg::util::frame::Audio as AvAudioFrame;
+use ffmpeg::util::mathematics::rescale::Rescale;
+use ffmpeg::{ChannelLayout as AvChannelLayout, Error as AvError, Rational as AvRational};
+
+use crate::core::audio_convert::{AudioConverter, AudioFormat};
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::io::private::Write;
+use crate::core::io::{Reader, ReaderBuilder, Writer, WriterBuilder};
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Re-export internal `AvAudioFrame` for caller to use.
+pub type RawAudioFrame = AvAudioFrame;
+
+/// Builds an [`AudioExporter`].
+pub struct AudioExporterBuilder<'a> {
+    source: Location,
+    destination: Location,
+    start: Option<Time>,
+    end: Option<Time>,
+    format: Option<&'a str>,
+    options: Option<&'a Options>,
+    bit_rate: Option<usize>,
+}
+
+impl<'a> AudioExporterBuilder<'a> {
+    /// Create an exporter that extracts the best audio stream from `source` into `destination`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read audio from.
+    /// * `destination` - Where to write the exported audio to. The codec is derived from the
+    ///   container format.
+    pub fn new(source: impl Into<Location>, destination: impl Into<Location>) -> Self {
+        Self {
+            source: source.into(),
+            destination: destination.into(),
+            start: None,
+            end: None,
+            format: None,
+            options: None,
+            bit_rate: None,
+        }
+    }
+
+    /// Only export audio from this timestamp on. The boundary is applied with sample accuracy.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Start of clip, relative to the start of the stream.
+    pub fn with_start(mut self, start: Time) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Only export audio up to this timestamp (exclusive). The boundary is applied with sample
+    /// accuracy.
+    ///
+    /// # Arguments
+    ///
+    /// * `end` - End of clip, relative to the start of the stream.
+    pub fn with_end(mut self, end: Time) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Specify a custom container format for the destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Container format to use.
+    pub fn with_format(mut self, format: &'a str) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Specify options for the audio encoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to the encoder.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Set the target bit rate of the audio encoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `bit_rate` - Bit rate in bits per second.
+    pub fn with_bit_rate(mut self, bit_rate: usize) -> Self {
+        self.bit_rate = Some(bit_rate);
+        self
+    }
+
+    /// Build [`AudioExporter`].
+    pub fn build(self) -> Result<AudioExporter> {
+        let reader = ReaderBuilder::new(self.source).build()?;
+        let reader_stream_index = reader.best_audio_stream_index()?;
+        let reader_stream = reader
+            .input
+            .stream(reader_stream_index)
+            .ok_or(AvError::StreamNotFound)?;
+        let reader_time_base = reader_stream.time_base();
+        // Timestamps of the stream do not necessarily start at zero, e.g. in MPEG-TS.
+        let stream_start = match reader_stream.start_time() {
+            ffmpeg::ffi::AV_NOPTS_VALUE => 0,
+            start_time => start_time,
+        };
+        let stream_start = Time::new(Some(stream_start), reader_time_base);
+        let start = self
+            .start
+            .map(|start| stream_start.aligned_with(start).add());
+        let end = self.end.map(|end| stream_start.aligned_with(end).add());
+
+        let mut decoder_context = AvContext::from_parameters(reader_stream.parameters())?;
+        ffi::set_decoder_context_time_base(&mut decoder_context, reader_time_base);
+        let decoder = decoder_context.decoder().audio()?;
+
+        let mut writer_builder = WriterBuilder::new(self.destination);
+        if let Some(format) = self.format {
+            writer_builder = writer_builder.with_format(format);
+        }
+        let mut writer = writer_builder.build()?;
+
+        let (encoder, writer_stream_index) = open_audio_encoder(
+            &mut writer,
+            AudioFormat::new(
+                decoder.format(),
+                AvChannelLayout::default(decoder.channels() as i32),
+                decoder.rate(),
+            ),
+            self.bit_rate,
+            self.options,
+        )?;
+        let sample_format = encoder.format();
+        let channel_layout = encoder.channel_layout();
+        let rate = encoder.rate() as i32;
+
+        let converter =
+            AudioConverter::new(AudioFormat::new(sample_format, channel_layout, rate as u32));
+
+        let sample_rate = decoder.rate();
+        let to_samples = |time: Time| {
+            time.aligned_with_rational(AvRational::new(1, sample_rate as i32))
+                .into_value()
+        };
+
+        Ok(AudioExporter {
+            reader,
+            reader_stream_index,
+            reader_time_base,
+            decoder,
+            converter,
+            fifo: SampleFifo::new(sample_format, channel_layout),
+            encoder,
+            encoder_time_base: AvRational::new(1, rate),
+            writer,
+            writer_stream_index,
+            start_sample: start.and_then(to_samples).unwrap_or(0).max(0),
+            end_sample: end.and_then(to_samples),
+            start,
+            next_sample: None,
+            samples_written: 0,
+        })
+    }
+}
+
+/// Exports (a clip of) an audio stream to a new file by decoding and re-encoding it.
+///
+/// Unlike stream copying, which can only cut at packet boundaries, the exporter trims the audio to
+/// the exact sample at the requested boundaries. Samples with negative timestamps, such as decoder
+/// priming samples in the source, are dropped.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut exporter = AudioExporterBuilder::new(Path::new("movie.mp4"), Path::new("clip.m4a"))
+///     .with_start(Time::from_secs(10.0))
+///     .with_end(Time::from_secs(12.5))
+///     .build()
+///     .unwrap();
+/// let samples = exporter.export().unwrap();
+/// ```
+pub struct AudioExporter {
+    reader: Reader,
+    reader_stream_index: usize,
+    reader_time_base: AvRational,
+    decoder: AvAudioDecoder,
+    converter: AudioConverter,
+    fifo: SampleFifo,
+    encoder: AvAudioEncoder,
+    encoder_time_base: AvRational,
+    writer: Writer,
+    writer_stream_index: usize,
+    start_sample: i64,
+    end_sample: Option<i64>,
+    // Start of the clip in stream timestamps, i.e. including the start time of the stream.
+    start: Option<Time>,
+    next_sample: Option<i64>,
+    samples_written: i64,
+}
+
+impl AudioExporter {
+    /// Extract the best audio stream from `source` into `destination`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read audio from.
+    /// * `destination` - Where to write the exported audio to.
+    #[inline]
+    pub fn new(source: impl Into<Location>, destination: impl Into<Location>) -> Result<Self> {
+        AudioExporterBuilder::new(source, destination).build()
+    }
+
+    /// Run the export.
+    ///
+    /// # Return value
+    ///
+    /// Number of samples (per channel) that were written.
+    pub fn export(&mut self) -> Result<u64> {
+        if let Some(start) = self.start {
+            // Seek to a point at or before the start, so no samples are lost.
+            let timestamp = start
+                .aligned_with_rational(ffmpeg::rescale::TIME_BASE)
+                .into_value()
+                .unwrap_or(0);
+            if timestamp > 0 {
+                self.reader.input.seek(timestamp, ..timestamp)?;
+            }
+        }
+
+        self.writer.write_header()?;
+
+        loop {
+            let packet = match self.reader.read(self.reader_stream_index) {
+                Ok(packet) => packet,
+                Err(Error::ReadExhausted) => break,
+                Err(err) => return Err(err),
+            };
+            let (packet, _) = packet.into_inner_parts();
+            self.decoder.send_packet(&packet)?;
+            if !self.receive_frames()? {
+                break;
+            }
+        }
+
+        self.decoder.send_eof()?;
+        self.receive_frames()?;
+        self.converter.send_eof()?;
+        self.encode_converted_frames()?;
+
+        // Encode whatever is left in the FIFO, the last frame may be smaller than the frame size.
+        while let Some(frame) = self.fifo.pop(self.fifo.len()) {
+            self.encode_frame(frame)?;
+        }
+
+        self.encoder.send_eof()?;
+        self.write_packets()?;
+        self.writer.write_trailer()?;
+
+        Ok(self.samples_written as u64)
+    }
+
+    /// Receive all available frames from the decoder, trim them, and send them on to the encoder.
+    ///
+    /// # Return value
+    ///
+    /// `false` if the end boundary has been passed and no more input is required.
+    fn receive_frames(&mut self) -> Result<bool> {
+        loop {
+            let mut frame = AvAudioFrame::empty();
+            match self.decoder.receive_frame(&mut frame) {
+                Ok(()) => {}
+                Err(AvError::Eof) => return Ok(true),
+                Err(AvError::Other { errno }) if errno == EAGAIN => return Ok(true),
+                Err(err) => return Err(err.into()),
+            }
+
+            let rate = AvRational::new(1, frame.rate() as i32);
+            let frame_start = match frame.pts() {
+                Some(pts) => pts.rescale(self.reader_time_base, rate),
+                None => self.next_sample.unwrap_or(0),
+            };
+            self.next_sample = Some(frame_start + frame.samples() as i64);
+
+            if let Some(end_sample) = self.end_sample {
+                if frame_start >= end_sample {
+                    return Ok(false);
+                }
+            }
+
+            let Some((offset, count)) = trim_range(
+                frame_start,
+                frame.samples(),
+                self.start_sample,
+                self.end_sample,
+            ) else {
+                continue;
+            };
+
+            let frame = if offset == 0 && count == frame.samples() {
+                frame
+            } else {
+                trim_frame(&frame, offset, count)
+            };
+
+            self.converter.send(&frame)?;
+            self.encode_converted_frames()?;
+        }
+    }
+
+    /// Move all converted frames into the FIFO, and encode as many full encoder frames as it holds.
+    fn encode_converted_frames(&mut self) -> Result<()> {
+        while let Some(frame) = self.converter.receive() {
+            self.fifo.push(&frame);
+        }
+
+        let frame_size = self.encoder.frame_size() as usize;
+        let frame_size = if frame_size > 0 {
+            frame_size
+        } else {
+            self.fifo.len()
+        };
+        while self.fifo.len() >= frame_size {
+            match self.fifo.pop(frame_size) {
+                Some(frame) => self.encode_frame(frame)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a frame to the encoder and write any resulting packets.
+    fn encode_frame(&mut self, mut frame: AvAudioFrame) -> Result<()> {
+        frame.set_rate(self.encoder.rate());
+        frame.set_pts(Some(self.samples_written));
+        self.samples_written += frame.samples() as i64;
+        self.encoder.send_frame(&frame)?;
+        self.write_packets()
+    }
+
+    /// Write all packets the encoder has available.
+    fn write_packets(&mut self) -> Result<()> {
+        let stream_time_base = self
+            .writer
+            .output
+            .stream(self.writer_stream_index)
+            .ok_or(AvError::StreamNotFound)?
+            .time_base();
+        loop {
+            let mut packet = AvPacket::empty();
+            match self.encoder.receive_packet(&mut packet) {
+                Ok(()) => {
+                    packet.set_stream(self.writer_stream_index);
+                    packet.set_position(-1);
+                    packet.rescale_ts(self.encoder_time_base, stream_time_base);
+                    self.writer.write_interleaved(&mut packet)?;
+                }
+                Err(AvError::Eof) => return Ok(()),
+                Err(AvError::Other { errno }) if errno == EAGAIN => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+unsafe impl Send for AudioExporter {}
+unsafe impl Sync for AudioExporter {}
+
+/// Add an audio stream to a writer, with an encoder for the default audio codec of the container.
+///
+/// # Arguments
+///
+/// * `writer` - Writer to add the stream to. The header must not have been written yet.
+/// * `format` - Preferred sample format, channel layout and sample rate. These are kept where the
+///   encoder supports them.
+/// * `bit_rate` - Target bit rate, if any.
+/// * `options` - Options to pass on to the encoder, if any.
+///
+/// # Return value
+///
+/// Opened encoder and index of the stream in the writer.
+pub(crate) fn open_audio_encoder(
+    writer: &mut Writer,
+    format: AudioFormat,
+    bit_rate: Option<usize>,
+    options: Option<&Options>,
+) -> Result<(AvAudioEncoder, usize)> {
+    let global_header = writer
+        .output
+        .format()
+        .flags()
+        .contains(AvFormatFlags::GLOBAL_HEADER);
+    let codec_id = writer
+        .output
+        .format()
+        .codec(&writer.destination.as_path(), AvMediaType::Audio);
+    let codec = ffmpeg::encoder::find(codec_id).ok_or(AvError::EncoderNotFound)?;
+    let codec_audio = codec.audio()?;
+
+    let channels = format.channel_layout.channels();
+    let sample_format = match codec_audio.formats() {
+        Some(formats) => {
+            let formats = formats.collect::<Vec<_>>();
+            if formats.contains(&format.sample_format) {
+                format.sample_format
+            } else {
+                *formats.first().ok_or(AvError::InvalidData)?
+            }
+        }
+        None => format.sample_format,
+    };
+    let rate = match codec_audio.rates() {
+        Some(rates) => {
+            let rates = rates.collect::<Vec<_>>();
+            if rates.contains(&(format.rate as i32)) {
+                format.rate as i32
+            } else {
+                *rates.first().ok_or(AvError::InvalidData)?
+            }
+        }
+        None => format.rate as i32,
+    };
+    let channel_layout = match codec_audio.channel_layouts() {
+        Some(channel_layouts) => channel_layouts.best(channels),
+        None => AvChannelLayout::default(channels),
+    };
+
+    let mut writer_stream = writer.output.add_stream(codec)?;
+    let writer_stream_index = writer_stream.index();
+
+    let mut encoder_context = ffi::codec_context_as(&codec)?;
+    if global_header {
+        encoder_context.set_flags(AvCodecFlags::GLOBAL_HEADER);
+    }
+    let mut encoder = encoder_context.encoder().audio()?;
+    encoder.set_rate(rate);
+    encoder.set_format(sample_format);
+    encoder.set_channel_layout(channel_layout);
+    encoder.set_time_base((1, rate));
+    if let Some(bit_rate) = bit_rate {
+        encoder.set_bit_rate(bit_rate);
+    }
+    let encoder = encoder.open_with(options.cloned().unwrap_or_default().to_dict())?;
+    writer_stream.set_parameters(&encoder);
+
+    Ok((encoder, writer_stream_index))
+}
+
+/// Simple FIFO of audio samples, used to re-chunk frames into the frame size the encoder requires.
+pub(crate) struct SampleFifo {
+    format: AvSampleFormat,
+    channel_layout: AvChannelLayout,
+    channels: u16,
+    planes: Vec<Vec<u8>>,
+}
+
+impl SampleFifo {
+    /// Create an empty FIFO. Frames that are popped from it carry `channel_layout`, so it must be
+    /// the layout of the frames that are pushed.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Sample format of the frames.
+    /// * `channel_layout` - Channel layout of the frames.
+    pub(crate) fn new(format: AvSampleFormat, channel_layout: AvChannelLayout) -> Self {
+        let channels = channel_layout.channels() as u16;
+        let num_planes = if format.is_planar() {
+            channels as usize
+        } else {
+            1
+        };
+        Self {
+            format,
+            channel_layout,
+            channels,
+            planes: vec![Vec::new(); num_planes],
+        }
+    }
+
+    /// Number of bytes per sample in each plane.
+    fn stride(&self) -> usize {
+        if self.format.is_planar() {
+            self.format.bytes()
+        } else {
+            self.format.bytes() * self.channels as usize
+        }
+    }
+
+    /// Number of samples (per channel) in the FIFO.
+    pub(crate) fn len(&self) -> usize {
+        self.planes[0].len() / self.stride()
+    }
+
+    pub(crate) fn push(&mut self, frame: &AvAudioFrame) {
+        let size = frame.samples() * self.stride();
+        for (index, plane) in self.planes.iter_mut().enumerate() {
+            plane.extend_from_slice(&frame.data(index)[..size]);
+        }
+    }
+
+    /// Append silent samples, e.g. to fill a gap in the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Number of samples (per channel).
+    pub(crate) fn push_silence(&mut self, samples: usize) {
+        // Unsigned 8-bit samples are centered around 128 rather than zero.
+        let silence = match self.format {
+            AvSampleFormat::U8(_) => 0x80,
+            _ => 0,
+        };
+        let size = samples * self.stride();
+        for plane in self.planes.iter_mut() {
+            plane.resize(plane.len() + size, silence);
+        }
+    }
+
+    pub(crate) fn pop(&mut self, samples: usize) -> Option<AvAudioFrame> {
+        let samples = samples.min(self.len());
+        if samples == 0 {
+            return None;
+        }
+        let size = samples * self.stride();
+        let mut frame = AvAudioFrame::new(self.format, samples, self.channel_layout);
+        for (index, plane) in self.planes.iter_mut().enumerate() {
+            frame.data_mut(index)[..size].copy_from_slice(&plane[..size]);
+            plane.drain(..size);
+        }
+        Some(frame)
+    }
+}
+
+/// Copy a range of samples from a frame into a new frame.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to copy from.
+/// * `offset` - First sample to copy.
+/// * `count` - Number of samples to copy.
+fn trim_frame(frame: &AvAudioFrame, offset: usize, count: usize) -> AvAudioFrame {
+    let stride = if frame.is_planar() {
+        frame.format().bytes()
+    } else {
+        frame.format().bytes() * frame.channels() as usize
+    };
+    let mut trimmed = AvAudioFrame::new(frame.format(), count, frame.channel_layout());
+    trimmed.set_rate(frame.rate());
+    for index in 0..frame.planes() {
+        trimmed.data_mut(index)[..count * stride]
+            .copy_from_slice(&frame.data(index)[offset * stride..(offset + count) * stride]);
+    }
+    trimmed
+}
+
+/// Compute which part of a frame falls within the requested boundaries.
+///
+/// # Arguments
+///
+/// * `frame_start` - Position of the first sample of the frame.
+/// * `frame_len` - Number of samples in the frame.
+/// * `start` - First sample to keep.
+/// * `end` - First sample not to keep, if any.
+///
+/// # Return value
+///
+/// Offset into the frame and number of samples to keep, or `None` if the frame must be dropped
+/// entirely.
+fn trim_range(
+    frame_start: i64,
+    frame_len: usize,
+    start: i64,
+    end: Option<i64>,
+) -> Option<(usize, usize)> {
+    let frame_end = frame_start + frame_len as i64;
+    let keep_start = frame_start.max(start);
+    let keep_end = match end {
+        Some(end) => frame_end.min(end),
+        None => frame_end,
+    };
+    if keep_end <= keep_start {
+        return None;
+    }
+    Some((
+        (keep_start - frame_start) as usize,
+        (keep_end - keep_start) as usize,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_range_inside() {
+        assert_eq!(trim_range(0, 1024, 0, None), Some((0, 1024)));
+        assert_eq!(trim_range(1024, 1024, 0, Some(4096)), Some((0, 1024)));
+    }
+
+    #[test]
+    fn test_trim_range_boundaries() {
+        assert_eq!(trim_range(0, 1024, 100, None), Some((100, 924)));
+        assert_eq!(trim_range(0, 1024, 0, Some(1000)), Some((0, 1000)));
+        assert_eq!(trim_range(0, 1024, 100, Some(200)), Some((100, 100)));
+    }
+
+    #[test]
+    fn test_trim_range_outside() {
+        assert_eq!(trim_range(0, 1024, 1024, None), None);
+        assert_eq!(trim_range(2048, 1024, 0, Some(2048)), None);
+    }
+
+    #[test]
+    fn test_trim_range_priming() {
+        // Priming samples have negative timestamps and are dropped.
+        assert_eq!(trim_range(-1024, 1024, 0, None), None);
+        assert_eq!(trim_range(-512, 1024, 0, None), Some((512, 512)));
+    }
+}
@@ -0,0 +1,318 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::collections::VecDeque;
+
+use ffmpeg::software::resampling::context::Context as AvResampler;
+use ffmpeg::util::format::Sample as AvSampleFormat;
+use ffmpeg::util::frame::Audio as AvAudioFrame;
+use ffmpeg::util::mathematics::rescale::Rescale;
+use ffmpeg::{ChannelLayout as AvChannelLayout, Rational as AvRational};
+
+use crate::core::audio::RawAudioFrame;
+use crate::core::error::Error;
+use crate::core::ffi;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Sample format, channel layout and sample rate of audio frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub sample_format: AvSampleFormat,
+    pub channel_layout: AvChannelLayout,
+    pub rate: u32,
+}
+
+impl AudioFormat {
+    /// Create an audio format.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_format` - Sample format.
+    /// * `channel_layout` - Channel layout.
+    /// * `rate` - Sample rate in Hz.
+    pub fn new(sample_format: AvSampleFormat, channel_layout: AvChannelLayout, rate: u32) -> Self {
+        Self {
+            sample_format,
+            channel_layout,
+            rate,
+        }
+    }
+
+    /// Get the format of an audio frame. Frames without a channel layout are assumed to use the
+    /// default layout for their number of channels.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to get format of.
+    pub fn of(frame: &RawAudioFrame) -> Self {
+        let channel_layout = if frame.channel_layout().is_empty() {
+            AvChannelLayout::default(frame.channels() as i32)
+        } else {
+            frame.channel_layout()
+        };
+        Self::new(frame.format(), channel_layout, frame.rate())
+    }
+}
+
+/// Converts decoded audio frames between sample formats, sample rates and channel layouts.
+///
+/// Resampling delays samples, so the converter works like a codec: frames go in with
+/// [`AudioConverter::send`], converted frames come out of [`AudioConverter::receive`], and
+/// [`AudioConverter::send_eof`] flushes the samples that are still buffered. If the format of
+/// the input changes mid-stream, the buffered samples are flushed before the converter is
+/// reconfigured, so no audio is lost.
+///
+/// Converted frames are timestamped in samples at the output rate, starting from the timestamp
+/// of the first frame sent (rescaled to the output rate).
+///
+/// # Example
+///
+/// ```ignore
+/// let mut converter = AudioConverter::new(AudioFormat::new(
+///     AvSampleFormat::F32(SampleType::Packed),
+///     AvChannelLayout::STEREO,
+///     48_000,
+/// ));
+/// converter.send(&frame)?;
+/// while let Some(frame) = converter.receive() {
+///     // ...
+/// }
+/// ```
+pub struct AudioConverter {
+    output_format: AudioFormat,
+    input_format: Option<AudioFormat>,
+    resampler: Option<AvResampler>,
+    next_pts: Option<i64>,
+    frames: VecDeque<RawAudioFrame>,
+}
+
+impl AudioConverter {
+    /// Create a converter that produces frames in the given format.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_format` - Format to convert to.
+    pub fn new(output_format: AudioFormat) -> Self {
+        Self {
+            output_format,
+            input_format: None,
+            resampler: None,
+            next_pts: None,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Get the format the converter produces.
+    #[inline]
+    pub fn output_format(&self) -> AudioFormat {
+        self.output_format
+    }
+
+    /// Send a frame to the converter.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to convert. Its timestamp, if any, is taken to be in units of samples at
+    ///   its own rate.
+    pub fn send(&mut self, frame: &RawAudioFrame) -> Result<()> {
+        let input_format = AudioFormat::of(frame);
+        if self.input_format != Some(input_format) {
+            self.flush_resampler()?;
+            self.resampler = Some(AvResampler::get(
+                input_format.sample_format,
+                input_format.channel_layout,
+                input_format.rate,
+                self.output_format.sample_format,
+                self.output_format.channel_layout,
+                self.output_format.rate,
+            )?);
+            self.input_format = Some(input_format);
+        }
+
+        if self.next_pts.is_none() {
+            let pts = frame.pts().unwrap_or(0).rescale(
+                AvRational::new(1, input_format.rate as i32),
+                AvRational::new(1, self.output_format.rate as i32),
+            );
+            self.next_pts = Some(pts);
+        }
+
+        if let Some(resampler) = self.resampler.as_mut() {
+            let mut frame_converted = AvAudioFrame::empty();
+            let capacity = ffi::resampler_output_samples(resampler, frame.samples());
+            unsafe {
+                frame_converted.alloc(
+                    self.output_format.sample_format,
+                    capacity,
+                    self.output_format.channel_layout,
+                );
+            }
+            resampler.run(frame, &mut frame_converted)?;
+            self.push(frame_converted);
+        }
+
+        Ok(())
+    }
+
+    /// Flush all samples still buffered in the converter. Call this after the last frame has been
+    /// sent, and then receive the remaining frames.
+    pub fn send_eof(&mut self) -> Result<()> {
+        self.flush_resampler()?;
+        self.resampler = None;
+        self.input_format = None;
+        Ok(())
+    }
+
+    /// Get the number of samples, at the output rate, that were sent but are still buffered in the
+    /// converter and cannot be received until more samples are sent or the converter is flushed.
+    pub fn delay(&self) -> usize {
+        self.resampler.as_ref().map_or(0, ffi::resampler_delay)
+    }
+
+    /// Receive a converted frame, if one is available.
+    pub fn receive(&mut self) -> Option<RawAudioFrame> {
+        self.frames.pop_front()
+    }
+
+    /// Drain the samples buffered in the current resampler.
+    fn flush_resampler(&mut self) -> Result<()> {
+        loop {
+            let Some(resampler) = self.resampler.as_mut() else {
+                return Ok(());
+            };
+            let capacity = ffi::resampler_output_samples(resampler, 0);
+            if capacity == 0 {
+                return Ok(());
+            }
+            let mut frame_converted = AvAudioFrame::empty();
+            unsafe {
+                frame_converted.alloc(
+                    self.output_format.sample_format,
+                    capacity,
+                    self.output_format.channel_layout,
+                );
+            }
+            resampler.flush(&mut frame_converted)?;
+            if frame_converted.samples() == 0 {
+                return Ok(());
+            }
+            self.push(frame_converted);
+        }
+    }
+
+    /// Timestamp a converted frame and queue it, unless it is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Converted frame.
+    fn push(&mut self, mut frame: RawAudioFrame) {
+        if frame.samples() == 0 {
+            return;
+        }
+        let pts = self.next_pts.unwrap_or(0);
+        frame.set_pts(Some(pts));
+        self.next_pts = Some(pts + frame.samples() as i64);
+        self.frames.push_back(frame);
+    }
+}
+
+unsafe impl Send for AudioConverter {}
+unsafe impl Sync for AudioConverter {}
+
+#[cfg(test)]
+mod tests {
+    use ffmpeg::util::format::sample::Type as AvSampleType;
+
+    use super::*;
+
+    /// Create a frame of silent mono samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate` - Sample rate of the frame.
+    /// * `pts` - Timestamp of the frame in samples.
+    /// * `samples` - Number of samples.
+    fn silence(rate: u32, pts: i64, samples: usize) -> RawAudioFrame {
+        let mut frame = RawAudioFrame::new(
+            AvSampleFormat::F32(AvSampleType::Packed),
+            samples,
+            AvChannelLayout::MONO,
+        );
+        frame.set_rate(rate);
+        frame.set_pts(Some(pts));
+        frame.data_mut(0).fill(0);
+        frame
+    }
+
+    /// Receive all frames from the converter, checking that their timestamps follow on.
+    ///
+    /// # Arguments
+    ///
+    /// * `converter` - Converter to receive from.
+    /// * `next_pts` - Timestamp the next frame must have.
+    ///
+    /// # Return value
+    ///
+    /// Number of samples received.
+    fn receive_all(converter: &mut AudioConverter, next_pts: &mut i64) -> usize {
+        let mut samples = 0;
+        while let Some(frame) = converter.receive() {
+            assert_eq!(frame.format(), AvSampleFormat::I16(AvSampleType::Packed));
+            assert_eq!(frame.channel_layout(), AvChannelLayout::STEREO);
+            assert_eq!(frame.pts(), Some(*next_pts));
+            *next_pts += frame.samples() as i64;
+            samples += frame.samples();
+        }
+        samples
+    }
+
+    /// Create a converter to 48 kHz stereo with 16-bit samples.
+    fn converter() -> AudioConverter {
+        AudioConverter::new(AudioFormat::new(
+            AvSampleFormat::I16(AvSampleType::Packed),
+            AvChannelLayout::STEREO,
+            48_000,
+        ))
+    }
+
+    #[test]
+    fn test_convert() {
+        let mut converter = converter();
+        // The first frame starts 10 ms in, which is 480 samples at the output rate.
+        let mut next_pts = 480;
+        let mut received = 0;
+        for chunk in 1..=100 {
+            converter.send(&silence(44_100, chunk * 441, 441)).unwrap();
+            received += receive_all(&mut converter, &mut next_pts);
+            // Samples that were sent are either received or buffered, up to rounding.
+            let sent = chunk as usize * 480;
+            assert!((received + converter.delay()).abs_diff(sent) <= 2);
+        }
+        converter.send_eof().unwrap();
+        assert_eq!(converter.delay(), 0);
+        received += receive_all(&mut converter, &mut next_pts);
+        assert!(received.abs_diff(48_000) <= 1);
+    }
+
+    #[test]
+    fn test_convert_format_change() {
+        let mut converter = converter();
+        let mut next_pts = 0;
+        let mut received = 0;
+        // Half a second at 44.1 kHz, then half a second at 48 kHz.
+        for chunk in 0..50 {
+            converter.send(&silence(44_100, chunk * 441, 441)).unwrap();
+            received += receive_all(&mut converter, &mut next_pts);
+        }
+        for chunk in 0..50 {
+            converter
+                .send(&silence(48_000, 24_000 + chunk * 480, 480))
+                .unwrap();
+            received += receive_all(&mut converter, &mut next_pts);
+        }
+        converter.send_eof().unwrap();
+        received += receive_all(&mut converter, &mut next_pts);
+        // The samples buffered at the first rate were flushed before switching.
+        assert!(received.abs_diff(48_000) <= 1);
+    }
+}
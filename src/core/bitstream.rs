@@ -0,0 +1,228 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::codec::packet::Packet as AvPacket;
+
+use crate::core::codec::VideoCodec;
+use crate::core::extradata::{extract_parameter_sets_h264, find_avc_start_code};
+
+/// Create a copy of a packet with different data.
+///
+/// # Arguments
+///
+/// * `packet` - Packet to copy properties from.
+/// * `data` - New packet data.
+pub(crate) fn with_data(packet: &AvPacket, data: &[u8]) -> AvPacket {
+    let mut new_packet = AvPacket::copy(data);
+    new_packet.set_pts(packet.pts());
+    new_packet.set_dts(packet.dts());
+    new_packet.set_duration(packet.duration());
+    new_packet.set_flags(packet.flags());
+    new_packet
+}
+
+/// Get the size of the NAL unit length prefix of a stream that stores its parameter sets
+/// out-of-band, or `None` if the stream uses Annex B start codes (or has no NAL units).
+///
+/// # Arguments
+///
+/// * `codec` - Codec of the stream.
+/// * `extradata` - Extradata of the stream.
+pub(crate) fn nal_length_size(codec: VideoCodec, extradata: &[u8]) -> Option<usize> {
+    match codec {
+        // AVCDecoderConfigurationRecord: `lengthSizeMinusOne` is in the fifth byte.
+        VideoCodec::H264 if extradata.len() > 4 && extradata[0] == 1 => {
+            Some((extradata[4] & 0x03) as usize + 1)
+        }
+        // HEVCDecoderConfigurationRecord: `lengthSizeMinusOne` is in the 22nd byte.
+        VideoCodec::H265 if extradata.len() > 22 && extradata[0] == 1 => {
+            Some((extradata[21] & 0x03) as usize + 1)
+        }
+        _ => None,
+    }
+}
+
+/// Extract the parameter set NAL units from out-of-band extradata.
+///
+/// # Arguments
+///
+/// * `codec` - Codec of the stream.
+/// * `extradata` - Extradata of the stream.
+pub(crate) fn parameter_sets(codec: VideoCodec, extradata: &[u8]) -> Vec<Vec<u8>> {
+    match codec {
+        VideoCodec::H264 => match extract_parameter_sets_h264(extradata) {
+            Ok((sps, ppss)) => std::iter::once(sps)
+                .chain(ppss)
+                .map(|nal| nal.to_vec())
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+        VideoCodec::H265 => parameter_sets_hvcc(extradata),
+        VideoCodec::Vp9 | VideoCodec::Av1 | VideoCodec::ProRes | VideoCodec::QtRle => Vec::new(),
+    }
+}
+
+/// Extract the NAL units from the arrays of an HEVCDecoderConfigurationRecord.
+///
+/// # Arguments
+///
+/// * `extradata` - Extradata in hvcC format.
+fn parameter_sets_hvcc(extradata: &[u8]) -> Vec<Vec<u8>> {
+    let mut nals = Vec::new();
+    if extradata.len() < 23 {
+        return nals;
+    }
+    let array_count = extradata[22];
+    let mut offset = 23;
+    for _ in 0..array_count {
+        if extradata.len() < offset + 3 {
+            break;
+        }
+        let nal_count = u16::from_be_bytes([extradata[offset + 1], extradata[offset + 2]]);
+        offset += 3;
+        for _ in 0..nal_count {
+            if extradata.len() < offset + 2 {
+                return nals;
+            }
+            let size = u16::from_be_bytes([extradata[offset], extradata[offset + 1]]) as usize;
+            offset += 2;
+            if extradata.len() < offset + size {
+                return nals;
+            }
+            nals.push(extradata[offset..offset + size].to_vec());
+            offset += size;
+        }
+    }
+    nals
+}
+
+/// Serialize NAL units with a big-endian length prefix.
+///
+/// # Arguments
+///
+/// * `nals` - NAL units.
+/// * `nal_length_size` - Size of the length prefix in bytes.
+pub(crate) fn length_prefixed(nals: &[Vec<u8>], nal_length_size: usize) -> Vec<u8> {
+    let mut data = Vec::new();
+    for nal in nals {
+        let length = (nal.len() as u32).to_be_bytes();
+        data.extend_from_slice(&length[4 - nal_length_size..]);
+        data.extend_from_slice(nal);
+    }
+    data
+}
+
+/// Convert NAL units separated by Annex B start codes to length-prefixed NAL units.
+///
+/// # Arguments
+///
+/// * `data` - Annex B data.
+/// * `nal_length_size` - Size of the length prefix in bytes.
+pub(crate) fn annexb_to_length_prefixed(data: &[u8], nal_length_size: usize) -> Vec<u8> {
+    let nals = nal_units(data, None)
+        .into_iter()
+        .map(|nal| nal.to_vec())
+        .collect::<Vec<_>>();
+    length_prefixed(&nals, nal_length_size)
+}
+
+/// Split the data of a packet into its NAL units.
+///
+/// # Arguments
+///
+/// * `data` - Packet data.
+/// * `nal_length_size` - Size of the length prefix of the NAL units, or `None` if they are
+///   separated by Annex B start codes.
+pub(crate) fn nal_units(data: &[u8], nal_length_size: Option<usize>) -> Vec<&[u8]> {
+    let mut nals = Vec::new();
+    match nal_length_size {
+        Some(nal_length_size) => {
+            let mut offset = 0;
+            while offset + nal_length_size <= data.len() {
+                let length = data[offset..offset + nal_length_size]
+                    .iter()
+                    .fold(0, |length, &byte| (length << 8) | byte as usize);
+                offset += nal_length_size;
+                if offset + length > data.len() {
+                    break;
+                }
+                nals.push(&data[offset..offset + length]);
+                offset += length;
+            }
+        }
+        None => {
+            let mut index_current = find_avc_start_code(data, 0).map(|(_, index_next)| index_next);
+            while let Some(index) = index_current {
+                let (end, index_next) = match find_avc_start_code(data, index) {
+                    Some((end, index_next)) => (end, Some(index_next)),
+                    None => (data.len(), None),
+                };
+                nals.push(&data[index..end]);
+                index_current = index_next;
+            }
+        }
+    }
+    nals
+}
+
+/// Whether the data of a packet carries a sequence parameter set in-band.
+///
+/// # Arguments
+///
+/// * `codec` - Codec of the stream.
+/// * `data` - Packet data.
+/// * `nal_length_size` - Size of the length prefix of the NAL units, or `None` if they are
+///   separated by Annex B start codes.
+pub(crate) fn has_parameter_sets(
+    codec: VideoCodec,
+    data: &[u8],
+    nal_length_size: Option<usize>,
+) -> bool {
+    nal_units(data, nal_length_size)
+        .into_iter()
+        .filter_map(|nal| nal.first())
+        .any(|&header| match codec {
+            VideoCodec::H264 => header & 0x1f == 7,
+            VideoCodec::H265 => (header >> 1) & 0x3f == 33,
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annexb_to_length_prefixed() {
+        let data = [0, 0, 0, 1, 0x67, 0xaa, 0, 0, 1, 0x68, 0xbb, 0xcc];
+        assert_eq!(
+            annexb_to_length_prefixed(&data, 4),
+            vec![0, 0, 0, 2, 0x67, 0xaa, 0, 0, 0, 3, 0x68, 0xbb, 0xcc],
+        );
+    }
+
+    #[test]
+    fn test_nal_length_size() {
+        let avcc = [1, 0x64, 0, 0x1f, 0xff, 0xe1];
+        assert_eq!(nal_length_size(VideoCodec::H264, &avcc), Some(4));
+        assert_eq!(nal_length_size(VideoCodec::H264, &[0, 0, 0, 1, 0x67]), None);
+    }
+
+    #[test]
+    fn test_has_parameter_sets() {
+        let annexb = [0, 0, 0, 1, 0x67, 0xaa, 0, 0, 1, 0x65, 0xbb];
+        assert!(has_parameter_sets(VideoCodec::H264, &annexb, None));
+        assert!(!has_parameter_sets(VideoCodec::H264, &annexb[6..], None));
+        let length_prefixed = [0, 2, 0x65, 0xaa, 0, 1, 0x67];
+        assert!(has_parameter_sets(
+            VideoCodec::H264,
+            &length_prefixed,
+            Some(2)
+        ));
+        assert!(!has_parameter_sets(
+            VideoCodec::H264,
+            &length_prefixed[..4],
+            Some(2)
+        ));
+        assert_eq!(nal_units(&[0, 9, 0x65], Some(2)), Vec::<&[u8]>::new());
+    }
+}
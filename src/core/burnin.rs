@@ -0,0 +1,549 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::codec::decoder::Subtitle as AvSubtitleDecoder;
+use ffmpeg::codec::subtitle::Rect as AvRect;
+use ffmpeg::codec::Context as AvContext;
+use ffmpeg::ffi::AVPixelFormat;
+use ffmpeg::filter::Graph as AvFilterGraph;
+use ffmpeg::media::Type as AvMediaType;
+use ffmpeg::{Error as AvError, Rational as AvRational, Subtitle as AvSubtitle};
+
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::frame::{RawFrame, FRAME_PIXEL_FORMAT};
+use crate::core::io::{Reader, ReaderBuilder};
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Builds a [`SubtitleBurner`].
+pub struct SubtitleBurnerBuilder<'a> {
+    source: Location,
+    options: Option<&'a Options>,
+    stream_index: Option<usize>,
+}
+
+impl<'a> SubtitleBurnerBuilder<'a> {
+    /// Create a new burner builder for the specified source.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read subtitles from. This is usually the source the frames are
+    ///   decoded from.
+    ///   Text subtitles can only be burned in from files and network sources.
+    pub fn new(source: impl Into<Location>) -> Self {
+        Self {
+            source: source.into(),
+            options: None,
+            stream_index: None,
+        }
+    }
+
+    /// Specify options for the backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to input.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Select the subtitle stream to burn in. By default the best subtitle stream is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of subtitle stream in source.
+    pub fn with_stream_index(mut self, stream_index: usize) -> Self {
+        self.stream_index = Some(stream_index);
+        self
+    }
+
+    /// Build [`SubtitleBurner`].
+    pub fn build(self) -> Result<SubtitleBurner> {
+        let mut reader_builder = ReaderBuilder::new(self.source.clone());
+        if let Some(options) = self.options {
+            reader_builder = reader_builder.with_options(options);
+        }
+        let reader = reader_builder.build()?;
+        let stream_index = match self.stream_index {
+            Some(stream_index) => stream_index,
+            None => reader.best_subtitle_stream_index()?,
+        };
+
+        let parameters = reader
+            .input
+            .stream(stream_index)
+            .ok_or(AvError::StreamNotFound)?
+            .parameters();
+        if parameters.medium() != AvMediaType::Subtitle {
+            return Err(AvError::StreamNotFound.into());
+        }
+
+        let renderer = if ffi::codec_is_bitmap_subtitle(parameters.id()) {
+            let canvas_size = ffi::codec_parameters_size(&parameters);
+            let decoder = AvContext::from_parameters(parameters)?
+                .decoder()
+                .subtitle()?;
+            Renderer::Bitmap(BitmapRenderer {
+                reader,
+                stream_index,
+                decoder,
+                canvas_size,
+                current: None,
+                next: None,
+                exhausted: false,
+            })
+        } else {
+            // The subtitles filter opens the source itself, and only counts subtitle streams.
+            let subtitle_stream_index = reader
+                .input
+                .streams()
+                .filter(|stream| {
+                    stream.index() < stream_index
+                        && stream.parameters().medium() == AvMediaType::Subtitle
+                })
+                .count();
+            Renderer::Text(TextRenderer {
+                filename: text_subtitle_filename(&self.source)?,
+                subtitle_stream_index,
+                graph: None,
+            })
+        };
+
+        Ok(SubtitleBurner { renderer })
+    }
+}
+
+/// Get the filename that the `subtitles` filter opens the source by. The filter opens the source
+/// itself, so sources that can only be read once or that only exist in memory are not supported.
+///
+/// # Arguments
+///
+/// * `source` - Source to read subtitles from.
+fn text_subtitle_filename(source: &Location) -> Result<String> {
+    match source {
+        Location::File(_) | Location::Network(_) if !source.is_pipe() => {
+            Ok(source.as_path().to_string_lossy().into_owned())
+        }
+        _ => Err(Error::InvalidConfiguration {
+            field: "source",
+            reason: "text subtitles can only be burned in from files and network sources"
+                .to_string(),
+        }),
+    }
+}
+
+/// Renders a subtitle track onto video frames, to produce hardsubbed output for devices that
+/// cannot render soft subtitles.
+///
+/// Text subtitles (e.g. `subrip`, `ass`, `mov_text`) are rendered with libass through the
+/// `subtitles` filter, which requires the backend to be built with libass. Bitmap subtitles
+/// (e.g. PGS, DVB or DVD) are decoded and blended in directly, scaled from the subtitle canvas to
+/// the size of the frames.
+///
+/// Frames must be in the pixel format produced by [`Decoder`](crate::core::decode::Decoder)
+/// (`RGB24`), and must be passed in presentation order. Each frame gets exactly the subtitles
+/// that are shown at its timestamp.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut decoder = Decoder::new(Path::new("movie.mkv")).unwrap();
+/// let mut burner = SubtitleBurner::new(Path::new("movie.mkv")).unwrap();
+/// while let Ok(frame) = decoder.decode_raw() {
+///     let frame = burner.burn(frame, decoder.time_base()).unwrap();
+///     encoder.encode_raw(frame).unwrap();
+/// }
+/// ```
+pub struct SubtitleBurner {
+    renderer: Renderer,
+}
+
+impl SubtitleBurner {
+    /// Create a burner for the best subtitle stream in the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read subtitles from.
+    #[inline]
+    pub fn new(source: impl Into<Location>) -> Result<Self> {
+        SubtitleBurnerBuilder::new(source).build()
+    }
+
+    /// Render the subtitles that are shown at the timestamp of a frame onto it.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to render onto.
+    /// * `time_base` - Time base of the timestamp of the frame.
+    ///
+    /// # Return value
+    ///
+    /// Frame with subtitles.
+    pub fn burn(&mut self, frame: RawFrame, time_base: AvRational) -> Result<RawFrame> {
+        if frame.format() != FRAME_PIXEL_FORMAT {
+            return Err(Error::InvalidFrameFormat);
+        }
+        match self.renderer {
+            Renderer::Text(ref mut renderer) => renderer.render(frame, time_base),
+            Renderer::Bitmap(ref mut renderer) => renderer.render(frame, time_base),
+        }
+    }
+}
+
+unsafe impl Send for SubtitleBurner {}
+unsafe impl Sync for SubtitleBurner {}
+
+/// Renders either text or bitmap subtitles.
+enum Renderer {
+    Text(TextRenderer),
+    Bitmap(BitmapRenderer),
+}
+
+/// Renders text subtitles with the `subtitles` filter.
+struct TextRenderer {
+    filename: String,
+    subtitle_stream_index: usize,
+    /// Filter graph, along with the frame size and time base it was configured for.
+    graph: Option<(AvFilterGraph, (u32, u32), AvRational)>,
+}
+
+impl TextRenderer {
+    /// Render subtitles onto a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to render onto.
+    /// * `time_base` - Time base of the timestamp of the frame.
+    fn render(&mut self, frame: RawFrame, time_base: AvRational) -> Result<RawFrame> {
+        let size = (frame.width(), frame.height());
+        let graph = match self.graph.as_mut() {
+            Some((graph, graph_size, graph_time_base))
+                if *graph_size == size && *graph_time_base == time_base =>
+            {
+                graph
+            }
+            _ => {
+                let graph = self.create_graph(size, time_base)?;
+                &mut self.graph.insert((graph, size, time_base)).0
+            }
+        };
+
+        graph
+            .get("in")
+            .ok_or(AvError::FilterNotFound)?
+            .source()
+            .add(&frame)?;
+        let mut frame_rendered = RawFrame::empty();
+        graph
+            .get("out")
+            .ok_or(AvError::FilterNotFound)?
+            .sink()
+            .frame(&mut frame_rendered)?;
+        Ok(frame_rendered)
+    }
+
+    /// Create a filter graph that renders subtitles onto frames of the given size.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Frame size.
+    /// * `time_base` - Time base of frame timestamps.
+    fn create_graph(&self, size: (u32, u32), time_base: AvRational) -> Result<AvFilterGraph> {
+        let find = |name| ffmpeg::filter::find(name).ok_or(AvError::FilterNotFound);
+
+        let mut graph = AvFilterGraph::new();
+        let buffer_args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect=1/1",
+            size.0,
+            size.1,
+            AVPixelFormat::from(FRAME_PIXEL_FORMAT) as i32,
+            time_base.numerator(),
+            time_base.denominator(),
+        );
+        let mut input = graph.add(&find("buffer")?, "in", &buffer_args)?;
+        let subtitles_args = format!(
+            "filename={}:si={}",
+            escape_filter_option(&self.filename),
+            self.subtitle_stream_index,
+        );
+        let mut subtitles = graph.add(&find("subtitles")?, "subtitles", &subtitles_args)?;
+        let mut output = graph.add(&find("buffersink")?, "out", "")?;
+        output.set_pixel_format(FRAME_PIXEL_FORMAT);
+
+        input.link(0, &mut subtitles, 0);
+        subtitles.link(0, &mut output, 0);
+        graph.validate()?;
+        Ok(graph)
+    }
+}
+
+/// Decodes bitmap subtitles and blends them onto frames.
+struct BitmapRenderer {
+    reader: Reader,
+    stream_index: usize,
+    decoder: AvSubtitleDecoder,
+    canvas_size: (u32, u32),
+    /// Subtitle event that was shown last.
+    current: Option<SubtitleEvent>,
+    /// Subtitle event that comes after the current one.
+    next: Option<SubtitleEvent>,
+    exhausted: bool,
+}
+
+impl BitmapRenderer {
+    /// Blend the subtitles that are shown at the timestamp of a frame onto it.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to render onto.
+    /// * `time_base` - Time base of the timestamp of the frame.
+    fn render(&mut self, mut frame: RawFrame, time_base: AvRational) -> Result<RawFrame> {
+        let time = Time::new(frame.pts(), time_base).as_secs_f64();
+        self.advance(time)?;
+
+        let Some(event) = self.current.as_ref() else {
+            return Ok(frame);
+        };
+        if event.end.is_some_and(|end| time >= end.as_secs_f64()) {
+            return Ok(frame);
+        }
+
+        let frame_size = (frame.width(), frame.height());
+        let canvas_size = if self.canvas_size.0 > 0 && self.canvas_size.1 > 0 {
+            self.canvas_size
+        } else {
+            frame_size
+        };
+        let stride = frame.stride(0);
+        let data = frame.data_mut(0);
+        for image in &event.images {
+            blend(data, stride, frame_size, canvas_size, image);
+        }
+        Ok(frame)
+    }
+
+    /// Move on to the last subtitle event that starts at or before `time`.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - Time in seconds.
+    fn advance(&mut self, time: f64) -> Result<()> {
+        loop {
+            if self.next.is_none() && !self.exhausted {
+                self.next = self.decode_event()?;
+                self.exhausted = self.next.is_none();
+            }
+            match self.next.as_ref() {
+                Some(next) if next.start.as_secs_f64() <= time => {
+                    self.current = self.next.take();
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Decode the next subtitle event from the source.
+    fn decode_event(&mut self) -> Result<Option<SubtitleEvent>> {
+        loop {
+            let packet = match self.reader.read(self.stream_index) {
+                Ok(packet) => packet,
+                Err(Error::ReadExhausted) => return Ok(None),
+                Err(err) => return Err(err),
+            };
+
+            let packet_start = packet.pts();
+            let (packet, _) = packet.into_inner_parts();
+
+            let mut subtitle = AvSubtitle::new();
+            if !self.decoder.decode(&packet, &mut subtitle)? {
+                continue;
+            }
+
+            // Display times of the subtitle are in milliseconds relative to the packet. Events
+            // without an end time are shown until the next event, which may be empty to clear
+            // the screen.
+            let start = packet_start
+                .aligned_with(Time::new(Some(subtitle.start() as i64), (1, 1000).into()))
+                .add();
+            let end =
+                (subtitle.end() > subtitle.start() && subtitle.end() != u32::MAX).then(|| {
+                    packet_start
+                        .aligned_with(Time::new(Some(subtitle.end() as i64), (1, 1000).into()))
+                        .add()
+                });
+            let images = subtitle
+                .rects()
+                .filter_map(|rect| match rect {
+                    AvRect::Bitmap(bitmap) => Some(SubtitleImage {
+                        x: bitmap.x() as u32,
+                        y: bitmap.y() as u32,
+                        width: bitmap.width(),
+                        height: bitmap.height(),
+                        pixels: ffi::subtitle_bitmap_rgba(&bitmap),
+                    }),
+                    _ => None,
+                })
+                .collect();
+
+            return Ok(Some(SubtitleEvent { start, end, images }));
+        }
+    }
+}
+
+/// Bitmaps that are shown from `start` until `end`, or until the next event if there is no end.
+struct SubtitleEvent {
+    start: Time,
+    end: Option<Time>,
+    images: Vec<SubtitleImage>,
+}
+
+/// RGBA subtitle bitmap, positioned on the subtitle canvas.
+struct SubtitleImage {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    pixels: Vec<[u8; 4]>,
+}
+
+/// Alpha-blend a subtitle image onto an `RGB24` frame, scaling it from the subtitle canvas to the
+/// frame.
+///
+/// # Arguments
+///
+/// * `data` - Frame data.
+/// * `stride` - Number of bytes per row of frame data.
+/// * `frame_size` - Frame size.
+/// * `canvas_size` - Size of the canvas the image is positioned on.
+/// * `image` - Image to blend.
+fn blend(
+    data: &mut [u8],
+    stride: usize,
+    frame_size: (u32, u32),
+    canvas_size: (u32, u32),
+    image: &SubtitleImage,
+) {
+    if image.width == 0
+        || image.height == 0
+        || image.pixels.len() < (image.width * image.height) as usize
+    {
+        return;
+    }
+    let scale = |value: u32, to: u32, from: u32| (value as u64 * to as u64 / from as u64) as u32;
+    let x_start = scale(image.x, frame_size.0, canvas_size.0).min(frame_size.0);
+    let x_end = scale(image.x + image.width, frame_size.0, canvas_size.0).min(frame_size.0);
+    let y_start = scale(image.y, frame_size.1, canvas_size.1).min(frame_size.1);
+    let y_end = scale(image.y + image.height, frame_size.1, canvas_size.1).min(frame_size.1);
+
+    for y in y_start..y_end {
+        let image_y = scale(y, canvas_size.1, frame_size.1)
+            .saturating_sub(image.y)
+            .min(image.height - 1);
+        for x in x_start..x_end {
+            let image_x = scale(x, canvas_size.0, frame_size.0)
+                .saturating_sub(image.x)
+                .min(image.width - 1);
+            let [r, g, b, a] = image.pixels[(image_y * image.width + image_x) as usize];
+            if a == 0 {
+                continue;
+            }
+            let offset = y as usize * stride + x as usize * 3;
+            let Some(pixel) = data.get_mut(offset..offset + 3) else {
+                continue;
+            };
+            for (dst, src) in pixel.iter_mut().zip([r, g, b]) {
+                *dst = ((src as u32 * a as u32 + *dst as u32 * (255 - a as u32) + 127) / 255) as u8;
+            }
+        }
+    }
+}
+
+/// Escape a value for use in the option string of a filter.
+///
+/// # Arguments
+///
+/// * `value` - Value to escape.
+fn escape_filter_option(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'' | ':' | '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(x: u32, y: u32, pixel: [u8; 4]) -> SubtitleImage {
+        SubtitleImage {
+            x,
+            y,
+            width: 1,
+            height: 1,
+            pixels: vec![pixel],
+        }
+    }
+
+    #[test]
+    fn test_text_subtitle_filename() {
+        assert_eq!(
+            text_subtitle_filename(&Location::File("movie.mkv".into())).unwrap(),
+            "movie.mkv"
+        );
+        assert!(text_subtitle_filename(&Location::Memory(vec![0u8; 4].into())).is_err());
+        assert!(text_subtitle_filename(&Location::Concat(Vec::new())).is_err());
+        assert!(text_subtitle_filename(&Location::Stdin).is_err());
+    }
+
+    #[test]
+    fn test_blend_opaque_and_transparent() {
+        let mut data = vec![0u8; 2 * 2 * 3];
+        blend(
+            &mut data,
+            6,
+            (2, 2),
+            (2, 2),
+            &image(1, 1, [255, 128, 0, 255]),
+        );
+        blend(
+            &mut data,
+            6,
+            (2, 2),
+            (2, 2),
+            &image(0, 0, [255, 255, 255, 0]),
+        );
+        assert_eq!(&data[9..12], &[255, 128, 0]);
+        assert_eq!(&data[0..3], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn test_blend_scales_from_canvas() {
+        let mut data = vec![0u8; 4 * 4 * 3];
+        blend(
+            &mut data,
+            12,
+            (4, 4),
+            (2, 2),
+            &image(1, 0, [100, 100, 100, 255]),
+        );
+        let lit = data.chunks(3).filter(|pixel| pixel[0] == 100).count();
+        assert_eq!(lit, 4);
+        assert_eq!(&data[6..9], &[100, 100, 100]);
+    }
+
+    #[test]
+    fn test_escape_filter_option() {
+        assert_eq!(
+            escape_filter_option("C:\\movies\\it's.mkv"),
+            "C\\:\\\\movies\\\\it\\'s.mkv"
+        );
+    }
+}
@@ -0,0 +1,1013 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::codec::codec::Codec as AvCodec;
+use ffmpeg::codec::Id as AvCodecId;
+use ffmpeg::util::format::Pixel as AvPixel;
+
+use crate::core::error::Error;
+use crate::core::hwaccel::HardwareAccelerationDeviceType;
+use crate::core::options::Options;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Video codecs that the encoder has first-class support for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum VideoCodec {
+    /// H.264 / AVC (prefers `libx264`).
+    #[default]
+    H264,
+    /// H.265 / HEVC (prefers `libx265`).
+    H265,
+    /// VP9 (prefers `libvpx-vp9`).
+    Vp9,
+    /// AV1 (prefers `libaom-av1`, then `libsvtav1`).
+    Av1,
+    /// Apple ProRes (prefers `prores_ks`). Intra-only, meant for editing and compositing.
+    ProRes,
+    /// QuickTime Animation (`qtrle`). Lossless and intra-only, meant for compositing.
+    QtRle,
+}
+
+impl VideoCodec {
+    /// Find the encoder to use for this codec. Preferred encoder implementations are tried first,
+    /// after which any encoder for the codec id is used.
+    pub fn find_encoder(self) -> Option<AvCodec> {
+        self.preferred_encoder_names()
+            .iter()
+            .find_map(|name| ffmpeg::encoder::find_by_name(name))
+            .or_else(|| ffmpeg::encoder::find(self.id()))
+    }
+
+    /// Get the backend codec id.
+    pub fn id(self) -> AvCodecId {
+        match self {
+            VideoCodec::H264 => AvCodecId::H264,
+            VideoCodec::H265 => AvCodecId::HEVC,
+            VideoCodec::Vp9 => AvCodecId::VP9,
+            VideoCodec::Av1 => AvCodecId::AV1,
+            VideoCodec::ProRes => AvCodecId::PRORES,
+            VideoCodec::QtRle => AvCodecId::QTRLE,
+        }
+    }
+
+    /// Get the codec for a backend codec id, if it is one of the supported inter-frame codecs.
+    /// ProRes and QuickTime Animation are only supported as encoder targets, so they map to
+    /// `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Backend codec id.
+    pub fn from_id(id: AvCodecId) -> Option<Self> {
+        match id {
+            AvCodecId::H264 => Some(VideoCodec::H264),
+            AvCodecId::HEVC => Some(VideoCodec::H265),
+            AvCodecId::VP9 => Some(VideoCodec::Vp9),
+            AvCodecId::AV1 => Some(VideoCodec::Av1),
+            _ => None,
+        }
+    }
+
+    /// Find the hardware encoder for this codec on a device type, or `None` if there is none or
+    /// the backend was built without it.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_type` - Device type to encode on.
+    pub fn find_hardware_encoder(
+        self,
+        device_type: HardwareAccelerationDeviceType,
+    ) -> Option<AvCodec> {
+        ffmpeg::encoder::find_by_name(self.hardware_encoder_name(device_type)?)
+    }
+
+    /// Name of the hardware encoder for this codec on a device type.
+    fn hardware_encoder_name(
+        self,
+        device_type: HardwareAccelerationDeviceType,
+    ) -> Option<&'static str> {
+        use HardwareAccelerationDeviceType::*;
+
+        match (self, device_type) {
+            (VideoCodec::H264, Cuda) => Some("h264_nvenc"),
+            (VideoCodec::H265, Cuda) => Some("hevc_nvenc"),
+            (VideoCodec::Av1, Cuda) => Some("av1_nvenc"),
+            (VideoCodec::H264, VaApi) => Some("h264_vaapi"),
+            (VideoCodec::H265, VaApi) => Some("hevc_vaapi"),
+            (VideoCodec::Vp9, VaApi) => Some("vp9_vaapi"),
+            (VideoCodec::Av1, VaApi) => Some("av1_vaapi"),
+            (VideoCodec::H264, Qsv) => Some("h264_qsv"),
+            (VideoCodec::H265, Qsv) => Some("hevc_qsv"),
+            (VideoCodec::Vp9, Qsv) => Some("vp9_qsv"),
+            (VideoCodec::Av1, Qsv) => Some("av1_qsv"),
+            (VideoCodec::H264, VideoToolbox) => Some("h264_videotoolbox"),
+            (VideoCodec::H265, VideoToolbox) => Some("hevc_videotoolbox"),
+            (VideoCodec::ProRes, VideoToolbox) => Some("prores_videotoolbox"),
+            _ => None,
+        }
+    }
+
+    /// Encoder implementations to try, in order of preference.
+    fn preferred_encoder_names(self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::H264 => &["libx264"],
+            VideoCodec::H265 => &["libx265"],
+            VideoCodec::Vp9 => &["libvpx-vp9"],
+            VideoCodec::Av1 => &["libaom-av1", "libsvtav1"],
+            VideoCodec::ProRes => &["prores_ks"],
+            VideoCodec::QtRle => &["qtrle"],
+        }
+    }
+
+    /// Pixel format to encode with to keep an alpha channel, or `None` if the codec cannot carry
+    /// alpha.
+    pub fn alpha_pixel_format(self) -> Option<AvPixel> {
+        match self {
+            VideoCodec::Vp9 => Some(AvPixel::YUVA420P),
+            VideoCodec::ProRes => Some(AvPixel::YUVA444P10LE),
+            VideoCodec::QtRle => Some(AvPixel::ARGB),
+            VideoCodec::H264 | VideoCodec::H265 | VideoCodec::Av1 => None,
+        }
+    }
+
+    /// Whether or not the codec has a rate control (CRF, QP or bitrate). ProRes and QuickTime
+    /// Animation have fixed quality levels instead.
+    pub fn has_rate_control(self) -> bool {
+        !matches!(self, VideoCodec::ProRes | VideoCodec::QtRle)
+    }
+
+    /// Default constant rate factor for the codec. These are the defaults of the respective
+    /// reference encoders. Returns `0` for codecs without rate control.
+    pub fn default_crf(self) -> u8 {
+        match self {
+            VideoCodec::H264 => 23,
+            VideoCodec::H265 => 28,
+            VideoCodec::Vp9 => 31,
+            VideoCodec::Av1 => 30,
+            VideoCodec::ProRes | VideoCodec::QtRle => 0,
+        }
+    }
+
+    /// Maximum constant rate factor value for the codec. Returns `0` for codecs without rate
+    /// control.
+    pub fn max_crf(self) -> u8 {
+        match self {
+            VideoCodec::H264 | VideoCodec::H265 => 51,
+            VideoCodec::Vp9 | VideoCodec::Av1 => 63,
+            VideoCodec::ProRes | VideoCodec::QtRle => 0,
+        }
+    }
+
+    /// Maximum number of consecutive B-frames supported by the codec. VP9 and AV1 use hidden
+    /// alternate reference frames instead of B-frames.
+    fn max_b_frames(self) -> u32 {
+        match self {
+            VideoCodec::H264 | VideoCodec::H265 => 16,
+            VideoCodec::Vp9 | VideoCodec::Av1 | VideoCodec::ProRes | VideoCodec::QtRle => 0,
+        }
+    }
+
+    /// Maximum number of lookahead frames supported by the preferred encoders for the codec.
+    /// `libvpx-vp9` caps `lag-in-frames` at 25 and `libaom-av1` at 35.
+    fn max_lookahead(self) -> u32 {
+        match self {
+            VideoCodec::Vp9 => 25,
+            VideoCodec::Av1 => 35,
+            VideoCodec::H264 | VideoCodec::H265 | VideoCodec::ProRes | VideoCodec::QtRle => 250,
+        }
+    }
+
+    /// Profiles supported by the codec.
+    fn profiles(self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::H264 => &[
+                "baseline", "main", "high", "high10", "high422", "high444",
+            ],
+            VideoCodec::H265 => &[
+                "main",
+                "main10",
+                "mainstillpicture",
+                "main422-10",
+                "main444-8",
+                "main444-10",
+            ],
+            VideoCodec::Vp9 => &["0", "1", "2", "3"],
+            VideoCodec::Av1 => &["main", "high", "professional"],
+            VideoCodec::ProRes => &["proxy", "lt", "standard", "hq", "4444", "4444xq"],
+            VideoCodec::QtRle => &[],
+        }
+    }
+
+    /// Levels supported by the codec. Returns an empty list if the encoder does not accept a level.
+    fn levels(self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::H264 => &[
+                "1", "1b", "1.1", "1.2", "1.3", "2", "2.1", "2.2", "3", "3.1", "3.2", "4", "4.1",
+                "4.2", "5", "5.1", "5.2", "6", "6.1", "6.2",
+            ],
+            VideoCodec::H265 => &[
+                "1", "2", "2.1", "3", "3.1", "4", "4.1", "5", "5.1", "5.2", "6", "6.1", "6.2",
+            ],
+            VideoCodec::Vp9 | VideoCodec::Av1 | VideoCodec::ProRes | VideoCodec::QtRle => &[],
+        }
+    }
+
+    /// Tunings supported by the codec.
+    fn tunes(self) -> &'static [Tune] {
+        match self {
+            VideoCodec::H264 => &[
+                Tune::Film,
+                Tune::Animation,
+                Tune::Grain,
+                Tune::StillImage,
+                Tune::FastDecode,
+                Tune::ZeroLatency,
+                Tune::Psnr,
+                Tune::Ssim,
+            ],
+            VideoCodec::H265 => &[
+                Tune::Animation,
+                Tune::Grain,
+                Tune::FastDecode,
+                Tune::ZeroLatency,
+                Tune::Psnr,
+                Tune::Ssim,
+            ],
+            VideoCodec::Vp9 | VideoCodec::Av1 => &[Tune::Psnr, Tune::Ssim],
+            VideoCodec::ProRes | VideoCodec::QtRle => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VideoCodec::H264 => write!(f, "h264"),
+            VideoCodec::H265 => write!(f, "h265"),
+            VideoCodec::Vp9 => write!(f, "vp9"),
+            VideoCodec::Av1 => write!(f, "av1"),
+            VideoCodec::ProRes => write!(f, "prores"),
+            VideoCodec::QtRle => write!(f, "qtrle"),
+        }
+    }
+}
+
+/// Encoder speed preset. Slower presets provide better compression at the cost of encoding time.
+///
+/// The presets follow the x264 naming, and are mapped onto the closest equivalent for other
+/// encoders (`deadline` and `cpu-used` for VP9, `cpu-used` or `preset` for AV1).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Preset {
+    UltraFast,
+    SuperFast,
+    VeryFast,
+    Faster,
+    Fast,
+    #[default]
+    Medium,
+    Slow,
+    Slower,
+    VerySlow,
+}
+
+impl Preset {
+    /// Name of the preset as used by x264 and x265.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Preset::UltraFast => "ultrafast",
+            Preset::SuperFast => "superfast",
+            Preset::VeryFast => "veryfast",
+            Preset::Faster => "faster",
+            Preset::Fast => "fast",
+            Preset::Medium => "medium",
+            Preset::Slow => "slow",
+            Preset::Slower => "slower",
+            Preset::VerySlow => "veryslow",
+        }
+    }
+
+    /// Speed of the preset from `0` (slowest) to `8` (fastest).
+    fn speed(self) -> u8 {
+        match self {
+            Preset::UltraFast => 8,
+            Preset::SuperFast => 7,
+            Preset::VeryFast => 6,
+            Preset::Faster => 5,
+            Preset::Fast => 4,
+            Preset::Medium => 3,
+            Preset::Slow => 2,
+            Preset::Slower => 1,
+            Preset::VerySlow => 0,
+        }
+    }
+}
+
+/// Encoder tuning. Not all tunings are supported by all codecs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Tune {
+    Film,
+    Animation,
+    Grain,
+    StillImage,
+    FastDecode,
+    ZeroLatency,
+    Psnr,
+    Ssim,
+}
+
+impl Tune {
+    /// Name of the tuning as used by the backend encoders.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tune::Film => "film",
+            Tune::Animation => "animation",
+            Tune::Grain => "grain",
+            Tune::StillImage => "stillimage",
+            Tune::FastDecode => "fastdecode",
+            Tune::ZeroLatency => "zerolatency",
+            Tune::Psnr => "psnr",
+            Tune::Ssim => "ssim",
+        }
+    }
+}
+
+/// Rate-control mode of the encoder. Bitrates are in bits per second.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RateControl {
+    /// Constant bitrate. The VBV buffer defaults to one second worth of data.
+    Cbr {
+        bitrate: u64,
+        buffer_size: Option<u64>,
+    },
+    /// Variable bitrate around a target, optionally constrained by a VBV buffer.
+    Vbr {
+        bitrate: u64,
+        min_bitrate: Option<u64>,
+        max_bitrate: Option<u64>,
+        buffer_size: Option<u64>,
+    },
+    /// Constant quantization parameter. Every frame is quantized with the same QP, regardless of
+    /// the resulting bitrate.
+    Cqp(u8),
+}
+
+impl RateControl {
+    /// Constant bitrate with the default VBV buffer size.
+    ///
+    /// # Arguments
+    ///
+    /// * `bitrate` - Bitrate in bits per second.
+    pub fn cbr(bitrate: u64) -> Self {
+        RateControl::Cbr {
+            bitrate,
+            buffer_size: None,
+        }
+    }
+
+    /// Unconstrained variable bitrate.
+    ///
+    /// # Arguments
+    ///
+    /// * `bitrate` - Average bitrate in bits per second.
+    pub fn vbr(bitrate: u64) -> Self {
+        RateControl::Vbr {
+            bitrate,
+            min_bitrate: None,
+            max_bitrate: None,
+            buffer_size: None,
+        }
+    }
+
+    /// Check whether the rate control is sensible for the codec.
+    fn validate(&self, codec: VideoCodec) -> Result<()> {
+        match *self {
+            RateControl::Cbr { bitrate, .. } | RateControl::Vbr { bitrate, .. } if bitrate == 0 => {
+                Err(Error::InvalidEncoderOption(
+                    "bitrate must be larger than zero".to_string(),
+                ))
+            }
+            RateControl::Vbr {
+                bitrate,
+                min_bitrate,
+                max_bitrate,
+                ..
+            } if min_bitrate.is_some_and(|min| min > bitrate)
+                || max_bitrate.is_some_and(|max| max < bitrate) =>
+            {
+                Err(Error::InvalidEncoderOption(
+                    "bitrate must lie between minimum and maximum bitrate".to_string(),
+                ))
+            }
+            RateControl::Cqp(qp) if qp > codec.max_crf() => Err(Error::InvalidEncoderOption(
+                format!("qp {qp} is out of range for {codec} (0 to {})", codec.max_crf()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Translate into backend options for a specific encoder.
+    fn apply_to(&self, encoder_name: &str, options: &mut Options) {
+        match *self {
+            RateControl::Cbr {
+                bitrate,
+                buffer_size,
+            } => {
+                let bitrate = bitrate.to_string();
+                options.set("b", &bitrate);
+                options.set("minrate", &bitrate);
+                options.set("maxrate", &bitrate);
+                options.set("bufsize", &buffer_size.map_or(bitrate, |size| size.to_string()));
+                if encoder_name == "libx264" {
+                    // Pad the stream so that the bitrate is truly constant.
+                    options.set("nal-hrd", "cbr");
+                }
+            }
+            RateControl::Vbr {
+                bitrate,
+                min_bitrate,
+                max_bitrate,
+                buffer_size,
+            } => {
+                options.set("b", &bitrate.to_string());
+                if let Some(min_bitrate) = min_bitrate {
+                    options.set("minrate", &min_bitrate.to_string());
+                }
+                if let Some(max_bitrate) = max_bitrate {
+                    options.set("maxrate", &max_bitrate.to_string());
+                }
+                if let Some(buffer_size) = buffer_size {
+                    options.set("bufsize", &buffer_size.to_string());
+                }
+            }
+            RateControl::Cqp(qp) => {
+                let qp = qp.to_string();
+                match encoder_name {
+                    "libx264" | "libx265" => options.set("qp", &qp),
+                    "libsvtav1" => {
+                        options.set("rc", "0");
+                        options.set("qp", &qp);
+                    }
+                    // Other encoders have no dedicated constant QP mode, so pin the quantizer
+                    // range instead.
+                    _ => {
+                        options.set("qmin", &qp);
+                        options.set("qmax", &qp);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rate control parameters of an open encoder, in bits and bits per second. Zero means unset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct RateControlParameters {
+    pub bitrate: i64,
+    pub min_bitrate: i64,
+    pub max_bitrate: i64,
+    pub buffer_size: i64,
+}
+
+impl RateControlParameters {
+    /// Encoders that pick up rate control changes on a running encoder.
+    const RECONFIGURABLE_ENCODERS: [&'static str; 6] = [
+        "libx264",
+        "h264_nvenc",
+        "hevc_nvenc",
+        "av1_nvenc",
+        "h264_qsv",
+        "hevc_qsv",
+    ];
+
+    /// Whether or not an encoder supports changing its bitrate while it is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoder_name` - Name of encoder implementation.
+    pub(crate) fn is_reconfigurable(encoder_name: &str) -> bool {
+        Self::RECONFIGURABLE_ENCODERS.contains(&encoder_name)
+    }
+
+    /// Retarget the parameters to a new bitrate. The minimum and maximum bitrate and the buffer
+    /// size are scaled along, so that a CBR stream stays CBR and the buffer keeps covering the same
+    /// duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `bitrate` - New target bitrate in bits per second.
+    pub(crate) fn with_bitrate(self, bitrate: u64) -> Result<Self> {
+        if bitrate == 0 || bitrate > i64::MAX as u64 {
+            return Err(Error::InvalidEncoderOption(
+                "bitrate must be larger than zero".to_string(),
+            ));
+        }
+        if self.bitrate <= 0 {
+            return Err(Error::InvalidEncoderOption(
+                "bitrate can only be changed when the encoder targets a bitrate".to_string(),
+            ));
+        }
+        let bitrate = bitrate as i64;
+        let scale = |value: i64| {
+            if value > 0 {
+                (value as i128 * bitrate as i128 / self.bitrate as i128) as i64
+            } else {
+                value
+            }
+        };
+        Ok(Self {
+            bitrate,
+            min_bitrate: scale(self.min_bitrate),
+            max_bitrate: scale(self.max_bitrate),
+            buffer_size: scale(self.buffer_size),
+        })
+    }
+}
+
+/// Typed codec options. These are validated against the codec and then translated into the
+/// backend options understood by the actual encoder implementation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodecOptions {
+    pub crf: Option<u8>,
+    pub rate_control: Option<RateControl>,
+    pub preset: Option<Preset>,
+    pub tune: Option<Tune>,
+    pub profile: Option<String>,
+    pub level: Option<String>,
+    /// Maximum number of frames between keyframes.
+    pub gop_size: Option<u32>,
+    /// Do not let frames reference frames of other GOPs, so that every GOP can be decoded on its
+    /// own. Required for segmented output such as HLS and DASH.
+    pub closed_gop: bool,
+    /// Maximum number of consecutive B-frames. Zero disables B-frames, which lowers latency.
+    pub max_b_frames: Option<u32>,
+    /// Number of reference frames.
+    pub reference_frames: Option<u32>,
+    /// Number of frames the rate control looks ahead. Larger values improve quality at the cost
+    /// of latency and memory.
+    pub lookahead: Option<u32>,
+    /// Do not insert extra keyframes on scene changes, so that keyframes only occur at GOP
+    /// boundaries.
+    pub disable_scene_cut: bool,
+    /// Number of slices (or tiles, for VP9 and AV1) to split each frame into. More slices allow
+    /// more parallelism at a small cost in compression.
+    pub slices: Option<u32>,
+}
+
+impl CodecOptions {
+    /// Check whether the options are supported by the codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - Codec to validate against.
+    pub fn validate(&self, codec: VideoCodec) -> Result<()> {
+        if !codec.has_rate_control() && (self.crf.is_some() || self.rate_control.is_some()) {
+            return Err(Error::InvalidEncoderOption(format!(
+                "{codec} does not support rate control",
+            )));
+        }
+        if let Some(crf) = self.crf {
+            if crf > codec.max_crf() {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "crf {crf} is out of range for {codec} (0 to {})",
+                    codec.max_crf(),
+                )));
+            }
+        }
+        if let Some(rate_control) = self.rate_control {
+            if self.crf.is_some() {
+                return Err(Error::InvalidEncoderOption(
+                    "crf cannot be combined with another rate control mode".to_string(),
+                ));
+            }
+            rate_control.validate(codec)?;
+        }
+        if let Some(tune) = self.tune {
+            if !codec.tunes().contains(&tune) {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "tune {} is not supported by {codec}",
+                    tune.as_str(),
+                )));
+            }
+        }
+        if let Some(profile) = self.profile.as_deref() {
+            if !codec.profiles().contains(&profile) {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "profile {profile} is not supported by {codec}",
+                )));
+            }
+        }
+        if let Some(max_b_frames) = self.max_b_frames {
+            let max = codec.max_b_frames();
+            if max_b_frames > max {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "max b-frames {max_b_frames} is out of range for {codec} (0 to {max})",
+                )));
+            }
+        }
+        if let Some(reference_frames) = self.reference_frames {
+            if !(1..=16).contains(&reference_frames) {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "reference frames {reference_frames} is out of range (1 to 16)",
+                )));
+            }
+        }
+        if let Some(lookahead) = self.lookahead {
+            let max = codec.max_lookahead();
+            if lookahead > max {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "lookahead {lookahead} is out of range for {codec} (0 to {max})",
+                )));
+            }
+        }
+        if let Some(slices) = self.slices {
+            if !(1..=64).contains(&slices) {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "slices {slices} is out of range (1 to 64)",
+                )));
+            }
+        }
+        if self.gop_size == Some(0) {
+            return Err(Error::InvalidEncoderOption(
+                "gop size must be at least 1".to_string(),
+            ));
+        }
+        if let Some(level) = self.level.as_deref() {
+            if !codec.levels().contains(&level) {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "level {level} is not supported by {codec}",
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Translate the typed options into backend options for a specific encoder, on top of the
+    /// given base options. Typed options take precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoder_name` - Name of the encoder implementation, e.g. `libx264`.
+    /// * `base` - Options to start from.
+    pub(crate) fn apply_to(&self, encoder_name: &str, base: &Options) -> Options {
+        let mut options = base.clone();
+
+        match encoder_name {
+            "libx264" | "libx265" => {
+                if let Some(preset) = self.preset {
+                    options.set("preset", preset.as_str());
+                }
+                if let Some(crf) = self.crf {
+                    options.set("crf", &crf.to_string());
+                }
+            }
+            "libvpx-vp9" => {
+                if let Some(preset) = self.preset {
+                    let deadline = match preset {
+                        Preset::UltraFast | Preset::SuperFast | Preset::VeryFast => "realtime",
+                        Preset::VerySlow => "best",
+                        _ => "good",
+                    };
+                    options.set("deadline", deadline);
+                    options.set("cpu-used", &preset.speed().to_string());
+                }
+                if let Some(crf) = self.crf {
+                    // Constant quality mode in libvpx requires the target bitrate to be zero.
+                    options.set("crf", &crf.to_string());
+                    options.set("b", "0");
+                }
+            }
+            "libaom-av1" => {
+                if let Some(preset) = self.preset {
+                    options.set("cpu-used", &preset.speed().to_string());
+                }
+                if let Some(crf) = self.crf {
+                    options.set("crf", &crf.to_string());
+                    options.set("b", "0");
+                }
+            }
+            "libsvtav1" => {
+                if let Some(preset) = self.preset {
+                    // SVT-AV1 presets range from 0 to 13.
+                    options.set("preset", &(preset.speed() + 4).to_string());
+                }
+                if let Some(crf) = self.crf {
+                    options.set("crf", &crf.to_string());
+                }
+            }
+            _ => {
+                if let Some(crf) = self.crf {
+                    options.set("crf", &crf.to_string());
+                }
+            }
+        }
+
+        if let Some(rate_control) = self.rate_control {
+            rate_control.apply_to(encoder_name, &mut options);
+        }
+        if let Some(tune) = self.tune {
+            options.set("tune", tune.as_str());
+        }
+        if let Some(profile) = self.profile.as_deref() {
+            options.set("profile", profile);
+        }
+        if let Some(level) = self.level.as_deref() {
+            options.set("level", level);
+        }
+
+        if let Some(gop_size) = self.gop_size {
+            options.set("g", &gop_size.to_string());
+        }
+        if self.closed_gop {
+            append_option(&mut options, "flags", "", "+cgop");
+            // Make sure forced keyframes become IDR frames, otherwise they do not start a new GOP.
+            if matches!(encoder_name, "libx264" | "libx265") {
+                options.set("forced-idr", "1");
+            }
+        }
+        if let Some(max_b_frames) = self.max_b_frames {
+            options.set("bf", &max_b_frames.to_string());
+        }
+        if let Some(reference_frames) = self.reference_frames {
+            options.set("refs", &reference_frames.to_string());
+        }
+        if let Some(slices) = self.slices {
+            // VP9 and AV1 split frames into tile columns instead, counted as a power of two.
+            let tile_columns = slices.ilog2().to_string();
+            match encoder_name {
+                "libx265" => append_option(
+                    &mut options,
+                    "x265-params",
+                    ":",
+                    &format!("slices={slices}"),
+                ),
+                "libvpx-vp9" | "libaom-av1" => options.set("tile-columns", &tile_columns),
+                "libsvtav1" => append_option(
+                    &mut options,
+                    "svtav1-params",
+                    ":",
+                    &format!("tile-columns={tile_columns}"),
+                ),
+                _ => options.set("slices", &slices.to_string()),
+            }
+        }
+        if let Some(lookahead) = self.lookahead {
+            let lookahead = lookahead.to_string();
+            match encoder_name {
+                "libx264" => options.set("rc-lookahead", &lookahead),
+                "libx265" => append_option(
+                    &mut options,
+                    "x265-params",
+                    ":",
+                    &format!("rc-lookahead={lookahead}"),
+                ),
+                "libvpx-vp9" | "libaom-av1" => options.set("lag-in-frames", &lookahead),
+                "libsvtav1" => append_option(
+                    &mut options,
+                    "svtav1-params",
+                    ":",
+                    &format!("lookahead={lookahead}"),
+                ),
+                _ => {}
+            }
+        }
+        if self.disable_scene_cut {
+            match encoder_name {
+                "libx264" => options.set("sc_threshold", "0"),
+                "libx265" => append_option(&mut options, "x265-params", ":", "scenecut=0"),
+                "libsvtav1" => append_option(&mut options, "svtav1-params", ":", "scd=0"),
+                _ => {}
+            }
+        }
+
+        options
+    }
+}
+
+/// Append a value to an option that may already have been set, instead of overwriting it.
+fn append_option(options: &mut Options, key: &str, separator: &str, value: &str) {
+    let combined = match options.get(key) {
+        Some(existing) if !existing.is_empty() => format!("{existing}{separator}{value}"),
+        _ => value.to_string(),
+    };
+    options.set(key, &combined);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::encode::{EncoderBuilder, Settings};
+
+    #[test]
+    fn test_validate_crf_range() {
+        let options = CodecOptions {
+            crf: Some(52),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_err());
+        assert!(options.validate(VideoCodec::Vp9).is_ok());
+    }
+
+    #[test]
+    fn test_validate_tune() {
+        let options = CodecOptions {
+            tune: Some(Tune::Film),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_ok());
+        assert!(options.validate(VideoCodec::H265).is_err());
+        assert!(options.validate(VideoCodec::Av1).is_err());
+    }
+
+    #[test]
+    fn test_validate_profile_and_level() {
+        let options = CodecOptions {
+            profile: Some("high".to_string()),
+            level: Some("4.1".to_string()),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_ok());
+        assert!(options.validate(VideoCodec::H265).is_err());
+        assert!(options.validate(VideoCodec::Vp9).is_err());
+    }
+
+    #[test]
+    fn test_rate_control_parameters_scale_with_bitrate() {
+        let cbr = RateControlParameters {
+            bitrate: 4_000_000,
+            min_bitrate: 4_000_000,
+            max_bitrate: 4_000_000,
+            buffer_size: 8_000_000,
+        };
+        assert_eq!(
+            cbr.with_bitrate(2_000_000).unwrap(),
+            RateControlParameters {
+                bitrate: 2_000_000,
+                min_bitrate: 2_000_000,
+                max_bitrate: 2_000_000,
+                buffer_size: 4_000_000,
+            }
+        );
+        let crf = RateControlParameters {
+            bitrate: 0,
+            min_bitrate: 0,
+            max_bitrate: 0,
+            buffer_size: 0,
+        };
+        assert!(crf.with_bitrate(2_000_000).is_err());
+        assert!(cbr.with_bitrate(0).is_err());
+    }
+
+    #[test]
+    fn test_validate_rate_control() {
+        let options = CodecOptions {
+            rate_control: Some(RateControl::Vbr {
+                bitrate: 2_000_000,
+                min_bitrate: None,
+                max_bitrate: Some(1_000_000),
+                buffer_size: None,
+            }),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_err());
+
+        let options = CodecOptions {
+            crf: Some(23),
+            rate_control: Some(RateControl::cbr(2_000_000)),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_err());
+
+        let options = CodecOptions {
+            rate_control: Some(RateControl::Cqp(60)),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_err());
+        assert!(options.validate(VideoCodec::Av1).is_ok());
+    }
+
+    #[test]
+    fn test_validate_intra_only_codecs() {
+        let options = CodecOptions {
+            crf: Some(0),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::ProRes).is_err());
+        let options = CodecOptions {
+            profile: Some("4444".to_string()),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::ProRes).is_ok());
+        assert!(options.validate(VideoCodec::QtRle).is_err());
+        assert_eq!(VideoCodec::H264.alpha_pixel_format(), None);
+        assert_eq!(
+            VideoCodec::Vp9.alpha_pixel_format(),
+            Some(AvPixel::YUVA420P)
+        );
+    }
+
+    #[test]
+    fn test_validate_b_frames() {
+        let options = CodecOptions {
+            max_b_frames: Some(3),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_ok());
+        assert!(options.validate(VideoCodec::Vp9).is_err());
+    }
+
+    #[test]
+    fn test_validate_keyframe_interval() {
+        let path = std::env::temp_dir().join(format!("keyframe-{}.mp4", std::process::id()));
+        let settings = Settings::preset_h264_yuv420p(64, 48, false).with_keyframe_interval(0);
+        let result = EncoderBuilder::new(path.as_path(), settings).build();
+        assert!(matches!(
+            result,
+            Err(Error::InvalidConfiguration {
+                field: "keyframe_interval",
+                ..
+            })
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_validate_lookahead() {
+        let options = CodecOptions {
+            lookahead: Some(40),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_ok());
+        assert!(options.validate(VideoCodec::Vp9).is_err());
+        assert!(options.validate(VideoCodec::Av1).is_err());
+        let options = CodecOptions {
+            lookahead: Some(25),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::Vp9).is_ok());
+    }
+
+    #[test]
+    fn test_apply_lookahead() {
+        let options = CodecOptions {
+            max_b_frames: Some(0),
+            lookahead: Some(10),
+            ..Default::default()
+        };
+        let x264 = options.apply_to("libx264", &Options::default());
+        assert_eq!(x264.get("bf"), Some("0"));
+        assert_eq!(x264.get("rc-lookahead"), Some("10"));
+        let vp9 = options.apply_to("libvpx-vp9", &Options::default());
+        assert_eq!(vp9.get("lag-in-frames"), Some("10"));
+    }
+
+    #[test]
+    fn test_apply_slices() {
+        let options = CodecOptions {
+            slices: Some(4),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_ok());
+        let x264 = options.apply_to("libx264", &Options::default());
+        assert_eq!(x264.get("slices"), Some("4"));
+        let vp9 = options.apply_to("libvpx-vp9", &Options::default());
+        assert_eq!(vp9.get("tile-columns"), Some("2"));
+        let options = CodecOptions {
+            slices: Some(0),
+            ..Default::default()
+        };
+        assert!(options.validate(VideoCodec::H264).is_err());
+    }
+
+    #[test]
+    fn test_apply_cbr() {
+        let options = CodecOptions {
+            rate_control: Some(RateControl::cbr(3_000_000)),
+            ..Default::default()
+        };
+        let x264 = options.apply_to("libx264", &Options::default());
+        assert_eq!(x264.get("b"), Some("3000000"));
+        assert_eq!(x264.get("maxrate"), Some("3000000"));
+        assert_eq!(x264.get("minrate"), Some("3000000"));
+        assert_eq!(x264.get("bufsize"), Some("3000000"));
+        assert_eq!(x264.get("nal-hrd"), Some("cbr"));
+    }
+
+    #[test]
+    fn test_apply_gop_options() {
+        let options = CodecOptions {
+            gop_size: Some(48),
+            closed_gop: true,
+            disable_scene_cut: true,
+            ..Default::default()
+        };
+        let mut base = Options::default();
+        base.set("x265-params", "log-level=error");
+
+        let x264 = options.apply_to("libx264", &base);
+        assert_eq!(x264.get("g"), Some("48"));
+        assert_eq!(x264.get("flags"), Some("+cgop"));
+        assert_eq!(x264.get("sc_threshold"), Some("0"));
+
+        let x265 = options.apply_to("libx265", &base);
+        assert_eq!(x265.get("x265-params"), Some("log-level=error:scenecut=0"));
+    }
+}
@@ -0,0 +1,140 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::codec::decoder::Video as AvDecoder;
+use ffmpeg::codec::encoder::video::Video as AvVideo;
+use ffmpeg::Rational as AvRational;
+
+use crate::core::ffi;
+
+/// Re-export backend color types since they are part of [`ColorMetadata`].
+pub type ColorRange = ffmpeg::util::color::Range;
+pub type ColorPrimaries = ffmpeg::util::color::Primaries;
+pub type ColorTransfer = ffmpeg::util::color::TransferCharacteristic;
+pub type ColorSpace = ffmpeg::util::color::Space;
+
+/// Color and aspect ratio metadata of a video stream. Fields that are unknown are left
+/// unspecified, so that players fall back to their defaults.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColorMetadata {
+    pub range: ColorRange,
+    pub primaries: ColorPrimaries,
+    pub transfer: ColorTransfer,
+    pub space: ColorSpace,
+    /// Sample (pixel) aspect ratio. `0/1` means unknown.
+    pub sample_aspect_ratio: AvRational,
+}
+
+impl ColorMetadata {
+    /// Get the color metadata of a decoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `decoder` - Decoder to get color metadata of.
+    pub(crate) fn from_decoder(decoder: &AvDecoder) -> Self {
+        Self {
+            range: decoder.color_range(),
+            primaries: decoder.color_primaries(),
+            transfer: decoder.color_transfer_characteristic(),
+            space: decoder.color_space(),
+            sample_aspect_ratio: decoder.aspect_ratio(),
+        }
+    }
+
+    /// Set the sample aspect ratio.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_aspect_ratio` - Sample aspect ratio.
+    pub fn with_sample_aspect_ratio(mut self, sample_aspect_ratio: AvRational) -> Self {
+        self.sample_aspect_ratio = sample_aspect_ratio;
+        self
+    }
+
+    /// Fill in every unspecified field with the corresponding field of `fallback`.
+    ///
+    /// # Arguments
+    ///
+    /// * `fallback` - Metadata to take missing fields from.
+    pub fn or(self, fallback: ColorMetadata) -> Self {
+        Self {
+            range: if self.range == ColorRange::Unspecified {
+                fallback.range
+            } else {
+                self.range
+            },
+            primaries: if self.primaries == ColorPrimaries::Unspecified {
+                fallback.primaries
+            } else {
+                self.primaries
+            },
+            transfer: if self.transfer == ColorTransfer::Unspecified {
+                fallback.transfer
+            } else {
+                self.transfer
+            },
+            space: if self.space == ColorSpace::Unspecified {
+                fallback.space
+            } else {
+                self.space
+            },
+            sample_aspect_ratio: if self.sample_aspect_ratio.numerator() <= 0 {
+                fallback.sample_aspect_ratio
+            } else {
+                self.sample_aspect_ratio
+            },
+        }
+    }
+}
+
+impl ColorMetadata {
+    /// Set the color metadata on an encoder that has not been opened yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoder` - Encoder to apply color metadata to.
+    pub(crate) fn apply_to(&self, encoder: &mut AvVideo) {
+        encoder.set_color_range(self.range);
+        encoder.set_colorspace(self.space);
+        ffi::set_encoder_color_primaries_and_transfer(encoder, self.primaries, self.transfer);
+        if self.sample_aspect_ratio.numerator() > 0 {
+            encoder.set_aspect_ratio(self.sample_aspect_ratio);
+        }
+    }
+}
+
+impl Default for ColorMetadata {
+    fn default() -> Self {
+        Self {
+            range: ColorRange::Unspecified,
+            primaries: ColorPrimaries::Unspecified,
+            transfer: ColorTransfer::Unspecified,
+            space: ColorSpace::Unspecified,
+            sample_aspect_ratio: AvRational::new(0, 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_or_keeps_specified_fields() {
+        let overrides = ColorMetadata {
+            range: ColorRange::JPEG,
+            ..Default::default()
+        };
+        let source = ColorMetadata {
+            range: ColorRange::MPEG,
+            primaries: ColorPrimaries::BT709,
+            transfer: ColorTransfer::BT709,
+            space: ColorSpace::BT709,
+            sample_aspect_ratio: AvRational::new(4, 3),
+        };
+        let merged = overrides.or(source);
+        assert_eq!(merged.range, ColorRange::JPEG);
+        assert_eq!(merged.primaries, ColorPrimaries::BT709);
+        assert_eq!(merged.space, ColorSpace::BT709);
+        assert_eq!(merged.sample_aspect_ratio, AvRational::new(4, 3));
+    }
+}
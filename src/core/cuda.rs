@@ -0,0 +1,174 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::util::format::Pixel as AvPixel;
+
+use crate::core::error::Error;
+use crate::core::ffi_hwaccel;
+use crate::core::frame::RawFrame;
+use crate::core::hwaccel::{HardwareFrame, HardwareFrameHandle};
+use crate::core::interop::{texture_planes, PlaneFormat, TexturePlane};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Plane of a frame in CUDA device memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CudaPlane {
+    /// `CUdeviceptr` to the first row of the plane.
+    pub device_ptr: u64,
+    /// Bytes between the starts of consecutive rows.
+    pub pitch: usize,
+    /// Format of the elements of the plane.
+    pub format: PlaneFormat,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+}
+
+impl CudaPlane {
+    /// Number of bytes of pixel data in a row, without the padding up to the pitch. This is the
+    /// width to pass to `cuMemcpy2D`.
+    pub fn width_in_bytes(&self) -> usize {
+        self.width as usize * self.format.bytes_per_pixel()
+    }
+}
+
+/// Frame decoded by NVDEC, in CUDA device memory. Gives CUDA-based inference direct access to the
+/// decoded pixels, without a round trip through host memory.
+///
+/// The frame holds a reference to the decoder surface, so that the decoder does not reuse it
+/// while it is in use. Drop the frame as soon as it has been consumed, since the surface pool of
+/// the decoder is small.
+///
+/// The device pointers belong to [`CudaFrame::context`]. Make it current with `cuCtxPushCurrent`
+/// before accessing them (or wrap it in the context type of a CUDA binding such as cudarc), and
+/// synchronize with [`CudaFrame::stream`] to make sure that decoding has finished.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut decoder = DecoderBuilder::new(source)
+///     .with_hardware_acceleration(HardwareAccelerationDeviceType::Cuda)
+///     .with_hardware_frames()
+///     .build()
+///     .unwrap();
+/// let frame = CudaFrame::from_raw_frame(&decoder.decode_raw().unwrap()).unwrap();
+/// let luma = frame.planes[0];
+/// // cuMemcpy2D from luma.device_ptr with luma.pitch into an input tensor
+/// ```
+pub struct CudaFrame {
+    /// `CUcontext` that owns the device memory of the frame.
+    pub context: *mut std::ffi::c_void,
+    /// `CUstream` that the decoder works on.
+    pub stream: *mut std::ffi::c_void,
+    /// Planes of the frame: luma first, then interleaved chroma.
+    pub planes: Vec<CudaPlane>,
+    /// Layout of the frame, NV12 or P010 for 10-bit video.
+    pub software_format: AvPixel,
+    // Reference that keeps the decoder surface alive.
+    _frame: RawFrame,
+}
+
+impl CudaFrame {
+    /// Get the device memory of a decoded frame.
+    ///
+    /// Fails with [`Error::InvalidFrameFormat`] if the frame is not in CUDA memory, or if it has
+    /// a layout other than NV12 or P010.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame returned by a decoder built with
+    ///   [`DecoderBuilder::with_hardware_frames`](crate::core::decode::DecoderBuilder::with_hardware_frames).
+    pub fn from_raw_frame(frame: &RawFrame) -> Result<CudaFrame> {
+        let Some(HardwareFrame {
+            handle: HardwareFrameHandle::Cuda { planes: pointers },
+            software_format,
+        }) = HardwareFrame::from_raw_frame(frame)
+        else {
+            return Err(Error::InvalidFrameFormat);
+        };
+        let (context, stream) =
+            ffi_hwaccel::frame_cuda_context(frame).ok_or(Error::InvalidFrameFormat)?;
+        let planes = texture_planes(software_format, frame.width(), frame.height())
+            .ok_or(Error::InvalidFrameFormat)?;
+        Ok(CudaFrame {
+            context,
+            stream,
+            planes: cuda_planes(&pointers, &planes).ok_or(Error::InvalidFrameFormat)?,
+            software_format,
+            _frame: ffi_hwaccel::frame_ref(frame).map_err(Error::BackendError)?,
+        })
+    }
+
+    /// Frame width.
+    pub fn width(&self) -> u32 {
+        self.planes[0].width
+    }
+
+    /// Frame height.
+    pub fn height(&self) -> u32 {
+        self.planes[0].height
+    }
+}
+
+impl std::fmt::Debug for CudaFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CudaFrame")
+            .field("context", &self.context)
+            .field("stream", &self.stream)
+            .field("planes", &self.planes)
+            .field("software_format", &self.software_format)
+            .finish_non_exhaustive()
+    }
+}
+
+unsafe impl Send for CudaFrame {}
+unsafe impl Sync for CudaFrame {}
+
+/// Combine the device pointers of a frame with the layout of its planes. Returns `None` if the
+/// frame has fewer planes than its layout requires.
+///
+/// # Arguments
+///
+/// * `pointers` - Device pointer and pitch of each plane.
+/// * `planes` - Layout of each plane.
+fn cuda_planes(pointers: &[(u64, usize)], planes: &[TexturePlane]) -> Option<Vec<CudaPlane>> {
+    if pointers.len() < planes.len() {
+        return None;
+    }
+    Some(
+        pointers
+            .iter()
+            .zip(planes)
+            .map(|(&(device_ptr, pitch), plane)| CudaPlane {
+                device_ptr,
+                pitch,
+                format: plane.format,
+                width: plane.width,
+                height: plane.height,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cuda_planes() {
+        let layout = texture_planes(AvPixel::P010LE, 1920, 1080).unwrap();
+        let planes = cuda_planes(&[(0x7000_0000, 4096), (0x7080_0000, 4096)], &layout).unwrap();
+        assert_eq!(planes[0].device_ptr, 0x7000_0000);
+        assert_eq!(planes[0].width_in_bytes(), 3840);
+        assert_eq!(planes[1].format, PlaneFormat::Rg16);
+        assert_eq!((planes[1].width, planes[1].height), (960, 540));
+        assert_eq!(planes[1].width_in_bytes(), 3840);
+    }
+
+    #[test]
+    fn test_cuda_planes_missing_plane() {
+        let layout = texture_planes(AvPixel::NV12, 1920, 1080).unwrap();
+        assert!(cuda_planes(&[(0x7000_0000, 2048)], &layout).is_none());
+    }
+}
@@ -0,0 +1,212 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::time::Duration;
+
+use crate::core::error::Error;
+use crate::core::io::private::Write as _;
+use crate::core::io::{delegate_writer, Writer, WriterBuilder};
+use crate::core::location::Location;
+use crate::core::options::Options;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Builds a [`DashWriter`].
+pub struct DashWriterBuilder<'a> {
+    manifest: Location,
+    segment_duration: Option<Duration>,
+    window_size: Option<usize>,
+    extra_window_size: Option<usize>,
+    init_segment_template: Option<&'a str>,
+    media_segment_template: Option<&'a str>,
+    timeline: bool,
+    low_latency: bool,
+    hls_playlist: bool,
+    options: Option<&'a Options>,
+}
+
+impl<'a> DashWriterBuilder<'a> {
+    /// Create a writer that writes an MPD manifest to `manifest` and puts the segments next to
+    /// it.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest` - Where to write the `mpd` manifest.
+    pub fn new(manifest: impl Into<Location>) -> Self {
+        Self {
+            manifest: manifest.into(),
+            segment_duration: None,
+            window_size: None,
+            extra_window_size: None,
+            init_segment_template: None,
+            media_segment_template: None,
+            timeline: true,
+            low_latency: false,
+            hls_playlist: false,
+            options: None,
+        }
+    }
+
+    /// Set the target segment duration. Segments are cut on keyframes, so the actual duration
+    /// depends on the keyframe interval of the stream. Defaults to 5 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment_duration` - Target duration of each segment.
+    pub fn with_segment_duration(mut self, segment_duration: Duration) -> Self {
+        self.segment_duration = Some(segment_duration);
+        self
+    }
+
+    /// Set the number of segments kept in the manifest, which turns the output into a live
+    /// stream. By default all segments are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_size` - Number of segments in the manifest.
+    /// * `extra_window_size` - Number of segments that are kept on disk after they have left
+    ///   the manifest, before they are deleted.
+    pub fn with_window_size(mut self, window_size: usize, extra_window_size: usize) -> Self {
+        self.window_size = Some(window_size);
+        self.extra_window_size = Some(extra_window_size);
+        self
+    }
+
+    /// Set the segment templates. Templates may use the DASH identifiers `$RepresentationID$`,
+    /// `$Number$`, `$Bandwidth$` and `$Time$`, as well as `$ext$` for the file extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `init_segment_template` - Name of the initialization segments, e.g.
+    ///   `init-$RepresentationID$.$ext$`.
+    /// * `media_segment_template` - Name of the media segments, e.g.
+    ///   `chunk-$RepresentationID$-$Number%05d$.$ext$`.
+    pub fn with_segment_template(
+        mut self,
+        init_segment_template: &'a str,
+        media_segment_template: &'a str,
+    ) -> Self {
+        self.init_segment_template = Some(init_segment_template);
+        self.media_segment_template = Some(media_segment_template);
+        self
+    }
+
+    /// Address segments by number only instead of writing a `SegmentTimeline` to the manifest.
+    pub fn without_timeline(mut self) -> Self {
+        self.timeline = false;
+        self
+    }
+
+    /// Write segments in chunks as they are produced and signal low-latency DASH in the
+    /// manifest.
+    pub fn with_low_latency(mut self) -> Self {
+        self.low_latency = true;
+        self
+    }
+
+    /// Also write an HLS playlist for the same segments.
+    pub fn with_hls_playlist(mut self) -> Self {
+        self.hls_playlist = true;
+        self
+    }
+
+    /// Specify options for the backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to output.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Build a [`DashWriter`].
+    pub fn build(self) -> Result<DashWriter> {
+        if self.segment_duration == Some(Duration::ZERO) {
+            return Err(Error::InvalidConfiguration {
+                field: "segment_duration",
+                reason: "segment duration must be greater than zero".to_string(),
+            });
+        }
+        if self.window_size == Some(0) {
+            return Err(Error::InvalidConfiguration {
+                field: "window_size",
+                reason: "window must hold at least one segment".to_string(),
+            });
+        }
+
+        let mut writer_builder = WriterBuilder::new(self.manifest).with_format("dash");
+        if let Some(options) = self.options {
+            writer_builder = writer_builder.with_options(options);
+        }
+        let mut writer = writer_builder.build()?;
+
+        let header_options = writer.header_options_mut();
+        header_options.set("use_template", "1");
+        header_options.set("use_timeline", if self.timeline { "1" } else { "0" });
+        if let Some(segment_duration) = self.segment_duration {
+            header_options.set("seg_duration", &segment_duration.as_secs_f64().to_string());
+        }
+        if let Some(window_size) = self.window_size {
+            header_options.set("window_size", &window_size.to_string());
+        }
+        if let Some(extra_window_size) = self.extra_window_size {
+            header_options.set("extra_window_size", &extra_window_size.to_string());
+        }
+        if let Some(init_segment_template) = self.init_segment_template {
+            header_options.set("init_seg_name", init_segment_template);
+        }
+        if let Some(media_segment_template) = self.media_segment_template {
+            header_options.set("media_seg_name", media_segment_template);
+        }
+        if self.low_latency {
+            header_options.set("streaming", "1");
+            header_options.set("ldash", "1");
+        }
+        if self.hls_playlist {
+            header_options.set("hls_playlist", "1");
+        }
+
+        Ok(DashWriter { writer })
+    }
+}
+
+/// Writes MPEG-DASH output: fragmented MP4 segments and an MPD manifest that refers to them
+/// through a segment template. It can be used with a [`Muxer`](crate::core::mux::Muxer), or
+/// passed to [`EncoderBuilder::from_writer`](crate::core::encode::EncoderBuilder::from_writer) to
+/// encode to it directly.
+///
+/// # Example
+///
+/// ```ignore
+/// let writer = DashWriterBuilder::new(Path::new("live/manifest.mpd"))
+///     .with_segment_duration(Duration::from_secs(4))
+///     .with_window_size(6, 2)
+///     .build()
+///     .unwrap();
+/// let mut muxer = MuxerBuilder::new(writer)
+///     .with_streams(&reader)?
+///     .interleaved()
+///     .build()?;
+/// ```
+pub struct DashWriter {
+    writer: Writer,
+}
+
+impl DashWriter {
+    /// Create a writer that writes an MPD manifest to `manifest` with default settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest` - Where to write the `mpd` manifest.
+    #[inline]
+    pub fn new(manifest: impl Into<Location>) -> Result<Self> {
+        DashWriterBuilder::new(manifest).build()
+    }
+
+    /// Location of the manifest.
+    pub fn manifest(&self) -> &Location {
+        &self.writer.destination
+    }
+}
+
+delegate_writer!(DashWriter);
@@ -1,5 +1,7 @@
 extern crate ffmpeg_next as ffmpeg;
 
+use std::collections::VecDeque;
+
 use ffmpeg::codec::decoder::Video as AvDecoder;
 use ffmpeg::codec::Context as AvContext;
 use ffmpeg::format::pixel::Pixel as AvPixel;
@@ -7,25 +9,36 @@ use ffmpeg::software::scaling::{context::Context as AvScaler, flag::Flags as AvS
 use ffmpeg::util::error::EAGAIN;
 use ffmpeg::{Error as AvError, Rational as AvRational};
 
+use crate::core::adaptive::{AdaptiveDecodeController, AdaptiveDecodePolicy, QualityChange};
+use crate::core::color::ColorMetadata;
+use crate::core::discontinuity::{DiscontinuityDetector, DiscontinuityPolicy};
 use crate::core::error::Error;
 use crate::core::ffi;
 use crate::core::ffi_hwaccel;
 #[cfg(feature = "ndarray")]
-use crate::core::frame::Frame;
-use crate::core::frame::{RawFrame, FRAME_PIXEL_FORMAT};
-use crate::core::hwaccel::{HardwareAccelerationContext, HardwareAccelerationDeviceType};
-use crate::core::io::{Reader, ReaderBuilder};
+use crate::core::frame::{Frame, Frame16, FrameF32, GrayFrame, GrayFrame16, YuvFrame};
+use crate::core::frame::{FrameFormat, RawFrame, FRAME_PIXEL_FORMAT};
+use crate::core::hdr::HdrMetadata;
+use crate::core::hwaccel::{
+    hardware_software_format, HardwareAccelerationContext, HardwareAccelerationDeviceType,
+    HardwareAccelerationStatus, HardwareContext, HardwareDevice, HardwareScaler,
+};
+use crate::core::interrupt::Interrupt;
+use crate::core::io::{NetworkOptions, Reader, ReaderBuilder};
+use crate::core::latency::LatencyProfile;
+use crate::core::live::LiveFile;
 use crate::core::location::Location;
+use crate::core::motion::{motion_vectors, MotionVector};
 use crate::core::options::Options;
 use crate::core::packet::Packet;
 use crate::core::resize::Resize;
+use crate::core::seek::{SeekOptions, SeekTarget};
+use crate::core::threads::ThreadBudget;
+use crate::core::throttle::Throttle;
 use crate::core::time::Time;
 
 type Result<T> = std::result::Result<T, Error>;
 
-/// 硬件加速时总是使用 NV12 像素格式，稍后再进行缩放。
-static HWACCEL_PIXEL_FORMAT: AvPixel = AvPixel::NV12;
-
 /// 解码器构建器，用于配置和创建解码器。
 pub struct DecoderBuilder<'a> {
     /// 解码器输入源。
@@ -34,8 +47,34 @@ pub struct DecoderBuilder<'a> {
     options: Option<&'a Options>,
     // 缩放策略。
     resize: Option<Resize>,
+    // 输出帧的格式。
+    frame_format: FrameFormat,
     // 硬件加速设备类型。
     hardware_acceleration_device_type: Option<HardwareAccelerationDeviceType>,
+    // 是否自动选择硬件加速，失败时回退到软件解码。
+    hardware_acceleration_auto: bool,
+    // 硬件加速使用的设备，`None` 表示默认设备。
+    hardware_device: Option<HardwareDevice>,
+    // 是否将硬件解码的帧保留在 GPU 上。
+    keep_hardware_frames: bool,
+    // 时间戳不连续时的处理策略。
+    discontinuity_policy: DiscontinuityPolicy,
+    // 网络源选项。
+    network_options: Option<NetworkOptions>,
+    // 读取带宽限制（用于测试）。
+    throttle: Option<Throttle>,
+    // 用于从其他线程中断阻塞读取的句柄。
+    interrupt: Option<Interrupt>,
+    // 正在写入的文件的读取设置。
+    live_file: Option<LiveFile>,
+    // 负载过高时自动降低输出质量的策略。
+    adaptive_policy: Option<AdaptiveDecodePolicy>,
+    // 线程预算。
+    thread_budget: Option<ThreadBudget>,
+    // 延迟配置。
+    latency: Option<LatencyProfile>,
+    // 是否导出运动矢量。
+    export_motion_vectors: bool,
 }
 
 impl<'a> DecoderBuilder<'a> {
@@ -46,7 +85,20 @@ impl<'a> DecoderBuilder<'a> {
             source: source.into(),
             options: None,
             resize: None,
+            frame_format: FrameFormat::default(),
             hardware_acceleration_device_type: None,
+            hardware_acceleration_auto: false,
+            hardware_device: None,
+            keep_hardware_frames: false,
+            discontinuity_policy: DiscontinuityPolicy::default(),
+            network_options: None,
+            throttle: None,
+            interrupt: None,
+            live_file: None,
+            adaptive_policy: None,
+            thread_budget: None,
+            latency: None,
+            export_motion_vectors: false,
         }
     }
 
@@ -60,12 +112,38 @@ impl<'a> DecoderBuilder<'a> {
 
     /// 设置帧的缩放。
     ///
+    /// 启用硬件加速时，如果设备支持（参见 [`HardwareAccelerationDeviceType::scale_filter`]），
+    /// 缩放在 GPU 上进行，只下载缩放后的帧。
+    ///
     /// * `resize` - 要应用的缩放。
     pub fn with_resize(mut self, resize: Resize) -> Self {
         self.resize = Some(resize);
         self
     }
 
+    /// 让解码器导出每帧的运动矢量，可通过 [`Decoder::motion_vectors`] 获取，无需重新计算光流即可进行
+    /// 运动分析。
+    ///
+    /// 只有软件解码器（如 H.264、HEVC 和 MPEG-4）会导出运动矢量，硬件加速解码时没有运动矢量。
+    pub fn with_motion_vectors(mut self) -> Self {
+        self.export_motion_vectors = true;
+        self
+    }
+
+    /// 设置输出帧的格式，默认为 RGB24。解码器直接输出该格式，视觉处理流水线无需再额外转换一次。
+    ///
+    /// RGB24 和 RGBA 帧通过 [`Decoder::decode`] 获取，灰度帧通过 [`Decoder::decode_gray`] 获取，
+    /// 平面 YUV 帧通过 [`Decoder::decode_yuv`] 获取。高位深和浮点帧分别通过
+    /// [`Decoder::decode_u16`]、[`Decoder::decode_gray16`]、[`Decoder::decode_yuv10`] 和
+    /// [`Decoder::decode_f32`] 获取，适用于 HDR 母版制作和科学视频处理。
+    /// [`Decoder::decode_raw`] 返回该格式的原始帧。
+    ///
+    /// * `frame_format` - 输出帧的格式。
+    pub fn with_frame_format(mut self, frame_format: FrameFormat) -> Self {
+        self.frame_format = frame_format;
+        self
+    }
+
     /// 启用硬件加速。
     ///
     /// * `device_type` - 硬件加速设备类型。
@@ -77,6 +155,113 @@ impl<'a> DecoderBuilder<'a> {
         self
     }
 
+    /// 选择硬件加速使用的设备，用于在有多块 GPU 的服务器上将解码固定到其中一块。默认使用设备类型的默认设备。
+    ///
+    /// 需要同时通过 [`DecoderBuilder::with_hardware_acceleration`] 指定设备类型。
+    ///
+    /// * `device` - 设备序号或设备路径。
+    pub fn with_hardware_device(mut self, device: HardwareDevice) -> Self {
+        self.hardware_device = Some(device);
+        self
+    }
+
+    /// 自动选择硬件加速：依次尝试可用且支持该流编解码器的设备类型（参见
+    /// [`HardwareAccelerationDeviceType::list_available_for_codec`]）。如果所有设备类型都无法初始化，
+    /// 或者第一帧解码失败，则静默回退到软件解码，而不是向调用者返回错误。
+    ///
+    /// 在第一帧解码成功之前，数据包会被保留下来，以便回退后重新送入软件解码器。最多保留 256 个
+    /// 数据包，硬件解码器在此之前仍未输出帧时不再回退。
+    pub fn with_hardware_acceleration_auto(mut self) -> Self {
+        self.hardware_acceleration_auto = true;
+        self
+    }
+
+    /// 将硬件解码的帧保留在 GPU 上，而不是下载为系统内存中的 NV12（10 位内容为 P010）帧，供零拷贝的渲染或处理使用。
+    /// 需要同时启用硬件加速，且不能与自适应解码质量一起使用。只有设备支持 GPU 缩放时才能同时设置缩放，
+    /// 参见 [`HardwareAccelerationDeviceType::scale_filter`]。
+    ///
+    /// 帧只能通过 [`Decoder::decode_raw`] 获取，再用
+    /// [`HardwareFrame::from_raw_frame`](crate::core::hwaccel::HardwareFrame::from_raw_frame)
+    /// 取得硬件句柄（CVPixelBuffer、VASurface、CUDA 指针、D3D11 纹理或 DRM 描述符）。句柄在帧被释放前有效。
+    pub fn with_hardware_frames(mut self) -> Self {
+        self.keep_hardware_frames = true;
+        self
+    }
+
+    /// 设置时间戳不连续（跳变或回绕）时的处理策略。默认为 [`DiscontinuityPolicy::PassThrough`]。
+    ///
+    /// * `policy` - 处理策略。
+    pub fn with_discontinuity_policy(mut self, policy: DiscontinuityPolicy) -> Self {
+        self.discontinuity_policy = policy;
+        self
+    }
+
+    /// 设置网络源的类型化选项，例如超时、自动重连和 HTTP 请求头，免去手动拼写 AVDictionary 键名。
+    ///
+    /// * `network_options` - 网络选项。
+    pub fn with_network_options(mut self, network_options: NetworkOptions) -> Self {
+        self.network_options = Some(network_options);
+        self
+    }
+
+    /// 限制读取带宽，用于在没有外部网络整形工具的情况下测试缓冲、自适应码率和重连逻辑。
+    ///
+    /// * `throttle` - 带宽限制。
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// 允许从其他线程中断打开和读取操作，避免服务器无响应时解码线程永远阻塞。
+    ///
+    /// * `interrupt` - 中断句柄。
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// 播放仍在写入的文件（例如正在进行的录制）。读到文件末尾时等待新数据，而不是结束。
+    ///
+    /// 有关更多信息，请参见 [`ReaderBuilder::with_live_file`](crate::io::ReaderBuilder::with_live_file)。
+    ///
+    /// * `live_file` - 等待新数据的时长和频率。
+    pub fn with_live_file(mut self, live_file: LiveFile) -> Self {
+        self.live_file = Some(live_file);
+        self
+    }
+
+    /// 启用自适应解码质量：当解码持续超过截止时间时，自动降低输出分辨率（或仅解码关键帧），
+    /// 并在性能恢复后切换回来。质量变化可以通过 [`Decoder::quality_changes`] 获取。
+    ///
+    /// 注意：降低质量时输出帧的尺寸会发生变化。
+    ///
+    /// * `policy` - 自适应策略。
+    pub fn with_adaptive_quality(mut self, policy: AdaptiveDecodePolicy) -> Self {
+        self.adaptive_policy = Some(policy);
+        self
+    }
+
+    /// 限制解码器使用的线程数。
+    ///
+    /// 注意：优先级和 CPU 亲和性不会在这里应用，解码线程继承构建解码器的线程的设置，
+    /// 参见 [`ThreadBudget::apply_to_current_thread`]。
+    ///
+    /// * `budget` - 线程预算。
+    pub fn with_thread_budget(mut self, budget: ThreadBudget) -> Self {
+        self.thread_budget = Some(budget);
+        self
+    }
+
+    /// 设置延迟配置，同时调整探测时长、解复用缓冲和低延迟解码，使直播源在一秒内开始播放。
+    ///
+    /// 有关更多信息，请参见 [`LatencyProfile`]。
+    ///
+    /// * `latency` - 延迟配置。
+    pub fn with_latency(mut self, latency: LatencyProfile) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
     /// 构建解码器。
     ///
     /// 此方法负责根据当前配置构建一个解码器实例。它首先使用`ReaderBuilder`来配置和创建一个媒体流读取器，
@@ -86,29 +271,158 @@ impl<'a> DecoderBuilder<'a> {
     ///
     /// 如果构建过程成功，则返回一个`Result`类型，包含构建好的`Decoder`实例；否则返回错误。
     pub fn build(self) -> Result<Decoder> {
+        // 先检查配置，避免在 FFmpeg 内部失败时只得到含义不明的错误码
+        self.validate()?;
         // 创建ReaderBuilder实例，并初始化配置
         let mut reader_builder = ReaderBuilder::new(self.source);
         // 如果有额外的选项配置，则应用这些配置
         if let Some(options) = self.options {
             reader_builder = reader_builder.with_options(options);
         }
+        // 如果设置了网络选项，则应用到读取器
+        if let Some(network_options) = self.network_options {
+            reader_builder = reader_builder.with_network_options(network_options);
+        }
+        // 如果设置了带宽限制，则应用到读取器
+        if let Some(throttle) = self.throttle {
+            reader_builder = reader_builder.with_throttle(throttle);
+        }
+        // 如果设置了中断句柄，则应用到读取器
+        if let Some(interrupt) = self.interrupt {
+            reader_builder = reader_builder.with_interrupt(interrupt);
+        }
+        // 如果设置了实时文件模式，则应用到读取器
+        if let Some(live_file) = self.live_file {
+            reader_builder = reader_builder.with_live_file(live_file);
+        }
+        // 如果设置了延迟配置，则应用到读取器
+        if let Some(latency) = self.latency {
+            reader_builder = reader_builder.with_latency(latency);
+        }
         // 构建配置好的媒体流读取器
         let reader = reader_builder.build()?;
         // 获取最佳的视频流索引
         let reader_stream_index = reader.best_video_stream_index()?;
-        // 创建并返回Decoder实例
-        Ok(Decoder {
-            decoder: DecoderSplit::new(
+        let low_delay = self.latency.is_some_and(LatencyProfile::low_delay_decode);
+        let open_options = DecoderOpenOptions {
+            resize: self.resize,
+            thread_budget: self.thread_budget,
+            low_delay,
+            keep_hardware_frames: self.keep_hardware_frames,
+            frame_format: self.frame_format.pixel_format(),
+            export_motion_vectors: self.export_motion_vectors,
+        };
+        // 打开解码器，自动选择硬件加速时保留回退到软件解码所需的状态
+        let (decoder, software_fallback) = if self.hardware_acceleration_auto {
+            SoftwareFallback::open(&reader, reader_stream_index, &open_options)?
+        } else {
+            let hardware_decoding =
+                self.hardware_acceleration_device_type
+                    .map(|device_type| HardwareDecoding {
+                        device_type,
+                        device: self.hardware_device,
+                        keep_frames: self.keep_hardware_frames,
+                    });
+            let decoder = DecoderSplit::open(
                 &reader,
                 reader_stream_index,
-                self.resize,
-                self.hardware_acceleration_device_type,
-            )?,
+                hardware_decoding,
+                &open_options,
+            )?;
+            (decoder, None)
+        };
+        // 自动选择硬件加速但没有设备能够解码时，记录使用软件解码的原因
+        let software_reason = (self.hardware_acceleration_auto && software_fallback.is_none())
+            .then(|| {
+                "no available hardware acceleration device type can decode the stream".to_string()
+            });
+        // 创建并返回Decoder实例
+        Ok(Decoder {
+            decoder,
             reader,
             reader_stream_index,
+            discontinuity: DiscontinuityDetector::new(self.discontinuity_policy),
+            adaptive: self.adaptive_policy.map(AdaptiveDecodeController::new),
+            quality_changes: Vec::new(),
+            software_fallback,
+            replay_packets: VecDeque::new(),
+            open_options,
+            software_reason,
+            wait_for_keyframe: false,
             draining: false,
         })
     }
+
+    /// 在打开输入之前检查配置是否自洽。
+    ///
+    /// 失败时返回 [`Error::InvalidConfiguration`]，指出有问题的设置及原因。
+    fn validate(&self) -> Result<()> {
+        if let Some(resize) = self.resize {
+            resize.validate()?;
+        }
+        if self.hardware_acceleration_auto && self.hardware_acceleration_device_type.is_some() {
+            return Err(Error::InvalidConfiguration {
+                field: "hardware_acceleration_auto",
+                reason: "automatic selection cannot be combined with a device type".to_string(),
+            });
+        }
+        if let Some(device_type) = self.hardware_acceleration_device_type {
+            if !device_type.is_available() {
+                return Err(Error::InvalidConfiguration {
+                    field: "hardware_acceleration_device_type",
+                    reason: format!("{device_type:?} is not available on this system"),
+                });
+            }
+        }
+        if let Some(device) = self.hardware_device.as_ref() {
+            let Some(device_type) = self.hardware_acceleration_device_type else {
+                return Err(Error::InvalidConfiguration {
+                    field: "hardware_device",
+                    reason: "device selection requires a hardware acceleration device type"
+                        .to_string(),
+                });
+            };
+            if device.device_string(device_type).is_none() {
+                return Err(Error::InvalidConfiguration {
+                    field: "hardware_device",
+                    reason: format!("{device_type:?} does not support selecting {device:?}"),
+                });
+            }
+        }
+        if self.keep_hardware_frames {
+            if self.hardware_acceleration_device_type.is_none() {
+                return Err(Error::InvalidConfiguration {
+                    field: "keep_hardware_frames",
+                    reason: "hardware frames require hardware acceleration".to_string(),
+                });
+            }
+            if self.adaptive_policy.is_some() {
+                return Err(Error::InvalidConfiguration {
+                    field: "keep_hardware_frames",
+                    reason: "hardware frames cannot be resized adaptively".to_string(),
+                });
+            }
+            let scale_filter = self
+                .hardware_acceleration_device_type
+                .and_then(HardwareAccelerationDeviceType::scale_filter);
+            if self.resize.is_some() && scale_filter.is_none() {
+                return Err(Error::InvalidConfiguration {
+                    field: "keep_hardware_frames",
+                    reason: "hardware frames can only be resized by devices that scale on the GPU"
+                        .to_string(),
+                });
+            }
+        }
+        if let Some(cores) = self.thread_budget.as_ref().and_then(ThreadBudget::affinity) {
+            if cores.is_empty() {
+                return Err(Error::InvalidConfiguration {
+                    field: "thread_budget",
+                    reason: "core affinity must contain at least one core".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
 }
 
 /// 解码视频文件和流。
@@ -130,6 +444,22 @@ pub struct Decoder {
     reader: Reader,
     // 媒体流索引。
     reader_stream_index: usize,
+    // 时间戳不连续检测器。
+    discontinuity: DiscontinuityDetector,
+    // 自适应解码质量控制器。
+    adaptive: Option<AdaptiveDecodeController>,
+    // 尚未被调用者取走的质量变化。
+    quality_changes: Vec<QualityChange>,
+    // 自动选择硬件加速时，第一帧解码成功之前回退到软件解码所需的状态。
+    software_fallback: Option<SoftwareFallback>,
+    // 回退到软件解码后需要重新送入解码器的数据包。
+    replay_packets: VecDeque<Packet>,
+    // 重新打开解码器（回退或切换硬件加速）所需的配置。
+    open_options: DecoderOpenOptions,
+    // 回退或切换到软件解码的原因。
+    software_reason: Option<String>,
+    // 切换解码器后是否跳过数据包直到下一个关键帧。
+    wait_for_keyframe: bool,
     // 读取器是否正在被排空。
     draining: bool,
 }
@@ -244,28 +574,62 @@ impl Decoder {
     /// - `Err(Error::DecodeExhausted)`: 解码器耗尽，无法解码出更多帧。
     #[cfg(feature = "ndarray")]
     pub fn decode(&mut self) -> Result<(Time, Frame)> {
-        Ok(loop {
-            // 当不处于排干状态时，尝试从reader中读取数据包
-            if !self.draining {
-                let packet_result = self.reader.read(self.reader_stream_index);
-                // 如果读取结果为ReadExhausted错误，说明当前数据源已耗尽，设置排干标志为true，继续下一轮循环
-                if matches!(packet_result, Err(Error::ReadExhausted)) {
-                    self.draining = true;
-                    continue;
-                }
-                // 成功读取数据包，尝试解码为帧
-                let packet = packet_result?;
-                if let Some(frame) = self.decoder.decode(packet)? {
-                    break frame;
-                }
-            // 当处于排干状态时，尝试从解码器中排出剩余帧
-            } else if let Some(frame) = self.decoder.drain()? {
-                break frame;
-            // 如果解码器中没有更多帧，且无法继续读取或排出帧时，返回DecodeExhausted错误
-            } else {
-                return Err(Error::DecodeExhausted);
-            }
-        })
+        // 解码原始帧（包括时间戳不连续处理和自适应质量控制），再转换为时间和帧
+        let mut frame = self.decode_raw()?;
+        self.decoder.raw_frame_to_time_and_frame(&mut frame)
+    }
+
+    /// 解码一帧灰度图像，返回时间和 `(H, W)` 的 ndarray。需要以
+    /// [`FrameFormat::Gray8`] 构建解码器（参见 [`DecoderBuilder::with_frame_format`]），
+    /// 否则返回 [`Error::InvalidFrameFormat`]。
+    #[cfg(feature = "ndarray")]
+    pub fn decode_gray(&mut self) -> Result<(Time, GrayFrame)> {
+        let frame = self.decode_raw()?;
+        self.decoder.raw_frame_to_time_and_gray_frame(&frame)
+    }
+
+    /// 解码一帧平面 YUV 4:2:0 图像，返回时间和每个平面的 ndarray。需要以
+    /// [`FrameFormat::Yuv420p`] 构建解码器（参见 [`DecoderBuilder::with_frame_format`]），
+    /// 否则返回 [`Error::InvalidFrameFormat`]。
+    #[cfg(feature = "ndarray")]
+    pub fn decode_yuv(&mut self) -> Result<(Time, YuvFrame)> {
+        let frame = self.decode_raw()?;
+        self.decoder.raw_frame_to_time_and_yuv_frame(&frame)
+    }
+
+    /// 解码一帧每个样本 16 位的 RGB 图像，返回时间和 `(H, W, 3)` 的 ndarray。10 位和 12 位视频
+    /// 不会损失精度。需要以 [`FrameFormat::Rgb48`] 构建解码器，否则返回
+    /// [`Error::InvalidFrameFormat`]。
+    #[cfg(feature = "ndarray")]
+    pub fn decode_u16(&mut self) -> Result<(Time, Frame16)> {
+        let frame = self.decode_raw()?;
+        self.decoder.raw_frame_to_time_and_frame16(&frame)
+    }
+
+    /// 解码一帧每个样本为 32 位浮点数的 RGB 图像，返回时间和 `(H, W, 3)` 的 ndarray，取值范围为
+    /// `0` 到 `1`。需要以 [`FrameFormat::RgbF32`] 构建解码器，否则返回
+    /// [`Error::InvalidFrameFormat`]。
+    #[cfg(feature = "ndarray")]
+    pub fn decode_f32(&mut self) -> Result<(Time, FrameF32)> {
+        let frame = self.decode_raw()?;
+        self.decoder.raw_frame_to_time_and_frame_f32(&frame)
+    }
+
+    /// 解码一帧每个样本 16 位的灰度图像，返回时间和 `(H, W)` 的 ndarray。需要以
+    /// [`FrameFormat::Gray16`] 构建解码器，否则返回 [`Error::InvalidFrameFormat`]。
+    #[cfg(feature = "ndarray")]
+    pub fn decode_gray16(&mut self) -> Result<(Time, GrayFrame16)> {
+        let frame = self.decode_raw()?;
+        self.decoder.raw_frame_to_time_and_gray_frame16(&frame)
+    }
+
+    /// 解码一帧每个样本 10 位的平面 YUV 4:2:0 图像，返回时间和每个平面的 ndarray，取值范围为
+    /// `0` 到 `1023`。需要以 [`FrameFormat::Yuv420p10`] 构建解码器，否则返回
+    /// [`Error::InvalidFrameFormat`]。
+    #[cfg(feature = "ndarray")]
+    pub fn decode_yuv10(&mut self) -> Result<(Time, YuvFrame<u16>)> {
+        let frame = self.decode_raw()?;
+        self.decoder.raw_frame_to_time_and_yuv_frame10(&frame)
     }
 
     /// 通过迭代器接口解码帧。类似于 `decode_raw`，但通过无限迭代器返回帧。
@@ -279,28 +643,221 @@ impl Decoder {
     /// 直到成功解码出一个原始帧。如果输入流被耗尽，则尝试通过解码器排出剩余数据来获取最后的原始帧。
     /// 如果没有更多的帧可以解码或排出，则返回错误。
     pub fn decode_raw(&mut self) -> Result<RawFrame> {
-        Ok(loop {
+        let started = std::time::Instant::now();
+        let frame = self.decode_raw_inner()?;
+        if let Some(adaptive) = self.adaptive.as_mut() {
+            // 根据解码耗时调整质量，并记录质量变化供调用者获取
+            if let Some(change) = adaptive.record(started.elapsed()) {
+                tracing::info!("decode quality changed: {change:?}");
+                self.decoder
+                    .set_size_divisor(adaptive.quality().size_divisor())?;
+                self.quality_changes.push(change);
+            }
+        }
+        Ok(frame)
+    }
+
+    /// 获取自上次调用以来发生的解码质量变化。仅在启用了
+    /// [`DecoderBuilder::with_adaptive_quality`] 时才会产生。
+    pub fn quality_changes(&mut self) -> impl Iterator<Item = QualityChange> + '_ {
+        self.quality_changes.drain(..)
+    }
+
+    /// 解码一个原始帧，不进行自适应质量控制。
+    fn decode_raw_inner(&mut self) -> Result<RawFrame> {
+        let frame = loop {
             // 当draining标志未设置时，继续读取数据包
             if !self.draining {
-                let packet_result = self.reader.read(self.reader_stream_index);
-                // 如果读取结果为ReadExhausted错误，表示输入流已被耗尽，设置draining标志以开始排出操作
-                if matches!(packet_result, Err(Error::ReadExhausted)) {
-                    self.draining = true;
-                    continue;
+                // 回退到软件解码后，先重新送入第一帧之前的数据包
+                let packet = match self.replay_packets.pop_front() {
+                    Some(packet) => packet,
+                    None => {
+                        let packet_result = self.reader.read(self.reader_stream_index);
+                        // 如果读取结果为ReadExhausted错误，表示输入流已被耗尽，设置draining标志以开始排出操作
+                        if matches!(packet_result, Err(Error::ReadExhausted)) {
+                            self.draining = true;
+                            continue;
+                        }
+                        // 按策略处理时间戳不连续，被丢弃的数据包直接跳过
+                        let Some(packet) = self.discontinuity.process(packet_result?) else {
+                            continue;
+                        };
+                        // 仅解码关键帧模式下跳过非关键帧
+                        // 离开该模式后也要等到下一个关键帧才能继续解码
+                        let is_key = packet.is_key();
+                        if self
+                            .adaptive
+                            .as_mut()
+                            .is_some_and(|adaptive| adaptive.skips_packet(is_key))
+                        {
+                            continue;
+                        }
+                        // 切换解码器后，新的解码器需要从关键帧开始
+                        if self.wait_for_keyframe {
+                            if !packet.is_key() {
+                                continue;
+                            }
+                            self.wait_for_keyframe = false;
+                        }
+                        packet
+                    }
+                };
+                // 第一帧之前保留数据包，以便回退后重新送入软件解码器
+                if let Some(software_fallback) = self.software_fallback.as_mut() {
+                    if !software_fallback.push(&packet) {
+                        tracing::warn!(
+                            "hardware decoder has not produced a frame after {} packets, \
+                             no longer able to fall back to software decoding",
+                            SoftwareFallback::MAX_PACKETS,
+                        );
+                        self.software_fallback = None;
+                    }
                 }
-                let packet = packet_result?;
                 // 尝试解码数据包为原始帧，如果成功则跳出循环返回帧
-                if let Some(frame) = self.decoder.decode_raw(packet)? {
-                    break frame;
+                match self.decoder.decode_raw(packet) {
+                    Ok(Some(frame)) => break frame,
+                    Ok(None) => {}
+                    Err(err) => self.fall_back_to_software(err)?,
                 }
-            } else if let Some(frame) = self.decoder.drain_raw()? {
-                // 如果draining标志已设置，则尝试通过排出操作获取剩余的原始帧，如果成功则跳出循环返回帧
-                break frame;
             } else {
-                // 如果没有更多的帧可以解码或排出，则返回DecodeExhausted错误
-                return Err(Error::DecodeExhausted);
+                // 如果draining标志已设置，则尝试通过排出操作获取剩余的原始帧，如果成功则跳出循环返回帧
+                match self.decoder.drain_raw() {
+                    Ok(Some(frame)) => break frame,
+                    // 如果没有更多的帧可以解码或排出，则返回DecodeExhausted错误
+                    Ok(None) => return Err(Error::DecodeExhausted),
+                    Err(err) => {
+                        self.fall_back_to_software(err)?;
+                        self.draining = false;
+                    }
+                }
             }
-        })
+        };
+        // 第一帧解码成功后不再需要回退
+        self.software_fallback = None;
+        Ok(frame)
+    }
+
+    /// 第一帧解码失败时回退到软件解码。如果没有启用自动选择硬件加速，或者已经解码过帧，则返回原错误。
+    ///
+    /// # 参数
+    ///
+    /// * `err` - 硬件解码器返回的错误。
+    fn fall_back_to_software(&mut self, err: Error) -> Result<()> {
+        let Some(software_fallback) = self.software_fallback.take() else {
+            return Err(err);
+        };
+        tracing::warn!("hardware decoding failed, falling back to software decoding: {err}");
+        self.decoder = self.open_decoder(None)?;
+        self.replay_packets = software_fallback.packets.into();
+        self.software_reason = Some(format!("hardware decoding failed: {err}"));
+        Ok(())
+    }
+
+    /// 查询当前流是否真正使用了硬件加速。某些编解码器在硬件不支持时会悄悄地在软件中解码，
+    /// 此时返回 [`HardwareAccelerationStatus::Inactive`] 并说明原因，便于诊断。
+    ///
+    /// 解码第一帧之前无法发现悄悄回退的情况。
+    pub fn hardware_acceleration_status(&self) -> HardwareAccelerationStatus {
+        match self.software_reason.as_ref() {
+            Some(reason) => HardwareAccelerationStatus::Inactive {
+                reason: reason.clone(),
+            },
+            None => self.decoder.hardware_acceleration_status(),
+        }
+    }
+
+    /// 在解码过程中切换到软件解码，例如在硬件解码出错或产生错误画面时。解码器被重新打开，
+    /// 从下一个关键帧开始继续解码，之间的帧会被跳过。
+    ///
+    /// 启用了 [`DecoderBuilder::with_hardware_frames`] 时，之后的帧位于系统内存中。
+    ///
+    /// # 参数
+    ///
+    /// * `reason` - 切换的原因，由 [`Decoder::hardware_acceleration_status`] 报告。
+    pub fn switch_to_software_decoding(&mut self, reason: impl Into<String>) -> Result<()> {
+        let reason = reason.into();
+        tracing::warn!("switching to software decoding: {reason}");
+        self.switch_decoder(None)?;
+        self.software_reason = Some(reason);
+        Ok(())
+    }
+
+    /// 在解码过程中切换到硬件解码，例如在软件解码跟不上时。解码器被重新打开，
+    /// 从下一个关键帧开始继续解码，之间的帧会被跳过。
+    ///
+    /// 如果设备类型无法解码该流，返回错误并保留当前的解码器。
+    ///
+    /// # 参数
+    ///
+    /// * `device_type` - 要使用的硬件加速设备类型。
+    /// * `device` - 要使用的设备，`None` 表示默认设备。
+    pub fn switch_to_hardware_decoding(
+        &mut self,
+        device_type: HardwareAccelerationDeviceType,
+        device: Option<HardwareDevice>,
+    ) -> Result<()> {
+        if self.open_options.keep_hardware_frames
+            && self.open_options.resize.is_some()
+            && device_type.scale_filter().is_none()
+        {
+            return Err(Error::InvalidConfiguration {
+                field: "keep_hardware_frames",
+                reason: "hardware frames can only be resized by devices that scale on the GPU"
+                    .to_string(),
+            });
+        }
+        self.switch_decoder(Some(HardwareDecoding {
+            device_type,
+            device,
+            keep_frames: self.open_options.keep_hardware_frames,
+        }))?;
+        self.software_reason = None;
+        Ok(())
+    }
+
+    /// 用新打开的解码器替换当前的解码器，并从下一个关键帧开始解码。
+    ///
+    /// # 参数
+    ///
+    /// * `hardware_decoding` - 新解码器的硬件加速配置，`None` 表示软件解码。
+    fn switch_decoder(&mut self, hardware_decoding: Option<HardwareDecoding>) -> Result<()> {
+        self.decoder = self.open_decoder(hardware_decoding)?;
+        self.software_fallback = None;
+        self.replay_packets.clear();
+        self.wait_for_keyframe = true;
+        Ok(())
+    }
+
+    /// 以构建时的配置重新打开解码器，并恢复自适应质量控制的输出尺寸。
+    ///
+    /// # 参数
+    ///
+    /// * `hardware_decoding` - 解码器的硬件加速配置，`None` 表示软件解码。
+    fn open_decoder(&self, hardware_decoding: Option<HardwareDecoding>) -> Result<DecoderSplit> {
+        let mut decoder = DecoderSplit::open(
+            &self.reader,
+            self.reader_stream_index,
+            hardware_decoding,
+            &self.open_options,
+        )?;
+        if let Some(adaptive) = self.adaptive.as_ref() {
+            decoder.set_size_divisor(adaptive.quality().size_divisor())?;
+        }
+        Ok(decoder)
+    }
+
+    /// 查找后刷新解码器并重置依赖于读取位置的状态。
+    fn reset_after_seek(&mut self) {
+        self.decoder.decoder.flush();
+        self.discontinuity.reset();
+        if let Some(adaptive) = self.adaptive.as_mut() {
+            adaptive.reset();
+        }
+        // 查找之前保留的数据包已经不再适用
+        if let Some(software_fallback) = self.software_fallback.as_mut() {
+            software_fallback.packets.clear();
+        }
+        self.replay_packets.clear();
     }
 
     /// 在读取器中查找。
@@ -322,7 +879,33 @@ impl Decoder {
         // 调用底层的 seek 方法来移动到接近指定时间戳的位置，并在寻求后刷新解码器状态
         self.reader
             .seek(timestamp_milliseconds)
-            .inspect(|_| self.decoder.decoder.flush())
+            .inspect(|_| self.reset_after_seek())
+    }
+
+    /// 按指定的查找语义在读取器中查找，例如拖动进度条时定位到目标之前的关键帧，
+    /// 或在直播 TS 流中按字节偏移查找。
+    ///
+    /// 有关更多信息，请参见 [`Reader::seek_with`](crate::io::Reader::seek_with)。
+    ///
+    /// # 参数
+    ///
+    /// * `target` - 查找目标。
+    /// * `options` - 相对于目标如何定位读取器。
+    #[inline]
+    pub fn seek_with(&mut self, target: SeekTarget, options: SeekOptions) -> Result<()> {
+        self.reader
+            .seek_with(target, options)
+            .inspect(|_| self.reset_after_seek())
+    }
+
+    /// 按字节偏移在读取器中查找，适用于索引缺失或损坏的格式（例如原始 TS 转储）。
+    ///
+    /// 有关更多信息，请参见 [`Reader::seek_to_byte`](crate::io::Reader::seek_to_byte)。
+    #[inline]
+    pub fn seek_to_byte(&mut self, offset: u64) -> Result<()> {
+        self.reader
+            .seek_to_byte(offset)
+            .inspect(|_| self.reset_after_seek())
     }
 
     /// 在读取器中查找特定帧。
@@ -332,7 +915,7 @@ impl Decoder {
     pub fn seek_to_frame(&mut self, frame_number: i64) -> Result<()> {
         self.reader
             .seek_to_frame(frame_number)
-            .inspect(|_| self.decoder.decoder.flush())
+            .inspect(|_| self.reset_after_seek())
     }
 
     /// 查找读取器的开头。
@@ -342,7 +925,7 @@ impl Decoder {
     pub fn seek_to_start(&mut self) -> Result<()> {
         self.reader
             .seek_to_start()
-            .inspect(|_| self.decoder.decoder.flush())
+            .inspect(|_| self.reset_after_seek())
     }
 
     /// 将解码器拆分为解码器（类型为 [`DecoderSplit`]）和 [`Reader`]。
@@ -369,6 +952,67 @@ impl Decoder {
         self.decoder.size_out
     }
 
+    /// 获取硬件解码所用的设备，未启用硬件加速（或已回退到软件解码）时返回 `None`。
+    /// 将其传给 [`EncoderBuilder::with_shared_hardware_context`](crate::core::encode::EncoderBuilder::with_shared_hardware_context)，
+    /// 配合 [`DecoderBuilder::with_hardware_frames`]，转码时帧始终留在 GPU 上。
+    pub fn hardware_context(&self) -> Option<HardwareContext> {
+        self.decoder.hardware_context()
+    }
+
+    /// 获取源视频流的颜色元数据（色彩范围、色域、传输特性、矩阵）和样本宽高比。
+    ///
+    /// 转码时可将其传给 [`Settings::with_color_metadata`](crate::core::encode::Settings::with_color_metadata)，
+    /// 使输出保持与源相同的颜色信息。容器中声明的宽高比优先于编解码器中的宽高比。
+    pub fn color_metadata(&self) -> ColorMetadata {
+        let color = self.decoder.color_metadata();
+        match ffi::get_stream_sample_aspect_ratio(&self.reader.input, self.reader_stream_index) {
+            Some(sample_aspect_ratio) if sample_aspect_ratio.numerator() > 0 => {
+                color.with_sample_aspect_ratio(sample_aspect_ratio)
+            }
+            _ => color,
+        }
+    }
+
+    /// 获取源视频流的 HDR 元数据：HDR 格式（HDR10、HLG 或 Dolby Vision）、母版显示元数据和内容亮度级别，
+    /// 以便应用决定是进行色调映射还是直通输出。
+    ///
+    /// 优先使用容器中声明的元数据。HEVC 等编解码器可能只在码流中携带元数据，此时需要先解码至少一帧。
+    pub fn hdr_metadata(&self) -> HdrMetadata {
+        let input = &self.reader.input;
+        let stream_index = self.reader_stream_index;
+        let mastering_display = ffi::stream_mastering_display_metadata(input, stream_index)
+            .or(self.decoder.frame_mastering_display);
+        let content_light_level = ffi::stream_content_light_level(input, stream_index)
+            .or(self.decoder.frame_content_light_level);
+        HdrMetadata::new(
+            self.color_metadata().transfer,
+            mastering_display.as_ref(),
+            content_light_level.as_ref(),
+            ffi::stream_dovi_configuration(input, stream_index).as_ref(),
+        )
+    }
+
+    /// 获取最近解码的帧的运动矢量，每个矢量描述一个块相对于参考帧的位移。需要以
+    /// [`DecoderBuilder::with_motion_vectors`] 构建解码器，否则为空。
+    ///
+    /// # 示例
+    ///
+    /// ```ignore
+    /// let mut decoder = DecoderBuilder::new(Path::new("video.mp4"))
+    ///     .with_motion_vectors()
+    ///     .build()
+    ///     .unwrap();
+    /// let (_, frame) = decoder.decode().unwrap();
+    /// let motion: f32 = decoder
+    ///     .motion_vectors()
+    ///     .iter()
+    ///     .map(|vector| vector.displacement().0.abs())
+    ///     .sum();
+    /// ```
+    pub fn motion_vectors(&self) -> &[MotionVector] {
+        self.decoder.motion_vectors()
+    }
+
     /// 获取解码器的输入帧率作为浮点值。
     ///
     /// 帧率表示视频每秒显示的帧数，这里通过计算帧率的分子和分母来得到具体的帧率值。
@@ -398,6 +1042,120 @@ impl Decoder {
     }
 }
 
+/// 重新打开解码器所需的配置。
+struct DecoderOpenOptions {
+    // 缩放策略
+    resize: Option<Resize>,
+    // 线程预算
+    thread_budget: Option<ThreadBudget>,
+    // 是否尽早输出帧
+    low_delay: bool,
+    // 是否直接输出硬件帧
+    keep_hardware_frames: bool,
+    // 输出帧的像素格式
+    frame_format: AvPixel,
+    // 是否导出运动矢量
+    export_motion_vectors: bool,
+}
+
+impl DecoderOpenOptions {
+    /// 使用给定的缩放策略和其余默认配置创建。
+    fn new(resize: Option<Resize>) -> Self {
+        Self {
+            resize,
+            thread_budget: None,
+            low_delay: false,
+            keep_hardware_frames: false,
+            frame_format: FRAME_PIXEL_FORMAT,
+            export_motion_vectors: false,
+        }
+    }
+}
+
+/// 自动选择硬件加速时，在第一帧解码成功之前回退到软件解码所需的状态。
+struct SoftwareFallback {
+    // 第一帧之前送入硬件解码器的数据包
+    packets: Vec<Packet>,
+}
+
+impl SoftwareFallback {
+    /// 最多保留的数据包数量。硬件解码器迟迟不输出帧时放弃回退，避免无限占用内存。
+    const MAX_PACKETS: usize = 256;
+
+    /// 保留一个送入硬件解码器的数据包。
+    ///
+    /// 如果已经保留了 [`SoftwareFallback::MAX_PACKETS`] 个数据包，则不再保留并返回 `false`，
+    /// 此时无法再回退。
+    ///
+    /// # 参数
+    ///
+    /// * `packet` - 送入硬件解码器的数据包。
+    fn push(&mut self, packet: &Packet) -> bool {
+        if self.packets.len() >= Self::MAX_PACKETS {
+            return false;
+        }
+        self.packets.push(packet.clone());
+        true
+    }
+
+    /// 依次尝试可用的硬件加速设备类型打开解码器，全部失败时使用软件解码。
+    ///
+    /// 使用硬件加速时，返回的回退状态用于在第一帧解码失败时回退到软件解码。
+    ///
+    /// # 参数
+    ///
+    /// * `reader` - 用于初始化解码器的读取器。
+    /// * `reader_stream_index` - 要解码的流的索引。
+    /// * `open_options` - 解码器的配置。
+    fn open(
+        reader: &Reader,
+        reader_stream_index: usize,
+        open_options: &DecoderOpenOptions,
+    ) -> Result<(DecoderSplit, Option<SoftwareFallback>)> {
+        let codec_id = reader
+            .input
+            .stream(reader_stream_index)
+            .ok_or(AvError::StreamNotFound)?
+            .parameters()
+            .id();
+        for device_type in HardwareAccelerationDeviceType::list_available_for_codec(codec_id) {
+            match DecoderSplit::open(
+                reader,
+                reader_stream_index,
+                Some(HardwareDecoding {
+                    device_type,
+                    device: None,
+                    keep_frames: false,
+                }),
+                open_options,
+            ) {
+                Ok(decoder) => {
+                    tracing::debug!("using hardware acceleration: {device_type:?}");
+                    let software_fallback = SoftwareFallback {
+                        packets: Vec::new(),
+                    };
+                    return Ok((decoder, Some(software_fallback)));
+                }
+                Err(err) => {
+                    tracing::debug!("hardware acceleration {device_type:?} failed: {err}");
+                }
+            }
+        }
+        let decoder = DecoderSplit::open(reader, reader_stream_index, None, open_options)?;
+        Ok((decoder, None))
+    }
+}
+
+/// 解码器的硬件加速配置。
+pub(crate) struct HardwareDecoding {
+    // 硬件加速设备类型
+    device_type: HardwareAccelerationDeviceType,
+    // 使用的设备，`None` 表示默认设备
+    device: Option<HardwareDevice>,
+    // 是否直接输出硬件帧
+    keep_frames: bool,
+}
+
 /// 解码器和读取器的拆分部分。
 ///
 /// 重要提示：在读取器耗尽后不要忘记排空解码器。它可能仍然包含帧。循环运行 `drain_raw()` 或 `drain()` 直到不再生成帧。
@@ -408,12 +1166,30 @@ pub struct DecoderSplit {
     decoder_time_base: AvRational,
     // 解码器输出的帧
     hwaccel_context: Option<HardwareAccelerationContext>,
+    // 是否跳过硬件帧的下载和软件缩放，直接输出硬件帧
+    keep_hardware_frames: bool,
+    // 在下载之前于 GPU 上缩放硬件帧的缩放器（如果设备支持）
+    hardware_scaler: Option<HardwareScaler>,
+    // 启用硬件加速时，最近解码的帧是否位于硬件内存中
+    last_frame_hardware: Option<bool>,
     // 解码器的输出帧
     scaler: Option<AvScaler>,
+    // 缩放器的输入像素格式
+    scaler_input_format: AvPixel,
+    // 输出帧的像素格式
+    frame_format: AvPixel,
     // 解码器输出帧的格式
     size: (u32, u32),
     // 解码器输出帧的格式
     size_out: (u32, u32),
+    // 请求的输出尺寸（自适应质量降低前）
+    size_out_requested: (u32, u32),
+    // 最近解码的帧中携带的母版显示元数据
+    frame_mastering_display: Option<ffi::AVMasteringDisplayMetadata>,
+    // 最近解码的帧中携带的内容亮度级别
+    frame_content_light_level: Option<ffi::AVContentLightMetadata>,
+    // 最近解码的帧的运动矢量
+    frame_motion_vectors: Vec<MotionVector>,
     // 解码器是否处于关闭状态
     draining: bool,
 }
@@ -436,6 +1212,94 @@ impl DecoderSplit {
         resize: Option<Resize>,
         hwaccel_device_type: Option<HardwareAccelerationDeviceType>,
     ) -> Result<Self> {
+        Self::open_with_device_type(
+            reader,
+            reader_stream_index,
+            hwaccel_device_type,
+            &DecoderOpenOptions::new(resize),
+        )
+    }
+
+    /// 创建新的 [`DecoderSplit`]，并使用线程预算限制解码器使用的线程数。
+    ///
+    /// * `reader` - 一个引用，指向用于读取媒体流的读取器。
+    /// * `reader_stream_index` - 读取器流的索引，用于指定要解码的流。
+    /// * `resize` - 可选的缩放策略，如果提供，则使用该策略对输出进行缩放。
+    /// * `hwaccel_device_type` - 可选的硬件加速设备类型，如果提供，则使用相应的硬件加速。
+    /// * `thread_budget` - 线程预算。
+    pub fn new_with_thread_budget(
+        reader: &Reader,
+        reader_stream_index: usize,
+        resize: Option<Resize>,
+        hwaccel_device_type: Option<HardwareAccelerationDeviceType>,
+        thread_budget: &ThreadBudget,
+    ) -> Result<Self> {
+        let mut open_options = DecoderOpenOptions::new(resize);
+        open_options.thread_budget = Some(thread_budget.clone());
+        Self::open_with_device_type(
+            reader,
+            reader_stream_index,
+            hwaccel_device_type,
+            &open_options,
+        )
+    }
+
+    /// 创建新的 [`DecoderSplit`]，并按延迟配置决定是否启用低延迟解码（参见
+    /// [`LatencyProfile::low_delay_decode`]）。适用于 [`RtpReceiver::into_reader`] 等自行创建的读取器。
+    ///
+    /// * `reader` - 一个引用，指向用于读取媒体流的读取器。
+    /// * `reader_stream_index` - 读取器流的索引，用于指定要解码的流。
+    /// * `resize` - 可选的缩放策略，如果提供，则使用该策略对输出进行缩放。
+    /// * `hwaccel_device_type` - 可选的硬件加速设备类型，如果提供，则使用相应的硬件加速。
+    /// * `latency` - 延迟配置。
+    ///
+    /// [`RtpReceiver::into_reader`]: crate::core::rtp::RtpReceiver::into_reader
+    pub fn new_with_latency(
+        reader: &Reader,
+        reader_stream_index: usize,
+        resize: Option<Resize>,
+        hwaccel_device_type: Option<HardwareAccelerationDeviceType>,
+        latency: LatencyProfile,
+    ) -> Result<Self> {
+        let mut open_options = DecoderOpenOptions::new(resize);
+        open_options.low_delay = latency.low_delay_decode();
+        Self::open_with_device_type(
+            reader,
+            reader_stream_index,
+            hwaccel_device_type,
+            &open_options,
+        )
+    }
+
+    /// 使用可选的硬件加速设备类型（默认设备，不保留硬件帧）创建新的 [`DecoderSplit`]。
+    fn open_with_device_type(
+        reader: &Reader,
+        reader_stream_index: usize,
+        hwaccel_device_type: Option<HardwareAccelerationDeviceType>,
+        open_options: &DecoderOpenOptions,
+    ) -> Result<Self> {
+        let hardware_decoding = hwaccel_device_type.map(|device_type| HardwareDecoding {
+            device_type,
+            device: None,
+            keep_frames: false,
+        });
+        Self::open(reader, reader_stream_index, hardware_decoding, open_options)
+    }
+
+    /// 创建新的 [`DecoderSplit`]，并可选择硬件加速设备、保留硬件帧和启用低延迟解码。
+    ///
+    /// * `hardware_decoding` - 可选的硬件加速配置，包括设备和是否直接输出硬件帧（参见
+    ///   [`DecoderBuilder::with_hardware_frames`]）。
+    /// * `open_options` - 缩放、线程预算、低延迟解码（参见 [`LatencyProfile::low_delay_decode`]）、
+    ///   输出帧的像素格式（参见 [`DecoderBuilder::with_frame_format`]）和运动矢量导出的配置。
+    fn open(
+        reader: &Reader,
+        reader_stream_index: usize,
+        hardware_decoding: Option<HardwareDecoding>,
+        open_options: &DecoderOpenOptions,
+    ) -> Result<Self> {
+        let resize = open_options.resize;
+        let frame_format = open_options.frame_format;
         // 获取指定索引的流，如果不存在则返回错误。
         let reader_stream = reader
             .input
@@ -447,12 +1311,31 @@ impl DecoderSplit {
         ffi::set_decoder_context_time_base(&mut decoder, reader_stream.time_base());
         // 设置解码器参数。
         decoder.set_parameters(reader_stream.parameters())?;
+        // 在打开解码器之前应用线程预算。
+        if let Some(thread_budget) = open_options.thread_budget.as_ref() {
+            thread_budget.apply_to_codec(&mut decoder);
+        }
+        // 低延迟解码在线程预算之后应用，因为它需要改用不增加延迟的切片多线程。
+        if open_options.low_delay {
+            ffi::set_codec_context_low_delay(&mut decoder);
+        }
+        // 让解码器将运动矢量作为帧的附加数据导出。
+        if open_options.export_motion_vectors {
+            ffi::set_codec_context_export_motion_vectors(&mut decoder);
+        }
 
         // 根据是否提供了硬件加速设备类型，决定是否创建硬件加速上下文。
-        let hwaccel_context = match hwaccel_device_type {
-            Some(device_type) => Some(HardwareAccelerationContext::new(&mut decoder, device_type)?),
+        let hwaccel_context = match hardware_decoding.as_ref() {
+            Some(hardware_decoding) => Some(HardwareAccelerationContext::new(
+                &mut decoder,
+                hardware_decoding.device_type,
+                hardware_decoding.device.as_ref(),
+            )?),
             None => None,
         };
+        let keep_hardware_frames = hardware_decoding
+            .as_ref()
+            .is_some_and(|hardware_decoding| hardware_decoding.keep_frames);
 
         // 获取视频解码器和时间基。
         let decoder = decoder.decoder().video()?;
@@ -471,50 +1354,147 @@ impl DecoderSplit {
             None => (decoder.width(), decoder.height()),
         };
 
-        // 确定缩放器的输入格式，如果使用了硬件加速，则使用硬件加速器的像素格式，否则使用解码器的格式。
+        // 确定缩放器的输入格式。如果使用了硬件加速，则根据内容的位深使用 NV12、P010 或 P016，
+        // 避免 10 位 HEVC 和 AV1 等 HDR 内容在下载时丢失精度，否则使用解码器的格式。
         let scaler_input_format = if hwaccel_context.is_some() {
-            HWACCEL_PIXEL_FORMAT
+            hardware_software_format(decoder.format())
         } else {
             decoder.format()
         };
 
-        // 判断是否需要创建缩放器，如果输入格式和输出格式不同，或者尺寸不同，则需要。
-        let is_scaler_needed = !(scaler_input_format == FRAME_PIXEL_FORMAT
-            && decoder.width() == resize_width
-            && decoder.height() == resize_height);
-        let scaler = if is_scaler_needed {
-            Some(
-                AvScaler::get(
-                    scaler_input_format,
-                    decoder.width(),
-                    decoder.height(),
-                    FRAME_PIXEL_FORMAT,
-                    resize_width,
-                    resize_height,
-                    AvScalerFlags::AREA,
-                )
-                .map_err(Error::BackendError)?,
-            )
-        } else {
-            None
-        };
-
         // 保存原始尺寸和输出尺寸。
         let size = (decoder.width(), decoder.height());
         let size_out = (resize_width, resize_height);
 
+        // 如果设备支持，硬件帧在下载之前于 GPU 上缩放，软件缩放器只需转换像素格式。
+        let hardware_scaler = match hardware_decoding {
+            Some(hardware_decoding) if hwaccel_context.is_some() => HardwareScaler::new(
+                hardware_decoding.device_type,
+                scaler_input_format,
+                size_out,
+                decoder_time_base,
+            ),
+            _ => None,
+        };
+        let scaler_input_size = if hardware_scaler.is_some() {
+            size_out
+        } else {
+            size
+        };
+
+        // 创建缩放器（如果需要）。
+        let scaler = Self::create_scaler(
+            scaler_input_format,
+            scaler_input_size,
+            size_out,
+            frame_format,
+        )?;
+
         // 返回新的实例。
         Ok(Self {
             decoder,
             decoder_time_base,
             hwaccel_context,
+            keep_hardware_frames,
+            hardware_scaler,
+            last_frame_hardware: None,
             scaler,
+            scaler_input_format,
+            frame_format,
             size,
             size_out,
+            size_out_requested: size_out,
+            frame_mastering_display: None,
+            frame_content_light_level: None,
+            frame_motion_vectors: Vec::new(),
             draining: false,
         })
     }
 
+    /// 创建缩放器。如果输入格式和输出格式不同，或者尺寸不同，则需要缩放器，否则返回 `None`。
+    ///
+    /// # 参数
+    ///
+    /// * `input_format` - 缩放器的输入像素格式。
+    /// * `size` - 输入尺寸。
+    /// * `size_out` - 输出尺寸。
+    /// * `output_format` - 输出像素格式。
+    fn create_scaler(
+        input_format: AvPixel,
+        size: (u32, u32),
+        size_out: (u32, u32),
+        output_format: AvPixel,
+    ) -> Result<Option<AvScaler>> {
+        if input_format == output_format && size == size_out {
+            return Ok(None);
+        }
+        let scaler = AvScaler::get(
+            input_format,
+            size.0,
+            size.1,
+            output_format,
+            size_out.0,
+            size_out.1,
+            AvScalerFlags::AREA,
+        )
+        .map_err(Error::BackendError)?;
+        Ok(Some(scaler))
+    }
+
+    /// 如果帧的格式或尺寸与缩放器的输入不一致，则重新创建缩放器。
+    ///
+    /// # 参数
+    ///
+    /// * `frame` - 即将缩放的帧。
+    fn update_scaler_input(&mut self, frame: &RawFrame) -> Result<()> {
+        let input = (frame.format(), (frame.width(), frame.height()));
+        let scaler_input = match self.scaler.as_ref() {
+            Some(scaler) => {
+                let definition = scaler.input();
+                (definition.format, (definition.width, definition.height))
+            }
+            None => (self.frame_format, self.size_out),
+        };
+        if input != scaler_input {
+            self.scaler = Self::create_scaler(input.0, input.1, self.size_out, self.frame_format)?;
+        }
+        Ok(())
+    }
+
+    /// 将输出尺寸设置为请求的输出尺寸除以 `divisor`（保持偶数尺寸），用于自适应解码质量。
+    ///
+    /// # 参数
+    ///
+    /// * `divisor` - 尺寸除数，`1` 表示恢复请求的输出尺寸。
+    pub(crate) fn set_size_divisor(&mut self, divisor: u32) -> Result<()> {
+        let (width, height) = self.size_out_requested;
+        let size_out = if divisor <= 1 {
+            self.size_out_requested
+        } else {
+            (
+                (width / divisor).max(2) & !1,
+                (height / divisor).max(2) & !1,
+            )
+        };
+        if size_out != self.size_out {
+            let scaler_input_size = match self.hardware_scaler.as_mut() {
+                Some(hardware_scaler) => {
+                    hardware_scaler.set_size_out(size_out);
+                    size_out
+                }
+                None => self.size,
+            };
+            self.scaler = Self::create_scaler(
+                self.scaler_input_format,
+                scaler_input_size,
+                size_out,
+                self.frame_format,
+            )?;
+            self.size_out = size_out;
+        }
+        Ok(())
+    }
+
     /// 获取解码器时间基。
     #[inline]
     pub fn time_base(&self) -> AvRational {
@@ -591,6 +1571,55 @@ impl DecoderSplit {
         self.size_out
     }
 
+    /// 获取硬件解码所用的设备，未启用硬件加速时返回 `None`。
+    pub fn hardware_context(&self) -> Option<HardwareContext> {
+        self.hwaccel_context
+            .as_ref()
+            .map(|hwaccel_context| hwaccel_context.hardware_context().clone())
+    }
+
+    /// 查询是否真正使用了硬件加速。启用了硬件加速但解码器输出系统内存中的帧时（编解码器悄悄回退到软件解码），
+    /// 返回 [`HardwareAccelerationStatus::Inactive`]。
+    pub fn hardware_acceleration_status(&self) -> HardwareAccelerationStatus {
+        let Some(hwaccel_context) = self.hwaccel_context.as_ref() else {
+            return HardwareAccelerationStatus::Inactive {
+                reason: "hardware acceleration is not enabled".to_string(),
+            };
+        };
+        let device_type = hwaccel_context.hardware_context().device_type();
+        if self.last_frame_hardware == Some(false) {
+            return HardwareAccelerationStatus::Inactive {
+                reason: format!(
+                    "the {} decoder does not decode this stream on {device_type:?} and decodes it \
+                     in software instead",
+                    self.decoder.id().name(),
+                ),
+            };
+        }
+        HardwareAccelerationStatus::Active(device_type)
+    }
+
+    /// 获取解码器的颜色元数据（色彩范围、色域、传输特性、矩阵）和样本宽高比。
+    pub fn color_metadata(&self) -> ColorMetadata {
+        ColorMetadata::from_decoder(&self.decoder)
+    }
+
+    /// 获取已解码帧中携带的 HDR 元数据。在解码第一帧之前，只能根据传输特性判断 HDR 格式。
+    pub fn hdr_metadata(&self) -> HdrMetadata {
+        HdrMetadata::new(
+            self.decoder.color_transfer_characteristic(),
+            self.frame_mastering_display.as_ref(),
+            self.frame_content_light_level.as_ref(),
+            None,
+        )
+    }
+
+    /// 获取最近解码的帧的运动矢量。没有导出运动矢量时为空，参见
+    /// [`DecoderBuilder::with_motion_vectors`]。
+    pub fn motion_vectors(&self) -> &[MotionVector] {
+        &self.frame_motion_vectors
+    }
+
     /// 将数据包发送到解码器。包括相应地重新缩放时间戳。
     ///
     /// # 参数
@@ -622,16 +1651,50 @@ impl DecoderSplit {
         match self.decoder_receive_frame()? {
             // 如果接收到帧数据
             Some(frame) => {
+                // HEVC 等编解码器在码流中携带 HDR 元数据，只有解码后才能获取
+                if let Some(mastering_display) = ffi::frame_mastering_display_metadata(&frame) {
+                    self.frame_mastering_display = Some(mastering_display);
+                }
+                if let Some(content_light_level) = ffi::frame_content_light_level(&frame) {
+                    self.frame_content_light_level = Some(content_light_level);
+                }
+                // 运动矢量在转换为 ndarray 时丢失，因此在这里保存
+                self.frame_motion_vectors = motion_vectors(&frame);
+
+                // 记录帧是否真正由硬件解码
+                if let Some(hwaccel_context) = self.hwaccel_context.as_ref() {
+                    self.last_frame_hardware = Some(hwaccel_context.format() == frame.format());
+                }
+
                 // 根据硬件加速上下文处理帧数据
                 let frame = match self.hwaccel_context.as_ref() {
-                    // 如果硬件加速上下文存在且格式与帧数据格式匹配，则下载帧数据
+                    // 如果硬件加速上下文存在且格式与帧数据格式匹配，则处理硬件帧
                     Some(hwaccel_context) if hwaccel_context.format() == frame.format() => {
-                        Self::download_frame(&frame)?
+                        // 如果设备支持，先在 GPU 上缩放到输出尺寸
+                        let frame = match self.hardware_scaler.as_mut() {
+                            Some(hardware_scaler)
+                                if (frame.width(), frame.height()) != self.size_out =>
+                            {
+                                hardware_scaler.scale(&frame)?
+                            }
+                            _ => frame,
+                        };
+                        // 如果需要保留硬件帧，则不下载，直接返回
+                        if self.keep_hardware_frames {
+                            return Ok(Some(frame));
+                        }
+                        // 否则以帧在 GPU 上的布局下载帧数据
+                        let format = ffi_hwaccel::frame_hardware_software_format(&frame)
+                            .unwrap_or(self.scaler_input_format);
+                        Self::download_frame(&frame, format)?
                     }
                     // 否则，直接使用原始帧数据
                     _ => frame,
                 };
 
+                // GPU 缩放过的帧与回退到软件解码的帧尺寸不同，确保缩放器的输入与帧一致
+                self.update_scaler_input(&frame)?;
+
                 // 根据缩放器处理帧数据
                 let frame = match self.scaler.as_mut() {
                     // 如果缩放器存在，则对帧数据进行缩放
@@ -678,16 +1741,17 @@ impl DecoderSplit {
     /// # 参数
     ///
     /// * `frame`: 一个指向原始帧的引用，该帧包含从硬件设备下载所需的信息。
+    /// * `format`: 下载后的像素格式，通常是帧在硬件内存中的布局（NV12、P010 或 P016）。
     ///
     /// # 返回
     ///
     /// * `Result<RawFrame>`: 返回一个结果类型，其中包含下载并格式化后的帧，如果操作成功，
     ///   或者一个错误，如果操作失败。
-    fn download_frame(frame: &RawFrame) -> Result<RawFrame> {
+    fn download_frame(frame: &RawFrame, format: AvPixel) -> Result<RawFrame> {
         // 创建一个空的帧用于接收下载的内容。
         let mut frame_downloaded = RawFrame::empty();
-        // 设置帧的格式以匹配硬件加速设备的像素格式。
-        frame_downloaded.set_format(HWACCEL_PIXEL_FORMAT);
+        // 设置帧的格式以匹配硬件内存中的布局，这样下载时不会丢失位深。
+        frame_downloaded.set_format(format);
         // 调用硬件设备的API将帧数据传输到我们创建的帧中。
         ffi_hwaccel::hwdevice_transfer_frame(&mut frame_downloaded, frame)?;
         // 复制原始帧的属性到新下载的帧中，以保留必要的元数据。
@@ -725,7 +1789,7 @@ impl DecoderSplit {
     /// 将原始帧转换为时间和帧
     ///
     /// 此函数接收一个可变引用到一个 `RawFrame` 对象，并将其转换为一个包含时间和帧的元组。
-    /// 时间是根据帧的 DTS（解码时间戳）计算的，而帧本身则被转换为一个 RGB24 格式的 ndarray。
+    /// 时间是根据帧的 DTS（解码时间戳）计算的，而帧本身则被转换为一个 RGB24 或 RGBA 格式的 ndarray。
     ///
     /// # 参数
     ///
@@ -737,16 +1801,130 @@ impl DecoderSplit {
     /// 如果转换过程中发生错误，则返回一个错误。
     #[cfg(feature = "ndarray")]
     fn raw_frame_to_time_and_frame(&self, frame: &mut RawFrame) -> Result<(Time, Frame)> {
-        // 我们在这里使用数据包 DTS（即 `frame->pkt_dts`），因为这就是编码器在为 `PTS` 字段编码时使用的。
-        // 这允许我们正确地同步音频和视频。
-        let timestamp = Time::new(Some(frame.packet().dts), self.decoder_time_base);
+        let timestamp = self.frame_timestamp(frame, &[AvPixel::RGB24, AvPixel::RGBA])?;
 
-        // 将帧转换为 RGB24 格式的 ndarray。这个转换可能会失败，因此我们在这里处理错误。
-        let frame = ffi::convert_frame_to_ndarray_rgb24(frame).map_err(Error::BackendError)?;
+        // 将帧转换为 RGB24 或 RGBA 格式的 ndarray。这个转换可能会失败，因此我们在这里处理错误。
+        let frame = if frame.format() == AvPixel::RGBA {
+            ffi::convert_frame_to_ndarray_rgba(frame)
+        } else {
+            ffi::convert_frame_to_ndarray_rgb24(frame)
+        }
+        .map_err(Error::BackendError)?;
 
         // 返回转换后的时间和帧。
         Ok((timestamp, frame))
     }
+
+    /// 将灰度原始帧转换为时间和 `(H, W)` 的 ndarray。
+    ///
+    /// # 参数
+    ///
+    /// * `frame` - 待转换的原始帧，必须是 GRAY8 格式。
+    #[cfg(feature = "ndarray")]
+    fn raw_frame_to_time_and_gray_frame(&self, frame: &RawFrame) -> Result<(Time, GrayFrame)> {
+        let timestamp = self.frame_timestamp(frame, &[AvPixel::GRAY8])?;
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        Ok((
+            timestamp,
+            ffi::convert_frame_plane_to_ndarray(frame, 0, width, height),
+        ))
+    }
+
+    /// 将平面 YUV 4:2:0 原始帧转换为时间和每个平面的 ndarray。
+    ///
+    /// # 参数
+    ///
+    /// * `frame` - 待转换的原始帧，必须是 YUV420P 格式。
+    #[cfg(feature = "ndarray")]
+    fn raw_frame_to_time_and_yuv_frame(&self, frame: &RawFrame) -> Result<(Time, YuvFrame)> {
+        let timestamp = self.frame_timestamp(frame, &[AvPixel::YUV420P])?;
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+        Ok((
+            timestamp,
+            YuvFrame {
+                y: ffi::convert_frame_plane_to_ndarray(frame, 0, width, height),
+                u: ffi::convert_frame_plane_to_ndarray(frame, 1, chroma_width, chroma_height),
+                v: ffi::convert_frame_plane_to_ndarray(frame, 2, chroma_width, chroma_height),
+            },
+        ))
+    }
+
+    /// 将 RGB48LE 原始帧转换为时间和 `(H, W, 3)` 的 16 位 ndarray。
+    ///
+    /// # 参数
+    ///
+    /// * `frame` - 待转换的原始帧，必须是 RGB48LE 格式。
+    #[cfg(feature = "ndarray")]
+    fn raw_frame_to_time_and_frame16(&self, frame: &RawFrame) -> Result<(Time, Frame16)> {
+        let timestamp = self.frame_timestamp(frame, &[AvPixel::RGB48LE])?;
+        Ok((timestamp, ffi::convert_frame_to_ndarray_rgb48(frame)))
+    }
+
+    /// 将 GBRPF32LE 原始帧转换为时间和 `(H, W, 3)` 的浮点 ndarray。
+    ///
+    /// # 参数
+    ///
+    /// * `frame` - 待转换的原始帧，必须是 GBRPF32LE 格式。
+    #[cfg(feature = "ndarray")]
+    fn raw_frame_to_time_and_frame_f32(&self, frame: &RawFrame) -> Result<(Time, FrameF32)> {
+        let timestamp = self.frame_timestamp(frame, &[AvPixel::GBRPF32LE])?;
+        Ok((timestamp, ffi::convert_frame_to_ndarray_gbrpf32(frame)))
+    }
+
+    /// 将 16 位灰度原始帧转换为时间和 `(H, W)` 的 ndarray。
+    ///
+    /// # 参数
+    ///
+    /// * `frame` - 待转换的原始帧，必须是 GRAY16LE 格式。
+    #[cfg(feature = "ndarray")]
+    fn raw_frame_to_time_and_gray_frame16(&self, frame: &RawFrame) -> Result<(Time, GrayFrame16)> {
+        let timestamp = self.frame_timestamp(frame, &[AvPixel::GRAY16LE])?;
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        Ok((
+            timestamp,
+            ffi::convert_frame_plane_to_ndarray_u16(frame, 0, width, height),
+        ))
+    }
+
+    /// 将 10 位平面 YUV 4:2:0 原始帧转换为时间和每个平面的 ndarray。
+    ///
+    /// # 参数
+    ///
+    /// * `frame` - 待转换的原始帧，必须是 YUV420P10LE 格式。
+    #[cfg(feature = "ndarray")]
+    fn raw_frame_to_time_and_yuv_frame10(&self, frame: &RawFrame) -> Result<(Time, YuvFrame<u16>)> {
+        let timestamp = self.frame_timestamp(frame, &[AvPixel::YUV420P10LE])?;
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+        Ok((
+            timestamp,
+            YuvFrame {
+                y: ffi::convert_frame_plane_to_ndarray_u16(frame, 0, width, height),
+                u: ffi::convert_frame_plane_to_ndarray_u16(frame, 1, chroma_width, chroma_height),
+                v: ffi::convert_frame_plane_to_ndarray_u16(frame, 2, chroma_width, chroma_height),
+            },
+        ))
+    }
+
+    /// 检查原始帧的格式并获取其时间戳。格式不在 `formats` 中时（包括保留在 GPU 上的帧）返回
+    /// [`Error::InvalidFrameFormat`]。
+    ///
+    /// # 参数
+    ///
+    /// * `frame` - 原始帧。
+    /// * `formats` - 允许的像素格式。
+    #[cfg(feature = "ndarray")]
+    fn frame_timestamp(&self, frame: &RawFrame, formats: &[AvPixel]) -> Result<Time> {
+        // 保留在 GPU 上的帧无法转换为 ndarray，只能通过 `decode_raw` 获取。
+        if ffi_hwaccel::frame_is_hardware(frame) || !formats.contains(&frame.format()) {
+            return Err(Error::InvalidFrameFormat);
+        }
+
+        // 我们在这里使用数据包 DTS（即 `frame->pkt_dts`），因为这就是编码器在为 `PTS` 字段编码时使用的。
+        // 这允许我们正确地同步音频和视频。
+        Ok(Time::new(Some(frame.packet().dts), self.decoder_time_base))
+    }
 }
 
 impl Drop for DecoderSplit {
@@ -767,3 +1945,27 @@ impl Drop for DecoderSplit {
 
 unsafe impl Send for DecoderSplit {}
 unsafe impl Sync for DecoderSplit {}
+
+#[cfg(test)]
+mod tests {
+    use ffmpeg::codec::packet::Packet as AvPacket;
+
+    use super::*;
+
+    #[test]
+    fn test_software_fallback_keeps_limited_packets() {
+        let mut software_fallback = SoftwareFallback {
+            packets: Vec::new(),
+        };
+        let packet = Packet::new(AvPacket::copy(&[0u8; 16]), AvRational::new(1, 90_000));
+        for _ in 0..SoftwareFallback::MAX_PACKETS {
+            assert!(software_fallback.push(&packet));
+        }
+        // 超过上限后不再保留数据包
+        assert!(!software_fallback.push(&packet));
+        assert_eq!(
+            software_fallback.packets.len(),
+            SoftwareFallback::MAX_PACKETS
+        );
+    }
+}
@@ -0,0 +1,1006 @@
+/// Codec carried in an RTP payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtpCodec {
+    /// H.264 payload as described in RFC 6184.
+    H264,
+    /// H.265 payload as described in RFC 7798.
+    H265,
+    /// VP8 payload as described in RFC 7741.
+    Vp8,
+    /// VP9 payload as described in RFC 9628. Spatially scalable streams are not supported.
+    Vp9,
+    /// AV1 payload as described in the RTP payload format for AV1 of the Alliance for Open Media.
+    Av1,
+    /// Opus payload as described in RFC 7587.
+    Opus,
+    /// AAC payload in the `MPEG4-GENERIC` format in AAC-hbr mode, as described in RFC 3640.
+    Aac,
+    /// AAC payload in the `MP4A-LATM` format as described in RFC 6416. Only streams of which the
+    /// configuration is signaled out of band (`cpresent=0`) are supported.
+    AacLatm,
+}
+
+impl RtpCodec {
+    /// Whether the codec is an audio codec.
+    pub fn is_audio(self) -> bool {
+        matches!(self, RtpCodec::Opus | RtpCodec::Aac | RtpCodec::AacLatm)
+    }
+}
+
+/// Fixed part of an RTP packet header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RtpHeader {
+    pub(crate) marker: bool,
+    pub(crate) payload_type: u8,
+    pub(crate) sequence_number: u16,
+    pub(crate) timestamp: u32,
+    pub(crate) ssrc: u32,
+}
+
+impl RtpHeader {
+    /// Parse an RTP packet into its header and payload. CSRCs, header extensions and padding are
+    /// skipped. Returns `None` if the packet is not a valid RTP packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Contents of the UDP datagram.
+    pub(crate) fn parse(buf: &[u8]) -> Option<(RtpHeader, &[u8])> {
+        if buf.len() < 12 || buf[0] >> 6 != 2 {
+            return None;
+        }
+        let header = RtpHeader {
+            marker: buf[1] & 0x80 != 0,
+            payload_type: buf[1] & 0x7f,
+            sequence_number: u16::from_be_bytes([buf[2], buf[3]]),
+            timestamp: u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            ssrc: u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]),
+        };
+        let mut start = 12 + 4 * (buf[0] & 0x0f) as usize;
+        if buf[0] & 0x10 != 0 {
+            let extension = buf.get(start..start + 4)?;
+            start += 4 + 4 * u16::from_be_bytes([extension[2], extension[3]]) as usize;
+        }
+        let mut end = buf.len();
+        if buf[0] & 0x20 != 0 {
+            end = end.checked_sub(buf[end - 1] as usize)?;
+        }
+        let payload = buf.get(start..end)?;
+        Some((header, payload))
+    }
+
+    /// Whether the packet is an RTCP packet that shares the port with RTP (RFC 5761). Such packets
+    /// look like RTP packets with a payload type of 72 to 76.
+    pub(crate) fn is_rtcp(&self) -> bool {
+        (72..=76).contains(&self.payload_type)
+    }
+
+    /// Write the header, without CSRCs or extensions.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer to append the header to.
+    pub(crate) fn write(&self, buf: &mut Vec<u8>) {
+        buf.push(0x80);
+        buf.push((self.marker as u8) << 7 | self.payload_type & 0x7f);
+        buf.extend_from_slice(&self.sequence_number.to_be_bytes());
+        buf.extend_from_slice(&self.timestamp.to_be_bytes());
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+    }
+}
+
+/// Access unit (one frame worth of data) reassembled from RTP packets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AccessUnit {
+    /// RTP timestamp of the access unit.
+    pub(crate) timestamp: u32,
+    /// For H.264 and H.265, NAL units in Annex B format, each prefixed with a start code. For VP8
+    /// and VP9, the compressed frame. For AV1, a temporal unit of OBUs with size fields, starting
+    /// with a temporal delimiter. For audio, a single compressed frame.
+    pub(crate) data: Vec<u8>,
+    /// Whether the access unit contains a random access point (IDR, CRA or BLA picture, VP8 or VP9
+    /// keyframe, or AV1 sequence header). Audio frames are always random access points.
+    pub(crate) is_key: bool,
+    /// Whether packets were lost around the access unit, so that it may be incomplete.
+    pub(crate) is_corrupt: bool,
+}
+
+/// Reassembles access units from RTP payloads. For H.264 and H.265, single NAL unit packets,
+/// aggregation packets (STAP-A, AP) and fragmentation units (FU-A, FU) are supported. Interleaved
+/// packetization mode is not supported. For AV1, OBUs that are fragmented over multiple packets
+/// are reassembled.
+///
+/// An access unit ends when a packet with the marker bit set arrives, or when the RTP timestamp
+/// changes. If packets are lost while a NAL unit or OBU is being reassembled from fragments, it is
+/// dropped. VP8 and VP9 frames of which the first packet was lost are dropped as well. Access units
+/// that may have lost packets are marked as corrupt.
+///
+/// Audio frames are returned as soon as they are complete. Every Opus payload is a frame. AAC-hbr
+/// payloads hold one or more frames or a fragment of one, and `MP4A-LATM` payloads hold a single
+/// frame or a fragment of one. Fragmented frames that lost packets are dropped.
+pub(crate) struct Depacketizer {
+    codec: RtpCodec,
+    // Access unit that is being assembled.
+    pending: Option<AccessUnit>,
+    // NAL unit, OBU or audio frame that is being reassembled from fragments.
+    fragment: Option<Vec<u8>>,
+    // Sequence number of the previous packet.
+    sequence_number: Option<u16>,
+    // Whether the previous packet ended an audio frame, so that the next one starts a frame.
+    at_frame_start: bool,
+}
+
+impl Depacketizer {
+    /// Start code that precedes every NAL unit in an Annex B stream.
+    const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+    /// Number of samples in an AAC frame.
+    const AAC_FRAME_SIZE: u32 = 1024;
+
+    /// Create a depacketizer for a codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - Codec of the RTP payload.
+    pub(crate) fn new(codec: RtpCodec) -> Self {
+        Self {
+            codec,
+            pending: None,
+            fragment: None,
+            sequence_number: None,
+            at_frame_start: true,
+        }
+    }
+
+    /// Process an RTP packet, and return the access units that were completed by it. Usually
+    /// this is zero or one, but it is two when the packet both starts and ends a new access unit
+    /// while the previous one was never ended with a marker bit. AAC-hbr payloads can hold more
+    /// frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - Header of the RTP packet.
+    /// * `payload` - Payload of the RTP packet.
+    pub(crate) fn push(&mut self, header: &RtpHeader, payload: &[u8]) -> Vec<AccessUnit> {
+        let mut completed = Vec::new();
+
+        let expected = self.sequence_number.map(|seq| seq.wrapping_add(1));
+        let lost = expected.is_some_and(|expected| expected != header.sequence_number);
+        if lost && self.fragment.take().is_some() {
+            tracing::warn!("dropping fragmented NAL unit, OBU or audio frame after packet loss");
+        }
+        self.sequence_number = Some(header.sequence_number);
+        match self.codec {
+            RtpCodec::Opus if !payload.is_empty() => {
+                return vec![Self::audio_frame(header.timestamp, payload.to_vec())]
+            }
+            RtpCodec::Opus => return Vec::new(),
+            RtpCodec::Aac => return self.push_aac(header, payload),
+            RtpCodec::AacLatm => return self.push_aac_latm(header, payload, lost),
+            _ => {}
+        }
+        // The lost packets belong to the pending access unit, the one this packet starts, or both.
+        if lost {
+            if let Some(pending) = self.pending.as_mut() {
+                pending.is_corrupt = true;
+            }
+        }
+
+        if self
+            .pending
+            .as_ref()
+            .is_some_and(|pending| pending.timestamp != header.timestamp)
+        {
+            self.fragment = None;
+            completed.extend(
+                self.pending
+                    .take()
+                    .filter(|access_unit| !access_unit.data.is_empty()),
+            );
+        }
+        let pending = self.pending.get_or_insert_with(|| AccessUnit {
+            timestamp: header.timestamp,
+            data: Vec::new(),
+            is_key: false,
+            is_corrupt: false,
+        });
+        pending.is_corrupt |= lost;
+
+        match self.codec {
+            RtpCodec::H264 => self.push_h264(payload),
+            RtpCodec::H265 => self.push_h265(payload),
+            RtpCodec::Vp8 => match Self::parse_vp8(payload) {
+                Some((start, is_key, data)) => self.push_frame_data(start, is_key, data),
+                None => tracing::warn!("truncated VP8 RTP payload descriptor"),
+            },
+            RtpCodec::Vp9 => match Self::parse_vp9(payload) {
+                Some((start, is_key, data)) => self.push_frame_data(start, is_key, data),
+                None => tracing::warn!("truncated VP9 RTP payload descriptor"),
+            },
+            RtpCodec::Av1 => self.push_av1(payload),
+            RtpCodec::Opus | RtpCodec::Aac | RtpCodec::AacLatm => {
+                unreachable!("audio frames are not assembled into access units")
+            }
+        }
+
+        if header.marker {
+            self.fragment = None;
+            completed.extend(
+                self.pending
+                    .take()
+                    .filter(|access_unit| !access_unit.data.is_empty()),
+            );
+        }
+        completed
+    }
+
+    /// Process an H.264 payload (RFC 6184).
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - Payload of the RTP packet.
+    fn push_h264(&mut self, payload: &[u8]) {
+        let Some(&indicator) = payload.first() else {
+            return;
+        };
+        match indicator & 0x1f {
+            1..=23 => self.push_nal_unit(payload),
+            // STAP-A
+            24 => self.push_aggregate(&payload[1..]),
+            // FU-A
+            28 => {
+                let Some(&fu_header) = payload.get(1) else {
+                    return;
+                };
+                let nal_header = [(indicator & 0xe0) | (fu_header & 0x1f)];
+                self.push_fragment(fu_header, &nal_header, &payload[2..]);
+            }
+            nal_type => tracing::warn!("unsupported H.264 RTP packet type: {nal_type}"),
+        }
+    }
+
+    /// Process an H.265 payload (RFC 7798).
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - Payload of the RTP packet.
+    fn push_h265(&mut self, payload: &[u8]) {
+        if payload.len() < 2 {
+            return;
+        }
+        match (payload[0] >> 1) & 0x3f {
+            0..=47 => self.push_nal_unit(payload),
+            // AP
+            48 => self.push_aggregate(&payload[2..]),
+            // FU
+            49 => {
+                let Some(&fu_header) = payload.get(2) else {
+                    return;
+                };
+                let nal_header = [(payload[0] & 0x81) | ((fu_header & 0x3f) << 1), payload[1]];
+                self.push_fragment(fu_header, &nal_header, &payload[3..]);
+            }
+            nal_type => tracing::warn!("unsupported H.265 RTP packet type: {nal_type}"),
+        }
+    }
+
+    /// Create an access unit of an audio frame, which is always a random access point.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - RTP timestamp of the frame.
+    /// * `data` - Compressed frame.
+    fn audio_frame(timestamp: u32, data: Vec<u8>) -> AccessUnit {
+        AccessUnit {
+            timestamp,
+            data,
+            is_key: true,
+            is_corrupt: false,
+        }
+    }
+
+    /// Process an AAC-hbr payload (RFC 3640), which starts with a 16-bit AU header for every frame
+    /// in the payload: a 13-bit size and a 3-bit index. A frame that is fragmented over multiple
+    /// packets is the only frame in each of them, and its last packet has the marker bit set.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - Header of the RTP packet.
+    /// * `payload` - Payload of the RTP packet.
+    fn push_aac(&mut self, header: &RtpHeader, payload: &[u8]) -> Vec<AccessUnit> {
+        let Some(&[high, low]) = payload.get(..2) else {
+            return Vec::new();
+        };
+        let headers_length = u16::from_be_bytes([high, low]) as usize;
+        let headers_end = 2 + headers_length.div_ceil(8);
+        let Some(au_headers) = payload.get(2..headers_end) else {
+            tracing::warn!("truncated AU headers in AAC RTP payload");
+            return Vec::new();
+        };
+        let mut data = &payload[headers_end..];
+        let sizes = au_headers
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .take(headers_length / 16)
+            .map(|&au_header| (u16::from_be_bytes(au_header) >> 3) as usize)
+            .collect::<Vec<_>>();
+
+        if let [size] = sizes[..] {
+            if size > data.len() || self.fragment.is_some() {
+                self.fragment
+                    .get_or_insert_with(Vec::new)
+                    .extend_from_slice(data);
+                if !header.marker {
+                    return Vec::new();
+                }
+                let frame = self.fragment.take().unwrap_or_default();
+                if frame.len() != size {
+                    tracing::warn!("dropping incomplete fragmented AAC frame");
+                    return Vec::new();
+                }
+                return vec![Self::audio_frame(header.timestamp, frame)];
+            }
+        }
+
+        let mut frames = Vec::with_capacity(sizes.len());
+        for (index, size) in sizes.into_iter().enumerate() {
+            let Some(frame) = data.get(..size) else {
+                tracing::warn!("truncated frame in AAC RTP payload");
+                break;
+            };
+            data = &data[size..];
+            let timestamp = header
+                .timestamp
+                .wrapping_add(index as u32 * Self::AAC_FRAME_SIZE);
+            frames.push(Self::audio_frame(timestamp, frame.to_vec()));
+        }
+        frames
+    }
+
+    /// Process an `MP4A-LATM` payload (RFC 6416). The payloads of packets up to and including the
+    /// one with the marker bit set form an audio mux element, which consists of the length of the
+    /// frame, coded as a sequence of bytes that are added up until one is less than 255, followed
+    /// by the frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - Header of the RTP packet.
+    /// * `payload` - Payload of the RTP packet.
+    /// * `lost` - Whether packets were lost before this one.
+    fn push_aac_latm(&mut self, header: &RtpHeader, payload: &[u8], lost: bool) -> Vec<AccessUnit> {
+        // Without the start of the element, the length of the frame is unknown.
+        if std::mem::replace(&mut self.at_frame_start, header.marker) && !lost {
+            self.fragment = Some(Vec::new());
+        }
+        let Some(fragment) = self.fragment.as_mut() else {
+            return Vec::new();
+        };
+        fragment.extend_from_slice(payload);
+        if !header.marker {
+            return Vec::new();
+        }
+
+        let element = self.fragment.take().unwrap_or_default();
+        let mut size = 0;
+        let mut offset = 0;
+        loop {
+            let Some(&length) = element.get(offset) else {
+                tracing::warn!("truncated AAC LATM payload length");
+                return Vec::new();
+            };
+            offset += 1;
+            size += length as usize;
+            if length != 0xff {
+                break;
+            }
+        }
+        match element.get(offset..offset + size) {
+            Some(frame) => vec![Self::audio_frame(header.timestamp, frame.to_vec())],
+            None => {
+                tracing::warn!("truncated frame in AAC LATM payload");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Parse the payload descriptor of a VP8 payload (RFC 7741).
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - Payload of the RTP packet.
+    ///
+    /// # Return value
+    ///
+    /// Whether the packet starts a frame, whether that frame is a keyframe, and the part of the
+    /// frame in the packet. `None` if the descriptor is truncated.
+    fn parse_vp8(payload: &[u8]) -> Option<(bool, bool, &[u8])> {
+        let descriptor = *payload.first()?;
+        let mut offset = 1;
+        if descriptor & 0x80 != 0 {
+            let extension = *payload.get(1)?;
+            offset += 1;
+            // Picture ID, which is 15 bits long if its first bit is set.
+            if extension & 0x80 != 0 {
+                offset += if *payload.get(offset)? & 0x80 != 0 {
+                    2
+                } else {
+                    1
+                };
+            }
+            // TL0PICIDX
+            if extension & 0x40 != 0 {
+                offset += 1;
+            }
+            // TID and KEYIDX
+            if extension & 0x30 != 0 {
+                offset += 1;
+            }
+        }
+        let data = payload.get(offset..)?;
+        // Start of partition 0, which begins with the frame header.
+        let start = descriptor & 0x10 != 0 && descriptor & 0x07 == 0;
+        let is_key = start && data.first().is_some_and(|header| header & 0x01 == 0);
+        Some((start, is_key, data))
+    }
+
+    /// Parse the payload descriptor of a VP9 payload (RFC 9628).
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - Payload of the RTP packet.
+    ///
+    /// # Return value
+    ///
+    /// Whether the packet starts a frame, whether that frame is a keyframe, and the part of the
+    /// frame in the packet. `None` if the descriptor is truncated.
+    fn parse_vp9(payload: &[u8]) -> Option<(bool, bool, &[u8])> {
+        let descriptor = *payload.first()?;
+        let inter_picture_predicted = descriptor & 0x40 != 0;
+        let flexible_mode = descriptor & 0x10 != 0;
+        let start = descriptor & 0x08 != 0;
+        let mut offset = 1;
+        // Picture ID, which is 15 bits long if its first bit is set.
+        if descriptor & 0x80 != 0 {
+            offset += if *payload.get(offset)? & 0x80 != 0 {
+                2
+            } else {
+                1
+            };
+        }
+        // Layer indices, followed by TL0PICIDX in non-flexible mode.
+        if descriptor & 0x20 != 0 {
+            offset += if flexible_mode { 1 } else { 2 };
+        }
+        // Reference indices, of which there are at most three.
+        if flexible_mode && inter_picture_predicted {
+            for _ in 0..3 {
+                let reference = *payload.get(offset)?;
+                offset += 1;
+                if reference & 0x01 == 0 {
+                    break;
+                }
+            }
+        }
+        // Scalability structure.
+        if descriptor & 0x02 != 0 {
+            let structure = *payload.get(offset)?;
+            offset += 1;
+            if structure & 0x10 != 0 {
+                let spatial_layers = (structure >> 5) as usize + 1;
+                offset += 4 * spatial_layers;
+            }
+            if structure & 0x08 != 0 {
+                let pictures = *payload.get(offset)?;
+                offset += 1;
+                for _ in 0..pictures {
+                    let picture = *payload.get(offset)?;
+                    offset += 1 + ((picture >> 2) & 0x03) as usize;
+                }
+            }
+        }
+        let data = payload.get(offset..)?;
+        Some((start, start && !inter_picture_predicted, data))
+    }
+
+    /// Append part of a VP8 or VP9 frame to the pending access unit.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Whether the data is the start of the frame.
+    /// * `is_key` - Whether the frame is a keyframe.
+    /// * `data` - Part of the frame.
+    fn push_frame_data(&mut self, start: bool, is_key: bool, data: &[u8]) {
+        let Some(pending) = self.pending.as_mut() else {
+            return;
+        };
+        // Without its start, the frame cannot be decoded.
+        if pending.data.is_empty() && !start {
+            pending.is_corrupt = true;
+            return;
+        }
+        pending.data.extend_from_slice(data);
+        pending.is_key |= is_key;
+    }
+
+    /// Process an AV1 payload, which consists of an aggregation header followed by OBU elements
+    /// that are each prefixed with their size, except for the last one if the number of elements
+    /// is given in the aggregation header.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - Payload of the RTP packet.
+    fn push_av1(&mut self, payload: &[u8]) {
+        let Some((&aggregation_header, mut elements)) = payload.split_first() else {
+            return;
+        };
+        let continues_fragment = aggregation_header & 0x80 != 0;
+        let ends_with_fragment = aggregation_header & 0x40 != 0;
+        let count = ((aggregation_header >> 4) & 0x03) as usize;
+        // First packet of a coded video sequence.
+        if aggregation_header & 0x08 != 0 {
+            if let Some(pending) = self.pending.as_mut() {
+                pending.is_key = true;
+            }
+        }
+
+        let mut index = 0;
+        while !elements.is_empty() {
+            index += 1;
+            let element = if index == count {
+                std::mem::take(&mut elements)
+            } else {
+                let Some((size, length)) = read_leb128(elements) else {
+                    tracing::warn!("truncated OBU element in AV1 RTP payload");
+                    return;
+                };
+                let Some(element) = elements.get(length..length + size) else {
+                    tracing::warn!("truncated OBU element in AV1 RTP payload");
+                    return;
+                };
+                elements = &elements[length + size..];
+                element
+            };
+            if index == 1 && continues_fragment {
+                // Without the first fragment, the OBU cannot be reassembled.
+                if let Some(fragment) = self.fragment.as_mut() {
+                    fragment.extend_from_slice(element);
+                }
+            } else {
+                self.fragment = Some(element.to_vec());
+            }
+            if !(elements.is_empty() && ends_with_fragment) {
+                if let Some(obu) = self.fragment.take() {
+                    self.push_obu(&obu);
+                }
+            }
+        }
+    }
+
+    /// Append a complete OBU to the pending access unit. OBUs are stored with a size field, as
+    /// decoders expect, and the temporal unit starts with a temporal delimiter, which RTP senders
+    /// leave out.
+    ///
+    /// # Arguments
+    ///
+    /// * `obu` - OBU including its header.
+    fn push_obu(&mut self, obu: &[u8]) {
+        /// Temporal delimiter OBU with an empty payload.
+        const TEMPORAL_DELIMITER: [u8; 2] = [0x12, 0x00];
+
+        let Some(&header) = obu.first() else {
+            return;
+        };
+        let header_size = if header & 0x04 != 0 { 2 } else { 1 };
+        let Some(obu_header) = obu.get(..header_size) else {
+            return;
+        };
+        let obu_type = (header >> 3) & 0x0f;
+        // Temporal delimiters and tile lists are not to be transmitted.
+        if obu_type == 2 || obu_type == 8 {
+            return;
+        }
+        let Some(pending) = self.pending.as_mut() else {
+            return;
+        };
+        if pending.data.is_empty() {
+            pending.data.extend_from_slice(&TEMPORAL_DELIMITER);
+        }
+        // Sequence header
+        pending.is_key |= obu_type == 1;
+        if header & 0x02 != 0 {
+            pending.data.extend_from_slice(obu);
+        } else {
+            pending.data.push(header | 0x02);
+            pending.data.extend_from_slice(&obu_header[1..]);
+            write_leb128(&mut pending.data, obu.len() - header_size);
+            pending.data.extend_from_slice(&obu[header_size..]);
+        }
+    }
+
+    /// Process the NAL units of an aggregation packet, which are each prefixed with their size.
+    ///
+    /// # Arguments
+    ///
+    /// * `units` - Aggregated NAL units, without the aggregation packet header.
+    fn push_aggregate(&mut self, mut units: &[u8]) {
+        while units.len() >= 2 {
+            let size = u16::from_be_bytes([units[0], units[1]]) as usize;
+            let Some(nal_unit) = units.get(2..2 + size) else {
+                tracing::warn!("truncated NAL unit in RTP aggregation packet");
+                return;
+            };
+            self.push_nal_unit(nal_unit);
+            units = &units[2 + size..];
+        }
+    }
+
+    /// Process a fragmentation unit.
+    ///
+    /// # Arguments
+    ///
+    /// * `fu_header` - Fragmentation unit header with the start and end bits.
+    /// * `nal_header` - Header of the fragmented NAL unit.
+    /// * `data` - Fragment of the NAL unit payload.
+    fn push_fragment(&mut self, fu_header: u8, nal_header: &[u8], data: &[u8]) {
+        let start = fu_header & 0x80 != 0;
+        let end = fu_header & 0x40 != 0;
+        if start {
+            self.fragment = Some(nal_header.to_vec());
+        }
+        // Without the first fragment, the NAL unit cannot be reassembled.
+        let Some(fragment) = self.fragment.as_mut() else {
+            return;
+        };
+        fragment.extend_from_slice(data);
+        if end {
+            if let Some(nal_unit) = self.fragment.take() {
+                self.push_nal_unit(&nal_unit);
+            }
+        }
+    }
+
+    /// Append a complete NAL unit to the pending access unit.
+    ///
+    /// # Arguments
+    ///
+    /// * `nal_unit` - NAL unit including its header.
+    fn push_nal_unit(&mut self, nal_unit: &[u8]) {
+        let Some(&first) = nal_unit.first() else {
+            return;
+        };
+        let is_key = match self.codec {
+            RtpCodec::H264 => first & 0x1f == 5,
+            RtpCodec::H265 => (16..=21).contains(&((first >> 1) & 0x3f)),
+            _ => false,
+        };
+        if let Some(pending) = self.pending.as_mut() {
+            pending.data.extend_from_slice(&Self::START_CODE);
+            pending.data.extend_from_slice(nal_unit);
+            pending.is_key |= is_key;
+        }
+    }
+}
+
+/// Read an unsigned LEB128 number, as used for sizes in AV1.
+///
+/// # Arguments
+///
+/// * `buf` - Buffer that starts with the number.
+///
+/// # Return value
+///
+/// The number and the number of bytes it takes up, or `None` if it is truncated or too large.
+fn read_leb128(buf: &[u8]) -> Option<(usize, usize)> {
+    // Eight bytes hold up to 56 bits, which may not fit in `usize` on 32-bit targets.
+    let mut value = 0u64;
+    for (index, &byte) in buf.iter().take(8).enumerate() {
+        value |= u64::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Some((usize::try_from(value).ok()?, index + 1));
+        }
+    }
+    None
+}
+
+/// Write an unsigned LEB128 number, as used for sizes in AV1.
+///
+/// # Arguments
+///
+/// * `buf` - Buffer to append to.
+/// * `value` - Number to write.
+fn write_leb128(buf: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(sequence_number: u16, timestamp: u32, marker: bool) -> RtpHeader {
+        RtpHeader {
+            marker,
+            payload_type: 96,
+            sequence_number,
+            timestamp,
+            ssrc: 1,
+        }
+    }
+
+    #[test]
+    fn test_parse_header_skips_csrcs_extension_and_padding() {
+        let mut packet = vec![0xb1, 0xe0, 0x00, 0x07, 0, 0, 0x0b, 0xb8, 0, 0, 0, 9];
+        packet.extend_from_slice(&[0xaa; 4]); // CSRC
+        packet.extend_from_slice(&[0xbe, 0xde, 0x00, 0x01, 1, 2, 3, 4]); // Extension
+        packet.extend_from_slice(&[0x65, 0x88]); // Payload
+        packet.extend_from_slice(&[0, 0, 3]); // Padding
+        let (header, payload) = RtpHeader::parse(&packet).unwrap();
+        assert!(header.marker);
+        assert_eq!(header.payload_type, 96);
+        assert_eq!(header.sequence_number, 7);
+        assert_eq!(header.timestamp, 3000);
+        assert_eq!(header.ssrc, 9);
+        assert_eq!(payload, &[0x65, 0x88]);
+        assert!(RtpHeader::parse(&packet[..10]).is_none());
+    }
+
+    #[test]
+    fn test_write_header() {
+        let mut packet = Vec::new();
+        header(7, 3000, true).write(&mut packet);
+        packet.extend_from_slice(&[0x65, 0x88]);
+        assert_eq!(
+            RtpHeader::parse(&packet),
+            Some((header(7, 3000, true), &[0x65, 0x88][..]))
+        );
+    }
+
+    #[test]
+    fn test_h264_stap_a_and_fu_a() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::H264);
+        let stap_a = [0x18, 0x00, 0x02, 0x67, 0x42, 0x00, 0x02, 0x68, 0xce];
+        assert!(depacketizer.push(&header(1, 0, false), &stap_a).is_empty());
+        assert!(depacketizer
+            .push(&header(2, 0, false), &[0x7c, 0x85, 0x01, 0x02])
+            .is_empty());
+        let completed = depacketizer.push(&header(3, 0, true), &[0x7c, 0x45, 0x03]);
+        assert_eq!(
+            completed,
+            vec![AccessUnit {
+                timestamp: 0,
+                data: vec![
+                    0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x68, 0xce, 0, 0, 0, 1, 0x65, 0x01, 0x02,
+                    0x03
+                ],
+                is_key: true,
+                is_corrupt: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_h264_fragment_dropped_after_loss() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::H264);
+        depacketizer.push(&header(1, 0, false), &[0x7c, 0x81, 0x01]);
+        assert!(depacketizer
+            .push(&header(3, 0, true), &[0x7c, 0x41, 0x03])
+            .is_empty());
+        let completed = depacketizer.push(&header(4, 3000, true), &[0x41, 0x9a]);
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].data, vec![0, 0, 0, 1, 0x41, 0x9a]);
+        assert!(!completed[0].is_key);
+        assert!(!completed[0].is_corrupt);
+
+        depacketizer.push(&header(5, 6000, false), &[0x41, 0x01]);
+        let completed = depacketizer.push(&header(7, 6000, true), &[0x41, 0x03]);
+        assert_eq!(completed.len(), 1);
+        assert!(completed[0].is_corrupt);
+    }
+
+    #[test]
+    fn test_access_unit_ends_on_timestamp_change() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::H264);
+        assert!(depacketizer
+            .push(&header(1, 0, false), &[0x41, 0x01])
+            .is_empty());
+        let completed = depacketizer.push(&header(2, 3000, true), &[0x41, 0x02]);
+        assert_eq!(completed.len(), 2);
+        assert_eq!(completed[0].timestamp, 0);
+        assert_eq!(completed[1].timestamp, 3000);
+    }
+
+    #[test]
+    fn test_timestamp_change_skips_empty_access_unit() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::H264);
+        // The start fragment of the FU-A was lost, so nothing of this access unit is kept.
+        assert!(depacketizer
+            .push(&header(2, 0, false), &[0x7c, 0x05, 0x02])
+            .is_empty());
+        let completed = depacketizer.push(&header(3, 3000, true), &[0x41, 0x9a]);
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].timestamp, 3000);
+        assert_eq!(completed[0].data, vec![0, 0, 0, 1, 0x41, 0x9a]);
+    }
+
+    #[test]
+    fn test_h265_ap_and_fu() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::H265);
+        let ap = [
+            0x60, 0x01, 0x00, 0x03, 0x40, 0x01, 0x0c, 0x00, 0x02, 0x42, 0x01,
+        ];
+        depacketizer.push(&header(1, 0, false), &ap);
+        depacketizer.push(&header(2, 0, false), &[0x62, 0x01, 0x93, 0xaa]);
+        let completed = depacketizer.push(&header(3, 0, true), &[0x62, 0x01, 0x53, 0xbb]);
+        assert_eq!(
+            completed,
+            vec![AccessUnit {
+                timestamp: 0,
+                data: vec![
+                    0, 0, 0, 1, 0x40, 0x01, 0x0c, 0, 0, 0, 1, 0x42, 0x01, 0, 0, 0, 1, 0x26, 0x01,
+                    0xaa, 0xbb
+                ],
+                is_key: true,
+                is_corrupt: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_vp8_frame_and_missing_start() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::Vp8);
+        // Extended descriptor with a 15-bit picture ID.
+        depacketizer.push(&header(1, 0, false), &[0x90, 0x80, 0x81, 0x23, 0x10, 0x02]);
+        let completed = depacketizer.push(&header(2, 0, true), &[0x80, 0x80, 0x81, 0x23, 0x03]);
+        assert_eq!(
+            completed,
+            vec![AccessUnit {
+                timestamp: 0,
+                data: vec![0x10, 0x02, 0x03],
+                is_key: true,
+                is_corrupt: false,
+            }]
+        );
+
+        assert!(depacketizer
+            .push(&header(4, 3000, true), &[0x00, 0x03])
+            .is_empty());
+        let completed = depacketizer.push(&header(5, 6000, true), &[0x10, 0x01, 0x02]);
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].data, vec![0x01, 0x02]);
+        assert!(!completed[0].is_key);
+    }
+
+    #[test]
+    fn test_vp9_descriptor() {
+        // Picture ID, layer indices with TL0PICIDX, and a scalability structure with resolution.
+        let payload = [0xaa, 0x05, 0x00, 0x01, 0x10, 0x02, 0x80, 0x01, 0x68, 0x42];
+        assert_eq!(
+            Depacketizer::parse_vp9(&payload),
+            Some((true, true, &[0x42][..]))
+        );
+        // Flexible mode with two reference indices.
+        let payload = [0xd4, 0x05, 0x03, 0x02, 0x42];
+        assert_eq!(
+            Depacketizer::parse_vp9(&payload),
+            Some((false, false, &[0x42][..]))
+        );
+        assert_eq!(Depacketizer::parse_vp9(&[0xaa, 0x05]), None);
+    }
+
+    #[test]
+    fn test_av1_fragmented_obu() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::Av1);
+        // Sequence header and the start of a frame OBU, both without size fields.
+        depacketizer.push(
+            &header(1, 0, false),
+            &[0x68, 0x02, 0x08, 0x01, 0x30, 0x01, 0x02],
+        );
+        let completed = depacketizer.push(&header(2, 0, true), &[0x90, 0x03]);
+        assert_eq!(
+            completed,
+            vec![AccessUnit {
+                timestamp: 0,
+                data: vec![0x12, 0x00, 0x0a, 0x01, 0x01, 0x32, 0x03, 0x01, 0x02, 0x03],
+                is_key: true,
+                is_corrupt: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_opus_frames() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::Opus);
+        let completed = depacketizer.push(&header(1, 0, true), &[0xfc, 0x01]);
+        assert_eq!(
+            completed,
+            vec![Depacketizer::audio_frame(0, vec![0xfc, 0x01])]
+        );
+        let completed = depacketizer.push(&header(3, 1920, false), &[0xfc, 0x02]);
+        assert_eq!(
+            completed,
+            vec![Depacketizer::audio_frame(1920, vec![0xfc, 0x02])]
+        );
+    }
+
+    #[test]
+    fn test_aac_hbr_multiple_and_fragmented_frames() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::Aac);
+        // Two AU headers with sizes 2 and 1.
+        let payload = [0x00, 0x20, 0x00, 0x10, 0x00, 0x08, 0x01, 0x02, 0x03];
+        let completed = depacketizer.push(&header(1, 0, true), &payload);
+        assert_eq!(
+            completed,
+            vec![
+                Depacketizer::audio_frame(0, vec![0x01, 0x02]),
+                Depacketizer::audio_frame(1024, vec![0x03]),
+            ]
+        );
+
+        // A frame of size 3 in two packets.
+        let payload = [0x00, 0x10, 0x00, 0x18, 0x04, 0x05];
+        assert!(depacketizer
+            .push(&header(2, 2048, false), &payload)
+            .is_empty());
+        let payload = [0x00, 0x10, 0x00, 0x18, 0x06];
+        let completed = depacketizer.push(&header(3, 2048, true), &payload);
+        assert_eq!(
+            completed,
+            vec![Depacketizer::audio_frame(2048, vec![0x04, 0x05, 0x06])]
+        );
+
+        // The same frame with the first packet lost.
+        let payload = [0x00, 0x10, 0x00, 0x18, 0x06];
+        assert!(depacketizer
+            .push(&header(5, 3072, true), &payload)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_aac_latm_fragmented_frame_and_loss() {
+        let mut depacketizer = Depacketizer::new(RtpCodec::AacLatm);
+        let mut element = vec![0xff, 0x01];
+        element.extend_from_slice(&[0xaa; 256]);
+        assert!(depacketizer
+            .push(&header(1, 0, false), &element[..100])
+            .is_empty());
+        let completed = depacketizer.push(&header(2, 0, true), &element[100..]);
+        assert_eq!(
+            completed,
+            vec![Depacketizer::audio_frame(0, vec![0xaa; 256])]
+        );
+
+        // After the first packet of an element is lost, the element is dropped.
+        assert!(depacketizer
+            .push(&header(4, 1024, true), &element[100..])
+            .is_empty());
+        let completed = depacketizer.push(&header(5, 2048, true), &[0x02, 0x01, 0x02]);
+        assert_eq!(
+            completed,
+            vec![Depacketizer::audio_frame(2048, vec![0x01, 0x02])]
+        );
+    }
+
+    #[test]
+    fn test_leb128() {
+        let mut buf = Vec::new();
+        write_leb128(&mut buf, 300);
+        assert_eq!(buf, vec![0xac, 0x02]);
+        assert_eq!(read_leb128(&[0xac, 0x02, 0xff]), Some((300, 2)));
+        assert_eq!(read_leb128(&[0x80]), None);
+        // The largest number that fits in eight bytes, if it fits in `usize`.
+        let largest = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        assert_eq!(
+            read_leb128(&largest),
+            usize::try_from((1u64 << 56) - 1)
+                .ok()
+                .map(|value| (value, 8)),
+        );
+    }
+}
@@ -0,0 +1,301 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::util::mathematics::rescale::Rescale;
+use ffmpeg::Rational as AvRational;
+
+use crate::core::packet::Packet;
+use crate::core::time::Time;
+
+/// Describes what should happen when a timestamp discontinuity is detected in a stream.
+///
+/// Live sources such as MPEG-TS and RTP frequently contain PTS jumps (for example when an encoder
+/// restarts) and wraparounds (the 33-bit MPEG-TS clock wraps roughly every 26.5 hours).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DiscontinuityPolicy {
+    /// Leave timestamps untouched. Discontinuities are only reported.
+    #[default]
+    PassThrough,
+    /// Rewrite timestamps after the discontinuity so that they continue where the stream left off.
+    Resync,
+    /// Drop all packets after the discontinuity until the next keyframe arrives, and continue from
+    /// there with the new timestamps.
+    DropUntilKeyframe,
+}
+
+/// Detects timestamp discontinuities in the packets of a single stream and applies a
+/// [`DiscontinuityPolicy`] to them.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut detector = DiscontinuityDetector::new(DiscontinuityPolicy::Resync);
+/// while let Ok(packet) = reader.read(stream_index) {
+///     if let Some(packet) = detector.process(packet) {
+///         // Timestamps of `packet` are now continuous.
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiscontinuityDetector {
+    policy: DiscontinuityPolicy,
+    threshold: Time,
+    // Timestamp and duration of the previous packet, and their time base.
+    last: Option<(i64, i64, AvRational)>,
+    offset: i64,
+    dropping: bool,
+    count: u64,
+}
+
+impl DiscontinuityDetector {
+    /// Default maximum forward gap between two consecutive packets before it is considered a
+    /// discontinuity, in seconds.
+    const DEFAULT_THRESHOLD_SECS: f64 = 3.0;
+
+    /// Create a new detector with the given policy and the default threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - What to do when a discontinuity is detected.
+    pub fn new(policy: DiscontinuityPolicy) -> Self {
+        Self {
+            policy,
+            threshold: Time::from_secs_f64(Self::DEFAULT_THRESHOLD_SECS),
+            last: None,
+            offset: 0,
+            dropping: false,
+            count: 0,
+        }
+    }
+
+    /// Set the maximum forward gap between two consecutive packets. Larger gaps, and any backward
+    /// jump, are treated as a discontinuity.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Maximum allowed forward gap.
+    pub fn with_threshold(mut self, threshold: Time) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Get the policy of this detector.
+    #[inline]
+    pub fn policy(&self) -> DiscontinuityPolicy {
+        self.policy
+    }
+
+    /// Number of discontinuities detected so far.
+    #[inline]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Forget about previous packets. Call this after seeking, since a seek is an intentional
+    /// discontinuity.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.offset = 0;
+        self.dropping = false;
+    }
+
+    /// Process a single packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - Packet to process.
+    ///
+    /// # Return value
+    ///
+    /// The packet (with rewritten timestamps if the policy is [`DiscontinuityPolicy::Resync`]), or
+    /// `None` if the packet must be dropped.
+    pub fn process(&mut self, mut packet: Packet) -> Option<Packet> {
+        let (timestamp, time_base) = match packet.dts().into_parts() {
+            (Some(dts), time_base) => (dts, time_base),
+            (None, _) => match packet.pts().into_parts() {
+                (Some(pts), time_base) => (pts, time_base),
+                // Packets without any timestamp cannot be checked.
+                (None, _) => return self.filter_untimed(packet),
+            },
+        };
+
+        let (duration, _) = packet.duration().into_parts();
+        let duration = duration.unwrap_or(0).max(0);
+
+        if let Some((last, last_duration, last_time_base)) = self.last {
+            let last = last.rescale(last_time_base, time_base);
+            let last_duration = last_duration.rescale(last_time_base, time_base);
+            let threshold = self
+                .threshold
+                .aligned_with_rational(time_base)
+                .into_value()
+                .unwrap_or(0);
+            let expected = last + last_duration;
+            let timestamp_adjusted = timestamp + self.offset;
+            let delta = timestamp_adjusted - last;
+
+            if is_discontinuity(delta, threshold) {
+                self.count += 1;
+                tracing::warn!(
+                    "timestamp discontinuity detected (jump of {}), policy: {:?}",
+                    Time::new(Some(delta), time_base),
+                    self.policy,
+                );
+                match self.policy {
+                    DiscontinuityPolicy::PassThrough => {}
+                    DiscontinuityPolicy::Resync => {
+                        self.offset = expected - timestamp;
+                    }
+                    DiscontinuityPolicy::DropUntilKeyframe => {
+                        self.dropping = true;
+                    }
+                }
+            }
+        }
+
+        // Dropped packets are remembered as well, so that the jump is only detected once.
+        self.last = Some((timestamp + self.offset, duration, time_base));
+
+        if self.dropping {
+            if !packet.is_key() {
+                return None;
+            }
+            self.dropping = false;
+        }
+
+        if self.offset != 0 {
+            shift(&mut packet, self.offset, time_base);
+        }
+
+        Some(packet)
+    }
+
+    /// Handle packets that carry no timestamp at all.
+    fn filter_untimed(&mut self, packet: Packet) -> Option<Packet> {
+        if self.dropping && !packet.is_key() {
+            None
+        } else {
+            self.dropping = false;
+            Some(packet)
+        }
+    }
+}
+
+impl Default for DiscontinuityDetector {
+    fn default() -> Self {
+        Self::new(DiscontinuityPolicy::default())
+    }
+}
+
+/// Whether or not the difference between two consecutive timestamps constitutes a discontinuity.
+///
+/// # Arguments
+///
+/// * `delta` - Difference between the current and the previous timestamp.
+/// * `threshold` - Maximum allowed forward gap, in the same time base as `delta`.
+fn is_discontinuity(delta: i64, threshold: i64) -> bool {
+    delta < 0 || delta > threshold
+}
+
+/// Shift both PTS and DTS of a packet by `offset`.
+fn shift(packet: &mut Packet, offset: i64, time_base: AvRational) {
+    if let Some(pts) = packet.pts().into_value() {
+        packet.set_pts(Time::new(Some(pts + offset), time_base));
+    }
+    if let Some(dts) = packet.dts().into_value() {
+        packet.set_dts(Time::new(Some(dts + offset), time_base));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ffmpeg::codec::packet::{Flags as AvPacketFlags, Packet as AvPacket};
+
+    use super::*;
+
+    fn packet(timestamp: i64, is_key: bool) -> Packet {
+        let mut packet = AvPacket::empty();
+        packet.set_pts(Some(timestamp));
+        packet.set_dts(Some(timestamp));
+        packet.set_duration(3_000);
+        if is_key {
+            packet.set_flags(AvPacketFlags::KEY);
+        }
+        Packet::new(packet, AvRational::new(1, 90_000))
+    }
+
+    fn timestamps(packet: Option<Packet>) -> Option<(i64, i64)> {
+        let packet = packet?;
+        Some((packet.pts().into_value()?, packet.dts().into_value()?))
+    }
+
+    #[test]
+    fn test_is_discontinuity_forward_gap() {
+        assert!(!is_discontinuity(0, 90_000));
+        assert!(!is_discontinuity(3_000, 90_000));
+        assert!(!is_discontinuity(90_000, 90_000));
+        assert!(is_discontinuity(90_001, 90_000));
+    }
+
+    #[test]
+    fn test_is_discontinuity_backward_jump() {
+        assert!(is_discontinuity(-1, 90_000));
+        // A 33-bit MPEG-TS wraparound shows up as a large backward jump.
+        assert!(is_discontinuity(-(1 << 33) + 3_000, 90_000));
+    }
+
+    #[test]
+    fn test_default_policy_is_pass_through() {
+        let detector = DiscontinuityDetector::default();
+        assert_eq!(detector.policy(), DiscontinuityPolicy::PassThrough);
+        assert_eq!(detector.count(), 0);
+    }
+
+    #[test]
+    fn test_pass_through_reports_jump() {
+        let mut detector = DiscontinuityDetector::new(DiscontinuityPolicy::PassThrough);
+        assert!(detector.process(packet(0, true)).is_some());
+        assert!(detector.process(packet(3_000, false)).is_some());
+        assert_eq!(detector.count(), 0);
+        let jumped = detector.process(packet(900_000, false));
+        assert_eq!(timestamps(jumped), Some((900_000, 900_000)));
+        assert_eq!(detector.count(), 1);
+        assert!(detector.process(packet(903_000, false)).is_some());
+        assert_eq!(detector.count(), 1);
+    }
+
+    #[test]
+    fn test_resync_continues_after_previous_packet() {
+        let mut detector = DiscontinuityDetector::new(DiscontinuityPolicy::Resync);
+        detector.process(packet(0, true));
+        detector.process(packet(3_000, false));
+        // The stream restarts at zero: the packet continues one duration after the previous one.
+        assert_eq!(
+            timestamps(detector.process(packet(0, true))),
+            Some((6_000, 6_000))
+        );
+        assert_eq!(
+            timestamps(detector.process(packet(3_000, false))),
+            Some((9_000, 9_000))
+        );
+        assert_eq!(detector.count(), 1);
+        detector.reset();
+        assert_eq!(
+            timestamps(detector.process(packet(3_000, false))),
+            Some((3_000, 3_000))
+        );
+    }
+
+    #[test]
+    fn test_drop_until_keyframe() {
+        let mut detector = DiscontinuityDetector::new(DiscontinuityPolicy::DropUntilKeyframe);
+        assert!(detector.process(packet(0, true)).is_some());
+        assert!(detector.process(packet(900_000, false)).is_none());
+        assert!(detector.process(packet(903_000, false)).is_none());
+        assert_eq!(
+            timestamps(detector.process(packet(906_000, true))),
+            Some((906_000, 906_000))
+        );
+        assert!(detector.process(packet(909_000, false)).is_some());
+        assert_eq!(detector.count(), 1);
+    }
+}
@@ -5,7 +5,7 @@ use ffmpeg::codec::encoder::video::Encoder as AvEncoder;
 use ffmpeg::codec::encoder::video::Video as AvVideo;
 use ffmpeg::codec::flag::Flags as AvCodecFlags;
 use ffmpeg::codec::packet::Packet as AvPacket;
-use ffmpeg::codec::{Context as AvContext, Id as AvCodecId};
+use ffmpeg::codec::Context as AvContext;
 use ffmpeg::format::flag::Flags as AvFormatFlags;
 use ffmpeg::software::scaling::context::Context as AvScaler;
 use ffmpeg::software::scaling::flag::Flags as AvScalerFlags;
@@ -16,15 +16,22 @@ use ffmpeg::util::picture::Type as AvFrameType;
 use ffmpeg::Error as AvError;
 use ffmpeg::Rational as AvRational;
 
+use crate::core::codec::{
+    CodecOptions, Preset, RateControl, RateControlParameters, Tune, VideoCodec,
+};
+use crate::core::color::{ColorMetadata, ColorPrimaries, ColorRange, ColorSpace, ColorTransfer};
 use crate::core::error::Error;
-use crate::core::ffi;   
+use crate::core::ffi;
+use crate::core::ffi_hwaccel;
 #[cfg(feature = "ndarray")]
-use crate::core::frame::Frame;
+use crate::core::frame::{Frame, Frame16, FrameF32};
 use crate::core::frame::{PixelFormat, RawFrame, FRAME_PIXEL_FORMAT};
+use crate::core::hwaccel::{HardwareAccelerationDeviceType, HardwareContext, HardwareDevice};
 use crate::core::io::private::Write;
 use crate::core::io::{Writer, WriterBuilder};
 use crate::core::location::Location;
 use crate::core::options::Options;
+use crate::core::threads::{CodecThreading, ThreadBudget};
 #[cfg(feature = "ndarray")]
 use crate::core::time::Time;
 
@@ -37,6 +44,12 @@ pub struct EncoderBuilder<'a> {
     options: Option<&'a Options>,
     format: Option<&'a str>,
     interleaved: bool,
+    two_pass: Option<(Pass, std::path::PathBuf)>,
+    thread_budget: Option<ThreadBudget>,
+    hardware_acceleration_device_type: Option<HardwareAccelerationDeviceType>,
+    hardware_device: Option<HardwareDevice>,
+    hardware_context: Option<HardwareContext>,
+    writer: Option<Writer>,
 }
 
 impl<'a> EncoderBuilder<'a> {
@@ -51,6 +64,38 @@ impl<'a> EncoderBuilder<'a> {
             options: None,
             format: None,
             interleaved: false,
+            two_pass: None,
+            thread_budget: None,
+            hardware_acceleration_device_type: None,
+            hardware_device: None,
+            hardware_context: None,
+            writer: None,
+        }
+    }
+
+    /// Create an encoder that encodes to an existing writer, such as an
+    /// [`HlsWriter`](crate::core::hls::HlsWriter). The writer has already been opened, so output
+    /// options and the container format must be set on the writer instead: building fails if they
+    /// are set on this builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer to encode to.
+    /// * `settings` - Encoding settings.
+    pub fn from_writer(writer: impl Into<Writer>, settings: Settings) -> Self {
+        let writer = writer.into();
+        Self {
+            destination: writer.destination.clone(),
+            settings,
+            options: None,
+            format: None,
+            interleaved: false,
+            two_pass: None,
+            thread_budget: None,
+            hardware_acceleration_device_type: None,
+            hardware_device: None,
+            hardware_context: None,
+            writer: Some(writer),
         }
     }
 
@@ -81,16 +126,381 @@ impl<'a> EncoderBuilder<'a> {
         self
     }
 
+    /// Set the constant rate factor (CRF). Lower values mean higher quality. The valid range
+    /// depends on the codec: `0` to `51` for H.264 and H.265, `0` to `63` for VP9 and AV1.
+    ///
+    /// # Arguments
+    ///
+    /// * `crf` - Constant rate factor.
+    pub fn with_crf(mut self, crf: u8) -> Self {
+        self.settings.codec_options.crf = Some(crf);
+        self
+    }
+
+    /// Set the rate-control mode, e.g. CBR with a VBV buffer for streaming. Cannot be combined
+    /// with [`EncoderBuilder::with_crf`].
+    ///
+    /// # Arguments
+    ///
+    /// * `rate_control` - Rate-control mode.
+    pub fn with_rate_control(mut self, rate_control: RateControl) -> Self {
+        self.settings.codec_options.rate_control = Some(rate_control);
+        self
+    }
+
+    /// Set the encoder speed preset.
+    ///
+    /// # Arguments
+    ///
+    /// * `preset` - Speed preset.
+    pub fn with_preset(mut self, preset: Preset) -> Self {
+        self.settings.codec_options.preset = Some(preset);
+        self
+    }
+
+    /// Set the encoder tuning.
+    ///
+    /// # Arguments
+    ///
+    /// * `tune` - Tuning, must be supported by the codec.
+    pub fn with_tune(mut self, tune: Tune) -> Self {
+        self.settings.codec_options.tune = Some(tune);
+        self
+    }
+
+    /// Set the codec profile, e.g. `high` for H.264 or `main10` for H.265.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - Profile name, must be supported by the codec.
+    pub fn with_profile(mut self, profile: &str) -> Self {
+        self.settings.codec_options.profile = Some(profile.to_string());
+        self
+    }
+
+    /// Set the codec level, e.g. `4.1`. Only H.264 and H.265 accept a level.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - Level, must be supported by the codec.
+    pub fn with_level(mut self, level: &str) -> Self {
+        self.settings.codec_options.level = Some(level.to_string());
+        self
+    }
+
+    /// Set the maximum number of consecutive B-frames. Use zero to disable B-frames for low latency
+    /// live streaming.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_b_frames` - Maximum number of consecutive B-frames.
+    pub fn with_max_b_frames(mut self, max_b_frames: u32) -> Self {
+        self.settings.codec_options.max_b_frames = Some(max_b_frames);
+        self
+    }
+
+    /// Set the number of reference frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference_frames` - Number of reference frames, from `1` to `16`.
+    pub fn with_reference_frames(mut self, reference_frames: u32) -> Self {
+        self.settings.codec_options.reference_frames = Some(reference_frames);
+        self
+    }
+
+    /// Set the number of frames the rate control looks ahead. Use a large value for archival and a
+    /// small value (or zero) for live use.
+    ///
+    /// # Arguments
+    ///
+    /// * `lookahead` - Number of lookahead frames.
+    pub fn with_lookahead(mut self, lookahead: u32) -> Self {
+        self.settings.codec_options.lookahead = Some(lookahead);
+        self
+    }
+
+    /// Set the GOP size: the number of frames between keyframes. A keyframe is forced at the start
+    /// of every GOP, so that GOPs of all renditions line up, which is what segmented formats
+    /// such as HLS and DASH need.
+    ///
+    /// # Arguments
+    ///
+    /// * `gop_size` - Number of frames per GOP.
+    pub fn with_gop_size(mut self, gop_size: u32) -> Self {
+        self.settings.codec_options.gop_size = Some(gop_size);
+        self.settings.keyframe_interval = gop_size as u64;
+        self
+    }
+
+    /// Use closed GOPs, so that every GOP can be decoded independently of the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `closed_gop` - Whether or not to close GOPs.
+    pub fn with_closed_gop(mut self, closed_gop: bool) -> Self {
+        self.settings.codec_options.closed_gop = closed_gop;
+        self
+    }
+
+    /// Disable scene-cut detection, so that the encoder only places keyframes at GOP boundaries
+    /// and where they are forced with [`Encoder::force_keyframe`].
+    ///
+    /// # Arguments
+    ///
+    /// * `disable` - Whether or not to disable scene-cut detection.
+    pub fn with_scene_cut_disabled(mut self, disable: bool) -> Self {
+        self.settings.codec_options.disable_scene_cut = disable;
+        self
+    }
+
+    /// Set the color range signaled in the output stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - Color range.
+    pub fn with_color_range(mut self, range: ColorRange) -> Self {
+        self.settings.color.range = range;
+        self
+    }
+
+    /// Set the color primaries signaled in the output stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `primaries` - Color primaries.
+    pub fn with_color_primaries(mut self, primaries: ColorPrimaries) -> Self {
+        self.settings.color.primaries = primaries;
+        self
+    }
+
+    /// Set the transfer characteristic signaled in the output stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `transfer` - Transfer characteristic.
+    pub fn with_color_transfer(mut self, transfer: ColorTransfer) -> Self {
+        self.settings.color.transfer = transfer;
+        self
+    }
+
+    /// Set the color space (matrix coefficients) signaled in the output stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `space` - Color space.
+    pub fn with_color_space(mut self, space: ColorSpace) -> Self {
+        self.settings.color.space = space;
+        self
+    }
+
+    /// Set the sample aspect ratio signaled in the output stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_aspect_ratio` - Sample aspect ratio.
+    pub fn with_sample_aspect_ratio(mut self, sample_aspect_ratio: impl Into<AvRational>) -> Self {
+        self.settings.color.sample_aspect_ratio = sample_aspect_ratio.into();
+        self
+    }
+
+    /// Run this encoder as one pass of a two-pass encode. The first pass writes encoding
+    /// statistics to `stats_file`, and the second pass reads them back to distribute bits more
+    /// efficiently. Both passes must use the same settings.
+    ///
+    /// See [`TwoPassTranscoder`](crate::core::transcode::TwoPassTranscoder) for a convenience
+    /// wrapper that runs both passes.
+    ///
+    /// # Arguments
+    ///
+    /// * `pass` - Which pass to run.
+    /// * `stats_file` - Statistics file to write to (first pass) or read from (second pass).
+    pub fn with_two_pass(mut self, pass: Pass, stats_file: impl Into<std::path::PathBuf>) -> Self {
+        self.two_pass = Some((pass, stats_file.into()));
+        self
+    }
+
+    /// Limit the threads the encoder uses. The priority and core affinity of the budget are not
+    /// applied here: the threads of the encoder inherit them from the thread that builds it. See
+    /// [`ThreadBudget::apply_to_current_thread`].
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Thread budget.
+    pub fn with_thread_budget(mut self, budget: ThreadBudget) -> Self {
+        self.thread_budget = Some(budget);
+        self
+    }
+
+    /// Encode on a hardware device, with the hardware encoder of the codec for the device type
+    /// (NVENC, VA-API, Quick Sync Video or VideoToolbox). Frames in system memory, whether decoded
+    /// in software or generated, are converted to NV12 (or P010 for 10-bit pixel formats) and
+    /// uploaded to the device before they are encoded.
+    ///
+    /// Fails on build with [`Error::InvalidConfiguration`] if the device type has no encoder for
+    /// the codec.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_type` - Device type to encode on.
+    pub fn with_hardware_acceleration(
+        mut self,
+        device_type: HardwareAccelerationDeviceType,
+    ) -> Self {
+        self.hardware_acceleration_device_type = Some(device_type);
+        self
+    }
+
+    /// Select the device to encode on, on systems with several GPUs. Requires
+    /// [`Self::with_hardware_acceleration`].
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - Device index or path.
+    pub fn with_hardware_device(mut self, device: HardwareDevice) -> Self {
+        self.hardware_device = Some(device);
+        self
+    }
+
+    /// Encode on the device of a hardware decoder, for full-GPU transcoding. Hardware frames that
+    /// the decoder produces (see
+    /// [`DecoderBuilder::with_hardware_frames`](crate::core::decode::DecoderBuilder::with_hardware_frames))
+    /// are encoded without leaving device memory, as long as they have the size of the encoder
+    /// and the layout of its frames (NV12, or P010 for 10-bit pixel formats). Other frames are
+    /// downloaded, converted and uploaded as with [`Self::with_hardware_acceleration`].
+    ///
+    /// Replaces [`Self::with_hardware_acceleration`] and [`Self::with_hardware_device`], since the
+    /// context determines both.
+    ///
+    /// # Arguments
+    ///
+    /// * `hardware_context` - Device of the decoder, see
+    ///   [`Decoder::hardware_context`](crate::core::decode::Decoder::hardware_context).
+    pub fn with_shared_hardware_context(mut self, hardware_context: HardwareContext) -> Self {
+        self.hardware_context = Some(hardware_context);
+        self
+    }
+
+    /// Set the number of threads the encoder uses, instead of the backend default. `0` picks one
+    /// thread per core, `1` disables threading.
+    ///
+    /// # Arguments
+    ///
+    /// * `threads` - Number of threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        let budget = self.thread_budget.take().unwrap_or_default();
+        self.thread_budget = Some(budget.with_codec_threads(threads));
+        self
+    }
+
+    /// Choose between frame-parallel and slice-parallel threading. Frame-parallel threading gives
+    /// the highest throughput, but adds a frame of latency per thread. Slice-parallel threading
+    /// adds no latency, which suits live streaming; combine it with [`Self::with_slices`].
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_parallel` - Whether to use frame-parallel (`true`) or slice-parallel (`false`)
+    ///   threading.
+    pub fn with_frame_parallel(mut self, frame_parallel: bool) -> Self {
+        let threading = if frame_parallel {
+            CodecThreading::Frame
+        } else {
+            CodecThreading::Slice
+        };
+        let budget = self.thread_budget.take().unwrap_or_default();
+        self.thread_budget = Some(budget.with_codec_threading(threading));
+        self
+    }
+
+    /// Set the number of slices to split each frame into. VP9 and AV1 encoders use tile columns
+    /// instead, rounded down to a power of two.
+    ///
+    /// # Arguments
+    ///
+    /// * `slices` - Number of slices, from `1` to `64`.
+    pub fn with_slices(mut self, slices: u32) -> Self {
+        self.settings.codec_options.slices = Some(slices);
+        self
+    }
+
     /// Build an [`Encoder`].
+    ///
+    /// Fails with [`Error::InvalidEncoderOption`] if any of the typed codec options is not
+    /// supported by the configured codec, and with [`Error::InvalidConfiguration`] if the
+    /// settings do not fit together (e.g. odd dimensions with a subsampled pixel format, a codec
+    /// the container cannot hold, or output options for an encoder created with
+    /// [`EncoderBuilder::from_writer`]).
     pub fn build(self) -> Result<Encoder> {
-        let mut writer_builder = WriterBuilder::new(self.destination);
-        if let Some(options) = self.options {
-            writer_builder = writer_builder.with_options(options);
+        self.settings
+            .codec_options
+            .validate(self.settings.video_codec)?;
+        self.settings.validate()?;
+        if self.hardware_device.is_some() && self.hardware_acceleration_device_type.is_none() {
+            return Err(Error::InvalidConfiguration {
+                field: "hardware_device",
+                reason: "device selection requires a hardware acceleration device type".to_string(),
+            });
         }
-        if let Some(format) = self.format {
-            writer_builder = writer_builder.with_format(format);
+        if self.hardware_context.is_some() && self.hardware_acceleration_device_type.is_some() {
+            return Err(Error::InvalidConfiguration {
+                field: "hardware_acceleration_device_type",
+                reason: "a shared hardware context already determines the device".to_string(),
+            });
         }
-        Encoder::from_writer(writer_builder.build()?, self.interleaved, self.settings)
+        let writer = match self.writer {
+            Some(_) if self.options.is_some() => {
+                return Err(Error::InvalidConfiguration {
+                    field: "options",
+                    reason: "output options must be set on the writer to encode to".to_string(),
+                });
+            }
+            Some(_) if self.format.is_some() => {
+                return Err(Error::InvalidConfiguration {
+                    field: "format",
+                    reason: "container format must be set on the writer to encode to".to_string(),
+                });
+            }
+            Some(writer) => writer,
+            None => {
+                let mut writer_builder = WriterBuilder::new(self.destination);
+                if let Some(options) = self.options {
+                    writer_builder = writer_builder.with_options(options);
+                }
+                if let Some(format) = self.format {
+                    writer_builder = writer_builder.with_format(format);
+                }
+                writer_builder.build()?
+            }
+        };
+        let video_codec = self.settings.video_codec;
+        if ffi::output_format_supports_codec(&writer.output, video_codec.id()) == Some(false) {
+            return Err(Error::InvalidConfiguration {
+                field: "format",
+                reason: format!(
+                    "container {} cannot hold {video_codec}",
+                    writer.output.format().name(),
+                ),
+            });
+        }
+        let hardware_context = match (
+            self.hardware_context,
+            self.hardware_acceleration_device_type,
+        ) {
+            (Some(hardware_context), _) => Some(hardware_context),
+            (None, Some(device_type)) => Some(HardwareContext::open(
+                device_type,
+                self.hardware_device.as_ref(),
+            )?),
+            (None, None) => None,
+        };
+        Encoder::from_writer(
+            writer,
+            self.interleaved,
+            self.settings,
+            self.two_pass,
+            self.thread_budget,
+            hardware_context,
+        )
     }
 }
 
@@ -120,13 +530,32 @@ pub struct Encoder {
     encoder: AvEncoder,
     encoder_time_base: AvRational,
     keyframe_interval: u64,
+    force_keyframe: bool,
     interleaved: bool,
     scaler: AvScaler,
     scaler_width: u32,
     scaler_height: u32,
+    // Color tags of the encoder, which the scaler converts to.
+    color: ColorMetadata,
+    // Pixel format that frames are converted to: the encoder pixel format, or the format of
+    // frames in the hardware frame pool.
+    frame_format: AvPixel,
+    // Device of hardware encoders, and the pool in device memory that frames are uploaded to.
+    hardware_context: Option<HardwareContext>,
+    hardware_frames: Option<ffi_hwaccel::HardwareFramesContext>,
     frame_count: u64,
+    variable_frame_rate: bool,
+    last_pts: Option<i64>,
+    last_duration: i64,
+    pending_frame: Option<RawFrame>,
     have_written_header: bool,
     have_written_trailer: bool,
+    finished: bool,
+    stats_out: Option<std::io::BufWriter<std::fs::File>>,
+    // First pass statistics that were written last, so that they are not written twice.
+    last_stats_out: Option<String>,
+    // Must be declared after `encoder` so that it outlives the codec context that points to it.
+    _stats_in: Option<std::ffi::CString>,
 }
 
 impl Encoder {
@@ -143,54 +572,184 @@ impl Encoder {
     ///
     /// # Arguments
     ///
-    /// * `frame` - Frame to encode in `HWC` format and standard layout.
+    /// * `frame` - Frame to encode in `HWC` format and standard layout. Frames with three channels
+    ///   are RGB, frames with four channels are RGBA. The alpha channel is only kept if the
+    ///   encoder was set up with [`Settings::preset_with_alpha`].
     /// * `source_timestamp` - Frame timestamp of original source. This is necessary to make sure
     ///   the output will be timed correctly.
     #[cfg(feature = "ndarray")]
     pub fn encode(&mut self, frame: &Frame, source_timestamp: Time) -> Result<()> {
         let (height, width, channels) = frame.dim();
+        if height != self.scaler_height as usize || width != self.scaler_width as usize {
+            return Err(Error::InvalidFrameFormat);
+        }
+
+        let frame = match channels {
+            3 => ffi::convert_ndarray_to_frame_rgb24(frame),
+            4 => ffi::convert_ndarray_to_frame_rgba(frame),
+            _ => return Err(Error::InvalidFrameFormat),
+        }
+        .map_err(Error::BackendError)?;
+
+        self.encode_with_timestamp(frame, source_timestamp)
+    }
+
+    /// Encode a single `ndarray` frame with 16 bits per sample, e.g. to encode 10-bit or 12-bit
+    /// video without losing precision. Set up the encoder with a high bit depth pixel format to
+    /// keep it.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - RGB frame to encode in `HWC` format, with three channels.
+    /// * `source_timestamp` - Frame timestamp of original source.
+    #[cfg(feature = "ndarray")]
+    pub fn encode_u16(&mut self, frame: &Frame16, source_timestamp: Time) -> Result<()> {
+        self.check_frame_dimensions(frame.dim())?;
+        let frame = ffi::convert_ndarray_to_frame_rgb48(frame);
+        self.encode_with_timestamp(frame, source_timestamp)
+    }
+
+    /// Encode a single `ndarray` frame with a float per sample, with values from `0` to `1`.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - RGB frame to encode in `HWC` format, with three channels.
+    /// * `source_timestamp` - Frame timestamp of original source.
+    #[cfg(feature = "ndarray")]
+    pub fn encode_f32(&mut self, frame: &FrameF32, source_timestamp: Time) -> Result<()> {
+        self.check_frame_dimensions(frame.dim())?;
+        let frame = ffi::convert_ndarray_to_frame_gbrpf32(frame);
+        self.encode_with_timestamp(frame, source_timestamp)
+    }
+
+    /// Encode a sequence of timestamped `ndarray` frames, such as the output of
+    /// [`Decoder::decode_iter`](crate::core::decode::Decoder::decode_iter). The frames do not
+    /// need to be evenly spaced when the encoder is set up with
+    /// [`Settings::with_variable_frame_rate`].
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - Frames in `HWC` format with their timestamps.
+    #[cfg(feature = "ndarray")]
+    pub fn encode_iter(&mut self, frames: impl IntoIterator<Item = (Time, Frame)>) -> Result<()> {
+        for (timestamp, frame) in frames {
+            self.encode(&frame, timestamp)?;
+        }
+        Ok(())
+    }
+
+    /// Encode a single raw frame.
+    ///
+    /// The frame may be in any pixel format. It is converted to the pixel format of the codec if
+    /// necessary. Frames that live in hardware memory (e.g. decoded with hardware acceleration) are
+    /// downloaded first. Hardware encoders (see [`EncoderBuilder::with_hardware_acceleration`])
+    /// then upload the frame to the device.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to encode. Must have the same size as the encoder.
+    pub fn encode_raw(&mut self, frame: RawFrame) -> Result<()> {
+        if self.finished {
+            return Err(Error::EncoderFinished);
+        }
+        if frame.width() != self.scaler_width || frame.height() != self.scaler_height {
+            return Err(Error::InvalidFrameFormat);
+        }
+
+        self.write_header_once()?;
+
+        if self.variable_frame_rate {
+            self.encode_variable_frame_rate(frame)
+        } else {
+            self.send(frame)
+        }
+    }
+
+    /// Check that an RGB `ndarray` frame matches the size of the encoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `dimensions` - Dimensions of the frame: `(H, W, C)`.
+    #[cfg(feature = "ndarray")]
+    fn check_frame_dimensions(&self, dimensions: (usize, usize, usize)) -> Result<()> {
+        let (height, width, channels) = dimensions;
         if height != self.scaler_height as usize
             || width != self.scaler_width as usize
             || channels != 3
         {
             return Err(Error::InvalidFrameFormat);
         }
+        Ok(())
+    }
 
-        let mut frame = ffi::convert_ndarray_to_frame_rgb24(frame).map_err(Error::BackendError)?;
-
+    /// Set the timestamp of a frame converted from an `ndarray`, and encode it.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to encode.
+    /// * `source_timestamp` - Frame timestamp of original source.
+    #[cfg(feature = "ndarray")]
+    fn encode_with_timestamp(&mut self, mut frame: RawFrame, source_timestamp: Time) -> Result<()> {
         frame.set_pts(
             source_timestamp
                 .aligned_with_rational(self.encoder_time_base)
                 .into_value(),
         );
-
         self.encode_raw(frame)
     }
 
-    /// Encode a single raw frame.
+    /// Queue a frame of a variable frame rate stream. The duration of a frame is only known once
+    /// the next frame arrives, so each frame is held back until then.
     ///
     /// # Arguments
     ///
-    /// * `frame` - Frame to encode.
-    pub fn encode_raw(&mut self, frame: RawFrame) -> Result<()> {
-        if frame.width() != self.scaler_width
-            || frame.height() != self.scaler_height
-            || frame.format() != FRAME_PIXEL_FORMAT
-        {
-            return Err(Error::InvalidFrameFormat);
+    /// * `frame` - Frame to encode, with PTS in the encoder time base.
+    fn encode_variable_frame_rate(&mut self, mut frame: RawFrame) -> Result<()> {
+        let mut pts = frame.pts().ok_or(Error::InvalidFrameFormat)?;
+        // Timestamps must be strictly increasing, nudge them forward if they are not.
+        if let Some(last_pts) = self.last_pts {
+            if pts <= last_pts {
+                tracing::warn!("frame timestamp {pts} is not after {last_pts}, adjusting");
+                pts = last_pts + 1;
+                frame.set_pts(Some(pts));
+            }
         }
+        self.last_pts = Some(pts);
 
-        // Write file header if we hadn't done that yet.
-        if !self.have_written_header {
-            self.writer.write_header()?;
-            self.have_written_header = true;
+        if let Some(mut pending_frame) = self.pending_frame.replace(frame) {
+            self.last_duration = pts - pending_frame.pts().unwrap_or(pts);
+            ffi::set_frame_duration(&mut pending_frame, self.last_duration);
+            self.send(pending_frame)?;
         }
 
-        // Reformat frame to target pixel format.
-        let mut frame = self.scale(frame)?;
-        // Producer key frame every once in a while
-        if self.frame_count % self.keyframe_interval == 0 {
+        Ok(())
+    }
+
+    /// Scale a frame and send it to the encoder, then write out any packet that is ready.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to encode.
+    fn send(&mut self, frame: RawFrame) -> Result<()> {
+        let mut frame = if self.is_on_encoder_device(&frame) {
+            frame
+        } else {
+            // Reformat frame to target pixel format.
+            let frame = self.scale(frame)?;
+            // Hardware encoders take frames in device memory.
+            match self.hardware_frames.as_ref() {
+                Some(hardware_frames) => hardware_frames.upload(&frame)?,
+                None => frame,
+            }
+        };
+        // Producer key frame every once in a while, or when the caller asked for one. Any other
+        // frame type that came along with the frame (e.g. from a decoder) is discarded, since
+        // encoders treat it as a request.
+        if self.force_keyframe || self.frame_count % self.keyframe_interval == 0 {
             frame.set_kind(AvFrameType::I);
+            self.force_keyframe = false;
+        } else {
+            frame.set_kind(AvFrameType::None);
         }
 
         self.encoder
@@ -200,29 +759,107 @@ impl Encoder {
         // https://github.com/oddity-ai/video-rs/issues/46.
         self.frame_count += 1;
 
-        if let Some(packet) = self.encoder_receive_packet()? {
+        // The encoder may have more than one packet ready, e.g. after a series of B-frames.
+        while let Some(packet) = self.encoder_receive_packet()? {
             self.write(packet)?;
         }
 
         Ok(())
     }
 
-    /// Signal to the encoder that writing has finished. This will cause any packets in the encoder
-    /// to be flushed and a trailer to be written if the container format has one.
+    /// Make the next frame passed to the encoder a keyframe, for example to start a new segment.
+    /// Combine with [`EncoderBuilder::with_closed_gop`] to make sure it is an IDR frame.
+    pub fn force_keyframe(&mut self) {
+        self.force_keyframe = true;
+    }
+
+    /// Change the target bitrate mid-stream without recreating the encoder, e.g. for adaptive
+    /// live streaming. The minimum and maximum bitrate and the VBV buffer size are scaled along
+    /// with the bitrate. The new bitrate applies from the next frame on.
+    ///
+    /// Only encoders that can be reconfigured while running support this: `libx264` and the
+    /// NVENC and Quick Sync encoders. Other encoders are rejected with
+    /// [`Error::UnsupportedCodec`]. Encoders that do not target a bitrate (such as CRF or constant
+    /// QP) are rejected with [`Error::InvalidEncoderOption`].
+    ///
+    /// # Arguments
+    ///
+    /// * `bitrate` - New target bitrate in bits per second.
+    pub fn set_bitrate(&mut self, bitrate: u64) -> Result<()> {
+        if self.finished {
+            return Err(Error::EncoderFinished);
+        }
+        let encoder_name = self
+            .encoder
+            .codec()
+            .map(|codec| codec.name().to_string())
+            .unwrap_or_default();
+        if !RateControlParameters::is_reconfigurable(&encoder_name) {
+            return Err(Error::UnsupportedCodec(encoder_name));
+        }
+        let parameters = ffi::get_encoder_rate_control(&self.encoder).with_bitrate(bitrate)?;
+        ffi::set_encoder_rate_control(&mut self.encoder, parameters);
+        Ok(())
+    }
+
+    /// Signal to the encoder that writing has finished. This will cause all packets that are still
+    /// delayed in the encoder to be flushed and written, after which the trailer is written if the
+    /// container format has one. Calling this function more than once has no effect, and frames
+    /// that are encoded afterwards are rejected with [`Error::EncoderFinished`].
     ///
     /// Note: If you don't call this function before dropping the encoder, it will be called
     /// automatically. This will block the caller thread. Any errors cannot be propagated in this
     /// case.
     pub fn finish(&mut self) -> Result<()> {
+        self.finished = true;
         if self.have_written_header && !self.have_written_trailer {
             self.have_written_trailer = true;
+            // The last frame of a variable frame rate stream gets the same duration as the one
+            // before it.
+            if let Some(mut pending_frame) = self.pending_frame.take() {
+                ffi::set_frame_duration(&mut pending_frame, self.last_duration);
+                self.send(pending_frame)?;
+            }
             self.flush()?;
             self.writer.write_trailer()?;
+            if let Some(stats_out) = self.stats_out.as_mut() {
+                std::io::Write::flush(stats_out)?;
+            }
         }
 
         Ok(())
     }
 
+    /// Finish encoding and flush the output, reporting any error. Prefer this over dropping the
+    /// encoder, which can only log errors.
+    pub fn close(mut self) -> Result<()> {
+        self.finish()?;
+        ffi::flush_output_io(&mut self.writer.output)?;
+        Ok(())
+    }
+
+    /// Whether or not [`Encoder::finish`] has been called.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Write the file header if that has not been done yet.
+    pub(crate) fn write_header_once(&mut self) -> Result<()> {
+        if !self.have_written_header {
+            self.writer.write_header()?;
+            self.have_written_header = true;
+        }
+        Ok(())
+    }
+
+    /// Get the writer, to add and write other streams next to the video stream. Streams must be
+    /// added before the header is written.
+    #[inline]
+    pub(crate) fn writer_mut(&mut self) -> &mut Writer {
+        &mut self.writer
+    }
+
     /// Get encoder time base.
     #[inline]
     pub fn time_base(&self) -> AvRational {
@@ -236,50 +873,142 @@ impl Encoder {
     /// * `writer` - [`Writer`] to create encoder from.
     /// * `interleaved` - Whether or not to use interleaved write.
     /// * `settings` - Encoder settings to use.
-    fn from_writer(mut writer: Writer, interleaved: bool, settings: Settings) -> Result<Self> {
+    /// * `two_pass` - Pass to run and statistics file, if this is part of a two-pass encode.
+    /// * `thread_budget` - Thread budget of the encoder.
+    /// * `hardware_context` - Device to encode on, if any.
+    fn from_writer(
+        mut writer: Writer,
+        interleaved: bool,
+        settings: Settings,
+        two_pass: Option<(Pass, std::path::PathBuf)>,
+        thread_budget: Option<ThreadBudget>,
+        hardware_context: Option<HardwareContext>,
+    ) -> Result<Self> {
+        let hardware_acceleration_device_type = hardware_context
+            .as_ref()
+            .map(HardwareContext::device_type);
         let global_header = writer
             .output
             .format()
             .flags()
             .contains(AvFormatFlags::GLOBAL_HEADER);
 
-        let mut writer_stream = writer.output.add_stream(settings.codec())?;
+        let codec = match hardware_acceleration_device_type {
+            Some(device_type) => Some(
+                settings
+                    .video_codec
+                    .find_hardware_encoder(device_type)
+                    .ok_or_else(|| Error::InvalidConfiguration {
+                        field: "hardware_acceleration_device_type",
+                        reason: format!("{device_type:?} has no {} encoder", settings.video_codec),
+                    })?,
+            ),
+            None => settings.codec(),
+        };
+        let mut writer_stream = writer.output.add_stream(codec)?;
         let writer_stream_index = writer_stream.index();
 
-        let mut encoder_context = match settings.codec() {
+        let mut encoder_context = match codec {
             Some(codec) => ffi::codec_context_as(&codec)?,
             None => AvContext::new(),
         };
 
+        let mut flags = AvCodecFlags::empty();
         // Some formats require this flag to be set or the output will
         // not be playable by dumb players.
         if global_header {
-            encoder_context.set_flags(AvCodecFlags::GLOBAL_HEADER);
+            flags |= AvCodecFlags::GLOBAL_HEADER;
+        }
+        match two_pass {
+            Some((Pass::First, _)) => flags |= AvCodecFlags::PASS1,
+            Some((Pass::Second, _)) => flags |= AvCodecFlags::PASS2,
+            None => {}
+        }
+        encoder_context.set_flags(flags);
+        if let Some(thread_budget) = thread_budget {
+            thread_budget.apply_to_codec(&mut encoder_context);
         }
 
         let mut encoder = encoder_context.encoder().video()?;
         settings.apply_to(&mut encoder);
 
+        // Hardware encoders take frames from a pool in device memory, which frames are uploaded to.
+        let frame_format = match hardware_acceleration_device_type {
+            Some(_) => settings.hardware_frame_format(),
+            None => encoder.format(),
+        };
+        let hardware_frames = match hardware_context.as_ref() {
+            Some(hardware_context) => {
+                let device_type = hardware_context.device_type();
+                let format = device_type
+                    .encoder_pixel_format()
+                    .ok_or(Error::UnsupportedCodecHardwareAccelerationDeviceType)?;
+                let hardware_frames = ffi_hwaccel::HardwareFramesContext::new(
+                    hardware_context.device(),
+                    format,
+                    frame_format,
+                    settings.width,
+                    settings.height,
+                    Settings::hardware_frame_pool_size(device_type),
+                )?;
+                ffi_hwaccel::codec_context_set_hw_frames_ctx(&mut encoder, &hardware_frames);
+                encoder.set_format(format);
+                Some(hardware_frames)
+            }
+            None => None,
+        };
+
+        // libx264 reads and writes the statistics file itself. Other encoders exchange statistics
+        // through the codec context, so we take care of the file for them.
+        let encoder_name = codec.as_ref().map(|codec| codec.name()).unwrap_or_default();
+        let mut encoder_options = settings.options_for(codec.as_ref());
+        let mut stats_out = None;
+        let mut stats_in = None;
+        match two_pass {
+            Some((_, ref stats_file)) if encoder_name == "libx264" => {
+                encoder_options.set("stats", &stats_file.to_string_lossy());
+            }
+            Some((Pass::First, ref stats_file)) => {
+                stats_out = Some(std::io::BufWriter::new(std::fs::File::create(stats_file)?));
+            }
+            Some((Pass::Second, ref stats_file)) => {
+                let stats = std::ffi::CString::new(std::fs::read(stats_file)?).map_err(|_| {
+                    Error::InvalidEncoderOption("stats file is corrupted".to_string())
+                })?;
+                ffi::set_encoder_stats_in(&mut encoder, &stats);
+                stats_in = Some(stats);
+            }
+            None => {}
+        }
+
         // Just use the ffmpeg global time base which is precise enough
         // that we should never get in trouble.
         encoder.set_time_base(TIME_BASE);
 
-        let encoder = encoder.open_with(settings.options().to_dict())?;
+        let encoder = encoder.open_with(encoder_options.to_dict())?;
         let encoder_time_base = ffi::get_encoder_time_base(&encoder);
 
         writer_stream.set_parameters(&encoder);
+        if settings.color.sample_aspect_ratio.numerator() > 0 {
+            ffi::set_stream_sample_aspect_ratio(
+                &mut writer.output,
+                writer_stream_index,
+                settings.color.sample_aspect_ratio,
+            );
+        }
 
         let scaler_width = encoder.width();
         let scaler_height = encoder.height();
-        let scaler = AvScaler::get(
+        let mut scaler = AvScaler::get(
             FRAME_PIXEL_FORMAT,
             scaler_width,
             scaler_height,
-            encoder.format(),
+            frame_format,
             scaler_width,
             scaler_height,
             AvScalerFlags::empty(),
         )?;
+        ffi::set_scaler_output_colorspace(&mut scaler, settings.color.space, settings.color.range);
 
         Ok(Self {
             writer,
@@ -287,29 +1016,88 @@ impl Encoder {
             encoder,
             encoder_time_base,
             keyframe_interval: settings.keyframe_interval,
+            force_keyframe: false,
             interleaved,
             scaler,
             scaler_width,
             scaler_height,
+            color: settings.color,
+            frame_format,
+            hardware_context,
+            hardware_frames,
             frame_count: 0,
+            variable_frame_rate: settings.variable_frame_rate,
+            last_pts: None,
+            last_duration: 0,
+            pending_frame: None,
             have_written_header: false,
             have_written_trailer: false,
+            finished: false,
+            stats_out,
+            last_stats_out: None,
+            _stats_in: stats_in,
         })
     }
 
-    /// Apply scaling (or pixel reformatting in this case) on the frame with the scaler we
-    /// initialized earlier.
+    /// Whether or not a frame is in the memory of the device of a hardware encoder, with the size
+    /// and layout of the frames of the encoder, so that it can be encoded as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to encode.
+    fn is_on_encoder_device(&self, frame: &RawFrame) -> bool {
+        self.hardware_context
+            .as_ref()
+            .is_some_and(|hardware_context| hardware_context.contains(frame))
+            && ffi_hwaccel::frame_hardware_software_format(frame) == Some(self.frame_format)
+            && (frame.width(), frame.height()) == (self.scaler_width, self.scaler_height)
+    }
+
+    /// Apply scaling (or pixel reformatting in this case) on the frame. The scaler is
+    /// reinitialized whenever the pixel format of the input changes, and frames that are already
+    /// in the codec pixel format are passed through as-is.
     ///
     /// # Arguments
     ///
     /// * `frame` - Frame to rescale.
     fn scale(&mut self, frame: RawFrame) -> Result<RawFrame> {
+        let frame = if ffi_hwaccel::frame_is_hardware(&frame) {
+            let mut frame_downloaded = RawFrame::empty();
+            ffi_hwaccel::hwdevice_transfer_frame(&mut frame_downloaded, &frame)?;
+            ffi::copy_frame_props(&frame, &mut frame_downloaded);
+            frame_downloaded
+        } else {
+            frame
+        };
+
+        if frame.format() == self.frame_format {
+            return Ok(frame);
+        }
+
+        if frame.format() != self.scaler.input().format {
+            self.scaler.cached(
+                frame.format(),
+                self.scaler_width,
+                self.scaler_height,
+                self.frame_format,
+                self.scaler_width,
+                self.scaler_height,
+                AvScalerFlags::empty(),
+            );
+            ffi::set_scaler_output_colorspace(
+                &mut self.scaler,
+                self.color.space,
+                self.color.range,
+            );
+        }
+
         let mut frame_scaled = RawFrame::empty();
         self.scaler
             .run(&frame, &mut frame_scaled)
             .map_err(Error::BackendError)?;
-        // Copy over PTS from old frame.
+        // Copy over PTS (and duration) from old frame.
         frame_scaled.set_pts(frame.pts());
+        ffi::set_frame_duration(&mut frame_scaled, ffi::get_frame_duration(&frame));
 
         Ok(frame_scaled)
     }
@@ -320,7 +1108,11 @@ impl Encoder {
         let mut packet = AvPacket::empty();
         let encode_result = self.encoder.receive_packet(&mut packet);
         match encode_result {
-            Ok(()) => Ok(Some(packet)),
+            Ok(()) => {
+                // Collect first pass statistics that the encoder produced for this packet.
+                self.write_stats_out()?;
+                Ok(Some(packet))
+            }
             Err(AvError::Other { errno }) if errno == EAGAIN => Ok(None),
             Err(err) => Err(err.into()),
         }
@@ -355,32 +1147,63 @@ impl Encoder {
 
     /// Flush the encoder, drain any packets that still need processing.
     fn flush(&mut self) -> Result<()> {
-        // Maximum number of invocations to `encoder_receive_packet`
-        // to drain the items still on the queue before giving up.
-        const MAX_DRAIN_ITERATIONS: u32 = 100;
-
         // Notify the encoder that the last frame has been sent.
         self.encoder.send_eof()?;
 
-        // We need to drain the items still in the encoders queue.
-        for _ in 0..MAX_DRAIN_ITERATIONS {
+        // We need to drain the items still in the encoders queue, until it signals that it is
+        // completely empty.
+        loop {
             match self.encoder_receive_packet() {
                 Ok(Some(packet)) => self.write(packet)?,
-                Ok(None) => continue,
-                Err(_) => break,
+                Ok(None) | Err(Error::BackendError(AvError::Eof)) => break,
+                Err(err) => return Err(err),
             }
         }
+        // Encoders such as libvpx and libaom produce no packets in the first pass, and only
+        // provide the statistics once they are flushed.
+        self.write_stats_out()?;
 
         Ok(())
     }
+
+    /// Write the first pass statistics that the encoder currently provides to the statistics
+    /// file, unless they were written already.
+    fn write_stats_out(&mut self) -> Result<()> {
+        let Some(stats_out) = self.stats_out.as_mut() else {
+            return Ok(());
+        };
+        if let Some(stats) = ffi::get_encoder_stats_out(&self.encoder) {
+            if self.last_stats_out.as_ref() != Some(&stats) {
+                std::io::Write::write_all(stats_out, stats.as_bytes())?;
+                self.last_stats_out = Some(stats);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Drop for Encoder {
     fn drop(&mut self) {
-        let _ = self.finish();
+        // Without a trailer most containers (notably MP4) cannot be played back, so make a best
+        // effort to write it.
+        if self.have_written_header && !self.have_written_trailer {
+            tracing::warn!("encoder dropped before it was finished, writing trailer");
+        }
+        if let Err(err) = self.finish() {
+            tracing::error!("failed to finish encoder: {err}");
+        }
     }
 }
 
+/// Pass of a two-pass encode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Pass {
+    /// First pass: analyze the input and write statistics.
+    First,
+    /// Second pass: encode the input using the statistics of the first pass.
+    Second,
+}
+
 /// Holds a logical combination of encoder settings.
 #[derive(Debug, Clone)]
 pub struct Settings {
@@ -388,6 +1211,10 @@ pub struct Settings {
     height: u32,
     pixel_format: AvPixel,
     keyframe_interval: u64,
+    variable_frame_rate: bool,
+    video_codec: VideoCodec,
+    codec_options: CodecOptions,
+    color: ColorMetadata,
     options: Options,
 }
 
@@ -414,6 +1241,10 @@ impl Settings {
             height: height as u32,
             pixel_format: AvPixel::YUV420P,
             keyframe_interval: Self::KEY_FRAME_INTERVAL,
+            variable_frame_rate: false,
+            video_codec: VideoCodec::H264,
+            codec_options: CodecOptions::default(),
+            color: ColorMetadata::default(),
             options,
         }
     }
@@ -443,10 +1274,67 @@ impl Settings {
             height: height as u32,
             pixel_format,
             keyframe_interval: Self::KEY_FRAME_INTERVAL,
+            variable_frame_rate: false,
+            video_codec: VideoCodec::H264,
+            codec_options: CodecOptions::default(),
+            color: ColorMetadata::default(),
             options,
         }
     }
 
+    /// Create encoder settings for a stream that keeps the alpha channel of the frames, for
+    /// overlay and compositing workflows. Feed the encoder RGBA frames; they are converted to the
+    /// alpha-carrying pixel format of the codec:
+    ///
+    /// * [`VideoCodec::Vp9`]: `yuva420p`, best stored in WebM or Matroska.
+    /// * [`VideoCodec::ProRes`]: ProRes 4444 in `yuva444p10le`, best stored in QuickTime.
+    /// * [`VideoCodec::QtRle`]: QuickTime Animation in `argb`, lossless but large.
+    ///
+    /// Fails with [`Error::UnsupportedCodec`] if the codec cannot carry alpha.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the video stream.
+    /// * `height` - The height of the video stream.
+    /// * `video_codec` - Codec to use.
+    pub fn preset_with_alpha(
+        width: usize,
+        height: usize,
+        video_codec: VideoCodec,
+    ) -> Result<Settings> {
+        let pixel_format = video_codec
+            .alpha_pixel_format()
+            .ok_or_else(|| Error::UnsupportedCodec(format!("{video_codec} with alpha")))?;
+
+        let mut options = Options::default();
+        let mut codec_options = CodecOptions::default();
+        match video_codec {
+            // The alternate reference frames of libvpx do not combine with the separately
+            // encoded alpha plane.
+            VideoCodec::Vp9 => options.set("auto-alt-ref", "0"),
+            VideoCodec::ProRes => codec_options.profile = Some("4444".to_string()),
+            _ => {}
+        }
+
+        Ok(Self {
+            width: width as u32,
+            height: height as u32,
+            pixel_format,
+            keyframe_interval: Self::KEY_FRAME_INTERVAL,
+            variable_frame_rate: false,
+            video_codec,
+            codec_options,
+            color: ColorMetadata::default(),
+            options,
+        })
+    }
+
+    /// Whether or not the encoded stream keeps the alpha channel of the frames.
+    #[inline]
+    pub fn has_alpha(&self) -> bool {
+        ffi::pixel_format_has_alpha(self.pixel_format)
+    }
+
     /// Set the keyframe interval.
     pub fn set_keyframe_interval(&mut self, keyframe_interval: u64) {
         self.keyframe_interval = keyframe_interval;
@@ -458,6 +1346,58 @@ impl Settings {
         self
     }
 
+    /// Encode with a variable frame rate. Frames may then be spaced arbitrarily, and their
+    /// timestamps are written as-is instead of assuming a fixed frame rate. Useful for screen
+    /// recordings and other sparse sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `variable_frame_rate` - Whether or not to use a variable frame rate.
+    pub fn with_variable_frame_rate(mut self, variable_frame_rate: bool) -> Self {
+        self.variable_frame_rate = variable_frame_rate;
+        self
+    }
+
+    /// Set the video codec to encode with. Defaults to H.264.
+    ///
+    /// # Arguments
+    ///
+    /// * `video_codec` - Codec to use.
+    pub fn with_codec(mut self, video_codec: VideoCodec) -> Self {
+        self.video_codec = video_codec;
+        self
+    }
+
+    /// Set the color metadata to signal in the output stream. Unspecified fields are left for the
+    /// encoder to decide.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Color metadata, usually taken from the source with
+    ///   [`Decoder::color_metadata`](crate::core::decode::Decoder::color_metadata).
+    pub fn with_color_metadata(mut self, color: ColorMetadata) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Get the color metadata to signal in the output stream.
+    #[inline]
+    pub fn color_metadata(&self) -> ColorMetadata {
+        self.color
+    }
+
+    /// Get the video codec to encode with.
+    #[inline]
+    pub fn video_codec(&self) -> VideoCodec {
+        self.video_codec
+    }
+
+    /// Get the width and height of the encoded video.
+    #[inline]
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
     /// Apply the settings to an encoder.
     ///
     /// # Arguments
@@ -471,22 +1411,120 @@ impl Settings {
         encoder.set_width(self.width);
         encoder.set_height(self.height);
         encoder.set_format(self.pixel_format);
-        encoder.set_frame_rate(Some((Self::FRAME_RATE, 1)));
+        // A variable frame rate stream has no nominal frame rate.
+        if !self.variable_frame_rate {
+            encoder.set_frame_rate(Some((Self::FRAME_RATE, 1)));
+        }
+        self.color.apply_to(encoder);
+    }
+
+    /// Check that the settings fit together and that an encoder is available for them.
+    fn validate(&self) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(Error::InvalidConfiguration {
+                field: "size",
+                reason: format!("{}x{} has a zero dimension", self.width, self.height),
+            });
+        }
+        if self.keyframe_interval == 0 {
+            return Err(Error::InvalidConfiguration {
+                field: "keyframe_interval",
+                reason: "keyframe interval must be greater than zero".to_string(),
+            });
+        }
+        let codec = self.codec().ok_or_else(|| Error::InvalidConfiguration {
+            field: "codec",
+            reason: format!("no encoder for {} is available", self.video_codec),
+        })?;
+        let Some(descriptor) = self.pixel_format.descriptor() else {
+            return Err(Error::InvalidConfiguration {
+                field: "pixel_format",
+                reason: format!("{:?} is not a valid pixel format", self.pixel_format),
+            });
+        };
+        // Subsampled chroma planes need the luma dimensions to be a multiple of the subsampling.
+        let width_multiple = 1_u32 << descriptor.log2_chroma_w();
+        let height_multiple = 1_u32 << descriptor.log2_chroma_h();
+        if self.width % width_multiple != 0 || self.height % height_multiple != 0 {
+            return Err(Error::InvalidConfiguration {
+                field: "size",
+                reason: format!(
+                    "{}x{} is not a multiple of {width_multiple}x{height_multiple} as required by \
+                     pixel format {}",
+                    self.width,
+                    self.height,
+                    descriptor.name(),
+                ),
+            });
+        }
+        let supported_formats = codec.video().ok().and_then(|video| video.formats());
+        if let Some(mut supported_formats) = supported_formats {
+            if !supported_formats.any(|format| format == self.pixel_format) {
+                return Err(Error::InvalidConfiguration {
+                    field: "pixel_format",
+                    reason: format!(
+                        "encoder {} does not support pixel format {}",
+                        codec.name(),
+                        descriptor.name(),
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Layout of frames in device memory for hardware encoders: P010 for pixel formats with more
+    /// than 8 bits per component, NV12 otherwise.
+    fn hardware_frame_format(&self) -> AvPixel {
+        match self.pixel_format {
+            AvPixel::YUV420P10LE
+            | AvPixel::YUV422P10LE
+            | AvPixel::YUV444P10LE
+            | AvPixel::P010LE => AvPixel::P010LE,
+            _ => AvPixel::NV12,
+        }
+    }
+
+    /// Number of frames to allocate up front in the hardware frame pool. Quick Sync Video needs a
+    /// fixed pool, other device types allocate frames as needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_type` - Device type of the pool.
+    fn hardware_frame_pool_size(device_type: HardwareAccelerationDeviceType) -> usize {
+        match device_type {
+            HardwareAccelerationDeviceType::Qsv => 32,
+            _ => 0,
+        }
     }
 
     /// Get codec.
     fn codec(&self) -> Option<AvCodec> {
-        // Try to use the libx264 decoder. If it is not available, then use use whatever default
-        // h264 decoder we have.
-        Some(
-            ffmpeg::encoder::find_by_name("libx264")
-                .unwrap_or(ffmpeg::encoder::find(AvCodecId::H264)?),
-        )
+        // Try to use the preferred encoder (e.g. libx264). If it is not available, then use
+        // whatever default encoder we have for the codec.
+        self.video_codec.find_encoder()
     }
 
-    /// Get encoder options.
-    fn options(&self) -> &Options {
-        &self.options
+    /// Get encoder options, with the typed codec options translated for the given encoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - Encoder that will be opened with the options.
+    fn options_for(&self, codec: Option<&AvCodec>) -> Options {
+        let mut codec_options = self.codec_options.clone();
+        // VP9 and AV1 encoders default to a very low target bitrate, so fall back to constant
+        // quality mode unless the caller asked for something else.
+        let has_rate_control = self.options.get("crf").is_some() || self.options.get("b").is_some();
+        if codec_options.crf.is_none()
+            && codec_options.rate_control.is_none()
+            && !has_rate_control
+            && matches!(self.video_codec, VideoCodec::Vp9 | VideoCodec::Av1)
+        {
+            codec_options.crf = Some(self.video_codec.default_crf());
+        }
+
+        let encoder_name = codec.map(|codec| codec.name()).unwrap_or_default();
+        codec_options.apply_to(encoder_name, &self.options)
     }
 }
 
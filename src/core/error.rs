@@ -16,6 +16,16 @@ pub enum Error {
     InvalidResizeParameters,
     UninitializedCodec,
     UnsupportedCodecHardwareAccelerationDeviceType,
+    InvalidEncoderOption(String),
+    EncoderFinished,
+    InvalidSessionState(String),
+    UnsupportedCodec(String),
+    InvalidConfiguration { field: &'static str, reason: String },
+    Interrupted,
+    NotSeekable,
+    InvalidSessionDescription(String),
+    SignalingFailed(String),
+    Io(std::sync::Arc<std::io::Error>),
     BackendError(FfmpegError),
 }
 
@@ -32,6 +42,16 @@ impl std::error::Error for Error {
             Error::InvalidResizeParameters => None,
             Error::UninitializedCodec => None,
             Error::UnsupportedCodecHardwareAccelerationDeviceType => None,
+            Error::InvalidEncoderOption(_) => None,
+            Error::EncoderFinished => None,
+            Error::InvalidSessionState(_) => None,
+            Error::UnsupportedCodec(_) => None,
+            Error::InvalidConfiguration { .. } => None,
+            Error::Interrupted => None,
+            Error::NotSeekable => None,
+            Error::InvalidSessionDescription(_) => None,
+            Error::SignalingFailed(_) => None,
+            Error::Io(ref internal) => Some(internal.as_ref()),
             Error::BackendError(ref internal) => Some(internal),
         }
     }
@@ -64,13 +84,47 @@ impl std::fmt::Display for Error {
             Error::UnsupportedCodecHardwareAccelerationDeviceType => {
                 write!(f, "codec does not supported hardware acceleration device")
             }
+            Error::InvalidEncoderOption(ref reason) => {
+                write!(f, "invalid encoder option: {reason}")
+            }
+            Error::EncoderFinished => {
+                write!(
+                    f,
+                    "cannot encode frames after the encoder has been finished"
+                )
+            }
+            Error::InvalidSessionState(ref reason) => {
+                write!(f, "invalid session state: {reason}")
+            }
+            Error::UnsupportedCodec(ref codec) => {
+                write!(f, "codec is not supported for this operation: {codec}")
+            }
+            Error::InvalidConfiguration { field, ref reason } => {
+                write!(f, "invalid configuration of {field}: {reason}")
+            }
+            Error::Interrupted => write!(f, "blocking operation was interrupted"),
+            Error::NotSeekable => write!(f, "source is not seekable"),
+            Error::InvalidSessionDescription(ref reason) => {
+                write!(f, "invalid session description: {reason}")
+            }
+            Error::SignalingFailed(ref reason) => write!(f, "signaling failed: {reason}"),
+            Error::Io(ref internal) => internal.fmt(f),
             Error::BackendError(ref internal) => internal.fmt(f),
         }
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(internal: std::io::Error) -> Error {
+        Error::Io(std::sync::Arc::new(internal))
+    }
+}
+
 impl From<FfmpegError> for Error {
     fn from(internal: FfmpegError) -> Error {
-        Error::BackendError(internal)
+        match internal {
+            FfmpegError::Exit => Error::Interrupted,
+            internal => Error::BackendError(internal),
+        }
     }
 }
@@ -41,6 +41,30 @@ pub fn extract_parameter_sets_h264(extradata_bytes: &[u8]) -> Result<(Sps<'_>, P
     }
 }
 
+/// Extract the parameter sets (VPS, SPS and PPS NAL units, in that order of appearance) from an
+/// H.265 stream `extradata` bytes (as provided by the `libavcodec` backend), which are either in
+/// hvcC or Annex B format.
+///
+/// # Arguments
+///
+/// * `extradata_bytes` - Borrowed slice pointing to extradata bytes.
+///
+/// # Return value
+///
+/// Parameter set NAL units or error.
+pub fn extract_parameter_sets_h265(extradata_bytes: &[u8]) -> Result<Vec<&[u8]>> {
+    let parameter_sets = match extradata_bytes.first() {
+        Some(0x00) => extract_parameter_sets_from_extradata_h265_annexb(extradata_bytes),
+        Some(0x01) => extract_parameter_sets_from_extradata_h265_hvcc(extradata_bytes)?,
+        _ => return Err(Error::InvalidExtraData),
+    };
+    if parameter_sets.is_empty() {
+        Err(Error::InvalidExtraData)
+    } else {
+        Ok(parameter_sets)
+    }
+}
+
 /// Extract parameter sets from H264 stream in AVCC format. The AVCC format is most commonly used in
 /// combination with the MP4 container format or any other format where it makes sense to include
 /// the parameter sets in the beginning of the stream (non-live formats).
@@ -112,6 +136,51 @@ fn extract_parameter_sets_from_extradata_h264_avc_annexb(
     }
 }
 
+/// Extract parameter sets from H.265 stream in hvcC format, which holds arrays of NAL units after a
+/// fixed 23 byte header.
+fn extract_parameter_sets_from_extradata_h265_hvcc(bytes: &[u8]) -> Result<Vec<&[u8]>> {
+    let num_arrays = *bytes.get(22).ok_or(Error::InvalidExtraData)?;
+    let mut parameter_sets = Vec::new();
+    let mut p = 23;
+    for _ in 0..num_arrays {
+        let array_header = bytes.get(p..p + 3).ok_or(Error::InvalidExtraData)?;
+        let num_nalus = u16::from_be_bytes([array_header[1], array_header[2]]);
+        p += 3;
+        for _ in 0..num_nalus {
+            let size = bytes.get(p..p + 2).ok_or(Error::InvalidExtraData)?;
+            let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+            let nal = bytes
+                .get(p + 2..p + 2 + size)
+                .ok_or(Error::InvalidExtraData)?;
+            parameter_sets.push(nal);
+            p += 2 + size;
+        }
+    }
+    Ok(parameter_sets)
+}
+
+/// Extract parameter sets from H.265 stream in Annex B format.
+fn extract_parameter_sets_from_extradata_h265_annexb(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut index_current = find_avc_start_code(bytes, 0).map(|(_, index_next)| index_next);
+    let mut parameter_sets = Vec::new();
+    while let Some(index) = index_current {
+        let (end, index_next) = match find_avc_start_code(bytes, index) {
+            Some((end, index_next)) => (end, Some(index_next)),
+            None => (bytes.len(), None),
+        };
+        let nal = &bytes[index..end];
+        // VPS, SPS and PPS.
+        if nal
+            .first()
+            .is_some_and(|header| (32..=34).contains(&((header >> 1) & 0x3f)))
+        {
+            parameter_sets.push(nal);
+        }
+        index_current = index_next;
+    }
+    parameter_sets
+}
+
 /// The H.264 AVC spec defines a NAL start code to be either two zero bytes followed by a 0x01-byte
 /// (allowed in Annex B format) or three zeros bytes followed by a 0x01-bytes (allowed in AVCC and
 /// Annex B formats). This function will find the AVC start code (both formats) and return its
@@ -128,7 +197,7 @@ fn extract_parameter_sets_from_extradata_h264_avc_annexb(
 /// and `end` is returned, where `start` is the index of the first byte of the start code, and `end`
 /// is the index of the first byte after the start code. If no start code was found, it returns
 /// `None`.
-fn find_avc_start_code(bytes: &[u8], offset: usize) -> Option<(usize, usize)> {
+pub(crate) fn find_avc_start_code(bytes: &[u8], offset: usize) -> Option<(usize, usize)> {
     let part = &bytes[offset..];
     if part.len() >= 3 {
         for i in 0..(part.len() - 3) {
@@ -1,14 +1,23 @@
 extern crate ffmpeg_next as ffmpeg;
 
 #[cfg(feature = "ndarray")]
-use ndarray::Array3;
+use ndarray::{Array2, Array3};
 
 use ffmpeg::codec::codec::Codec;
 use ffmpeg::codec::context::Context;
+use ffmpeg::codec::subtitle::Bitmap;
+use ffmpeg::codec::{Id, Parameters};
 use ffmpeg::encoder::video::Video;
-use ffmpeg::format::context::Output;
+use ffmpeg::format::context::{Input, Output};
+use ffmpeg::software::resampling::context::Context as Resampler;
+use ffmpeg::software::scaling::context::Context as Scaler;
+use ffmpeg::util::color::{Primaries, Range, Space, TransferCharacteristic};
 use ffmpeg::util::frame::video::Video as Frame;
-use ffmpeg::{Error, Rational};
+use ffmpeg::{Dictionary, Error, Rational};
+
+use std::sync::atomic::AtomicBool;
+
+use crate::core::codec::RateControlParameters;
 
 #[cfg(feature = "ndarray")]
 use ffmpeg::util::format::Pixel;
@@ -213,240 +222,1833 @@ pub fn output_raw_packetized_buf_end(output: &mut Output) {
     }
 }
 
-/// Flush the output. This can be useful in some circumstances.options
+/// Type of the callback that seeks in a custom output sink.
+pub type OutputSinkSeek = extern "C" fn(*mut std::ffi::c_void, i64, std::ffi::c_int) -> i64;
+
+/// This function initializes an IO context for the `Output` that passes all writes on to a Rust
+/// [`std::io::Write`] sink. If `seek` is `None`, the output is marked as not seekable, so only
+/// streamable formats (or formats in streaming mode, such as fragmented MP4) can be written.
 ///
-/// For example: It is used to flush fragments when outputting fragmented mp4 packets in combination
-/// with the `frag_custom` option.
+/// The callee must invoke `output_sink_end` before `sink` is dropped or moved, and before the
+/// output is dropped.
 ///
 /// # Arguments
 ///
-/// * `output` - Output context to flush.
-pub fn flush_output(output: &mut Output) -> Result<(), Error> {
+/// * `output` - Output context to attach sink to.
+/// * `sink` - Sink to write to. Must live until `output_sink_end`.
+/// * `seek` - Seek callback for `S`, obtained through `output_sink_seek_callback`.
+/// * `buffer_size` - Size of the IO buffer.
+pub fn output_sink_start<S: std::io::Write>(
+    output: &mut Output,
+    sink: &mut S,
+    seek: Option<OutputSinkSeek>,
+    buffer_size: usize,
+) {
     unsafe {
-        match av_write_frame(output.as_mut_ptr(), std::ptr::null_mut()) {
-            0 => Ok(()),
-            1 => Ok(()),
-            e => Err(Error::from(e)),
+        let buffer = av_malloc(buffer_size) as *mut u8;
+
+        let io: *mut AVIOContext = avio_alloc_context(
+            buffer,
+            buffer_size.try_into().unwrap(),
+            // Set stream to WRITE.
+            1,
+            // Pass on a pointer *UNSAFE* to the sink, assuming the sink will live long enough.
+            sink as *mut S as *mut std::ffi::c_void,
+            // No `read_packet`.
+            None,
+            // See `output_raw_packetized_buf_start` for why this is transmuted.
+            #[allow(clippy::missing_transmute_annotations)]
+            Some(std::mem::transmute::<*const (), _>(
+                output_sink_write_callback::<S> as _,
+            )),
+            seek.map(|seek| seek as _),
+        );
+        if seek.is_none() {
+            (*io).seekable = 0;
         }
+
+        (*output.as_mut_ptr()).pb = io;
     }
 }
 
-/// Initialize a new codec context using a specific codec.
+/// Get the seek callback for a seekable sink of type `S`.
+pub fn output_sink_seek_callback<S: std::io::Write + std::io::Seek>() -> OutputSinkSeek {
+    io_seek_callback::<S>
+}
+
+/// This function flushes and cleans up the IO context created by `output_sink_start`. It is safe
+/// to call this function more than once.
 ///
 /// # Arguments
 ///
-/// * `codec` - Codec to initialize with.
-pub fn codec_context_as(codec: &Codec) -> Result<Context, Error> {
+/// * `output` - Output context to detach sink from.
+pub fn output_sink_end(output: &mut Output) {
     unsafe {
-        let context_ptr = ffmpeg::ffi::avcodec_alloc_context3(codec.as_ptr());
-        if !context_ptr.is_null() {
-            Ok(Context::wrap(context_ptr, None))
-        } else {
-            Err(Error::Unknown)
+        let mut output_pb = (*output.as_mut_ptr()).pb;
+        if output_pb.is_null() {
+            return;
         }
+
+        avio_flush(output_pb);
+        free_io_context(output_pb);
+
+        // Reset the `pb` field or `avformat_close` will try to free it!
+        ((*output.as_mut_ptr()).pb) = std::ptr::null_mut::<AVIOContext>();
     }
 }
 
-/// Set the `time_base` field of a decoder. (Not natively supported in the public API.)
+/// Type of the callback that reads from a custom input source.
+pub type InputSourceRead = extern "C" fn(*mut std::ffi::c_void, *mut u8, i32) -> i32;
+
+/// Type of the callback that seeks in a custom input source.
+pub type InputSourceSeek = extern "C" fn(*mut std::ffi::c_void, i64, std::ffi::c_int) -> i64;
+
+/// Open an input that reads from a Rust [`std::io::Read`] source through a custom IO context. If
+/// `seek` is `None`, the input is marked as not seekable, so the demuxer only reads forward.
+///
+/// The callee must invoke `input_source_end` before the source is dropped or moved, and before the
+/// input is dropped.
 ///
 /// # Arguments
 ///
-/// * `decoder_context` - Decoder context.
-/// * `time_base` - Time base to assign.
-pub fn set_decoder_context_time_base(decoder_context: &mut Context, time_base: Rational) {
+/// * `source` - Pointer to the source to read from. Must stay valid until `input_source_end`.
+/// * `read` - Read callback for the source, obtained through `input_source_read_callback`.
+/// * `seek` - Seek callback for the source, obtained through `input_source_seek_callback`.
+/// * `format` - Name of the demuxer to use, or `None` to probe the source.
+/// * `options` - Options to pass on to the demuxer.
+/// * `interrupt` - Flag that interrupts blocking calls when set. Must outlive the input.
+/// * `buffer_size` - Size of the IO buffer.
+pub fn input_source(
+    source: *mut std::ffi::c_void,
+    read: InputSourceRead,
+    seek: Option<InputSourceSeek>,
+    format: Option<&str>,
+    options: Dictionary,
+    interrupt: Option<&AtomicBool>,
+    buffer_size: usize,
+) -> Result<Input, Error> {
     unsafe {
-        (*decoder_context.as_mut_ptr()).time_base = time_base.into();
+        let input_format = match format {
+            Some(format) => {
+                let format = std::ffi::CString::new(format).unwrap();
+                let input_format = av_find_input_format(format.as_ptr());
+                if input_format.is_null() {
+                    return Err(Error::DemuxerNotFound);
+                }
+                input_format
+            }
+            None => std::ptr::null(),
+        };
+
+        let buffer = av_malloc(buffer_size) as *mut u8;
+        let io: *mut AVIOContext = avio_alloc_context(
+            buffer,
+            buffer_size.try_into().unwrap(),
+            // Set stream to READ.
+            0,
+            // Pass on a pointer *UNSAFE* to the source, assuming the source will live long enough.
+            source,
+            Some(read as _),
+            // No `write_packet`.
+            None,
+            seek.map(|seek| seek as _),
+        );
+        if seek.is_none() {
+            (*io).seekable = 0;
+        }
+
+        let mut input_ptr = avformat_alloc_context();
+        (*input_ptr).pb = io;
+        // Tell `libavformat` that it does not own the IO context.
+        (*input_ptr).flags |= AVFMT_FLAG_CUSTOM_IO;
+        if let Some(interrupt) = interrupt {
+            set_interrupt_callback(input_ptr, interrupt);
+        }
+
+        let mut opts = options.disown();
+        let res = avformat_open_input(&mut input_ptr, std::ptr::null(), input_format, &mut opts);
+        Dictionary::own(opts);
+
+        match res {
+            0 => match avformat_find_stream_info(input_ptr, std::ptr::null_mut()) {
+                r if r >= 0 => Ok(Input::wrap(input_ptr)),
+                e => {
+                    avformat_close_input(&mut input_ptr);
+                    free_io_context(io);
+                    Err(Error::from(e))
+                }
+            },
+            // `avformat_open_input` frees the format context on failure, but not the IO context.
+            e => {
+                free_io_context(io);
+                Err(Error::from(e))
+            }
+        }
     }
 }
 
-/// Get the `time_base` field of an encoder. (Not natively supported in the public API.)
+/// Open an input like `ffmpeg::format::input_with_dictionary`, but with an interrupt callback
+/// that is installed before the input is opened, so that opening can be interrupted as well.
 ///
 /// # Arguments
 ///
-/// * `encoder` - Encoder to get `time_base` of.
-pub fn get_encoder_time_base(encoder: &Video) -> Rational {
-    unsafe { (*encoder.0.as_ptr()).time_base.into() }
+/// * `path` - Path or URL to open.
+/// * `options` - Options to pass on to the input.
+/// * `interrupt` - Flag that interrupts blocking calls when set. Must outlive the input.
+pub fn input_with_interrupt(
+    path: &std::path::Path,
+    options: Dictionary,
+    interrupt: &AtomicBool,
+) -> Result<Input, Error> {
+    let path = path_to_cstring(path)?;
+    unsafe {
+        let mut input_ptr = avformat_alloc_context();
+        set_interrupt_callback(input_ptr, interrupt);
+
+        let mut opts = options.disown();
+        let res = avformat_open_input(
+            &mut input_ptr,
+            path.as_ptr(),
+            std::ptr::null_mut(),
+            &mut opts,
+        );
+        Dictionary::own(opts);
+
+        match res {
+            0 => match avformat_find_stream_info(input_ptr, std::ptr::null_mut()) {
+                r if r >= 0 => Ok(Input::wrap(input_ptr)),
+                e => {
+                    avformat_close_input(&mut input_ptr);
+                    Err(Error::from(e))
+                }
+            },
+            // `avformat_open_input` frees the format context on failure.
+            e => Err(Error::from(e)),
+        }
+    }
 }
 
-/// Copy frame properties from `src` to `dst`.
+/// Open an output like `ffmpeg::format::output_as_with`, but with an interrupt callback that is
+/// passed on to the IO context, so that opening and writing can be interrupted.
 ///
 /// # Arguments
 ///
-/// * `src` - Frame to get properties from.
-/// * `dst` - Frame to copy properties to.
-pub fn copy_frame_props(src: &Frame, dst: &mut Frame) {
+/// * `path` - Path or URL to open.
+/// * `format` - Container format, or `None` to derive it from the path.
+/// * `options` - Options to pass on to the IO context.
+/// * `interrupt` - Flag that interrupts blocking calls when set. Must outlive the output.
+pub fn output_with_interrupt(
+    path: &std::path::Path,
+    format: Option<&str>,
+    options: Dictionary,
+    interrupt: &AtomicBool,
+) -> Result<Output, Error> {
     unsafe {
-        av_frame_copy_props(dst.as_mut_ptr(), src.as_ptr());
+        let path = path_to_cstring(path)?;
+        let format = format
+            .map(|format| std::ffi::CString::new(format).map_err(|_| Error::InvalidData))
+            .transpose()?;
+        let mut output_ptr = std::ptr::null_mut();
+        match avformat_alloc_output_context2(
+            &mut output_ptr,
+            std::ptr::null_mut(),
+            format.as_ref().map_or(std::ptr::null(), |format| format.as_ptr()),
+            path.as_ptr(),
+        ) {
+            0 => {}
+            e => return Err(Error::from(e)),
+        }
+        set_interrupt_callback(output_ptr, interrupt);
+
+        let mut opts = options.disown();
+        let res = if (*(*output_ptr).oformat).flags & AVFMT_NOFILE == 0 {
+            avio_open2(
+                &mut (*output_ptr).pb,
+                path.as_ptr(),
+                AVIO_FLAG_WRITE,
+                &(*output_ptr).interrupt_callback,
+                &mut opts,
+            )
+        } else {
+            0
+        };
+        Dictionary::own(opts);
+
+        if res < 0 {
+            avformat_free_context(output_ptr);
+            return Err(Error::from(res));
+        }
+        Ok(Output::wrap(output_ptr))
     }
 }
 
-/// A frame array is the `ndarray` version of `AVFrame`. It is 3-dimensional array with dims `(H, W,
-/// C)` and type byte.
-#[cfg(feature = "ndarray")]
-pub type FrameArray = Array3<u8>;
-
-/// Converts an `ndarray` to an RGB24 video `AVFrame` for ffmpeg.
+/// Convert a path or URL to a C string. Paths that are not valid UTF-8 or contain a nul byte
+/// cannot be passed on to ffmpeg.
 ///
 /// # Arguments
 ///
-/// * `frame_array` - Video frame to convert. The frame format must be `(H, W, C)`.
+/// * `path` - Path or URL to convert.
+fn path_to_cstring(path: &std::path::Path) -> Result<std::ffi::CString, Error> {
+    let path = path.to_str().ok_or(Error::InvalidData)?;
+    std::ffi::CString::new(path).map_err(|_| Error::InvalidData)
+}
+
+/// Install an interrupt callback on a format context that polls `interrupt`.
 ///
-/// # Return value
+/// # Arguments
 ///
-/// An ffmpeg-native `AvFrame`.
-#[cfg(feature = "ndarray")]
-pub fn convert_ndarray_to_frame_rgb24(frame_array: &FrameArray) -> Result<Frame, Error> {
-    unsafe {
-        assert!(frame_array.is_standard_layout());
+/// * `context` - Format context to install callback on.
+/// * `interrupt` - Flag that interrupts blocking calls when set. Must outlive the context.
+unsafe fn set_interrupt_callback(context: *mut AVFormatContext, interrupt: &AtomicBool) {
+    (*context).interrupt_callback = AVIOInterruptCB {
+        callback: Some(interrupt_callback),
+        opaque: interrupt as *const AtomicBool as *mut std::ffi::c_void,
+    };
+}
 
-        let (frame_height, frame_width, _) = frame_array.dim();
+/// Get a random number from the best source of randomness the backend has access to, such as
+/// `/dev/urandom`, e.g. for identifiers that must not be guessable.
+pub fn random_seed() -> u32 {
+    unsafe { av_get_random_seed() }
+}
 
-        // Temporary frame structure to place correctly formatted data and linesize stuff in, which
-        // we'll copy later.
-        let mut frame_tmp = Frame::empty();
-        let frame_tmp_ptr = frame_tmp.as_mut_ptr();
+/// Get the read callback for a source of type `S`.
+pub fn input_source_read_callback<S: std::io::Read>() -> InputSourceRead {
+    io_read_callback::<S>
+}
 
-        // This does not copy the data, but it sets the `frame_tmp` data and linesize pointers
-        // correctly.
-        let bytes_copied = av_image_fill_arrays(
-            (*frame_tmp_ptr).data.as_ptr() as *mut *mut u8,
-            (*frame_tmp_ptr).linesize.as_ptr() as *mut i32,
-            frame_array.as_ptr(),
-            AVPixelFormat::AV_PIX_FMT_RGB24,
-            frame_width as i32,
-            frame_height as i32,
-            1,
-        );
+/// Get the seek callback for a seekable source of type `S`.
+pub fn input_source_seek_callback<S: std::io::Read + std::io::Seek>() -> InputSourceSeek {
+    io_seek_callback::<S>
+}
 
-        if bytes_copied != frame_array.len() as i32 {
-            return Err(Error::from(bytes_copied));
+/// This function cleans up the IO context created by `input_source`. It is safe to call this
+/// function more than once.
+///
+/// # Arguments
+///
+/// * `input` - Input context to detach source from.
+pub fn input_source_end(input: &mut Input) {
+    unsafe {
+        let input_pb = (*input.as_mut_ptr()).pb;
+        if input_pb.is_null() {
+            return;
         }
 
-        let mut frame = Frame::new(Pixel::RGB24, frame_width as u32, frame_height as u32);
-        let frame_ptr = frame.as_mut_ptr();
-
-        // Do the actual copying.
-        av_image_copy(
-            (*frame_ptr).data.as_ptr() as *mut *mut u8,
-            (*frame_ptr).linesize.as_ptr() as *mut i32,
-            (*frame_tmp_ptr).data.as_ptr() as *mut *const u8,
-            (*frame_tmp_ptr).linesize.as_ptr(),
-            AVPixelFormat::AV_PIX_FMT_RGB24,
-            frame_width as i32,
-            frame_height as i32,
-        );
+        free_io_context(input_pb);
 
-        Ok(frame)
+        // Reset the `pb` field, so that nothing touches the freed IO context anymore.
+        ((*input.as_mut_ptr()).pb) = std::ptr::null_mut::<AVIOContext>();
     }
 }
 
-/// Converts an RGB24 video `AVFrame` produced by ffmpeg to an `ndarray`.
+/// Free a custom IO context and its buffer.
 ///
 /// # Arguments
 ///
-/// * `frame` - Video frame to convert.
+/// * `io` - IO context to free.
+unsafe fn free_io_context(mut io: *mut AVIOContext) {
+    // The buffer may have been reallocated by `libavformat`, so free whatever it points to now.
+    av_free((*io).buffer as *mut std::ffi::c_void);
+    avio_context_free(&mut io);
+}
+
+/// Flush the output. This can be useful in some circumstances.options
 ///
-/// # Return value
+/// For example: It is used to flush fragments when outputting fragmented mp4 packets in combination
+/// with the `frag_custom` option.
 ///
-/// A three-dimensional `ndarray` with dimensions `(H, W, C)` and type byte.
-#[cfg(feature = "ndarray")]
-pub fn convert_frame_to_ndarray_rgb24(frame: &mut Frame) -> Result<FrameArray, Error> {
+/// # Arguments
+///
+/// * `output` - Output context to flush.
+pub fn flush_output(output: &mut Output) -> Result<(), Error> {
     unsafe {
-        let frame_ptr = frame.as_mut_ptr();
-        let frame_width: i32 = (*frame_ptr).width;
-        let frame_height: i32 = (*frame_ptr).height;
-        let frame_format =
-            std::mem::transmute::<std::ffi::c_int, AVPixelFormat>((*frame_ptr).format);
-        assert_eq!(frame_format, AVPixelFormat::AV_PIX_FMT_RGB24);
-
-        let mut frame_array =
-            FrameArray::default((frame_height as usize, frame_width as usize, 3_usize));
-
-        let bytes_copied = av_image_copy_to_buffer(
-            frame_array.as_mut_ptr(),
-            frame_array.len() as i32,
-            (*frame_ptr).data.as_ptr() as *const *const u8,
-            (*frame_ptr).linesize.as_ptr(),
-            frame_format,
-            frame_width,
-            frame_height,
-            1,
-        );
-
-        if bytes_copied == frame_array.len() as i32 {
-            Ok(frame_array)
-        } else {
-            Err(Error::from(bytes_copied))
+        match av_write_frame(output.as_mut_ptr(), std::ptr::null_mut()) {
+            0 => Ok(()),
+            1 => Ok(()),
+            e => Err(Error::from(e)),
         }
     }
 }
 
-/// Retrieve a reference to the extradata bytes in codec parameters of an output stream.
+/// Extend the duration of an input stream, and of the input as a whole, so that it includes a
+/// timestamp. Used for files that grow while they are read.
 ///
 /// # Arguments
 ///
-/// * `output` - Output that contains stream to get extradata from.
-/// * `stream_index` - Index of stream.
-pub fn extradata(output: &Output, stream_index: usize) -> Result<&[u8], Error> {
-    let parameters = output
-        .stream(stream_index)
-        .map(|stream| stream.parameters())
-        .ok_or(Error::StreamNotFound)?;
-
-    Ok(unsafe {
-        std::slice::from_raw_parts(
-            (*parameters.as_ptr()).extradata,
-            (*parameters.as_ptr()).extradata_size as usize,
-        )
-    })
+/// * `input` - Input context.
+/// * `stream_index` - Index of the stream.
+/// * `end` - Timestamp in the time base of the stream.
+pub fn extend_input_duration(input: &mut Input, stream_index: usize, end: i64) {
+    unsafe {
+        let context = input.as_mut_ptr();
+        if stream_index >= (*context).nb_streams as usize {
+            return;
+        }
+        let stream = *(*context).streams.add(stream_index);
+        let start_time = match (*stream).start_time {
+            AV_NOPTS_VALUE => 0,
+            start_time => start_time,
+        };
+        // Unknown durations are `AV_NOPTS_VALUE`, which is smaller than any duration.
+        let duration = end - start_time;
+        if duration > (*stream).duration {
+            (*stream).duration = duration;
+        }
+        let duration = av_rescale_q(duration, (*stream).time_base, AV_TIME_BASE_Q);
+        if duration > (*context).duration {
+            (*context).duration = duration;
+        }
+    }
 }
 
-/// Whether or not the output format context is configured to use H.264 packetization mode 0.
+/// Whether or not the IO context of an input supports seeking. Inputs without an IO context, such
+/// as HLS playlists, manage seeking in the demuxer and are considered seekable.
 ///
 /// # Arguments
 ///
-/// * `output` - Output format context.
-pub fn rtp_h264_mode_0(output: &Output) -> bool {
+/// * `input` - Input context.
+pub fn input_is_seekable(input: &Input) -> bool {
     unsafe {
-        av_opt_flag_is_set(
-            (*output.as_ptr()).priv_data,
-            "rtpflags".as_ptr() as *const std::ffi::c_char,
-            "h264_mode0".as_ptr() as *const std::ffi::c_char,
-        ) != 0
+        let pb = (*input.as_ptr()).pb;
+        pb.is_null() || ((*pb).seekable & AVIO_SEEKABLE_NORMAL as i32) != 0
     }
 }
 
-/// Get the current sequence number and timestamp of the RTP muxer.
+/// Flush the IO context of an output and report any error that occurred while writing to it, such
+/// as a full disk. Outputs without an IO context are left alone.
 ///
-/// Note: This method is only safe to use on RTP output formats.
-pub fn rtp_seq_and_timestamp(output: &Output) -> (u16, u32) {
+/// # Arguments
+///
+/// * `output` - Output context to flush.
+pub fn flush_output_io(output: &mut Output) -> Result<(), Error> {
     unsafe {
-        let rtp_mux_context = &*((*output.as_ptr()).priv_data as *const RTPMuxContext);
-        (rtp_mux_context.seq, rtp_mux_context.timestamp)
+        let pb = (*output.as_mut_ptr()).pb;
+        if pb.is_null() {
+            return Ok(());
+        }
+        avio_flush(pb);
+        match (*pb).error {
+            0 => Ok(()),
+            e => Err(Error::from(e)),
+        }
     }
 }
 
-/// Create SDP file contents for the given output. Useful for RTP muxers.
-///
-/// A media entry will be created for each stream in the output. This function will take care of all
-/// details, such as setting the correct media attributes needed by any SDP consumers.
+/// Get the number of bytes written to the IO context of an output so far, including bytes that are
+/// still buffered. Outputs without an IO context report zero.
 ///
 /// # Arguments
 ///
-/// * `output` - Output to generate SDP file for.
+/// * `output` - Output context.
+pub fn output_position(output: &Output) -> u64 {
+    unsafe {
+        let pb = (*output.as_ptr()).pb;
+        if pb.is_null() {
+            return 0;
+        }
+        // Equivalent to `avio_tell`, which is an inline function and therefore not exported.
+        let buffered = (*pb).buf_ptr.offset_from((*pb).buffer);
+        ((*pb).pos + buffered as i64).max(0) as u64
+    }
+}
+
+/// Initialize a new codec context using a specific codec.
 ///
-/// # Return value
+/// # Arguments
 ///
-/// A string with the SDP file contents.
-pub fn sdp(output: &Output) -> Result<String, Error> {
-    const BUF_SIZE: i32 = 4096;
-
+/// * `codec` - Codec to initialize with.
+pub fn codec_context_as(codec: &Codec) -> Result<Context, Error> {
     unsafe {
-        let mut buf: [std::ffi::c_char; BUF_SIZE as usize] = [0; BUF_SIZE as usize];
-        let buf_ptr = &mut buf as *mut std::ffi::c_char;
-        let mut output_format_context = output.as_ptr();
-        let output_format_context_ptr = &mut output_format_context as *mut *const AVFormatContext;
-        // WARNING! Casting from const ptr to mutable ptr here!
+        let context_ptr = ffmpeg::ffi::avcodec_alloc_context3(codec.as_ptr());
+        if !context_ptr.is_null() {
+            Ok(Context::wrap(context_ptr, None))
+        } else {
+            Err(Error::Unknown)
+        }
+    }
+}
+
+/// Set the `time_base` field of a decoder. (Not natively supported in the public API.)
+///
+/// # Arguments
+///
+/// * `decoder_context` - Decoder context.
+/// * `time_base` - Time base to assign.
+pub fn set_decoder_context_time_base(decoder_context: &mut Context, time_base: Rational) {
+    unsafe {
+        (*decoder_context.as_mut_ptr()).time_base = time_base.into();
+    }
+}
+
+/// Set the number of threads and the thread type of a codec context. (The public API always
+/// overwrites both.)
+///
+/// # Arguments
+///
+/// * `context` - Codec context that has not been opened yet.
+/// * `thread_count` - Number of threads, or `None` to keep the current number.
+/// * `thread_type` - Thread type flags, or `None` to keep the current flags.
+pub fn set_codec_context_threads(
+    context: &mut Context,
+    thread_count: Option<usize>,
+    thread_type: Option<i32>,
+) {
+    unsafe {
+        let context = context.as_mut_ptr();
+        if let Some(thread_count) = thread_count {
+            (*context).thread_count = thread_count as i32;
+        }
+        if let Some(thread_type) = thread_type {
+            (*context).thread_type = thread_type;
+        }
+    }
+}
+
+/// Make a codec context output frames as early as possible, and use slice threading instead of
+/// frame threading, which adds one frame of latency per thread. Must be called before the codec is
+/// opened.
+///
+/// # Arguments
+///
+/// * `context` - Codec context.
+pub fn set_codec_context_low_delay(context: &mut Context) {
+    unsafe {
+        let context = context.as_mut_ptr();
+        (*context).flags |= AV_CODEC_FLAG_LOW_DELAY as i32;
+        (*context).thread_type = FF_THREAD_SLICE;
+    }
+}
+
+/// Make a codec context export the motion vectors of decoded frames as side data. Must be called
+/// before the codec is opened.
+///
+/// # Arguments
+///
+/// * `context` - Codec context.
+pub fn set_codec_context_export_motion_vectors(context: &mut Context) {
+    unsafe {
+        (*context.as_mut_ptr()).export_side_data |= AV_CODEC_EXPORT_DATA_MVS as i32;
+    }
+}
+
+/// Pin the calling thread to a set of cores. Threads spawned by it afterwards inherit the
+/// affinity. Does nothing on platforms other than Linux.
+///
+/// # Arguments
+///
+/// * `cores` - Indices of the cores to run on.
+#[cfg(target_os = "linux")]
+pub fn set_current_thread_affinity(cores: &[usize]) -> std::io::Result<()> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &core in cores {
+            if core >= libc::CPU_SETSIZE as usize {
+                return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+            }
+            libc::CPU_SET(core, &mut set);
+        }
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_current_thread_affinity(_cores: &[usize]) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Set the niceness of the calling thread. Threads spawned by it afterwards inherit the
+/// niceness. Does nothing on platforms other than Linux.
+///
+/// # Arguments
+///
+/// * `niceness` - Niceness, from `-20` (highest priority) to `19` (lowest priority).
+#[cfg(target_os = "linux")]
+pub fn set_current_thread_niceness(niceness: i32) -> std::io::Result<()> {
+    unsafe {
+        let thread_id = libc::syscall(libc::SYS_gettid) as libc::id_t;
+        if libc::setpriority(libc::PRIO_PROCESS, thread_id, niceness) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_current_thread_niceness(_niceness: i32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Get the `time_base` field of an encoder. (Not natively supported in the public API.)
+///
+/// # Arguments
+///
+/// * `encoder` - Encoder to get `time_base` of.
+pub fn get_encoder_time_base(encoder: &Video) -> Rational {
+    unsafe { (*encoder.0.as_ptr()).time_base.into() }
+}
+
+/// Set the `stats_in` field of an encoder, used for the second pass of a two-pass encode.
+///
+/// The backend does not take ownership of the string, so the caller must keep `stats` alive until
+/// the encoder has been dropped.
+///
+/// # Arguments
+///
+/// * `encoder` - Encoder to set `stats_in` of.
+/// * `stats` - Statistics produced by the first pass.
+pub fn set_encoder_stats_in(encoder: &mut Video, stats: &std::ffi::CStr) {
+    unsafe {
+        (*encoder.as_mut_ptr()).stats_in = stats.as_ptr() as *mut std::ffi::c_char;
+    }
+}
+
+/// Check whether an output container can hold streams of a codec.
+///
+/// # Arguments
+///
+/// * `output` - Output to check the container format of.
+/// * `codec_id` - Codec of the stream.
+///
+/// # Return value
+///
+/// `None` if the container format does not know whether it supports the codec.
+pub fn output_format_supports_codec(output: &Output, codec_id: Id) -> Option<bool> {
+    let result = unsafe {
+        avformat_query_codec(
+            output.format().as_ptr(),
+            codec_id.into(),
+            ffmpeg::codec::Compliance::Normal.into(),
+        )
+    };
+    match result {
+        0 => Some(false),
+        1.. => Some(true),
+        _ => None,
+    }
+}
+
+/// Get the rate control parameters of an encoder.
+///
+/// # Arguments
+///
+/// * `encoder` - Encoder to get rate control parameters of.
+pub fn get_encoder_rate_control(encoder: &Video) -> RateControlParameters {
+    unsafe {
+        let context = encoder.as_ptr();
+        RateControlParameters {
+            bitrate: (*context).bit_rate,
+            min_bitrate: (*context).rc_min_rate,
+            max_bitrate: (*context).rc_max_rate,
+            buffer_size: (*context).rc_buffer_size as i64,
+        }
+    }
+}
+
+/// Set the rate control parameters of an encoder. Encoders that support reconfiguration pick up
+/// the new parameters with the next frame.
+///
+/// # Arguments
+///
+/// * `encoder` - Encoder to set rate control parameters of.
+/// * `parameters` - Rate control parameters.
+pub fn set_encoder_rate_control(encoder: &mut Video, parameters: RateControlParameters) {
+    unsafe {
+        let context = encoder.as_mut_ptr();
+        (*context).bit_rate = parameters.bitrate;
+        (*context).rc_min_rate = parameters.min_bitrate;
+        (*context).rc_max_rate = parameters.max_bitrate;
+        (*context).rc_buffer_size = parameters.buffer_size.min(i32::MAX as i64) as i32;
+    }
+}
+
+/// Get the `stats_out` field of an encoder, which contains the statistics produced by the first
+/// pass of a two-pass encode for the last packet.
+///
+/// # Arguments
+///
+/// * `encoder` - Encoder to get `stats_out` of.
+pub fn get_encoder_stats_out(encoder: &Video) -> Option<String> {
+    unsafe {
+        let stats_out = (*encoder.as_ptr()).stats_out;
+        if stats_out.is_null() {
+            None
+        } else {
+            Some(std::ffi::CStr::from_ptr(stats_out).to_string_lossy().to_string())
+        }
+    }
+}
+
+/// Set the color primaries and transfer characteristic of an encoder. (Not natively supported in
+/// the public API.)
+///
+/// # Arguments
+///
+/// * `encoder` - Encoder to set color properties of.
+/// * `primaries` - Color primaries.
+/// * `transfer` - Transfer characteristic.
+pub fn set_encoder_color_primaries_and_transfer(
+    encoder: &mut Video,
+    primaries: Primaries,
+    transfer: TransferCharacteristic,
+) {
+    unsafe {
+        (*encoder.as_mut_ptr()).color_primaries = primaries.into();
+        (*encoder.as_mut_ptr()).color_trc = transfer.into();
+    }
+}
+
+/// Set the YUV matrix and range that a scaler converts to, so that the converted pixels match
+/// the color tags of the encoder. By default, the scaler converts to BT.601 with limited range.
+/// Must be called again after the scaler has been reinitialized.
+///
+/// # Arguments
+///
+/// * `scaler` - Scaler to configure.
+/// * `space` - Matrix coefficients of the output. Unspecified keeps the default.
+/// * `range` - Range of the output. Only full (JPEG) range changes the default.
+pub fn set_scaler_output_colorspace(scaler: &mut Scaler, space: Space, range: Range) {
+    unsafe {
+        let context = scaler.as_mut_ptr();
+        let mut inv_table = std::ptr::null_mut();
+        let mut src_range = 0;
+        let mut table = std::ptr::null_mut();
+        let mut dst_range = 0;
+        let mut brightness = 0;
+        let mut contrast = 0;
+        let mut saturation = 0;
+        // Fails for scalers that do not convert between RGB and YUV, which have nothing to set.
+        if sws_getColorspaceDetails(
+            context,
+            &mut inv_table,
+            &mut src_range,
+            &mut table,
+            &mut dst_range,
+            &mut brightness,
+            &mut contrast,
+            &mut saturation,
+        ) < 0
+        {
+            return;
+        }
+        if space != Space::Unspecified {
+            table = sws_getCoefficients(AVColorSpace::from(space) as i32) as *mut i32;
+        }
+        let dst_range = i32::from(range == Range::JPEG);
+        sws_setColorspaceDetails(
+            context,
+            inv_table,
+            src_range,
+            table,
+            dst_range,
+            brightness,
+            contrast,
+            saturation,
+        );
+    }
+}
+
+/// Seek in an input with explicit seek flags. (The public API always passes no flags.)
+///
+/// # Arguments
+///
+/// * `input` - Input to seek in.
+/// * `min` - Smallest acceptable position.
+/// * `position` - Target position.
+/// * `max` - Largest acceptable position.
+/// * `flags` - Seek flags.
+pub fn seek_file(
+    input: &mut Input,
+    min: i64,
+    position: i64,
+    max: i64,
+    flags: i32,
+) -> Result<(), Error> {
+    unsafe {
+        match avformat_seek_file(input.as_mut_ptr(), -1, min, position, max, flags) {
+            s if s >= 0 => Ok(()),
+            e => Err(Error::from(e)),
+        }
+    }
+}
+
+/// Get the `sample_aspect_ratio` field of a stream, as set by the container.
+///
+/// # Arguments
+///
+/// * `input` - Input to get stream of.
+/// * `stream_index` - Index of stream.
+pub fn get_stream_sample_aspect_ratio(input: &Input, stream_index: usize) -> Option<Rational> {
+    input
+        .stream(stream_index)
+        .map(|stream| unsafe { (*stream.as_ptr()).sample_aspect_ratio.into() })
+}
+
+/// Set the `sample_aspect_ratio` field of an output stream, so that the container signals it as
+/// well as the codec.
+///
+/// # Arguments
+///
+/// * `output` - Output to set stream property of.
+/// * `stream_index` - Index of stream.
+/// * `sample_aspect_ratio` - Sample aspect ratio.
+pub fn set_stream_sample_aspect_ratio(
+    output: &mut Output,
+    stream_index: usize,
+    sample_aspect_ratio: Rational,
+) {
+    if let Some(mut stream) = output.stream_mut(stream_index) {
+        unsafe {
+            (*stream.as_mut_ptr()).sample_aspect_ratio = sample_aspect_ratio.into();
+        }
+    }
+}
+
+/// Clear the codec tag of an output stream if the container format does not accept it, so that
+/// the muxer picks a tag of its own. Codec tags copied from another container (e.g. Matroska to
+/// MP4) would otherwise make writing the header fail.
+///
+/// # Arguments
+///
+/// * `output` - Output to set stream property of.
+/// * `stream_index` - Index of stream.
+pub fn clear_incompatible_codec_tag(output: &mut Output, stream_index: usize) {
+    unsafe {
+        let codec_tags = (*(*output.as_ptr()).oformat).codec_tag;
+        if let Some(mut stream) = output.stream_mut(stream_index) {
+            let codec_parameters = (*stream.as_mut_ptr()).codecpar;
+            let codec_tag = (*codec_parameters).codec_tag;
+            let codec_id = (*codec_parameters).codec_id;
+            let mut codec_tag_supported = 0;
+            let keep = codec_tag == 0
+                || codec_tags.is_null()
+                || av_codec_get_id(codec_tags, codec_tag) == codec_id
+                || av_codec_get_tag2(codec_tags, codec_id, &mut codec_tag_supported) == 0;
+            if !keep {
+                (*codec_parameters).codec_tag = 0;
+            }
+        }
+    }
+}
+
+/// Set a metadata entry of an output container, such as the service name of an MPEG-TS output.
+///
+/// # Arguments
+///
+/// * `output` - Output to set metadata of.
+/// * `key` - Metadata key.
+/// * `value` - Metadata value.
+pub fn set_output_metadata(output: &mut Output, key: &str, value: &str) {
+    let mut metadata = output.metadata().to_owned();
+    metadata.set(key, value);
+    unsafe {
+        av_dict_free(&mut (*output.as_mut_ptr()).metadata);
+    }
+    output.set_metadata(metadata);
+}
+
+/// Set a metadata entry of a stream of an output container, such as its language.
+///
+/// # Arguments
+///
+/// * `output` - Output to set stream metadata of.
+/// * `stream_index` - Index of stream.
+/// * `key` - Metadata key.
+/// * `value` - Metadata value.
+pub fn set_output_stream_metadata(
+    output: &mut Output,
+    stream_index: usize,
+    key: &str,
+    value: &str,
+) {
+    if let Some(mut stream) = output.stream_mut(stream_index) {
+        let mut metadata = stream.metadata().to_owned();
+        metadata.set(key, value);
+        unsafe {
+            av_dict_free(&mut (*stream.as_mut_ptr()).metadata);
+        }
+        stream.set_metadata(metadata);
+    }
+}
+
+/// Set or clear disposition flags of a stream of an output container, such as `default` or
+/// `forced`.
+///
+/// # Arguments
+///
+/// * `output` - Output to set stream disposition of.
+/// * `stream_index` - Index of stream.
+/// * `disposition` - Flags to set or clear.
+/// * `enabled` - Whether to set or clear the flags.
+pub fn set_output_stream_disposition(
+    output: &mut Output,
+    stream_index: usize,
+    disposition: ffmpeg::format::stream::Disposition,
+    enabled: bool,
+) {
+    if let Some(mut stream) = output.stream_mut(stream_index) {
+        unsafe {
+            let stream = stream.as_mut_ptr();
+            if enabled {
+                (*stream).disposition |= disposition.bits();
+            } else {
+                (*stream).disposition &= !disposition.bits();
+            }
+        }
+    }
+}
+
+/// Add a stream with the `attached_pic` disposition to an output, to hold a cover image. The image
+/// must be written to the stream as its only packet.
+///
+/// # Arguments
+///
+/// * `output` - Output to add stream to.
+/// * `codec_id` - Codec of the image, e.g. MJPEG or PNG.
+/// * `width` - Width of the image.
+/// * `height` - Height of the image.
+///
+/// # Return value
+///
+/// Index of the new stream.
+pub fn add_attached_pic_stream(
+    output: &mut Output,
+    codec_id: Id,
+    width: u32,
+    height: u32,
+) -> Result<usize, Error> {
+    unsafe {
+        let stream = avformat_new_stream(output.as_mut_ptr(), std::ptr::null());
+        if stream.is_null() {
+            return Err(Error::Unknown);
+        }
+        let codec_parameters = (*stream).codecpar;
+        (*codec_parameters).codec_type = AVMediaType::AVMEDIA_TYPE_VIDEO;
+        (*codec_parameters).codec_id = codec_id.into();
+        (*codec_parameters).width = width as i32;
+        (*codec_parameters).height = height as i32;
+        (*stream).disposition |= AV_DISPOSITION_ATTACHED_PIC;
+        Ok((*stream).index as usize)
+    }
+}
+
+/// Add an attachment stream to an output, such as a Matroska attachment. The attached file is
+/// stored in the extradata of the stream.
+///
+/// # Arguments
+///
+/// * `output` - Output to add stream to.
+/// * `codec_id` - Codec of the attached file.
+/// * `data` - Contents of the attached file.
+/// * `filename` - Name of the attached file.
+/// * `mime_type` - MIME type of the attached file.
+///
+/// # Return value
+///
+/// Index of the new stream.
+pub fn add_attachment_stream(
+    output: &mut Output,
+    codec_id: Id,
+    data: &[u8],
+    filename: &str,
+    mime_type: &str,
+) -> Result<usize, Error> {
+    let stream_index = unsafe {
+        let stream = avformat_new_stream(output.as_mut_ptr(), std::ptr::null());
+        if stream.is_null() {
+            return Err(Error::Unknown);
+        }
+        let extradata =
+            av_mallocz(data.len() + AV_INPUT_BUFFER_PADDING_SIZE as usize) as *mut u8;
+        if extradata.is_null() {
+            return Err(Error::Unknown);
+        }
+        std::ptr::copy_nonoverlapping(data.as_ptr(), extradata, data.len());
+        let codec_parameters = (*stream).codecpar;
+        (*codec_parameters).codec_type = AVMediaType::AVMEDIA_TYPE_ATTACHMENT;
+        (*codec_parameters).codec_id = codec_id.into();
+        (*codec_parameters).extradata = extradata;
+        (*codec_parameters).extradata_size = data.len() as i32;
+        (*stream).index as usize
+    };
+    set_output_stream_metadata(output, stream_index, "filename", filename);
+    set_output_stream_metadata(output, stream_index, "mimetype", mime_type);
+    Ok(stream_index)
+}
+
+/// Get an upper bound on the number of samples a resampler outputs for the given number of input
+/// samples, including the samples it has buffered.
+///
+/// # Arguments
+///
+/// * `resampler` - Resampler to query.
+/// * `input_samples` - Number of input samples that will be sent.
+pub fn resampler_output_samples(resampler: &mut Resampler, input_samples: usize) -> usize {
+    unsafe { swr_get_out_samples(resampler.as_mut_ptr(), input_samples as i32).max(0) as usize }
+}
+
+/// Get the number of samples, at the output rate, that a resampler has buffered and not output
+/// yet. (Not accurately exposed by the public API, which rounds the delay to whole seconds first.)
+///
+/// # Arguments
+///
+/// * `resampler` - Resampler to query.
+pub fn resampler_delay(resampler: &Resampler) -> usize {
+    unsafe {
+        swr_get_delay(
+            resampler.as_ptr() as *mut _,
+            resampler.output().rate as i64,
+        )
+        .max(0) as usize
+    }
+}
+
+/// Get the size stored in codec parameters. (Not natively supported in the public API.)
+///
+/// # Arguments
+///
+/// * `parameters` - Codec parameters.
+pub fn codec_parameters_size(parameters: &Parameters) -> (u32, u32) {
+    unsafe {
+        let parameters = parameters.as_ptr();
+        (
+            (*parameters).width.max(0) as u32,
+            (*parameters).height.max(0) as u32,
+        )
+    }
+}
+
+/// Get the sample rate and number of channels from codec parameters of an audio stream.
+///
+/// # Arguments
+///
+/// * `parameters` - Codec parameters.
+pub fn codec_parameters_audio(parameters: &Parameters) -> (u32, u32) {
+    unsafe {
+        let parameters = parameters.as_ptr();
+        (
+            (*parameters).sample_rate.max(0) as u32,
+            (*parameters).ch_layout.nb_channels.max(0) as u32,
+        )
+    }
+}
+
+/// Get the bit rate from codec parameters, or zero if it is unknown.
+///
+/// # Arguments
+///
+/// * `parameters` - Codec parameters.
+pub fn codec_parameters_bit_rate(parameters: &Parameters) -> i64 {
+    unsafe { (*parameters.as_ptr()).bit_rate }
+}
+
+/// Get the programs of an input as pairs of the bit rate that the program advertises and the
+/// indices of the streams that belong to it. The HLS demuxer exposes each variant of a master
+/// playlist as a program, with its `BANDWIDTH` in the `variant_bitrate` metadata entry. Programs
+/// that do not advertise a bit rate report zero.
+///
+/// # Arguments
+///
+/// * `input` - Input context.
+pub fn input_programs(input: &Input) -> Vec<(u64, Vec<usize>)> {
+    let key = std::ffi::CString::new("variant_bitrate").unwrap();
+    unsafe {
+        let context = input.as_ptr();
+        (0..(*context).nb_programs as usize)
+            .map(|program_index| {
+                let program = *(*context).programs.add(program_index);
+                let entry = av_dict_get((*program).metadata, key.as_ptr(), std::ptr::null(), 0);
+                let bit_rate = if entry.is_null() {
+                    0
+                } else {
+                    std::ffi::CStr::from_ptr((*entry).value)
+                        .to_str()
+                        .ok()
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or(0)
+                };
+                let stream_indices = (0..(*program).nb_stream_indexes as usize)
+                    .map(|i| *(*program).stream_index.add(i) as usize)
+                    .collect();
+                (bit_rate, stream_indices)
+            })
+            .collect()
+    }
+}
+
+/// Set whether or not the demuxer discards all packets of an input stream. Demuxers of adaptive
+/// formats such as HLS stop downloading the media playlists of which all streams are discarded.
+///
+/// # Arguments
+///
+/// * `input` - Input context.
+/// * `stream_index` - Index of the stream.
+/// * `discard` - Whether to discard the stream.
+pub fn set_input_stream_discard(input: &mut Input, stream_index: usize, discard: bool) {
+    if let Some(mut stream) = input.stream_mut(stream_index) {
+        unsafe {
+            (*stream.as_mut_ptr()).discard = if discard {
+                AVDiscard::AVDISCARD_ALL
+            } else {
+                AVDiscard::AVDISCARD_DEFAULT
+            };
+        }
+    }
+}
+
+/// Whether or not a codec produces bitmap subtitles (as opposed to text subtitles).
+///
+/// # Arguments
+///
+/// * `id` - Codec id.
+pub fn codec_is_bitmap_subtitle(id: Id) -> bool {
+    unsafe {
+        let descriptor = avcodec_descriptor_get(id.into());
+        !descriptor.is_null() && ((*descriptor).props & AV_CODEC_PROP_BITMAP_SUB) != 0
+    }
+}
+
+/// Convert a palettized subtitle bitmap to RGBA pixels, row by row.
+///
+/// # Arguments
+///
+/// * `bitmap` - Subtitle bitmap.
+pub fn subtitle_bitmap_rgba(bitmap: &Bitmap) -> Vec<[u8; 4]> {
+    let width = bitmap.width() as usize;
+    let height = bitmap.height() as usize;
+    let mut pixels = Vec::with_capacity(width * height);
+    unsafe {
+        let rect = bitmap.as_ptr();
+        let indices = (*rect).data[0];
+        let palette = (*rect).data[1] as *const u32;
+        let num_colors = (*rect).nb_colors.max(0) as usize;
+        if indices.is_null() || palette.is_null() {
+            return Vec::new();
+        }
+        for y in 0..height {
+            let row = indices.offset(y as isize * (*rect).linesize[0] as isize);
+            for x in 0..width {
+                let index = *row.add(x) as usize;
+                // Palette entries are stored as native-endian ARGB.
+                let argb = if index < num_colors {
+                    palette.add(index).read_unaligned()
+                } else {
+                    0
+                };
+                pixels.push([
+                    (argb >> 16) as u8,
+                    (argb >> 8) as u8,
+                    argb as u8,
+                    (argb >> 24) as u8,
+                ]);
+            }
+        }
+    }
+    pixels
+}
+
+/// Mastering display metadata as defined by `libavutil/mastering_display_metadata.h`, which is
+/// not part of the generated bindings.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct AVMasteringDisplayMetadata {
+    pub display_primaries: [[AVRational; 2]; 3],
+    pub white_point: [AVRational; 2],
+    pub min_luminance: AVRational,
+    pub max_luminance: AVRational,
+    pub has_primaries: std::ffi::c_int,
+    pub has_luminance: std::ffi::c_int,
+}
+
+/// Content light level metadata as defined by `libavutil/mastering_display_metadata.h`, which is
+/// not part of the generated bindings.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct AVContentLightMetadata {
+    pub max_cll: std::ffi::c_uint,
+    pub max_fall: std::ffi::c_uint,
+}
+
+/// Leading fields of the Dolby Vision configuration record as defined by `libavutil/dovi_meta.h`,
+/// which is not part of the generated bindings.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct AVDOVIDecoderConfigurationRecord {
+    pub dv_version_major: u8,
+    pub dv_version_minor: u8,
+    pub dv_profile: u8,
+    pub dv_level: u8,
+    pub rpu_present_flag: u8,
+    pub el_present_flag: u8,
+    pub bl_present_flag: u8,
+    pub dv_bl_signal_compatibility_id: u8,
+}
+
+/// Get the mastering display metadata of a stream, if any.
+///
+/// # Arguments
+///
+/// * `input` - Input to get metadata from.
+/// * `stream_index` - Index of stream.
+pub fn stream_mastering_display_metadata(
+    input: &Input,
+    stream_index: usize,
+) -> Option<AVMasteringDisplayMetadata> {
+    unsafe {
+        stream_side_data(
+            input,
+            stream_index,
+            AVPacketSideDataType::AV_PKT_DATA_MASTERING_DISPLAY_METADATA,
+        )
+    }
+}
+
+/// Get the content light level metadata of a stream, if any.
+///
+/// # Arguments
+///
+/// * `input` - Input to get metadata from.
+/// * `stream_index` - Index of stream.
+pub fn stream_content_light_level(
+    input: &Input,
+    stream_index: usize,
+) -> Option<AVContentLightMetadata> {
+    unsafe {
+        stream_side_data(
+            input,
+            stream_index,
+            AVPacketSideDataType::AV_PKT_DATA_CONTENT_LIGHT_LEVEL,
+        )
+    }
+}
+
+/// Get the Dolby Vision configuration record of a stream, if any.
+///
+/// # Arguments
+///
+/// * `input` - Input to get configuration from.
+/// * `stream_index` - Index of stream.
+pub fn stream_dovi_configuration(
+    input: &Input,
+    stream_index: usize,
+) -> Option<AVDOVIDecoderConfigurationRecord> {
+    unsafe {
+        stream_side_data(
+            input,
+            stream_index,
+            AVPacketSideDataType::AV_PKT_DATA_DOVI_CONF,
+        )
+    }
+}
+
+/// Get the mastering display metadata attached to a decoded frame, if any.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get metadata from.
+pub fn frame_mastering_display_metadata(frame: &Frame) -> Option<AVMasteringDisplayMetadata> {
+    unsafe {
+        frame_side_data(
+            frame,
+            AVFrameSideDataType::AV_FRAME_DATA_MASTERING_DISPLAY_METADATA,
+        )
+    }
+}
+
+/// Get the content light level metadata attached to a decoded frame, if any.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get metadata from.
+pub fn frame_content_light_level(frame: &Frame) -> Option<AVContentLightMetadata> {
+    unsafe {
+        frame_side_data(
+            frame,
+            AVFrameSideDataType::AV_FRAME_DATA_CONTENT_LIGHT_LEVEL,
+        )
+    }
+}
+
+/// Get the motion vectors attached to a decoded frame, if the decoder exports them.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get motion vectors from.
+pub fn frame_motion_vectors(frame: &Frame) -> Vec<AVMotionVector> {
+    unsafe {
+        let side_data = av_frame_get_side_data(
+            frame.as_ptr(),
+            AVFrameSideDataType::AV_FRAME_DATA_MOTION_VECTORS,
+        );
+        if side_data.is_null() || (*side_data).data.is_null() {
+            return Vec::new();
+        }
+        let count = (*side_data).size / std::mem::size_of::<AVMotionVector>();
+        std::slice::from_raw_parts((*side_data).data as *const AVMotionVector, count).to_vec()
+    }
+}
+
+/// Read side data of a stream from its codec parameters.
+///
+/// # Safety
+///
+/// `T` must match the layout of the side data of the given type.
+unsafe fn stream_side_data<T: Copy>(
+    input: &Input,
+    stream_index: usize,
+    kind: AVPacketSideDataType,
+) -> Option<T> {
+    let stream = input.stream(stream_index)?;
+    let codecpar = (*stream.as_ptr()).codecpar;
+    if codecpar.is_null() {
+        return None;
+    }
+    let side_data = av_packet_side_data_get(
+        (*codecpar).coded_side_data,
+        (*codecpar).nb_coded_side_data,
+        kind,
+    );
+    if side_data.is_null() {
+        return None;
+    }
+    read_side_data((*side_data).data, (*side_data).size)
+}
+
+/// Read side data attached to a frame.
+///
+/// # Safety
+///
+/// `T` must match the layout of the side data of the given type.
+unsafe fn frame_side_data<T: Copy>(frame: &Frame, kind: AVFrameSideDataType) -> Option<T> {
+    let side_data = av_frame_get_side_data(frame.as_ptr(), kind);
+    if side_data.is_null() {
+        return None;
+    }
+    read_side_data((*side_data).data, (*side_data).size)
+}
+
+/// Copy a side data payload into a value, if it is large enough.
+///
+/// # Safety
+///
+/// `data` must point to `size` readable bytes, laid out as `T`.
+unsafe fn read_side_data<T: Copy>(data: *const u8, size: usize) -> Option<T> {
+    if data.is_null() || size < std::mem::size_of::<T>() {
+        return None;
+    }
+    Some((data as *const T).read_unaligned())
+}
+
+/// Get the duration of a frame, in the time base of the frame.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get duration of.
+pub fn get_frame_duration(frame: &Frame) -> i64 {
+    unsafe { (*frame.as_ptr()).duration }
+}
+
+/// Set the duration of a frame, in the time base of the frame.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to set duration of.
+/// * `duration` - Duration to set.
+pub fn set_frame_duration(frame: &mut Frame, duration: i64) {
+    unsafe {
+        (*frame.as_mut_ptr()).duration = duration;
+    }
+}
+
+/// Set the quality of a frame, in lambda units. Encoders that use a fixed quantizer scale read the
+/// quality of every frame they encode.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to set quality of.
+/// * `quality` - Quality to set.
+pub fn set_frame_quality(frame: &mut Frame, quality: i32) {
+    unsafe {
+        (*frame.as_mut_ptr()).quality = quality;
+    }
+}
+
+/// Copy frame properties from `src` to `dst`.
+///
+/// # Arguments
+///
+/// * `src` - Frame to get properties from.
+/// * `dst` - Frame to copy properties to.
+pub fn copy_frame_props(src: &Frame, dst: &mut Frame) {
+    unsafe {
+        av_frame_copy_props(dst.as_mut_ptr(), src.as_ptr());
+    }
+}
+
+/// Whether or not a pixel format has an alpha channel.
+///
+/// # Arguments
+///
+/// * `format` - Pixel format.
+pub fn pixel_format_has_alpha(format: Pixel) -> bool {
+    format.descriptor().is_some_and(|descriptor| unsafe {
+        (*descriptor.as_ptr()).flags & AV_PIX_FMT_FLAG_ALPHA as u64 != 0
+    })
+}
+
+/// A frame array is the `ndarray` version of `AVFrame`. It is 3-dimensional array with dims `(H, W,
+/// C)` and type byte.
+#[cfg(feature = "ndarray")]
+pub type FrameArray = Array3<u8>;
+
+/// Converts an `ndarray` to an RGB24 video `AVFrame` for ffmpeg.
+///
+/// # Arguments
+///
+/// * `frame_array` - Video frame to convert. The frame format must be `(H, W, C)`.
+///
+/// # Return value
+///
+/// An ffmpeg-native `AvFrame`.
+#[cfg(feature = "ndarray")]
+pub fn convert_ndarray_to_frame_rgb24(frame_array: &FrameArray) -> Result<Frame, Error> {
+    convert_ndarray_to_frame(frame_array, Pixel::RGB24)
+}
+
+/// Converts an `ndarray` with an alpha channel to an RGBA video `AVFrame` for ffmpeg.
+///
+/// # Arguments
+///
+/// * `frame_array` - Video frame to convert. The frame format must be `(H, W, 4)`.
+///
+/// # Return value
+///
+/// An ffmpeg-native `AvFrame`.
+#[cfg(feature = "ndarray")]
+pub fn convert_ndarray_to_frame_rgba(frame_array: &FrameArray) -> Result<Frame, Error> {
+    convert_ndarray_to_frame(frame_array, Pixel::RGBA)
+}
+
+/// Converts an `ndarray` with packed pixels to a video `AVFrame` for ffmpeg.
+///
+/// # Arguments
+///
+/// * `frame_array` - Video frame to convert. The frame format must be `(H, W, C)`.
+/// * `format` - Packed pixel format of the frame array.
+#[cfg(feature = "ndarray")]
+fn convert_ndarray_to_frame(frame_array: &FrameArray, format: Pixel) -> Result<Frame, Error> {
+    unsafe {
+        assert!(frame_array.is_standard_layout());
+
+        let (frame_height, frame_width, _) = frame_array.dim();
+
+        // Temporary frame structure to place correctly formatted data and linesize stuff in, which
+        // we'll copy later.
+        let mut frame_tmp = Frame::empty();
+        let frame_tmp_ptr = frame_tmp.as_mut_ptr();
+
+        // This does not copy the data, but it sets the `frame_tmp` data and linesize pointers
+        // correctly.
+        let bytes_copied = av_image_fill_arrays(
+            (*frame_tmp_ptr).data.as_ptr() as *mut *mut u8,
+            (*frame_tmp_ptr).linesize.as_ptr() as *mut i32,
+            frame_array.as_ptr(),
+            format.into(),
+            frame_width as i32,
+            frame_height as i32,
+            1,
+        );
+
+        if bytes_copied != frame_array.len() as i32 {
+            return Err(Error::from(bytes_copied));
+        }
+
+        let mut frame = Frame::new(format, frame_width as u32, frame_height as u32);
+        let frame_ptr = frame.as_mut_ptr();
+
+        // Do the actual copying.
+        av_image_copy(
+            (*frame_ptr).data.as_ptr() as *mut *mut u8,
+            (*frame_ptr).linesize.as_ptr() as *mut i32,
+            (*frame_tmp_ptr).data.as_ptr() as *mut *const u8,
+            (*frame_tmp_ptr).linesize.as_ptr(),
+            format.into(),
+            frame_width as i32,
+            frame_height as i32,
+        );
+
+        Ok(frame)
+    }
+}
+
+/// Converts an RGB24 video `AVFrame` produced by ffmpeg to an `ndarray`.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to convert.
+///
+/// # Return value
+///
+/// A three-dimensional `ndarray` with dimensions `(H, W, C)` and type byte.
+#[cfg(feature = "ndarray")]
+pub fn convert_frame_to_ndarray_rgb24(frame: &mut Frame) -> Result<FrameArray, Error> {
+    convert_frame_to_ndarray_packed(frame, AVPixelFormat::AV_PIX_FMT_RGB24, 3)
+}
+
+/// Converts an RGBA video `AVFrame` produced by ffmpeg to an `ndarray`.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to convert.
+///
+/// # Return value
+///
+/// A three-dimensional `ndarray` with dimensions `(H, W, 4)` and type byte.
+#[cfg(feature = "ndarray")]
+pub fn convert_frame_to_ndarray_rgba(frame: &mut Frame) -> Result<FrameArray, Error> {
+    convert_frame_to_ndarray_packed(frame, AVPixelFormat::AV_PIX_FMT_RGBA, 4)
+}
+
+/// Converts a video `AVFrame` with packed pixels produced by ffmpeg to an `ndarray`.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to convert.
+/// * `format` - Packed pixel format of the frame.
+/// * `channels` - Number of channels of the pixel format.
+#[cfg(feature = "ndarray")]
+fn convert_frame_to_ndarray_packed(
+    frame: &mut Frame,
+    format: AVPixelFormat,
+    channels: usize,
+) -> Result<FrameArray, Error> {
+    unsafe {
+        let frame_ptr = frame.as_mut_ptr();
+        let frame_width: i32 = (*frame_ptr).width;
+        let frame_height: i32 = (*frame_ptr).height;
+        let frame_format =
+            std::mem::transmute::<std::ffi::c_int, AVPixelFormat>((*frame_ptr).format);
+        assert_eq!(frame_format, format);
+
+        let mut frame_array =
+            FrameArray::default((frame_height as usize, frame_width as usize, channels));
+
+        let bytes_copied = av_image_copy_to_buffer(
+            frame_array.as_mut_ptr(),
+            frame_array.len() as i32,
+            (*frame_ptr).data.as_ptr() as *const *const u8,
+            (*frame_ptr).linesize.as_ptr(),
+            frame_format,
+            frame_width,
+            frame_height,
+            1,
+        );
+
+        if bytes_copied == frame_array.len() as i32 {
+            Ok(frame_array)
+        } else {
+            Err(Error::from(bytes_copied))
+        }
+    }
+}
+
+/// Copies one 8-bit plane of a planar video `AVFrame` produced by ffmpeg to an `ndarray`, dropping
+/// the padding at the end of each row.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to copy from.
+/// * `plane` - Index of the plane.
+/// * `width` - Width of the plane.
+/// * `height` - Height of the plane.
+///
+/// # Return value
+///
+/// A two-dimensional `ndarray` with dimensions `(H, W)` and type byte.
+#[cfg(feature = "ndarray")]
+pub fn convert_frame_plane_to_ndarray(
+    frame: &Frame,
+    plane: usize,
+    width: usize,
+    height: usize,
+) -> Array2<u8> {
+    let stride = frame.stride(plane);
+    let data = frame.data(plane);
+    let mut plane_array = Array2::zeros((height, width));
+    for (y, mut row) in plane_array.rows_mut().into_iter().enumerate() {
+        row.as_slice_mut()
+            .expect("rows of a new array are contiguous")
+            .copy_from_slice(&data[y * stride..y * stride + width]);
+    }
+    plane_array
+}
+
+/// Converts an RGB48LE video `AVFrame` produced by ffmpeg to an `ndarray` of 16-bit samples.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to convert.
+///
+/// # Return value
+///
+/// A three-dimensional `ndarray` with dimensions `(H, W, 3)` and type `u16`.
+#[cfg(feature = "ndarray")]
+pub fn convert_frame_to_ndarray_rgb48(frame: &Frame) -> Array3<u16> {
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let samples = read_plane_samples(frame, 0, width * 3, height, u16::from_le_bytes);
+    Array3::from_shape_vec((height, width, 3), samples).expect("plane holds H * W * 3 samples")
+}
+
+/// Copies one 16-bit little-endian plane of a planar video `AVFrame` produced by ffmpeg to an
+/// `ndarray`, dropping the padding at the end of each row. Formats with fewer bits per sample,
+/// such as YUV420P10LE, keep their range.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to copy from.
+/// * `plane` - Index of the plane.
+/// * `width` - Width of the plane.
+/// * `height` - Height of the plane.
+///
+/// # Return value
+///
+/// A two-dimensional `ndarray` with dimensions `(H, W)` and type `u16`.
+#[cfg(feature = "ndarray")]
+pub fn convert_frame_plane_to_ndarray_u16(
+    frame: &Frame,
+    plane: usize,
+    width: usize,
+    height: usize,
+) -> Array2<u16> {
+    let samples = read_plane_samples(frame, plane, width, height, u16::from_le_bytes);
+    Array2::from_shape_vec((height, width), samples).expect("plane holds H * W samples")
+}
+
+/// Converts a GBRPF32LE video `AVFrame` produced by ffmpeg to an `ndarray` of packed RGB floats.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to convert.
+///
+/// # Return value
+///
+/// A three-dimensional `ndarray` with dimensions `(H, W, 3)` and type `f32`, with values from `0`
+/// to `1`.
+#[cfg(feature = "ndarray")]
+pub fn convert_frame_to_ndarray_gbrpf32(frame: &Frame) -> Array3<f32> {
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    // The planes are stored in G, B, R order.
+    let [g, b, r] =
+        [0, 1, 2].map(|plane| read_plane_samples(frame, plane, width, height, f32::from_le_bytes));
+    Array3::from_shape_fn((height, width, 3), |(y, x, channel)| {
+        [&r, &g, &b][channel][y * width + x]
+    })
+}
+
+/// Converts an `ndarray` of 16-bit samples to an RGB48LE video `AVFrame` for ffmpeg.
+///
+/// # Arguments
+///
+/// * `frame_array` - Video frame to convert. The frame format must be `(H, W, 3)`.
+///
+/// # Return value
+///
+/// An ffmpeg-native `AvFrame`.
+#[cfg(feature = "ndarray")]
+pub fn convert_ndarray_to_frame_rgb48(frame_array: &Array3<u16>) -> Frame {
+    let (height, width, _) = frame_array.dim();
+    let mut frame = Frame::new(Pixel::RGB48LE, width as u32, height as u32);
+    write_plane_samples(&mut frame, 0, width * 3, frame_array.iter().copied(), u16::to_le_bytes);
+    frame
+}
+
+/// Converts an `ndarray` of packed RGB floats to a GBRPF32LE video `AVFrame` for ffmpeg.
+///
+/// # Arguments
+///
+/// * `frame_array` - Video frame to convert. The frame format must be `(H, W, 3)`, with values
+///   from `0` to `1`.
+///
+/// # Return value
+///
+/// An ffmpeg-native `AvFrame`.
+#[cfg(feature = "ndarray")]
+pub fn convert_ndarray_to_frame_gbrpf32(frame_array: &Array3<f32>) -> Frame {
+    let (height, width, _) = frame_array.dim();
+    let mut frame = Frame::new(Pixel::GBRPF32LE, width as u32, height as u32);
+    // The planes are stored in G, B, R order.
+    for (plane, channel) in [1, 2, 0].into_iter().enumerate() {
+        let samples = frame_array.index_axis(ndarray::Axis(2), channel);
+        write_plane_samples(&mut frame, plane, width, samples.iter().copied(), f32::to_le_bytes);
+    }
+    frame
+}
+
+/// Reads the samples of a plane of a video `AVFrame`, dropping the padding at the end of each row.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to read from.
+/// * `plane` - Index of the plane.
+/// * `row_samples` - Number of samples in a row.
+/// * `height` - Number of rows.
+/// * `decode` - Decodes a sample from its bytes.
+#[cfg(feature = "ndarray")]
+fn read_plane_samples<T, const N: usize>(
+    frame: &Frame,
+    plane: usize,
+    row_samples: usize,
+    height: usize,
+    decode: fn([u8; N]) -> T,
+) -> Vec<T> {
+    let stride = frame.stride(plane);
+    let data = frame.data(plane);
+    (0..height)
+        .flat_map(|y| data[y * stride..y * stride + row_samples * N].chunks_exact(N))
+        .map(|bytes| decode(bytes.try_into().expect("chunks have N bytes")))
+        .collect()
+}
+
+/// Writes samples to a plane of a video `AVFrame`, row by row.
+///
+/// # Arguments
+///
+/// * `frame` - Video frame to write to.
+/// * `plane` - Index of the plane.
+/// * `row_samples` - Number of samples in a row.
+/// * `samples` - Samples in row-major order.
+/// * `encode` - Encodes a sample to its bytes.
+#[cfg(feature = "ndarray")]
+fn write_plane_samples<T, const N: usize>(
+    frame: &mut Frame,
+    plane: usize,
+    row_samples: usize,
+    mut samples: impl Iterator<Item = T>,
+    encode: fn(T) -> [u8; N],
+) {
+    let stride = frame.stride(plane);
+    let data = frame.data_mut(plane);
+    for row in data.chunks_exact_mut(stride) {
+        for bytes in row[..row_samples * N].chunks_exact_mut(N) {
+            match samples.next() {
+                Some(sample) => bytes.copy_from_slice(&encode(sample)),
+                None => return,
+            }
+        }
+    }
+}
+
+/// Get the linesize of a plane of a frame. Unlike `Frame::stride`, this keeps the sign of the
+/// linesize, which is negative for frames that are stored bottom-up.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get linesize of.
+/// * `plane` - Index of plane.
+pub fn frame_linesize(frame: &Frame, plane: usize) -> i32 {
+    unsafe { (*frame.as_ptr()).linesize.get(plane).copied().unwrap_or(0) }
+}
+
+/// Get the number of bytes of pixel data in a row of a plane of an image, without padding.
+///
+/// # Arguments
+///
+/// * `format` - Pixel format of the image.
+/// * `width` - Width of the image.
+/// * `plane` - Index of plane.
+///
+/// # Return value
+///
+/// Number of bytes, or `None` if the pixel format has no such plane.
+pub fn image_row_bytes(
+    format: ffmpeg::util::format::Pixel,
+    width: u32,
+    plane: usize,
+) -> Option<usize> {
+    let row_bytes = unsafe { av_image_get_linesize(format.into(), width as i32, plane as i32) };
+    (row_bytes > 0).then_some(row_bytes as usize)
+}
+
+/// Retrieve a reference to the extradata bytes in codec parameters of an output stream.
+///
+/// # Arguments
+///
+/// * `output` - Output that contains stream to get extradata from.
+/// * `stream_index` - Index of stream.
+pub fn extradata(output: &Output, stream_index: usize) -> Result<&[u8], Error> {
+    let parameters = output
+        .stream(stream_index)
+        .map(|stream| stream.parameters())
+        .ok_or(Error::StreamNotFound)?;
+
+    Ok(unsafe {
+        std::slice::from_raw_parts(
+            (*parameters.as_ptr()).extradata,
+            (*parameters.as_ptr()).extradata_size as usize,
+        )
+    })
+}
+
+/// Whether or not the output format context is configured to use H.264 packetization mode 0.
+///
+/// # Arguments
+///
+/// * `output` - Output format context.
+pub fn rtp_h264_mode_0(output: &Output) -> bool {
+    unsafe {
+        av_opt_flag_is_set(
+            (*output.as_ptr()).priv_data,
+            "rtpflags".as_ptr() as *const std::ffi::c_char,
+            "h264_mode0".as_ptr() as *const std::ffi::c_char,
+        ) != 0
+    }
+}
+
+/// Get the current sequence number and timestamp of the RTP muxer.
+///
+/// Note: This method is only safe to use on RTP output formats.
+pub fn rtp_seq_and_timestamp(output: &Output) -> (u16, u32) {
+    unsafe {
+        let rtp_mux_context = &*((*output.as_ptr()).priv_data as *const RTPMuxContext);
+        (rtp_mux_context.seq, rtp_mux_context.timestamp)
+    }
+}
+
+/// Get the payload type of the RTP muxer, or `None` if it has not been chosen yet. The payload
+/// type is chosen when the header is written.
+///
+/// Note: This method is only safe to use on RTP output formats.
+pub fn rtp_payload_type(output: &Output) -> Option<u8> {
+    unsafe {
+        let rtp_mux_context = &*((*output.as_ptr()).priv_data as *const RTPMuxContext);
+        u8::try_from(rtp_mux_context.payload_type).ok()
+    }
+}
+
+/// Create SDP file contents for the given output. Useful for RTP muxers.
+///
+/// A media entry will be created for each stream in the output. This function will take care of all
+/// details, such as setting the correct media attributes needed by any SDP consumers.
+///
+/// # Arguments
+///
+/// * `output` - Output to generate SDP file for.
+///
+/// # Return value
+///
+/// A string with the SDP file contents.
+pub fn sdp(output: &Output) -> Result<String, Error> {
+    const BUF_SIZE: i32 = 4096;
+
+    unsafe {
+        let mut buf: [std::ffi::c_char; BUF_SIZE as usize] = [0; BUF_SIZE as usize];
+        let buf_ptr = &mut buf as *mut std::ffi::c_char;
+        let mut output_format_context = output.as_ptr();
+        let output_format_context_ptr = &mut output_format_context as *mut *const AVFormatContext;
+        // WARNING! Casting from const ptr to mutable ptr here!
         let output_format_context_ptr = output_format_context_ptr as *mut *mut AVFormatContext;
         let ret = av_sdp_create(output_format_context_ptr, 1, buf_ptr, BUF_SIZE);
 
@@ -487,6 +2089,108 @@ extern "C" fn output_raw_buf_start_callback(
     buffer_size
 }
 
+/// Internal function with C-style callback behavior that writes a buffer to the sink of type `S`
+/// passed through `opaque`.
+///
+/// # Arguments
+///
+/// * `opaque` - Pointer to the sink.
+/// * `buffer` - Buffer to write.
+/// * `buffer_size` - Size of buffer.
+extern "C" fn output_sink_write_callback<S: std::io::Write>(
+    opaque: *mut std::ffi::c_void,
+    buffer: *const u8,
+    buffer_size: i32,
+) -> i32 {
+    let sink: &mut S = unsafe { &mut *(opaque as *mut S) };
+    let buffer = unsafe { std::slice::from_raw_parts(buffer, buffer_size as usize) };
+    match sink.write_all(buffer) {
+        Ok(()) => buffer_size,
+        Err(err) => {
+            tracing::error!("failed to write to output sink: {err}");
+            Error::Unknown.into()
+        }
+    }
+}
+
+/// Internal function with C-style callback behavior that tells ffmpeg whether to abort a blocking
+/// call.
+///
+/// # Arguments
+///
+/// * `opaque` - Pointer to the interrupt flag.
+extern "C" fn interrupt_callback(opaque: *mut std::ffi::c_void) -> std::ffi::c_int {
+    let interrupt: &AtomicBool = unsafe { &*(opaque as *const AtomicBool) };
+    interrupt.load(std::sync::atomic::Ordering::Relaxed) as std::ffi::c_int
+}
+
+/// Internal function with C-style callback behavior that reads from the source of type `S` passed
+/// through `opaque`.
+///
+/// # Arguments
+///
+/// * `opaque` - Pointer to the source.
+/// * `buffer` - Buffer to read into.
+/// * `buffer_size` - Size of buffer.
+extern "C" fn io_read_callback<S: std::io::Read>(
+    opaque: *mut std::ffi::c_void,
+    buffer: *mut u8,
+    buffer_size: i32,
+) -> i32 {
+    let source: &mut S = unsafe { &mut *(opaque as *mut S) };
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer, buffer_size as usize) };
+    loop {
+        match source.read(buffer) {
+            Ok(0) => return Error::Eof.into(),
+            Ok(read) => return read as i32,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                tracing::error!("failed to read from input source: {err}");
+                return Error::Unknown.into();
+            }
+        }
+    }
+}
+
+/// Internal function with C-style callback behavior that seeks in the source or sink of type `S`
+/// passed through `opaque`.
+///
+/// # Arguments
+///
+/// * `opaque` - Pointer to the source or sink.
+/// * `offset` - Offset to seek to.
+/// * `whence` - Seek mode, or `AVSEEK_SIZE` to query the size of the source or sink.
+extern "C" fn io_seek_callback<S: std::io::Seek>(
+    opaque: *mut std::ffi::c_void,
+    offset: i64,
+    whence: std::ffi::c_int,
+) -> i64 {
+    use std::io::SeekFrom;
+
+    let io: &mut S = unsafe { &mut *(opaque as *mut S) };
+    let result = if whence & AVSEEK_SIZE as std::ffi::c_int != 0 {
+        io.stream_position().and_then(|position| {
+            let size = io.seek(SeekFrom::End(0))?;
+            io.seek(SeekFrom::Start(position))?;
+            Ok(size)
+        })
+    } else {
+        match whence & !(AVSEEK_FORCE as std::ffi::c_int) {
+            0 => io.seek(SeekFrom::Start(offset.max(0) as u64)),
+            1 => io.seek(SeekFrom::Current(offset)),
+            2 => io.seek(SeekFrom::End(offset)),
+            _ => return i32::from(Error::InvalidData) as i64,
+        }
+    };
+    match result {
+        Ok(position) => position as i64,
+        Err(err) => {
+            tracing::error!("failed to seek: {err}");
+            i32::from(Error::Unknown) as i64
+        }
+    }
+}
+
 /// Internal function with C-style callback behavior that receives all log messages from ffmpeg and
 /// handles them with the `log` crate, the Rust way.
 ///
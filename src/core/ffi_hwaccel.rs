@@ -1,6 +1,7 @@
 extern crate ffmpeg_next as ffmpeg;
 
-use crate::core::hwaccel::HardwareAccelerationDeviceType;
+use crate::core::hwaccel::{HardwareAccelerationDeviceType, HardwareFrameHandle, HardwarePointer};
+use crate::core::interop::DmaBufPlane;
 
 pub struct HardwareDeviceContext {
     ptr: *mut ffmpeg::ffi::AVBufferRef,
@@ -9,14 +10,21 @@ pub struct HardwareDeviceContext {
 impl HardwareDeviceContext {
     pub fn new(
         device_type: HardwareAccelerationDeviceType,
+        device: Option<&str>,
     ) -> Result<HardwareDeviceContext, ffmpeg::error::Error> {
         let mut ptr: *mut ffmpeg::ffi::AVBufferRef = std::ptr::null_mut();
+        let device = device
+            .map(std::ffi::CString::new)
+            .transpose()
+            .map_err(|_| ffmpeg::error::Error::InvalidData)?;
 
         unsafe {
             match ffmpeg::ffi::av_hwdevice_ctx_create(
                 (&mut ptr) as *mut *mut ffmpeg::ffi::AVBufferRef,
                 device_type.into(),
-                std::ptr::null(),
+                device
+                    .as_ref()
+                    .map_or(std::ptr::null(), |device| device.as_ptr()),
                 std::ptr::null_mut(),
                 0,
             ) {
@@ -31,6 +39,14 @@ impl HardwareDeviceContext {
     }
 }
 
+impl Clone for HardwareDeviceContext {
+    fn clone(&self) -> Self {
+        HardwareDeviceContext {
+            ptr: unsafe { self.ref_raw() },
+        }
+    }
+}
+
 impl Drop for HardwareDeviceContext {
     fn drop(&mut self) {
         unsafe {
@@ -39,6 +55,124 @@ impl Drop for HardwareDeviceContext {
     }
 }
 
+/// Pool of frames in hardware memory, that frames in system memory are uploaded into before they
+/// are passed to a hardware encoder.
+pub struct HardwareFramesContext {
+    ptr: *mut ffmpeg::ffi::AVBufferRef,
+}
+
+impl HardwareFramesContext {
+    pub fn new(
+        device: &HardwareDeviceContext,
+        format: ffmpeg::format::pixel::Pixel,
+        software_format: ffmpeg::format::pixel::Pixel,
+        width: u32,
+        height: u32,
+        pool_size: usize,
+    ) -> Result<HardwareFramesContext, ffmpeg::error::Error> {
+        unsafe {
+            let mut ptr = ffmpeg::ffi::av_hwframe_ctx_alloc(device.ptr);
+            if ptr.is_null() {
+                return Err(ffmpeg::error::Error::Other {
+                    errno: ffmpeg::util::error::ENOMEM,
+                });
+            }
+            let frames_context = (*ptr).data as *mut ffmpeg::ffi::AVHWFramesContext;
+            (*frames_context).format = format.into();
+            (*frames_context).sw_format = software_format.into();
+            (*frames_context).width = width as i32;
+            (*frames_context).height = height as i32;
+            (*frames_context).initial_pool_size = pool_size as i32;
+            match ffmpeg::ffi::av_hwframe_ctx_init(ptr) {
+                0 => Ok(HardwareFramesContext { ptr }),
+                e => {
+                    ffmpeg::ffi::av_buffer_unref(&mut ptr);
+                    Err(ffmpeg::error::Error::from(e))
+                }
+            }
+        }
+    }
+
+    /// Copy a frame in system memory into a new frame from the pool.
+    pub fn upload(
+        &self,
+        frame: &ffmpeg::frame::Frame,
+    ) -> Result<ffmpeg::frame::Video, ffmpeg::error::Error> {
+        unsafe {
+            let mut frame_uploaded = ffmpeg::frame::Video::empty();
+            let ret = ffmpeg::ffi::av_hwframe_get_buffer(self.ptr, frame_uploaded.as_mut_ptr(), 0);
+            if ret < 0 {
+                return Err(ffmpeg::error::Error::from(ret));
+            }
+            let ret = ffmpeg::ffi::av_hwframe_transfer_data(
+                frame_uploaded.as_mut_ptr(),
+                frame.as_ptr(),
+                0,
+            );
+            if ret < 0 {
+                return Err(ffmpeg::error::Error::from(ret));
+            }
+            match ffmpeg::ffi::av_frame_copy_props(frame_uploaded.as_mut_ptr(), frame.as_ptr()) {
+                0 => Ok(frame_uploaded),
+                e => Err(ffmpeg::error::Error::from(e)),
+            }
+        }
+    }
+
+    unsafe fn ref_raw(&self) -> *mut ffmpeg::ffi::AVBufferRef {
+        ffmpeg::ffi::av_buffer_ref(self.ptr)
+    }
+}
+
+impl Drop for HardwareFramesContext {
+    fn drop(&mut self) {
+        unsafe {
+            ffmpeg::ffi::av_buffer_unref(&mut self.ptr);
+        }
+    }
+}
+
+/// Frame constraints of a hardware device: the software formats that its frames can have, and the
+/// minimum and maximum frame size.
+pub type HardwareFrameConstraints = (Vec<ffmpeg::format::pixel::Pixel>, (u32, u32), (u32, u32));
+
+/// Get the frame constraints of a hardware device, independent of any codec or hardware
+/// configuration. Returns `None` if the device does not report constraints.
+pub fn hwdevice_frame_constraints(
+    device: &HardwareDeviceContext,
+) -> Option<HardwareFrameConstraints> {
+    unsafe {
+        let mut constraints =
+            ffmpeg::ffi::av_hwdevice_get_hwframe_constraints(device.ptr, std::ptr::null());
+        if constraints.is_null() {
+            return None;
+        }
+        let mut software_formats = Vec::new();
+        let mut p = (*constraints).valid_sw_formats as *const ffmpeg::ffi::AVPixelFormat;
+        while !p.is_null() && *p != ffmpeg::ffi::AVPixelFormat::AV_PIX_FMT_NONE {
+            software_formats.push((*p).into());
+            p = p.add(1);
+        }
+        let min_size = (
+            (*constraints).min_width.max(0) as u32,
+            (*constraints).min_height.max(0) as u32,
+        );
+        let max_size = (
+            (*constraints).max_width.max(0) as u32,
+            (*constraints).max_height.max(0) as u32,
+        );
+        ffmpeg::ffi::av_hwframe_constraints_free(&mut constraints);
+        Some((software_formats, min_size, max_size))
+    }
+}
+
+/// Get the number of bits of the first component of a pixel format, or `None` for pixel formats
+/// without a descriptor.
+pub fn pixel_format_bit_depth(pixel_format: ffmpeg::format::pixel::Pixel) -> Option<u8> {
+    let descriptor = pixel_format.descriptor()?;
+    unsafe { Some((*descriptor.as_ptr()).comp[0].depth as u8) }
+}
+
 pub fn hwdevice_list_available_device_types() -> Vec<HardwareAccelerationDeviceType> {
     let mut hwdevice_types = Vec::new();
     let mut hwdevice_type = unsafe {
@@ -67,6 +201,242 @@ pub fn hwdevice_transfer_frame(
     }
 }
 
+pub fn frame_is_hardware(frame: &ffmpeg::frame::Frame) -> bool {
+    unsafe { !(*frame.as_ptr()).hw_frames_ctx.is_null() }
+}
+
+/// Get the handle of a hardware frame. Where the handle is stored in the frame depends on the
+/// pixel format, see the documentation of `AVPixelFormat`.
+pub fn frame_hardware_handle(frame: &ffmpeg::frame::Frame) -> Option<HardwareFrameHandle<'_>> {
+    use ffmpeg::ffi::AVPixelFormat::*;
+
+    if !frame_is_hardware(frame) {
+        return None;
+    }
+    unsafe {
+        let frame = frame.as_ptr();
+        let data = (*frame).data;
+        let format = (*frame).format;
+        if format == AV_PIX_FMT_VIDEOTOOLBOX as i32 {
+            Some(HardwareFrameHandle::CvPixelBuffer(HardwarePointer::new(
+                data[3] as _,
+            )))
+        } else if format == AV_PIX_FMT_VAAPI as i32 {
+            Some(HardwareFrameHandle::VaSurface(data[3] as usize as u32))
+        } else if format == AV_PIX_FMT_CUDA as i32 {
+            let planes = data
+                .iter()
+                .zip((*frame).linesize)
+                .take_while(|(plane, _)| !plane.is_null())
+                .map(|(plane, linesize)| (*plane as usize as u64, linesize as usize))
+                .collect();
+            Some(HardwareFrameHandle::Cuda { planes })
+        } else if format == AV_PIX_FMT_D3D11 as i32 {
+            Some(HardwareFrameHandle::D3D11Texture {
+                texture: HardwarePointer::new(data[0] as _),
+                array_index: data[1] as usize,
+            })
+        } else if format == AV_PIX_FMT_DRM_PRIME as i32 {
+            Some(HardwareFrameHandle::DrmPrime(HardwarePointer::new(
+                data[0] as _,
+            )))
+        } else if format == AV_PIX_FMT_VULKAN as i32 {
+            Some(HardwareFrameHandle::VkFrame(HardwarePointer::new(
+                data[0] as _,
+            )))
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether or not a hardware frame lives in the memory of a device.
+pub fn frame_is_on_device(frame: &ffmpeg::frame::Frame, device: &HardwareDeviceContext) -> bool {
+    unsafe {
+        let hw_frames_ctx = (*frame.as_ptr()).hw_frames_ctx;
+        if hw_frames_ctx.is_null() {
+            return false;
+        }
+        let frames_context = (*hw_frames_ctx).data as *const ffmpeg::ffi::AVHWFramesContext;
+        let device_ref = (*frames_context).device_ref;
+        !device_ref.is_null() && (*device_ref).data == (*device.ptr).data
+    }
+}
+
+/// Get the pixel format of the data of a hardware frame in hardware memory.
+pub fn frame_hardware_software_format(
+    frame: &ffmpeg::frame::Frame,
+) -> Option<ffmpeg::format::pixel::Pixel> {
+    unsafe {
+        let hw_frames_ctx = (*frame.as_ptr()).hw_frames_ctx;
+        if hw_frames_ctx.is_null() {
+            return None;
+        }
+        let frames_context = (*hw_frames_ctx).data as *const ffmpeg::ffi::AVHWFramesContext;
+        Some((*frames_context).sw_format.into())
+    }
+}
+
+/// Create a new reference to the data of a frame, without copying it. For hardware frames, this
+/// keeps the surface from being returned to the pool of the decoder.
+pub fn frame_ref(
+    frame: &ffmpeg::frame::Frame,
+) -> Result<ffmpeg::frame::Video, ffmpeg::error::Error> {
+    unsafe {
+        let mut reference = ffmpeg::frame::Video::empty();
+        match ffmpeg::ffi::av_frame_ref(reference.as_mut_ptr(), frame.as_ptr()) {
+            0 => Ok(reference),
+            e => Err(ffmpeg::error::Error::from(e)),
+        }
+    }
+}
+
+// Mirror of `AVCUDADeviceContext` in `hwcontext_cuda.h`, which the generated bindings do not
+// include.
+#[repr(C)]
+struct AVCUDADeviceContext {
+    cuda_ctx: *mut std::ffi::c_void,
+    stream: *mut std::ffi::c_void,
+    internal: *mut std::ffi::c_void,
+}
+
+/// Get the `CUcontext` and `CUstream` of the device of a CUDA frame.
+pub fn frame_cuda_context(
+    frame: &ffmpeg::frame::Frame,
+) -> Option<(*mut std::ffi::c_void, *mut std::ffi::c_void)> {
+    unsafe {
+        let frame = frame.as_ptr();
+        if (*frame).format != ffmpeg::ffi::AVPixelFormat::AV_PIX_FMT_CUDA as i32
+            || (*frame).hw_frames_ctx.is_null()
+        {
+            return None;
+        }
+        let frames_context =
+            (*(*frame).hw_frames_ctx).data as *const ffmpeg::ffi::AVHWFramesContext;
+        let device_context = (*frames_context).device_ctx;
+        let cuda_device_context = (*device_context).hwctx as *const AVCUDADeviceContext;
+        Some((
+            (*cuda_device_context).cuda_ctx,
+            (*cuda_device_context).stream,
+        ))
+    }
+}
+
+/// Map a hardware frame to DRM PRIME, so that it can be imported by other APIs as DMA-BUF. The
+/// mapped frame references the data of the original frame, and keeps its file descriptors open.
+pub fn frame_map_drm_prime(
+    frame: &ffmpeg::frame::Frame,
+) -> Result<ffmpeg::frame::Video, ffmpeg::error::Error> {
+    // `AV_HWFRAME_MAP_READ` from `hwcontext.h`.
+    const AV_HWFRAME_MAP_READ: i32 = 1;
+
+    unsafe {
+        let mut mapped = ffmpeg::frame::Video::empty();
+        let ret = if (*frame.as_ptr()).format
+            == ffmpeg::ffi::AVPixelFormat::AV_PIX_FMT_DRM_PRIME as i32
+        {
+            ffmpeg::ffi::av_frame_ref(mapped.as_mut_ptr(), frame.as_ptr())
+        } else {
+            (*mapped.as_mut_ptr()).format = ffmpeg::ffi::AVPixelFormat::AV_PIX_FMT_DRM_PRIME as i32;
+            ffmpeg::ffi::av_hwframe_map(mapped.as_mut_ptr(), frame.as_ptr(), AV_HWFRAME_MAP_READ)
+        };
+        match ret {
+            0 => Ok(mapped),
+            e => Err(ffmpeg::error::Error::from(e)),
+        }
+    }
+}
+
+// Mirrors of the descriptors in `hwcontext_drm.h`, which the generated bindings do not include.
+const AV_DRM_MAX_PLANES: usize = 4;
+
+#[repr(C)]
+struct AVDRMObjectDescriptor {
+    fd: i32,
+    size: usize,
+    format_modifier: u64,
+}
+
+#[repr(C)]
+struct AVDRMPlaneDescriptor {
+    object_index: i32,
+    offset: isize,
+    pitch: isize,
+}
+
+#[repr(C)]
+struct AVDRMLayerDescriptor {
+    format: u32,
+    nb_planes: i32,
+    planes: [AVDRMPlaneDescriptor; AV_DRM_MAX_PLANES],
+}
+
+#[repr(C)]
+struct AVDRMFrameDescriptor {
+    nb_objects: i32,
+    objects: [AVDRMObjectDescriptor; AV_DRM_MAX_PLANES],
+    nb_layers: i32,
+    layers: [AVDRMLayerDescriptor; AV_DRM_MAX_PLANES],
+}
+
+/// Get the layers of a DRM PRIME frame, as the DRM fourcc and planes of each layer.
+pub fn frame_drm_layers(frame: &ffmpeg::frame::Frame) -> Option<Vec<(u32, Vec<DmaBufPlane>)>> {
+    unsafe {
+        let frame = frame.as_ptr();
+        if (*frame).format != ffmpeg::ffi::AVPixelFormat::AV_PIX_FMT_DRM_PRIME as i32
+            || (*frame).data[0].is_null()
+        {
+            return None;
+        }
+        let descriptor = &*((*frame).data[0] as *const AVDRMFrameDescriptor);
+        let objects = &descriptor.objects[..(descriptor.nb_objects as usize)];
+        let layers = descriptor.layers[..(descriptor.nb_layers as usize)]
+            .iter()
+            .map(|layer| {
+                let planes = layer.planes[..(layer.nb_planes as usize)]
+                    .iter()
+                    .map(|plane| {
+                        let object = &objects[plane.object_index as usize];
+                        DmaBufPlane {
+                            fd: object.fd,
+                            offset: plane.offset as usize,
+                            pitch: plane.pitch as usize,
+                            modifier: object.format_modifier,
+                        }
+                    })
+                    .collect();
+                (layer.format, planes)
+            })
+            .collect();
+        Some(layers)
+    }
+}
+
+/// Pass the hardware frames context of a frame to a `buffer` filter, so that the filter graph
+/// accepts frames in hardware memory.
+pub fn filter_buffer_source_set_hw_frames_ctx(
+    filter: &mut ffmpeg::filter::Context,
+    frame: &ffmpeg::frame::Frame,
+) -> Result<(), ffmpeg::error::Error> {
+    unsafe {
+        let parameters = ffmpeg::ffi::av_buffersrc_parameters_alloc();
+        if parameters.is_null() {
+            return Err(ffmpeg::error::Error::Other {
+                errno: ffmpeg::util::error::ENOMEM,
+            });
+        }
+        (*parameters).format = (*frame.as_ptr()).format;
+        // The filter takes its own reference to the frames context.
+        (*parameters).hw_frames_ctx = (*frame.as_ptr()).hw_frames_ctx;
+        let ret = ffmpeg::ffi::av_buffersrc_parameters_set(filter.as_mut_ptr(), parameters);
+        ffmpeg::ffi::av_free(parameters as *mut _);
+        match ret {
+            0 => Ok(()),
+            e => Err(ffmpeg::error::Error::from(e)),
+        }
+    }
+}
+
 pub fn codec_find_corresponding_hwaccel_pixfmt(
     codec: &ffmpeg::codec::codec::Codec,
     hwaccel_type: HardwareAccelerationDeviceType,
@@ -110,6 +480,15 @@ pub fn codec_context_hwaccel_set_hw_device_ctx(
     }
 }
 
+pub fn codec_context_set_hw_frames_ctx(
+    codec_context: &mut ffmpeg::codec::context::Context,
+    hardware_frames_context: &HardwareFramesContext,
+) {
+    unsafe {
+        (*codec_context.as_mut_ptr()).hw_frames_ctx = hardware_frames_context.ref_raw();
+    }
+}
+
 #[no_mangle]
 unsafe extern "C" fn hwaccel_get_format(
     ctx: *mut ffmpeg::ffi::AVCodecContext,
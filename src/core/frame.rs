@@ -1,8 +1,16 @@
 extern crate ffmpeg_next as ffmpeg;
 
+use std::cell::RefCell;
+
+use ffmpeg::software::scaling::context::Context as AvScaler;
+use ffmpeg::software::scaling::flag::Flags as AvScalerFlags;
 use ffmpeg::util::format::Pixel as AvPixel;
 use ffmpeg::util::frame::Video as AvFrame;
 
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::ffi_hwaccel;
+
 /// Re-export internal `AvPixel` as `PixelFormat` for callers.
 pub type PixelFormat = AvPixel;
 
@@ -15,3 +23,376 @@ pub type Frame = crate::core::ffi::FrameArray;
 
 /// Default frame pixel format.
 pub(crate) const FRAME_PIXEL_FORMAT: AvPixel = AvPixel::RGB24;
+
+/// Layout of the frames that a decoder outputs. Decoding straight into the layout that a vision
+/// pipeline consumes saves a conversion per frame. See
+/// [`DecoderBuilder::with_frame_format`](crate::core::decode::DecoderBuilder::with_frame_format).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum FrameFormat {
+    /// Packed RGB, as an `(H, W, 3)` [`Frame`].
+    #[default]
+    Rgb24,
+    /// Packed RGBA, as an `(H, W, 4)` [`Frame`].
+    Rgba,
+    /// Grayscale, as an `(H, W)` [`GrayFrame`].
+    Gray8,
+    /// Planar YUV 4:2:0, as a [`YuvFrame`].
+    Yuv420p,
+    /// Packed RGB with 16 bits per sample, as an `(H, W, 3)` [`Frame16`]. Keeps the precision of
+    /// 10-bit and 12-bit video, e.g. for HDR mastering.
+    Rgb48,
+    /// Grayscale with 16 bits per sample, as an `(H, W)` [`GrayFrame16`].
+    Gray16,
+    /// Planar YUV 4:2:0 with 10 bits per sample, as a [`YuvFrame<u16>`](YuvFrame) with values
+    /// from `0` to `1023`. Holds the samples of 10-bit video as they were decoded.
+    Yuv420p10,
+    /// RGB with a 32-bit float per sample, as an `(H, W, 3)` [`FrameF32`] with values from `0` to
+    /// `1`.
+    RgbF32,
+}
+
+impl FrameFormat {
+    /// Pixel format of the frames.
+    pub fn pixel_format(self) -> PixelFormat {
+        match self {
+            FrameFormat::Rgb24 => AvPixel::RGB24,
+            FrameFormat::Rgba => AvPixel::RGBA,
+            FrameFormat::Gray8 => AvPixel::GRAY8,
+            FrameFormat::Yuv420p => AvPixel::YUV420P,
+            FrameFormat::Rgb48 => AvPixel::RGB48LE,
+            FrameFormat::Gray16 => AvPixel::GRAY16LE,
+            FrameFormat::Yuv420p10 => AvPixel::YUV420P10LE,
+            // Swscale has no packed float output, so the planes are interleaved when converting to
+            // an ndarray.
+            FrameFormat::RgbF32 => AvPixel::GBRPF32LE,
+        }
+    }
+}
+
+/// Grayscale frame as ndarray, with dimensions `(H, W)`.
+#[cfg(feature = "ndarray")]
+pub type GrayFrame = ndarray::Array2<u8>;
+
+/// Frame with 16 bits per sample as ndarray, with dimensions `(H, W, C)`.
+#[cfg(feature = "ndarray")]
+pub type Frame16 = ndarray::Array3<u16>;
+
+/// Frame with a 32-bit float per sample as ndarray, with dimensions `(H, W, C)`.
+#[cfg(feature = "ndarray")]
+pub type FrameF32 = ndarray::Array3<f32>;
+
+/// Grayscale frame with 16 bits per sample as ndarray, with dimensions `(H, W)`.
+#[cfg(feature = "ndarray")]
+pub type GrayFrame16 = ndarray::Array2<u16>;
+
+/// Planar YUV 4:2:0 frame as ndarrays, one per plane. The luma plane has dimensions `(H, W)`, and
+/// the chroma planes half that, rounded up. Samples are bytes, or `u16` for video with more than 8
+/// bits per sample.
+#[cfg(feature = "ndarray")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YuvFrame<T = u8> {
+    /// Luma plane.
+    pub y: ndarray::Array2<T>,
+    /// Blue-difference chroma plane.
+    pub u: ndarray::Array2<T>,
+    /// Red-difference chroma plane.
+    pub v: ndarray::Array2<T>,
+}
+
+/// Borrow the pixels of a frame with packed pixels as an `(H, W, C)` array view, without copying
+/// them. Unlike [`Decoder::decode`](crate::core::decode::Decoder::decode), which copies every
+/// frame into a new [`Frame`], this costs neither an allocation nor a copy, which suits consumers
+/// that only read the pixels.
+///
+/// The view follows the row stride of the frame, so it is not contiguous if the rows are padded.
+/// Use `to_owned` to get a contiguous copy.
+///
+/// Fails with [`Error::InvalidFrameFormat`] unless the frame is RGB24, BGR24, RGBA, BGRA or GRAY8
+/// (which has one channel).
+///
+/// # Arguments
+///
+/// * `frame` - Frame to borrow, e.g. from
+///   [`Decoder::decode_raw`](crate::core::decode::Decoder::decode_raw).
+///
+/// # Example
+///
+/// ```ignore
+/// let frame = decoder.decode_raw().unwrap();
+/// let view = frame_view(&frame).unwrap();
+/// let mean = view.iter().map(|&value| value as f64).sum::<f64>() / view.len() as f64;
+/// ```
+#[cfg(feature = "ndarray")]
+pub fn frame_view(frame: &RawFrame) -> Result<ndarray::ArrayView3<'_, u8>, Error> {
+    use ndarray::ShapeBuilder;
+
+    let channels = match frame.format() {
+        AvPixel::RGB24 | AvPixel::BGR24 => 3,
+        AvPixel::RGBA | AvPixel::BGRA => 4,
+        AvPixel::GRAY8 => 1,
+        _ => return Err(Error::InvalidFrameFormat),
+    };
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let stride = frame.stride(0);
+    if stride < width * channels {
+        return Err(Error::InvalidFrameFormat);
+    }
+    ndarray::ArrayView3::from_shape(
+        (height, width, channels).strides((stride, channels, 1)),
+        frame.data(0),
+    )
+    .map_err(|_| Error::InvalidFrameFormat)
+}
+
+/// Plane of a frame in system memory, laid out the way texture upload APIs take it, such as the
+/// planes and pitches of SDL's `Texture::update_yuv`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FramePlane<'a> {
+    /// Pixel data: `height` rows that start `stride` bytes apart.
+    pub data: &'a [u8],
+    /// Bytes between the starts of consecutive rows, also known as linesize or pitch.
+    pub stride: usize,
+    /// Bytes of pixel data in a row, without the padding up to the stride.
+    pub row_bytes: usize,
+    /// Width in pixels. Chroma planes of subsampled formats are narrower than the frame.
+    pub width: u32,
+    /// Height in rows. Chroma planes of subsampled formats are shorter than the frame.
+    pub height: u32,
+}
+
+impl<'a> FramePlane<'a> {
+    /// Get the pixel data of a row, without padding. Returns `None` past the last row.
+    ///
+    /// # Arguments
+    ///
+    /// * `y` - Index of row.
+    pub fn row(&self, y: u32) -> Option<&'a [u8]> {
+        if y >= self.height {
+            return None;
+        }
+        let start = y as usize * self.stride;
+        self.data.get(start..start + self.row_bytes)
+    }
+
+    /// Iterate over the pixel data of the rows, without padding, from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &'a [u8]> {
+        let plane = *self;
+        (0..plane.height).filter_map(move |y| plane.row(y))
+    }
+}
+
+/// Get the number of planes of a frame in system memory: one for packed formats such as RGB24,
+/// two for NV12 and three for YUV420P. Hardware frames have no planes in system memory.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get number of planes of.
+pub fn plane_count(frame: &RawFrame) -> usize {
+    if ffi_hwaccel::frame_is_hardware(frame) {
+        0
+    } else {
+        frame.planes()
+    }
+}
+
+/// Get a plane of a frame in system memory.
+///
+/// Fails with [`Error::InvalidFrameFormat`] if the frame has no such plane, if it is a hardware
+/// frame, or if it is stored bottom-up.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get plane of.
+/// * `index` - Index of plane, less than [`plane_count`].
+///
+/// # Example
+///
+/// ```ignore
+/// let frame = convert(&decoder.decode_raw().unwrap(), PixelFormat::YUV420P, None).unwrap();
+/// let [y, u, v] = [0, 1, 2].map(|index| frame_plane(&frame, index).unwrap());
+/// texture
+///     .update_yuv(None, y.data, y.stride, u.data, u.stride, v.data, v.stride)
+///     .unwrap();
+/// ```
+pub fn frame_plane(frame: &RawFrame, index: usize) -> Result<FramePlane<'_>, Error> {
+    if index >= plane_count(frame) || ffi::frame_linesize(frame, index) <= 0 {
+        return Err(Error::InvalidFrameFormat);
+    }
+    let stride = frame.stride(index);
+    let row_bytes = ffi::image_row_bytes(frame.format(), frame.width(), index)
+        .filter(|&row_bytes| row_bytes <= stride)
+        .ok_or(Error::InvalidFrameFormat)?;
+    Ok(FramePlane {
+        data: frame.data(index),
+        stride,
+        row_bytes,
+        width: frame.plane_width(index),
+        height: frame.plane_height(index),
+    })
+}
+
+/// Get all planes of a frame in system memory. See [`frame_plane`].
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get planes of.
+pub fn frame_planes(frame: &RawFrame) -> Result<Vec<FramePlane<'_>>, Error> {
+    (0..plane_count(frame))
+        .map(|index| frame_plane(frame, index))
+        .collect()
+}
+
+thread_local! {
+    // Scaler of the last conversion on this thread, reused while the conversion stays the same.
+    static CONVERT_SCALER: RefCell<Option<AvScaler>> = const { RefCell::new(None) };
+}
+
+/// Convert a frame to another pixel format and size, e.g. to prepare frames that were decoded or
+/// produced elsewhere for an encoder or a model.
+///
+/// The scaling context is cached per thread and reused as long as consecutive calls convert
+/// between the same formats and sizes, so converting a stream of frames costs no more than using a
+/// scaler directly. Hardware frames are downloaded first. The timestamp of the frame is kept.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to convert.
+/// * `format` - Pixel format to convert to.
+/// * `size` - Width and height to scale to, or `None` to keep the size of the frame.
+///
+/// # Example
+///
+/// ```ignore
+/// let frame = decoder.decode_raw().unwrap();
+/// let frame = convert(&frame, PixelFormat::GRAY8, Some((640, 360))).unwrap();
+/// ```
+pub fn convert(
+    frame: &RawFrame,
+    format: PixelFormat,
+    size: Option<(u32, u32)>,
+) -> Result<RawFrame, Error> {
+    let pts = frame.pts();
+    let frame_downloaded;
+    let frame = if ffi_hwaccel::frame_is_hardware(frame) {
+        let mut frame_transferred = RawFrame::empty();
+        ffi_hwaccel::hwdevice_transfer_frame(&mut frame_transferred, frame)?;
+        frame_downloaded = frame_transferred;
+        &frame_downloaded
+    } else {
+        frame
+    };
+    let (width, height) = size.unwrap_or((frame.width(), frame.height()));
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidResizeParameters);
+    }
+
+    CONVERT_SCALER.with_borrow_mut(|scaler| {
+        let is_same_conversion = scaler.as_ref().is_some_and(|scaler| {
+            let (input, output) = (scaler.input(), scaler.output());
+            (input.format, input.width, input.height)
+                == (frame.format(), frame.width(), frame.height())
+                && (output.format, output.width, output.height) == (format, width, height)
+        });
+        // A new scaler is made rather than updating the cached one, since `sws_getCachedContext`
+        // leaves a null context behind if the conversion is not supported.
+        let scaler = if is_same_conversion {
+            scaler.as_mut().expect("scaler is cached")
+        } else {
+            *scaler = None;
+            scaler.insert(AvScaler::get(
+                frame.format(),
+                frame.width(),
+                frame.height(),
+                format,
+                width,
+                height,
+                AvScalerFlags::AREA,
+            )?)
+        };
+        let mut frame_converted = RawFrame::empty();
+        scaler.run(frame, &mut frame_converted)?;
+        frame_converted.set_pts(pts);
+        Ok(frame_converted)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_reuses_scaler() {
+        let mut frame = RawFrame::new(AvPixel::RGB24, 8, 4);
+        frame.set_pts(Some(42));
+        let converted = convert(&frame, AvPixel::GRAY8, None).unwrap();
+        assert_eq!(converted.format(), AvPixel::GRAY8);
+        assert_eq!((converted.width(), converted.height()), (8, 4));
+        assert_eq!(converted.pts(), Some(42));
+        let converted = convert(&frame, AvPixel::YUV420P, Some((4, 2))).unwrap();
+        assert_eq!(converted.format(), AvPixel::YUV420P);
+        assert_eq!((converted.width(), converted.height()), (4, 2));
+        assert!(matches!(
+            convert(&frame, AvPixel::RGB24, Some((0, 2))),
+            Err(Error::InvalidResizeParameters)
+        ));
+    }
+
+    #[test]
+    fn test_convert_recovers_from_unsupported_conversion() {
+        let frame = RawFrame::new(AvPixel::RGB24, 8, 4);
+        convert(&frame, AvPixel::GRAY8, None).unwrap();
+        assert!(convert(&frame, AvPixel::VAAPI, None).is_err());
+        let converted = convert(&frame, AvPixel::GRAY8, None).unwrap();
+        assert_eq!(converted.format(), AvPixel::GRAY8);
+    }
+
+    #[test]
+    fn test_frame_planes_of_subsampled_formats() {
+        let mut frame = RawFrame::new(AvPixel::YUV420P, 5, 3);
+        assert_eq!(plane_count(&frame), 3);
+        let stride = frame.stride(1);
+        frame.data_mut(1)[stride + 2] = 7;
+        let planes = frame_planes(&frame).unwrap();
+        assert_eq!(
+            (planes[0].width, planes[0].height, planes[0].row_bytes),
+            (5, 3, 5)
+        );
+        assert_eq!(
+            (planes[1].width, planes[1].height, planes[1].row_bytes),
+            (3, 2, 3)
+        );
+        assert_eq!(planes[1].row(1), Some(&[0, 0, 7][..]));
+        assert_eq!(planes[1].row(2), None);
+        assert_eq!(planes[2].rows().count(), 2);
+
+        let frame = RawFrame::new(AvPixel::NV12, 5, 3);
+        let chroma = frame_plane(&frame, 1).unwrap();
+        assert_eq!((chroma.width, chroma.row_bytes), (3, 6));
+        assert!(matches!(
+            frame_plane(&frame, 2),
+            Err(Error::InvalidFrameFormat)
+        ));
+        assert_eq!(plane_count(&RawFrame::empty()), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "ndarray")]
+    fn test_frame_view_follows_stride() {
+        let mut frame = RawFrame::new(AvPixel::RGB24, 5, 3);
+        let stride = frame.stride(0);
+        // Rows are padded to the alignment of ffmpeg.
+        assert!(stride > 15);
+        frame.data_mut(0)[2 * stride + 4 * 3 + 1] = 200;
+        let view = frame_view(&frame).unwrap();
+        assert_eq!(view.dim(), (3, 5, 3));
+        assert_eq!(view[[2, 4, 1]], 200);
+        assert_eq!(view.to_owned()[[2, 4, 1]], 200);
+    }
+
+    #[test]
+    #[cfg(feature = "ndarray")]
+    fn test_frame_view_rejects_planar_frames() {
+        let frame = RawFrame::new(AvPixel::YUV420P, 4, 4);
+        assert!(matches!(frame_view(&frame), Err(Error::InvalidFrameFormat)));
+    }
+}
@@ -0,0 +1,236 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::io::{Read, Write};
+
+use ffmpeg::util::format::Pixel as AvPixel;
+
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::ffi_hwaccel;
+use crate::core::frame::{frame_planes, RawFrame};
+#[cfg(feature = "ndarray")]
+use crate::core::frame::{frame_view, Frame};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Identifies a frame dump.
+const MAGIC: [u8; 4] = *b"RAWF";
+
+/// Version of the layout of a frame dump.
+const VERSION: u8 = 1;
+
+/// Largest width or height of a frame that is restored. Protects against allocating huge frames
+/// for corrupt dumps.
+const MAX_DIMENSION: u32 = 1 << 15;
+
+/// Largest number of padding bytes at the end of a row of a frame that is restored.
+const MAX_ROW_PADDING: usize = 4096;
+
+/// Write a raw frame in a compact binary layout, so that it can be cached on disk or sent to
+/// another process, and restored with [`read_frame`]. The dump holds the pixel format, size,
+/// timestamp, duration and the planes of the frame including their stride. Hardware frames are
+/// downloaded first.
+///
+/// All numbers are little-endian, and the pixel format is stored by name, so dumps can be read on
+/// other machines and with other versions of ffmpeg.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to write.
+/// * `writer` - Where to write the frame to, e.g. a file or a socket.
+///
+/// # Example
+///
+/// ```ignore
+/// let frame = decoder.decode_raw().unwrap();
+/// write_frame(&frame, BufWriter::new(File::create("frame.raw").unwrap())).unwrap();
+/// let frame = read_frame(BufReader::new(File::open("frame.raw").unwrap())).unwrap();
+/// ```
+pub fn write_frame(frame: &RawFrame, mut writer: impl Write) -> Result<()> {
+    let frame_downloaded;
+    let frame = if ffi_hwaccel::frame_is_hardware(frame) {
+        let mut frame_transferred = RawFrame::empty();
+        ffi_hwaccel::hwdevice_transfer_frame(&mut frame_transferred, frame)?;
+        ffi::copy_frame_props(frame, &mut frame_transferred);
+        frame_downloaded = frame_transferred;
+        &frame_downloaded
+    } else {
+        frame
+    };
+    let format_name = frame
+        .format()
+        .descriptor()
+        .map(|descriptor| descriptor.name())
+        .ok_or(Error::InvalidFrameFormat)?;
+    let planes = frame_planes(frame)?;
+
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&[VERSION, format_name.len() as u8])?;
+    writer.write_all(format_name.as_bytes())?;
+    writer.write_all(&frame.width().to_le_bytes())?;
+    writer.write_all(&frame.height().to_le_bytes())?;
+    writer.write_all(&[frame.pts().is_some() as u8])?;
+    writer.write_all(&frame.pts().unwrap_or_default().to_le_bytes())?;
+    writer.write_all(&ffi::get_frame_duration(frame).to_le_bytes())?;
+    writer.write_all(&[planes.len() as u8])?;
+    for plane in planes {
+        writer.write_all(&(plane.stride as u32).to_le_bytes())?;
+        writer.write_all(&plane.height.to_le_bytes())?;
+        writer.write_all(plane.data)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Restore a raw frame written by [`write_frame`]. The restored frame has the same pixel format,
+/// size, timestamp and pixels, though its stride may differ if this build of ffmpeg aligns rows
+/// differently.
+///
+/// Fails with [`Error::InvalidFrameFormat`] if the data is not a frame dump, or if its pixel
+/// format is unknown.
+///
+/// # Arguments
+///
+/// * `reader` - Where to read the frame from.
+pub fn read_frame(mut reader: impl Read) -> Result<RawFrame> {
+    let [magic @ .., version, format_name_len] = read_array::<6>(&mut reader)?;
+    if magic != MAGIC || version != VERSION {
+        return Err(Error::InvalidFrameFormat);
+    }
+    let mut format_name = vec![0; format_name_len as usize];
+    reader.read_exact(&mut format_name)?;
+    let format: AvPixel = std::str::from_utf8(&format_name)
+        .ok()
+        .and_then(|format_name| format_name.parse().ok())
+        .ok_or(Error::InvalidFrameFormat)?;
+    let width = u32::from_le_bytes(read_array(&mut reader)?);
+    let height = u32::from_le_bytes(read_array(&mut reader)?);
+    let [has_pts] = read_array(&mut reader)?;
+    let pts = i64::from_le_bytes(read_array(&mut reader)?);
+    let duration = i64::from_le_bytes(read_array(&mut reader)?);
+    let [plane_count] = read_array(&mut reader)?;
+    if !(1..=MAX_DIMENSION).contains(&width) || !(1..=MAX_DIMENSION).contains(&height) {
+        return Err(Error::InvalidFrameFormat);
+    }
+
+    // Frames of formats without planes in system memory, such as hardware formats, cannot be
+    // allocated.
+    let mut frame = RawFrame::new(format, width, height);
+    if frame.planes() == 0 || plane_count as usize != frame.planes() {
+        return Err(Error::InvalidFrameFormat);
+    }
+    for index in 0..frame.planes() {
+        let stride = u32::from_le_bytes(read_array(&mut reader)?) as usize;
+        let rows = u32::from_le_bytes(read_array(&mut reader)?);
+        let row_bytes =
+            ffi::image_row_bytes(format, width, index).ok_or(Error::InvalidFrameFormat)?;
+        if rows != frame.plane_height(index)
+            || stride < row_bytes
+            || stride > row_bytes + MAX_ROW_PADDING
+        {
+            return Err(Error::InvalidFrameFormat);
+        }
+        let mut data = vec![0; stride * rows as usize];
+        reader.read_exact(&mut data)?;
+        let frame_stride = frame.stride(index);
+        let frame_data = frame.data_mut(index);
+        for (row, frame_row) in data
+            .chunks_exact(stride)
+            .zip(frame_data.chunks_exact_mut(frame_stride))
+        {
+            frame_row[..row_bytes].copy_from_slice(&row[..row_bytes]);
+        }
+    }
+    frame.set_pts((has_pts != 0).then_some(pts));
+    ffi::set_frame_duration(&mut frame, duration);
+    Ok(frame)
+}
+
+/// Write an `ndarray` frame in the layout of [`write_frame`], as an RGB24 or RGBA frame without
+/// timestamp.
+///
+/// # Arguments
+///
+/// * `frame` - Frame in `HWC` format and standard layout, with three or four channels.
+/// * `writer` - Where to write the frame to.
+#[cfg(feature = "ndarray")]
+pub fn write_ndarray_frame(frame: &Frame, writer: impl Write) -> Result<()> {
+    let frame = match frame.dim().2 {
+        3 => ffi::convert_ndarray_to_frame_rgb24(frame),
+        4 => ffi::convert_ndarray_to_frame_rgba(frame),
+        _ => return Err(Error::InvalidFrameFormat),
+    }
+    .map_err(Error::BackendError)?;
+    write_frame(&frame, writer)
+}
+
+/// Restore an `ndarray` frame from a frame dump. Fails with [`Error::InvalidFrameFormat`] unless
+/// the dumped frame has packed pixels, see [`frame_view`].
+///
+/// # Arguments
+///
+/// * `reader` - Where to read the frame from.
+#[cfg(feature = "ndarray")]
+pub fn read_ndarray_frame(reader: impl Read) -> Result<Frame> {
+    let frame = read_frame(reader)?;
+    Ok(frame_view(&frame)?.to_owned())
+}
+
+/// Read a fixed number of bytes.
+///
+/// # Arguments
+///
+/// * `reader` - Where to read from.
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut frame = RawFrame::new(AvPixel::YUV420P, 5, 3);
+        frame.set_pts(Some(1234));
+        for index in 0..3 {
+            let stride = frame.stride(index);
+            frame.data_mut(index)[stride + 1] = index as u8 + 10;
+        }
+        let mut dump = Vec::new();
+        write_frame(&frame, &mut dump).unwrap();
+        let restored = read_frame(dump.as_slice()).unwrap();
+        assert_eq!(restored.format(), AvPixel::YUV420P);
+        assert_eq!((restored.width(), restored.height()), (5, 3));
+        assert_eq!(restored.pts(), Some(1234));
+        for index in 0..3 {
+            let stride = restored.stride(index);
+            assert_eq!(restored.data(index)[stride + 1], index as u8 + 10);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_dumps() {
+        assert!(matches!(
+            read_frame(&b"NOPE\x01\x00"[..]),
+            Err(Error::InvalidFrameFormat)
+        ));
+        let mut dump = Vec::new();
+        write_frame(&RawFrame::new(AvPixel::GRAY8, 4, 4), &mut dump).unwrap();
+        assert!(matches!(
+            read_frame(&dump[..dump.len() - 1]),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "ndarray")]
+    fn test_ndarray_round_trip() {
+        let frame = Frame::from_shape_fn((2, 3, 4), |(y, x, c)| (y * 12 + x * 4 + c) as u8);
+        let mut dump = Vec::new();
+        write_ndarray_frame(&frame, &mut dump).unwrap();
+        assert_eq!(read_ndarray_frame(dump.as_slice()).unwrap(), frame);
+    }
+}
@@ -0,0 +1,303 @@
+use ndarray::{ArrayView2, ArrayView3, Axis};
+
+use crate::core::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Histogram of 8-bit samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    /// Number of samples with each value.
+    pub bins: [u64; 256],
+}
+
+impl Histogram {
+    /// Number of samples in the histogram.
+    pub fn total(&self) -> u64 {
+        self.bins.iter().sum()
+    }
+
+    /// Mean value of the samples. `None` if the histogram is empty.
+    pub fn mean(&self) -> Option<f64> {
+        let total = self.total();
+        let sum = self
+            .bins
+            .iter()
+            .enumerate()
+            .map(|(value, &count)| value as u64 * count)
+            .sum::<u64>();
+        (total > 0).then(|| sum as f64 / total as f64)
+    }
+
+    /// Smallest value that at least the given fraction of the samples is at or below, e.g. `0.5`
+    /// for the median. `None` if the histogram is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `fraction` - Fraction of samples, from `0` to `1`.
+    pub fn percentile(&self, fraction: f64) -> Option<u8> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let target = ((fraction.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut cumulative = 0;
+        self.bins
+            .iter()
+            .position(|&count| {
+                cumulative += count;
+                cumulative >= target
+            })
+            .map(|value| value as u8)
+    }
+
+    /// Fraction of the samples at or below a value, e.g. to detect black frames by the fraction
+    /// of dark pixels. Zero if the histogram is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Largest value to count.
+    pub fn fraction_at_most(&self, value: u8) -> f64 {
+        let total = self.total();
+        if total == 0 {
+            return 0.0;
+        }
+        self.bins[..=value as usize].iter().sum::<u64>() as f64 / total as f64
+    }
+}
+
+/// Histogram that is being counted. Counts go to four interleaved partial histograms, so that runs
+/// of equal values do not stall on incrementing the same counter, and are merged at the end.
+struct PartialHistogram {
+    counts: Box<[[u64; 256]; 4]>,
+}
+
+impl PartialHistogram {
+    /// Create an empty histogram.
+    fn new() -> Self {
+        Self {
+            counts: Box::new([[0; 256]; 4]),
+        }
+    }
+
+    /// Count samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Samples to count.
+    fn add(&mut self, samples: &[u8]) {
+        let [counts_0, counts_1, counts_2, counts_3] = &mut *self.counts;
+        let chunks = samples.chunks_exact(4);
+        for &sample in chunks.remainder() {
+            counts_0[sample as usize] += 1;
+        }
+        for chunk in chunks {
+            counts_0[chunk[0] as usize] += 1;
+            counts_1[chunk[1] as usize] += 1;
+            counts_2[chunk[2] as usize] += 1;
+            counts_3[chunk[3] as usize] += 1;
+        }
+    }
+
+    /// Merge the partial histograms.
+    fn finish(self) -> Histogram {
+        let mut bins = [0; 256];
+        for (value, bin) in bins.iter_mut().enumerate() {
+            *bin = self.counts.iter().map(|counts| counts[value]).sum();
+        }
+        Histogram { bins }
+    }
+}
+
+/// Statistics of the samples of one channel of a frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChannelStatistics {
+    /// Mean value.
+    pub mean: f64,
+    /// Smallest value.
+    pub min: u8,
+    /// Largest value.
+    pub max: u8,
+    /// Value that 5% of the samples are at or below.
+    pub p5: u8,
+    /// Median value.
+    pub median: u8,
+    /// Value that 95% of the samples are at or below.
+    pub p95: u8,
+}
+
+impl ChannelStatistics {
+    /// Compute statistics from a histogram. `None` if the histogram is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `histogram` - Histogram of the channel.
+    pub fn from_histogram(histogram: &Histogram) -> Option<Self> {
+        Some(Self {
+            mean: histogram.mean()?,
+            min: histogram.percentile(0.0)?,
+            max: histogram.bins.iter().rposition(|&count| count > 0)? as u8,
+            p5: histogram.percentile(0.05)?,
+            median: histogram.percentile(0.5)?,
+            p95: histogram.percentile(0.95)?,
+        })
+    }
+}
+
+/// Compute the histogram of the luma of a frame, e.g. to judge its exposure or to detect black
+/// frames. Luma is computed with integer BT.601 weights for RGB and RGBA frames.
+///
+/// Fails with [`Error::InvalidFrameFormat`] unless the frame has one, three or four channels.
+///
+/// # Arguments
+///
+/// * `frame` - Frame with dimensions `(H, W, C)`, e.g. from
+///   [`Decoder::decode`](crate::core::decode::Decoder::decode), or a view from
+///   [`frame_view`](crate::core::frame::frame_view).
+///
+/// # Example
+///
+/// ```ignore
+/// let (_, frame) = decoder.decode().unwrap();
+/// let histogram = luma_histogram(frame.view()).unwrap();
+/// let is_black = histogram.fraction_at_most(16) > 0.98;
+/// ```
+pub fn luma_histogram(frame: ArrayView3<'_, u8>) -> Result<Histogram> {
+    let channels = frame.dim().2;
+    match channels {
+        1 => return Ok(channel_histogram(frame.index_axis(Axis(2), 0))),
+        3 | 4 => {}
+        _ => return Err(Error::InvalidFrameFormat),
+    }
+    let mut histogram = PartialHistogram::new();
+    let mut luma_row = Vec::with_capacity(frame.dim().1);
+    for row in frame.outer_iter() {
+        luma_row.clear();
+        match row.as_slice() {
+            // Rows of contiguous pixels convert in a loop that the compiler can vectorize.
+            Some(row) => luma_row.extend(
+                row.chunks_exact(channels)
+                    .map(|pixel| luma(pixel[0], pixel[1], pixel[2])),
+            ),
+            None => luma_row.extend(
+                row.outer_iter()
+                    .map(|pixel| luma(pixel[0], pixel[1], pixel[2])),
+            ),
+        }
+        histogram.add(&luma_row);
+    }
+    Ok(histogram.finish())
+}
+
+/// Compute the mean luma of a frame, from `0` to `255`. See [`luma_histogram`].
+///
+/// # Arguments
+///
+/// * `frame` - Frame with dimensions `(H, W, C)`.
+pub fn mean_luma(frame: ArrayView3<'_, u8>) -> Result<f64> {
+    luma_histogram(frame)?
+        .mean()
+        .ok_or(Error::InvalidFrameFormat)
+}
+
+/// Compute the histogram of each channel of a frame.
+///
+/// # Arguments
+///
+/// * `frame` - Frame with dimensions `(H, W, C)`.
+pub fn channel_histograms(frame: ArrayView3<'_, u8>) -> Vec<Histogram> {
+    frame.axis_iter(Axis(2)).map(channel_histogram).collect()
+}
+
+/// Compute the mean, extremes and percentiles of each channel of a frame.
+///
+/// Fails with [`Error::InvalidFrameFormat`] if the frame is empty.
+///
+/// # Arguments
+///
+/// * `frame` - Frame with dimensions `(H, W, C)`.
+pub fn channel_statistics(frame: ArrayView3<'_, u8>) -> Result<Vec<ChannelStatistics>> {
+    channel_histograms(frame)
+        .iter()
+        .map(|histogram| {
+            ChannelStatistics::from_histogram(histogram).ok_or(Error::InvalidFrameFormat)
+        })
+        .collect()
+}
+
+/// Compute the luma of a pixel with integer BT.601 weights.
+///
+/// # Arguments
+///
+/// * `r` - Red.
+/// * `g` - Green.
+/// * `b` - Blue.
+#[inline]
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((77 * r as u32 + 150 * g as u32 + 29 * b as u32 + 128) >> 8) as u8
+}
+
+/// Compute the histogram of one channel of a frame.
+///
+/// # Arguments
+///
+/// * `channel` - Samples of the channel, with dimensions `(H, W)`.
+fn channel_histogram(channel: ArrayView2<'_, u8>) -> Histogram {
+    let mut histogram = PartialHistogram::new();
+    let mut row_samples = Vec::with_capacity(channel.dim().1);
+    for row in channel.outer_iter() {
+        match row.as_slice() {
+            Some(row) => histogram.add(row),
+            // Samples of packed frames are interleaved with the other channels.
+            None => {
+                row_samples.clear();
+                row_samples.extend(row.iter().copied());
+                histogram.add(&row_samples);
+            }
+        }
+    }
+    histogram.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{s, Array3};
+
+    use super::*;
+
+    #[test]
+    fn test_histogram_statistics() {
+        let mut histogram = Histogram { bins: [0; 256] };
+        histogram.bins[10] = 50;
+        histogram.bins[200] = 50;
+        assert_eq!(histogram.total(), 100);
+        assert_eq!(histogram.mean(), Some(105.0));
+        assert_eq!(histogram.percentile(0.5), Some(10));
+        assert_eq!(histogram.percentile(0.51), Some(200));
+        assert_eq!(histogram.fraction_at_most(16), 0.5);
+        let statistics = ChannelStatistics::from_histogram(&histogram).unwrap();
+        assert_eq!((statistics.min, statistics.max), (10, 200));
+        assert_eq!(Histogram { bins: [0; 256] }.percentile(0.5), None);
+    }
+
+    #[test]
+    fn test_luma_histogram() {
+        let mut frame = Array3::<u8>::zeros((4, 5, 3));
+        frame.slice_mut(s![..2, .., ..]).fill(255);
+        let histogram = luma_histogram(frame.view()).unwrap();
+        assert_eq!((histogram.bins[0], histogram.bins[255]), (10, 10));
+        assert_eq!(mean_luma(frame.view()).unwrap(), 127.5);
+        assert_eq!(luma(255, 0, 0), 77);
+        assert!(luma_histogram(Array3::<u8>::zeros((2, 2, 2)).view()).is_err());
+    }
+
+    #[test]
+    fn test_channel_statistics_of_strided_view() {
+        let frame = Array3::from_shape_fn((3, 6, 3), |(y, x, c)| (y * 6 + x + c * 100) as u8);
+        let statistics = channel_statistics(frame.slice(s![.., ..;2, ..])).unwrap();
+        assert_eq!(statistics.len(), 3);
+        assert_eq!((statistics[0].min, statistics[0].max), (0, 16));
+        assert_eq!((statistics[2].min, statistics[2].max), (200, 216));
+        assert_eq!(statistics[0].median, 8);
+    }
+}
@@ -0,0 +1,266 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::ffi::AVRational;
+
+use crate::core::color::ColorTransfer;
+use crate::core::ffi;
+
+/// Dynamic range format of a video stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HdrFormat {
+    /// Standard dynamic range.
+    Sdr,
+    /// HDR10: PQ (SMPTE ST 2084) transfer, usually with static mastering display metadata.
+    Hdr10,
+    /// Hybrid log-gamma (ARIB STD-B67) transfer.
+    Hlg,
+    /// Dolby Vision.
+    DolbyVision,
+}
+
+/// Color volume of the display the content was mastered on (SMPTE ST 2086).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MasteringDisplay {
+    /// CIE 1931 xy chromaticity of the red, green and blue primaries, if known.
+    pub primaries: Option<[(f64, f64); 3]>,
+    /// CIE 1931 xy chromaticity of the white point, if known.
+    pub white_point: Option<(f64, f64)>,
+    /// Minimum luminance in cd/m², if known.
+    pub min_luminance: Option<f64>,
+    /// Maximum luminance in cd/m², if known.
+    pub max_luminance: Option<f64>,
+}
+
+impl MasteringDisplay {
+    /// Convert backend mastering display metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - Backend metadata.
+    fn from_backend(metadata: &ffi::AVMasteringDisplayMetadata) -> Self {
+        let has_primaries = metadata.has_primaries != 0;
+        let has_luminance = metadata.has_luminance != 0;
+        let xy = |xy: &[AVRational; 2]| (rational_to_f64(xy[0]), rational_to_f64(xy[1]));
+        Self {
+            primaries: has_primaries.then(|| {
+                [
+                    xy(&metadata.display_primaries[0]),
+                    xy(&metadata.display_primaries[1]),
+                    xy(&metadata.display_primaries[2]),
+                ]
+            }),
+            white_point: has_primaries.then(|| xy(&metadata.white_point)),
+            min_luminance: has_luminance.then(|| rational_to_f64(metadata.min_luminance)),
+            max_luminance: has_luminance.then(|| rational_to_f64(metadata.max_luminance)),
+        }
+    }
+}
+
+/// Content light level of a video stream (CTA-861.3).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ContentLightLevel {
+    /// Maximum content light level (MaxCLL) in cd/m².
+    pub max_content_light_level: u32,
+    /// Maximum frame-average light level (MaxFALL) in cd/m².
+    pub max_frame_average_light_level: u32,
+}
+
+impl ContentLightLevel {
+    /// Convert backend content light level metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - Backend metadata.
+    fn from_backend(metadata: &ffi::AVContentLightMetadata) -> Self {
+        Self {
+            max_content_light_level: metadata.max_cll,
+            max_frame_average_light_level: metadata.max_fall,
+        }
+    }
+}
+
+/// Dolby Vision configuration of a video stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DolbyVisionConfiguration {
+    /// Dolby Vision profile, e.g. `5` for single-layer IPTPQc2 or `8` for a base layer that is
+    /// compatible with HDR10, SDR or HLG.
+    pub profile: u8,
+    /// Dolby Vision level, which limits resolution and frame rate.
+    pub level: u8,
+    /// Whether or not the stream carries reference processing unit (RPU) metadata.
+    pub has_rpu: bool,
+    /// Whether or not the stream carries an enhancement layer.
+    pub has_enhancement_layer: bool,
+    /// Whether or not the stream carries a base layer.
+    pub has_base_layer: bool,
+    /// Signals what the base layer is compatible with: `0` for none, `1` for HDR10, `2` for SDR,
+    /// `4` for HLG and `6` for HDR10 (Blu-ray).
+    pub base_layer_compatibility_id: u8,
+}
+
+impl DolbyVisionConfiguration {
+    /// Convert a backend Dolby Vision configuration record.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - Backend configuration record.
+    fn from_backend(record: &ffi::AVDOVIDecoderConfigurationRecord) -> Self {
+        Self {
+            profile: record.dv_profile,
+            level: record.dv_level,
+            has_rpu: record.rpu_present_flag != 0,
+            has_enhancement_layer: record.el_present_flag != 0,
+            has_base_layer: record.bl_present_flag != 0,
+            base_layer_compatibility_id: record.dv_bl_signal_compatibility_id,
+        }
+    }
+
+    /// Get the format of the base layer, as seen by a player that does not support Dolby Vision.
+    /// Streams without a compatible base layer (such as profile 5) cannot be shown correctly
+    /// without Dolby Vision, in which case this returns `None`.
+    pub fn base_layer_format(&self) -> Option<HdrFormat> {
+        match self.base_layer_compatibility_id {
+            1 | 6 => Some(HdrFormat::Hdr10),
+            2 => Some(HdrFormat::Sdr),
+            4 => Some(HdrFormat::Hlg),
+            _ => None,
+        }
+    }
+}
+
+/// HDR metadata of a video stream, used to decide whether to tone-map or pass through.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HdrMetadata {
+    /// Dynamic range format. Dolby Vision takes precedence over the format of its base layer.
+    pub format: HdrFormat,
+    /// Dynamic range format of the video when Dolby Vision metadata is ignored, as derived from
+    /// the transfer characteristic.
+    pub base_layer_format: HdrFormat,
+    /// Color volume of the mastering display, if the stream carries it.
+    pub mastering_display: Option<MasteringDisplay>,
+    /// Content light level, if the stream carries it.
+    pub content_light_level: Option<ContentLightLevel>,
+    /// Dolby Vision configuration, if the stream carries Dolby Vision.
+    pub dolby_vision: Option<DolbyVisionConfiguration>,
+}
+
+impl HdrMetadata {
+    /// Combine HDR metadata from its parts.
+    ///
+    /// # Arguments
+    ///
+    /// * `transfer` - Transfer characteristic of the stream.
+    /// * `mastering_display` - Mastering display metadata, if any.
+    /// * `content_light_level` - Content light level, if any.
+    /// * `dolby_vision` - Dolby Vision configuration, if any.
+    pub(crate) fn new(
+        transfer: ColorTransfer,
+        mastering_display: Option<&ffi::AVMasteringDisplayMetadata>,
+        content_light_level: Option<&ffi::AVContentLightMetadata>,
+        dolby_vision: Option<&ffi::AVDOVIDecoderConfigurationRecord>,
+    ) -> Self {
+        let dolby_vision = dolby_vision.map(DolbyVisionConfiguration::from_backend);
+        let base_layer_format = match transfer {
+            ColorTransfer::SMPTE2084 => HdrFormat::Hdr10,
+            ColorTransfer::ARIB_STD_B67 => HdrFormat::Hlg,
+            _ => dolby_vision
+                .and_then(|dolby_vision| dolby_vision.base_layer_format())
+                .unwrap_or(HdrFormat::Sdr),
+        };
+        Self {
+            format: if dolby_vision.is_some() {
+                HdrFormat::DolbyVision
+            } else {
+                base_layer_format
+            },
+            base_layer_format,
+            mastering_display: mastering_display.map(MasteringDisplay::from_backend),
+            content_light_level: content_light_level.map(ContentLightLevel::from_backend),
+            dolby_vision,
+        }
+    }
+
+    /// Whether or not the video has a high dynamic range.
+    #[inline]
+    pub fn is_hdr(&self) -> bool {
+        self.format != HdrFormat::Sdr
+    }
+}
+
+/// Convert a backend rational to a float. Returns `0.0` for rationals with a zero denominator.
+///
+/// # Arguments
+///
+/// * `rational` - Rational to convert.
+fn rational_to_f64(rational: AVRational) -> f64 {
+    if rational.den == 0 {
+        0.0
+    } else {
+        rational.num as f64 / rational.den as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dovi(profile: u8, base_layer_compatibility_id: u8) -> ffi::AVDOVIDecoderConfigurationRecord {
+        ffi::AVDOVIDecoderConfigurationRecord {
+            dv_version_major: 1,
+            dv_version_minor: 0,
+            dv_profile: profile,
+            dv_level: 6,
+            rpu_present_flag: 1,
+            el_present_flag: 0,
+            bl_present_flag: 1,
+            dv_bl_signal_compatibility_id: base_layer_compatibility_id,
+        }
+    }
+
+    #[test]
+    fn test_format_from_transfer() {
+        let pq = HdrMetadata::new(ColorTransfer::SMPTE2084, None, None, None);
+        assert_eq!(pq.format, HdrFormat::Hdr10);
+        assert!(pq.is_hdr());
+        let hlg = HdrMetadata::new(ColorTransfer::ARIB_STD_B67, None, None, None);
+        assert_eq!(hlg.format, HdrFormat::Hlg);
+        let sdr = HdrMetadata::new(ColorTransfer::BT709, None, None, None);
+        assert!(!sdr.is_hdr());
+    }
+
+    #[test]
+    fn test_dolby_vision_base_layer() {
+        let profile_8 = HdrMetadata::new(ColorTransfer::Unspecified, None, None, Some(&dovi(8, 1)));
+        assert_eq!(profile_8.format, HdrFormat::DolbyVision);
+        assert_eq!(profile_8.base_layer_format, HdrFormat::Hdr10);
+        let profile_5 = HdrMetadata::new(ColorTransfer::Unspecified, None, None, Some(&dovi(5, 0)));
+        assert_eq!(profile_5.format, HdrFormat::DolbyVision);
+        assert_eq!(
+            profile_5.dolby_vision.and_then(|dv| dv.base_layer_format()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_mastering_display_conversion() {
+        let rational = |num, den| AVRational { num, den };
+        let metadata = ffi::AVMasteringDisplayMetadata {
+            display_primaries: [
+                [rational(34000, 50000), rational(16000, 50000)],
+                [rational(13250, 50000), rational(34500, 50000)],
+                [rational(7500, 50000), rational(3000, 50000)],
+            ],
+            white_point: [rational(15635, 50000), rational(16450, 50000)],
+            min_luminance: rational(50, 10000),
+            max_luminance: rational(10000000, 10000),
+            has_primaries: 1,
+            has_luminance: 1,
+        };
+        let display = MasteringDisplay::from_backend(&metadata);
+        assert_eq!(display.max_luminance, Some(1000.0));
+        assert_eq!(
+            display.primaries.map(|primaries| primaries[0]),
+            Some((0.68, 0.32))
+        );
+    }
+}
@@ -0,0 +1,192 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::time::Duration;
+
+use crate::core::error::Error;
+use crate::core::io::private::Write as _;
+use crate::core::io::{delegate_writer, Writer, WriterBuilder};
+use crate::core::location::Location;
+use crate::core::options::Options;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Container format of HLS segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HlsSegmentType {
+    /// MPEG transport stream segments (`.ts`).
+    #[default]
+    MpegTs,
+    /// Fragmented MP4 segments (`.m4s`) with a separate initialization segment.
+    Fmp4,
+}
+
+impl HlsSegmentType {
+    /// Name of the segment type as understood by the HLS muxer.
+    fn as_str(self) -> &'static str {
+        match self {
+            HlsSegmentType::MpegTs => "mpegts",
+            HlsSegmentType::Fmp4 => "fmp4",
+        }
+    }
+}
+
+/// Builds an [`HlsWriter`].
+pub struct HlsWriterBuilder<'a> {
+    playlist: Location,
+    segment_type: HlsSegmentType,
+    segment_duration: Option<Duration>,
+    playlist_size: Option<usize>,
+    delete_old_segments: bool,
+    segment_filename: Option<&'a str>,
+    options: Option<&'a Options>,
+}
+
+impl<'a> HlsWriterBuilder<'a> {
+    /// Create a writer that writes an HLS playlist to `playlist` and puts the segments next to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `playlist` - Where to write the `m3u8` playlist.
+    pub fn new(playlist: impl Into<Location>) -> Self {
+        Self {
+            playlist: playlist.into(),
+            segment_type: HlsSegmentType::default(),
+            segment_duration: None,
+            playlist_size: None,
+            delete_old_segments: false,
+            segment_filename: None,
+            options: None,
+        }
+    }
+
+    /// Set the container format of the segments. Defaults to MPEG-TS.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment_type` - Segment container format.
+    pub fn with_segment_type(mut self, segment_type: HlsSegmentType) -> Self {
+        self.segment_type = segment_type;
+        self
+    }
+
+    /// Set the target segment duration. Segments are cut on keyframes, so the actual duration
+    /// depends on the keyframe interval of the stream. Defaults to 2 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment_duration` - Target duration of each segment.
+    pub fn with_segment_duration(mut self, segment_duration: Duration) -> Self {
+        self.segment_duration = Some(segment_duration);
+        self
+    }
+
+    /// Set the maximum number of segments in the playlist. Use `0` to keep all segments, which
+    /// produces a playlist that can be played back as video on demand. Defaults to 5.
+    ///
+    /// # Arguments
+    ///
+    /// * `playlist_size` - Maximum number of segments in the playlist.
+    pub fn with_playlist_size(mut self, playlist_size: usize) -> Self {
+        self.playlist_size = Some(playlist_size);
+        self
+    }
+
+    /// Delete segments from disk once they are no longer in the playlist.
+    pub fn with_delete_old_segments(mut self) -> Self {
+        self.delete_old_segments = true;
+        self
+    }
+
+    /// Set the filename pattern of the segments, e.g. `segment_%05d.ts`. By default the segments
+    /// are named after the playlist.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment_filename` - Filename pattern with a `printf`-style segment number.
+    pub fn with_segment_filename(mut self, segment_filename: &'a str) -> Self {
+        self.segment_filename = Some(segment_filename);
+        self
+    }
+
+    /// Specify options for the backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to output.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Build an [`HlsWriter`].
+    pub fn build(self) -> Result<HlsWriter> {
+        if self.segment_duration == Some(Duration::ZERO) {
+            return Err(Error::InvalidConfiguration {
+                field: "segment_duration",
+                reason: "segment duration must be greater than zero".to_string(),
+            });
+        }
+
+        let mut writer_builder = WriterBuilder::new(self.playlist).with_format("hls");
+        if let Some(options) = self.options {
+            writer_builder = writer_builder.with_options(options);
+        }
+        let mut writer = writer_builder.build()?;
+
+        let header_options = writer.header_options_mut();
+        header_options.set("hls_segment_type", self.segment_type.as_str());
+        if let Some(segment_duration) = self.segment_duration {
+            header_options.set("hls_time", &segment_duration.as_secs_f64().to_string());
+        }
+        if let Some(playlist_size) = self.playlist_size {
+            header_options.set("hls_list_size", &playlist_size.to_string());
+        }
+        if self.delete_old_segments {
+            header_options.set("hls_flags", "+delete_segments");
+        }
+        if let Some(segment_filename) = self.segment_filename {
+            header_options.set("hls_segment_filename", segment_filename);
+        }
+
+        Ok(HlsWriter { writer })
+    }
+}
+
+/// Writes HLS output: a series of MPEG-TS or fMP4 segments and an `m3u8` playlist that refers to
+/// them. It can be used with a [`Muxer`](crate::core::mux::Muxer), or passed to
+/// [`EncoderBuilder::from_writer`](crate::core::encode::EncoderBuilder::from_writer) to encode to
+/// it directly.
+///
+/// # Example
+///
+/// ```ignore
+/// let writer = HlsWriterBuilder::new(Path::new("live/playlist.m3u8"))
+///     .with_segment_duration(Duration::from_secs(4))
+///     .with_playlist_size(6)
+///     .with_delete_old_segments()
+///     .build()
+///     .unwrap();
+/// let mut encoder = EncoderBuilder::from_writer(writer, settings).build().unwrap();
+/// ```
+pub struct HlsWriter {
+    writer: Writer,
+}
+
+impl HlsWriter {
+    /// Create a writer that writes an HLS playlist to `playlist` with default settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `playlist` - Where to write the `m3u8` playlist.
+    #[inline]
+    pub fn new(playlist: impl Into<Location>) -> Result<Self> {
+        HlsWriterBuilder::new(playlist).build()
+    }
+
+    /// Location of the playlist.
+    pub fn playlist(&self) -> &Location {
+        &self.writer.destination
+    }
+}
+
+delegate_writer!(HlsWriter);
@@ -2,18 +2,20 @@ extern crate ffmpeg_next as ffmpeg;
 
 use crate::core::error::Error;
 use crate::core::ffi_hwaccel;
+use crate::core::frame::RawFrame;
 
 type Result<T> = std::result::Result<T, Error>;
 
 pub(crate) struct HardwareAccelerationContext {
     pixel_format: ffmpeg::util::format::Pixel,
-    _hardware_device_context: ffi_hwaccel::HardwareDeviceContext,
+    hardware_context: HardwareContext,
 }
 
 impl HardwareAccelerationContext {
     pub(crate) fn new(
         decoder: &mut ffmpeg::codec::Context,
         device_type: HardwareAccelerationDeviceType,
+        device: Option<&HardwareDevice>,
     ) -> Result<Self> {
         let codec = ffmpeg::codec::decoder::find(decoder.id()).ok_or(Error::UninitializedCodec)?;
         let pixel_format =
@@ -22,18 +24,386 @@ impl HardwareAccelerationContext {
 
         ffi_hwaccel::codec_context_hwaccel_set_get_format(decoder, pixel_format);
 
-        let hardware_device_context = ffi_hwaccel::HardwareDeviceContext::new(device_type)?;
-        ffi_hwaccel::codec_context_hwaccel_set_hw_device_ctx(decoder, &hardware_device_context);
+        let hardware_context = HardwareContext::open(device_type, device)?;
+        ffi_hwaccel::codec_context_hwaccel_set_hw_device_ctx(decoder, &hardware_context.device);
 
         Ok(HardwareAccelerationContext {
             pixel_format,
-            _hardware_device_context: hardware_device_context,
+            hardware_context,
         })
     }
 
     pub(crate) fn format(&self) -> ffmpeg::util::format::Pixel {
         self.pixel_format
     }
+
+    pub(crate) fn hardware_context(&self) -> &HardwareContext {
+        &self.hardware_context
+    }
+}
+
+/// Hardware device that a decoder or encoder runs on. Sharing the device of a decoder with a
+/// hardware encoder makes for full-GPU transcoding: decoded frames are encoded without leaving
+/// device memory.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut decoder = DecoderBuilder::new(source)
+///     .with_hardware_acceleration(HardwareAccelerationDeviceType::Cuda)
+///     .with_hardware_frames()
+///     .build()
+///     .unwrap();
+/// let mut encoder = EncoderBuilder::new(destination, settings)
+///     .with_shared_hardware_context(decoder.hardware_context().unwrap())
+///     .build()
+///     .unwrap();
+/// while let Ok(frame) = decoder.decode_raw() {
+///     encoder.encode_raw(frame).unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct HardwareContext {
+    device_type: HardwareAccelerationDeviceType,
+    device: ffi_hwaccel::HardwareDeviceContext,
+}
+
+impl HardwareContext {
+    /// Open a device.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_type` - Device type of the device.
+    /// * `device` - Device to open, or `None` for the default device.
+    pub(crate) fn open(
+        device_type: HardwareAccelerationDeviceType,
+        device: Option<&HardwareDevice>,
+    ) -> Result<Self> {
+        Ok(Self {
+            device_type,
+            device: HardwareDevice::open(device, device_type)?,
+        })
+    }
+
+    /// Device type of the device.
+    pub fn device_type(&self) -> HardwareAccelerationDeviceType {
+        self.device_type
+    }
+
+    /// Whether or not a frame lives in the memory of this device.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to check.
+    pub fn contains(&self, frame: &RawFrame) -> bool {
+        ffi_hwaccel::frame_is_on_device(frame, &self.device)
+    }
+
+    pub(crate) fn device(&self) -> &ffi_hwaccel::HardwareDeviceContext {
+        &self.device
+    }
+}
+
+impl std::fmt::Debug for HardwareContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HardwareContext")
+            .field("device_type", &self.device_type)
+            .finish_non_exhaustive()
+    }
+}
+
+unsafe impl Send for HardwareContext {}
+unsafe impl Sync for HardwareContext {}
+
+/// Whether or not a decoder actually decodes on a hardware device. See
+/// [`Decoder::hardware_acceleration_status`](crate::core::decode::Decoder::hardware_acceleration_status).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HardwareAccelerationStatus {
+    /// Frames are decoded on a device of this type.
+    Active(HardwareAccelerationDeviceType),
+    /// Frames are decoded in software.
+    Inactive {
+        /// Why hardware acceleration is not in use, for diagnostics.
+        reason: String,
+    },
+}
+
+/// Device to use for hardware acceleration, so that servers with several GPUs can pin decoding
+/// to a specific one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HardwareDevice {
+    /// Index of the device: the CUDA ordinal, the Vulkan physical device index, the DXGI adapter
+    /// index on Windows, or the n-th DRM render node (`/dev/dri/renderD128` and up) for VA-API, DRM
+    /// and Quick Sync Video on Linux.
+    Index(usize),
+    /// Device path or name, passed to the backend as is, such as `/dev/dri/renderD129`.
+    Path(std::path::PathBuf),
+}
+
+impl HardwareDevice {
+    /// Get the device string that the backend expects for a device type, or `None` if the device
+    /// type does not support selecting this device. VideoToolbox and MediaCodec always use the
+    /// system device, and VDPAU and OpenCL are only selected by path.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_type` - Device type of the device.
+    pub fn device_string(&self, device_type: HardwareAccelerationDeviceType) -> Option<String> {
+        use HardwareAccelerationDeviceType::*;
+
+        let render_node = |index: usize| format!("/dev/dri/renderD{}", 128 + index);
+        match (self, device_type) {
+            (_, VideoToolbox | MediaCodec) => None,
+            (HardwareDevice::Path(path), _) => Some(path.to_string_lossy().into_owned()),
+            (HardwareDevice::Index(index), Cuda | Vulkan | D3D11Va | D3D12Va | Dxva2) => {
+                Some(index.to_string())
+            }
+            (HardwareDevice::Index(index), VaApi | Drm) => Some(render_node(*index)),
+            (HardwareDevice::Index(index), Qsv) if cfg!(windows) => Some(index.to_string()),
+            (HardwareDevice::Index(index), Qsv) => Some(render_node(*index)),
+            (HardwareDevice::Index(_), Vdpau | OpenCl) => None,
+        }
+    }
+
+    /// Open a device of a device type.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - Device to open, or `None` for the default device.
+    /// * `device_type` - Device type of the device.
+    pub(crate) fn open(
+        device: Option<&HardwareDevice>,
+        device_type: HardwareAccelerationDeviceType,
+    ) -> Result<ffi_hwaccel::HardwareDeviceContext> {
+        let device = match device {
+            Some(device) => Some(device.device_string(device_type).ok_or_else(|| {
+                Error::InvalidConfiguration {
+                    field: "hardware_device",
+                    reason: format!("{device_type:?} does not support selecting {device:?}"),
+                }
+            })?),
+            None => None,
+        };
+        Ok(ffi_hwaccel::HardwareDeviceContext::new(
+            device_type,
+            device.as_deref(),
+        )?)
+    }
+}
+
+/// Get the layout of hardware frames for content of a pixel format: NV12 for 8-bit content, P010
+/// for 9 and 10-bit content, and P016 for deeper content. Hardware decoders of 10-bit HEVC and AV1
+/// produce P010, which would lose its extra bits or fail to download as NV12.
+///
+/// # Arguments
+///
+/// * `pixel_format` - Pixel format of the content, such as `YUV420P10LE`.
+pub(crate) fn hardware_software_format(
+    pixel_format: ffmpeg::util::format::Pixel,
+) -> ffmpeg::util::format::Pixel {
+    match ffi_hwaccel::pixel_format_bit_depth(pixel_format) {
+        Some(depth) if depth > 10 => ffmpeg::util::format::Pixel::P016LE,
+        Some(depth) if depth > 8 => ffmpeg::util::format::Pixel::P010LE,
+        _ => ffmpeg::util::format::Pixel::NV12,
+    }
+}
+
+/// Scales hardware frames on the GPU with a filter graph, so that frames are downloaded at the
+/// output size instead of being scaled in software after the download.
+pub(crate) struct HardwareScaler {
+    // Name of the scale filter of the device type.
+    filter: &'static str,
+    // Layout of scaled frames in hardware memory.
+    software_format: ffmpeg::util::format::Pixel,
+    size_out: (u32, u32),
+    time_base: ffmpeg::Rational,
+    // Filter graph, along with the input size it was configured for.
+    graph: Option<(ffmpeg::filter::Graph, (u32, u32))>,
+}
+
+impl HardwareScaler {
+    /// Create a scaler, or `None` if the device type cannot scale on the GPU.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_type` - Device type of the frames.
+    /// * `software_format` - Layout of scaled frames in hardware memory, see
+    ///   [`hardware_software_format`].
+    /// * `size_out` - Output size.
+    /// * `time_base` - Time base of frame timestamps.
+    pub(crate) fn new(
+        device_type: HardwareAccelerationDeviceType,
+        software_format: ffmpeg::util::format::Pixel,
+        size_out: (u32, u32),
+        time_base: ffmpeg::Rational,
+    ) -> Option<Self> {
+        Some(Self {
+            filter: device_type.scale_filter()?,
+            software_format,
+            size_out,
+            time_base,
+            graph: None,
+        })
+    }
+
+    /// Change the output size.
+    ///
+    /// # Arguments
+    ///
+    /// * `size_out` - Output size.
+    pub(crate) fn set_size_out(&mut self, size_out: (u32, u32)) {
+        if size_out != self.size_out {
+            self.size_out = size_out;
+            self.graph = None;
+        }
+    }
+
+    /// Scale a frame in hardware memory. The scaled frame stays in hardware memory, in the
+    /// software format of the scaler if the device can convert on the GPU.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Hardware frame to scale.
+    pub(crate) fn scale(&mut self, frame: &RawFrame) -> Result<RawFrame> {
+        let size = (frame.width(), frame.height());
+        let graph = match self.graph.as_mut() {
+            Some((graph, graph_size)) if *graph_size == size => graph,
+            _ => {
+                let graph = self.create_graph(frame)?;
+                &mut self.graph.insert((graph, size)).0
+            }
+        };
+
+        graph
+            .get("in")
+            .ok_or(ffmpeg::Error::FilterNotFound)?
+            .source()
+            .add(frame)?;
+        let mut frame_scaled = RawFrame::empty();
+        graph
+            .get("out")
+            .ok_or(ffmpeg::Error::FilterNotFound)?
+            .sink()
+            .frame(&mut frame_scaled)?;
+        Ok(frame_scaled)
+    }
+
+    /// Create a filter graph that scales frames like the given frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Hardware frame, of which the graph takes the size, pixel format and hardware
+    ///   frames context.
+    fn create_graph(&self, frame: &RawFrame) -> Result<ffmpeg::filter::Graph> {
+        let find = |name| ffmpeg::filter::find(name).ok_or(ffmpeg::Error::FilterNotFound);
+
+        let mut graph = ffmpeg::filter::Graph::new();
+        let buffer_args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect=1/1",
+            frame.width(),
+            frame.height(),
+            ffmpeg::ffi::AVPixelFormat::from(frame.format()) as i32,
+            self.time_base.numerator(),
+            self.time_base.denominator(),
+        );
+        let mut input = graph.add(&find("buffer")?, "in", &buffer_args)?;
+        ffi_hwaccel::filter_buffer_source_set_hw_frames_ctx(&mut input, frame)?;
+        let mut scale_args = format!("w={}:h={}", self.size_out.0, self.size_out.1);
+        // `scale_vt` keeps the layout of its input, the others convert along the way.
+        if self.filter != "scale_vt" {
+            if let Some(descriptor) = self.software_format.descriptor() {
+                scale_args.push_str(&format!(":format={}", descriptor.name()));
+            }
+        }
+        let mut scale = graph.add(&find(self.filter)?, "scale", &scale_args)?;
+        let mut output = graph.add(&find("buffersink")?, "out", "")?;
+
+        input.link(0, &mut scale, 0);
+        scale.link(0, &mut output, 0);
+        graph.validate()?;
+        Ok(graph)
+    }
+}
+
+unsafe impl Send for HardwareScaler {}
+unsafe impl Sync for HardwareScaler {}
+
+/// Pointer to an object of a hardware decoder, such as a `CVPixelBufferRef`, that belongs to a
+/// decoded frame. The lifetime ties the pointer to the frame, since the object may be reused or
+/// freed once the frame is dropped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HardwarePointer<'a> {
+    ptr: *mut std::ffi::c_void,
+    _frame: std::marker::PhantomData<&'a RawFrame>,
+}
+
+impl<'a> HardwarePointer<'a> {
+    /// Wrap a pointer that is taken from a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `ptr` - Pointer to wrap.
+    pub(crate) fn new(ptr: *mut std::ffi::c_void) -> Self {
+        Self {
+            ptr,
+            _frame: std::marker::PhantomData,
+        }
+    }
+
+    /// Get the raw pointer, to pass to the API of the device. It must not be used after the frame
+    /// it was taken from is dropped.
+    pub fn as_ptr(self) -> *mut std::ffi::c_void {
+        self.ptr
+    }
+}
+
+/// Handle to a decoded frame that is kept in the memory of the hardware decoder, so that it can be
+/// rendered or processed without copying it to system memory. The handle borrows the frame it was
+/// taken from, since it is only valid for as long as the frame is alive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HardwareFrameHandle<'a> {
+    /// `CVPixelBufferRef` (VideoToolbox).
+    CvPixelBuffer(HardwarePointer<'a>),
+    /// `VASurfaceID` (VA-API).
+    VaSurface(u32),
+    /// `CUdeviceptr` and pitch in bytes of each plane (CUDA).
+    Cuda { planes: Vec<(u64, usize)> },
+    /// `ID3D11Texture2D` and the index of the frame in the texture array (Direct3D 11).
+    D3D11Texture {
+        texture: HardwarePointer<'a>,
+        array_index: usize,
+    },
+    /// `AVDRMFrameDescriptor` with the DMA-BUF file descriptors of the frame (Linux DRM).
+    DrmPrime(HardwarePointer<'a>),
+    /// `AVVkFrame` with the `VkImage` of each plane, along with its layout and the timeline
+    /// semaphore to wait on before accessing it (Vulkan).
+    VkFrame(HardwarePointer<'a>),
+}
+
+/// Decoded frame that is kept in the memory of the hardware decoder. See
+/// [`DecoderBuilder::with_hardware_frames`](crate::core::decode::DecoderBuilder::with_hardware_frames).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardwareFrame<'a> {
+    /// Handle to the frame in hardware memory.
+    pub handle: HardwareFrameHandle<'a>,
+    /// Layout of the data in hardware memory, usually NV12, or P010 for 10-bit video.
+    pub software_format: ffmpeg::util::format::Pixel,
+}
+
+impl<'a> HardwareFrame<'a> {
+    /// Get the hardware handle of a decoded frame. Returns `None` if the frame is in system
+    /// memory, or if its device type does not expose a handle (only VideoToolbox, VA-API, CUDA,
+    /// Direct3D 11, DRM and Vulkan do).
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame returned by the decoder.
+    pub fn from_raw_frame(frame: &'a RawFrame) -> Option<HardwareFrame<'a>> {
+        Some(HardwareFrame {
+            handle: ffi_hwaccel::frame_hardware_handle(frame)?,
+            software_format: ffi_hwaccel::frame_hardware_software_format(frame)?,
+        })
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -60,6 +430,8 @@ pub enum HardwareAccelerationDeviceType {
     MediaCodec,
     /// Direct3D 12 Video Acceleration
     D3D12Va,
+    /// Vulkan Video, the cross-vendor decode API of Vulkan
+    Vulkan,
 }
 
 impl HardwareAccelerationDeviceType {
@@ -74,6 +446,159 @@ impl HardwareAccelerationDeviceType {
     pub fn list_available() -> Vec<HardwareAccelerationDeviceType> {
         ffi_hwaccel::hwdevice_list_available_device_types()
     }
+
+    /// Pixel format of frames in the memory of the device type that hardware encoders take, or
+    /// `None` if the device type has no encoders.
+    pub(crate) fn encoder_pixel_format(self) -> Option<ffmpeg::util::format::Pixel> {
+        match self {
+            HardwareAccelerationDeviceType::Cuda => Some(ffmpeg::util::format::Pixel::CUDA),
+            HardwareAccelerationDeviceType::VaApi => Some(ffmpeg::util::format::Pixel::VAAPI),
+            HardwareAccelerationDeviceType::Qsv => Some(ffmpeg::util::format::Pixel::QSV),
+            HardwareAccelerationDeviceType::VideoToolbox => {
+                Some(ffmpeg::util::format::Pixel::VIDEOTOOLBOX)
+            }
+            _ => None,
+        }
+    }
+
+    /// List available device types that can decode a codec, most preferred first: the native API
+    /// of the platform, then vendor APIs, then the rest. Used by
+    /// [`DecoderBuilder::with_hardware_acceleration_auto`](crate::core::decode::DecoderBuilder::with_hardware_acceleration_auto).
+    ///
+    /// # Arguments
+    ///
+    /// * `codec_id` - Codec to decode.
+    pub fn list_available_for_codec(
+        codec_id: ffmpeg::codec::Id,
+    ) -> Vec<HardwareAccelerationDeviceType> {
+        const PREFERENCE: [HardwareAccelerationDeviceType; 10] = [
+            HardwareAccelerationDeviceType::VideoToolbox,
+            HardwareAccelerationDeviceType::D3D11Va,
+            HardwareAccelerationDeviceType::MediaCodec,
+            HardwareAccelerationDeviceType::Cuda,
+            HardwareAccelerationDeviceType::VaApi,
+            HardwareAccelerationDeviceType::Qsv,
+            HardwareAccelerationDeviceType::Vulkan,
+            HardwareAccelerationDeviceType::Dxva2,
+            HardwareAccelerationDeviceType::Vdpau,
+            HardwareAccelerationDeviceType::Drm,
+        ];
+
+        let Some(codec) = ffmpeg::codec::decoder::find(codec_id) else {
+            return Vec::new();
+        };
+        let available = Self::list_available();
+        PREFERENCE
+            .into_iter()
+            .filter(|device_type| available.contains(device_type))
+            .filter(|device_type| {
+                ffi_hwaccel::codec_find_corresponding_hwaccel_pixfmt(&codec, *device_type).is_some()
+            })
+            .collect()
+    }
+
+    /// Query the limits of the frames of the default device of the device type, if the backend
+    /// was built to decode the codec on the device type, so that applications can rule out device
+    /// types before opening a file.
+    ///
+    /// Returns `None` if the device type is not available, if the backend cannot decode the codec
+    /// on it, or if the device fails to open.
+    ///
+    /// The limits are device-wide rather than specific to the codec: they are the constraints of
+    /// the frames of the device, which some devices report loosely (CUDA reports no maximum size,
+    /// and lists 16-bit formats whatever the codec). Which profiles, frame sizes and bit depths
+    /// of the codec the device can decode is only known once a decoder is opened on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec_id` - Codec to decode.
+    pub fn device_decode_limits(self, codec_id: ffmpeg::codec::Id) -> Option<HardwareDeviceLimits> {
+        let codec = ffmpeg::codec::decoder::find(codec_id)?;
+        if !self.is_available()
+            || ffi_hwaccel::codec_find_corresponding_hwaccel_pixfmt(&codec, self).is_none()
+        {
+            return None;
+        }
+        let device = HardwareDevice::open(None, self).ok()?;
+        let (software_formats, min_size, max_size) =
+            ffi_hwaccel::hwdevice_frame_constraints(&device)?;
+        Some(HardwareDeviceLimits {
+            device_type: self,
+            codec_id,
+            min_size,
+            max_size,
+            software_formats,
+        })
+    }
+
+    /// Name of the filter that scales frames of the device type on the GPU, or `None` if the
+    /// device type has none, or if the backend was built without it. Decoders with a
+    /// [`Resize`](crate::core::resize::Resize) use it to scale frames before downloading them.
+    pub fn scale_filter(self) -> Option<&'static str> {
+        let candidates: &[&'static str] = match self {
+            HardwareAccelerationDeviceType::Cuda => &["scale_cuda", "scale_npp"],
+            HardwareAccelerationDeviceType::VaApi => &["scale_vaapi"],
+            HardwareAccelerationDeviceType::VideoToolbox => &["scale_vt"],
+            HardwareAccelerationDeviceType::Vulkan => &["scale_vulkan"],
+            _ => &[],
+        };
+        candidates
+            .iter()
+            .copied()
+            .find(|name| ffmpeg::filter::find(name).is_some())
+    }
+}
+
+/// Device-wide limits of the frames of a device type, queried for decoding a codec. See
+/// [`HardwareAccelerationDeviceType::device_decode_limits`].
+///
+/// # Example
+///
+/// ```ignore
+/// let limits = HardwareAccelerationDeviceType::VaApi
+///     .device_decode_limits(ffmpeg::codec::Id::HEVC)
+///     .filter(|limits| limits.supports(3840, 2160, 10));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardwareDeviceLimits {
+    /// Device type that was queried.
+    pub device_type: HardwareAccelerationDeviceType,
+    /// Codec that the backend can decode on the device type.
+    pub codec_id: ffmpeg::codec::Id,
+    /// Minimum frame size of the device, as width and height.
+    pub min_size: (u32, u32),
+    /// Maximum frame size of the device, as width and height.
+    pub max_size: (u32, u32),
+    /// Layouts that frames can have in the memory of the device, such as NV12 for 8-bit 4:2:0
+    /// video and P010 for 10-bit 4:2:0 video.
+    pub software_formats: Vec<ffmpeg::util::format::Pixel>,
+}
+
+impl HardwareDeviceLimits {
+    /// Highest bit depth per component that frames of the device can have. This is an upper bound
+    /// on the bit depth that the device can decode.
+    pub fn max_bit_depth(&self) -> u8 {
+        self.software_formats
+            .iter()
+            .filter_map(|software_format| ffi_hwaccel::pixel_format_bit_depth(*software_format))
+            .max()
+            .unwrap_or(8)
+    }
+
+    /// Whether or not video of a frame size and bit depth fits within the limits of the device. The
+    /// device may still be unable to decode it, see
+    /// [`HardwareAccelerationDeviceType::device_decode_limits`].
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Frame width.
+    /// * `height` - Frame height.
+    /// * `bit_depth` - Bits per component, such as 8 or 10.
+    pub fn supports(&self, width: u32, height: u32, bit_depth: u8) -> bool {
+        (self.min_size.0..=self.max_size.0).contains(&width)
+            && (self.min_size.1..=self.max_size.1).contains(&height)
+            && bit_depth <= self.max_bit_depth()
+    }
 }
 
 impl HardwareAccelerationDeviceType {
@@ -89,6 +614,7 @@ impl HardwareAccelerationDeviceType {
             ffmpeg::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_DRM => Some(Self::Drm),
             ffmpeg::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_OPENCL => Some(Self::OpenCl),
             ffmpeg::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_MEDIACODEC => Some(Self::MediaCodec),
+            ffmpeg::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VULKAN => Some(Self::Vulkan),
             ffmpeg::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_NONE => None,
             // FIXME: Find a way to handle the new variants in ffmpeg 7 without breaking backwards
             // compatibility...
@@ -134,6 +660,68 @@ impl From<HardwareAccelerationDeviceType> for ffmpeg::ffi::AVHWDeviceType {
             HardwareAccelerationDeviceType::D3D12Va => {
                 unimplemented!()
             }
+            HardwareAccelerationDeviceType::Vulkan => {
+                ffmpeg::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VULKAN
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hardware_software_format() {
+        use ffmpeg::util::format::Pixel;
+
+        assert_eq!(hardware_software_format(Pixel::YUV420P), Pixel::NV12);
+        assert_eq!(hardware_software_format(Pixel::YUV420P10LE), Pixel::P010LE);
+        assert_eq!(hardware_software_format(Pixel::YUV420P12LE), Pixel::P016LE);
+    }
+
+    #[test]
+    fn test_device_decode_limits_supports() {
+        let limits = HardwareDeviceLimits {
+            device_type: HardwareAccelerationDeviceType::Cuda,
+            codec_id: ffmpeg::codec::Id::HEVC,
+            min_size: (144, 144),
+            max_size: (8192, 8192),
+            software_formats: vec![
+                ffmpeg::util::format::Pixel::NV12,
+                ffmpeg::util::format::Pixel::P010LE,
+            ],
+        };
+        assert_eq!(limits.max_bit_depth(), 10);
+        assert!(limits.supports(3840, 2160, 10));
+        assert!(!limits.supports(3840, 2160, 12));
+        assert!(!limits.supports(16384, 2160, 8));
+        assert!(!limits.supports(64, 64, 8));
+    }
+
+    #[test]
+    fn test_device_string() {
+        let second = HardwareDevice::Index(1);
+        assert_eq!(
+            second.device_string(HardwareAccelerationDeviceType::Cuda),
+            Some("1".to_string())
+        );
+        assert_eq!(
+            second.device_string(HardwareAccelerationDeviceType::VaApi),
+            Some("/dev/dri/renderD129".to_string())
+        );
+        assert_eq!(
+            second.device_string(HardwareAccelerationDeviceType::Vulkan),
+            Some("1".to_string())
+        );
+        assert_eq!(
+            second.device_string(HardwareAccelerationDeviceType::VideoToolbox),
+            None
+        );
+        let path = HardwareDevice::Path("/dev/dri/renderD130".into());
+        assert_eq!(
+            path.device_string(HardwareAccelerationDeviceType::Drm),
+            Some("/dev/dri/renderD130".to_string())
+        );
+    }
+}
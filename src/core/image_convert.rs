@@ -0,0 +1,119 @@
+use image::{RgbImage, RgbaImage};
+use ndarray::{Array3, ArrayView3};
+
+use crate::core::error::Error;
+use crate::core::frame::Frame;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Convert an RGB frame to an [`RgbImage`], e.g. to draw annotations on it or to save it with
+/// the `image` crate.
+///
+/// Fails with [`Error::InvalidFrameFormat`] if the frame does not have three channels.
+///
+/// # Arguments
+///
+/// * `frame` - Frame with dimensions `(H, W, 3)`: `frame.view()` of a decoded [`Frame`], or
+///   [`frame_view`](crate::core::frame::frame_view) of a raw frame.
+///
+/// # Example
+///
+/// ```ignore
+/// let (_, frame) = decoder.decode().unwrap();
+/// rgb_image_from_frame(frame.view()).unwrap().save("frame.png").unwrap();
+/// ```
+pub fn rgb_image_from_frame(frame: ArrayView3<'_, u8>) -> Result<RgbImage> {
+    let (width, height, pixels) = image_pixels(frame, 3)?;
+    RgbImage::from_raw(width, height, pixels).ok_or(Error::InvalidFrameFormat)
+}
+
+/// Convert an RGBA frame to an [`RgbaImage`].
+///
+/// Fails with [`Error::InvalidFrameFormat`] if the frame does not have four channels.
+///
+/// # Arguments
+///
+/// * `frame` - Frame with dimensions `(H, W, 4)`.
+pub fn rgba_image_from_frame(frame: ArrayView3<'_, u8>) -> Result<RgbaImage> {
+    let (width, height, pixels) = image_pixels(frame, 4)?;
+    RgbaImage::from_raw(width, height, pixels).ok_or(Error::InvalidFrameFormat)
+}
+
+/// Convert an [`RgbImage`] to a frame that
+/// [`Encoder::encode`](crate::core::encode::Encoder::encode) takes.
+///
+/// # Arguments
+///
+/// * `image` - Image to convert.
+pub fn frame_from_rgb_image(image: &RgbImage) -> Frame {
+    frame_from_pixels(image.width(), image.height(), 3, image.as_raw().clone())
+}
+
+/// Convert an [`RgbaImage`] to a frame that
+/// [`Encoder::encode`](crate::core::encode::Encoder::encode) takes. The alpha channel is only kept
+/// by encoders set up for it.
+///
+/// # Arguments
+///
+/// * `image` - Image to convert.
+pub fn frame_from_rgba_image(image: &RgbaImage) -> Frame {
+    frame_from_pixels(image.width(), image.height(), 4, image.as_raw().clone())
+}
+
+/// Get the size and the pixels in row-major order of a frame.
+///
+/// # Arguments
+///
+/// * `frame` - Frame with dimensions `(H, W, C)`.
+/// * `channels` - Number of channels that the frame must have.
+fn image_pixels(frame: ArrayView3<'_, u8>, channels: usize) -> Result<(u32, u32, Vec<u8>)> {
+    let (height, width, frame_channels) = frame.dim();
+    if frame_channels != channels {
+        return Err(Error::InvalidFrameFormat);
+    }
+    // Views of raw frames may have padded rows, so they are copied in logical order.
+    let pixels = match frame.as_slice() {
+        Some(pixels) => pixels.to_vec(),
+        None => frame.iter().copied().collect(),
+    };
+    Ok((width as u32, height as u32, pixels))
+}
+
+/// Create a frame from pixels in row-major order.
+///
+/// # Arguments
+///
+/// * `width` - Width of the image.
+/// * `height` - Height of the image.
+/// * `channels` - Number of channels of the image.
+/// * `pixels` - Pixels of the image.
+fn frame_from_pixels(width: u32, height: u32, channels: usize, pixels: Vec<u8>) -> Frame {
+    Array3::from_shape_vec((height as usize, width as usize, channels), pixels)
+        .expect("image buffers hold exactly width * height * channels values")
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::s;
+
+    use super::*;
+
+    #[test]
+    fn test_rgb_round_trip() {
+        let mut image = RgbImage::new(4, 2);
+        image.put_pixel(3, 1, image::Rgb([10, 20, 30]));
+        let frame = frame_from_rgb_image(&image);
+        assert_eq!(frame.dim(), (2, 4, 3));
+        assert_eq!(frame[[1, 3, 2]], 30);
+        assert_eq!(rgb_image_from_frame(frame.view()).unwrap(), image);
+    }
+
+    #[test]
+    fn test_image_from_strided_view() {
+        let frame = Array3::from_shape_fn((2, 4, 4), |(y, x, c)| (y * 16 + x * 4 + c) as u8);
+        let image = rgba_image_from_frame(frame.slice(s![.., 1..3, ..])).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.get_pixel(1, 1).0, [24, 25, 26, 27]);
+        assert!(rgb_image_from_frame(frame.view()).is_err());
+    }
+}
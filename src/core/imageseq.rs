@@ -0,0 +1,524 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::path::Path;
+
+use ffmpeg::codec::encoder::video::Encoder as AvEncoder;
+use ffmpeg::codec::flag::Flags as AvCodecFlags;
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::codec::Id as AvCodecId;
+use ffmpeg::software::scaling::context::Context as AvScaler;
+use ffmpeg::software::scaling::flag::Flags as AvScalerFlags;
+use ffmpeg::util::color::Range as AvColorRange;
+use ffmpeg::util::error::EAGAIN;
+use ffmpeg::util::format::Pixel as AvPixel;
+use ffmpeg::{Error as AvError, Rational as AvRational};
+
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::ffi_hwaccel;
+#[cfg(feature = "ndarray")]
+use crate::core::frame::Frame;
+use crate::core::frame::RawFrame;
+use crate::core::io::private::Write;
+use crate::core::io::{Writer, WriterBuilder};
+use crate::core::location::Location;
+use crate::core::options::Options;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Image file format of an image sequence.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Lossless PNG images.
+    Png,
+    /// Lossy JPEG images.
+    Jpeg,
+}
+
+impl ImageFormat {
+    /// Get the conventional file extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+
+    /// Get the format of an image file from its extension: `png`, or `jpg` or `jpeg`. The
+    /// extension is not case sensitive.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the image file.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            _ => None,
+        }
+    }
+
+    /// Get the backend codec id.
+    fn codec_id(self) -> AvCodecId {
+        match self {
+            ImageFormat::Png => AvCodecId::PNG,
+            ImageFormat::Jpeg => AvCodecId::MJPEG,
+        }
+    }
+
+    /// Get the pixel format the images are stored in.
+    fn pixel_format(self) -> AvPixel {
+        match self {
+            ImageFormat::Png => AvPixel::RGB24,
+            ImageFormat::Jpeg => AvPixel::YUVJ420P,
+        }
+    }
+}
+
+/// Builds an [`ImageSequenceWriter`].
+pub struct ImageSequenceWriterBuilder {
+    pattern: String,
+    format: ImageFormat,
+    quality: Option<u8>,
+    compression_level: Option<u8>,
+    start_number: u64,
+}
+
+impl ImageSequenceWriterBuilder {
+    /// Create a writer that writes images to files named after `pattern`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - File name pattern with a `printf`-style placeholder for the image number,
+    ///   e.g. `frames/%06d.png`.
+    /// * `format` - Image format.
+    pub fn new(pattern: impl Into<String>, format: ImageFormat) -> Self {
+        Self {
+            pattern: pattern.into(),
+            format,
+            quality: None,
+            compression_level: None,
+            start_number: 1,
+        }
+    }
+
+    /// Set the JPEG quality, from `1` (smallest files) to `100` (best quality). Ignored for PNG.
+    ///
+    /// # Arguments
+    ///
+    /// * `quality` - Image quality.
+    pub fn with_quality(mut self, quality: u8) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// Set the PNG compression level, from `0` (fastest) to `9` (smallest files). Ignored for
+    /// JPEG.
+    ///
+    /// # Arguments
+    ///
+    /// * `compression_level` - Compression level.
+    pub fn with_compression_level(mut self, compression_level: u8) -> Self {
+        self.compression_level = Some(compression_level);
+        self
+    }
+
+    /// Set the number of the first image. Defaults to `1`.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_number` - Number of first image.
+    pub fn with_start_number(mut self, start_number: u64) -> Self {
+        self.start_number = start_number;
+        self
+    }
+
+    /// Build an [`ImageSequenceWriter`].
+    pub fn build(self) -> Result<ImageSequenceWriter> {
+        if let Some(quality) = self.quality {
+            check_quality(quality)?;
+        }
+        if let Some(compression_level) = self.compression_level {
+            if compression_level > 9 {
+                return Err(Error::InvalidEncoderOption(format!(
+                    "compression level {compression_level} is out of range (0 to 9)"
+                )));
+            }
+        }
+        if !self.pattern.contains('%') {
+            return Err(Error::InvalidEncoderOption(format!(
+                "file name pattern {} has no placeholder for the image number",
+                self.pattern,
+            )));
+        }
+
+        let writer = WriterBuilder::new(Location::File(self.pattern.into()))
+            .with_format("image2")
+            .build()?;
+
+        Ok(ImageSequenceWriter {
+            writer,
+            format: self.format,
+            quality: self.quality,
+            compression_level: self.compression_level,
+            start_number: self.start_number,
+            encoder: None,
+            scaler: None,
+            frame_count: 0,
+            have_written_trailer: false,
+        })
+    }
+}
+
+/// Writes frames as a sequence of numbered image files, e.g. to extract a dataset from a video.
+///
+/// The size of the images is taken from the first frame. Frames may be in any pixel format.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut decoder = Decoder::new(Path::new("video.mp4")).unwrap();
+/// let mut writer = ImageSequenceWriterBuilder::new("frames/%06d.jpg", ImageFormat::Jpeg)
+///     .with_quality(90)
+///     .build()
+///     .unwrap();
+/// while let Ok(frame) = decoder.decode_raw() {
+///     writer.write_raw(frame).unwrap();
+/// }
+/// writer.finish().unwrap();
+/// ```
+pub struct ImageSequenceWriter {
+    writer: Writer,
+    format: ImageFormat,
+    quality: Option<u8>,
+    compression_level: Option<u8>,
+    start_number: u64,
+    encoder: Option<AvEncoder>,
+    scaler: Option<AvScaler>,
+    frame_count: u64,
+    have_written_trailer: bool,
+}
+
+impl ImageSequenceWriter {
+    /// Time base of the image stream. Images have no duration, so they are simply numbered.
+    const TIME_BASE: AvRational = AvRational(1, 1);
+
+    /// Write a single `ndarray` frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame in `HWC` format and standard layout.
+    #[cfg(feature = "ndarray")]
+    pub fn write(&mut self, frame: &Frame) -> Result<()> {
+        let frame = ffi::convert_ndarray_to_frame_rgb24(frame).map_err(Error::BackendError)?;
+        self.write_raw(frame)
+    }
+
+    /// Write a single raw frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to write. Must have the same size as the first frame.
+    pub fn write_raw(&mut self, frame: RawFrame) -> Result<()> {
+        if self.have_written_trailer {
+            return Err(Error::EncoderFinished);
+        }
+
+        if self.encoder.is_none() {
+            self.open(frame.width(), frame.height())?;
+        }
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Err(Error::UninitializedCodec);
+        };
+        if frame.width() != encoder.width() || frame.height() != encoder.height() {
+            return Err(Error::InvalidFrameFormat);
+        }
+
+        let mut frame = convert_frame(&mut self.scaler, frame, self.format.pixel_format())?;
+        frame.set_pts(Some(self.frame_count as i64));
+        if let (ImageFormat::Jpeg, Some(quality)) = (self.format, self.quality) {
+            ffi::set_frame_quality(&mut frame, qscale(quality) * LAMBDA_PER_QP);
+        }
+        encoder.send_frame(&frame)?;
+        self.frame_count += 1;
+        self.write_packets()
+    }
+
+    /// Number of images written so far.
+    #[inline]
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Flush the encoder and finish the image sequence.
+    pub fn finish(&mut self) -> Result<()> {
+        if self.have_written_trailer {
+            return Ok(());
+        }
+        self.have_written_trailer = true;
+        if let Some(encoder) = self.encoder.as_mut() {
+            encoder.send_eof()?;
+            self.write_packets()?;
+            self.writer.write_trailer()?;
+        }
+        Ok(())
+    }
+
+    /// Open the encoder and write the header, once the size of the images is known.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the images.
+    /// * `height` - Height of the images.
+    fn open(&mut self, width: u32, height: u32) -> Result<()> {
+        let codec =
+            ffmpeg::encoder::find(self.format.codec_id()).ok_or(AvError::EncoderNotFound)?;
+        let mut writer_stream = self.writer.output.add_stream(codec)?;
+
+        let encoder = open_encoder(
+            self.format,
+            width,
+            height,
+            self.quality,
+            self.compression_level,
+        )?;
+        writer_stream.set_parameters(&encoder);
+        writer_stream.set_time_base(Self::TIME_BASE);
+
+        let mut muxer_options = Options::default();
+        muxer_options.set("start_number", &self.start_number.to_string());
+        self.writer
+            .output
+            .write_header_with(muxer_options.to_dict())?;
+
+        self.encoder = Some(encoder);
+        Ok(())
+    }
+
+    /// Write all packets the encoder has available.
+    fn write_packets(&mut self) -> Result<()> {
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(());
+        };
+        let stream_time_base = self
+            .writer
+            .output
+            .stream(0)
+            .ok_or(AvError::StreamNotFound)?
+            .time_base();
+        loop {
+            let mut packet = AvPacket::empty();
+            match encoder.receive_packet(&mut packet) {
+                Ok(()) => {
+                    packet.set_stream(0);
+                    packet.set_position(-1);
+                    packet.rescale_ts(Self::TIME_BASE, stream_time_base);
+                    self.writer.write(&mut packet)?;
+                }
+                Err(AvError::Eof) => return Ok(()),
+                Err(AvError::Other { errno }) if errno == EAGAIN => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for ImageSequenceWriter {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+unsafe impl Send for ImageSequenceWriter {}
+unsafe impl Sync for ImageSequenceWriter {}
+
+/// Save a single frame as an image file, e.g. to take a screenshot. The image format is chosen by
+/// the extension of the file: `.png` or `.jpg`/`.jpeg`.
+///
+/// # Arguments
+///
+/// * `frame` - Frame in `HWC` format and standard layout.
+/// * `path` - Path of the image file.
+/// * `quality` - JPEG quality, from `1` (smallest file) to `100` (best quality), or `None` for the
+///   default. Ignored for PNG.
+///
+/// # Example
+///
+/// ```ignore
+/// let (_, frame) = decoder.decode().unwrap();
+/// save_frame(&frame, "screenshot.jpg", Some(90)).unwrap();
+/// ```
+#[cfg(feature = "ndarray")]
+pub fn save_frame(frame: &Frame, path: impl AsRef<Path>, quality: Option<u8>) -> Result<()> {
+    let frame = ffi::convert_ndarray_to_frame_rgb24(frame).map_err(Error::BackendError)?;
+    save_raw_frame(frame, path, quality)
+}
+
+/// Save a single raw frame as an image file. The image format is chosen by the extension of the
+/// file: `.png` or `.jpg`/`.jpeg`.
+///
+/// # Arguments
+///
+/// * `frame` - Frame to save, in any pixel format. Hardware frames are downloaded first.
+/// * `path` - Path of the image file.
+/// * `quality` - JPEG quality, from `1` (smallest file) to `100` (best quality), or `None` for the
+///   default. Ignored for PNG.
+pub fn save_raw_frame(frame: RawFrame, path: impl AsRef<Path>, quality: Option<u8>) -> Result<()> {
+    let path = path.as_ref();
+    let format = ImageFormat::from_path(path).ok_or_else(|| {
+        Error::InvalidEncoderOption(format!(
+            "cannot tell the image format from the extension of {}",
+            path.display(),
+        ))
+    })?;
+    if let Some(quality) = quality {
+        check_quality(quality)?;
+    }
+
+    let mut encoder = open_encoder(format, frame.width(), frame.height(), quality, None)?;
+    let mut frame = convert_frame(&mut None, frame, format.pixel_format())?;
+    frame.set_pts(Some(0));
+    if let (ImageFormat::Jpeg, Some(quality)) = (format, quality) {
+        ffi::set_frame_quality(&mut frame, qscale(quality) * LAMBDA_PER_QP);
+    }
+    encoder.send_frame(&frame)?;
+    encoder.send_eof()?;
+
+    let mut packet = AvPacket::empty();
+    encoder.receive_packet(&mut packet)?;
+    std::fs::write(path, packet.data().unwrap_or_default())?;
+    Ok(())
+}
+
+/// Number of lambda units per quantizer step.
+const LAMBDA_PER_QP: i32 = 118;
+
+/// Open an encoder for images of the given format and size.
+///
+/// # Arguments
+///
+/// * `format` - Image format.
+/// * `width` - Width of the images.
+/// * `height` - Height of the images.
+/// * `quality` - JPEG quality, if any.
+/// * `compression_level` - PNG compression level, if any.
+fn open_encoder(
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    quality: Option<u8>,
+    compression_level: Option<u8>,
+) -> Result<AvEncoder> {
+    let codec = ffmpeg::encoder::find(format.codec_id()).ok_or(AvError::EncoderNotFound)?;
+    let mut encoder = ffi::codec_context_as(&codec)?.encoder().video()?;
+    encoder.set_width(width);
+    encoder.set_height(height);
+    encoder.set_format(format.pixel_format());
+    encoder.set_time_base(ImageSequenceWriter::TIME_BASE);
+    let mut options = Options::default();
+    match format {
+        ImageFormat::Jpeg => {
+            encoder.set_color_range(AvColorRange::JPEG);
+            if let Some(quality) = quality {
+                encoder.set_flags(AvCodecFlags::QSCALE);
+                encoder.set_global_quality(qscale(quality) * LAMBDA_PER_QP);
+            }
+        }
+        ImageFormat::Png => {
+            if let Some(compression_level) = compression_level {
+                options.set("compression_level", &compression_level.to_string());
+            }
+        }
+    }
+    Ok(encoder.open_with(options.to_dict())?)
+}
+
+/// Download a frame from the hardware device if needed, and convert it to the pixel format of the
+/// images.
+///
+/// # Arguments
+///
+/// * `scaler` - Scaler to reuse, if its input format matches. Replaced otherwise.
+/// * `frame` - Frame to convert.
+/// * `format` - Pixel format of the images.
+fn convert_frame(
+    scaler: &mut Option<AvScaler>,
+    frame: RawFrame,
+    format: AvPixel,
+) -> Result<RawFrame> {
+    let frame = if ffi_hwaccel::frame_is_hardware(&frame) {
+        let mut frame_downloaded = RawFrame::empty();
+        ffi_hwaccel::hwdevice_transfer_frame(&mut frame_downloaded, &frame)?;
+        frame_downloaded
+    } else {
+        frame
+    };
+    if frame.format() == format {
+        return Ok(frame);
+    }
+    let scaler = match scaler.as_mut() {
+        Some(scaler) if scaler.input().format == frame.format() => scaler,
+        _ => scaler.insert(AvScaler::get(
+            frame.format(),
+            frame.width(),
+            frame.height(),
+            format,
+            frame.width(),
+            frame.height(),
+            AvScalerFlags::BILINEAR,
+        )?),
+    };
+    let mut frame_scaled = RawFrame::empty();
+    scaler.run(&frame, &mut frame_scaled)?;
+    Ok(frame_scaled)
+}
+
+/// Check that a JPEG quality is within range.
+///
+/// # Arguments
+///
+/// * `quality` - Image quality.
+fn check_quality(quality: u8) -> Result<()> {
+    if !(1..=100).contains(&quality) {
+        return Err(Error::InvalidEncoderOption(format!(
+            "image quality {quality} is out of range (1 to 100)"
+        )));
+    }
+    Ok(())
+}
+
+/// Map a JPEG quality from `1` to `100` onto the quantizer scale of the encoder, which runs from
+/// `31` (worst) to `2` (best).
+///
+/// # Arguments
+///
+/// * `quality` - Image quality.
+fn qscale(quality: u8) -> i32 {
+    let quality = quality.clamp(1, 100) as i32;
+    2 + (100 - quality) * 29 / 99
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qscale_range() {
+        assert_eq!(qscale(100), 2);
+        assert_eq!(qscale(1), 31);
+        assert!(qscale(50) > qscale(90));
+    }
+
+    #[test]
+    fn test_image_format_from_path() {
+        assert_eq!(ImageFormat::from_path("shot.png"), Some(ImageFormat::Png));
+        assert_eq!(
+            ImageFormat::from_path("a/b/shot.JPEG"),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(ImageFormat::from_path("shot.jpg"), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::from_path("shot.bmp"), None);
+        assert_eq!(ImageFormat::from_path("shot"), None);
+    }
+}
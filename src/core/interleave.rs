@@ -0,0 +1,117 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Orders packets of multiple streams by timestamp before they are written, so that packets that
+/// are pushed out of order (e.g. audio and video produced by different threads) end up properly
+/// interleaved in the output.
+///
+/// A packet is released once every stream has at least one packet queued, and the packet with the
+/// lowest timestamp goes first. This mirrors `av_interleaved_write_frame`. To avoid buffering
+/// without bounds when one of the streams stalls or ends, packets are also released once the
+/// queue holds more than a maximum number of packets.
+pub(crate) struct Interleaver<T> {
+    queues: HashMap<usize, VecDeque<(f64, T)>>,
+    max_queued: usize,
+    queued: usize,
+}
+
+impl<T> Interleaver<T> {
+    /// Create an interleaver for a set of streams.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_indices` - Indices of the streams to interleave.
+    /// * `max_queued` - Maximum number of packets to hold before releasing packets regardless of
+    ///   whether all streams have one queued.
+    pub(crate) fn new(stream_indices: impl IntoIterator<Item = usize>, max_queued: usize) -> Self {
+        Self {
+            queues: stream_indices
+                .into_iter()
+                .map(|stream_index| (stream_index, VecDeque::new()))
+                .collect(),
+            max_queued,
+            queued: 0,
+        }
+    }
+
+    /// Queue a packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the stream the packet belongs to.
+    /// * `timestamp` - Decoding timestamp of the packet in seconds, or `None` if it is unknown.
+    ///   Packets without a timestamp are ordered right after the previous packet of the stream.
+    /// * `packet` - Packet to queue.
+    pub(crate) fn push(&mut self, stream_index: usize, timestamp: Option<f64>, packet: T) {
+        let queue = self.queues.entry(stream_index).or_default();
+        let timestamp = timestamp
+            .or_else(|| queue.back().map(|(timestamp, _)| *timestamp))
+            .unwrap_or(f64::NEG_INFINITY);
+        queue.push_back((timestamp, packet));
+        self.queued += 1;
+    }
+
+    /// Take the next packet if it can be written without breaking interleaving.
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let ready = self.queued > self.max_queued
+            || (self.queued > 0 && self.queues.values().all(|queue| !queue.is_empty()));
+        if ready {
+            self.pop_earliest()
+        } else {
+            None
+        }
+    }
+
+    /// Take the queued packet with the lowest timestamp, regardless of whether all streams have a
+    /// packet queued. Used to drain the queue at the end.
+    pub(crate) fn pop_earliest(&mut self) -> Option<T> {
+        let queue = self
+            .queues
+            .values_mut()
+            .filter(|queue| !queue.is_empty())
+            .min_by(|a, b| a[0].0.total_cmp(&b[0].0))?;
+        self.queued -= 1;
+        queue.pop_front().map(|(_, packet)| packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waits_for_all_streams() {
+        let mut interleaver = Interleaver::new([0, 1], 16);
+        interleaver.push(0, Some(0.00), "v0");
+        interleaver.push(0, Some(0.04), "v1");
+        assert_eq!(interleaver.pop(), None);
+        interleaver.push(1, Some(0.02), "a0");
+        assert_eq!(interleaver.pop(), Some("v0"));
+        assert_eq!(interleaver.pop(), Some("a0"));
+        assert_eq!(interleaver.pop(), None);
+        assert_eq!(interleaver.pop_earliest(), Some("v1"));
+        assert_eq!(interleaver.pop_earliest(), None);
+    }
+
+    #[test]
+    fn test_releases_when_queue_is_full() {
+        let mut interleaver = Interleaver::new([0, 1], 2);
+        interleaver.push(0, Some(0.0), 0);
+        interleaver.push(0, Some(1.0), 1);
+        assert_eq!(interleaver.pop(), None);
+        interleaver.push(0, Some(2.0), 2);
+        assert_eq!(interleaver.pop(), Some(0));
+        assert_eq!(interleaver.pop(), None);
+    }
+
+    #[test]
+    fn test_missing_timestamp_follows_previous_packet() {
+        let mut interleaver = Interleaver::new([0, 1], 16);
+        interleaver.push(0, Some(1.0), "v0");
+        interleaver.push(0, None, "v1");
+        interleaver.push(1, Some(0.5), "a0");
+        interleaver.push(1, Some(1.5), "a1");
+        assert_eq!(interleaver.pop(), Some("a0"));
+        assert_eq!(interleaver.pop(), Some("v0"));
+        assert_eq!(interleaver.pop(), Some("v1"));
+    }
+}
@@ -0,0 +1,548 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::util::format::Pixel as AvPixel;
+
+use crate::core::error::Error;
+use crate::core::ffi_hwaccel;
+use crate::core::frame::RawFrame;
+use crate::core::hwaccel::{HardwareFrame, HardwareFrameHandle, HardwarePointer};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Format of a texture that holds one plane of a hardware frame. Luma planes are single channel,
+/// and the interleaved chroma planes of NV12 and P010 are two channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PlaneFormat {
+    /// 8-bit single channel.
+    R8,
+    /// 8-bit two channel.
+    Rg8,
+    /// 16-bit single channel.
+    R16,
+    /// 16-bit two channel.
+    Rg16,
+}
+
+impl PlaneFormat {
+    /// Number of bytes per pixel.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PlaneFormat::R8 => 1,
+            PlaneFormat::Rg8 | PlaneFormat::R16 => 2,
+            PlaneFormat::Rg16 => 4,
+        }
+    }
+
+    /// OpenGL sized internal format (`GL_R8`, `GL_RG8`, `GL_R16` or `GL_RG16`).
+    pub fn gl_internal_format(self) -> u32 {
+        match self {
+            PlaneFormat::R8 => 0x8229,
+            PlaneFormat::Rg8 => 0x822B,
+            PlaneFormat::R16 => 0x822A,
+            PlaneFormat::Rg16 => 0x822C,
+        }
+    }
+
+    /// DRM fourcc (`DRM_FORMAT_R8`, `DRM_FORMAT_GR88`, `DRM_FORMAT_R16` or `DRM_FORMAT_GR1616`).
+    pub fn drm_fourcc(self) -> u32 {
+        let code = match self {
+            PlaneFormat::R8 => b"R8  ",
+            PlaneFormat::Rg8 => b"GR88",
+            PlaneFormat::R16 => b"R16 ",
+            PlaneFormat::Rg16 => b"GR32",
+        };
+        u32::from_le_bytes(*code)
+    }
+
+    /// Metal pixel format (`MTLPixelFormatR8Unorm`, `MTLPixelFormatRG8Unorm`,
+    /// `MTLPixelFormatR16Unorm` or `MTLPixelFormatRG16Unorm`).
+    pub fn metal_pixel_format(self) -> u64 {
+        match self {
+            PlaneFormat::R8 => 10,
+            PlaneFormat::Rg8 => 30,
+            PlaneFormat::R16 => 20,
+            PlaneFormat::Rg16 => 60,
+        }
+    }
+
+    /// DXGI format of a shader resource view (`DXGI_FORMAT_R8_UNORM`, `DXGI_FORMAT_R8G8_UNORM`,
+    /// `DXGI_FORMAT_R16_UNORM` or `DXGI_FORMAT_R16G16_UNORM`).
+    pub fn dxgi_format(self) -> u32 {
+        match self {
+            PlaneFormat::R8 => 61,
+            PlaneFormat::Rg8 => 49,
+            PlaneFormat::R16 => 56,
+            PlaneFormat::Rg16 => 35,
+        }
+    }
+}
+
+/// Texture to create for one plane of a hardware frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TexturePlane {
+    /// Texture format.
+    pub format: PlaneFormat,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+}
+
+/// Get the textures to create for each plane of a hardware frame. Returns `None` if the layout in
+/// hardware memory is not supported. Only the biplanar formats that hardware decoders produce are:
+/// NV12 for 8-bit, and P010 or P016 for 10-bit and higher.
+///
+/// # Arguments
+///
+/// * `software_format` - Layout of the frame in hardware memory.
+/// * `width` - Frame width.
+/// * `height` - Frame height.
+pub fn texture_planes(
+    software_format: AvPixel,
+    width: u32,
+    height: u32,
+) -> Option<Vec<TexturePlane>> {
+    let (luma, chroma) = match software_format {
+        AvPixel::NV12 => (PlaneFormat::R8, PlaneFormat::Rg8),
+        AvPixel::P010LE | AvPixel::P016LE => (PlaneFormat::R16, PlaneFormat::Rg16),
+        _ => return None,
+    };
+    Some(vec![
+        TexturePlane {
+            format: luma,
+            width,
+            height,
+        },
+        TexturePlane {
+            format: chroma,
+            width: width.div_ceil(2),
+            height: height.div_ceil(2),
+        },
+    ])
+}
+
+/// One plane of a DMA-BUF layer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DmaBufPlane {
+    /// DMA-BUF file descriptor. Owned by the frame.
+    pub fd: i32,
+    /// Offset of the plane in the buffer in bytes.
+    pub offset: usize,
+    /// Bytes per row.
+    pub pitch: usize,
+    /// DRM format modifier of the buffer, which describes tiling and compression.
+    pub modifier: u64,
+}
+
+impl DmaBufPlane {
+    /// High and low 32 bits of the modifier, as `zwp_linux_buffer_params_v1.add` of the Wayland
+    /// `linux-dmabuf` protocol takes them.
+    pub fn modifier_hi_lo(&self) -> (u32, u32) {
+        ((self.modifier >> 32) as u32, self.modifier as u32)
+    }
+}
+
+/// Layer of a DMA-BUF frame, which is imported as one `EGLImage`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DmaBufLayer {
+    /// DRM fourcc of the layer.
+    pub fourcc: u32,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Planes of the layer.
+    pub planes: Vec<DmaBufPlane>,
+}
+
+impl DmaBufLayer {
+    // Attributes of `EGL_EXT_image_dma_buf_import` and `EGL_EXT_image_dma_buf_import_modifiers`,
+    // as (fd, offset, pitch, modifier low, modifier high) for each plane.
+    const EGL_NONE: i32 = 0x3038;
+    const EGL_WIDTH: i32 = 0x3057;
+    const EGL_HEIGHT: i32 = 0x3056;
+    const EGL_LINUX_DRM_FOURCC_EXT: i32 = 0x3271;
+    const EGL_DMA_BUF_PLANES: [[i32; 5]; 4] = [
+        [0x3272, 0x3273, 0x3274, 0x3443, 0x3444],
+        [0x3275, 0x3276, 0x3277, 0x3445, 0x3446],
+        [0x3278, 0x3279, 0x327A, 0x3447, 0x3448],
+        [0x3440, 0x3441, 0x3442, 0x3449, 0x344A],
+    ];
+    // `DRM_FORMAT_MOD_INVALID`, for buffers without an explicit modifier.
+    const DRM_FORMAT_MOD_INVALID: u64 = 0x00ff_ffff_ffff_ffff;
+
+    /// Attribute list to pass to `eglCreateImageKHR` with target `EGL_LINUX_DMA_BUF_EXT`,
+    /// terminated by `EGL_NONE`. Bind the image to a texture with `glEGLImageTargetTexture2DOES`.
+    pub fn egl_image_attributes(&self) -> Vec<i32> {
+        let mut attributes = vec![
+            Self::EGL_WIDTH,
+            self.width as i32,
+            Self::EGL_HEIGHT,
+            self.height as i32,
+            Self::EGL_LINUX_DRM_FOURCC_EXT,
+            self.fourcc as i32,
+        ];
+        for (plane, [fd, offset, pitch, modifier_lo, modifier_hi]) in
+            self.planes.iter().zip(Self::EGL_DMA_BUF_PLANES)
+        {
+            attributes.extend([
+                fd,
+                plane.fd,
+                offset,
+                plane.offset as i32,
+                pitch,
+                plane.pitch as i32,
+            ]);
+            if plane.modifier != Self::DRM_FORMAT_MOD_INVALID {
+                attributes.extend([
+                    modifier_lo,
+                    plane.modifier as u32 as i32,
+                    modifier_hi,
+                    (plane.modifier >> 32) as u32 as i32,
+                ]);
+            }
+        }
+        attributes.push(Self::EGL_NONE);
+        attributes
+    }
+}
+
+/// Hardware frame exported as DMA-BUF. The file descriptors stay valid for as long as this value
+/// exists, but the `EGLImage`s created from them may outlive it.
+///
+/// Each layer carries what importers need: the DRM fourcc and size of the layer, and the file
+/// descriptor, offset, pitch and modifier of each of its planes. Wayland compositors take a layer
+/// as a `zwp_linux_buffer_params_v1` with one `add` request per plane, EGL as an `EGLImage` (see
+/// [`DmaBufLayer::egl_image_attributes`]), and Vulkan as an image created with
+/// `VkImageDrmFormatModifierExplicitCreateInfoEXT` on memory imported from the file descriptor.
+///
+/// # Example
+///
+/// ```ignore
+/// let frame = DmaBufFrame::from_raw_frame(&decoder.decode_raw().unwrap()).unwrap();
+/// for layer in &frame.layers {
+///     let params = linux_dmabuf.create_params();
+///     for (index, plane) in layer.planes.iter().enumerate() {
+///         let (modifier_hi, modifier_lo) = plane.modifier_hi_lo();
+///         let (fd, offset, pitch) = (plane.fd, plane.offset as u32, plane.pitch as u32);
+///         params.add(fd, index as u32, offset, pitch, modifier_hi, modifier_lo);
+///     }
+///     params.create(layer.width as i32, layer.height as i32, layer.fourcc, Flags::empty());
+/// }
+/// ```
+pub struct DmaBufFrame {
+    /// Layers of the frame. Drivers export either one layer per plane, or a single layer that
+    /// holds all planes.
+    pub layers: Vec<DmaBufLayer>,
+    /// Layout of the frame, NV12 or P010 for 10-bit video.
+    pub software_format: AvPixel,
+    // Mapping that owns the file descriptors.
+    _mapped: RawFrame,
+}
+
+impl DmaBufFrame {
+    /// Export a decoded frame as DMA-BUF, without copying it. Works for frames of VA-API, Vulkan
+    /// and DRM decoders, and for DRM PRIME frames that V4L2 memory-to-memory decoders (such as
+    /// `h264_v4l2m2m`) produce.
+    ///
+    /// Fails with [`Error::InvalidFrameFormat`] if the frame is in system memory, comes from
+    /// another device type, or has a layout other than NV12 or P010.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Decoded frame.
+    pub fn from_raw_frame(frame: &RawFrame) -> Result<DmaBufFrame> {
+        let supported = match HardwareFrame::from_raw_frame(frame) {
+            Some(HardwareFrame { handle, .. }) => matches!(
+                handle,
+                HardwareFrameHandle::VaSurface(_)
+                    | HardwareFrameHandle::DrmPrime(_)
+                    | HardwareFrameHandle::VkFrame(_)
+            ),
+            // V4L2 decoders output DRM PRIME frames without a hardware frames context.
+            None => frame.format() == AvPixel::DRM_PRIME,
+        };
+        if !supported {
+            return Err(Error::InvalidFrameFormat);
+        }
+
+        let mapped = ffi_hwaccel::frame_map_drm_prime(frame).map_err(Error::BackendError)?;
+        let layers = ffi_hwaccel::frame_drm_layers(&mapped).ok_or(Error::InvalidFrameFormat)?;
+        let software_format = ffi_hwaccel::frame_hardware_software_format(frame)
+            .or_else(|| drm_software_format(layers.first()?.0))
+            .ok_or(Error::InvalidFrameFormat)?;
+        let planes = texture_planes(software_format, frame.width(), frame.height())
+            .ok_or(Error::InvalidFrameFormat)?;
+        Ok(DmaBufFrame {
+            layers: dma_buf_layers(layers, &planes, frame.width(), frame.height()),
+            software_format,
+            _mapped: mapped,
+        })
+    }
+}
+
+impl std::fmt::Debug for DmaBufFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DmaBufFrame")
+            .field("layers", &self.layers)
+            .field("software_format", &self.software_format)
+            .finish_non_exhaustive()
+    }
+}
+
+unsafe impl Send for DmaBufFrame {}
+unsafe impl Sync for DmaBufFrame {}
+
+/// How a renderer imports a hardware frame into textures without copying it through system
+/// memory. This removes the download, pixel format conversion and texture upload that otherwise
+/// dominate the CPU usage of 4K playback.
+///
+/// The renderer performs the actual graphics API calls, with the plane formats of
+/// [`PlaneFormat`], and converts from YUV to RGB in a shader. Handles are borrowed from the
+/// decoded frame (except for DMA-BUF), so the frame must be kept alive while the textures are in
+/// use.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut decoder = DecoderBuilder::new(source)
+///     .with_hardware_acceleration(HardwareAccelerationDeviceType::VaApi)
+///     .with_hardware_frames()
+///     .build()
+///     .unwrap();
+/// let frame = decoder.decode_raw().unwrap();
+/// if let TextureImport::DmaBuf(dma_buf) = TextureImport::from_raw_frame(&frame).unwrap() {
+///     for layer in &dma_buf.layers {
+///         let attributes = layer.egl_image_attributes();
+///         // eglCreateImageKHR(display, EGL_NO_CONTEXT, EGL_LINUX_DMA_BUF_EXT, null, attributes)
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub enum TextureImport<'a> {
+    /// VideoToolbox: `CVPixelBufferRef` backed by an IOSurface. Create a texture for each plane
+    /// with `CVMetalTextureCacheCreateTextureFromImage` (Metal) or `CGLTexImageIOSurface2D`
+    /// (OpenGL).
+    CvPixelBuffer {
+        pixel_buffer: HardwarePointer<'a>,
+        planes: Vec<TexturePlane>,
+    },
+    /// VA-API, Vulkan (on Linux) and DRM: DMA-BUF. Create an `EGLImage` for each layer with
+    /// [`DmaBufLayer::egl_image_attributes`].
+    DmaBuf(DmaBufFrame),
+    /// Direct3D 11: slice of an `ID3D11Texture2D` array. Create a shader resource view for each
+    /// plane with `ID3D11Device::CreateShaderResourceView` on the device of the decoder.
+    D3D11Texture {
+        texture: HardwarePointer<'a>,
+        array_index: usize,
+        planes: Vec<TexturePlane>,
+    },
+}
+
+impl<'a> TextureImport<'a> {
+    /// Prepare a decoded hardware frame for import into textures. For VA-API and Vulkan, this
+    /// exports the surface as DMA-BUF.
+    ///
+    /// Fails with [`Error::InvalidFrameFormat`] if the frame is in system memory, has a layout
+    /// other than NV12 or P010, or comes from a device type without texture interop. CUDA frames
+    /// are accessed with [`CudaFrame`](crate::core::cuda::CudaFrame) instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame returned by a decoder built with
+    ///   [`DecoderBuilder::with_hardware_frames`](crate::core::decode::DecoderBuilder::with_hardware_frames).
+    pub fn from_raw_frame(frame: &'a RawFrame) -> Result<TextureImport<'a>> {
+        let HardwareFrame {
+            handle,
+            software_format,
+        } = HardwareFrame::from_raw_frame(frame).ok_or(Error::InvalidFrameFormat)?;
+        let planes = texture_planes(software_format, frame.width(), frame.height())
+            .ok_or(Error::InvalidFrameFormat)?;
+        match handle {
+            HardwareFrameHandle::CvPixelBuffer(pixel_buffer) => Ok(TextureImport::CvPixelBuffer {
+                pixel_buffer,
+                planes,
+            }),
+            HardwareFrameHandle::D3D11Texture {
+                texture,
+                array_index,
+            } => Ok(TextureImport::D3D11Texture {
+                texture,
+                array_index,
+                planes,
+            }),
+            HardwareFrameHandle::VaSurface(_)
+            | HardwareFrameHandle::DrmPrime(_)
+            | HardwareFrameHandle::VkFrame(_) => {
+                Ok(TextureImport::DmaBuf(DmaBufFrame::from_raw_frame(frame)?))
+            }
+            HardwareFrameHandle::Cuda { .. } => Err(Error::InvalidFrameFormat),
+        }
+    }
+}
+
+unsafe impl Send for TextureImport<'_> {}
+unsafe impl Sync for TextureImport<'_> {}
+
+/// Get the layout of a DRM PRIME frame from the DRM fourcc of its first layer, for frames that do
+/// not have a hardware frames context to take it from. Returns `None` for unsupported layouts.
+///
+/// # Arguments
+///
+/// * `fourcc` - DRM fourcc of the first layer.
+fn drm_software_format(fourcc: u32) -> Option<AvPixel> {
+    match &fourcc.to_le_bytes() {
+        b"NV12" | b"R8  " => Some(AvPixel::NV12),
+        b"P010" | b"R16 " => Some(AvPixel::P010LE),
+        b"P016" => Some(AvPixel::P016LE),
+        _ => None,
+    }
+}
+
+/// Size the layers of a DMA-BUF frame. When there is a layer per plane, each layer has the size of
+/// its plane. Otherwise, a single layer holds all planes and has the size of the frame.
+///
+/// # Arguments
+///
+/// * `layers` - DRM fourcc and planes of each layer.
+/// * `planes` - Texture planes of the frame.
+/// * `width` - Frame width.
+/// * `height` - Frame height.
+fn dma_buf_layers(
+    layers: Vec<(u32, Vec<DmaBufPlane>)>,
+    planes: &[TexturePlane],
+    width: u32,
+    height: u32,
+) -> Vec<DmaBufLayer> {
+    let layer_per_plane = layers.len() == planes.len();
+    layers
+        .into_iter()
+        .enumerate()
+        .map(|(index, (fourcc, dma_buf_planes))| {
+            let (width, height) = if layer_per_plane {
+                (planes[index].width, planes[index].height)
+            } else {
+                (width, height)
+            };
+            DmaBufLayer {
+                fourcc,
+                width,
+                height,
+                planes: dma_buf_planes,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plane(fd: i32, offset: usize, modifier: u64) -> DmaBufPlane {
+        DmaBufPlane {
+            fd,
+            offset,
+            pitch: 4096,
+            modifier,
+        }
+    }
+
+    #[test]
+    fn test_texture_planes() {
+        let planes = texture_planes(AvPixel::P010LE, 3841, 2160).unwrap();
+        assert_eq!(planes[0].format, PlaneFormat::R16);
+        assert_eq!((planes[0].width, planes[0].height), (3841, 2160));
+        assert_eq!(planes[1].format, PlaneFormat::Rg16);
+        assert_eq!((planes[1].width, planes[1].height), (1921, 1080));
+        assert!(texture_planes(AvPixel::YUV420P, 1920, 1080).is_none());
+    }
+
+    #[test]
+    fn test_drm_fourcc() {
+        // `DRM_FORMAT_GR88` from `drm_fourcc.h`.
+        assert_eq!(PlaneFormat::Rg8.drm_fourcc(), 0x3838_5247);
+    }
+
+    #[test]
+    fn test_drm_software_format() {
+        // `DRM_FORMAT_NV12` from `drm_fourcc.h`.
+        assert_eq!(drm_software_format(0x3231_564E), Some(AvPixel::NV12));
+        assert_eq!(
+            drm_software_format(PlaneFormat::R16.drm_fourcc()),
+            Some(AvPixel::P010LE)
+        );
+        assert_eq!(drm_software_format(PlaneFormat::Rg8.drm_fourcc()), None);
+    }
+
+    #[test]
+    fn test_modifier_hi_lo() {
+        // `I915_FORMAT_MOD_Y_TILED` from `drm_fourcc.h`.
+        assert_eq!(
+            plane(5, 0, 0x0100_0000_0000_0002).modifier_hi_lo(),
+            (0x0100_0000, 2)
+        );
+    }
+
+    #[test]
+    fn test_layer_per_plane() {
+        let planes = texture_planes(AvPixel::NV12, 1920, 1080).unwrap();
+        let layers = dma_buf_layers(
+            vec![
+                (PlaneFormat::R8.drm_fourcc(), vec![plane(5, 0, 0)]),
+                (
+                    PlaneFormat::Rg8.drm_fourcc(),
+                    vec![plane(5, 4096 * 1088, 0)],
+                ),
+            ],
+            &planes,
+            1920,
+            1080,
+        );
+        assert_eq!((layers[1].width, layers[1].height), (960, 540));
+        let attributes = layers[1].egl_image_attributes();
+        assert_eq!(
+            attributes,
+            vec![
+                0x3057,
+                960,
+                0x3056,
+                540,
+                0x3271,
+                0x3838_5247,
+                0x3272,
+                5,
+                0x3273,
+                4096 * 1088,
+                0x3274,
+                4096,
+                0x3443,
+                0,
+                0x3444,
+                0,
+                0x3038,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_layer_without_modifier() {
+        let planes = texture_planes(AvPixel::NV12, 1920, 1080).unwrap();
+        let nv12 = u32::from_le_bytes(*b"NV12");
+        let layers = dma_buf_layers(
+            vec![(
+                nv12,
+                vec![
+                    plane(7, 0, DmaBufLayer::DRM_FORMAT_MOD_INVALID),
+                    plane(7, 4096 * 1088, DmaBufLayer::DRM_FORMAT_MOD_INVALID),
+                ],
+            )],
+            &planes,
+            1920,
+            1080,
+        );
+        assert_eq!((layers[0].width, layers[0].height), (1920, 1080));
+        let attributes = layers[0].egl_image_attributes();
+        assert_eq!(attributes.len(), 6 + 2 * 6 + 1);
+        assert_eq!(&attributes[12..14], &[0x3275, 7]);
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cancels blocking IO of a [`Reader`](crate::core::io::Reader) or
+/// [`Writer`](crate::core::io::Writer) from another thread, e.g. when a server stops responding
+/// while a stream is being opened or read.
+///
+/// Clones share the same state. Once interrupted, every blocking call of the reader or writer
+/// fails with [`Error::Interrupted`](crate::core::error::Error::Interrupted) until the interrupt is
+/// reset.
+///
+/// # Example
+///
+/// ```ignore
+/// let interrupt = Interrupt::new();
+/// let handle = interrupt.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_secs(10));
+///     handle.interrupt();
+/// });
+/// let reader = ReaderBuilder::new(url).with_interrupt(interrupt).build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt(Arc<AtomicBool>);
+
+impl Interrupt {
+    /// Create a new interrupt that has not been triggered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interrupt all blocking IO of the readers and writers that use this interrupt.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Reset the interrupt, so that IO can continue.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether or not the interrupt has been triggered.
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Get the underlying flag, which is polled by ffmpeg.
+    pub(crate) fn flag(&self) -> &AtomicBool {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupt_is_shared_between_clones() {
+        let interrupt = Interrupt::new();
+        let handle = interrupt.clone();
+        assert!(!interrupt.is_interrupted());
+        handle.interrupt();
+        assert!(interrupt.is_interrupted());
+        interrupt.reset();
+        assert!(!handle.is_interrupted());
+    }
+}
@@ -1,21 +1,348 @@
 extern crate ffmpeg_next as ffmpeg;
 
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
 use ffmpeg::codec::packet::Packet as AvPacket;
-use ffmpeg::ffi::AV_TIME_BASE_Q;
 use ffmpeg::format::context::{Input as AvInput, Output as AvOutput};
 use ffmpeg::media::Type as AvMediaType;
+use ffmpeg::util::error::EAGAIN;
 use ffmpeg::Error as AvError;
 use ffmpeg::ffi::av_seek_frame;
 
+use crate::core::abr::{self, Variant, VariantSelection};
 use crate::core::error::Error;
 use crate::core::ffi;
+use crate::core::interrupt::Interrupt;
+use crate::core::latency::LatencyProfile;
+use crate::core::live::{GrowingFile, LiveFile};
 use crate::core::location::Location;
+use crate::core::netstats::{NetworkMonitor, NetworkStatistics, NetworkStatisticsCallback};
 use crate::core::options::Options;
 use crate::core::packet::Packet;
+use crate::core::probe::StreamDetails;
+use crate::core::seek::{SeekDirection, SeekOptions, SeekTarget};
 use crate::core::stream::StreamInfo;
+use crate::core::throttle::{Throttle, Throttler};
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// Typed options for network sources such as HTTP(S) streams. Options that are not set keep the
+/// defaults of ffmpeg.
+///
+/// # Example
+///
+/// ```ignore
+/// let reader = ReaderBuilder::new(url)
+///     .with_network_options(NetworkOptions {
+///         timeout: Some(Duration::from_secs(10)),
+///         reconnect: true,
+///         user_agent: Some("my-player/1.0".to_string()),
+///         ..Default::default()
+///     })
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NetworkOptions {
+    /// Maximum time to wait for a read or write on the connection before failing.
+    pub timeout: Option<Duration>,
+    /// Reconnect when the connection drops or a network error occurs before the end of the
+    /// stream.
+    pub reconnect: bool,
+    /// Also reconnect on streams that cannot seek, such as live streams.
+    pub reconnect_streamed: bool,
+    /// Maximum delay between reconnection attempts. Rounded down to whole seconds.
+    pub reconnect_delay_max: Option<Duration>,
+    /// Extra HTTP headers as name and value.
+    pub headers: Vec<(String, String)>,
+    /// HTTP user agent.
+    pub user_agent: Option<String>,
+    /// HTTP cookies as name and value. Cookies are sent to the host of the source only, so they
+    /// can only be used with network sources.
+    pub cookies: Vec<(String, String)>,
+}
+
+impl NetworkOptions {
+    /// Add the input options that correspond to these network options to `options`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Input options to add to.
+    /// * `source` - Source that is read, used to scope cookies to its host.
+    pub(crate) fn apply(&self, options: &mut Options, source: &Location) -> Result<()> {
+        if let Some(timeout) = self.timeout {
+            options.set("rw_timeout", &timeout.as_micros().to_string());
+        }
+        if self.reconnect {
+            options.set("reconnect", "1");
+            options.set("reconnect_on_network_error", "1");
+        }
+        if self.reconnect_streamed {
+            options.set("reconnect_streamed", "1");
+        }
+        if let Some(reconnect_delay_max) = self.reconnect_delay_max {
+            options.set("reconnect_delay_max", &reconnect_delay_max.as_secs().to_string());
+        }
+        if !self.headers.is_empty() {
+            let headers = self
+                .headers
+                .iter()
+                .map(|(name, value)| format!("{name}: {value}\r\n"))
+                .collect::<String>();
+            options.set("headers", &headers);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            options.set("user_agent", user_agent);
+        }
+        if !self.cookies.is_empty() {
+            // ffmpeg only sends cookies that have a domain, so scope them to the host of the
+            // source.
+            let host = match source {
+                Location::Network(url) => url.host_str(),
+                _ => None,
+            }
+            .ok_or_else(|| Error::InvalidConfiguration {
+                field: "cookies",
+                reason: format!("{source} has no host to send cookies to"),
+            })?;
+            let cookies = self
+                .cookies
+                .iter()
+                .map(|(name, value)| format!("{name}={value}; path=/; domain={host}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            options.set("cookies", &cookies);
+        }
+        Ok(())
+    }
+}
+
+/// Connection mode of an SRT socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SrtMode {
+    /// Connect to a listener at the host and port of the URL.
+    #[default]
+    Caller,
+    /// Wait for a caller to connect on the port of the URL. Use `0.0.0.0` as host to listen on
+    /// all interfaces.
+    Listener,
+    /// Connect to a peer that is connecting back at the same time, e.g. to traverse firewalls.
+    Rendezvous,
+}
+
+impl SrtMode {
+    /// Name of the mode as used by the backend.
+    fn as_str(self) -> &'static str {
+        match self {
+            SrtMode::Caller => "caller",
+            SrtMode::Listener => "listener",
+            SrtMode::Rendezvous => "rendezvous",
+        }
+    }
+}
+
+/// Options for SRT (Secure Reliable Transport) sources and destinations, i.e. `srt://` URLs.
+/// Options that are not set keep the defaults of libsrt.
+///
+/// # Example
+///
+/// ```ignore
+/// let writer = WriterBuilder::new(Url::parse("srt://0.0.0.0:9000").unwrap())
+///     .with_srt(SrtOptions {
+///         mode: SrtMode::Listener,
+///         latency: Some(Duration::from_millis(200)),
+///         passphrase: Some("correct horse battery".to_string()),
+///         ..Default::default()
+///     })
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SrtOptions {
+    /// Whether to connect to the peer or to wait for it to connect.
+    pub mode: SrtMode,
+    /// Receive latency, i.e. how long packets are buffered to recover lost packets. Should be a
+    /// few times the round trip time. Libsrt uses 120 ms by default.
+    pub latency: Option<Duration>,
+    /// Passphrase to encrypt the stream with, 10 to 79 characters. Both peers need the same
+    /// passphrase.
+    pub passphrase: Option<String>,
+    /// Length of the encryption key in bytes: 16, 24 or 32. Only used with a passphrase.
+    pub key_length: Option<u32>,
+    /// Stream ID sent by the caller, which listeners such as media servers use to select the
+    /// stream.
+    pub stream_id: Option<String>,
+    /// Maximum time to wait for the connection to be set up in caller mode.
+    pub connect_timeout: Option<Duration>,
+}
+
+impl SrtOptions {
+    /// Add the protocol options that correspond to these SRT options to `options`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Input or output options to add to.
+    /// * `location` - Source or destination, which must be an `srt://` URL.
+    pub(crate) fn apply(&self, options: &mut Options, location: &Location) -> Result<()> {
+        if !matches!(location, Location::Network(url) if url.scheme() == "srt") {
+            return Err(Error::InvalidConfiguration {
+                field: "srt",
+                reason: format!("{location} is not an SRT URL"),
+            });
+        }
+        if let Some(passphrase) = &self.passphrase {
+            if !(10..=79).contains(&passphrase.len()) {
+                return Err(Error::InvalidConfiguration {
+                    field: "passphrase",
+                    reason: "passphrase must be 10 to 79 characters long".to_string(),
+                });
+            }
+        }
+        if let Some(key_length) = self.key_length {
+            if ![16, 24, 32].contains(&key_length) {
+                return Err(Error::InvalidConfiguration {
+                    field: "key_length",
+                    reason: "key length must be 16, 24 or 32 bytes".to_string(),
+                });
+            }
+        }
+
+        options.set("mode", self.mode.as_str());
+        if let Some(latency) = self.latency {
+            options.set("latency", &latency.as_micros().to_string());
+        }
+        if let Some(passphrase) = &self.passphrase {
+            options.set("passphrase", passphrase);
+            if let Some(key_length) = self.key_length {
+                options.set("pbkeylen", &key_length.to_string());
+            }
+        }
+        if let Some(stream_id) = &self.stream_id {
+            options.set("streamid", stream_id);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            options.set("connect_timeout", &connect_timeout.as_millis().to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Options for UDP sources, i.e. `udp://` URLs, such as MPEG-TS over multicast. Options that are
+/// not set keep the defaults of ffmpeg.
+///
+/// To receive a multicast stream, use the multicast group as host of the URL, e.g.
+/// `udp://239.0.0.1:1234`.
+///
+/// # Example
+///
+/// ```ignore
+/// let reader = ReaderBuilder::new(Url::parse("udp://239.0.0.1:1234").unwrap())
+///     .with_udp(UdpOptions {
+///         buffer_size: Some(8 * 1024 * 1024),
+///         fifo_size: Some(100_000),
+///         overrun_nonfatal: true,
+///         local_address: Some("10.0.0.2".parse().unwrap()),
+///         sources: vec!["10.0.0.1".parse().unwrap()],
+///         ..Default::default()
+///     })
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UdpOptions {
+    /// Size of the receive buffer of the socket in bytes. A larger buffer prevents packet loss
+    /// when the reader is briefly busy. The operating system may limit the size.
+    pub buffer_size: Option<usize>,
+    /// Size of the buffer between the socket and the demuxer, in packets of 188 bytes. Packets are
+    /// received on a separate thread into this buffer.
+    pub fifo_size: Option<usize>,
+    /// Drop packets when the buffer between the socket and the demuxer is full, instead of
+    /// failing.
+    pub overrun_nonfatal: bool,
+    /// Allow other sockets to bind to the same address and port, e.g. so that multiple processes
+    /// can receive the same multicast group.
+    pub reuse_address: bool,
+    /// Address of the local interface to receive on, which selects the interface that joins the
+    /// multicast group.
+    pub local_address: Option<IpAddr>,
+    /// Only receive packets from these senders (source-specific multicast).
+    pub sources: Vec<IpAddr>,
+    /// Do not receive packets from these senders.
+    pub blocked_sources: Vec<IpAddr>,
+    /// Maximum time to wait for data before failing.
+    pub timeout: Option<Duration>,
+}
+
+impl UdpOptions {
+    /// Add the protocol options that correspond to these UDP options to `options`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Input options to add to.
+    /// * `location` - Source, which must be a `udp://` URL.
+    pub(crate) fn apply(&self, options: &mut Options, location: &Location) -> Result<()> {
+        let url = match location {
+            Location::Network(url) if url.scheme() == "udp" => url,
+            _ => {
+                return Err(Error::InvalidConfiguration {
+                    field: "udp",
+                    reason: format!("{location} is not a UDP URL"),
+                })
+            }
+        };
+        if !self.sources.is_empty() || !self.blocked_sources.is_empty() {
+            let is_multicast = match url.host() {
+                Some(url::Host::Ipv4(address)) => address.is_multicast(),
+                Some(url::Host::Ipv6(address)) => address.is_multicast(),
+                _ => false,
+            };
+            if !is_multicast {
+                return Err(Error::InvalidConfiguration {
+                    field: "sources",
+                    reason: format!("{location} is not a multicast group"),
+                });
+            }
+            if !self.sources.is_empty() && !self.blocked_sources.is_empty() {
+                return Err(Error::InvalidConfiguration {
+                    field: "blocked_sources",
+                    reason: "sources cannot be allowed and blocked at the same time".to_string(),
+                });
+            }
+        }
+
+        if let Some(buffer_size) = self.buffer_size {
+            options.set("buffer_size", &buffer_size.to_string());
+        }
+        if let Some(fifo_size) = self.fifo_size {
+            options.set("fifo_size", &fifo_size.to_string());
+        }
+        if self.overrun_nonfatal {
+            options.set("overrun_nonfatal", "1");
+        }
+        if self.reuse_address {
+            options.set("reuse", "1");
+        }
+        if let Some(local_address) = self.local_address {
+            options.set("localaddr", &local_address.to_string());
+        }
+        let join = |addresses: &[IpAddr]| {
+            addresses
+                .iter()
+                .map(IpAddr::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        if !self.sources.is_empty() {
+            options.set("sources", &join(&self.sources));
+        }
+        if !self.blocked_sources.is_empty() {
+            options.set("block", &join(&self.blocked_sources));
+        }
+        if let Some(timeout) = self.timeout {
+            options.set("timeout", &timeout.as_micros().to_string());
+        }
+        Ok(())
+    }
+}
+
 /// Builds a [`Reader`].
 ///
 /// # Example
@@ -33,7 +360,17 @@ type Result<T> = std::result::Result<T, Error>;
 /// ```
 pub struct ReaderBuilder<'a> {
     source: Location,
+    custom_source: Option<CustomSource>,
     options: Option<&'a Options>,
+    network_options: Option<NetworkOptions>,
+    srt_options: Option<SrtOptions>,
+    udp_options: Option<UdpOptions>,
+    throttle: Option<Throttle>,
+    interrupt: Option<Interrupt>,
+    live_file: Option<LiveFile>,
+    variant_selection: Option<VariantSelection>,
+    latency: Option<LatencyProfile>,
+    on_network_statistics: Option<(Duration, NetworkStatisticsCallback)>,
 }
 
 impl<'a> ReaderBuilder<'a> {
@@ -45,10 +382,94 @@ impl<'a> ReaderBuilder<'a> {
     pub fn new(source: impl Into<Location>) -> Self {
         Self {
             source: source.into(),
+            custom_source: None,
+            options: None,
+            network_options: None,
+            srt_options: None,
+            udp_options: None,
+            throttle: None,
+            interrupt: None,
+            live_file: None,
+            variant_selection: None,
+            latency: None,
+            on_network_statistics: None,
+        }
+    }
+
+    /// Create a new reader that reads from any [`std::io::Read`] and [`std::io::Seek`]
+    /// implementation, such as a decrypting reader, a file inside an archive or a virtual
+    /// filesystem. The container format is probed from the data.
+    ///
+    /// The [`Reader::source`] of the resulting reader is [`Location::Custom`].
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read.
+    pub fn from_io(source: impl std::io::Read + std::io::Seek + Send + 'static) -> Self {
+        Self::from_custom_source(CustomSource::seekable(source))
+    }
+
+    /// Create a new reader that reads from a [`std::io::Read`] implementation that cannot seek,
+    /// such as a live stream. The demuxer only reads forward, so the resulting reader cannot seek
+    /// either. The container format is probed from the data.
+    ///
+    /// The [`Reader::source`] of the resulting reader is [`Location::Custom`].
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read.
+    pub fn from_read(source: impl std::io::Read + Send + 'static) -> Self {
+        Self::from_custom_source(CustomSource::unseekable(source))
+    }
+
+    /// Create a new reader that reads from a custom source.
+    ///
+    /// # Arguments
+    ///
+    /// * `custom_source` - Source to read.
+    fn from_custom_source(custom_source: CustomSource) -> Self {
+        Self {
+            source: Location::Custom,
+            custom_source: Some(custom_source),
             options: None,
+            network_options: None,
+            srt_options: None,
+            udp_options: None,
+            throttle: None,
+            interrupt: None,
+            live_file: None,
+            variant_selection: None,
+            latency: None,
+            on_network_statistics: None,
         }
     }
 
+    /// Create a new reader that reads from an async source, such as the body of an HTTP response.
+    /// Reads block the thread the reader is used on, so the reader must not be used on a thread
+    /// of an async runtime. See [`AsyncSource`](crate::core::async_io::AsyncSource).
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Async source to read.
+    #[cfg(feature = "async-io")]
+    pub fn from_async_io<S>(source: S) -> Self
+    where
+        S: futures_io::AsyncRead + futures_io::AsyncSeek + Unpin + Send + 'static,
+    {
+        Self::from_io(crate::core::async_io::AsyncSource::new(source))
+    }
+
+    /// Create a new reader that reads media that is already in memory, e.g. a small file that was
+    /// received over HTTP. This is a shorthand for [`ReaderBuilder::new`] with
+    /// [`Location::Memory`].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Contents of the media file.
+    pub fn from_bytes(bytes: impl Into<std::sync::Arc<[u8]>>) -> Self {
+        Self::new(Location::Memory(bytes.into()))
+    }
+
     /// Specify options for the backend.
     ///
     /// # Arguments
@@ -59,21 +480,298 @@ impl<'a> ReaderBuilder<'a> {
         self
     }
 
+    /// Specify typed options for network sources, such as timeouts, reconnection and HTTP
+    /// headers. These are added to the options passed with [`ReaderBuilder::with_options`].
+    ///
+    /// # Arguments
+    ///
+    /// * `network_options` - Network options to apply.
+    pub fn with_network_options(mut self, network_options: NetworkOptions) -> Self {
+        self.network_options = Some(network_options);
+        self
+    }
+
+    /// Specify options for an SRT source (`srt://` URL), such as the connection mode, latency
+    /// and passphrase. These are added to the options passed with [`ReaderBuilder::with_options`].
+    ///
+    /// # Arguments
+    ///
+    /// * `srt_options` - SRT options to apply.
+    pub fn with_srt(mut self, srt_options: SrtOptions) -> Self {
+        self.srt_options = Some(srt_options);
+        self
+    }
+
+    /// Specify options for a UDP source (`udp://` URL), such as buffer sizes and multicast source
+    /// filtering. These are added to the options passed with [`ReaderBuilder::with_options`].
+    ///
+    /// # Arguments
+    ///
+    /// * `udp_options` - UDP options to apply.
+    pub fn with_udp(mut self, udp_options: UdpOptions) -> Self {
+        self.udp_options = Some(udp_options);
+        self
+    }
+
+    /// Limit the bandwidth at which packets are read. This is meant for testing how callers behave
+    /// on slow or unstable networks.
+    ///
+    /// # Arguments
+    ///
+    /// * `throttle` - Bandwidth limit to apply.
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Make opening and reading interruptible from another thread. Without an interrupt, a server
+    /// that stops responding can block the reader indefinitely (unless a timeout is set).
+    ///
+    /// # Arguments
+    ///
+    /// * `interrupt` - Interrupt to poll during blocking calls.
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Read a file that is still being written, such as a recording in progress. At the end of the
+    /// file, the reader waits for more data instead of ending the stream. Only supported for file
+    /// sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `live_file` - How long and how often to wait for more data.
+    pub fn with_live_file(mut self, live_file: LiveFile) -> Self {
+        self.live_file = Some(live_file);
+        self
+    }
+
+    /// Play a single variant of an adaptive source, such as an HLS master playlist or a DASH
+    /// manifest. Only the streams of the selected variant are downloaded, and the
+    /// `best_*_stream_index` functions return its streams, so that the source can be played like a
+    /// file. Sources that are not adaptive are not affected. Use
+    /// [`AdaptiveReader`](crate::core::abr::AdaptiveReader) to switch variants during playback.
+    ///
+    /// # Arguments
+    ///
+    /// * `variant_selection` - Variant to play.
+    pub fn with_variant_selection(mut self, variant_selection: VariantSelection) -> Self {
+        self.variant_selection = Some(variant_selection);
+        self
+    }
+
+    /// Trade robustness for latency, e.g. to start live sources quickly. The profile limits
+    /// probing and disables buffering in the demuxer. Options passed with
+    /// [`ReaderBuilder::with_options`] take precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `latency` - Latency profile.
+    pub fn with_latency(mut self, latency: LatencyProfile) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Periodically report the throughput of the source, e.g. to show the health of the connection
+    /// to a network source. Reports are made while packets are read, on the thread that reads, so
+    /// the callback should not block for long. See [`Reader::network_statistics`].
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How often to report.
+    /// * `callback` - Callback that receives the statistics.
+    pub fn with_on_network_statistics(
+        mut self,
+        interval: Duration,
+        callback: impl FnMut(&NetworkStatistics) + Send + 'static,
+    ) -> Self {
+        self.on_network_statistics = Some((interval, Box::new(callback)));
+        self
+    }
+
     /// Build [`Reader`].
     pub fn build(self) -> Result<Reader> {
-        match self.options {
-            None => Ok(Reader {
-                input: ffmpeg::format::input(&self.source.as_path())?,
-                source: self.source,
-            }),
-            Some(options) => Ok(Reader {
-                input: ffmpeg::format::input_with_dictionary(
-                    &self.source.as_path(),
+        let throttler = self.throttle.map(Throttler::new);
+        let mut network_monitor = NetworkMonitor::new();
+        if let Some((interval, callback)) = self.on_network_statistics {
+            network_monitor = network_monitor.with_callback(interval, callback);
+        }
+        let mut options = self.options.cloned();
+        let mut format = None;
+        // Concatenated sources are read through the concat demuxer, which reads the list of
+        // sources from an in-memory script.
+        let concat_script = match &self.source {
+            Location::Concat(locations) => {
+                format = Some("concat");
+                // Allow absolute paths and URLs in the script.
+                options.get_or_insert_with(Options::default).set("safe", "0");
+                Some(Location::concat_script(locations)?)
+            }
+            _ => None,
+        };
+        // Files that are still being written are read through a source that waits at the end.
+        let live_source = match (self.live_file, &self.source) {
+            (None, _) => None,
+            (Some(live_file), Location::File(path)) => {
+                let file = std::fs::File::open(path)?;
+                Some(CustomSource::seekable(GrowingFile::new(
+                    file,
+                    live_file,
+                    self.interrupt.clone(),
+                )))
+            }
+            (Some(_), source) => {
+                return Err(Error::InvalidConfiguration {
+                    field: "live_file",
+                    reason: format!("{source} is not a file"),
+                })
+            }
+        };
+        let live = live_source.is_some();
+        let custom_source = match (self.custom_source, &self.source, concat_script, live_source) {
+            (Some(source), ..) | (_, _, _, Some(source)) => Some(source),
+            (_, Location::Memory(bytes), ..) => {
+                Some(CustomSource::seekable(std::io::Cursor::new(bytes.clone())))
+            }
+            (_, _, Some(script), _) => Some(CustomSource::seekable(std::io::Cursor::new(
+                script.into_bytes(),
+            ))),
+            _ => None,
+        };
+        if let Some(network_options) = &self.network_options {
+            network_options.apply(options.get_or_insert_with(Options::default), &self.source)?;
+        }
+        if let Some(srt_options) = &self.srt_options {
+            srt_options.apply(options.get_or_insert_with(Options::default), &self.source)?;
+        }
+        if let Some(udp_options) = &self.udp_options {
+            udp_options.apply(options.get_or_insert_with(Options::default), &self.source)?;
+        }
+        // Image sequences carry no timing, so the timestamps follow from the frame rate.
+        if let Location::Pattern(_, fps) = &self.source {
+            if *fps == 0 {
+                return Err(Error::InvalidConfiguration {
+                    field: "source",
+                    reason: "frame rate of an image sequence must be greater than zero".to_string(),
+                });
+            }
+            let options = options.get_or_insert_with(Options::default);
+            options.set("framerate", &fps.to_string());
+            options.set("pattern_type", "sequence");
+        }
+        if let Some(latency) = self.latency {
+            latency.apply(options.get_or_insert_with(Options::default));
+        }
+        // A pipe cannot be rewound, so everything needed to detect the streams must be read in one
+        // go. Probe further than usual, unless the caller has decided otherwise.
+        if self.source.is_pipe() {
+            let options = options.get_or_insert_with(Options::default);
+            if options.get("probesize").is_none() {
+                options.set("probesize", &Reader::PIPE_PROBE_SIZE.to_string());
+            }
+            if options.get("analyzeduration").is_none() {
+                options.set("analyzeduration", &Reader::PIPE_ANALYZE_DURATION.to_string());
+            }
+        }
+        if let Some(custom_source) = custom_source {
+            let options = options.unwrap_or_default();
+            let reader = Reader {
+                input: ffi::input_source(
+                    custom_source.source,
+                    custom_source.read,
+                    custom_source.seek,
+                    format,
                     options.to_dict(),
+                    self.interrupt.as_ref().map(Interrupt::flag),
+                    Reader::CUSTOM_SOURCE_BUFFER_SIZE,
                 )?,
                 source: self.source,
-            }),
+                throttler,
+                network_monitor,
+                custom_source: Some(custom_source),
+                interrupt: self.interrupt,
+                live,
+                variant: None,
+            };
+            return Ok(reader.select_variant(self.variant_selection));
         }
+        let input = match (options, &self.interrupt) {
+            (options, Some(interrupt)) => ffi::input_with_interrupt(
+                self.source.as_path(),
+                options.unwrap_or_default().to_dict(),
+                interrupt.flag(),
+            )?,
+            (None, None) => ffmpeg::format::input(&self.source.as_path())?,
+            (Some(options), None) => {
+                ffmpeg::format::input_with_dictionary(&self.source.as_path(), options.to_dict())?
+            }
+        };
+        let reader = Reader {
+            input,
+            source: self.source,
+            throttler,
+            network_monitor,
+            custom_source: None,
+            interrupt: self.interrupt,
+            live,
+            variant: None,
+        };
+        Ok(reader.select_variant(self.variant_selection))
+    }
+}
+
+/// Source of a reader that reads from memory or from a Rust reader, rather than through a
+/// protocol of the backend.
+struct CustomSource {
+    // Pointer to the boxed source. The IO context points to it, and it keeps the same address when
+    // the reader is moved. Freed through `drop_source`.
+    source: *mut std::ffi::c_void,
+    read: ffi::InputSourceRead,
+    // `None` if the demuxer may not seek in the source.
+    seek: Option<ffi::InputSourceSeek>,
+    drop_source: unsafe fn(*mut std::ffi::c_void),
+}
+
+impl CustomSource {
+    /// Create a custom source that the demuxer may seek in.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read.
+    fn seekable<S: std::io::Read + std::io::Seek + Send + 'static>(source: S) -> Self {
+        let mut custom_source = Self::unseekable(source);
+        custom_source.seek = Some(ffi::input_source_seek_callback::<S>());
+        custom_source
+    }
+
+    /// Create a custom source that the demuxer only reads forward.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read.
+    fn unseekable<S: std::io::Read + Send + 'static>(source: S) -> Self {
+        Self {
+            source: Box::into_raw(Box::new(source)) as *mut std::ffi::c_void,
+            read: ffi::input_source_read_callback::<S>(),
+            seek: None,
+            drop_source: Self::drop_source::<S>,
+        }
+    }
+
+    /// Free a source that was boxed by [`CustomSource::unseekable`].
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Pointer to the boxed source of type `S`.
+    unsafe fn drop_source<S>(source: *mut std::ffi::c_void) {
+        drop(Box::from_raw(source as *mut S));
+    }
+}
+
+impl Drop for CustomSource {
+    fn drop(&mut self) {
+        unsafe { (self.drop_source)(self.source) }
     }
 }
 
@@ -81,9 +779,32 @@ impl<'a> ReaderBuilder<'a> {
 pub struct Reader {
     pub source: Location,
     pub input: AvInput,
+    throttler: Option<Throttler>,
+    network_monitor: NetworkMonitor,
+    // The IO context of `input` points to the custom source, so it is detached in `Drop` before
+    // the source is dropped.
+    custom_source: Option<CustomSource>,
+    // Kept alive because the interrupt callback of `input` points to it.
+    interrupt: Option<Interrupt>,
+    // Whether the source is a file that is still being written.
+    live: bool,
+    // Variant of an adaptive source that was selected when it was opened.
+    variant: Option<Variant>,
 }
 
 impl Reader {
+    /// Size of the buffer between a custom source and the demuxer.
+    const CUSTOM_SOURCE_BUFFER_SIZE: usize = 32 * 1024;
+
+    /// Number of bytes to probe when reading from a pipe.
+    const PIPE_PROBE_SIZE: usize = 32 * 1024 * 1024;
+
+    /// Duration in microseconds to analyze when reading from a pipe.
+    const PIPE_ANALYZE_DURATION: usize = 10_000_000;
+
+    /// Time to wait before reading again when the demuxer has no packet available yet.
+    const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
     /// Create a new video file reader on a given source (path, URL, etc.).
     ///
     /// # Arguments
@@ -110,24 +831,100 @@ impl Reader {
     /// let mut packet = reader.read(stream).unwrap();
     /// ```
     pub fn read(&mut self, stream_index: usize) -> Result<Packet> {
+        loop {
+            let (index, packet) = self.read_any()?;
+            if index == stream_index {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Read a single packet from any stream of the source.
+    ///
+    /// # Return value
+    ///
+    /// Index of the stream the packet belongs to, and the packet.
+    pub fn read_any(&mut self) -> Result<(usize, Packet)> {
         let mut error_count = 0;
         loop {
-            match self.input.packets().next() {
-                Some((stream, packet)) => {
-                    if stream.index() == stream_index {
-                        return Ok(Packet::new(packet, stream.time_base()));
+            let mut packet = AvPacket::empty();
+            match packet.read(&mut self.input) {
+                Ok(()) => {
+                    let stream_index = packet.stream();
+                    let time_base = self
+                        .input
+                        .stream(stream_index)
+                        .ok_or(AvError::StreamNotFound)?
+                        .time_base();
+                    // Packets of other streams are received over the same connection, so they
+                    // count towards the bandwidth as well.
+                    if let Some(throttler) = self.throttler.as_mut() {
+                        throttler.wait(packet.size());
+                    }
+                    let now = Instant::now();
+                    self.network_monitor.record(packet.size(), now);
+                    if self.network_monitor.is_due(now) {
+                        let statistics = self.network_monitor.statistics(now);
+                        self.network_monitor.report(now, &statistics);
+                    }
+                    // The duration of a file that is still being written grows as it is read.
+                    if let (true, Some(pts)) = (self.live, packet.pts()) {
+                        ffi::extend_input_duration(
+                            &mut self.input,
+                            stream_index,
+                            pts + packet.duration(),
+                        );
                     }
+                    return Ok((stream_index, Packet::new(packet, time_base)));
                 }
-                None => {
+                // Custom sources end the input when they are interrupted.
+                Err(AvError::Eof) if self.is_interrupted() => return Err(Error::Interrupted),
+                Err(AvError::Eof) => {
                     error_count += 1;
                     if error_count > 3 {
                         return Err(Error::ReadExhausted);
                     }
                 }
+                // Retrying would block forever once the reader has been interrupted.
+                Err(AvError::Exit) => return Err(Error::Interrupted),
+                // The demuxer has no packet available yet, e.g. on a non-blocking network input.
+                Err(AvError::Other { errno }) if errno == EAGAIN => {
+                    if self.is_interrupted() {
+                        return Err(Error::Interrupted);
+                    }
+                    std::thread::sleep(Self::RETRY_INTERVAL);
+                }
+                Err(err) => return Err(err.into()),
             }
         }
     }
 
+    /// Get the amount of data read from the source and the current throughput, as measured from
+    /// the packets that were read. Round trip time, packet loss and buffer depth are not known
+    /// for sources read through ffmpeg.
+    pub fn network_statistics(&self) -> NetworkStatistics {
+        self.network_monitor.statistics(Instant::now())
+    }
+
+    /// Iterate over the packets of all streams of the source, in the order they are stored. The
+    /// iterator ends when the source is exhausted, or after yielding the first error.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut reader = Reader::new(Path::new("my_video.mp4")).unwrap();
+    /// for item in reader.packets() {
+    ///     let (stream_index, packet) = item.unwrap();
+    ///     // Route the packet to the decoder of `stream_index`.
+    /// }
+    /// ```
+    pub fn packets(&mut self) -> Packets<'_> {
+        Packets {
+            reader: self,
+            done: false,
+        }
+    }
+
     /// Retrieve stream information for a stream. Stream information can be used to set up a
     /// corresponding stream for transmuxing or transcoding.
     ///
@@ -138,6 +935,25 @@ impl Reader {
         StreamInfo::from_reader(self, stream_index)
     }
 
+    /// Describe all streams of the reader, in stream order. This can be used to implement custom
+    /// stream selection when the `best_*_stream_index` functions do not suffice.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let reader = Reader::new(Path::new("movie.mkv")).unwrap();
+    /// let dutch_audio = reader
+    ///     .streams()
+    ///     .into_iter()
+    ///     .find(|s| s.medium == Type::Audio && s.language.as_deref() == Some("dut"));
+    /// ```
+    pub fn streams(&self) -> Vec<StreamDetails> {
+        self.input
+            .streams()
+            .map(|stream| StreamDetails::new(&stream))
+            .collect()
+    }
+
     /// Seek in reader. This will change the reader head so that it points to a location within one
     /// second of the target timestamp or it will return an error.
     ///
@@ -145,25 +961,53 @@ impl Reader {
     ///
     /// * `timestamp_milliseconds` - Number of millisecond from start of video to seek to.
     pub fn seek(&mut self, timestamp_milliseconds: i64) -> Result<()> {
-        // Conversion factor from timestamp in milliseconds to `TIME_BASE` units.
-        const CONVERSION_FACTOR: i64 = (AV_TIME_BASE_Q.den / 1000) as i64;
-        // One second left and right leeway when seeking.
-        const LEEWAY: i64 = AV_TIME_BASE_Q.den as i64;
-
-        let timestamp = CONVERSION_FACTOR * timestamp_milliseconds;
-        let range = timestamp - LEEWAY..timestamp + LEEWAY;
+        self.seek_with(
+            SeekTarget::Milliseconds(timestamp_milliseconds),
+            SeekOptions::default(),
+        )
+    }
 
-        self.input
-            .seek(timestamp, range)
+    /// Seek in reader with explicit seek semantics, e.g. to land on the keyframe before the target
+    /// when scrubbing, or to seek by byte offset in a live MPEG-TS stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Where to seek to.
+    /// * `options` - How to position the reader relative to the target.
+    pub fn seek_with(&mut self, target: SeekTarget, options: SeekOptions) -> Result<()> {
+        self.ensure_seekable()?;
+        let (min, position, max) = options.range(target);
+        self.reset_throttle();
+        ffi::seek_file(&mut self.input, min, position, max, options.flags(target))
             .map_err(Error::BackendError)
     }
 
+    /// Seek to a byte offset in the source. The reader lands on the first packet the demuxer can
+    /// find at or before the offset. This works for formats with a missing or broken index, such
+    /// as raw MPEG-TS dumps, where seeking by timestamp fails.
+    ///
+    /// Use [`Reader::seek_with`] with [`SeekOptions::with_any_frame`] to also land on
+    /// non-keyframes.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Byte offset from the start of the source.
+    pub fn seek_to_byte(&mut self, offset: u64) -> Result<()> {
+        let offset = i64::try_from(offset).unwrap_or(i64::MAX);
+        self.seek_with(
+            SeekTarget::Byte(offset),
+            SeekOptions::new().with_direction(SeekDirection::Backward),
+        )
+    }
+
     /// Seek to a specific frame in the video stream.
     ///
     /// # Arguments
     ///
     /// * `frame_number` - The frame number to seek to.
     pub fn seek_to_frame(&mut self, frame_number: i64) -> Result<()> {
+        self.ensure_seekable()?;
+        self.reset_throttle();
         unsafe {
             match av_seek_frame(self.input.as_mut_ptr(), -1, frame_number, 0) {
                 0 => Ok(()),
@@ -175,31 +1019,322 @@ impl Reader {
     /// Seek to start of reader. This function performs best effort seeking to the start of the
     /// file.
     pub fn seek_to_start(&mut self) -> Result<()> {
+        self.ensure_seekable()?;
+        self.reset_throttle();
         self.input.seek(i64::MIN, ..).map_err(Error::BackendError)
     }
 
-    /// Find the best video stream and return the index.
-    pub fn best_video_stream_index(&self) -> Result<usize> {
-        Ok(self
-            .input
-            .streams()
-            .best(AvMediaType::Video)
-            .ok_or(AvError::StreamNotFound)?
-            .index())
-    }
+    /// Whether or not the source can be seeked. Pipes such as standard input cannot be seeked, and
+    /// the seek functions fail with [`Error::NotSeekable`] for them.
+    pub fn is_seekable(&self) -> bool {
+        !self.source.is_pipe() && ffi::input_is_seekable(&self.input)
+    }
+
+    /// Fail with [`Error::NotSeekable`] if the source cannot be seeked.
+    fn ensure_seekable(&self) -> Result<()> {
+        if self.is_seekable() {
+            Ok(())
+        } else {
+            Err(Error::NotSeekable)
+        }
+    }
+
+    /// Whether or not the interrupt of the reader, if any, has been triggered.
+    fn is_interrupted(&self) -> bool {
+        self.interrupt.as_ref().is_some_and(Interrupt::is_interrupted)
+    }
+
+    /// Variant of an adaptive source that is played, if one was selected with
+    /// [`ReaderBuilder::with_variant_selection`].
+    #[inline]
+    pub fn variant(&self) -> Option<&Variant> {
+        self.variant.as_ref()
+    }
+
+    /// Select a variant of an adaptive source and discard the streams of all other variants.
+    fn select_variant(mut self, variant_selection: Option<VariantSelection>) -> Self {
+        let Some(variant_selection) = variant_selection else {
+            return self;
+        };
+        let variants = abr::input_variants(&self.input);
+        if let Some(index) = variant_selection.select(&variants) {
+            abr::discard_other_variants(&mut self.input, &variants, index);
+            self.variant = Some(variants[index].clone());
+        }
+        self
+    }
+
+    /// Restart bandwidth pacing, since a seek discards whatever was buffered.
+    fn reset_throttle(&mut self) {
+        if let Some(throttler) = self.throttler.as_mut() {
+            throttler.reset();
+        }
+    }
+
+    /// Find the best video stream and return the index.
+    pub fn best_video_stream_index(&self) -> Result<usize> {
+        if let Some(stream_index) = self
+            .variant
+            .as_ref()
+            .and_then(|variant| variant.video_stream_index)
+        {
+            return Ok(stream_index);
+        }
+        Ok(self
+            .input
+            .streams()
+            .best(AvMediaType::Video)
+            .ok_or(AvError::StreamNotFound)?
+            .index())
+    }
+
+    /// Find the best audio stream and return the index.
+    pub fn best_audio_stream_index(&self) -> Result<usize> {
+        if let Some(stream_index) = self
+            .variant
+            .as_ref()
+            .and_then(|variant| variant.audio_stream_index)
+        {
+            return Ok(stream_index);
+        }
+        Ok(self
+            .input
+            .streams()
+            .best(AvMediaType::Audio)
+            .ok_or(AvError::StreamNotFound)?
+            .index())
+    }
+
+    /// Find the best subtitle stream and return the index.
+    pub fn best_subtitle_stream_index(&self) -> Result<usize> {
+        Ok(self
+            .input
+            .streams()
+            .best(AvMediaType::Subtitle)
+            .ok_or(AvError::StreamNotFound)?
+            .index())
+    }
+}
+
+/// Iterator over the packets of all streams of a [`Reader`]. Created by [`Reader::packets`].
+pub struct Packets<'a> {
+    reader: &'a mut Reader,
+    done: bool,
+}
+
+impl Iterator for Packets<'_> {
+    type Item = Result<(usize, Packet)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.reader.read_any() {
+            Ok(item) => Some(Ok(item)),
+            Err(Error::ReadExhausted) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for Packets<'_> {}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        if self.custom_source.is_some() {
+            ffi::input_source_end(&mut self.input);
+        }
+    }
+}
+
+unsafe impl Send for Reader {}
+unsafe impl Sync for Reader {}
+
+/// Any type that implements this can write video packets.
+pub trait Write: private::Write + private::Output {}
+
+/// Settings for fragmented MP4 (fMP4) output.
+///
+/// Fragmented output starts with an empty `moov` box and then writes self-contained `moof`/`mdat`
+/// fragments, so it can be streamed while it is being written and fed to MSE or used for DASH and
+/// low-latency delivery.
+#[derive(Debug, Clone, Default)]
+pub struct Fragmentation {
+    /// Minimum duration of a fragment. A new fragment is started at the first keyframe after this
+    /// duration has passed. By default every keyframe starts a new fragment.
+    pub fragment_duration: Option<Duration>,
+    /// Start a new fragment for every frame instead of on keyframes. This minimizes latency at the
+    /// cost of overhead and ignores `fragment_duration`.
+    pub low_latency: bool,
+}
+
+impl Fragmentation {
+    /// Add the muxer options that enable this fragmentation mode to `options`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Muxer options to add to.
+    pub(crate) fn apply(&self, options: &mut Options) {
+        let movflags = if self.low_latency {
+            "frag_every_frame+empty_moov+default_base_moof"
+        } else {
+            "frag_keyframe+empty_moov+default_base_moof"
+        };
+        options.set("movflags", movflags);
+        if let Some(fragment_duration) = self.fragment_duration {
+            options.set("min_frag_duration", &fragment_duration.as_micros().to_string());
+        }
+    }
+}
+
+/// Settings for Matroska (MKV) output. Settings that are not set keep the defaults of the muxer.
+#[derive(Debug, Clone, Default)]
+pub struct Matroska {
+    /// Move the cues (the seek index) to the front of the file once writing is done, so players
+    /// can seek without reading to the end first. Requires a seekable output, and rewrites the
+    /// file unless enough space was reserved with `reserve_index_space`.
+    pub cues_to_front: bool,
+    /// Number of bytes to reserve at the front of the file for the cues. If the cues fit, they
+    /// are written there without rewriting the file.
+    pub reserve_index_space: Option<usize>,
+    /// Maximum duration of a cluster. Shorter clusters make seeking more precise.
+    pub cluster_time_limit: Option<Duration>,
+    /// Maximum size of a cluster in bytes.
+    pub cluster_size_limit: Option<usize>,
+    /// Write CRC32 checksums for each element, so that damage can be detected. Enabled by default.
+    pub write_crc32: Option<bool>,
+}
+
+impl Matroska {
+    /// Apply these settings to the header options of a writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Header options to apply settings to.
+    pub(crate) fn apply(&self, options: &mut Options) {
+        if self.cues_to_front {
+            options.set("cues_to_front", "1");
+        }
+        if let Some(reserve_index_space) = self.reserve_index_space {
+            options.set("reserve_index_space", &reserve_index_space.to_string());
+        }
+        if let Some(cluster_time_limit) = self.cluster_time_limit {
+            options.set("cluster_time_limit", &cluster_time_limit.as_millis().to_string());
+        }
+        if let Some(cluster_size_limit) = self.cluster_size_limit {
+            options.set("cluster_size_limit", &cluster_size_limit.to_string());
+        }
+        if let Some(write_crc32) = self.write_crc32 {
+            options.set("write_crc32", if write_crc32 { "1" } else { "0" });
+        }
+    }
+}
+
+/// Settings for MPEG transport stream (MPEG-TS) output, e.g. for piping into broadcast equipment
+/// or streaming over UDP. Settings that are not set keep the defaults of the muxer.
+#[derive(Debug, Clone, Default)]
+pub struct MpegTs {
+    /// Maximum interval between PCRs. Broadcast equipment usually requires at most 40 ms.
+    pub pcr_period: Option<Duration>,
+    /// Maximum interval between PAT and PMT tables.
+    pub pat_period: Option<Duration>,
+    /// Maximum interval between SDT tables.
+    pub sdt_period: Option<Duration>,
+    /// Transport stream ID.
+    pub transport_stream_id: Option<u16>,
+    /// Original network ID.
+    pub original_network_id: Option<u16>,
+    /// Service ID, also known as program number.
+    pub service_id: Option<u16>,
+    /// PID of the PMT.
+    pub pmt_pid: Option<u16>,
+    /// PID of the first elementary stream.
+    pub start_pid: Option<u16>,
+    /// Service name that is written to the SDT.
+    pub service_name: Option<String>,
+    /// Service provider that is written to the SDT.
+    pub service_provider: Option<String>,
+    /// Constant mux rate in bits per second. Null packets are inserted to keep the rate constant.
+    /// By default the output has a variable bit rate.
+    pub mux_rate: Option<u64>,
+    /// Write PAT and PMT before every keyframe, so receivers can join at any keyframe.
+    pub resend_headers: bool,
+    /// Set the discontinuity indicator on the first packet of each stream, so receivers do not
+    /// report continuity counter errors when the output continues an earlier stream, e.g. after a
+    /// restart.
+    pub initial_discontinuity: bool,
 }
 
-unsafe impl Send for Reader {}
-unsafe impl Sync for Reader {}
+impl MpegTs {
+    /// Apply these settings to the header options and metadata of a writer.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer to apply settings to.
+    pub(crate) fn apply<W: private::Write + private::Output>(&self, writer: &mut W) {
+        let options = writer.header_options_mut();
+        if let Some(pcr_period) = self.pcr_period {
+            options.set("pcr_period", &pcr_period.as_millis().to_string());
+        }
+        if let Some(pat_period) = self.pat_period {
+            options.set("pat_period", &pat_period.as_secs_f64().to_string());
+        }
+        if let Some(sdt_period) = self.sdt_period {
+            options.set("sdt_period", &sdt_period.as_secs_f64().to_string());
+        }
+        let ids = [
+            ("mpegts_transport_stream_id", self.transport_stream_id),
+            ("mpegts_original_network_id", self.original_network_id),
+            ("mpegts_service_id", self.service_id),
+            ("mpegts_pmt_start_pid", self.pmt_pid),
+            ("mpegts_start_pid", self.start_pid),
+        ];
+        for (key, value) in ids {
+            if let Some(value) = value {
+                options.set(key, &value.to_string());
+            }
+        }
+        if let Some(mux_rate) = self.mux_rate {
+            options.set("muxrate", &mux_rate.to_string());
+        }
+        let mut flags = String::new();
+        if self.resend_headers {
+            flags.push_str("+resend_headers");
+        }
+        if self.initial_discontinuity {
+            flags.push_str("+initial_discontinuity");
+        }
+        if !flags.is_empty() {
+            options.set("mpegts_flags", &flags);
+        }
 
-/// Any type that implements this can write video packets.
-pub trait Write: private::Write + private::Output {}
+        let output = writer.output_mut();
+        if let Some(service_name) = &self.service_name {
+            ffi::set_output_metadata(output, "service_name", service_name);
+        }
+        if let Some(service_provider) = &self.service_provider {
+            ffi::set_output_metadata(output, "service_provider", service_provider);
+        }
+    }
+}
 
 /// Build a [`Writer`].
 pub struct WriterBuilder<'a> {
     destination: Location,
     format: Option<&'a str>,
     options: Option<&'a Options>,
+    fragmentation: Option<Fragmentation>,
+    mpeg_ts: Option<MpegTs>,
+    matroska: Option<Matroska>,
+    srt_options: Option<SrtOptions>,
+    metadata: Vec<(&'a str, &'a str)>,
+    interrupt: Option<Interrupt>,
 }
 
 impl<'a> WriterBuilder<'a> {
@@ -213,6 +1348,12 @@ impl<'a> WriterBuilder<'a> {
             destination: destination.into(),
             format: None,
             options: None,
+            fragmentation: None,
+            mpeg_ts: None,
+            matroska: None,
+            srt_options: None,
+            metadata: Vec::new(),
+            interrupt: None,
         }
     }
 
@@ -236,33 +1377,146 @@ impl<'a> WriterBuilder<'a> {
         self
     }
 
+    /// Write fragmented MP4 (fMP4). If no format was specified, the format is set to `mp4`.
+    ///
+    /// # Arguments
+    ///
+    /// * `fragmentation` - How to fragment the output.
+    pub fn with_fragmented_mp4(mut self, fragmentation: Fragmentation) -> Self {
+        self.format = self.format.or(Some("mp4"));
+        self.fragmentation = Some(fragmentation);
+        self
+    }
+
+    /// Set a global metadata entry of the output container, such as `title`, `artist`,
+    /// `comment` or any custom key. Which keys end up in the file depends on the container.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Metadata key.
+    /// * `value` - Metadata value.
+    pub fn with_metadata(mut self, key: &'a str, value: &'a str) -> Self {
+        self.metadata.push((key, value));
+        self
+    }
+
+    /// Write an MPEG transport stream. If no format was specified, the format is set to `mpegts`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mpeg_ts` - MPEG-TS settings.
+    pub fn with_mpeg_ts(mut self, mpeg_ts: MpegTs) -> Self {
+        self.format = self.format.or(Some("mpegts"));
+        self.mpeg_ts = Some(mpeg_ts);
+        self
+    }
+
+    /// Write Matroska (MKV). If no format was specified, the format is set to `matroska`.
+    ///
+    /// # Arguments
+    ///
+    /// * `matroska` - Matroska settings.
+    pub fn with_matroska(mut self, matroska: Matroska) -> Self {
+        self.format = self.format.or(Some("matroska"));
+        self.matroska = Some(matroska);
+        self
+    }
+
+    /// Stream to an SRT destination (`srt://` URL) with the given connection mode, latency and
+    /// passphrase. These are added to the options passed with [`WriterBuilder::with_options`].
+    /// If no format was specified, the format is set to `mpegts`, which is what SRT receivers
+    /// expect.
+    ///
+    /// # Arguments
+    ///
+    /// * `srt_options` - SRT options to apply.
+    pub fn with_srt(mut self, srt_options: SrtOptions) -> Self {
+        self.format = self.format.or(Some("mpegts"));
+        self.srt_options = Some(srt_options);
+        self
+    }
+
+    /// Make opening and writing interruptible from another thread, e.g. when streaming to a
+    /// server that stops responding.
+    ///
+    /// # Arguments
+    ///
+    /// * `interrupt` - Interrupt to poll during blocking calls.
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
     /// Build [`Writer`].
     pub fn build(self) -> Result<Writer> {
-        match (self.format, self.options) {
-            (None, None) => Ok(Writer {
-                output: ffmpeg::format::output(&self.destination.as_path())?,
-                destination: self.destination,
-            }),
-            (Some(format), None) => Ok(Writer {
-                output: ffmpeg::format::output_as(&self.destination.as_path(), format)?,
-                destination: self.destination,
-            }),
-            (None, Some(options)) => Ok(Writer {
-                output: ffmpeg::format::output_with(
-                    &self.destination.as_path(),
-                    options.to_dict(),
-                )?,
-                destination: self.destination,
-            }),
-            (Some(format), Some(options)) => Ok(Writer {
-                output: ffmpeg::format::output_as_with(
-                    &self.destination.as_path(),
-                    format,
-                    options.to_dict(),
-                )?,
-                destination: self.destination,
-            }),
+        // A pipe cannot be seeked, so the format cannot be derived from a file extension, and the
+        // muxer cannot go back to finish the header once all packets are written.
+        if self.destination.is_pipe() {
+            match self.format {
+                None => {
+                    return Err(Error::InvalidConfiguration {
+                        field: "format",
+                        reason: "a format is required when writing to a pipe".to_string(),
+                    })
+                }
+                Some(format)
+                    if Writer::UNSTREAMABLE_FORMATS.contains(&format)
+                        && self.fragmentation.is_none() =>
+                {
+                    return Err(Error::InvalidConfiguration {
+                        field: "format",
+                        reason: format!("{format} can only be written to a pipe when fragmented"),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut options = self.options.cloned();
+        if let Some(srt_options) = &self.srt_options {
+            srt_options.apply(options.get_or_insert_with(Options::default), &self.destination)?;
+        }
+
+        let destination = self.destination.as_path();
+        let output = match (self.format, options, &self.interrupt) {
+            (format, options, Some(interrupt)) => ffi::output_with_interrupt(
+                destination,
+                format,
+                options.unwrap_or_default().to_dict(),
+                interrupt.flag(),
+            )?,
+            (None, None, None) => ffmpeg::format::output(&destination)?,
+            (Some(format), None, None) => ffmpeg::format::output_as(&destination, format)?,
+            (None, Some(options), None) => {
+                ffmpeg::format::output_with(&destination, options.to_dict())?
+            }
+            (Some(format), Some(options), None) => {
+                ffmpeg::format::output_as_with(&destination, format, options.to_dict())?
+            }
+        };
+
+        let mut header_options = Options::default();
+        if let Some(fragmentation) = self.fragmentation {
+            fragmentation.apply(&mut header_options);
+        }
+        if let Some(matroska) = self.matroska {
+            matroska.apply(&mut header_options);
+        }
+
+        let mut writer = Writer {
+            destination: self.destination,
+            output,
+            header_options,
+            _interrupt: self.interrupt,
+        };
+        if let Some(mpeg_ts) = self.mpeg_ts {
+            mpeg_ts.apply(&mut writer);
+        }
+        for (key, value) in self.metadata {
+            writer.set_metadata(key, value);
         }
+
+        Ok(writer)
     }
 }
 
@@ -273,22 +1527,24 @@ impl<'a> WriterBuilder<'a> {
 /// Create a video writer that produces fragmented MP4:
 ///
 /// ```ignore
-/// let mut options = HashMap::new();
-/// options.insert(
-///     "movflags".to_string(),
-///     "frag_keyframe+empty_moov".to_string(),
-/// );
-///
 /// let mut writer = WriterBuilder::new(Path::new("my_file.mp4"))
-/// .with_options(&options.into())
-/// .unwrap();
+///     .with_fragmented_mp4(Fragmentation::default())
+///     .build()
+///     .unwrap();
 /// ```
 pub struct Writer {
     pub destination: Location,
     pub(crate) output: AvOutput,
+    header_options: Options,
+    // Kept alive because the interrupt callback of `output` points to it.
+    _interrupt: Option<Interrupt>,
 }
 
 impl Writer {
+    /// Formats that need a seekable output, unless they are fragmented.
+    const UNSTREAMABLE_FORMATS: &'static [&'static str] =
+        &["mp4", "mov", "ipod", "3gp", "3g2", "ismv", "f4v"];
+
     /// Create a new file writer for video files.
     ///
     /// # Arguments
@@ -298,6 +1554,29 @@ impl Writer {
     pub fn new(destination: impl Into<Location>) -> Result<Self> {
         WriterBuilder::new(destination).build()
     }
+
+    /// Set a global metadata entry of the output container. This only has effect before the
+    /// header has been written.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Metadata key.
+    /// * `value` - Metadata value.
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        ffi::set_output_metadata(&mut self.output, key, value);
+    }
+
+    /// Set a metadata entry of an output stream, such as `language` or `title`. This only has
+    /// effect before the header has been written.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of output stream.
+    /// * `key` - Metadata key.
+    /// * `value` - Metadata value.
+    pub fn set_stream_metadata(&mut self, stream_index: usize, key: &str, value: &str) {
+        ffi::set_output_stream_metadata(&mut self.output, stream_index, key, value);
+    }
 }
 
 impl Write for Writer {}
@@ -305,6 +1584,62 @@ impl Write for Writer {}
 unsafe impl Send for Writer {}
 unsafe impl Sync for Writer {}
 
+/// Implement [`Write`] for a type that wraps a [`Writer`] in a field named `writer`, by delegating
+/// to the wrapped writer. Used by writers that only differ from [`Writer`] in how they are set up.
+macro_rules! delegate_writer {
+    ($wrapper:ty) => {
+        impl From<$wrapper> for $crate::core::io::Writer {
+            fn from(wrapper: $wrapper) -> $crate::core::io::Writer {
+                wrapper.writer
+            }
+        }
+
+        impl $crate::core::io::Write for $wrapper {}
+
+        impl $crate::core::io::private::Write for $wrapper {
+            type Out = ();
+
+            fn write_header(&mut self) -> std::result::Result<(), $crate::core::error::Error> {
+                $crate::core::io::private::Write::write_header(&mut self.writer)
+            }
+
+            fn write(
+                &mut self,
+                packet: &mut ::ffmpeg_next::codec::packet::Packet,
+            ) -> std::result::Result<(), $crate::core::error::Error> {
+                $crate::core::io::private::Write::write(&mut self.writer, packet)
+            }
+
+            fn write_interleaved(
+                &mut self,
+                packet: &mut ::ffmpeg_next::codec::packet::Packet,
+            ) -> std::result::Result<(), $crate::core::error::Error> {
+                $crate::core::io::private::Write::write_interleaved(&mut self.writer, packet)
+            }
+
+            fn write_trailer(&mut self) -> std::result::Result<(), $crate::core::error::Error> {
+                $crate::core::io::private::Write::write_trailer(&mut self.writer)
+            }
+
+            fn header_options_mut(&mut self) -> &mut $crate::core::options::Options {
+                $crate::core::io::private::Write::header_options_mut(&mut self.writer)
+            }
+        }
+
+        impl $crate::core::io::private::Output for $wrapper {
+            fn output(&self) -> &::ffmpeg_next::format::context::Output {
+                $crate::core::io::private::Output::output(&self.writer)
+            }
+
+            fn output_mut(&mut self) -> &mut ::ffmpeg_next::format::context::Output {
+                $crate::core::io::private::Output::output_mut(&mut self.writer)
+            }
+        }
+    };
+}
+
+pub(crate) use delegate_writer;
+
 /// Type alias for a byte buffer.
 pub type Buf = Vec<u8>;
 
@@ -492,13 +1827,153 @@ impl Write for PacketizedBufWriter {}
 unsafe impl Send for PacketizedBufWriter {}
 unsafe impl Sync for PacketizedBufWriter {}
 
+/// Build a [`SinkWriter`].
+pub struct SinkWriterBuilder<'a, S: std::io::Write + Send> {
+    sink: S,
+    seek: Option<ffi::OutputSinkSeek>,
+    format: &'a str,
+    options: Option<&'a Options>,
+}
+
+impl<'a, S: std::io::Write + std::io::Seek + Send> SinkWriterBuilder<'a, S> {
+    /// Create a new writer that writes to a seekable sink, such as a file or an in-memory cursor.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Sink to write to.
+    /// * `format` - Container format to use.
+    pub fn new(sink: S, format: &'a str) -> Self {
+        Self {
+            sink,
+            seek: Some(ffi::output_sink_seek_callback::<S>()),
+            format,
+            options: None,
+        }
+    }
+}
+
+impl<'a, S: std::io::Write + Send> SinkWriterBuilder<'a, S> {
+    /// Create a new writer that writes to a sink that cannot seek, such as a socket or a pipe.
+    /// Only streamable formats (e.g. `mpegts`, or `mp4` in fragmented mode) can be written this
+    /// way.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Sink to write to.
+    /// * `format` - Container format to use.
+    pub fn new_streaming(sink: S, format: &'a str) -> Self {
+        Self {
+            sink,
+            seek: None,
+            format,
+            options: None,
+        }
+    }
+
+    /// Specify options for the muxer.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to the muxer when writing the header.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Build [`SinkWriter`].
+    pub fn build(self) -> Result<SinkWriter<S>> {
+        let mut output = ffi::output_raw(self.format)?;
+        // The sink is boxed so that its address stays the same when the writer is moved.
+        let mut sink = Box::new(self.sink);
+        ffi::output_sink_start(&mut output, sink.as_mut(), self.seek, SinkWriter::<S>::BUFFER_SIZE);
+        Ok(SinkWriter {
+            output,
+            options: self.options.cloned().unwrap_or_default(),
+            sink: Some(sink),
+        })
+    }
+}
+
+/// Video writer that writes to any [`std::io::Write`] sink, such as an in-memory buffer, a socket
+/// or an adapter for object storage.
+///
+/// # Example
+///
+/// ```ignore
+/// let writer = SinkWriterBuilder::new(std::io::Cursor::new(Vec::new()), "mp4")
+///     .build()
+///     .unwrap();
+/// let mut muxer = MuxerBuilder::new(writer).with_streams(&reader)?.build()?;
+/// // ...
+/// muxer.finish()?;
+/// let bytes = muxer.into_writer().into_inner().into_inner();
+/// ```
+pub struct SinkWriter<S: std::io::Write + Send> {
+    pub(crate) output: AvOutput,
+    options: Options,
+    // The IO context of `output` points to the sink, so it is detached in `Drop` before the sink
+    // is dropped.
+    sink: Option<Box<S>>,
+}
+
+impl<S: std::io::Write + Send> SinkWriter<S> {
+    /// Size of the buffer between the muxer and the sink.
+    const BUFFER_SIZE: usize = 32 * 1024;
+
+    /// Get a reference to the sink.
+    pub fn sink(&self) -> &S {
+        self.sink.as_ref().expect("sink is only taken when the writer is consumed")
+    }
+
+    /// Flush all buffered output into the sink and return the sink. This should be called after
+    /// the trailer has been written.
+    pub fn into_inner(mut self) -> S {
+        ffi::output_sink_end(&mut self.output);
+        *self.sink.take().expect("sink is only taken when the writer is consumed")
+    }
+}
+
+impl<S: std::io::Write + Send> Write for SinkWriter<S> {}
+
+impl<S: std::io::Write + Send> Drop for SinkWriter<S> {
+    fn drop(&mut self) {
+        // Detach the IO context before the sink is dropped, and so that `avio_close` does not try
+        // to close it.
+        ffi::output_sink_end(&mut self.output);
+    }
+}
+
+unsafe impl<S: std::io::Write + Send> Send for SinkWriter<S> {}
+unsafe impl<S: std::io::Write + Send + Sync> Sync for SinkWriter<S> {}
+
 pub(crate) mod private {
     use super::*;
 
     type Result<T> = std::result::Result<T, Error>;
 
+    /// Output of a write operation that can be combined with the output of later writes, for
+    /// when a single call writes multiple packets.
+    pub trait Append: Default {
+        /// Append the output of a later write.
+        ///
+        /// # Arguments
+        ///
+        /// * `other` - Output to append.
+        fn append(&mut self, other: Self);
+    }
+
+    impl Append for () {
+        fn append(&mut self, _other: ()) {}
+    }
+
+    impl<T> Append for Vec<T> {
+        fn append(&mut self, mut other: Vec<T>) {
+            Vec::append(self, &mut other);
+        }
+    }
+
     pub trait Write {
-        type Out;
+        type Out: Append;
 
         /// Write the container header.
         fn write_header(&mut self) -> Result<Self::Out>;
@@ -519,13 +1994,18 @@ pub(crate) mod private {
 
         /// Write the container trailer.
         fn write_trailer(&mut self) -> Result<Self::Out>;
+
+        /// Obtain mutable reference to the options that are passed to the muxer when writing the
+        /// header.
+        fn header_options_mut(&mut self) -> &mut Options;
     }
 
     impl Write for Writer {
         type Out = ();
 
         fn write_header(&mut self) -> Result<()> {
-            Ok(self.output.write_header()?)
+            self.output.write_header_with(self.header_options.to_dict())?;
+            Ok(())
         }
 
         fn write(&mut self, packet: &mut AvPacket) -> Result<()> {
@@ -541,6 +2021,10 @@ pub(crate) mod private {
         fn write_trailer(&mut self) -> Result<()> {
             Ok(self.output.write_trailer()?)
         }
+
+        fn header_options_mut(&mut self) -> &mut Options {
+            &mut self.header_options
+        }
     }
 
     impl Write for BufWriter {
@@ -571,6 +2055,10 @@ pub(crate) mod private {
             self.output.write_trailer()?;
             Ok(self.end_write())
         }
+
+        fn header_options_mut(&mut self) -> &mut Options {
+            &mut self.options
+        }
     }
 
     impl Write for PacketizedBufWriter {
@@ -605,6 +2093,37 @@ pub(crate) mod private {
             self.end_write();
             Ok(self.take_buffers())
         }
+
+        fn header_options_mut(&mut self) -> &mut Options {
+            &mut self.options
+        }
+    }
+
+    impl<S: std::io::Write + Send> Write for SinkWriter<S> {
+        type Out = ();
+
+        fn write_header(&mut self) -> Result<()> {
+            self.output.write_header_with(self.options.to_dict())?;
+            Ok(())
+        }
+
+        fn write(&mut self, packet: &mut AvPacket) -> Result<()> {
+            packet.write(&mut self.output)?;
+            Ok(())
+        }
+
+        fn write_interleaved(&mut self, packet: &mut AvPacket) -> Result<()> {
+            packet.write_interleaved(&mut self.output)?;
+            Ok(())
+        }
+
+        fn write_trailer(&mut self) -> Result<()> {
+            Ok(self.output.write_trailer()?)
+        }
+
+        fn header_options_mut(&mut self) -> &mut Options {
+            &mut self.options
+        }
     }
 
     pub trait Output {
@@ -644,4 +2163,200 @@ pub(crate) mod private {
             &mut self.output
         }
     }
+
+    impl<S: std::io::Write + Send> Output for SinkWriter<S> {
+        fn output(&self) -> &AvOutput {
+            &self.output
+        }
+
+        fn output_mut(&mut self) -> &mut AvOutput {
+            &mut self.output
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::location::Url;
+
+    #[test]
+    fn test_network_options_apply() {
+        let network_options = NetworkOptions {
+            timeout: Some(Duration::from_millis(2500)),
+            reconnect: true,
+            reconnect_delay_max: Some(Duration::from_millis(4500)),
+            headers: vec![
+                ("Authorization".to_string(), "Bearer token".to_string()),
+                ("X-Client".to_string(), "player".to_string()),
+            ],
+            user_agent: Some("player/1.0".to_string()),
+            cookies: vec![
+                ("session".to_string(), "1234".to_string()),
+                ("theme".to_string(), "dark".to_string()),
+            ],
+            ..Default::default()
+        };
+        let source = Location::from(Url::parse("https://example.com/live/index.m3u8").unwrap());
+        let mut options = Options::default();
+        network_options.apply(&mut options, &source).unwrap();
+        assert_eq!(options.get("rw_timeout"), Some("2500000"));
+        assert_eq!(options.get("reconnect"), Some("1"));
+        assert_eq!(options.get("reconnect_on_network_error"), Some("1"));
+        assert_eq!(options.get("reconnect_streamed"), None);
+        assert_eq!(options.get("reconnect_delay_max"), Some("4"));
+        assert_eq!(
+            options.get("headers"),
+            Some("Authorization: Bearer token\r\nX-Client: player\r\n"),
+        );
+        assert_eq!(options.get("user_agent"), Some("player/1.0"));
+        assert_eq!(
+            options.get("cookies"),
+            Some(
+                "session=1234; path=/; domain=example.com\n\
+                 theme=dark; path=/; domain=example.com"
+            ),
+        );
+    }
+
+    #[test]
+    fn test_network_options_cookies_require_host() {
+        let network_options = NetworkOptions {
+            cookies: vec![("session".to_string(), "1234".to_string())],
+            ..Default::default()
+        };
+        let source = Location::from(std::path::Path::new("movie.mp4"));
+        assert!(network_options
+            .apply(&mut Options::default(), &source)
+            .is_err());
+
+        // Without cookies, the options can be applied to any source.
+        let network_options = NetworkOptions {
+            timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        network_options
+            .apply(&mut Options::default(), &source)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_udp_options_apply() {
+        let udp_options = UdpOptions {
+            buffer_size: Some(8 * 1024 * 1024),
+            fifo_size: Some(100_000),
+            overrun_nonfatal: true,
+            reuse_address: true,
+            local_address: Some("10.0.0.2".parse().unwrap()),
+            sources: vec!["10.0.0.1".parse().unwrap(), "10.0.0.3".parse().unwrap()],
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let source = Location::from(Url::parse("udp://239.0.0.1:1234").unwrap());
+        let mut options = Options::default();
+        udp_options.apply(&mut options, &source).unwrap();
+        assert_eq!(options.get("buffer_size"), Some("8388608"));
+        assert_eq!(options.get("fifo_size"), Some("100000"));
+        assert_eq!(options.get("overrun_nonfatal"), Some("1"));
+        assert_eq!(options.get("reuse"), Some("1"));
+        assert_eq!(options.get("localaddr"), Some("10.0.0.2"));
+        assert_eq!(options.get("sources"), Some("10.0.0.1,10.0.0.3"));
+        assert_eq!(options.get("block"), None);
+        assert_eq!(options.get("timeout"), Some("5000000"));
+
+        let udp_options = UdpOptions {
+            blocked_sources: vec!["10.0.0.1".parse().unwrap()],
+            ..Default::default()
+        };
+        let mut options = Options::default();
+        udp_options.apply(&mut options, &source).unwrap();
+        assert_eq!(options.get("block"), Some("10.0.0.1"));
+        assert_eq!(options.get("sources"), None);
+    }
+
+    #[test]
+    fn test_udp_options_reject_invalid() {
+        let multicast = Location::from(Url::parse("udp://239.0.0.1:1234").unwrap());
+        let unicast = Location::from(Url::parse("udp://10.0.0.2:1234").unwrap());
+        let filter = UdpOptions {
+            sources: vec!["10.0.0.1".parse().unwrap()],
+            ..Default::default()
+        };
+        let apply = |udp_options: &UdpOptions, location: &Location| {
+            udp_options.apply(&mut Options::default(), location)
+        };
+
+        // Only UDP URLs.
+        let http = Location::from(Url::parse("http://239.0.0.1:1234").unwrap());
+        assert!(apply(&UdpOptions::default(), &http).is_err());
+        assert!(apply(&UdpOptions::default(), &unicast).is_ok());
+        // Filtering senders requires a multicast group.
+        assert!(apply(&filter, &unicast).is_err());
+        assert!(apply(&filter, &multicast).is_ok());
+        // Senders cannot be allowed and blocked at the same time.
+        let conflicting = UdpOptions {
+            blocked_sources: vec!["10.0.0.3".parse().unwrap()],
+            ..filter
+        };
+        assert!(apply(&conflicting, &multicast).is_err());
+    }
+
+    #[test]
+    fn test_srt_options_apply() {
+        let srt_options = SrtOptions {
+            mode: SrtMode::Listener,
+            latency: Some(Duration::from_millis(200)),
+            passphrase: Some("correct horse battery".to_string()),
+            key_length: Some(32),
+            stream_id: Some("live/camera".to_string()),
+            connect_timeout: Some(Duration::from_secs(3)),
+        };
+        let location = Location::from(Url::parse("srt://0.0.0.0:9000").unwrap());
+        let mut options = Options::default();
+        srt_options.apply(&mut options, &location).unwrap();
+        assert_eq!(options.get("mode"), Some("listener"));
+        assert_eq!(options.get("latency"), Some("200000"));
+        assert_eq!(options.get("passphrase"), Some("correct horse battery"));
+        assert_eq!(options.get("pbkeylen"), Some("32"));
+        assert_eq!(options.get("streamid"), Some("live/camera"));
+        assert_eq!(options.get("connect_timeout"), Some("3000"));
+
+        // The key length is only used with a passphrase.
+        let srt_options = SrtOptions {
+            key_length: Some(16),
+            ..Default::default()
+        };
+        let mut options = Options::default();
+        srt_options.apply(&mut options, &location).unwrap();
+        assert_eq!(options.get("mode"), Some("caller"));
+        assert_eq!(options.get("passphrase"), None);
+        assert_eq!(options.get("pbkeylen"), None);
+    }
+
+    #[test]
+    fn test_srt_options_reject_invalid() {
+        let location = Location::from(Url::parse("srt://example.com:9000").unwrap());
+        let apply = |srt_options: &SrtOptions, location: &Location| {
+            srt_options.apply(&mut Options::default(), location)
+        };
+
+        // Only SRT URLs.
+        let udp = Location::from(Url::parse("udp://example.com:9000").unwrap());
+        assert!(apply(&SrtOptions::default(), &udp).is_err());
+        assert!(apply(&SrtOptions::default(), &location).is_ok());
+        // Passphrases are 10 to 79 characters long.
+        for (length, valid) in [(9, false), (10, true), (79, true), (80, false)] {
+            let srt_options = SrtOptions {
+                passphrase: Some("x".repeat(length)),
+                ..Default::default()
+            };
+            assert_eq!(apply(&srt_options, &location).is_ok(), valid);
+        }
+        let srt_options = SrtOptions {
+            passphrase: Some("correct horse battery".to_string()),
+            key_length: Some(20),
+            ..Default::default()
+        };
+        assert!(apply(&srt_options, &location).is_err());
+    }
 }
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// What to do with RTP packets that arrive later than the jitter buffer allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LatePacketPolicy {
+    /// Keep the delay of the buffer at the target delay. Packets that arrive after the packets
+    /// that follow them have been released are dropped.
+    #[default]
+    Drop,
+    /// Increase the delay of the buffer when a packet arrives later than the current delay allows,
+    /// up to the maximum delay, so that packets with a similar network delay are not lost in the
+    /// future. Packets that still arrive too late are dropped.
+    Adapt,
+}
+
+/// Settings of the jitter buffer of an [`RtpReceiver`](crate::core::rtp::RtpReceiver).
+///
+/// The jitter buffer holds received packets for a while, so that packets that arrive out of order
+/// can be put back in order and variations in network delay are smoothed out. Packets are
+/// released at the time they were sent (according to their RTP timestamp) plus the lowest network
+/// delay observed so far plus the delay of the buffer. Packets that have not arrived by then are
+/// considered lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JitterBuffer {
+    /// Delay that packets are held for.
+    pub target_delay: Duration,
+    /// Largest delay that the buffer allows. When a packet arrives later than this, the network
+    /// delay has changed (or the clocks of sender and receiver drift apart), and the buffer starts
+    /// over from that packet.
+    pub max_delay: Duration,
+    /// What to do with packets that arrive too late.
+    pub late_packet_policy: LatePacketPolicy,
+}
+
+impl Default for JitterBuffer {
+    fn default() -> Self {
+        Self {
+            target_delay: Duration::from_millis(50),
+            max_delay: Duration::from_millis(500),
+            late_packet_policy: LatePacketPolicy::default(),
+        }
+    }
+}
+
+/// Queue that reorders RTP packets by sequence number and releases them at their playout time,
+/// according to the [`JitterBuffer`] settings.
+pub(crate) struct JitterQueue<T> {
+    settings: JitterBuffer,
+    clock_rate: u32,
+    // Current delay of the buffer, between the target and maximum delay.
+    delay: Duration,
+    // Queued packets by extended sequence number, with the time at which they were expected to
+    // arrive at the lowest network delay.
+    packets: BTreeMap<i64, (Instant, T)>,
+    // Highest extended sequence number and RTP timestamp received.
+    highest_sequence_number: Option<i64>,
+    highest_timestamp: Option<i64>,
+    // Extended sequence number of the next packet to release.
+    next_sequence_number: Option<i64>,
+    // Arrival time and extended RTP timestamp of the packet with the lowest network delay.
+    reference: Option<(Instant, i64)>,
+}
+
+impl<T> JitterQueue<T> {
+    /// Create a jitter queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Jitter buffer settings.
+    /// * `clock_rate` - Clock rate of the RTP timestamps.
+    pub(crate) fn new(settings: JitterBuffer, clock_rate: u32) -> Self {
+        Self {
+            settings,
+            clock_rate,
+            delay: settings.target_delay,
+            packets: BTreeMap::new(),
+            highest_sequence_number: None,
+            highest_timestamp: None,
+            next_sequence_number: None,
+            reference: None,
+        }
+    }
+
+    /// Current delay of the buffer.
+    pub(crate) fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Queue a received packet. Packets that arrive after later packets were already released,
+    /// and duplicates, are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence_number` - Sequence number of the packet.
+    /// * `timestamp` - RTP timestamp of the packet.
+    /// * `arrival` - When the packet arrived.
+    /// * `packet` - Packet to queue.
+    pub(crate) fn push(
+        &mut self,
+        sequence_number: u16,
+        timestamp: u32,
+        arrival: Instant,
+        packet: T,
+    ) {
+        let sequence_number = unwrap_counter(
+            self.highest_sequence_number,
+            sequence_number as i64,
+            1 << 16,
+        );
+        let timestamp = unwrap_counter(self.highest_timestamp, timestamp as i64, 1 << 32);
+        self.highest_sequence_number = self.highest_sequence_number.max(Some(sequence_number));
+        self.highest_timestamp = self.highest_timestamp.max(Some(timestamp));
+
+        if self
+            .next_sequence_number
+            .is_some_and(|next_sequence_number| sequence_number < next_sequence_number)
+        {
+            tracing::debug!("dropping RTP packet that arrived too late");
+            return;
+        }
+        if self.packets.contains_key(&sequence_number) {
+            return;
+        }
+
+        let expected = self.expected_arrival(timestamp).unwrap_or(arrival);
+        let lateness = arrival.saturating_duration_since(expected);
+        let expected = if arrival <= expected {
+            // The packet had the lowest network delay so far.
+            self.reference = Some((arrival, timestamp));
+            arrival
+        } else if lateness > self.settings.max_delay {
+            tracing::debug!("jitter buffer exceeded maximum delay, resynchronizing");
+            self.reference = Some((arrival, timestamp));
+            self.delay = self.settings.target_delay;
+            arrival
+        } else {
+            if lateness > self.delay && self.settings.late_packet_policy == LatePacketPolicy::Adapt
+            {
+                self.delay = lateness;
+            }
+            expected
+        };
+        self.packets.insert(sequence_number, (expected, packet));
+    }
+
+    /// Queue a retransmission of a packet that was missing (RFC 4588). Retransmissions arrive
+    /// later than the original packet would have, so they do not affect the delay of the buffer.
+    /// Retransmissions that arrive after later packets were already released, and duplicates, are
+    /// dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence_number` - Original sequence number of the packet.
+    /// * `timestamp` - RTP timestamp of the packet.
+    /// * `arrival` - When the retransmission arrived.
+    /// * `packet` - Packet to queue.
+    pub(crate) fn push_retransmission(
+        &mut self,
+        sequence_number: u16,
+        timestamp: u32,
+        arrival: Instant,
+        packet: T,
+    ) {
+        let Some(highest_sequence_number) = self.highest_sequence_number else {
+            return;
+        };
+        let sequence_number = unwrap_counter(
+            Some(highest_sequence_number),
+            sequence_number as i64,
+            1 << 16,
+        );
+        if sequence_number >= highest_sequence_number
+            || self
+                .next_sequence_number
+                .is_some_and(|next_sequence_number| sequence_number < next_sequence_number)
+        {
+            tracing::debug!("dropping RTP retransmission that arrived too late");
+            return;
+        }
+        if self.packets.contains_key(&sequence_number) {
+            return;
+        }
+        let timestamp = unwrap_counter(self.highest_timestamp, timestamp as i64, 1 << 32);
+        let expected = self.expected_arrival(timestamp).unwrap_or(arrival);
+        self.packets.insert(sequence_number, (expected, packet));
+    }
+
+    /// Time at which the next packet is due to be released.
+    pub(crate) fn next_release(&self) -> Option<Instant> {
+        self.packets
+            .first_key_value()
+            .map(|(_, (expected, _))| *expected + self.delay)
+    }
+
+    /// Take the next packet in sequence order if it is due to be released. Packets that are
+    /// missing at that time are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current time.
+    pub(crate) fn pop(&mut self, now: Instant) -> Option<T> {
+        if self.next_release()? > now {
+            return None;
+        }
+        let (sequence_number, (_, packet)) = self.packets.pop_first()?;
+        self.next_sequence_number = Some(sequence_number + 1);
+        Some(packet)
+    }
+
+    /// Time at which a packet was expected to arrive at the lowest network delay observed so far,
+    /// or `None` if no packet has arrived yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - Extended RTP timestamp of the packet.
+    fn expected_arrival(&self, timestamp: i64) -> Option<Instant> {
+        let (reference_arrival, reference_timestamp) = self.reference?;
+        let offset = timestamp - reference_timestamp;
+        let offset_duration = Duration::from_nanos(
+            (offset.unsigned_abs() as u128 * 1_000_000_000 / self.clock_rate as u128) as u64,
+        );
+        Some(if offset >= 0 {
+            reference_arrival + offset_duration
+        } else {
+            reference_arrival
+                .checked_sub(offset_duration)
+                .unwrap_or(reference_arrival)
+        })
+    }
+}
+
+/// Extend a counter that wraps around (such as an RTP sequence number or timestamp) to 64 bits,
+/// choosing the value closest to the previous value.
+///
+/// # Arguments
+///
+/// * `previous` - Previous extended value.
+/// * `value` - Counter value to extend.
+/// * `range` - Range of the counter.
+fn unwrap_counter(previous: Option<i64>, value: i64, range: i64) -> i64 {
+    match previous {
+        Some(previous) => {
+            let delta = (value - previous).rem_euclid(range);
+            if delta >= range / 2 {
+                previous + delta - range
+            } else {
+                previous + delta
+            }
+        }
+        None => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(late_packet_policy: LatePacketPolicy) -> JitterBuffer {
+        JitterBuffer {
+            target_delay: Duration::from_millis(50),
+            max_delay: Duration::from_millis(200),
+            late_packet_policy,
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_unwrap_counter() {
+        assert_eq!(unwrap_counter(None, 65535, 1 << 16), 65535);
+        assert_eq!(unwrap_counter(Some(65535), 0, 1 << 16), 65536);
+        assert_eq!(unwrap_counter(Some(65536), 65535, 1 << 16), 65535);
+        assert_eq!(unwrap_counter(Some(10), 4_294_967_290, 1 << 32), -6);
+    }
+
+    #[test]
+    fn test_reorders_and_holds_packets() {
+        let start = Instant::now();
+        let mut queue = JitterQueue::new(settings(LatePacketPolicy::Drop), 1000);
+        queue.push(1, 0, start, "a");
+        queue.push(3, 20, start + ms(20), "c");
+        queue.push(2, 10, start + ms(30), "b");
+        assert_eq!(queue.pop(start + ms(49)), None);
+        assert_eq!(queue.pop(start + ms(50)), Some("a"));
+        assert_eq!(queue.pop(start + ms(59)), None);
+        assert_eq!(queue.pop(start + ms(60)), Some("b"));
+        assert_eq!(queue.pop(start + ms(70)), Some("c"));
+        assert_eq!(queue.pop(start + ms(1000)), None);
+    }
+
+    #[test]
+    fn test_skips_missing_packets_and_drops_late_ones() {
+        let start = Instant::now();
+        let mut queue = JitterQueue::new(settings(LatePacketPolicy::Drop), 1000);
+        queue.push(1, 0, start, "a");
+        queue.push(3, 20, start + ms(20), "c");
+        assert_eq!(queue.pop(start + ms(50)), Some("a"));
+        assert_eq!(queue.pop(start + ms(70)), Some("c"));
+        queue.push(2, 10, start + ms(80), "b");
+        queue.push(4, 30, start + ms(80), "d");
+        assert_eq!(queue.pop(start + ms(80)), Some("d"));
+        assert_eq!(queue.delay(), ms(50));
+    }
+
+    #[test]
+    fn test_retransmission_fills_gap_without_changing_delay() {
+        let start = Instant::now();
+        let mut queue = JitterQueue::new(settings(LatePacketPolicy::Adapt), 1000);
+        queue.push(1, 0, start, "a");
+        queue.push(3, 20, start + ms(20), "c");
+        assert_eq!(queue.pop(start + ms(50)), Some("a"));
+        queue.push_retransmission(2, 10, start + ms(55), "b");
+        assert_eq!(queue.delay(), ms(50));
+        assert_eq!(queue.pop(start + ms(60)), Some("b"));
+        assert_eq!(queue.pop(start + ms(70)), Some("c"));
+        // Too late, and not missing.
+        queue.push_retransmission(2, 10, start + ms(80), "b");
+        queue.push_retransmission(4, 30, start + ms(80), "d");
+        assert_eq!(queue.pop(start + ms(1000)), None);
+    }
+
+    #[test]
+    fn test_adapts_delay_to_late_packets() {
+        let start = Instant::now();
+        let mut queue = JitterQueue::new(settings(LatePacketPolicy::Adapt), 1000);
+        queue.push(1, 0, start, "a");
+        queue.push(2, 10, start + ms(110), "b");
+        assert_eq!(queue.delay(), ms(100));
+        assert_eq!(queue.next_release(), Some(start + ms(100)));
+    }
+
+    #[test]
+    fn test_resynchronizes_after_max_delay() {
+        let start = Instant::now();
+        let mut queue = JitterQueue::new(settings(LatePacketPolicy::Adapt), 1000);
+        queue.push(1, 0, start, "a");
+        assert_eq!(queue.pop(start + ms(50)), Some("a"));
+        queue.push(2, 10, start + ms(500), "b");
+        assert_eq!(queue.delay(), ms(50));
+        assert_eq!(queue.next_release(), Some(start + ms(550)));
+    }
+}
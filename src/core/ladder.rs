@@ -0,0 +1,426 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::software::scaling::context::Context as AvScaler;
+use ffmpeg::software::scaling::flag::Flags as AvScalerFlags;
+use ffmpeg::util::format::Pixel as AvPixel;
+use ffmpeg::Rational as AvRational;
+
+use crate::core::codec::RateControl;
+use crate::core::decode::{Decoder, DecoderBuilder};
+use crate::core::encode::{Encoder, EncoderBuilder, Settings};
+use crate::core::error::Error;
+use crate::core::frame::{RawFrame, FRAME_PIXEL_FORMAT};
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::threads::ThreadBudget;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A single rendition of a bitrate ladder.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rendition {
+    pub width: u32,
+    pub height: u32,
+    /// Average bitrate in bits per second.
+    pub bitrate: u64,
+}
+
+impl Rendition {
+    /// Short name of the rendition, e.g. `720p`.
+    pub fn name(&self) -> String {
+        format!("{}p", self.height)
+    }
+}
+
+/// Reference ladder for 30 fps content: height and average bitrate in bits per second. Loosely
+/// based on the HLS authoring guidelines for H.264.
+const REFERENCE_LADDER: [(u32, u64); 8] = [
+    (2160, 16_000_000),
+    (1440, 9_000_000),
+    (1080, 6_000_000),
+    (720, 3_000_000),
+    (540, 2_000_000),
+    (432, 1_100_000),
+    (360, 730_000),
+    (234, 365_000),
+];
+
+/// Produce a recommended encoding ladder for a source. Renditions are never larger than the
+/// source, and are ordered from highest to lowest quality.
+///
+/// # Arguments
+///
+/// * `width` - Width of the source.
+/// * `height` - Height of the source.
+/// * `frame_rate` - Frame rate of the source. High frame rate content gets more bits.
+pub fn recommend_ladder(width: u32, height: u32, frame_rate: f32) -> Vec<Rendition> {
+    // High frame rate content needs roughly 50% more bits for the same quality.
+    let frame_rate_factor = if frame_rate > 31.0 { 1.5 } else { 1.0 };
+    let source_pixels = width as u64 * height as u64;
+
+    let mut ladder: Vec<Rendition> = REFERENCE_LADDER
+        .iter()
+        .filter(|(rung_height, _)| *rung_height <= height)
+        .map(|&(rung_height, bitrate)| Rendition {
+            width: scale_width_even(width, height, rung_height),
+            height: rung_height,
+            bitrate: (bitrate as f64 * frame_rate_factor) as u64,
+        })
+        .collect();
+
+    // Make sure the source resolution itself is part of the ladder if it lies above the closest
+    // reference rung, or if it is smaller than all reference rungs.
+    let source_bitrate = match ladder.first() {
+        Some(top) if top.height == height => None,
+        Some(top) => {
+            let pixels = top.width as u64 * top.height as u64;
+            Some(top.bitrate * source_pixels / pixels.max(1))
+        }
+        None => {
+            let (smallest_height, smallest_bitrate) = REFERENCE_LADDER[REFERENCE_LADDER.len() - 1];
+            let pixels = scale_width_even(width, height, smallest_height) as u64
+                * smallest_height as u64;
+            Some(
+                (smallest_bitrate as f64 * frame_rate_factor) as u64 * source_pixels
+                    / pixels.max(1),
+            )
+        }
+    };
+    if let Some(bitrate) = source_bitrate {
+        ladder.insert(
+            0,
+            Rendition {
+                width: width & !1,
+                height: height & !1,
+                bitrate,
+            },
+        );
+    }
+
+    ladder
+}
+
+/// Builds a [`LadderTranscoder`].
+pub struct LadderTranscoderBuilder<'a> {
+    source: Location,
+    renditions: Vec<(Rendition, Location)>,
+    input_options: Option<&'a Options>,
+    format: Option<&'a str>,
+    segment_duration: std::time::Duration,
+    thread_budget: Option<ThreadBudget>,
+}
+
+impl<'a> LadderTranscoderBuilder<'a> {
+    /// Create a new ladder transcoder builder without any renditions.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to transcode.
+    pub fn new(source: impl Into<Location>) -> Self {
+        Self {
+            source: source.into(),
+            renditions: Vec::new(),
+            input_options: None,
+            format: None,
+            segment_duration: std::time::Duration::from_secs(2),
+            thread_budget: None,
+        }
+    }
+
+    /// Add a rendition to produce.
+    ///
+    /// # Arguments
+    ///
+    /// * `rendition` - Rendition to encode.
+    /// * `destination` - Where to write the rendition to.
+    pub fn with_rendition(mut self, rendition: Rendition, destination: impl Into<Location>) -> Self {
+        self.renditions.push((rendition, destination.into()));
+        self
+    }
+
+    /// Set the options for the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to input.
+    pub fn with_input_options(mut self, options: &'a Options) -> Self {
+        self.input_options = Some(options);
+        self
+    }
+
+    /// Set the container format of all renditions.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Container format to use.
+    pub fn with_format(mut self, format: &'a str) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Set the duration between keyframes. Keyframes are placed at the same frames in every
+    /// rendition, so that the renditions can be segmented at the same points. Defaults to two
+    /// seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment_duration` - Keyframe distance.
+    pub fn with_segment_duration(mut self, segment_duration: std::time::Duration) -> Self {
+        self.segment_duration = segment_duration;
+        self
+    }
+
+    /// Limit the threads used by the decoder and every rendition encoder, and apply the priority
+    /// and core affinity of the budget to the rendition threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Thread budget.
+    pub fn with_thread_budget(mut self, budget: ThreadBudget) -> Self {
+        self.thread_budget = Some(budget);
+        self
+    }
+
+    /// Build [`LadderTranscoder`]. This opens the source and the encoders for all renditions.
+    pub fn build(self) -> Result<LadderTranscoder> {
+        let mut decoder_builder = DecoderBuilder::new(self.source);
+        if let Some(options) = self.input_options {
+            decoder_builder = decoder_builder.with_options(options);
+        }
+        if let Some(thread_budget) = self.thread_budget.clone() {
+            decoder_builder = decoder_builder.with_thread_budget(thread_budget);
+        }
+        let decoder = decoder_builder.build()?;
+
+        let frame_rate = if decoder.frame_rate() > 0.0 {
+            decoder.frame_rate()
+        } else {
+            30.0
+        };
+        let gop_size = ((self.segment_duration.as_secs_f32() * frame_rate).round() as u32).max(1);
+        // Renditions are scaled to their own size, so the sample aspect ratio of the source does
+        // not apply to them. Only the color properties are carried over.
+        let color = decoder
+            .color_metadata()
+            .with_sample_aspect_ratio(AvRational::new(0, 1));
+
+        let encoders = self
+            .renditions
+            .into_iter()
+            .map(|(rendition, destination)| {
+                let settings = Settings::preset_h264_yuv420p(
+                    rendition.width as usize,
+                    rendition.height as usize,
+                    false,
+                )
+                .with_color_metadata(color);
+                let mut encoder_builder = EncoderBuilder::new(destination, settings)
+                    .with_rate_control(RateControl::Vbr {
+                        bitrate: rendition.bitrate,
+                        min_bitrate: None,
+                        // Keep the peak bitrate close to the average, as recommended for HLS.
+                        max_bitrate: Some(rendition.bitrate * 11 / 10),
+                        buffer_size: Some(rendition.bitrate * 2),
+                    })
+                    .with_gop_size(gop_size)
+                    .with_closed_gop(true)
+                    .with_scene_cut_disabled(true);
+                if let Some(format) = self.format {
+                    encoder_builder = encoder_builder.with_format(format);
+                }
+                if let Some(thread_budget) = self.thread_budget.clone() {
+                    encoder_builder = encoder_builder.with_thread_budget(thread_budget);
+                }
+                Ok((rendition, encoder_builder.build()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(LadderTranscoder {
+            decoder,
+            encoders,
+            thread_budget: self.thread_budget,
+        })
+    }
+}
+
+/// Transcodes a source into multiple renditions of a bitrate ladder at once, for HLS or DASH
+/// packaging. The source is decoded only once, and every rendition is scaled and encoded on its
+/// own thread.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut builder = LadderTranscoderBuilder::new(Path::new("input.mp4"));
+/// for rendition in recommend_ladder(1920, 1080, 30.0) {
+///     let destination = PathBuf::from(format!("output_{}.mp4", rendition.name()));
+///     builder = builder.with_rendition(rendition, destination);
+/// }
+/// builder.build().unwrap().run().unwrap();
+/// ```
+pub struct LadderTranscoder {
+    decoder: Decoder,
+    encoders: Vec<(Rendition, Encoder)>,
+    thread_budget: Option<ThreadBudget>,
+}
+
+impl LadderTranscoder {
+    /// Maximum number of decoded frames that may be queued for each rendition.
+    const QUEUE_SIZE: usize = 8;
+
+    /// Run the transcoder until the source is exhausted.
+    ///
+    /// # Return value
+    ///
+    /// Number of frames decoded.
+    pub fn run(self) -> Result<u64> {
+        let LadderTranscoder {
+            mut decoder,
+            encoders,
+            thread_budget,
+        } = self;
+        let thread_budget = thread_budget.as_ref();
+        let (source_width, source_height) = decoder.size_out();
+        let decoder_time_base = decoder.time_base();
+
+        std::thread::scope(|scope| {
+            let (senders, workers): (Vec<_>, Vec<_>) = encoders
+                .into_iter()
+                .map(|(rendition, encoder)| {
+                    let (sender, receiver) =
+                        std::sync::mpsc::sync_channel::<SharedFrame>(Self::QUEUE_SIZE);
+                    let worker = scope.spawn(move || {
+                        if let Some(thread_budget) = thread_budget {
+                            thread_budget.apply_to_current_thread_or_warn();
+                        }
+                        encode_rendition(
+                            rendition,
+                            encoder,
+                            (source_width, source_height),
+                            decoder_time_base,
+                            receiver,
+                        )
+                    });
+                    (sender, worker)
+                })
+                .unzip();
+
+            let mut frame_count = 0;
+            let decode_result = loop {
+                let frame = match decoder.decode_raw() {
+                    Ok(frame) => SharedFrame(std::sync::Arc::new(frame)),
+                    Err(Error::DecodeExhausted) => break Ok(()),
+                    Err(err) => break Err(err),
+                };
+                // A worker that failed has dropped its receiver. Its error is reported below.
+                for sender in senders.iter() {
+                    let _ = sender.send(frame.clone());
+                }
+                frame_count += 1;
+            };
+            // Closing the channels tells the workers to finish up.
+            drop(senders);
+
+            let mut result = decode_result.map(|_| frame_count);
+            for worker in workers {
+                let worker_result = worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                if let Err(err) = worker_result {
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+            result
+        })
+    }
+}
+
+unsafe impl Send for LadderTranscoder {}
+unsafe impl Sync for LadderTranscoder {}
+
+/// Decoded frame that is shared between the rendition workers. Workers only read from it.
+#[derive(Clone)]
+struct SharedFrame(std::sync::Arc<RawFrame>);
+
+unsafe impl Send for SharedFrame {}
+unsafe impl Sync for SharedFrame {}
+
+/// Scale and encode frames for a single rendition until the channel is closed.
+fn encode_rendition(
+    rendition: Rendition,
+    mut encoder: Encoder,
+    (source_width, source_height): (u32, u32),
+    decoder_time_base: AvRational,
+    frames: std::sync::mpsc::Receiver<SharedFrame>,
+) -> Result<()> {
+    let mut scaler = AvScaler::get(
+        FRAME_PIXEL_FORMAT,
+        source_width,
+        source_height,
+        AvPixel::YUV420P,
+        rendition.width,
+        rendition.height,
+        AvScalerFlags::AREA,
+    )?;
+    for SharedFrame(frame) in frames {
+        let mut frame_scaled = RawFrame::empty();
+        scaler.run(&frame, &mut frame_scaled)?;
+        frame_scaled.set_pts(
+            Time::new(frame.pts(), decoder_time_base)
+                .aligned_with_rational(encoder.time_base())
+                .into_value(),
+        );
+        encoder.encode_raw(frame_scaled)?;
+    }
+    encoder.finish()
+}
+
+/// Compute the even width that preserves the aspect ratio at the given height.
+fn scale_width_even(width: u32, height: u32, target_height: u32) -> u32 {
+    let target_width = width as f64 * target_height as f64 / height.max(1) as f64;
+    (target_width / 2.0).round() as u32 * 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_width_even() {
+        assert_eq!(scale_width_even(1920, 1080, 720), 1280);
+        assert_eq!(scale_width_even(1920, 1080, 234), 416);
+        assert_eq!(scale_width_even(1080, 1920, 360), 202);
+    }
+
+    #[test]
+    fn test_recommend_ladder_1080p() {
+        let ladder = recommend_ladder(1920, 1080, 30.0);
+        assert_eq!(ladder.len(), 6);
+        assert_eq!(ladder[0].height, 1080);
+        assert_eq!(ladder[0].width, 1920);
+        assert_eq!(ladder[0].bitrate, 6_000_000);
+        assert_eq!(ladder[5].height, 234);
+        assert!(ladder.windows(2).all(|pair| pair[0].bitrate > pair[1].bitrate));
+    }
+
+    #[test]
+    fn test_recommend_ladder_high_frame_rate() {
+        let ladder = recommend_ladder(1280, 720, 60.0);
+        assert_eq!(ladder[0].height, 720);
+        assert_eq!(ladder[0].bitrate, 4_500_000);
+    }
+
+    #[test]
+    fn test_recommend_ladder_includes_source() {
+        let ladder = recommend_ladder(1600, 900, 30.0);
+        assert_eq!(ladder[0].height, 900);
+        assert_eq!(ladder[1].height, 720);
+        assert!(ladder[0].bitrate > ladder[1].bitrate);
+
+        let ladder = recommend_ladder(320, 180, 30.0);
+        assert_eq!(ladder.len(), 1);
+        assert_eq!((ladder[0].width, ladder[0].height), (320, 180));
+    }
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use crate::core::jitter::{JitterBuffer, LatePacketPolicy};
+use crate::core::options::Options;
+
+/// Trade-off between latency and robustness for live sources. A single profile configures how
+/// long the input is probed, demuxer buffering, low-delay decoding and the jitter buffer of RTP
+/// receivers together. Low-delay decoding is applied by [`DecoderBuilder::with_latency`], or by
+/// [`DecoderSplit::new_with_latency`] for readers created separately, such as those of RTP
+/// receivers.
+///
+/// By default, ffmpeg probes up to 5 seconds of the input before playback starts, which is fine
+/// for files but makes live sources start slowly. The low-latency profiles probe only a fraction
+/// of that, so that live sources start in less than a second.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut decoder = DecoderBuilder::new(Url::parse("rtsp://camera/stream").unwrap())
+///     .with_latency(LatencyProfile::Low)
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// [`DecoderBuilder::with_latency`]: crate::core::decode::DecoderBuilder::with_latency
+/// [`DecoderSplit::new_with_latency`]: crate::core::decode::DecoderSplit::new_with_latency
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum LatencyProfile {
+    /// Defaults of ffmpeg. Suitable for files and on-demand streams.
+    #[default]
+    Default,
+    /// Short probing and no demuxer buffering, with enough buffering to deal with a moderate
+    /// amount of network jitter. Suitable for watching live streams.
+    Low,
+    /// Minimal probing and buffering everywhere. Suitable for interactive use on reliable networks,
+    /// such as controlling a camera.
+    UltraLow,
+}
+
+impl LatencyProfile {
+    /// Maximum number of bytes to read while probing the input, or `None` to keep the default.
+    pub fn probe_size(self) -> Option<usize> {
+        match self {
+            LatencyProfile::Default => None,
+            LatencyProfile::Low => Some(256 * 1024),
+            LatencyProfile::UltraLow => Some(32 * 1024),
+        }
+    }
+
+    /// Maximum duration of the input to analyze while probing, or `None` to keep the default.
+    pub fn analyze_duration(self) -> Option<Duration> {
+        match self {
+            LatencyProfile::Default => None,
+            LatencyProfile::Low => Some(Duration::from_millis(500)),
+            LatencyProfile::UltraLow => Some(Duration::from_millis(100)),
+        }
+    }
+
+    /// Whether or not the demuxer passes on packets immediately instead of buffering them.
+    pub fn no_buffer(self) -> bool {
+        self != LatencyProfile::Default
+    }
+
+    /// Whether or not decoders output frames as early as possible. This forces slice threading,
+    /// since frame threading adds one frame of latency per thread.
+    pub fn low_delay_decode(self) -> bool {
+        self != LatencyProfile::Default
+    }
+
+    /// Jitter buffer settings for RTP receivers.
+    pub fn jitter_buffer(self) -> JitterBuffer {
+        match self {
+            LatencyProfile::Default => JitterBuffer::default(),
+            LatencyProfile::Low => JitterBuffer {
+                target_delay: Duration::from_millis(30),
+                max_delay: Duration::from_millis(200),
+                late_packet_policy: LatePacketPolicy::Adapt,
+            },
+            LatencyProfile::UltraLow => JitterBuffer {
+                target_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(100),
+                late_packet_policy: LatePacketPolicy::Drop,
+            },
+        }
+    }
+
+    /// Apply the input options of the profile. Options that are already set are kept, so that the
+    /// caller can override individual settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options of the input.
+    pub(crate) fn apply(self, options: &mut Options) {
+        if let Some(probe_size) = self.probe_size() {
+            if options.get("probesize").is_none() {
+                options.set("probesize", &probe_size.to_string());
+            }
+        }
+        if let Some(analyze_duration) = self.analyze_duration() {
+            if options.get("analyzeduration").is_none() {
+                options.set("analyzeduration", &analyze_duration.as_micros().to_string());
+            }
+        }
+        if self.no_buffer() {
+            let fflags = match options.get("fflags") {
+                Some(fflags) => format!("{fflags}+nobuffer"),
+                None => "nobuffer".to_string(),
+            };
+            options.set("fflags", &fflags);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_profile_changes_nothing() {
+        let mut options = Options::default();
+        LatencyProfile::Default.apply(&mut options);
+        assert_eq!(options.get("probesize"), None);
+        assert_eq!(options.get("analyzeduration"), None);
+        assert_eq!(options.get("fflags"), None);
+    }
+
+    #[test]
+    fn test_low_latency_options() {
+        let mut options = Options::default();
+        options.set("probesize", "1000");
+        options.set("fflags", "genpts");
+        LatencyProfile::Low.apply(&mut options);
+        assert_eq!(options.get("probesize"), Some("1000"));
+        assert_eq!(options.get("analyzeduration"), Some("500000"));
+        assert_eq!(options.get("fflags"), Some("genpts+nobuffer"));
+    }
+
+    #[test]
+    fn test_profiles_are_ordered_by_latency() {
+        let profiles = [
+            LatencyProfile::Default,
+            LatencyProfile::Low,
+            LatencyProfile::UltraLow,
+        ];
+        for pair in profiles.windows(2) {
+            assert!(pair[1].jitter_buffer().target_delay <= pair[0].jitter_buffer().target_delay);
+            assert!(pair[1].jitter_buffer().max_delay <= pair[0].jitter_buffer().max_delay);
+        }
+    }
+}
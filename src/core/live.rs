@@ -0,0 +1,180 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::time::{Duration, Instant};
+
+use crate::core::interrupt::Interrupt;
+
+/// Settings for reading a file that is still being written, such as a recording in progress.
+///
+/// When the reader reaches the end of the file, it waits for more data instead of ending the
+/// stream, retrying with exponential backoff. Reading ends once the file has not grown for
+/// `idle_timeout`, or when the reader is interrupted.
+///
+/// The container must be readable while it is incomplete, e.g. MPEG-TS, Matroska or fragmented
+/// MP4. A regular MP4 file cannot be read until it is finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiveFile {
+    /// Delay before the first retry after reaching the end of the file.
+    pub initial_backoff: Duration,
+    /// Longest delay between retries. The delay doubles with every retry up to this limit.
+    pub max_backoff: Duration,
+    /// How long to wait for the file to grow before treating its end as final. If `None`, the
+    /// reader waits until it is interrupted.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for LiveFile {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(20),
+            max_backoff: Duration::from_millis(500),
+            idle_timeout: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+/// Source that waits for more data at the end of a file that is still being written, according to
+/// the [`LiveFile`] settings.
+pub(crate) struct GrowingFile<R> {
+    inner: R,
+    settings: LiveFile,
+    interrupt: Option<Interrupt>,
+}
+
+impl<R: Read + Seek> GrowingFile<R> {
+    /// Wrap a source that may still grow.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - Source to read from.
+    /// * `settings` - How long and how often to retry at the end of the source.
+    /// * `interrupt` - Interrupt that stops waiting for more data.
+    pub(crate) fn new(inner: R, settings: LiveFile, interrupt: Option<Interrupt>) -> Self {
+        Self {
+            inner,
+            settings,
+            interrupt,
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for GrowingFile<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut backoff = self.settings.initial_backoff;
+        let mut idle_since = None;
+        loop {
+            let read = self.inner.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            let idle_since = *idle_since.get_or_insert_with(Instant::now);
+            let interrupted = self
+                .interrupt
+                .as_ref()
+                .is_some_and(Interrupt::is_interrupted);
+            let timed_out = self
+                .settings
+                .idle_timeout
+                .is_some_and(|idle_timeout| idle_since.elapsed() >= idle_timeout);
+            if interrupted || timed_out {
+                return Ok(0);
+            }
+            std::thread::sleep(backoff);
+            backoff = (backoff * 2).min(self.settings.max_backoff);
+        }
+    }
+}
+
+impl<R: Read + Seek> Seek for GrowingFile<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    /// In-memory file that can be appended to from another thread.
+    struct SharedFile {
+        data: Arc<Mutex<Vec<u8>>>,
+        position: u64,
+    }
+
+    impl Read for SharedFile {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let data = self.data.lock().unwrap();
+            let mut cursor = std::io::Cursor::new(data.as_slice());
+            cursor.set_position(self.position);
+            let read = cursor.read(buf)?;
+            self.position += read as u64;
+            Ok(read)
+        }
+    }
+
+    impl Seek for SharedFile {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            let data = self.data.lock().unwrap();
+            let mut cursor = std::io::Cursor::new(data.as_slice());
+            cursor.set_position(self.position);
+            self.position = cursor.seek(pos)?;
+            Ok(self.position)
+        }
+    }
+
+    fn settings() -> LiveFile {
+        LiveFile {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            idle_timeout: Some(Duration::from_millis(200)),
+        }
+    }
+
+    #[test]
+    fn test_waits_for_data_to_be_appended() {
+        let data = Arc::new(Mutex::new(b"abc".to_vec()));
+        let mut file = GrowingFile::new(
+            SharedFile {
+                data: data.clone(),
+                position: 0,
+            },
+            settings(),
+            None,
+        );
+        let mut buf = [0; 8];
+        assert_eq!(file.read(&mut buf).unwrap(), 3);
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            data.lock().unwrap().extend_from_slice(b"def");
+        });
+        assert_eq!(file.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"def");
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_ends_after_idle_timeout_or_interrupt() {
+        let data = Arc::new(Mutex::new(Vec::new()));
+        let mut file = GrowingFile::new(
+            SharedFile {
+                data: data.clone(),
+                position: 0,
+            },
+            settings(),
+            None,
+        );
+        let start = Instant::now();
+        assert_eq!(file.read(&mut [0; 8]).unwrap(), 0);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        let interrupt = Interrupt::new();
+        interrupt.interrupt();
+        let mut file = GrowingFile::new(
+            SharedFile { data, position: 0 },
+            settings(),
+            Some(interrupt),
+        );
+        assert_eq!(file.read(&mut [0; 8]).unwrap(), 0);
+    }
+}
@@ -1,27 +1,130 @@
+extern crate ffmpeg_next as ffmpeg;
+
 /// Re-export [`url::Url`] since it is an input type for callers of the API.
 pub use url::Url;
 
-/// Represents a video file or stream location. Can be either a file resource (a path) or a network
-/// resource (a URL).
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+use crate::core::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Represents a video file or stream location. Can be either a file resource (a path), a network
+/// resource (a URL), media that is already in memory, a list of files that are played back to
+/// back, a numbered image sequence, the standard input or output of the process, or a custom Rust
+/// source.
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Location {
     /// File source.
     File(std::path::PathBuf),
     /// Network source.
     Network(Url),
+    /// In-memory source, such as a small media file received over HTTP. Only supported for
+    /// reading.
+    Memory(std::sync::Arc<[u8]>),
+    /// Files or network sources that are read one after the other as a single continuous
+    /// timeline, with the timestamps of each source shifted to follow the previous one. The
+    /// sources must have the same streams with the same codecs. Only supported for reading.
+    Concat(Vec<Location>),
+    /// Standard input, e.g. when another process pipes media into this one. The source cannot be
+    /// seeked. Only supported for reading.
+    Stdin,
+    /// Standard output, e.g. to pipe media into another process. Only streamable container formats
+    /// such as MPEG-TS, Matroska or NUT can be written to it. Only supported for writing.
+    Stdout,
+    /// Sequence of numbered images that are read as a video stream, e.g. `frames/%06d.png` at 25
+    /// frames per second. The pattern contains a `printf`-style placeholder for the frame number.
+    /// Numbering may start anywhere between 0 and 4. Timestamps are derived from the frame number
+    /// and the frame rate. Only supported for reading.
+    Pattern(std::path::PathBuf, u32),
+    /// Custom Rust source passed to
+    /// [`ReaderBuilder::from_io`](crate::core::io::ReaderBuilder::from_io) or
+    /// [`ReaderBuilder::from_read`](crate::core::io::ReaderBuilder::from_read). The data is not
+    /// reachable through the location itself. Only supported for reading.
+    Custom,
 }
 
 impl Location {
     /// Coerce underlying location to a path.
     ///
     /// This will create a path with a URL in it (which is kind of weird but we use it to pass on
-    /// URLs to ffmpeg).
+    /// URLs to ffmpeg). In-memory, concatenated and custom locations have an empty path.
     pub fn as_path(&self) -> &std::path::Path {
         match self {
-            Location::File(path) => path.as_path(),
+            Location::File(path) | Location::Pattern(path, _) => path.as_path(),
             Location::Network(url) => std::path::Path::new(url.as_str()),
+            Location::Memory(_) | Location::Concat(_) | Location::Custom => {
+                std::path::Path::new("")
+            }
+            Location::Stdin => std::path::Path::new("pipe:0"),
+            Location::Stdout => std::path::Path::new("pipe:1"),
+        }
+    }
+
+    /// Whether or not the location is a pipe: standard input or output, or a `pipe:` URL that
+    /// refers to a file descriptor.
+    pub fn is_pipe(&self) -> bool {
+        match self {
+            Location::Stdin | Location::Stdout => true,
+            Location::Network(url) => url.scheme() == "pipe",
+            _ => false,
         }
     }
+
+    /// Produce an `ffconcat` script that lists the sources of a concatenated location, for the
+    /// concat demuxer. Nested concatenations are flattened.
+    ///
+    /// # Arguments
+    ///
+    /// * `locations` - Sources to concatenate.
+    pub(crate) fn concat_script(locations: &[Location]) -> Result<String> {
+        let mut script = String::from("ffconcat version 1.0\n");
+        Self::append_concat_entries(locations, &mut script)?;
+        if script.lines().count() == 1 {
+            return Err(Error::InvalidConfiguration {
+                field: "source",
+                reason: "concatenation requires at least one source".to_string(),
+            });
+        }
+        Ok(script)
+    }
+
+    /// Append a `file` directive for each of `locations` to an `ffconcat` script.
+    ///
+    /// # Arguments
+    ///
+    /// * `locations` - Sources to append.
+    /// * `script` - Script to append to.
+    fn append_concat_entries(locations: &[Location], script: &mut String) -> Result<()> {
+        for location in locations {
+            let entry = match location {
+                Location::File(path) => {
+                    path.to_str().ok_or_else(|| Error::InvalidConfiguration {
+                        field: "source",
+                        reason: format!("path is not valid UTF-8: {}", path.display()),
+                    })?
+                }
+                Location::Network(url) => url.as_str(),
+                Location::Memory(_)
+                | Location::Stdin
+                | Location::Stdout
+                | Location::Pattern(..)
+                | Location::Custom => {
+                    return Err(Error::InvalidConfiguration {
+                        field: "source",
+                        reason: format!("{location} cannot be concatenated"),
+                    })
+                }
+                Location::Concat(locations) => {
+                    Self::append_concat_entries(locations, script)?;
+                    continue;
+                }
+            };
+            // Single quotes cannot be escaped inside a quoted token, so close the quote, add an
+            // escaped quote and reopen it.
+            script.push_str(&format!("file '{}'\n", entry.replace('\'', "'\\''")));
+        }
+        Ok(())
+    }
 }
 
 impl From<&Location> for Location {
@@ -54,11 +157,145 @@ impl From<&Url> for Location {
     }
 }
 
+impl From<Vec<u8>> for Location {
+    fn from(value: Vec<u8>) -> Location {
+        Location::Memory(value.into())
+    }
+}
+
+impl From<std::sync::Arc<[u8]>> for Location {
+    fn from(value: std::sync::Arc<[u8]>) -> Location {
+        Location::Memory(value)
+    }
+}
+
+impl From<Vec<Location>> for Location {
+    fn from(value: Vec<Location>) -> Location {
+        Location::Concat(value)
+    }
+}
+
+impl std::fmt::Debug for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::File(path) => f.debug_tuple("File").field(path).finish(),
+            Location::Network(url) => f.debug_tuple("Network").field(url).finish(),
+            // Do not dump the contents, they can be large.
+            Location::Memory(bytes) => write!(f, "Memory({} bytes)", bytes.len()),
+            Location::Concat(locations) => f.debug_tuple("Concat").field(locations).finish(),
+            Location::Stdin => write!(f, "Stdin"),
+            Location::Stdout => write!(f, "Stdout"),
+            Location::Pattern(pattern, fps) => {
+                f.debug_tuple("Pattern").field(pattern).field(fps).finish()
+            }
+            Location::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
 impl std::fmt::Display for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Location::File(path) => write!(f, "{}", path.display()),
             Location::Network(url) => write!(f, "{url}"),
+            Location::Memory(bytes) => write!(f, "memory ({} bytes)", bytes.len()),
+            Location::Concat(locations) => {
+                for (index, location) in locations.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " + ")?;
+                    }
+                    write!(f, "{location}")?;
+                }
+                Ok(())
+            }
+            Location::Stdin => write!(f, "stdin"),
+            Location::Stdout => write!(f, "stdout"),
+            Location::Pattern(pattern, fps) => write!(f, "{} ({fps} fps)", pattern.display()),
+            Location::Custom => write!(f, "custom source"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ffmpeg::util::format::Pixel as AvPixel;
+
+    use super::*;
+    use crate::core::encode::{EncoderBuilder, Settings};
+    use crate::core::frame::RawFrame;
+    use crate::core::io::{Reader, ReaderBuilder};
+
+    #[test]
+    fn test_concat_script() {
+        let locations = vec![
+            Location::from(std::path::Path::new("/videos/part 1.mp4")),
+            Location::Concat(vec![Location::from(std::path::Path::new(
+                "/videos/director's cut.mp4",
+            ))]),
+            Location::from(Url::parse("https://example.com/part3.mp4").unwrap()),
+        ];
+        assert_eq!(
+            Location::concat_script(&locations).unwrap(),
+            "ffconcat version 1.0\n\
+             file '/videos/part 1.mp4'\n\
+             file '/videos/director'\\''s cut.mp4'\n\
+             file 'https://example.com/part3.mp4'\n",
+        );
+    }
+
+    #[test]
+    fn test_concat_script_rejects_memory_and_empty() {
+        assert!(Location::concat_script(&[]).is_err());
+        assert!(Location::concat_script(&[Location::from(vec![0u8; 4])]).is_err());
+        assert!(Location::concat_script(&[Location::Stdin]).is_err());
+    }
+
+    #[test]
+    fn test_is_pipe() {
+        assert!(Location::Stdin.is_pipe());
+        assert!(Location::Stdout.is_pipe());
+        assert!(Location::from(Url::parse("pipe:3").unwrap()).is_pipe());
+        assert!(!Location::from(std::path::Path::new("pipe.mp4")).is_pipe());
+    }
+
+    #[test]
+    fn test_memory() {
+        let location = Location::from(vec![0u8; 4]);
+        assert_eq!(location, Location::Memory(vec![0u8; 4].into()));
+        assert_eq!(location.as_path(), std::path::Path::new(""));
+        assert!(!location.is_pipe());
+        // The contents are not dumped.
+        assert_eq!(format!("{location:?}"), "Memory(4 bytes)");
+        assert_eq!(location.to_string(), "memory (4 bytes)");
+    }
+
+    #[test]
+    fn test_read_memory() {
+        let path = std::env::temp_dir().join(format!("memory-{}.mp4", std::process::id()));
+        let mut encoder =
+            EncoderBuilder::new(path.as_path(), Settings::preset_h264_yuv420p(64, 48, false))
+                .build()
+                .unwrap();
+        for index in 0..30 {
+            let mut frame = RawFrame::new(AvPixel::YUV420P, 64, 48);
+            frame.data_mut(0).fill(index as u8);
+            frame.set_pts(Some(index * 1_000_000 / 30));
+            encoder.encode_raw(frame).unwrap();
         }
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut reader = ReaderBuilder::from_bytes(bytes).build().unwrap();
+        assert!(matches!(reader.source, Location::Memory(_)));
+        let mut packets = 0;
+        while reader.read_any().is_ok() {
+            packets += 1;
+        }
+        assert_eq!(packets, 30);
+
+        // Arbitrary bytes are not a valid container.
+        assert!(Reader::new(Location::from(vec![0u8; 64])).is_err());
     }
 }
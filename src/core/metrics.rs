@@ -0,0 +1,311 @@
+use ndarray::{Array2, ArrayView3, Axis};
+
+use crate::core::error::Error;
+use crate::core::frame::Frame;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Quality of a distorted frame compared to its reference.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrameQuality {
+    /// Mean squared error over all samples of all channels.
+    pub mse: f64,
+    /// Peak signal-to-noise ratio in dB, over all channels. Infinite if the frames are identical.
+    pub psnr: f64,
+    /// Structural similarity of the luma of the frames, from `-1` to `1` (identical).
+    pub ssim: f64,
+}
+
+/// Quality of a sequence of distorted frames compared to their references, such as the output of
+/// an encoder compared to its input.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QualityReport {
+    /// Quality of each pair of frames, in order.
+    pub frames: Vec<FrameQuality>,
+}
+
+impl QualityReport {
+    /// Peak signal-to-noise ratio in dB of the whole sequence, computed from the mean squared
+    /// error of all frames like ffmpeg's `psnr` filter does. `None` if there are no frames.
+    pub fn psnr(&self) -> Option<f64> {
+        let mse = self.frames.iter().map(|frame| frame.mse).sum::<f64>();
+        (!self.frames.is_empty()).then(|| psnr_from_mse(mse / self.frames.len() as f64))
+    }
+
+    /// Mean structural similarity of all frames. `None` if there are no frames.
+    pub fn ssim(&self) -> Option<f64> {
+        let ssim = self.frames.iter().map(|frame| frame.ssim).sum::<f64>();
+        (!self.frames.is_empty()).then(|| ssim / self.frames.len() as f64)
+    }
+
+    /// Structural similarity of the worst frame. `None` if there are no frames.
+    pub fn min_ssim(&self) -> Option<f64> {
+        self.frames.iter().map(|frame| frame.ssim).reduce(f64::min)
+    }
+}
+
+/// Compute the PSNR and SSIM of a distorted frame compared to its reference.
+///
+/// Fails with [`Error::InvalidFrameFormat`] if the frames differ in size or number of channels.
+///
+/// # Arguments
+///
+/// * `reference` - Reference frame with dimensions `(H, W, C)`: one channel for grayscale, three
+///   for RGB or four for RGBA.
+/// * `distorted` - Distorted frame, e.g. the reference after encoding and decoding.
+pub fn compare_frames(
+    reference: ArrayView3<'_, u8>,
+    distorted: ArrayView3<'_, u8>,
+) -> Result<FrameQuality> {
+    let mse = mse(reference, distorted)?;
+    Ok(FrameQuality {
+        mse,
+        psnr: psnr_from_mse(mse),
+        ssim: ssim(reference, distorted)?,
+    })
+}
+
+/// Compute the peak signal-to-noise ratio in dB of a distorted frame compared to its reference,
+/// over all channels. Infinite if the frames are identical.
+///
+/// # Arguments
+///
+/// * `reference` - Reference frame with dimensions `(H, W, C)`.
+/// * `distorted` - Distorted frame with the same dimensions.
+pub fn psnr(reference: ArrayView3<'_, u8>, distorted: ArrayView3<'_, u8>) -> Result<f64> {
+    Ok(psnr_from_mse(mse(reference, distorted)?))
+}
+
+/// Compute the structural similarity of the luma of a distorted frame compared to its reference.
+/// Statistics are taken over 8x8 windows that overlap by half, like ffmpeg's `ssim` filter does.
+///
+/// # Arguments
+///
+/// * `reference` - Reference frame with dimensions `(H, W, C)`: one channel for grayscale, three
+///   for RGB or four for RGBA.
+/// * `distorted` - Distorted frame with the same dimensions.
+pub fn ssim(reference: ArrayView3<'_, u8>, distorted: ArrayView3<'_, u8>) -> Result<f64> {
+    check_dimensions(reference, distorted)?;
+    Ok(ssim_plane(&luma(reference)?, &luma(distorted)?))
+}
+
+/// Compare two sequences of frames pair by pair, such as the output of two decoders, to measure
+/// the quality of an encoder or to catch regressions in a pipeline. Stops at the end of the
+/// shorter sequence. The timestamps are not compared.
+///
+/// # Arguments
+///
+/// * `reference` - Reference frames, e.g. [`Decoder::decode_iter`](crate::core::decode::Decoder::decode_iter)
+///   of the source.
+/// * `distorted` - Distorted frames, e.g. `decode_iter` of the encoded source.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut source = Decoder::new(Path::new("source.mp4")).unwrap();
+/// let mut encoded = Decoder::new(Path::new("encoded.mp4")).unwrap();
+/// let report = compare(source.decode_iter(), encoded.decode_iter()).unwrap();
+/// println!("PSNR {:.2} dB, SSIM {:.4}", report.psnr().unwrap(), report.ssim().unwrap());
+/// ```
+pub fn compare(
+    reference: impl IntoIterator<Item = Result<(Time, Frame)>>,
+    distorted: impl IntoIterator<Item = Result<(Time, Frame)>>,
+) -> Result<QualityReport> {
+    let mut report = QualityReport::default();
+    let mut distorted = distorted.into_iter();
+    for reference in reference {
+        let Some((_, reference)) = end_of_stream_as_none(reference)? else {
+            break;
+        };
+        let Some((_, distorted)) = distorted
+            .next()
+            .map(end_of_stream_as_none)
+            .transpose()?
+            .flatten()
+        else {
+            break;
+        };
+        report
+            .frames
+            .push(compare_frames(reference.view(), distorted.view())?);
+    }
+    Ok(report)
+}
+
+/// Turn the errors that decoders return at the end of the stream into `None`.
+///
+/// # Arguments
+///
+/// * `frame` - Result of decoding a frame.
+fn end_of_stream_as_none(frame: Result<(Time, Frame)>) -> Result<Option<(Time, Frame)>> {
+    match frame {
+        Ok(frame) => Ok(Some(frame)),
+        Err(Error::ReadExhausted | Error::DecodeExhausted) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Check that two frames have the same dimensions.
+///
+/// # Arguments
+///
+/// * `reference` - Reference frame.
+/// * `distorted` - Distorted frame.
+fn check_dimensions(reference: ArrayView3<'_, u8>, distorted: ArrayView3<'_, u8>) -> Result<()> {
+    if reference.dim() != distorted.dim() || reference.is_empty() {
+        return Err(Error::InvalidFrameFormat);
+    }
+    Ok(())
+}
+
+/// Compute the mean squared error over all samples of two frames.
+///
+/// # Arguments
+///
+/// * `reference` - Reference frame.
+/// * `distorted` - Distorted frame.
+fn mse(reference: ArrayView3<'_, u8>, distorted: ArrayView3<'_, u8>) -> Result<f64> {
+    check_dimensions(reference, distorted)?;
+    let sum_squared_error = reference
+        .iter()
+        .zip(distorted.iter())
+        .map(|(&a, &b)| (a as i32 - b as i32).pow(2) as u64)
+        .sum::<u64>();
+    Ok(sum_squared_error as f64 / reference.len() as f64)
+}
+
+/// Convert a mean squared error of 8-bit samples to a peak signal-to-noise ratio in dB.
+///
+/// # Arguments
+///
+/// * `mse` - Mean squared error.
+fn psnr_from_mse(mse: f64) -> f64 {
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+}
+
+/// Get the luma of a frame, with BT.601 weights for RGB and RGBA frames.
+///
+/// # Arguments
+///
+/// * `frame` - Frame with one, three or four channels.
+fn luma(frame: ArrayView3<'_, u8>) -> Result<Array2<f64>> {
+    match frame.dim().2 {
+        1 => Ok(frame.index_axis(Axis(2), 0).mapv(f64::from)),
+        3 | 4 => Ok(frame.map_axis(Axis(2), |pixel| {
+            0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64
+        })),
+        _ => Err(Error::InvalidFrameFormat),
+    }
+}
+
+/// Compute the mean structural similarity of two planes over 8x8 windows with a step of 4. Planes
+/// smaller than a window are compared as a whole.
+///
+/// # Arguments
+///
+/// * `reference` - Reference plane.
+/// * `distorted` - Distorted plane with the same dimensions.
+fn ssim_plane(reference: &Array2<f64>, distorted: &Array2<f64>) -> f64 {
+    const WINDOW: usize = 8;
+    const STEP: usize = 4;
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (height, width) = reference.dim();
+    let (window_height, window_width) = (WINDOW.min(height), WINDOW.min(width));
+    let mut ssim_sum = 0.0;
+    let mut window_count = 0;
+    for y in (0..=height - window_height).step_by(STEP) {
+        for x in (0..=width - window_width).step_by(STEP) {
+            let window = ndarray::s![y..y + window_height, x..x + window_width];
+            let (a, b) = (reference.slice(window), distorted.slice(window));
+            let n = a.len() as f64;
+            let (mean_a, mean_b) = (a.sum() / n, b.sum() / n);
+            let (mut variance_a, mut variance_b, mut covariance) = (0.0, 0.0, 0.0);
+            for (&a, &b) in a.iter().zip(b.iter()) {
+                variance_a += (a - mean_a) * (a - mean_a);
+                variance_b += (b - mean_b) * (b - mean_b);
+                covariance += (a - mean_a) * (b - mean_b);
+            }
+            let (variance_a, variance_b, covariance) =
+                (variance_a / n, variance_b / n, covariance / n);
+            ssim_sum += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+            window_count += 1;
+        }
+    }
+    ssim_sum / window_count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array3;
+
+    use super::*;
+
+    fn gradient(height: usize, width: usize) -> Frame {
+        Array3::from_shape_fn((height, width, 3), |(y, x, c)| {
+            (y * 7 + x * 3 + c * 40) as u8
+        })
+    }
+
+    #[test]
+    fn test_identical_frames() {
+        let frame = gradient(16, 24);
+        let quality = compare_frames(frame.view(), frame.view()).unwrap();
+        assert_eq!(quality.mse, 0.0);
+        assert_eq!(quality.psnr, f64::INFINITY);
+        assert!((quality.ssim - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_known_error() {
+        let reference = Array3::<u8>::zeros((4, 4, 1));
+        let distorted = Array3::<u8>::from_elem((4, 4, 1), 10);
+        let quality = compare_frames(reference.view(), distorted.view()).unwrap();
+        assert_eq!(quality.mse, 100.0);
+        assert!((quality.psnr - 28.1308).abs() < 1e-3);
+        assert!(quality.ssim < 1.0);
+        assert!(matches!(
+            psnr(reference.view(), gradient(4, 4).view()),
+            Err(Error::InvalidFrameFormat)
+        ));
+    }
+
+    #[test]
+    fn test_noise_lowers_ssim() {
+        let reference = gradient(32, 32);
+        let mut distorted = reference.clone();
+        distorted
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, value)| *value = value.wrapping_add(if i % 2 == 0 { 20 } else { 0 }));
+        let quality = compare_frames(reference.view(), distorted.view()).unwrap();
+        assert!(quality.ssim < 0.99);
+        assert!(quality.psnr.is_finite());
+    }
+
+    #[test]
+    fn test_compare_sequences() {
+        let frames = |offset: u8| {
+            (0..3)
+                .map(move |i| {
+                    Ok((
+                        Time::from_secs(i as f32),
+                        Array3::from_elem((8, 8, 3), offset),
+                    ))
+                })
+                .chain(std::iter::once(Err(Error::DecodeExhausted)))
+        };
+        let report = compare(frames(0), frames(2).take(2)).unwrap();
+        assert_eq!(report.frames.len(), 2);
+        assert!((report.psnr().unwrap() - psnr_from_mse(4.0)).abs() < 1e-9);
+        assert_eq!(report.ssim(), report.min_ssim());
+        assert_eq!(QualityReport::default().psnr(), None);
+    }
+}
@@ -1,34 +1,147 @@
+pub mod abr;
+pub mod adaptive;
+#[cfg(feature = "async-io")]
+pub mod async_io;
+pub mod audio;
+pub mod audio_convert;
+pub mod burnin;
+pub mod codec;
+pub mod color;
+pub mod cuda;
+pub mod dash;
 pub mod decode;
+pub mod discontinuity;
 pub mod encode;
 pub mod error;
 pub mod extradata;
 pub mod frame;
+pub mod frame_dump;
+#[cfg(feature = "ndarray")]
+pub mod frame_stats;
+pub mod hdr;
+pub mod hls;
 pub mod hwaccel;
+#[cfg(feature = "image")]
+pub mod image_convert;
+pub mod imageseq;
 pub mod init;
+pub mod interop;
+pub mod interrupt;
 pub mod io;
+pub mod jitter;
+pub mod ladder;
+pub mod latency;
+pub mod live;
 pub mod location;
+#[cfg(feature = "ndarray")]
+pub mod metrics;
+pub mod motion;
 pub mod mux;
+pub mod netstats;
 pub mod options;
 pub mod packet;
+pub mod probe;
+pub mod recorder;
+pub mod remux;
 pub mod resize;
+pub mod rtcp;
 pub mod rtp;
+pub mod rtsp;
+pub mod sdp;
+pub mod seek;
+pub mod segment;
+pub mod session;
+pub mod smartcut;
 pub mod stream;
+pub mod subtitle;
+pub mod sync;
+pub mod syncpattern;
+pub mod threads;
+pub mod throttle;
 pub mod time;
+pub mod transcode;
+pub mod webrtc;
 
+mod bitstream;
+mod depacketize;
 mod ffi;
 mod ffi_hwaccel;
+mod interleave;
+mod rtsp_message;
 
+pub use self::abr::{
+    AbrPolicy, AdaptiveReader, AdaptiveReaderBuilder, Variant, VariantSelection,
+};
+pub use self::adaptive::{AdaptiveDecodePolicy, DecodeQuality, QualityChange};
+#[cfg(feature = "async-io")]
+pub use self::async_io::AsyncSource;
+pub use self::audio::{AudioExporter, AudioExporterBuilder};
+pub use self::audio_convert::{AudioConverter, AudioFormat};
+pub use self::burnin::{SubtitleBurner, SubtitleBurnerBuilder};
+pub use self::codec::{CodecOptions, Preset, RateControl, Tune, VideoCodec};
+pub use self::color::{ColorMetadata, ColorPrimaries, ColorRange, ColorSpace, ColorTransfer};
+pub use self::dash::{DashWriter, DashWriterBuilder};
 pub use self::decode::{Decoder, DecoderBuilder};
-pub use self::encode::{Encoder, EncoderBuilder};
+pub use self::discontinuity::{DiscontinuityDetector, DiscontinuityPolicy};
+pub use self::encode::{Encoder, EncoderBuilder, Pass};
 pub use self::error::Error;
 #[cfg(feature = "ndarray")]
-pub use self::frame::Frame;
+pub use self::frame::{
+    frame_view, Frame, Frame16, FrameF32, GrayFrame, GrayFrame16, YuvFrame,
+};
+pub use self::frame::{
+    convert, frame_plane, frame_planes, plane_count, FrameFormat, FramePlane,
+};
+#[cfg(feature = "ndarray")]
+pub use self::frame_dump::{read_ndarray_frame, write_ndarray_frame};
+pub use self::frame_dump::{read_frame, write_frame};
+#[cfg(feature = "ndarray")]
+pub use self::frame_stats::{
+    channel_histograms, channel_statistics, luma_histogram, mean_luma, ChannelStatistics, Histogram,
+};
+pub use self::hdr::{
+    ContentLightLevel, DolbyVisionConfiguration, HdrFormat, HdrMetadata, MasteringDisplay,
+};
+pub use self::hls::{HlsSegmentType, HlsWriter, HlsWriterBuilder};
+#[cfg(feature = "image")]
+pub use self::image_convert::{
+    frame_from_rgb_image, frame_from_rgba_image, rgb_image_from_frame, rgba_image_from_frame,
+};
+#[cfg(feature = "ndarray")]
+pub use self::imageseq::save_frame;
+pub use self::imageseq::{
+    save_raw_frame, ImageFormat, ImageSequenceWriter, ImageSequenceWriterBuilder,
+};
 pub use self::init::init;
-pub use self::io::{Reader, ReaderBuilder, Writer, WriterBuilder};
+pub use self::interrupt::Interrupt;
+pub use self::io::{
+    Fragmentation, Matroska, MpegTs, NetworkOptions, Packets, Reader, ReaderBuilder, SinkWriter,
+    SinkWriterBuilder, SrtMode, SrtOptions, UdpOptions, Writer, WriterBuilder,
+};
+pub use self::ladder::{recommend_ladder, LadderTranscoder, LadderTranscoderBuilder, Rendition};
+pub use self::latency::LatencyProfile;
+pub use self::live::LiveFile;
 pub use self::location::{Location, Url};
-pub use self::mux::{Muxer, MuxerBuilder};
+#[cfg(feature = "ndarray")]
+pub use self::metrics::{compare, compare_frames, psnr, ssim, FrameQuality, QualityReport};
+pub use self::motion::{motion_vectors, MotionVector, MotionVectorReference};
+pub use self::mux::{CoverArtFormat, Muxer, MuxerBuilder};
+pub use self::netstats::NetworkStatistics;
 pub use self::options::Options;
 pub use self::packet::Packet;
+pub use self::probe::{probe, MediaInfo, StreamDetails};
+pub use self::recorder::{Recorder, RecorderBuilder};
+pub use self::remux::{remux, Remuxer, RemuxerBuilder};
 pub use self::resize::Resize;
+pub use self::seek::{SeekDirection, SeekOptions, SeekTarget};
+pub use self::segment::{SegmentInfo, SegmentedWriter, SegmentedWriterBuilder};
+pub use self::session::SessionState;
+pub use self::smartcut::{CutPlan, SmartCut, SmartCutBuilder, SmartCutSummary};
+pub use self::subtitle::{SubtitleExporter, SubtitleExporterBuilder, SubtitleFormat};
+pub use self::sync::{CueWatch, MasterClock, SyncGroup, SyncGroupBuilder};
+pub use self::syncpattern::{SyncAnalyzer, SyncPattern, SyncReport};
+pub use self::threads::{CodecThreading, ThreadBudget, ThreadPriority};
+pub use self::throttle::Throttle;
 pub use self::time::Time;
+pub use self::transcode::{TwoPassTranscoder, TwoPassTranscoderBuilder};
 
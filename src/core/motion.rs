@@ -0,0 +1,113 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::ffi::AVMotionVector;
+
+use crate::core::ffi;
+use crate::core::frame::RawFrame;
+
+/// Frame that a block is predicted from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MotionVectorReference {
+    /// A frame before the current frame in display order.
+    Past,
+    /// A frame after the current frame in display order (bidirectional prediction).
+    Future,
+}
+
+/// Motion vector of a block of a decoded frame, as exported by the decoder. Gives motion analysis
+/// the motion that the encoder already estimated, without running optical flow.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MotionVector {
+    /// Frame that the block is predicted from.
+    pub reference: MotionVectorReference,
+    /// Width of the block in pixels.
+    pub block_width: u8,
+    /// Height of the block in pixels.
+    pub block_height: u8,
+    /// Position of the center of the block in the reference frame, rounded to whole pixels.
+    pub source: (i16, i16),
+    /// Position of the center of the block in the current frame.
+    pub destination: (i16, i16),
+    /// Offset from the block in the current frame to the block it is predicted from in the
+    /// reference frame, in units of `1 / motion_scale` pixels, i.e. `source = destination +
+    /// motion / motion_scale`. For a past reference this points opposite to the motion of the
+    /// content.
+    pub motion: (i32, i32),
+    /// Number of units of `motion` per pixel.
+    pub motion_scale: u16,
+}
+
+impl MotionVector {
+    /// Offset from the block in the current frame to the block it is predicted from in the
+    /// reference frame, in pixels with subpixel precision. Negate it to get how far the content
+    /// moved since a past reference frame.
+    pub fn displacement(&self) -> (f32, f32) {
+        let scale = self.motion_scale.max(1) as f32;
+        (self.motion.0 as f32 / scale, self.motion.1 as f32 / scale)
+    }
+
+    /// Convert a backend motion vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `vector` - Backend motion vector.
+    fn from_backend(vector: &AVMotionVector) -> Self {
+        Self {
+            reference: if vector.source > 0 {
+                MotionVectorReference::Future
+            } else {
+                MotionVectorReference::Past
+            },
+            block_width: vector.w,
+            block_height: vector.h,
+            source: (vector.src_x, vector.src_y),
+            destination: (vector.dst_x, vector.dst_y),
+            motion: (vector.motion_x, vector.motion_y),
+            motion_scale: vector.motion_scale,
+        }
+    }
+}
+
+/// Get the motion vectors of a decoded frame. Empty unless the decoder was built with
+/// [`DecoderBuilder::with_motion_vectors`](crate::core::decode::DecoderBuilder::with_motion_vectors).
+///
+/// # Arguments
+///
+/// * `frame` - Frame to get motion vectors of, e.g. from
+///   [`Decoder::decode_raw`](crate::core::decode::Decoder::decode_raw).
+pub fn motion_vectors(frame: &RawFrame) -> Vec<MotionVector> {
+    ffi::frame_motion_vectors(frame)
+        .iter()
+        .map(MotionVector::from_backend)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_motion_vector_from_backend() {
+        let vector = MotionVector::from_backend(&AVMotionVector {
+            source: -1,
+            w: 16,
+            h: 8,
+            src_x: 40,
+            src_y: 20,
+            dst_x: 38,
+            dst_y: 21,
+            flags: 0,
+            motion_x: 8,
+            motion_y: -4,
+            motion_scale: 4,
+        });
+        assert_eq!(vector.reference, MotionVectorReference::Past);
+        assert_eq!((vector.block_width, vector.block_height), (16, 8));
+        assert_eq!(vector.displacement(), (2.0, -1.0));
+    }
+
+    #[test]
+    fn test_empty_without_side_data() {
+        assert!(motion_vectors(&RawFrame::empty()).is_empty());
+    }
+}
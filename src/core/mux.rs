@@ -1,14 +1,22 @@
 extern crate ffmpeg_next as ffmpeg;
 
-use ffmpeg::codec::Id as AvCodecId;
+use ffmpeg::codec::packet::Flags as AvPacketFlags;
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::codec::{Context as AvContext, Id as AvCodecId};
+use ffmpeg::format::stream::Disposition as AvDisposition;
+use ffmpeg::media::Type as AvMediaType;
+use ffmpeg::util::frame::Video as AvFrame;
 use ffmpeg::{Error as AvError, Rational as AvRational};
 
 use crate::core::error::Error;
 use crate::core::extradata::{extract_parameter_sets_h264, Pps, Sps};
-use crate::core::ffi::extradata;
-use crate::core::io::{Reader, Write};
+use crate::core::ffi::{self, extradata, output_format_supports_codec};
+use crate::core::interleave::Interleaver;
+use crate::core::io::private::Append as _;
+use crate::core::io::{Fragmentation, Matroska, MpegTs, Reader, Write};
 use crate::core::packet::Packet;
 use crate::core::stream::StreamInfo;
+use crate::core::time::Time;
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -16,7 +24,9 @@ type Result<T> = std::result::Result<T, Error>;
 pub struct MuxerBuilder<W: Write> {
     writer: W,
     interleaved: bool,
+    interleave_queue: Option<usize>,
     mapping: std::collections::HashMap<usize, StreamDescription>,
+    cover_art: Option<(usize, Vec<u8>)>,
 }
 
 impl<W: Write> MuxerBuilder<W> {
@@ -25,7 +35,9 @@ impl<W: Write> MuxerBuilder<W> {
         Self {
             writer,
             interleaved: false,
+            interleave_queue: None,
             mapping: std::collections::HashMap::new(),
+            cover_art: None,
         }
     }
 
@@ -46,14 +58,124 @@ impl<W: Write> MuxerBuilder<W> {
             .output_mut()
             .add_stream(ffmpeg::encoder::find(codec_parameters.id()))?;
         writer_stream.set_parameters(codec_parameters);
+        let writer_stream_index = writer_stream.index();
+        ffi::clear_incompatible_codec_tag(self.writer.output_mut(), writer_stream_index);
         let stream_description = StreamDescription {
-            index: writer_stream.index(),
+            index: writer_stream_index,
             source_time_base: reader_stream_time_base,
         };
         self.mapping.insert(index, stream_description);
         Ok(self)
     }
 
+    /// Add an audio output stream to the muxer, e.g. to keep the soundtrack when remuxing a video
+    /// stream. Packets of the audio stream can be passed to [`Muxer::mux()`] like packets of any
+    /// other stream.
+    ///
+    /// Audio packets usually arrive in different chunks than video packets, so this also switches
+    /// the muxer to interleaved writing, which orders the packets of all streams by time.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_info` - Stream information of an audio stream. Usually this information is
+    ///   retrieved by calling [`Reader::stream_info()`] with
+    ///   [`Reader::best_audio_stream_index()`].
+    pub fn with_audio_stream(self, stream_info: StreamInfo) -> Result<Self> {
+        self.with_stream_of_medium(stream_info, AvMediaType::Audio)
+    }
+
+    /// Add a subtitle output stream to the muxer by copying the codec parameters of an input
+    /// stream. Like [`Self::with_audio_stream`], this switches the muxer to interleaved writing.
+    ///
+    /// Subtitles are copied as-is, so the container must support the subtitle codec. For example,
+    /// MP4 only holds `mov_text` subtitles, not the SubRip or ASS subtitles found in Matroska.
+    /// Fails with [`Error::InvalidConfiguration`] if the container cannot hold the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_info` - Stream information of a subtitle stream.
+    pub fn with_subtitle_stream(self, stream_info: StreamInfo) -> Result<Self> {
+        self.with_stream_of_medium(stream_info, AvMediaType::Subtitle)
+    }
+
+    /// Add a data output stream to the muxer by copying the codec parameters of an input stream,
+    /// e.g. a timecode track. Like [`Self::with_audio_stream`], this switches the muxer to
+    /// interleaved writing. Fails with [`Error::InvalidConfiguration`] if the container cannot
+    /// hold the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_info` - Stream information of a data stream.
+    pub fn with_data_stream(self, stream_info: StreamInfo) -> Result<Self> {
+        self.with_stream_of_medium(stream_info, AvMediaType::Data)
+    }
+
+    /// Add all streams in the reader that the container format can hold, and skip the others
+    /// with a warning. Use this to remux between containers that support different codecs (e.g.
+    /// Matroska to MP4), keeping as many streams as possible. Packets of skipped streams are
+    /// rejected by [`Muxer::mux()`], use [`Muxer::has_stream()`] to filter them out.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader to add streams from.
+    pub fn with_supported_streams(mut self, reader: &Reader) -> Result<Self> {
+        for stream in reader.input.streams() {
+            let codec_id = stream.parameters().id();
+            let output = self.writer.output();
+            if output_format_supports_codec(output, codec_id) == Some(false) {
+                tracing::warn!(
+                    "skipping stream {}: container {} cannot hold {codec_id:?}",
+                    stream.index(),
+                    output.format().name(),
+                );
+                continue;
+            }
+            if stream.parameters().medium() != AvMediaType::Video {
+                self.interleaved = true;
+            }
+            self = self.with_stream(reader.stream_info(stream.index())?)?;
+        }
+        Ok(self)
+    }
+
+    /// Add a stream after checking that it carries the expected kind of media and that the
+    /// container can hold it. Switches the muxer to interleaved writing.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_info` - Stream information.
+    /// * `expected_medium` - Kind of media the stream must carry.
+    fn with_stream_of_medium(
+        mut self,
+        stream_info: StreamInfo,
+        expected_medium: AvMediaType,
+    ) -> Result<Self> {
+        let medium = stream_info.medium();
+        if medium != expected_medium {
+            return Err(Error::InvalidConfiguration {
+                field: "streams",
+                reason: format!(
+                    "stream {} is a {medium:?} stream, not {expected_medium:?}",
+                    stream_info.index,
+                ),
+            });
+        }
+        let codec_id = stream_info.codec_id();
+        let output = self.writer.output();
+        if output_format_supports_codec(output, codec_id) == Some(false) {
+            return Err(Error::InvalidConfiguration {
+                field: "streams",
+                reason: format!(
+                    "container {} cannot hold {codec_id:?} (stream {})",
+                    output.format().name(),
+                    stream_info.index,
+                ),
+            });
+        }
+        self.interleaved = true;
+        self.with_stream(stream_info)
+    }
+
     /// Add output streams from reader to muxer. This will add all streams in the reader and
     /// duplicate them in the muxer. After calling this, it is safe to mux all packets from the
     /// provided reader.
@@ -68,6 +190,180 @@ impl<W: Write> MuxerBuilder<W> {
         Ok(self)
     }
 
+    /// Produce fragmented MP4 (fMP4) output that can be streamed while it is written. The writer
+    /// must use the `mp4` (or `mov`) container format.
+    ///
+    /// # Arguments
+    ///
+    /// * `fragmentation` - How to fragment the output.
+    pub fn with_fragmented_mp4(mut self, fragmentation: Fragmentation) -> Self {
+        fragmentation.apply(self.writer.header_options_mut());
+        self
+    }
+
+    /// Apply MPEG-TS settings such as the PCR period and service metadata. The writer must use the
+    /// `mpegts` container format.
+    ///
+    /// # Arguments
+    ///
+    /// * `mpeg_ts` - MPEG-TS settings.
+    pub fn with_mpeg_ts(mut self, mpeg_ts: MpegTs) -> Self {
+        mpeg_ts.apply(&mut self.writer);
+        self
+    }
+
+    /// Set a global metadata entry of the output container, such as `title`, `artist` or any
+    /// custom key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Metadata key.
+    /// * `value` - Metadata value.
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        ffi::set_output_metadata(self.writer.output_mut(), key, value);
+        self
+    }
+
+    /// Set a metadata entry of an output stream, such as `language` or `title`.
+    ///
+    /// Fails with [`Error::InvalidConfiguration`] if no output stream was added for the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the input stream that was added to the muxer.
+    /// * `key` - Metadata key.
+    /// * `value` - Metadata value.
+    pub fn with_stream_metadata(
+        mut self,
+        stream_index: usize,
+        key: &str,
+        value: &str,
+    ) -> Result<Self> {
+        let writer_stream_index = self.writer_stream_index(stream_index)?;
+        ffi::set_output_stream_metadata(self.writer.output_mut(), writer_stream_index, key, value);
+        Ok(self)
+    }
+
+    /// Apply Matroska settings such as where to write the cues. The writer must use the
+    /// `matroska` container format.
+    ///
+    /// # Arguments
+    ///
+    /// * `matroska` - Matroska settings.
+    pub fn with_matroska(mut self, matroska: Matroska) -> Self {
+        matroska.apply(self.writer.header_options_mut());
+        self
+    }
+
+    /// Set the name of a track, which players show in their track selection menu (e.g.
+    /// "Director's commentary").
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the input stream that was added to the muxer.
+    /// * `name` - Track name.
+    pub fn with_track_name(self, stream_index: usize, name: &str) -> Result<Self> {
+        self.with_stream_metadata(stream_index, "title", name)
+    }
+
+    /// Mark a track as the default track of its kind, which players select unless the user
+    /// prefers otherwise. If no track of a kind is marked, the Matroska muxer marks the first
+    /// track of that kind as default anyway, unless its `default_mode` option is set to
+    /// `passthrough`.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the input stream that was added to the muxer.
+    /// * `default` - Whether or not the track is a default track.
+    pub fn with_default_track(mut self, stream_index: usize, default: bool) -> Result<Self> {
+        let writer_stream_index = self.writer_stream_index(stream_index)?;
+        ffi::set_output_stream_disposition(
+            self.writer.output_mut(),
+            writer_stream_index,
+            AvDisposition::DEFAULT,
+            default,
+        );
+        Ok(self)
+    }
+
+    /// Mark a track as forced, which players always show, e.g. subtitles that translate foreign
+    /// dialogue in an otherwise unsubtitled movie. By default, no track is marked.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the input stream that was added to the muxer.
+    /// * `forced` - Whether or not the track is forced.
+    pub fn with_forced_track(mut self, stream_index: usize, forced: bool) -> Result<Self> {
+        let writer_stream_index = self.writer_stream_index(stream_index)?;
+        ffi::set_output_stream_disposition(
+            self.writer.output_mut(),
+            writer_stream_index,
+            AvDisposition::FORCED,
+            forced,
+        );
+        Ok(self)
+    }
+
+    /// Find the output stream that an input stream is muxed into.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the stream in the input.
+    fn writer_stream_index(&self, stream_index: usize) -> Result<usize> {
+        self.mapping
+            .get(&stream_index)
+            .map(|description| description.index)
+            .ok_or_else(|| Error::InvalidConfiguration {
+                field: "stream_index",
+                reason: format!("stream {stream_index} was not added to the muxer"),
+            })
+    }
+
+    /// Embed a cover image in the output. MP4, MP3 and other containers that support cover art
+    /// store it as a stream with the `attached_pic` disposition (`covr` atom in MP4, `APIC` frame
+    /// in MP3). Matroska stores it as an attachment.
+    ///
+    /// Fails with [`Error::InvalidConfiguration`] if the container format cannot hold the image.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Encoded image.
+    /// * `format` - Format of the encoded image.
+    pub fn with_cover_art(mut self, image: Vec<u8>, format: CoverArtFormat) -> Result<Self> {
+        let output = self.writer.output_mut();
+        let format_name = output.format().name().to_string();
+        match format_name.as_str() {
+            "matroska" => {
+                ffi::add_attachment_stream(
+                    output,
+                    format.codec_id(),
+                    &image,
+                    format.filename(),
+                    format.mime_type(),
+                )?;
+            }
+            "webm" => {
+                return Err(Error::InvalidConfiguration {
+                    field: "cover_art",
+                    reason: "container webm cannot hold attachments".to_string(),
+                });
+            }
+            _ => {
+                if output_format_supports_codec(output, format.codec_id()) == Some(false) {
+                    return Err(Error::InvalidConfiguration {
+                        field: "cover_art",
+                        reason: format!("container {format_name} cannot hold {format:?} images"),
+                    });
+                }
+                let (width, height) = format.image_size(&image)?;
+                let stream_index =
+                    ffi::add_attached_pic_stream(output, format.codec_id(), width, height)?;
+                self.cover_art = Some((stream_index, image));
+            }
+        }
+        Ok(self)
+    }
+
     /// Set interleaved. This will cause the muxer to use interleaved write instead of normal
     /// write.
     pub fn interleaved(mut self) -> Self {
@@ -75,15 +371,56 @@ impl<W: Write> MuxerBuilder<W> {
         self
     }
 
+    /// Order packets of all streams by decoding timestamp before writing them, so that packets can
+    /// be passed to [`Muxer::mux()`] in any order, e.g. by separate audio and video threads that
+    /// share the muxer behind a mutex.
+    ///
+    /// A packet is held back until every stream has a packet queued, so that the earliest one can
+    /// go first. If a stream stalls or ends early, packets are written anyway once more than
+    /// `max_queued` packets are queued. Remaining packets are written by [`Muxer::finish()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `max_queued` - Maximum number of packets to hold back.
+    pub fn with_interleave_queue(mut self, max_queued: usize) -> Self {
+        self.interleave_queue = Some(max_queued);
+        self
+    }
+
     /// Build [`Muxer`].
-    pub fn build(self) -> Muxer<W> {
-        Muxer {
-            writer: self.writer,
+    ///
+    /// Fails with [`Error::InvalidConfiguration`] if the container format cannot hold one of the
+    /// streams.
+    pub fn build(self) -> Result<Muxer<W>> {
+        let output = self.writer.output();
+        for stream in output.streams() {
+            let codec_id = stream.parameters().id();
+            if output_format_supports_codec(output, codec_id) == Some(false) {
+                return Err(Error::InvalidConfiguration {
+                    field: "streams",
+                    reason: format!(
+                        "container {} cannot hold {codec_id:?} (stream {})",
+                        output.format().name(),
+                        stream.index(),
+                    ),
+                });
+            }
+        }
+        let interleaver = self.interleave_queue.map(|max_queued| {
+            Interleaver::new(
+                self.mapping.values().map(|description| description.index),
+                max_queued,
+            )
+        });
+        Ok(Muxer {
+            writer: Some(self.writer),
             mapping: self.mapping,
             interleaved: self.interleaved,
+            interleaver,
             have_written_header: false,
             have_written_trailer: false,
-        }
+            cover_art: self.cover_art,
+        })
     }
 }
 
@@ -99,6 +436,7 @@ impl<W: Write> MuxerBuilder<W> {
 /// let writer = Writer::new(Path::new("to_file.mkv")).unwrap();
 /// let muxer = MuxerBuilder::new(writer)
 ///     .with_streams(&reader)
+///     .unwrap()
 ///     .build()
 ///     .unwrap();
 /// while let Ok(packet) = reader.read() {
@@ -121,11 +459,14 @@ impl<W: Write> MuxerBuilder<W> {
 /// muxer.finish()?;
 /// ```
 pub struct Muxer<W: Write> {
-    pub(crate) writer: W,
+    // Only taken by `Muxer::into_writer`, which consumes the muxer.
+    writer: Option<W>,
     mapping: std::collections::HashMap<usize, StreamDescription>,
     interleaved: bool,
+    interleaver: Option<Interleaver<AvPacket>>,
     have_written_header: bool,
     have_written_trailer: bool,
+    cover_art: Option<(usize, Vec<u8>)>,
 }
 
 impl<W: Write> Muxer<W> {
@@ -143,43 +484,165 @@ impl<W: Write> Muxer<W> {
                 .ok_or(AvError::StreamNotFound)?;
 
             let destination_stream = self
-                .writer
+                .writer()
                 .output()
                 .stream(stream_description.index)
                 .ok_or(AvError::StreamNotFound)?;
 
-            packet.set_stream(destination_stream.index());
+            let destination_index = destination_stream.index();
+            let destination_time_base = destination_stream.time_base();
+            packet.set_stream(destination_index);
             packet.set_position(-1);
-            packet.rescale_ts(
-                stream_description.source_time_base,
-                destination_stream.time_base(),
-            );
-
-            Ok({
-                if self.interleaved {
-                    self.writer.write_interleaved(&mut packet)?
-                } else {
-                    self.writer.write(&mut packet)?
-                }
-            })
+            packet.rescale_ts(stream_description.source_time_base, destination_time_base);
+
+            let Some(interleaver) = self.interleaver.as_mut() else {
+                return self.write_packet(&mut packet);
+            };
+            let timestamp = packet
+                .dts()
+                .or(packet.pts())
+                .map(|timestamp| timestamp as f64 * f64::from(destination_time_base));
+            interleaver.push(destination_index, timestamp, packet);
+            let mut out = W::Out::default();
+            while let Some(mut packet) = self.interleaver.as_mut().and_then(Interleaver::pop) {
+                out.append(self.write_packet(&mut packet)?);
+            }
+            Ok(out)
         } else {
             self.have_written_header = true;
-            self.writer.write_header()?;
+            self.writer_mut().write_header()?;
+            self.write_cover_art()?;
             self.mux(packet)
         }
     }
 
+    /// Add a chapter to the output, e.g. for audiobooks or long recordings. MP4 and MKV outputs
+    /// write their chapters when the muxer is finished, so chapters can be added at any point
+    /// before [`Muxer::finish()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Start of the chapter.
+    /// * `end` - End of the chapter.
+    /// * `title` - Title of the chapter.
+    pub fn add_chapter(&mut self, start: Time, end: Time, title: &str) -> Result<()> {
+        if self.have_written_trailer {
+            return Err(Error::InvalidConfiguration {
+                field: "chapter",
+                reason: "muxer has already been finished".to_string(),
+            });
+        }
+        let (start, time_base) = start.into_parts();
+        let end = end.aligned_with_rational(time_base).into_value();
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err(Error::InvalidConfiguration {
+                field: "chapter",
+                reason: "chapter start and end must have a value".to_string(),
+            });
+        };
+        if start > end {
+            return Err(Error::InvalidConfiguration {
+                field: "chapter",
+                reason: "chapter ends before it starts".to_string(),
+            });
+        }
+        let output = self.writer_mut().output_mut();
+        let id = output.nb_chapters() as i64 + 1;
+        output.add_chapter(id, time_base, start, end, title)?;
+        Ok(())
+    }
+
+    /// Write a packet that is ready to go into the output.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - Packet to write, in the time base of its output stream.
+    fn write_packet(&mut self, packet: &mut AvPacket) -> Result<W::Out> {
+        if self.interleaved {
+            self.writer_mut().write_interleaved(packet)
+        } else {
+            self.writer_mut().write(packet)
+        }
+    }
+
+    /// Write the cover image as the single packet of its `attached_pic` stream.
+    fn write_cover_art(&mut self) -> Result<()> {
+        if let Some((stream_index, image)) = self.cover_art.take() {
+            let mut packet = AvPacket::copy(&image);
+            packet.set_stream(stream_index);
+            packet.set_pts(Some(0));
+            packet.set_dts(Some(0));
+            packet.set_flags(AvPacketFlags::KEY);
+            self.write_packet(&mut packet)?;
+        }
+        Ok(())
+    }
+
+    /// Whether or not packets of an input stream are muxed.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the stream in the input.
+    #[inline]
+    pub fn has_stream(&self, stream_index: usize) -> bool {
+        self.mapping.contains_key(&stream_index)
+    }
+
+    /// Finish writing and flush the output, reporting any error. Prefer this over dropping the
+    /// muxer: dropping writes the trailer as well, but can only log errors.
+    ///
+    /// # Return value
+    ///
+    /// Output of writing the remaining packets and the trailer, like [`Muxer::finish()`].
+    pub fn close(mut self) -> Result<Option<W::Out>> {
+        let out = self.finish()?;
+        ffi::flush_output_io(self.writer_mut().output_mut())?;
+        Ok(out)
+    }
+
     /// Signal to the muxer that writing has finished. This will cause a trailer to be written if
     /// the container format has one.
     pub fn finish(&mut self) -> Result<Option<W::Out>> {
         if self.have_written_header && !self.have_written_trailer {
+            let mut out = W::Out::default();
+            while let Some(mut packet) = self
+                .interleaver
+                .as_mut()
+                .and_then(Interleaver::pop_earliest)
+            {
+                out.append(self.write_packet(&mut packet)?);
+            }
             self.have_written_trailer = true;
-            self.writer.write_trailer().map(Some)
+            out.append(self.writer_mut().write_trailer()?);
+            Ok(Some(out))
         } else {
             Ok(None)
         }
     }
 
+    /// Consume the muxer and return the writer, e.g. to retrieve the sink of a
+    /// [`SinkWriter`](crate::core::io::SinkWriter) after [`Muxer::finish()`]. Unlike dropping the
+    /// muxer, this does not write the trailer.
+    pub fn into_writer(mut self) -> W {
+        self.writer
+            .take()
+            .expect("writer is present until the muxer is consumed")
+    }
+
+    /// Get the writer.
+    pub(crate) fn writer(&self) -> &W {
+        self.writer
+            .as_ref()
+            .expect("writer is present until the muxer is consumed")
+    }
+
+    /// Get the writer mutably.
+    pub(crate) fn writer_mut(&mut self) -> &mut W {
+        self.writer
+            .as_mut()
+            .expect("writer is present until the muxer is consumed")
+    }
+
     /// Get parameter sets corresponding to each internal stream. The parameter set contains one SPS
     /// (Sequence Parameter Set) and zero or more PPSs (Picture Parameter Sets).
     ///
@@ -187,12 +650,12 @@ impl<W: Write> Muxer<W> {
     /// codec and will return `Error::UnsupportedCodecParameterSets` for streams with another type
     /// of codec.
     pub fn parameter_sets_h264(&self) -> Vec<Result<(Sps<'_>, Pps<'_>)>> {
-        self.writer
+        self.writer()
             .output()
             .streams()
             .map(|stream| {
                 if stream.parameters().id() == AvCodecId::H264 {
-                    extract_parameter_sets_h264(extradata(self.writer.output(), stream.index())?)
+                    extract_parameter_sets_h264(extradata(self.writer().output(), stream.index())?)
                 } else {
                     Err(Error::UnsupportedCodecParameterSets)
                 }
@@ -201,9 +664,80 @@ impl<W: Write> Muxer<W> {
     }
 }
 
+impl<W: Write> Drop for Muxer<W> {
+    fn drop(&mut self) {
+        // Without a trailer most containers (notably MP4) cannot be played back, so make a best
+        // effort to write it.
+        if self.writer.is_none() || !self.have_written_header || self.have_written_trailer {
+            return;
+        }
+        // Writing the trailer while unwinding could panic again, which would abort the process.
+        if std::thread::panicking() {
+            tracing::error!("muxer dropped while panicking, not writing trailer");
+            return;
+        }
+        tracing::warn!("muxer dropped before it was finished, writing trailer");
+        if let Err(err) = self.finish() {
+            tracing::error!("failed to finish muxer: {err}");
+        }
+    }
+}
+
 unsafe impl<W: Write> Send for Muxer<W> {}
 unsafe impl<W: Write> Sync for Muxer<W> {}
 
+/// Image format of cover art.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverArtFormat {
+    Jpeg,
+    Png,
+}
+
+impl CoverArtFormat {
+    fn codec_id(self) -> AvCodecId {
+        match self {
+            CoverArtFormat::Jpeg => AvCodecId::MJPEG,
+            CoverArtFormat::Png => AvCodecId::PNG,
+        }
+    }
+
+    fn mime_type(self) -> &'static str {
+        match self {
+            CoverArtFormat::Jpeg => "image/jpeg",
+            CoverArtFormat::Png => "image/png",
+        }
+    }
+
+    fn filename(self) -> &'static str {
+        match self {
+            CoverArtFormat::Jpeg => "cover.jpg",
+            CoverArtFormat::Png => "cover.png",
+        }
+    }
+
+    /// Decode an image to find its width and height, which containers such as MP4 store with
+    /// the cover art.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Encoded image.
+    fn image_size(self, image: &[u8]) -> Result<(u32, u32)> {
+        let invalid = || Error::InvalidConfiguration {
+            field: "cover_art",
+            reason: format!("image is not a valid {self:?} image"),
+        };
+        let codec = ffmpeg::decoder::find(self.codec_id()).ok_or(Error::UninitializedCodec)?;
+        let mut decoder = AvContext::new_with_codec(codec).decoder().video()?;
+        decoder
+            .send_packet(&AvPacket::copy(image))
+            .map_err(|_| invalid())?;
+        decoder.send_eof()?;
+        let mut frame = AvFrame::empty();
+        decoder.receive_frame(&mut frame).map_err(|_| invalid())?;
+        Ok((frame.width(), frame.height()))
+    }
+}
+
 /// Internal structure that holds the stream index and the time base of the source packet for
 /// rescaling.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -211,3 +745,159 @@ struct StreamDescription {
     index: usize,
     source_time_base: AvRational,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use ffmpeg::util::format::Pixel as AvPixel;
+
+    use super::*;
+    use crate::core::encode::{EncoderBuilder, Settings};
+    use crate::core::frame::RawFrame;
+    use crate::core::io::Writer;
+
+    /// Grey PNG image of 16 by 8 pixels.
+    const PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x08, 0x08, 0x00, 0x00, 0x00, 0x00, 0xd5,
+        0x1d, 0x20, 0x4b, 0x00, 0x00, 0x00, 0x0f, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x68,
+        0x40, 0x03, 0x0c, 0x03, 0x23, 0x00, 0x00, 0x01, 0x87, 0x40, 0x01, 0x6c, 0x3a, 0x11, 0xe8,
+        0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    /// Encode a second of video to a temporary MP4 file.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the file in the temporary directory.
+    fn encode_source(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+        let mut encoder =
+            EncoderBuilder::new(path.as_path(), Settings::preset_h264_yuv420p(64, 48, false))
+                .build()
+                .unwrap();
+        for index in 0..30 {
+            let mut frame = RawFrame::new(AvPixel::YUV420P, 64, 48);
+            frame.data_mut(0).fill(index as u8);
+            frame.set_pts(Some(index * 1_000_000 / 30));
+            encoder.encode_raw(frame).unwrap();
+        }
+        encoder.finish().unwrap();
+        path
+    }
+
+    /// Mux all packets of `reader` with `muxer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader to read packets from.
+    /// * `muxer` - Muxer to mux packets with.
+    fn mux_all(reader: &mut Reader, mut muxer: Muxer<Writer>) {
+        while let Ok((_, packet)) = reader.read_any() {
+            muxer.mux(packet).unwrap();
+        }
+        muxer.finish().unwrap();
+    }
+
+    /// Mux the video stream of `source` into a file of the format that its extension implies.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - File to read.
+    /// * `destination` - File to write.
+    /// * `configure` - Configures the muxer.
+    fn remux(
+        source: &Path,
+        destination: &Path,
+        configure: impl FnOnce(MuxerBuilder<Writer>) -> Result<MuxerBuilder<Writer>>,
+    ) {
+        let mut reader = Reader::new(source).unwrap();
+        let builder = MuxerBuilder::new(Writer::new(destination).unwrap())
+            .with_streams(&reader)
+            .unwrap();
+        let muxer = configure(builder).unwrap().build().unwrap();
+        mux_all(&mut reader, muxer);
+    }
+
+    #[test]
+    fn test_matroska_tracks() {
+        let source = encode_source("tracks.mp4");
+        let destination = source.with_extension("mkv");
+
+        // The muxer infers a default track when none is marked.
+        remux(&source, &destination, Ok);
+        let details = Reader::new(destination.as_path()).unwrap().streams();
+        assert!(details[0].is_default());
+        assert!(!details[0].disposition.contains(AvDisposition::FORCED));
+
+        remux(&source, &destination, |builder| {
+            builder
+                .with_track_name(0, "Main")?
+                .with_forced_track(0, true)?
+                .with_default_track(0, true)
+        });
+        let details = Reader::new(destination.as_path()).unwrap().streams();
+        assert!(details[0].is_default());
+        assert!(details[0].disposition.contains(AvDisposition::FORCED));
+        assert_eq!(
+            details[0].metadata.get("title").map(String::as_str),
+            Some("Main")
+        );
+
+        // Only streams that were added to the muxer can be configured.
+        let reader = Reader::new(source.as_path()).unwrap();
+        let builder = MuxerBuilder::new(Writer::new(destination.as_path()).unwrap())
+            .with_streams(&reader)
+            .unwrap();
+        assert!(builder.with_default_track(1, true).is_err());
+
+        std::fs::remove_file(source).unwrap();
+        std::fs::remove_file(destination).unwrap();
+    }
+
+    #[test]
+    fn test_cover_art() {
+        let source = encode_source("cover.mp4");
+
+        let destination = source.with_file_name(format!("{}-cover-art.mp4", std::process::id()));
+        remux(&source, &destination, |builder| {
+            builder.with_cover_art(PNG.to_vec(), CoverArtFormat::Png)
+        });
+        let details = Reader::new(destination.as_path()).unwrap().streams();
+        let cover = details
+            .iter()
+            .find(|stream| stream.is_attached_pic())
+            .unwrap();
+        assert_eq!(cover.codec_id, AvCodecId::PNG);
+        assert_eq!(cover.resolution, Some((16, 8)));
+        std::fs::remove_file(destination).unwrap();
+
+        // Matroska stores the image as an attachment.
+        let destination = source.with_extension("mkv");
+        remux(&source, &destination, |builder| {
+            builder.with_cover_art(PNG.to_vec(), CoverArtFormat::Png)
+        });
+        let details = Reader::new(destination.as_path()).unwrap().streams();
+        assert!(details
+            .iter()
+            .any(|stream| stream.medium == AvMediaType::Attachment));
+        std::fs::remove_file(destination).unwrap();
+
+        let reader = Reader::new(source.as_path()).unwrap();
+        for (extension, image, format) in [
+            ("webm", PNG, CoverArtFormat::Png),
+            ("mp4", &PNG[..16], CoverArtFormat::Png),
+            ("mp4", PNG, CoverArtFormat::Jpeg),
+        ] {
+            let destination = source.with_extension(format!("invalid.{extension}"));
+            let builder = MuxerBuilder::new(Writer::new(destination.as_path()).unwrap())
+                .with_streams(&reader)
+                .unwrap();
+            assert!(builder.with_cover_art(image.to_vec(), format).is_err());
+            let _ = std::fs::remove_file(destination);
+        }
+
+        std::fs::remove_file(source).unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Callback that receives network statistics.
+pub(crate) type NetworkStatisticsCallback = Box<dyn FnMut(&NetworkStatistics) + Send>;
+
+/// Health of the connection of a network source or sink, e.g. to show a connection quality
+/// indicator. Sources and sinks fill in what they know about; the other fields are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkStatistics {
+    /// Total number of bytes received or sent.
+    pub bytes: u64,
+    /// Number of bytes received or sent per second, averaged over the last second.
+    pub bytes_per_second: u64,
+    /// Round trip time to the peer.
+    pub round_trip_time: Option<Duration>,
+    /// Total number of packets lost. Negative if duplicates were received.
+    pub packets_lost: Option<i64>,
+    /// Fraction of packets lost recently, between 0 and 1.
+    pub fraction_lost: Option<f32>,
+    /// Amount of media buffered between the network and the caller, such as the delay of a jitter
+    /// buffer.
+    pub buffer_depth: Option<Duration>,
+}
+
+/// Measures the throughput of a connection, and periodically reports [`NetworkStatistics`] to a
+/// callback.
+pub(crate) struct NetworkMonitor {
+    bytes: u64,
+    // Amount of data transferred within the last measurement window, oldest first.
+    window: VecDeque<(Instant, usize)>,
+    // When the first data was transferred.
+    start: Option<Instant>,
+    // How often to report, and where to.
+    interval: Duration,
+    callback: Option<NetworkStatisticsCallback>,
+    last_report: Option<Instant>,
+}
+
+impl NetworkMonitor {
+    /// Period over which the throughput is averaged.
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    /// Create a monitor that only measures, without reporting.
+    pub(crate) fn new() -> Self {
+        Self {
+            bytes: 0,
+            window: VecDeque::new(),
+            start: None,
+            interval: Duration::ZERO,
+            callback: None,
+            last_report: None,
+        }
+    }
+
+    /// Report statistics to a callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How often to report.
+    /// * `callback` - Callback that receives the statistics.
+    pub(crate) fn with_callback(
+        mut self,
+        interval: Duration,
+        callback: NetworkStatisticsCallback,
+    ) -> Self {
+        self.interval = interval;
+        self.callback = Some(callback);
+        self
+    }
+
+    /// Whether or not statistics are reported to a callback.
+    pub(crate) fn is_reporting(&self) -> bool {
+        self.callback.is_some()
+    }
+
+    /// Register transferred data.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Number of bytes transferred.
+    /// * `now` - Current time.
+    pub(crate) fn record(&mut self, bytes: usize, now: Instant) {
+        self.bytes += bytes as u64;
+        self.start.get_or_insert(now);
+        self.window.push_back((now, bytes));
+        self.expire(now);
+    }
+
+    /// Get the statistics that the monitor measures itself: the number of bytes and the
+    /// throughput.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current time.
+    pub(crate) fn statistics(&self, now: Instant) -> NetworkStatistics {
+        let Some(start) = self.start else {
+            return NetworkStatistics::default();
+        };
+        // Shortly after the start, the window is not full yet.
+        let window = now.saturating_duration_since(start).min(Self::WINDOW);
+        let bytes_in_window: usize = self
+            .window
+            .iter()
+            .filter(|(time, _)| now.saturating_duration_since(*time) < Self::WINDOW)
+            .map(|(_, bytes)| bytes)
+            .sum();
+        let bytes_per_second = if window.is_zero() {
+            0
+        } else {
+            (bytes_in_window as f64 / window.as_secs_f64()) as u64
+        };
+        NetworkStatistics {
+            bytes: self.bytes,
+            bytes_per_second,
+            ..NetworkStatistics::default()
+        }
+    }
+
+    /// Whether or not a report to the callback is due.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current time.
+    pub(crate) fn is_due(&self, now: Instant) -> bool {
+        self.callback.is_some()
+            && self.last_report.is_none_or(|last_report| {
+                now.saturating_duration_since(last_report) >= self.interval
+            })
+    }
+
+    /// Report statistics to the callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current time.
+    /// * `statistics` - Statistics to report, usually those of [`NetworkMonitor::statistics`]
+    ///   completed with what the caller knows about the connection, such as the round trip time.
+    pub(crate) fn report(&mut self, now: Instant, statistics: &NetworkStatistics) {
+        self.last_report = Some(now);
+        if let Some(callback) = self.callback.as_mut() {
+            callback(statistics);
+        }
+    }
+
+    /// Forget about data that was transferred before the measurement window.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current time.
+    fn expire(&mut self, now: Instant) {
+        while self
+            .window
+            .front()
+            .is_some_and(|(time, _)| now.saturating_duration_since(*time) >= Self::WINDOW)
+        {
+            self.window.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn ms(milliseconds: u64) -> Duration {
+        Duration::from_millis(milliseconds)
+    }
+
+    #[test]
+    fn test_throughput_over_window() {
+        let start = Instant::now();
+        let mut monitor = NetworkMonitor::new();
+        assert_eq!(monitor.statistics(start), NetworkStatistics::default());
+        monitor.record(1000, start);
+        monitor.record(1000, start + ms(500));
+        let statistics = monitor.statistics(start + ms(500));
+        assert_eq!(statistics.bytes, 2000);
+        assert_eq!(statistics.bytes_per_second, 4000);
+        // The first kilobyte has left the window.
+        monitor.record(500, start + ms(1200));
+        let statistics = monitor.statistics(start + ms(1200));
+        assert_eq!(statistics.bytes, 2500);
+        assert_eq!(statistics.bytes_per_second, 1500);
+        // Nothing was transferred for a second.
+        assert_eq!(monitor.statistics(start + ms(2200)).bytes_per_second, 0);
+    }
+
+    #[test]
+    fn test_reports_at_interval() {
+        let start = Instant::now();
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut monitor = NetworkMonitor::new().with_callback(ms(100), {
+            let reports = reports.clone();
+            Box::new(move |statistics: &NetworkStatistics| {
+                reports.lock().unwrap().push(statistics.clone())
+            })
+        });
+        monitor.record(100, start);
+        for elapsed in [10, 50, 110] {
+            let now = start + ms(elapsed);
+            if monitor.is_due(now) {
+                let statistics = NetworkStatistics {
+                    round_trip_time: Some(ms(20)),
+                    ..monitor.statistics(now)
+                };
+                monitor.report(now, &statistics);
+            }
+        }
+        assert!(!NetworkMonitor::new().is_due(start));
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].bytes, 100);
+        assert_eq!(reports[0].round_trip_time, Some(ms(20)));
+    }
+}
@@ -74,6 +74,25 @@ impl Options {
         Self(opts)
     }
 
+    /// Set an option, overwriting any existing value for the same key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Option name.
+    /// * `value` - Option value.
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.0.set(key, value);
+    }
+
+    /// Get the value of an option, if it is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Option name.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key)
+    }
+
     /// Convert back to ffmpeg native dictionary, which can be used with `ffmpeg_next` functions.
     pub(super) fn to_dict(&self) -> AvDictionary {
         self.0.clone()
@@ -37,6 +37,12 @@ impl Packet {
         self.inner.is_key()
     }
 
+    /// Get packet size in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
     /// Set packet PTS (presentation timestamp).
     #[inline]
     pub fn set_pts(&mut self, timestamp: Time) {
@@ -0,0 +1,237 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ffmpeg::codec::Id as AvCodecId;
+use ffmpeg::format::stream::{Disposition as AvDisposition, Stream as AvStream};
+use ffmpeg::media::Type as AvMediaType;
+use ffmpeg::util::dictionary::Ref as AvDictionaryRef;
+use ffmpeg::util::mathematics::rescale::TIME_BASE;
+use ffmpeg::Rational as AvRational;
+
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::io::Reader;
+use crate::core::location::Location;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Information about a media file or stream and the streams it contains.
+#[derive(Debug, Clone)]
+pub struct MediaInfo {
+    /// Short name of the container format, e.g. `mov,mp4,m4a,3gp,3g2,mj2`.
+    pub format_name: String,
+    /// Descriptive name of the container format.
+    pub format_long_name: String,
+    /// Duration of the media, if known.
+    pub duration: Option<Duration>,
+    /// Total bit rate in bits per second, if known.
+    pub bit_rate: Option<u64>,
+    /// Container metadata, such as `title`.
+    pub metadata: HashMap<String, String>,
+    /// Information about each stream, in stream order.
+    pub streams: Vec<StreamDetails>,
+}
+
+impl MediaInfo {
+    /// Gather media information from an opened reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader to gather information from.
+    pub fn from_reader(reader: &Reader) -> Self {
+        let input = &reader.input;
+        Self {
+            format_name: input.format().name().to_string(),
+            format_long_name: input.format().description().to_string(),
+            duration: timestamp_to_duration(input.duration(), TIME_BASE),
+            bit_rate: positive(input.bit_rate()),
+            metadata: collect_metadata(input.metadata()),
+            streams: input
+                .streams()
+                .map(|stream| StreamDetails::new(&stream))
+                .collect(),
+        }
+    }
+
+    /// Find the first stream of a kind of media.
+    ///
+    /// # Arguments
+    ///
+    /// * `medium` - Kind of media to look for.
+    pub fn first_stream_of(&self, medium: AvMediaType) -> Option<&StreamDetails> {
+        self.streams.iter().find(|stream| stream.medium == medium)
+    }
+}
+
+/// Information about a single stream.
+#[derive(Debug, Clone)]
+pub struct StreamDetails {
+    /// Index of the stream.
+    pub index: usize,
+    /// Kind of media the stream carries.
+    pub medium: AvMediaType,
+    /// Codec of the stream.
+    pub codec_id: AvCodecId,
+    /// Name of the codec, e.g. `h264`.
+    pub codec: String,
+    /// Time base of the stream.
+    pub time_base: AvRational,
+    /// Duration of the stream, if known.
+    pub duration: Option<Duration>,
+    /// Bit rate in bits per second, if known.
+    pub bit_rate: Option<u64>,
+    /// Width and height of video streams.
+    pub resolution: Option<(u32, u32)>,
+    /// Average frame rate of video streams, if known.
+    pub frame_rate: Option<f64>,
+    /// Sample rate of audio streams.
+    pub sample_rate: Option<u32>,
+    /// Number of channels of audio streams.
+    pub channels: Option<u32>,
+    /// Disposition flags of the stream, e.g. `DEFAULT`, `FORCED` or `ATTACHED_PIC`.
+    pub disposition: AvDisposition,
+    /// Language of the stream, usually an ISO 639-2 code such as `eng`.
+    pub language: Option<String>,
+    /// Stream metadata, such as `title` or `handler_name`.
+    pub metadata: HashMap<String, String>,
+}
+
+impl StreamDetails {
+    /// Gather information about a stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Stream to gather information about.
+    pub(crate) fn new(stream: &AvStream) -> Self {
+        let parameters = stream.parameters();
+        let medium = parameters.medium();
+        let metadata = collect_metadata(stream.metadata());
+        let (resolution, frame_rate) = if medium == AvMediaType::Video {
+            let frame_rate = stream.avg_frame_rate();
+            (
+                Some(ffi::codec_parameters_size(&parameters)),
+                (frame_rate.numerator() > 0 && frame_rate.denominator() > 0)
+                    .then(|| f64::from(frame_rate)),
+            )
+        } else {
+            (None, None)
+        };
+        let (sample_rate, channels) = if medium == AvMediaType::Audio {
+            let (sample_rate, channels) = ffi::codec_parameters_audio(&parameters);
+            (Some(sample_rate), Some(channels))
+        } else {
+            (None, None)
+        };
+        Self {
+            index: stream.index(),
+            medium,
+            codec_id: parameters.id(),
+            codec: parameters.id().name().to_string(),
+            time_base: stream.time_base(),
+            duration: timestamp_to_duration(stream.duration(), stream.time_base()),
+            bit_rate: positive(ffi::codec_parameters_bit_rate(&parameters)),
+            resolution,
+            frame_rate,
+            sample_rate,
+            channels,
+            disposition: stream.disposition(),
+            language: metadata.get("language").cloned(),
+            metadata,
+        }
+    }
+
+    /// Whether or not the stream is marked as the default stream of its kind.
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        self.disposition.contains(AvDisposition::DEFAULT)
+    }
+
+    /// Whether or not the stream holds a cover image rather than actual video.
+    #[inline]
+    pub fn is_attached_pic(&self) -> bool {
+        self.disposition.contains(AvDisposition::ATTACHED_PIC)
+    }
+}
+
+/// Probe a source and return information about its container and streams, without setting up any
+/// decoders.
+///
+/// # Arguments
+///
+/// * `source` - Source to probe.
+///
+/// # Example
+///
+/// ```ignore
+/// let info = probe(Path::new("movie.mkv")).unwrap();
+/// for stream in &info.streams {
+///     println!("#{} {:?} {} {:?}", stream.index, stream.medium, stream.codec, stream.language);
+/// }
+/// ```
+pub fn probe(source: impl Into<Location>) -> Result<MediaInfo> {
+    let reader = Reader::new(source)?;
+    Ok(MediaInfo::from_reader(&reader))
+}
+
+/// Copy metadata into a map.
+///
+/// # Arguments
+///
+/// * `metadata` - Metadata to copy.
+fn collect_metadata(metadata: AvDictionaryRef) -> HashMap<String, String> {
+    metadata
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Convert a value that ffmpeg reports as zero or negative when unknown.
+///
+/// # Arguments
+///
+/// * `value` - Value to convert.
+fn positive(value: i64) -> Option<u64> {
+    (value > 0).then_some(value as u64)
+}
+
+/// Convert a timestamp to a duration. Unknown (negative) timestamps are `None`.
+///
+/// # Arguments
+///
+/// * `timestamp` - Timestamp in `time_base` units.
+/// * `time_base` - Time base of the timestamp.
+pub(crate) fn timestamp_to_duration(timestamp: i64, time_base: AvRational) -> Option<Duration> {
+    if timestamp < 0 || time_base.denominator() <= 0 {
+        return None;
+    }
+    let seconds = timestamp as f64 * f64::from(time_base);
+    Some(Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_to_duration() {
+        assert_eq!(
+            timestamp_to_duration(90_000, AvRational::new(1, 90_000)),
+            Some(Duration::from_secs(1)),
+        );
+        assert_eq!(
+            timestamp_to_duration(2_500_000, TIME_BASE),
+            Some(Duration::from_millis(2500)),
+        );
+        // `AV_NOPTS_VALUE` means the duration is unknown.
+        assert_eq!(timestamp_to_duration(i64::MIN, TIME_BASE), None);
+    }
+
+    #[test]
+    fn test_positive() {
+        assert_eq!(positive(0), None);
+        assert_eq!(positive(-1), None);
+        assert_eq!(positive(128_000), Some(128_000));
+    }
+}
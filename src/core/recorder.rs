@@ -0,0 +1,561 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::time::{Duration, Instant};
+
+use ffmpeg::codec::encoder::audio::Encoder as AvAudioEncoder;
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::util::error::EAGAIN;
+use ffmpeg::{Error as AvError, Rational as AvRational};
+
+use crate::core::audio::{open_audio_encoder, RawAudioFrame, SampleFifo};
+use crate::core::audio_convert::{AudioConverter, AudioFormat};
+use crate::core::encode::{Encoder, EncoderBuilder, Settings};
+use crate::core::error::Error;
+#[cfg(feature = "ndarray")]
+use crate::core::frame::Frame;
+use crate::core::frame::RawFrame;
+use crate::core::io::private::Write;
+use crate::core::io::Writer;
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Builds a [`Recorder`].
+pub struct RecorderBuilder<'a> {
+    destination: Location,
+    settings: Settings,
+    format: Option<&'a str>,
+    options: Option<&'a Options>,
+    audio: Option<AudioFormat>,
+    audio_bit_rate: Option<usize>,
+    audio_options: Option<&'a Options>,
+    max_audio_gap: Duration,
+}
+
+impl<'a> RecorderBuilder<'a> {
+    /// Create a recorder that records video with the given settings to `destination`.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Where to record to.
+    /// * `settings` - Video encoder settings.
+    pub fn new(destination: impl Into<Location>, settings: Settings) -> Self {
+        Self {
+            destination: destination.into(),
+            settings,
+            format: None,
+            options: None,
+            audio: None,
+            audio_bit_rate: None,
+            audio_options: None,
+            max_audio_gap: Recorder::DEFAULT_MAX_AUDIO_GAP,
+        }
+    }
+
+    /// Set the container format of the output. By default it is derived from the destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Container format to use.
+    pub fn with_format(mut self, format: &'a str) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Set the options of the output.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to the output.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Record audio next to the video. The audio is encoded with the default audio codec of the
+    /// container.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Format of the pushed audio. The encoder keeps the sample format, channel layout
+    ///   and sample rate where it supports them.
+    pub fn with_audio(mut self, format: AudioFormat) -> Self {
+        self.audio = Some(format);
+        self
+    }
+
+    /// Set the target bit rate of the audio encoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `bit_rate` - Target bit rate in bits per second.
+    pub fn with_audio_bit_rate(mut self, bit_rate: usize) -> Self {
+        self.audio_bit_rate = Some(bit_rate);
+        self
+    }
+
+    /// Specify options for the audio encoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to the audio encoder.
+    pub fn with_audio_options(mut self, options: &'a Options) -> Self {
+        self.audio_options = Some(options);
+        self
+    }
+
+    /// Set how far the timestamp of pushed audio may run ahead of the samples pushed so far,
+    /// before the gap is filled with silence. Defaults to 100 ms.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_audio_gap` - Largest gap that is ignored.
+    pub fn with_max_audio_gap(mut self, max_audio_gap: Duration) -> Self {
+        self.max_audio_gap = max_audio_gap;
+        self
+    }
+
+    /// Build a [`Recorder`].
+    pub fn build(self) -> Result<Recorder> {
+        // Frames are pushed as they come, so they cannot be assumed to be evenly spaced.
+        let settings = self.settings.with_variable_frame_rate(true);
+        let mut encoder_builder = EncoderBuilder::new(self.destination, settings).interleaved();
+        if let Some(format) = self.format {
+            encoder_builder = encoder_builder.with_format(format);
+        }
+        if let Some(options) = self.options {
+            encoder_builder = encoder_builder.with_options(options);
+        }
+        let mut video_encoder = encoder_builder.build()?;
+
+        let audio = match self.audio {
+            Some(format) => {
+                let (encoder, writer_stream_index) = open_audio_encoder(
+                    video_encoder.writer_mut(),
+                    format,
+                    self.audio_bit_rate,
+                    self.audio_options,
+                )?;
+                let encoder_format =
+                    AudioFormat::new(encoder.format(), encoder.channel_layout(), encoder.rate());
+                Some(AudioTrack {
+                    converter: AudioConverter::new(encoder_format),
+                    fifo: SampleFifo::new(
+                        encoder_format.sample_format,
+                        encoder_format.channel_layout,
+                    ),
+                    encoder_time_base: AvRational::new(1, encoder_format.rate as i32),
+                    encoder,
+                    writer_stream_index,
+                    next_sample: None,
+                    max_gap: (self.max_audio_gap.as_secs_f64() * encoder_format.rate as f64) as i64,
+                })
+            }
+            None => None,
+        };
+
+        Ok(Recorder {
+            video_encoder,
+            audio,
+            origin: None,
+            finished: false,
+        })
+    }
+}
+
+/// Records pushed video frames and audio samples into a single file.
+///
+/// Video and audio are timestamped with the wall-clock time at which they were captured. The
+/// earliest timestamp becomes the start of the recording. The recorder encodes both streams,
+/// takes care of interleaving them, and keeps audio continuous: small jitter in the audio
+/// timestamps is ignored, while larger gaps (e.g. a dropout of the capture device) are filled with
+/// silence so that audio stays in sync with video.
+///
+/// # Example
+///
+/// ```ignore
+/// let settings = Settings::preset_h264_yuv420p(1280, 720, true);
+/// let mut recorder = RecorderBuilder::new(Path::new("recording.mp4"), settings)
+///     .with_audio(AudioFormat::new(
+///         AvSampleFormat::F32(SampleType::Packed),
+///         AvChannelLayout::STEREO,
+///         48_000,
+///     ))
+///     .build()
+///     .unwrap();
+/// recorder.push_video_raw(frame, Instant::now()).unwrap();
+/// recorder.push_audio(samples, Instant::now()).unwrap();
+/// recorder.finish().unwrap();
+/// ```
+pub struct Recorder {
+    video_encoder: Encoder,
+    audio: Option<AudioTrack>,
+    origin: Option<Instant>,
+    // Set once `finish` has been called, whether or not it succeeded, so that it is not retried.
+    finished: bool,
+}
+
+impl Recorder {
+    /// Default largest gap in the audio that is not filled with silence.
+    const DEFAULT_MAX_AUDIO_GAP: Duration = Duration::from_millis(100);
+
+    /// Push a single `ndarray` video frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame in `HWC` format and standard layout.
+    /// * `timestamp` - Wall-clock time at which the frame was captured.
+    #[cfg(feature = "ndarray")]
+    pub fn push_video(&mut self, frame: &Frame, timestamp: Instant) -> Result<()> {
+        let time = self.time_since_origin(timestamp);
+        self.video_encoder.encode(frame, time)
+    }
+
+    /// Push a single raw video frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame to record. Must have the size of the encoder, but may be in any pixel
+    ///   format.
+    /// * `timestamp` - Wall-clock time at which the frame was captured.
+    pub fn push_video_raw(&mut self, mut frame: RawFrame, timestamp: Instant) -> Result<()> {
+        let time = self.time_since_origin(timestamp);
+        frame.set_pts(
+            time.aligned_with_rational(self.video_encoder.time_base())
+                .into_value(),
+        );
+        self.video_encoder.encode_raw(frame)
+    }
+
+    /// Push a frame of audio samples. Fails with [`Error::InvalidSessionState`] if the recorder
+    /// was built without audio.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Audio samples. The format may differ from the one given to
+    ///   [`RecorderBuilder::with_audio`], in which case the samples are converted.
+    /// * `timestamp` - Wall-clock time at which the first sample was captured.
+    pub fn push_audio(&mut self, frame: RawAudioFrame, timestamp: Instant) -> Result<()> {
+        if self.finished {
+            return Err(Error::EncoderFinished);
+        }
+        if self.audio.is_none() {
+            return Err(Error::InvalidSessionState(
+                "recorder was built without audio".to_string(),
+            ));
+        }
+        let time = self.time_since_origin(timestamp);
+        self.video_encoder.write_header_once()?;
+        let Some(audio) = self.audio.as_mut() else {
+            return Ok(());
+        };
+        audio.push(&frame, time, self.video_encoder.writer_mut())
+    }
+
+    /// Flush both encoders and finish the recording.
+    ///
+    /// Note: If you don't call this function before dropping the recorder, it will be called
+    /// automatically. This will block the caller thread. Any errors cannot be propagated in this
+    /// case.
+    ///
+    /// The recorder is finished after this call, even if it fails. Calling it again does nothing.
+    pub fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        let audio_result = match self.audio.as_mut() {
+            Some(audio) if audio.next_sample.is_some() => {
+                audio.flush(self.video_encoder.writer_mut())
+            }
+            _ => Ok(()),
+        };
+        // Finish the video even if the audio failed, so that the trailer is still written.
+        let video_result = self.video_encoder.finish();
+        audio_result.and(video_result)
+    }
+
+    /// Get the time elapsed since the start of the recording. The first timestamp seen starts the
+    /// recording. Timestamps from before the start are clamped to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - Wall-clock timestamp.
+    fn time_since_origin(&mut self, timestamp: Instant) -> Time {
+        let origin = *self.origin.get_or_insert(timestamp);
+        Time::from_secs_f64(timestamp.saturating_duration_since(origin).as_secs_f64())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+unsafe impl Send for Recorder {}
+unsafe impl Sync for Recorder {}
+
+/// Audio stream of a recording.
+struct AudioTrack {
+    converter: AudioConverter,
+    fifo: SampleFifo,
+    encoder: AvAudioEncoder,
+    encoder_time_base: AvRational,
+    writer_stream_index: usize,
+    /// Position of the next sample that goes into the encoder, once recording has started.
+    next_sample: Option<i64>,
+    /// Largest gap, in samples, that is not filled with silence.
+    max_gap: i64,
+}
+
+impl AudioTrack {
+    /// Convert a frame of samples and encode as many full encoder frames as possible.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Audio samples.
+    /// * `time` - Time of the first sample since the start of the recording.
+    /// * `writer` - Writer to write packets to.
+    fn push(&mut self, frame: &RawAudioFrame, time: Time, writer: &mut Writer) -> Result<()> {
+        let position = time
+            .aligned_with_rational(self.encoder_time_base)
+            .into_value()
+            .unwrap_or(0);
+        let next_sample = *self.next_sample.get_or_insert(position);
+        // Samples still buffered in the converter come before the pushed audio as well.
+        let expected = next_sample + self.fifo.len() as i64 + self.converter.delay() as i64;
+        let gap = silence_to_insert(expected, position, self.max_gap);
+        if gap > 0 {
+            tracing::warn!("audio is {gap} samples behind its timestamp, inserting silence");
+            self.fifo.push_silence(gap);
+        }
+
+        self.converter.send(frame)?;
+        while let Some(frame) = self.converter.receive() {
+            self.fifo.push(&frame);
+        }
+
+        let frame_size = self.encoder.frame_size() as usize;
+        let frame_size = if frame_size > 0 {
+            frame_size
+        } else {
+            self.fifo.len()
+        };
+        while self.fifo.len() >= frame_size {
+            match self.fifo.pop(frame_size) {
+                Some(frame) => self.encode(frame, writer)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode the samples that are still buffered and drain the encoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer to write packets to.
+    fn flush(&mut self, writer: &mut Writer) -> Result<()> {
+        self.converter.send_eof()?;
+        while let Some(frame) = self.converter.receive() {
+            self.fifo.push(&frame);
+        }
+        // The last frame may be smaller than the frame size.
+        while let Some(frame) = self.fifo.pop(self.fifo.len()) {
+            self.encode(frame, writer)?;
+        }
+        self.encoder.send_eof()?;
+        self.write_packets(writer)
+    }
+
+    /// Send a frame to the encoder and write any resulting packets.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Frame in the format of the encoder.
+    /// * `writer` - Writer to write packets to.
+    fn encode(&mut self, mut frame: RawAudioFrame, writer: &mut Writer) -> Result<()> {
+        let pts = self.next_sample.unwrap_or(0);
+        frame.set_rate(self.encoder.rate());
+        frame.set_pts(Some(pts));
+        self.next_sample = Some(pts + frame.samples() as i64);
+        self.encoder.send_frame(&frame)?;
+        self.write_packets(writer)
+    }
+
+    /// Write all packets the encoder has available.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Writer to write packets to.
+    fn write_packets(&mut self, writer: &mut Writer) -> Result<()> {
+        let stream_time_base = writer
+            .output
+            .stream(self.writer_stream_index)
+            .ok_or(AvError::StreamNotFound)?
+            .time_base();
+        loop {
+            let mut packet = AvPacket::empty();
+            match self.encoder.receive_packet(&mut packet) {
+                Ok(()) => {
+                    packet.set_stream(self.writer_stream_index);
+                    packet.set_position(-1);
+                    packet.rescale_ts(self.encoder_time_base, stream_time_base);
+                    writer.write_interleaved(&mut packet)?;
+                }
+                Err(AvError::Eof) => return Ok(()),
+                Err(AvError::Other { errno }) if errno == EAGAIN => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// Compute how many silent samples to insert before pushed audio, so that it lines up with its
+/// timestamp again.
+///
+/// # Arguments
+///
+/// * `expected` - Position the pushed audio would get if it continued the audio so far.
+/// * `actual` - Position of the pushed audio according to its timestamp.
+/// * `max_gap` - Largest gap that is not filled.
+fn silence_to_insert(expected: i64, actual: i64, max_gap: i64) -> usize {
+    let gap = actual - expected;
+    if gap > max_gap {
+        gap as usize
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ffmpeg::util::format::sample::Type as AvSampleType;
+    use ffmpeg::util::format::Pixel as AvPixel;
+    use ffmpeg::util::format::Sample as AvSampleFormat;
+    use ffmpeg::ChannelLayout as AvChannelLayout;
+
+    use super::*;
+
+    /// Create a frame of silent mono samples.
+    fn silence(rate: u32, samples: usize) -> RawAudioFrame {
+        let mut frame = RawAudioFrame::new(
+            AvSampleFormat::F32(AvSampleType::Packed),
+            samples,
+            AvChannelLayout::MONO,
+        );
+        frame.set_rate(rate);
+        frame.data_mut(0).fill(0);
+        frame
+    }
+
+    /// Get the duration of the audio stream of a file in seconds.
+    fn audio_duration(path: &std::path::Path) -> f64 {
+        let input = ffmpeg::format::input(&path).unwrap();
+        let stream = input.streams().best(ffmpeg::media::Type::Audio).unwrap();
+        stream.duration() as f64 * f64::from(stream.time_base())
+    }
+
+    /// Record one second of video, and audio in chunks of 10 ms at the given rate, skipping the
+    /// chunks in `skip`.
+    fn record(
+        path: &std::path::Path,
+        rate: u32,
+        max_audio_gap: Duration,
+        skip: std::ops::Range<u32>,
+    ) {
+        let format = AudioFormat::new(
+            AvSampleFormat::F32(AvSampleType::Packed),
+            AvChannelLayout::MONO,
+            48_000,
+        );
+        let mut recorder = RecorderBuilder::new(path, Settings::preset_h264_yuv420p(64, 48, false))
+            .with_audio(format)
+            .with_max_audio_gap(max_audio_gap)
+            .build()
+            .unwrap();
+        let origin = Instant::now();
+        for index in 0..100 {
+            let time = origin + Duration::from_millis(index as u64 * 10);
+            if index % 4 == 0 {
+                recorder
+                    .push_video_raw(RawFrame::new(AvPixel::YUV420P, 64, 48), time)
+                    .unwrap();
+            }
+            if !skip.contains(&index) {
+                recorder
+                    .push_audio(silence(rate, rate as usize / 100), time)
+                    .unwrap();
+            }
+        }
+        recorder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_record_audio_and_video() {
+        let path = std::env::temp_dir().join(format!("recorder-{}.mp4", std::process::id()));
+        record(&path, 48_000, Recorder::DEFAULT_MAX_AUDIO_GAP, 0..0);
+        assert!((audio_duration(&path) - 1.0).abs() < 0.05);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_record_fills_gap_with_silence() {
+        let path = std::env::temp_dir().join(format!("recorder-gap-{}.mp4", std::process::id()));
+        // Half a second of audio goes missing in the middle.
+        record(&path, 48_000, Recorder::DEFAULT_MAX_AUDIO_GAP, 25..75);
+        assert!((audio_duration(&path) - 1.0).abs() < 0.05);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_record_resampled_audio_is_not_padded() {
+        let path =
+            std::env::temp_dir().join(format!("recorder-resampled-{}.mp4", std::process::id()));
+        // Without any tolerance, samples buffered in the resampler would otherwise be taken for a
+        // gap on every push.
+        record(&path, 44_100, Duration::ZERO, 0..0);
+        assert!((audio_duration(&path) - 1.0).abs() < 0.05);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_push_after_finish() {
+        let path =
+            std::env::temp_dir().join(format!("recorder-finished-{}.mp4", std::process::id()));
+        let mut recorder =
+            RecorderBuilder::new(path.as_path(), Settings::preset_h264_yuv420p(64, 48, false))
+                .build()
+                .unwrap();
+        assert!(matches!(
+            recorder.push_audio(silence(48_000, 480), Instant::now()),
+            Err(Error::InvalidSessionState(_)),
+        ));
+        recorder
+            .push_video_raw(RawFrame::new(AvPixel::YUV420P, 64, 48), Instant::now())
+            .unwrap();
+        recorder.finish().unwrap();
+        // Finishing again does nothing.
+        recorder.finish().unwrap();
+        assert!(matches!(
+            recorder.push_audio(silence(48_000, 480), Instant::now()),
+            Err(Error::EncoderFinished),
+        ));
+        drop(recorder);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_jitter_is_ignored() {
+        assert_eq!(silence_to_insert(48_000, 48_100, 4_800), 0);
+        assert_eq!(silence_to_insert(48_000, 47_000, 4_800), 0);
+    }
+
+    #[test]
+    fn test_gap_is_filled() {
+        assert_eq!(silence_to_insert(48_000, 96_000, 4_800), 48_000);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::core::error::Error;
+use crate::core::io::{Reader, ReaderBuilder, Writer, WriterBuilder};
+use crate::core::location::Location;
+use crate::core::mux::{Muxer, MuxerBuilder};
+use crate::core::options::Options;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Builds a [`Remuxer`].
+pub struct RemuxerBuilder<'a> {
+    source: Location,
+    destination: Location,
+    input_options: Option<&'a Options>,
+    output_options: Option<&'a Options>,
+    format: Option<&'a str>,
+}
+
+impl<'a> RemuxerBuilder<'a> {
+    /// Create a remuxer that copies the streams of `source` into `destination`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read from.
+    /// * `destination` - Where to write to.
+    pub fn new(source: impl Into<Location>, destination: impl Into<Location>) -> Self {
+        Self {
+            source: source.into(),
+            destination: destination.into(),
+            input_options: None,
+            output_options: None,
+            format: None,
+        }
+    }
+
+    /// Set the options of the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to the input.
+    pub fn with_input_options(mut self, options: &'a Options) -> Self {
+        self.input_options = Some(options);
+        self
+    }
+
+    /// Set the options of the output, e.g. `movflags` for MP4.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to the output.
+    pub fn with_output_options(mut self, options: &'a Options) -> Self {
+        self.output_options = Some(options);
+        self
+    }
+
+    /// Set the container format of the output. By default it is derived from the destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Container format to use.
+    pub fn with_format(mut self, format: &'a str) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Build a [`Remuxer`].
+    pub fn build(self) -> Result<Remuxer> {
+        let mut reader_builder = ReaderBuilder::new(self.source);
+        if let Some(options) = self.input_options {
+            reader_builder = reader_builder.with_options(options);
+        }
+        let reader = reader_builder.build()?;
+
+        let mut writer_builder = WriterBuilder::new(self.destination);
+        if let Some(options) = self.output_options {
+            writer_builder = writer_builder.with_options(options);
+        }
+        if let Some(format) = self.format {
+            writer_builder = writer_builder.with_format(format);
+        }
+        let writer = writer_builder.build()?;
+
+        let muxer = MuxerBuilder::new(writer)
+            .with_supported_streams(&reader)?
+            .interleaved()
+            .build()?;
+
+        Ok(Remuxer { reader, muxer })
+    }
+}
+
+/// Copies the streams of a source into another container without re-encoding, e.g. to turn a
+/// Matroska file into an MP4 file.
+///
+/// All streams that the destination container can hold are copied packet by packet, with their
+/// timestamps rescaled to the time bases of the destination. Streams the container cannot hold
+/// (such as SubRip subtitles in MP4) are skipped with a warning.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut remuxer = Remuxer::new(Path::new("movie.mkv"), Path::new("movie.mp4")).unwrap();
+/// let packets = remuxer.remux().unwrap();
+/// ```
+pub struct Remuxer {
+    reader: Reader,
+    muxer: Muxer<Writer>,
+}
+
+impl Remuxer {
+    /// Create a remuxer that copies the streams of `source` into `destination`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read from.
+    /// * `destination` - Where to write to.
+    #[inline]
+    pub fn new(source: impl Into<Location>, destination: impl Into<Location>) -> Result<Self> {
+        RemuxerBuilder::new(source, destination).build()
+    }
+
+    /// Run the remux.
+    ///
+    /// # Return value
+    ///
+    /// Number of packets that were copied.
+    pub fn remux(&mut self) -> Result<u64> {
+        let mut packet_count = 0;
+        for item in self.reader.packets() {
+            let (stream_index, packet) = item?;
+            if !self.muxer.has_stream(stream_index) {
+                continue;
+            }
+            self.muxer.mux(packet)?;
+            packet_count += 1;
+        }
+        self.muxer.finish()?;
+        Ok(packet_count)
+    }
+}
+
+unsafe impl Send for Remuxer {}
+unsafe impl Sync for Remuxer {}
+
+/// Copy the streams of `source` into `destination` without re-encoding. This is a shorthand for
+/// [`Remuxer::new`] followed by [`Remuxer::remux`].
+///
+/// # Arguments
+///
+/// * `source` - Source to read from.
+/// * `destination` - Where to write to.
+///
+/// # Return value
+///
+/// Number of packets that were copied.
+pub fn remux(source: impl Into<Location>, destination: impl Into<Location>) -> Result<u64> {
+    Remuxer::new(source, destination)?.remux()
+}
@@ -1,3 +1,7 @@
+use crate::core::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
 /// Represents width and height in a tuple.
 type Dims = (u32, u32);
 
@@ -36,6 +40,23 @@ impl Resize {
             Resize::FitEven(w, h) => calculate_fit_dims_even(dims, (w, h)),
         }
     }
+
+    /// Check that the resize strategy can produce frames at all, whatever the input dimensions.
+    pub(crate) fn validate(self) -> Result<()> {
+        match self {
+            Resize::Exact(w, h) | Resize::Fit(w, h) if w == 0 || h == 0 => {
+                Err(Error::InvalidConfiguration {
+                    field: "resize",
+                    reason: format!("{w}x{h} has a zero dimension"),
+                })
+            }
+            Resize::FitEven(w, h) if w < 2 || h < 2 => Err(Error::InvalidConfiguration {
+                field: "resize",
+                reason: format!("{w}x{h} cannot fit even dimensions"),
+            }),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Calculates the maximum image dimensions `w` and `h` that fit inside `w_max` and `h_max`
@@ -111,6 +132,15 @@ mod tests {
 
     const TESTING_DIM_CANDIDATES: [u32; 8] = [0, 1, 2, 3, 8, 111, 256, 1000];
 
+    #[test]
+    fn validate_rejects_degenerate_dims() {
+        assert!(Resize::Exact(0, 480).validate().is_err());
+        assert!(Resize::Fit(640, 0).validate().is_err());
+        assert!(Resize::FitEven(1, 480).validate().is_err());
+        assert!(Resize::Exact(641, 481).validate().is_ok());
+        assert!(Resize::FitEven(2, 2).validate().is_ok());
+    }
+
     #[test]
     fn calculate_fit_dims_works() {
         let testset = generate_testset();
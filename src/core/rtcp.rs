@@ -0,0 +1,832 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// NTP timestamp as carried in RTCP sender reports: seconds since 1900 in the upper 32 bits and
+/// the fraction of a second in the lower 32 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NtpTimestamp(pub u64);
+
+impl NtpTimestamp {
+    /// Seconds between the NTP epoch (1900) and the Unix epoch (1970).
+    const UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+    /// Get the current wall clock time as NTP timestamp.
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    /// Get the middle 32 bits of the timestamp, which is the compact form used in report blocks.
+    pub fn middle_bits(self) -> u32 {
+        (self.0 >> 16) as u32
+    }
+}
+
+impl From<SystemTime> for NtpTimestamp {
+    fn from(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = since_epoch.as_secs() + Self::UNIX_EPOCH_OFFSET;
+        let fraction = ((since_epoch.subsec_nanos() as u64) << 32) / 1_000_000_000;
+        NtpTimestamp((seconds << 32) | fraction)
+    }
+}
+
+impl From<NtpTimestamp> for SystemTime {
+    fn from(timestamp: NtpTimestamp) -> Self {
+        let seconds = (timestamp.0 >> 32).saturating_sub(NtpTimestamp::UNIX_EPOCH_OFFSET);
+        let nanos = ((timestamp.0 & 0xffff_ffff) * 1_000_000_000) >> 32;
+        UNIX_EPOCH + Duration::new(seconds, nanos as u32)
+    }
+}
+
+/// Reception report about a single RTP source, as sent in sender and receiver reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportBlock {
+    /// SSRC of the source the report is about.
+    pub ssrc: u32,
+    /// Fraction of packets lost since the previous report, in units of 1/256.
+    pub fraction_lost: u8,
+    /// Total number of packets lost. Negative if duplicates were received.
+    pub packets_lost: i32,
+    /// Highest sequence number received, extended with the number of wrap-arounds.
+    pub highest_sequence_number: u32,
+    /// Interarrival jitter in RTP timestamp units.
+    pub jitter: u32,
+    /// Middle 32 bits of the NTP timestamp of the last sender report received from the source.
+    pub last_sender_report: u32,
+    /// Delay since the last sender report was received, in units of 1/65536 seconds.
+    pub delay_since_last_sender_report: u32,
+}
+
+impl ReportBlock {
+    /// Size of a report block in bytes.
+    const SIZE: usize = 24;
+
+    /// Compute the round trip time between the sender and the receiver that sent the report
+    /// (RFC 3550 section 6.4.1). Returns `None` if the receiver has not received a sender report
+    /// yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `arrival` - Wall clock time at which the report arrived at the sender.
+    pub fn round_trip_time(&self, arrival: NtpTimestamp) -> Option<Duration> {
+        if self.last_sender_report == 0 {
+            return None;
+        }
+        // In units of 1/65536 seconds. Clock differences can make the result slightly negative.
+        let round_trip_time = arrival
+            .middle_bits()
+            .wrapping_sub(self.last_sender_report)
+            .wrapping_sub(self.delay_since_last_sender_report) as i32;
+        Some(Duration::from_nanos(
+            (round_trip_time.max(0) as u64 * 1_000_000_000) >> 16,
+        ))
+    }
+
+    /// Parse a report block.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Report block of at least 24 bytes.
+    fn parse(buf: &[u8]) -> Self {
+        let word = |offset: usize| {
+            u32::from_be_bytes([
+                buf[offset],
+                buf[offset + 1],
+                buf[offset + 2],
+                buf[offset + 3],
+            ])
+        };
+        // The number of packets lost is a signed 24-bit integer.
+        let packets_lost = ((word(4) << 8) as i32) >> 8;
+        ReportBlock {
+            ssrc: word(0),
+            fraction_lost: buf[4],
+            packets_lost,
+            highest_sequence_number: word(8),
+            jitter: word(12),
+            last_sender_report: word(16),
+            delay_since_last_sender_report: word(20),
+        }
+    }
+
+    /// Append the report block to a buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer to write to.
+    fn write(&self, buf: &mut Vec<u8>) {
+        let packets_lost = self.packets_lost.clamp(-0x80_0000, 0x7f_ffff) as u32 & 0xff_ffff;
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        buf.extend_from_slice(&(((self.fraction_lost as u32) << 24) | packets_lost).to_be_bytes());
+        buf.extend_from_slice(&self.highest_sequence_number.to_be_bytes());
+        buf.extend_from_slice(&self.jitter.to_be_bytes());
+        buf.extend_from_slice(&self.last_sender_report.to_be_bytes());
+        buf.extend_from_slice(&self.delay_since_last_sender_report.to_be_bytes());
+    }
+}
+
+/// RTCP sender report (SR). Besides reception reports, it maps the RTP timestamps of the sender to
+/// wall clock time, which is needed to synchronize streams with each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderReport {
+    /// SSRC of the sender.
+    pub ssrc: u32,
+    /// Wall clock time at which the report was sent.
+    pub ntp_timestamp: NtpTimestamp,
+    /// RTP timestamp that corresponds to `ntp_timestamp`.
+    pub rtp_timestamp: u32,
+    /// Number of RTP packets sent.
+    pub packet_count: u32,
+    /// Number of payload bytes sent.
+    pub octet_count: u32,
+    /// Reception reports of the sender about other sources.
+    pub report_blocks: Vec<ReportBlock>,
+}
+
+impl SenderReport {
+    /// RTCP packet type of a sender report.
+    const PACKET_TYPE: u8 = 200;
+
+    /// Map an RTP timestamp of the sender to wall clock time.
+    ///
+    /// # Arguments
+    ///
+    /// * `rtp_timestamp` - RTP timestamp to map. It must be within half the RTP timestamp range
+    ///   (about 6.6 hours at 90 kHz) of the report.
+    /// * `clock_rate` - Clock rate of the RTP timestamps.
+    pub fn ntp_time_of(&self, rtp_timestamp: u32, clock_rate: u32) -> NtpTimestamp {
+        let delta = rtp_timestamp.wrapping_sub(self.rtp_timestamp) as i32 as i128;
+        let offset = (delta << 32) / clock_rate as i128;
+        NtpTimestamp((self.ntp_timestamp.0 as i128 + offset) as u64)
+    }
+
+    /// Append the report to a buffer in its wire format.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer to write to.
+    pub fn write(&self, buf: &mut Vec<u8>) {
+        write_header(
+            buf,
+            Self::PACKET_TYPE,
+            self.report_blocks.len(),
+            6 + 6 * self.report_blocks.len(),
+        );
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        buf.extend_from_slice(&self.ntp_timestamp.0.to_be_bytes());
+        buf.extend_from_slice(&self.rtp_timestamp.to_be_bytes());
+        buf.extend_from_slice(&self.packet_count.to_be_bytes());
+        buf.extend_from_slice(&self.octet_count.to_be_bytes());
+        for report_block in &self.report_blocks {
+            report_block.write(buf);
+        }
+    }
+}
+
+/// RTCP receiver report (RR), sent by participants that do not send RTP themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiverReport {
+    /// SSRC of the receiver.
+    pub ssrc: u32,
+    /// Reception reports of the receiver about the sources it receives.
+    pub report_blocks: Vec<ReportBlock>,
+}
+
+impl ReceiverReport {
+    /// RTCP packet type of a receiver report.
+    const PACKET_TYPE: u8 = 201;
+
+    /// Append the report to a buffer in its wire format.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer to write to.
+    pub fn write(&self, buf: &mut Vec<u8>) {
+        write_header(
+            buf,
+            Self::PACKET_TYPE,
+            self.report_blocks.len(),
+            1 + 6 * self.report_blocks.len(),
+        );
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        for report_block in &self.report_blocks {
+            report_block.write(buf);
+        }
+    }
+}
+
+/// RTCP generic negative acknowledgement (NACK, RFC 4585), with which a receiver requests the
+/// retransmission of RTP packets that it did not receive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenericNack {
+    /// SSRC of the receiver that sends the NACK.
+    pub sender_ssrc: u32,
+    /// SSRC of the RTP stream that packets were lost from.
+    pub media_ssrc: u32,
+    /// Sequence numbers of the lost packets.
+    pub lost: Vec<u16>,
+}
+
+impl GenericNack {
+    /// RTCP packet type of transport layer feedback.
+    const PACKET_TYPE: u8 = 205;
+    /// Feedback message type of a generic NACK.
+    const FORMAT: u8 = 1;
+
+    /// Append the NACK to a buffer in its wire format. Each lost packet is described by the first
+    /// lost packet of a feedback control item, or by a bit in its bitmask of the 16 packets that
+    /// follow that one.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer to write to.
+    pub fn write(&self, buf: &mut Vec<u8>) {
+        let mut items: Vec<(u16, u16)> = Vec::new();
+        for &sequence_number in &self.lost {
+            match items.iter_mut().find(|(first, _)| {
+                (1..=16).contains(&sequence_number.wrapping_sub(*first))
+                    || sequence_number == *first
+            }) {
+                Some((first, bitmask)) if sequence_number != *first => {
+                    *bitmask |= 1 << (sequence_number.wrapping_sub(*first) - 1);
+                }
+                Some(_) => {}
+                None => items.push((sequence_number, 0)),
+            }
+        }
+        write_header(
+            buf,
+            Self::PACKET_TYPE,
+            Self::FORMAT as usize,
+            2 + items.len(),
+        );
+        buf.extend_from_slice(&self.sender_ssrc.to_be_bytes());
+        buf.extend_from_slice(&self.media_ssrc.to_be_bytes());
+        for (first, bitmask) in items {
+            buf.extend_from_slice(&first.to_be_bytes());
+            buf.extend_from_slice(&bitmask.to_be_bytes());
+        }
+    }
+}
+
+/// RTCP packet. Only sender and receiver reports and generic NACKs are parsed, other packet types
+/// are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtcpPacket {
+    SenderReport(SenderReport),
+    ReceiverReport(ReceiverReport),
+    GenericNack(GenericNack),
+}
+
+impl RtcpPacket {
+    /// Parse the reports in a compound RTCP packet. Parsing stops at the first malformed packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Contents of the UDP datagram.
+    pub fn parse_compound(mut buf: &[u8]) -> Vec<RtcpPacket> {
+        let mut packets = Vec::new();
+        while buf.len() >= 4 && buf[0] >> 6 == 2 {
+            let count = (buf[0] & 0x1f) as usize;
+            let size = 4 * (u16::from_be_bytes([buf[2], buf[3]]) as usize + 1);
+            let Some(packet) = buf.get(..size) else {
+                break;
+            };
+            let word = |offset: usize| {
+                u32::from_be_bytes([
+                    packet[offset],
+                    packet[offset + 1],
+                    packet[offset + 2],
+                    packet[offset + 3],
+                ])
+            };
+            let report_blocks = |offset: usize| {
+                (0..count)
+                    .map_while(|index| {
+                        let start = offset + index * ReportBlock::SIZE;
+                        packet.get(start..start + ReportBlock::SIZE)
+                    })
+                    .map(ReportBlock::parse)
+                    .collect()
+            };
+            match packet[1] {
+                SenderReport::PACKET_TYPE if size >= 28 => {
+                    packets.push(RtcpPacket::SenderReport(SenderReport {
+                        ssrc: word(4),
+                        ntp_timestamp: NtpTimestamp(((word(8) as u64) << 32) | word(12) as u64),
+                        rtp_timestamp: word(16),
+                        packet_count: word(20),
+                        octet_count: word(24),
+                        report_blocks: report_blocks(28),
+                    }))
+                }
+                ReceiverReport::PACKET_TYPE if size >= 8 => {
+                    packets.push(RtcpPacket::ReceiverReport(ReceiverReport {
+                        ssrc: word(4),
+                        report_blocks: report_blocks(8),
+                    }))
+                }
+                GenericNack::PACKET_TYPE if count == GenericNack::FORMAT as usize && size >= 12 => {
+                    let lost = packet[12..]
+                        .as_chunks::<4>()
+                        .0
+                        .iter()
+                        .flat_map(|item| {
+                            let first = u16::from_be_bytes([item[0], item[1]]);
+                            let bitmask = u16::from_be_bytes([item[2], item[3]]);
+                            std::iter::once(first).chain(
+                                (0..16)
+                                    .filter(move |bit| bitmask & (1 << bit) != 0)
+                                    .map(move |bit| first.wrapping_add(bit + 1)),
+                            )
+                        })
+                        .collect();
+                    packets.push(RtcpPacket::GenericNack(GenericNack {
+                        sender_ssrc: word(4),
+                        media_ssrc: word(8),
+                        lost,
+                    }))
+                }
+                _ => {}
+            }
+            buf = &buf[size..];
+        }
+        packets
+    }
+}
+
+/// Append a source description (SDES) packet with a canonical name (CNAME) to a buffer. Every
+/// compound RTCP packet must contain one.
+///
+/// # Arguments
+///
+/// * `buf` - Buffer to write to.
+/// * `ssrc` - SSRC of the participant.
+/// * `cname` - Canonical name of the participant. Truncated to 255 bytes.
+pub(crate) fn write_source_description(buf: &mut Vec<u8>, ssrc: u32, cname: &str) {
+    const PACKET_TYPE: u8 = 202;
+    const CNAME: u8 = 1;
+
+    let cname = &cname.as_bytes()[..cname.len().min(255)];
+    // SSRC, item type, item length and name, followed by at least one null byte that ends the
+    // list of items, padded to a multiple of four bytes.
+    let chunk_size = (4 + 2 + cname.len()) / 4 * 4 + 4;
+    write_header(buf, PACKET_TYPE, 1, chunk_size / 4);
+    let start = buf.len();
+    buf.extend_from_slice(&ssrc.to_be_bytes());
+    buf.push(CNAME);
+    buf.push(cname.len() as u8);
+    buf.extend_from_slice(cname);
+    buf.resize(start + chunk_size, 0);
+}
+
+/// Append an RTCP packet header to a buffer.
+///
+/// # Arguments
+///
+/// * `buf` - Buffer to write to.
+/// * `packet_type` - RTCP packet type.
+/// * `count` - Number of report blocks or chunks in the packet, or the feedback message type.
+/// * `length` - Length of the packet in 32-bit words, not counting the header.
+fn write_header(buf: &mut Vec<u8>, packet_type: u8, count: usize, length: usize) {
+    buf.push(0x80 | count as u8);
+    buf.push(packet_type);
+    buf.extend_from_slice(&(length as u16).to_be_bytes());
+}
+
+/// Statistics about the reception of an RTP stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReceptionStatistics {
+    /// SSRC of the sender.
+    pub ssrc: u32,
+    /// Number of RTP packets received, including duplicates.
+    pub packets_received: u64,
+    /// Number of RTP packets that were expected but not received. Negative if duplicates were
+    /// received.
+    pub packets_lost: i64,
+    /// Fraction of packets lost since the previous receiver report, between 0 and 1.
+    pub fraction_lost: f32,
+    /// Interarrival jitter, i.e. the variation of the network delay.
+    pub jitter: Duration,
+}
+
+/// Tracks sequence numbers and arrival times of an RTP stream to produce reception statistics and
+/// report blocks, following RFC 3550 appendix A.
+pub(crate) struct ReceptionTracker {
+    ssrc: u32,
+    clock_rate: u32,
+    // Reference point for arrival times.
+    start: Instant,
+    base_sequence_number: u32,
+    // Highest sequence number received, extended with the number of wrap-arounds.
+    highest_sequence_number: u32,
+    received: u64,
+    expected_prior: u64,
+    received_prior: u64,
+    // Relative transit time of the previous packet in RTP timestamp units.
+    transit: Option<u32>,
+    // Interarrival jitter in RTP timestamp units.
+    jitter: f64,
+    // Compact NTP timestamp of the last sender report, and when it arrived.
+    last_sender_report: Option<(u32, Instant)>,
+}
+
+impl ReceptionTracker {
+    /// Start tracking a stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `ssrc` - SSRC of the sender.
+    /// * `sequence_number` - Sequence number of the first packet.
+    /// * `clock_rate` - Clock rate of the RTP timestamps.
+    pub(crate) fn new(ssrc: u32, sequence_number: u16, clock_rate: u32) -> Self {
+        Self {
+            ssrc,
+            clock_rate,
+            start: Instant::now(),
+            base_sequence_number: sequence_number as u32,
+            highest_sequence_number: (sequence_number as u32).wrapping_sub(1),
+            received: 0,
+            expected_prior: 0,
+            received_prior: 0,
+            transit: None,
+            jitter: 0.0,
+            last_sender_report: None,
+        }
+    }
+
+    /// SSRC of the tracked sender.
+    pub(crate) fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    /// Register a received packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence_number` - Sequence number of the packet.
+    /// * `rtp_timestamp` - RTP timestamp of the packet.
+    /// * `arrival` - When the packet arrived.
+    pub(crate) fn update(&mut self, sequence_number: u16, rtp_timestamp: u32, arrival: Instant) {
+        self.received += 1;
+
+        let highest = self.highest_sequence_number as u16;
+        let delta = sequence_number.wrapping_sub(highest);
+        // Packets that are behind the highest sequence number arrived out of order, or are
+        // duplicates.
+        if delta != 0 && delta < 0x8000 {
+            if sequence_number < highest {
+                self.highest_sequence_number = self.highest_sequence_number.wrapping_add(1 << 16);
+            }
+            self.highest_sequence_number =
+                (self.highest_sequence_number & 0xffff_0000) | sequence_number as u32;
+        }
+
+        let arrival = arrival.saturating_duration_since(self.start).as_secs_f64();
+        let arrival = (arrival * self.clock_rate as f64) as u64 as u32;
+        let transit = arrival.wrapping_sub(rtp_timestamp);
+        if let Some(previous) = self.transit.replace(transit) {
+            let difference = (transit.wrapping_sub(previous) as i32).unsigned_abs() as f64;
+            self.jitter += (difference - self.jitter) / 16.0;
+        }
+    }
+
+    /// Register a sender report from the tracked sender.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender_report` - Received sender report.
+    /// * `arrival` - When the report arrived.
+    pub(crate) fn update_sender_report(&mut self, sender_report: &SenderReport, arrival: Instant) {
+        self.last_sender_report = Some((sender_report.ntp_timestamp.middle_bits(), arrival));
+    }
+
+    /// Number of packets expected, based on the highest sequence number received.
+    fn expected(&self) -> u64 {
+        (self
+            .highest_sequence_number
+            .wrapping_sub(self.base_sequence_number) as u64
+            + 1)
+            & 0xffff_ffff
+    }
+
+    /// Fraction lost since the previous report, in units of 1/256.
+    fn fraction_lost(&self) -> u8 {
+        let expected_interval = self.expected().saturating_sub(self.expected_prior);
+        let received_interval = self.received.saturating_sub(self.received_prior);
+        let lost_interval = expected_interval.saturating_sub(received_interval);
+        if expected_interval == 0 || lost_interval == 0 {
+            0
+        } else {
+            ((lost_interval << 8) / expected_interval).min(255) as u8
+        }
+    }
+
+    /// Current reception statistics.
+    pub(crate) fn statistics(&self) -> ReceptionStatistics {
+        ReceptionStatistics {
+            ssrc: self.ssrc,
+            packets_received: self.received,
+            packets_lost: self.expected() as i64 - self.received as i64,
+            fraction_lost: self.fraction_lost() as f32 / 256.0,
+            jitter: Duration::from_secs_f64(self.jitter / self.clock_rate as f64),
+        }
+    }
+
+    /// Produce a report block, and start a new reporting interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Time at which the report is sent.
+    pub(crate) fn report_block(&mut self, now: Instant) -> ReportBlock {
+        let fraction_lost = self.fraction_lost();
+        self.expected_prior = self.expected();
+        self.received_prior = self.received;
+        let (last_sender_report, delay_since_last_sender_report) = match self.last_sender_report {
+            Some((last_sender_report, arrival)) => {
+                let delay = now.saturating_duration_since(arrival).as_secs_f64();
+                (last_sender_report, (delay * 65536.0) as u32)
+            }
+            None => (0, 0),
+        };
+        ReportBlock {
+            ssrc: self.ssrc,
+            fraction_lost,
+            packets_lost: (self.expected() as i64 - self.received as i64)
+                .clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+            highest_sequence_number: self.highest_sequence_number,
+            jitter: self.jitter as u32,
+            last_sender_report,
+            delay_since_last_sender_report,
+        }
+    }
+}
+
+/// Keeps track of RTP packets that are missing from a stream, and decides when to request their
+/// retransmission with a [`GenericNack`].
+///
+/// A packet is requested once it has been missing for a short while, since it may just be
+/// reordered, and requested again at an interval until it arrives. Packets that are missing for
+/// longer than they can still be used are given up on.
+pub(crate) struct NackTracker {
+    // Time after which missing packets are no longer requested.
+    max_age: Duration,
+    // Highest sequence number received.
+    highest_sequence_number: Option<u16>,
+    // Missing sequence numbers, when they were found missing and when they were last requested.
+    missing: VecDeque<(u16, Instant, Option<Instant>)>,
+}
+
+impl NackTracker {
+    /// Time to wait for a reordered packet before requesting it.
+    const REORDERING_DELAY: Duration = Duration::from_millis(10);
+
+    /// Number of times a packet is requested within its maximum age.
+    const MAX_REQUESTS: u32 = 4;
+
+    /// Gap in sequence numbers beyond which the stream is considered to have restarted, rather
+    /// than having lost packets.
+    const MAX_GAP: u16 = 512;
+
+    /// Start tracking a stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_age` - Time after which missing packets are no longer requested, usually the
+    ///   maximum delay of the jitter buffer.
+    pub(crate) fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            highest_sequence_number: None,
+            missing: VecDeque::new(),
+        }
+    }
+
+    /// Register a received packet, either the original or a retransmission.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequence_number` - Sequence number of the packet.
+    /// * `arrival` - When the packet arrived.
+    pub(crate) fn update(&mut self, sequence_number: u16, arrival: Instant) {
+        let Some(highest) = self.highest_sequence_number else {
+            self.highest_sequence_number = Some(sequence_number);
+            return;
+        };
+        let delta = sequence_number.wrapping_sub(highest);
+        if delta == 0 || delta >= 0x8000 {
+            self.missing
+                .retain(|(missing, _, _)| *missing != sequence_number);
+            return;
+        }
+        if delta > Self::MAX_GAP {
+            self.missing.clear();
+        } else {
+            self.missing
+                .extend((1..delta).map(|offset| (highest.wrapping_add(offset), arrival, None)));
+        }
+        self.highest_sequence_number = Some(sequence_number);
+    }
+
+    /// Get the sequence numbers of the missing packets that should be requested now, and mark
+    /// them as requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current time.
+    pub(crate) fn poll(&mut self, now: Instant) -> Vec<u16> {
+        let retry_interval = self.max_age / Self::MAX_REQUESTS;
+        self.missing
+            .retain(|(_, found, _)| now.saturating_duration_since(*found) < self.max_age);
+        self.missing
+            .iter_mut()
+            .filter(|(_, found, requested)| match requested {
+                Some(requested) => now.saturating_duration_since(*requested) >= retry_interval,
+                None => now.saturating_duration_since(*found) >= Self::REORDERING_DELAY,
+            })
+            .map(|(sequence_number, _, requested)| {
+                *requested = Some(now);
+                *sequence_number
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ntp_timestamp_conversion() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_500);
+        let ntp_timestamp = NtpTimestamp::from(time);
+        assert_eq!(ntp_timestamp.0 >> 32, NtpTimestamp::UNIX_EPOCH_OFFSET + 1);
+        assert_eq!(ntp_timestamp.0 & 0xffff_ffff, 0x8000_0000);
+        assert_eq!(SystemTime::from(ntp_timestamp), time);
+    }
+
+    #[test]
+    fn test_ntp_time_of_rtp_timestamp() {
+        let sender_report = SenderReport {
+            ssrc: 1,
+            ntp_timestamp: NtpTimestamp(10 << 32),
+            rtp_timestamp: 10,
+            packet_count: 0,
+            octet_count: 0,
+            report_blocks: Vec::new(),
+        };
+        assert_eq!(
+            sender_report.ntp_time_of(90_010, 90_000),
+            NtpTimestamp(11 << 32)
+        );
+        assert_eq!(
+            sender_report.ntp_time_of(10u32.wrapping_sub(45_000), 90_000),
+            NtpTimestamp((10 << 32) - (1 << 31))
+        );
+    }
+
+    #[test]
+    fn test_reports_round_trip() {
+        let report_block = ReportBlock {
+            ssrc: 7,
+            fraction_lost: 64,
+            packets_lost: -2,
+            highest_sequence_number: 0x1_0005,
+            jitter: 120,
+            last_sender_report: 0x1234_5678,
+            delay_since_last_sender_report: 65536,
+        };
+        let sender_report = SenderReport {
+            ssrc: 1,
+            ntp_timestamp: NtpTimestamp(0x0102_0304_0506_0708),
+            rtp_timestamp: 9000,
+            packet_count: 10,
+            octet_count: 12000,
+            report_blocks: vec![report_block],
+        };
+        let receiver_report = ReceiverReport {
+            ssrc: 2,
+            report_blocks: vec![report_block],
+        };
+        let mut buf = Vec::new();
+        sender_report.write(&mut buf);
+        write_source_description(&mut buf, 1, "sender");
+        receiver_report.write(&mut buf);
+        assert_eq!(buf.len() % 4, 0);
+        assert_eq!(
+            RtcpPacket::parse_compound(&buf),
+            vec![
+                RtcpPacket::SenderReport(sender_report),
+                RtcpPacket::ReceiverReport(receiver_report),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip_time() {
+        let report_block = ReportBlock {
+            ssrc: 1,
+            fraction_lost: 0,
+            packets_lost: 0,
+            highest_sequence_number: 0,
+            jitter: 0,
+            last_sender_report: 0x0001_0000,
+            // Half a second.
+            delay_since_last_sender_report: 0x8000,
+        };
+        // The report arrives 0.75 seconds after the sender report was sent.
+        let arrival = NtpTimestamp(0x0001_c000 << 16);
+        assert_eq!(
+            report_block.round_trip_time(arrival),
+            Some(Duration::from_millis(250))
+        );
+        let report_block = ReportBlock {
+            last_sender_report: 0,
+            ..report_block
+        };
+        assert_eq!(report_block.round_trip_time(arrival), None);
+    }
+
+    #[test]
+    fn test_generic_nack_round_trip() {
+        let nack = GenericNack {
+            sender_ssrc: 2,
+            media_ssrc: 1,
+            lost: vec![65535, 0, 14, 15, 100],
+        };
+        let mut buf = Vec::new();
+        nack.write(&mut buf);
+        // Two feedback control items: 65535 with 0, 14 and 15 in its bitmask, and 100.
+        assert_eq!(buf.len(), 12 + 2 * 4);
+        assert_eq!(&buf[12..16], &[0xff, 0xff, 0xc0, 0x01]);
+        assert_eq!(
+            RtcpPacket::parse_compound(&buf),
+            vec![RtcpPacket::GenericNack(nack)]
+        );
+    }
+
+    #[test]
+    fn test_nack_tracker_requests_missing_packets() {
+        let start = Instant::now();
+        let mut tracker = NackTracker::new(Duration::from_millis(400));
+        tracker.update(65534, start);
+        tracker.update(1, start);
+        // Packets that are merely reordered are not requested.
+        tracker.update(65535, start + Duration::from_millis(5));
+        assert_eq!(
+            tracker.poll(start + Duration::from_millis(5)),
+            Vec::<u16>::new()
+        );
+        assert_eq!(tracker.poll(start + Duration::from_millis(10)), vec![0]);
+        assert_eq!(
+            tracker.poll(start + Duration::from_millis(50)),
+            Vec::<u16>::new()
+        );
+        assert_eq!(tracker.poll(start + Duration::from_millis(110)), vec![0]);
+        tracker.update(0, start + Duration::from_millis(120));
+        assert_eq!(
+            tracker.poll(start + Duration::from_millis(300)),
+            Vec::<u16>::new()
+        );
+
+        // Packets that are missing for too long are given up on.
+        tracker.update(4, start + Duration::from_millis(300));
+        assert_eq!(tracker.poll(start + Duration::from_millis(350)), vec![2, 3]);
+        assert_eq!(
+            tracker.poll(start + Duration::from_millis(700)),
+            Vec::<u16>::new()
+        );
+    }
+
+    #[test]
+    fn test_loss_across_sequence_number_wrap() {
+        let start = Instant::now();
+        let mut tracker = ReceptionTracker::new(1, 65534, 90_000);
+        for sequence_number in [65534, 65535, 1, 0, 3] {
+            tracker.update(sequence_number, 0, start);
+        }
+        let statistics = tracker.statistics();
+        assert_eq!(statistics.packets_received, 5);
+        assert_eq!(statistics.packets_lost, 1);
+        let report_block = tracker.report_block(start);
+        assert_eq!(report_block.highest_sequence_number, 0x1_0003);
+        assert_eq!(report_block.fraction_lost, (256 / 6) as u8);
+        assert_eq!(tracker.report_block(start).fraction_lost, 0);
+    }
+
+    #[test]
+    fn test_jitter_of_constant_and_varying_delay() {
+        let start = Instant::now();
+        let mut tracker = ReceptionTracker::new(1, 0, 90_000);
+        for i in 0..10u16 {
+            let arrival = start + Duration::from_millis(40 * i as u64);
+            tracker.update(i, 3600 * i as u32, arrival);
+        }
+        assert!(tracker.statistics().jitter < Duration::from_millis(1));
+        for i in 10..20u16 {
+            let arrival = start + Duration::from_millis(40 * i as u64 + 20 * (i as u64 % 2));
+            tracker.update(i, 3600 * i as u32, arrival);
+        }
+        assert!(tracker.statistics().jitter > Duration::from_millis(5));
+    }
+}
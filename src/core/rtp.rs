@@ -1,23 +1,70 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+
+use ffmpeg::codec::packet::Flags as AvPacketFlags;
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::codec::Id as AvCodecId;
+use ffmpeg::media::Type as AvMediaType;
+use ffmpeg::Error as AvError;
+use ffmpeg::Rational as AvRational;
+
+use crate::core::depacketize::{AccessUnit, Depacketizer, RtpHeader};
 use crate::core::error::Error;
-use crate::core::extradata::{Pps, Sps};
-use crate::core::ffi::{rtp_h264_mode_0, rtp_seq_and_timestamp, sdp};
-use crate::core::io::{Buf, PacketizedBufWriter, Reader};
+use crate::core::extradata::{extract_parameter_sets_h264, extract_parameter_sets_h265, Pps, Sps};
+use crate::core::ffi::{
+    codec_parameters_audio, extradata, rtp_h264_mode_0, rtp_payload_type, rtp_seq_and_timestamp,
+    sdp,
+};
+use crate::core::interrupt::Interrupt;
+use crate::core::io::{
+    Buf, PacketizedBufWriter, PacketizedBufWriterBuilder, Reader, ReaderBuilder,
+};
+use crate::core::jitter::{JitterBuffer, JitterQueue};
+use crate::core::latency::LatencyProfile;
 use crate::core::mux::{Muxer, MuxerBuilder};
+use crate::core::netstats::{NetworkMonitor, NetworkStatistics, NetworkStatisticsCallback};
+use crate::core::options::Options;
 use crate::core::packet::Packet;
+use crate::core::rtcp::{
+    self, GenericNack, NackTracker, NtpTimestamp, ReceiverReport, ReceptionStatistics,
+    ReceptionTracker, ReportBlock, RtcpPacket, SenderReport,
+};
+use crate::core::sdp::{
+    base64_decode, base64_encode, hex_decode, hex_encode, MediaDescription, MediaKind,
+    SessionDescription,
+};
 use crate::core::stream::StreamInfo;
+use crate::core::time::Time;
 
 type Result<T> = std::result::Result<T, Error>;
 
+pub use crate::core::depacketize::RtpCodec;
+
 /// Build an [`RtpMuxer`].
 pub struct RtpMuxerBuilder {
     inner: MuxerBuilder<PacketizedBufWriter>,
+    retransmission_payload_type: Option<u8>,
+    on_network_statistics: Option<(Duration, NetworkStatisticsCallback)>,
 }
 
 impl RtpMuxerBuilder {
     /// Create a new [`RtpMuxerBuilder`].
     pub fn new() -> Result<RtpMuxerBuilder> {
+        let mut options = Options::default();
+        // The backend considers its VP9 and AV1 packetizers experimental.
+        options.set("strict", "experimental");
         Ok(RtpMuxerBuilder {
-            inner: MuxerBuilder::new(PacketizedBufWriter::new("rtp")?),
+            inner: MuxerBuilder::new(
+                PacketizedBufWriterBuilder::new("rtp")
+                    .with_options(&options)
+                    .build()?,
+            ),
+            retransmission_payload_type: None,
+            on_network_statistics: None,
         })
     }
 
@@ -48,45 +95,210 @@ impl RtpMuxerBuilder {
         Ok(self)
     }
 
+    /// Keep recently sent RTP packets, and retransmit them when a receiver reports them lost
+    /// with an RTCP NACK. Retransmissions are sent in a separate stream with their own payload
+    /// type and SSRC (RFC 4588), which is signaled in the session description. RTCP packets from
+    /// the receiver must be passed to [`RtpMuxer::receive_rtcp_and_retransmit`].
+    ///
+    /// # Arguments
+    ///
+    /// * `payload_type` - Dynamic payload type of retransmissions, distinct from the payload type
+    ///   of the stream.
+    pub fn with_retransmission(mut self, payload_type: u8) -> Self {
+        self.retransmission_payload_type = Some(payload_type);
+        self
+    }
+
+    /// Periodically report the throughput of the stream, and the round trip time and packet loss
+    /// reported by the receiver, e.g. to show the health of the connection. Reports are made
+    /// while packets are muxed, so the callback should not block for long. See
+    /// [`RtpMuxer::network_statistics`].
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How often to report.
+    /// * `callback` - Callback that receives the statistics.
+    pub fn with_on_network_statistics(
+        mut self,
+        interval: Duration,
+        callback: impl FnMut(&NetworkStatistics) + Send + 'static,
+    ) -> Self {
+        self.on_network_statistics = Some((interval, Box::new(callback)));
+        self
+    }
+
     /// Build [`RtpMuxer`].
     ///
     /// The muxer will not write in interleaved mode.
     #[inline]
-    pub fn build(self) -> RtpMuxer {
-        RtpMuxer(self.inner.build())
+    pub fn build(self) -> Result<RtpMuxer> {
+        let mut network_monitor = NetworkMonitor::new();
+        if let Some((interval, callback)) = self.on_network_statistics {
+            network_monitor = network_monitor.with_callback(interval, callback);
+        }
+        Ok(RtpMuxer {
+            muxer: self.inner.build()?,
+            sender_report: None,
+            receiver_reports: HashMap::new(),
+            report_block: None,
+            round_trip_time: None,
+            retransmission_cache: self
+                .retransmission_payload_type
+                .map(RetransmissionCache::new),
+            network_monitor,
+        })
     }
 }
 
 /// Represents a muxer that muxes into the RTP format and streams the output over RTP.
-pub struct RtpMuxer(Muxer<PacketizedBufWriter>);
+///
+/// The muxer periodically produces RTCP sender reports along with the RTP packets. These should be
+/// sent to the RTCP port of the receivers.
+pub struct RtpMuxer {
+    muxer: Muxer<PacketizedBufWriter>,
+    // Last sender report produced by the muxer.
+    sender_report: Option<SenderReport>,
+    // Last receiver report of each receiver, by SSRC.
+    receiver_reports: HashMap<u32, ReceiverReport>,
+    // Last report block about the stream, and the round trip time it was received with.
+    report_block: Option<ReportBlock>,
+    round_trip_time: Option<Duration>,
+    // Recently sent RTP packets, if retransmission is enabled.
+    retransmission_cache: Option<RetransmissionCache>,
+    network_monitor: NetworkMonitor,
+}
 
 impl RtpMuxer {
     /// Create a new non-interleaved writing [`RtpMuxer`].
     ///
     /// The muxer muxes into the RTP format and streams the output over RTP.
     pub fn new() -> Result<RtpMuxer> {
-        Ok(RtpMuxerBuilder::new()?.build())
+        RtpMuxerBuilder::new()?.build()
     }
 
     /// Mux a single packet. This will cause the muxer to try and read packets from the preferred
     /// stream, mux it and return one or more RTP buffers.
     pub fn mux(&mut self, packet: Packet) -> Result<Vec<RtpBuf>> {
-        self.0
-            .mux(packet)
-            .map(|bufs| bufs.into_iter().map(|buf| buf.into()).collect())
+        let bufs = self.muxer.mux(packet)?;
+        Ok(self.rtp_bufs(bufs))
     }
 
     /// Signal to the muxer that writing has finished. This will cause trailing packets to be
     /// returned if the container format has one.
     pub fn finish(&mut self) -> Result<Option<Vec<RtpBuf>>> {
-        self.0
-            .finish()
-            .map(|bufs| bufs.map(|bufs| bufs.into_iter().map(|buf| buf.into()).collect()))
+        let bufs = self.muxer.finish()?;
+        Ok(bufs.map(|bufs| self.rtp_bufs(bufs)))
+    }
+
+    /// Get the last RTCP sender report produced by the muxer. It maps the RTP timestamps of the
+    /// stream to wall clock time.
+    pub fn last_sender_report(&self) -> Option<&SenderReport> {
+        self.sender_report.as_ref()
+    }
+
+    /// Process an RTCP packet sent back by a receiver, to keep track of its receiver reports.
+    ///
+    /// Packets that the receiver requests with NACKs are not retransmitted, use
+    /// [`RtpMuxer::receive_rtcp_and_retransmit`] for that.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Contents of the UDP datagram received on the RTCP port.
+    pub fn receive_rtcp(&mut self, buf: &[u8]) {
+        self.process_rtcp(buf, false);
+    }
+
+    /// Process an RTCP packet sent back by a receiver like [`RtpMuxer::receive_rtcp`], and
+    /// retransmit the packets that the receiver requests with NACKs, as far as they are still
+    /// cached. Requires retransmission to be enabled, see
+    /// [`RtpMuxerBuilder::with_retransmission`].
+    ///
+    /// The retransmissions are returned, and should be sent to the receiver like other RTP
+    /// packets.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Contents of the UDP datagram received on the RTCP port.
+    pub fn receive_rtcp_and_retransmit(&mut self, buf: &[u8]) -> Vec<RtpBuf> {
+        self.process_rtcp(buf, true)
+    }
+
+    /// Process an RTCP packet sent back by a receiver.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Contents of the UDP datagram received on the RTCP port.
+    /// * `retransmit` - Whether to retransmit the packets requested with NACKs.
+    fn process_rtcp(&mut self, buf: &[u8], retransmit: bool) -> Vec<RtpBuf> {
+        let arrival = NtpTimestamp::now();
+        let ssrc = self
+            .sender_report
+            .as_ref()
+            .map(|sender_report| sender_report.ssrc);
+        let mut retransmissions = Vec::new();
+        for packet in RtcpPacket::parse_compound(buf) {
+            match packet {
+                RtcpPacket::ReceiverReport(receiver_report) => {
+                    let report_block = receiver_report
+                        .report_blocks
+                        .iter()
+                        .find(|report_block| Some(report_block.ssrc) == ssrc);
+                    if let Some(report_block) = report_block {
+                        self.round_trip_time = report_block
+                            .round_trip_time(arrival)
+                            .or(self.round_trip_time);
+                        self.report_block = Some(*report_block);
+                    }
+                    self.receiver_reports
+                        .insert(receiver_report.ssrc, receiver_report);
+                }
+                RtcpPacket::GenericNack(nack) if retransmit => {
+                    if let Some(retransmission_cache) = self.retransmission_cache.as_mut() {
+                        retransmissions.extend(
+                            retransmission_cache
+                                .retransmit(&nack)
+                                .into_iter()
+                                .map(RtpBuf::Rtp),
+                        );
+                    }
+                }
+                RtcpPacket::SenderReport(_) | RtcpPacket::GenericNack(_) => {}
+            }
+        }
+        let now = Instant::now();
+        for retransmission in &retransmissions {
+            let (RtpBuf::Rtp(buf) | RtpBuf::Rtcp(buf)) = retransmission;
+            self.network_monitor.record(buf.len(), now);
+        }
+        retransmissions
+    }
+
+    /// Get the amount of data produced by the muxer and the current throughput, along with the
+    /// round trip time and packet loss from the last receiver report about the stream. The round
+    /// trip time is only known once a receiver has received a sender report.
+    pub fn network_statistics(&self) -> NetworkStatistics {
+        NetworkStatistics {
+            round_trip_time: self.round_trip_time,
+            packets_lost: self
+                .report_block
+                .map(|report_block| report_block.packets_lost as i64),
+            fraction_lost: self
+                .report_block
+                .map(|report_block| report_block.fraction_lost as f32 / 256.0),
+            ..self.network_monitor.statistics(Instant::now())
+        }
+    }
+
+    /// Get the last receiver report of each receiver that was passed to
+    /// [`RtpMuxer::receive_rtcp`]. The report blocks contain the packet loss and jitter as
+    /// observed by the receiver.
+    pub fn receiver_reports(&self) -> impl Iterator<Item = &ReceiverReport> {
+        self.receiver_reports.values()
     }
 
     /// Get the RTP packetization mode used by the muxer.
     pub fn packetization_mode(&self) -> usize {
-        let is_packetization_mode_0 = rtp_h264_mode_0(&self.0.writer.output);
+        let is_packetization_mode_0 = rtp_h264_mode_0(&self.muxer.writer().output);
 
         if !is_packetization_mode_0 {
             1
@@ -102,12 +314,12 @@ impl RtpMuxer {
     /// codec and will return `Error::UnsupportedCodecParameterSets` for streams with another type
     /// of codec.
     pub fn parameter_sets_h264(&self) -> Vec<Result<(Sps<'_>, Pps<'_>)>> {
-        self.0.parameter_sets_h264()
+        self.muxer.parameter_sets_h264()
     }
 
     /// Get the current RTP sequence number and timestamp.
     pub fn seq_and_timestamp(&self) -> (u16, u32) {
-        rtp_seq_and_timestamp(&self.0.writer.output)
+        rtp_seq_and_timestamp(&self.muxer.writer().output)
     }
 
     /// Produce SDP (Session Description Protocol) file contents for this stream using the
@@ -128,8 +340,289 @@ impl RtpMuxer {
     /// a=rtpmap:96 H264/90000
     /// a=fmtp:96 packetization-mode=1
     /// ```
+    ///
+    /// The connection address and port are not known to the backend, so this SDP is usually not
+    /// usable as is. Use [`RtpMuxer::session_description`] instead to describe where the stream is
+    /// sent to.
     pub fn sdp(&self) -> Result<String> {
-        sdp(&self.0.writer.output).map_err(Error::BackendError)
+        sdp(&self.muxer.writer().output).map_err(Error::BackendError)
+    }
+
+    /// Describe the RTP session, so that receivers such as ffplay or GStreamer can receive the
+    /// stream from an `.sdp` file. The description contains the payload type, clock rate and codec
+    /// parameters of each stream, including the parameter sets of H.264 and H.265 streams
+    /// (`sprop-parameter-sets`), so that receivers can start decoding without waiting for
+    /// parameter sets in the stream.
+    ///
+    /// Supported codecs are H.264, H.265, VP8, VP9, AV1, AAC, Opus, G.711 (PCMU and PCMA) and
+    /// G.722.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Address the RTP packets are sent to. If the muxer has more than one
+    ///   stream, the streams are described on consecutive even ports starting from this port
+    ///   (the odd ports being used for RTCP).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let session_description = muxer.session_description("127.0.0.1:5004".parse().unwrap())?;
+    /// std::fs::write("stream.sdp", session_description.to_string())?;
+    /// ```
+    pub fn session_description(&self, destination: SocketAddr) -> Result<SessionDescription> {
+        let media = (0..self.muxer.writer().output.nb_streams() as usize)
+            .map(|stream_index| {
+                let port = destination.port().wrapping_add(2 * stream_index as u16);
+                self.media_description(stream_index, port)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(SessionDescription {
+            session_name: "Stream".to_string(),
+            connection_address: destination.ip(),
+            media,
+        })
+    }
+}
+
+impl RtpMuxer {
+    /// Get the extradata of a stream of the muxer, e.g. to find out whether H.264 packets are in
+    /// Annex B or length-prefixed format.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the stream in the muxer.
+    pub(crate) fn extradata(&self, stream_index: usize) -> Result<&[u8]> {
+        Ok(extradata(&self.muxer.writer().output, stream_index)?)
+    }
+
+    /// Describe a single stream of the muxer for a session description.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the stream to describe.
+    /// * `port` - Port the stream is sent to.
+    fn media_description(&self, stream_index: usize, port: u16) -> Result<MediaDescription> {
+        let output = &self.muxer.writer().output;
+        let parameters = output
+            .stream(stream_index)
+            .ok_or(AvError::StreamNotFound)?
+            .parameters();
+        let codec_id = parameters.id();
+        let kind = match parameters.medium() {
+            AvMediaType::Video => MediaKind::Video,
+            AvMediaType::Audio => MediaKind::Audio,
+            _ => return Err(Error::UnsupportedCodec(format!("{codec_id:?}"))),
+        };
+        // Same choice as the backend makes when it writes the header: the static payload type
+        // of the codec if it has one, or else the first dynamic payload type.
+        let payload_type = rtp_payload_type(output).unwrap_or(match codec_id {
+            AvCodecId::PCM_MULAW => 0,
+            AvCodecId::PCM_ALAW => 8,
+            AvCodecId::ADPCM_G722 => 9,
+            _ if kind == MediaKind::Video => 96,
+            _ => 97,
+        });
+        let (sample_rate, channels) = codec_parameters_audio(&parameters);
+        let extradata = extradata(output, stream_index)?;
+
+        let mut format_parameters = Vec::new();
+        let (encoding_name, clock_rate, channels) = match codec_id {
+            AvCodecId::H264 => {
+                format_parameters.push((
+                    "packetization-mode".to_string(),
+                    self.packetization_mode().to_string(),
+                ));
+                // Without parameter sets in the extradata, receivers find them in the stream.
+                if let Ok((sps, ppss)) = extract_parameter_sets_h264(extradata) {
+                    if let Some(profile_level_id) = sps.get(1..4) {
+                        format_parameters
+                            .push(("profile-level-id".to_string(), hex_encode(profile_level_id)));
+                    }
+                    let parameter_sets = std::iter::once(sps)
+                        .chain(ppss)
+                        .map(base64_encode)
+                        .collect::<Vec<_>>();
+                    format_parameters
+                        .push(("sprop-parameter-sets".to_string(), parameter_sets.join(",")));
+                }
+                ("H264", 90_000, None)
+            }
+            AvCodecId::HEVC => {
+                if let Ok(parameter_sets) = extract_parameter_sets_h265(extradata) {
+                    for (nal_type, name) in
+                        [(32, "sprop-vps"), (33, "sprop-sps"), (34, "sprop-pps")]
+                    {
+                        let parameter_sets = parameter_sets
+                            .iter()
+                            .filter(|nal| {
+                                nal.first()
+                                    .is_some_and(|header| (header >> 1) & 0x3f == nal_type)
+                            })
+                            .map(|nal| base64_encode(nal))
+                            .collect::<Vec<_>>();
+                        if !parameter_sets.is_empty() {
+                            format_parameters.push((name.to_string(), parameter_sets.join(",")));
+                        }
+                    }
+                }
+                ("H265", 90_000, None)
+            }
+            AvCodecId::VP8 => ("VP8", 90_000, None),
+            AvCodecId::VP9 => ("VP9", 90_000, None),
+            AvCodecId::AV1 => ("AV1", 90_000, None),
+            AvCodecId::AAC => {
+                for (name, value) in [
+                    ("profile-level-id", "1"),
+                    ("mode", "AAC-hbr"),
+                    ("sizelength", "13"),
+                    ("indexlength", "3"),
+                    ("indexdeltalength", "3"),
+                ] {
+                    format_parameters.push((name.to_string(), value.to_string()));
+                }
+                format_parameters.push(("config".to_string(), hex_encode(extradata)));
+                ("MPEG4-GENERIC", sample_rate, Some(channels))
+            }
+            // Opus is always signaled as stereo at 48 kHz, whatever the actual stream is.
+            AvCodecId::OPUS => ("opus", 48_000, Some(2)),
+            AvCodecId::PCM_MULAW => ("PCMU", sample_rate, None),
+            AvCodecId::PCM_ALAW => ("PCMA", sample_rate, None),
+            // G.722 is signaled with an 8 kHz clock rate for historical reasons.
+            AvCodecId::ADPCM_G722 => ("G722", 8_000, None),
+            _ => return Err(Error::UnsupportedCodec(format!("{codec_id:?}"))),
+        };
+        Ok(MediaDescription {
+            kind,
+            port,
+            payload_type,
+            encoding_name: encoding_name.to_string(),
+            clock_rate,
+            channels,
+            format_parameters,
+            rtcp_port: None,
+            control: None,
+            retransmission_payload_type: self
+                .retransmission_cache
+                .as_ref()
+                .map(|retransmission_cache| retransmission_cache.payload_type),
+        })
+    }
+
+    /// Classify the buffers produced by the muxer, and keep track of the sender reports among them.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufs` - Buffers produced by the muxer.
+    fn rtp_bufs(&mut self, bufs: Vec<Buf>) -> Vec<RtpBuf> {
+        let bufs: Vec<RtpBuf> = bufs.into_iter().map(RtpBuf::from).collect();
+        for buf in &bufs {
+            match buf {
+                RtpBuf::Rtcp(buf) => {
+                    for packet in RtcpPacket::parse_compound(buf) {
+                        if let RtcpPacket::SenderReport(sender_report) = packet {
+                            self.sender_report = Some(sender_report);
+                        }
+                    }
+                }
+                RtpBuf::Rtp(buf) => {
+                    if let Some(retransmission_cache) = self.retransmission_cache.as_mut() {
+                        retransmission_cache.insert(buf);
+                    }
+                }
+            }
+        }
+        let now = Instant::now();
+        for buf in &bufs {
+            let (RtpBuf::Rtp(buf) | RtpBuf::Rtcp(buf)) = buf;
+            self.network_monitor.record(buf.len(), now);
+        }
+        if self.network_monitor.is_due(now) {
+            let statistics = self.network_statistics();
+            self.network_monitor.report(now, &statistics);
+        }
+        bufs
+    }
+}
+
+/// Recently sent RTP packets of a stream, from which lost packets are retransmitted in a separate
+/// retransmission stream (RFC 4588).
+struct RetransmissionCache {
+    payload_type: u8,
+    // SSRC and next sequence number of the retransmission stream.
+    ssrc: u32,
+    sequence_number: u16,
+    // Sent packets, oldest first.
+    packets: VecDeque<Buf>,
+}
+
+impl RetransmissionCache {
+    /// Number of packets to keep, enough for about a second of high bitrate video.
+    const CAPACITY: usize = 1024;
+
+    /// Create an empty cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload_type` - Payload type of retransmissions.
+    fn new(payload_type: u8) -> Self {
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        Self {
+            payload_type,
+            ssrc: random as u32,
+            sequence_number: (random >> 32) as u16,
+            packets: VecDeque::with_capacity(Self::CAPACITY),
+        }
+    }
+
+    /// Keep a sent packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - Sent RTP packet.
+    fn insert(&mut self, packet: &[u8]) {
+        if RtpHeader::parse(packet).is_none() {
+            return;
+        }
+        if self.packets.len() == Self::CAPACITY {
+            self.packets.pop_front();
+        }
+        self.packets.push_back(packet.to_vec());
+    }
+
+    /// Build retransmissions of the packets that a NACK reports lost. Each retransmission carries
+    /// the original sequence number in front of the original payload. Packets that are no longer
+    /// cached, or that belong to another stream, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `nack` - NACK received from a receiver.
+    fn retransmit(&mut self, nack: &GenericNack) -> Vec<Buf> {
+        let mut retransmissions = Vec::new();
+        for &lost in &nack.lost {
+            let original = self.packets.iter().rev().find_map(|packet| {
+                RtpHeader::parse(packet).filter(|(header, _)| {
+                    header.ssrc == nack.media_ssrc && header.sequence_number == lost
+                })
+            });
+            let Some((header, payload)) = original else {
+                continue;
+            };
+            let mut retransmission = Vec::with_capacity(12 + 2 + payload.len());
+            RtpHeader {
+                payload_type: self.payload_type,
+                sequence_number: self.sequence_number,
+                ssrc: self.ssrc,
+                ..header
+            }
+            .write(&mut retransmission);
+            retransmission.extend_from_slice(&header.sequence_number.to_be_bytes());
+            retransmission.extend_from_slice(payload);
+            self.sequence_number = self.sequence_number.wrapping_add(1);
+            retransmissions.push(retransmission);
+        }
+        retransmissions
     }
 }
 
@@ -168,3 +661,1248 @@ impl From<RtpBuf> for Buf {
         }
     }
 }
+
+/// Build an [`RtpReceiver`].
+pub struct RtpReceiverBuilder {
+    address: SocketAddr,
+    codec: RtpCodec,
+    payload_type: Option<u8>,
+    interrupt: Option<Interrupt>,
+    rtcp_address: Option<SocketAddr>,
+    receiver_report_interval: Option<Duration>,
+    jitter_buffer: Option<JitterBuffer>,
+    multicast_group: Option<IpAddr>,
+    parameter_sets: Vec<Vec<u8>>,
+    clock_rate: Option<u32>,
+    audio_specific_config: Option<Vec<u8>>,
+    retransmission_payload_type: Option<u8>,
+    // Retransmission payload type of the session description, used if there is a jitter buffer.
+    offered_retransmission_payload_type: Option<u8>,
+    on_network_statistics: Option<(Duration, NetworkStatisticsCallback)>,
+}
+
+impl RtpReceiverBuilder {
+    /// Create a new [`RtpReceiverBuilder`].
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Local address to bind the UDP socket to, e.g. `0.0.0.0:5004`.
+    /// * `codec` - Codec of the RTP payload.
+    pub fn new(address: SocketAddr, codec: RtpCodec) -> Self {
+        Self {
+            address,
+            codec,
+            payload_type: None,
+            interrupt: None,
+            rtcp_address: None,
+            receiver_report_interval: Some(RtpReceiver::DEFAULT_RECEIVER_REPORT_INTERVAL),
+            jitter_buffer: None,
+            multicast_group: None,
+            parameter_sets: Vec::new(),
+            clock_rate: None,
+            audio_specific_config: None,
+            retransmission_payload_type: None,
+            offered_retransmission_payload_type: None,
+            on_network_statistics: None,
+        }
+    }
+
+    /// Create a new [`RtpReceiverBuilder`] from a session description, such as an SDP file
+    /// published by the sender.
+    ///
+    /// The first H.264, H.265, VP8, VP9 or AV1 stream in the description is received. The
+    /// receiver binds to the port of the stream, joins the multicast group if the connection
+    /// address is a multicast address, accepts only the payload type of the stream, and receives
+    /// RTCP on the port given by the description (the RTP port plus one by default). H.264 and
+    /// H.265 parameter sets given in the format parameters are passed on in front of the first
+    /// access unit, so that decoding can start before the sender repeats them in the stream. If the
+    /// stream has a retransmission payload type and a jitter buffer is configured (see
+    /// [`RtpReceiverBuilder::with_jitter_buffer`]), lost packets are requested from the sender.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_description` - Description of the session to receive.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let session_description = std::fs::read_to_string("stream.sdp")?.parse()?;
+    /// let receiver = RtpReceiverBuilder::from_session_description(&session_description)?
+    ///     .with_jitter_buffer(JitterBuffer::default())
+    ///     .build()?;
+    /// ```
+    pub fn from_session_description(session_description: &SessionDescription) -> Result<Self> {
+        Self::from_session_description_of_kind(session_description, MediaKind::Video)
+    }
+
+    /// Create a new [`RtpReceiverBuilder`] for the first supported stream of a kind of media in a
+    /// session description. A session with both audio and video is received with a receiver for
+    /// each of them. See [`RtpReceiverBuilder::from_session_description`].
+    ///
+    /// Supported audio streams are Opus, AAC in AAC-hbr mode (`MPEG4-GENERIC`) and AAC in the
+    /// `MP4A-LATM` format with out of band configuration (`cpresent=0`). The audio specific config
+    /// of AAC streams is taken from the `config` format parameter.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_description` - Description of the session to receive.
+    /// * `kind` - Kind of media to receive.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let session_description = std::fs::read_to_string("stream.sdp")?.parse()?;
+    /// let video = RtpReceiverBuilder::from_session_description(&session_description)?.build()?;
+    /// let audio = RtpReceiverBuilder::from_session_description_of_kind(
+    ///     &session_description,
+    ///     MediaKind::Audio,
+    /// )?
+    /// .build()?;
+    /// ```
+    pub fn from_session_description_of_kind(
+        session_description: &SessionDescription,
+        kind: MediaKind,
+    ) -> Result<Self> {
+        let (media, codec) = session_description
+            .media
+            .iter()
+            .filter(|media| media.kind == kind)
+            .find_map(|media| {
+                let codec = match media.encoding_name.to_ascii_uppercase().as_str() {
+                    "H264" => RtpCodec::H264,
+                    "H265" => RtpCodec::H265,
+                    "VP8" => RtpCodec::Vp8,
+                    "VP9" => RtpCodec::Vp9,
+                    "AV1" => RtpCodec::Av1,
+                    "OPUS" => RtpCodec::Opus,
+                    "MPEG4-GENERIC"
+                        if media
+                            .format_parameter("mode")
+                            .is_some_and(|mode| mode.eq_ignore_ascii_case("AAC-hbr")) =>
+                    {
+                        RtpCodec::Aac
+                    }
+                    "MP4A-LATM" => RtpCodec::AacLatm,
+                    _ => return None,
+                };
+                Some((media, codec))
+            })
+            .ok_or_else(|| {
+                Error::InvalidSessionDescription(format!("no supported {} stream", kind.as_str()))
+            })?;
+        let clock_rate = match codec {
+            // The clock rate of AAC is the sample rate.
+            RtpCodec::Aac | RtpCodec::AacLatm => media.clock_rate,
+            RtpCodec::Opus => RtpReceiver::OPUS_CLOCK_RATE,
+            _ => RtpReceiver::CLOCK_RATE as u32,
+        };
+        if media.clock_rate != clock_rate {
+            return Err(Error::InvalidSessionDescription(format!(
+                "unexpected clock rate {} for {} stream",
+                media.clock_rate,
+                kind.as_str()
+            )));
+        }
+        if media.format_parameter("packetization-mode") == Some("2") {
+            return Err(Error::UnsupportedCodec(
+                "H.264 interleaved packetization mode".to_string(),
+            ));
+        }
+
+        let connection_address = session_description.connection_address;
+        let local_address: IpAddr = match connection_address {
+            IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            IpAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        let mut builder = Self::new(SocketAddr::new(local_address, media.port), codec)
+            .with_payload_type(media.payload_type)
+            .with_clock_rate(clock_rate);
+        if connection_address.is_multicast() {
+            builder = builder.with_multicast_group(connection_address);
+        }
+        builder.offered_retransmission_payload_type = media.retransmission_payload_type;
+        match media.rtcp_port {
+            Some(rtcp_port) if rtcp_port == media.port => {}
+            rtcp_port => {
+                let rtcp_port = rtcp_port.unwrap_or(media.port.wrapping_add(1));
+                builder = builder.with_rtcp_address(SocketAddr::new(local_address, rtcp_port));
+            }
+        }
+
+        let parameter_set_names: &[&str] = match codec {
+            RtpCodec::H264 => &["sprop-parameter-sets"],
+            RtpCodec::H265 => &["sprop-vps", "sprop-sps", "sprop-pps"],
+            _ => &[],
+        };
+        builder.parameter_sets = parameter_set_names
+            .iter()
+            .filter_map(|name| media.format_parameter(name))
+            .flat_map(|parameter_sets| parameter_sets.split(','))
+            .filter(|parameter_set| !parameter_set.is_empty())
+            .map(|parameter_set| {
+                base64_decode(parameter_set).ok_or_else(|| {
+                    Error::InvalidSessionDescription(format!(
+                        "invalid parameter set: {parameter_set}"
+                    ))
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let config = media
+            .format_parameter("config")
+            .map(|config| {
+                hex_decode(config).ok_or_else(|| {
+                    Error::InvalidSessionDescription(format!("invalid config: {config}"))
+                })
+            })
+            .transpose()?;
+        builder.audio_specific_config = match codec {
+            RtpCodec::Aac => config,
+            RtpCodec::AacLatm => {
+                // The configuration is in band unless signaled otherwise.
+                if media.format_parameter("cpresent") != Some("0") {
+                    return Err(Error::UnsupportedCodec(
+                        "AAC LATM with in-band configuration".to_string(),
+                    ));
+                }
+                config
+                    .as_deref()
+                    .and_then(audio_specific_config_from_stream_mux_config)
+            }
+            _ => None,
+        };
+        Ok(builder)
+    }
+
+    /// Only accept packets with this payload type, and ignore all others. By default, packets of
+    /// any payload type are accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload_type` - Dynamic payload type of the stream, usually 96 or higher.
+    pub fn with_payload_type(mut self, payload_type: u8) -> Self {
+        self.payload_type = Some(payload_type);
+        self
+    }
+
+    /// Make receiving interruptible from another thread. Without an interrupt, a sender that stops
+    /// sending blocks the receiver indefinitely.
+    ///
+    /// # Arguments
+    ///
+    /// * `interrupt` - Interrupt to poll while waiting for packets.
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Receive RTCP on a separate socket, usually bound to the RTP port plus one. By default, RTCP
+    /// is expected on the same socket as RTP (RFC 5761).
+    ///
+    /// # Arguments
+    ///
+    /// * `rtcp_address` - Local address to bind the RTCP socket to, e.g. `0.0.0.0:5005`.
+    pub fn with_rtcp_address(mut self, rtcp_address: SocketAddr) -> Self {
+        self.rtcp_address = Some(rtcp_address);
+        self
+    }
+
+    /// Join a multicast group on the RTP (and RTCP) socket, to receive a stream that is sent to a
+    /// multicast address. The sockets must be bound to the unspecified address, or to the group
+    /// address itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `multicast_group` - Multicast address the stream is sent to, e.g. `239.0.0.1`.
+    pub fn with_multicast_group(mut self, multicast_group: IpAddr) -> Self {
+        self.multicast_group = Some(multicast_group);
+        self
+    }
+
+    /// Set the parameter sets (SPS, PPS and for H.265 VPS) of the stream, without start codes.
+    /// They are passed on in Annex B format in front of the first access unit, for senders that
+    /// only signal them out of band.
+    ///
+    /// # Arguments
+    ///
+    /// * `parameter_sets` - Parameter set NAL units.
+    pub fn with_parameter_sets(mut self, parameter_sets: Vec<Vec<u8>>) -> Self {
+        self.parameter_sets = parameter_sets;
+        self
+    }
+
+    /// Set the clock rate of the RTP timestamps. By default, this is 90 kHz for video and 48 kHz
+    /// for Opus. For AAC, the clock rate is the sample rate of the stream, and it must be set.
+    ///
+    /// # Arguments
+    ///
+    /// * `clock_rate` - Clock rate in Hz.
+    pub fn with_clock_rate(mut self, clock_rate: u32) -> Self {
+        self.clock_rate = Some(clock_rate);
+        self
+    }
+
+    /// Set the audio specific config (ISO/IEC 14496-3) of an AAC stream, as found in the `config`
+    /// format parameter of an `MPEG4-GENERIC` stream. It is required to turn the receiver into a
+    /// reader with [`RtpReceiver::into_reader`].
+    ///
+    /// # Arguments
+    ///
+    /// * `audio_specific_config` - Audio specific config.
+    pub fn with_audio_specific_config(mut self, audio_specific_config: Vec<u8>) -> Self {
+        self.audio_specific_config = Some(audio_specific_config);
+        self
+    }
+
+    /// Set how often receiver reports are sent back to the sender, or `None` to not send any.
+    /// Defaults to every 5 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver_report_interval` - Interval between receiver reports.
+    pub fn with_receiver_report_interval(
+        mut self,
+        receiver_report_interval: Option<Duration>,
+    ) -> Self {
+        self.receiver_report_interval = receiver_report_interval;
+        self
+    }
+
+    /// Pass received packets through a jitter buffer, which puts packets that arrive out of order
+    /// back in order at the cost of some latency. Loss is concealed by dropping access units that
+    /// are damaged by lost packets, and the ones that depend on them up to the next keyframe, so
+    /// that the decoder repeats the last intact frame instead of showing artifacts.
+    ///
+    /// Without a jitter buffer, packets are depacketized as they arrive and damaged access units
+    /// are passed on, marked as corrupt.
+    ///
+    /// # Arguments
+    ///
+    /// * `jitter_buffer` - Jitter buffer settings.
+    pub fn with_jitter_buffer(mut self, jitter_buffer: JitterBuffer) -> Self {
+        self.jitter_buffer = Some(jitter_buffer);
+        self
+    }
+
+    /// Request packets that are lost from the sender with RTCP NACKs, and accept their
+    /// retransmissions in a separate stream with their own payload type (RFC 4588). Lost packets
+    /// are requested until they arrive or the jitter buffer gives up on them, so this requires a
+    /// jitter buffer, see [`RtpReceiverBuilder::with_jitter_buffer`]. Retransmissions do not count
+    /// towards the reception statistics, so that these still show the loss of the network.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload_type` - Dynamic payload type of retransmissions.
+    pub fn with_retransmission(mut self, payload_type: u8) -> Self {
+        self.retransmission_payload_type = Some(payload_type);
+        self
+    }
+
+    /// Periodically report the throughput of the stream, the packet loss and the delay of the
+    /// jitter buffer, e.g. to show the health of the connection. Reports are made while receiving,
+    /// on the thread that receives, so the callback should not block for long. See
+    /// [`RtpReceiver::network_statistics`].
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How often to report.
+    /// * `callback` - Callback that receives the statistics.
+    pub fn with_on_network_statistics(
+        mut self,
+        interval: Duration,
+        callback: impl FnMut(&NetworkStatistics) + Send + 'static,
+    ) -> Self {
+        self.on_network_statistics = Some((interval, Box::new(callback)));
+        self
+    }
+
+    /// Pass received packets through a jitter buffer with the settings of a latency profile. See
+    /// [`RtpReceiverBuilder::with_jitter_buffer`].
+    ///
+    /// # Arguments
+    ///
+    /// * `latency` - Latency profile.
+    pub fn with_latency(self, latency: LatencyProfile) -> Self {
+        self.with_jitter_buffer(latency.jitter_buffer())
+    }
+
+    /// Build [`RtpReceiver`].
+    pub fn build(mut self) -> Result<RtpReceiver> {
+        if let Some(jitter_buffer) = &self.jitter_buffer {
+            if jitter_buffer.max_delay < jitter_buffer.target_delay {
+                return Err(Error::InvalidConfiguration {
+                    field: "jitter_buffer",
+                    reason: "maximum delay must not be less than the target delay".to_string(),
+                });
+            }
+        }
+        // Retransmissions offered by the session description are only requested when lost
+        // packets can wait for them in a jitter buffer.
+        if self.retransmission_payload_type.is_none() && self.jitter_buffer.is_some() {
+            self.retransmission_payload_type = self.offered_retransmission_payload_type;
+        }
+        if self.retransmission_payload_type.is_some() && self.jitter_buffer.is_none() {
+            return Err(Error::InvalidConfiguration {
+                field: "retransmission_payload_type",
+                reason: "retransmission requires a jitter buffer".to_string(),
+            });
+        }
+        let clock_rate = match (self.clock_rate, self.codec) {
+            (Some(0), _) => {
+                return Err(Error::InvalidConfiguration {
+                    field: "clock_rate",
+                    reason: "clock rate must not be zero".to_string(),
+                })
+            }
+            (Some(clock_rate), _) => clock_rate,
+            (None, RtpCodec::Aac | RtpCodec::AacLatm) => {
+                return Err(Error::InvalidConfiguration {
+                    field: "clock_rate",
+                    reason: "clock rate of AAC streams must be set".to_string(),
+                })
+            }
+            (None, RtpCodec::Opus) => RtpReceiver::OPUS_CLOCK_RATE,
+            (None, _) => RtpReceiver::CLOCK_RATE as u32,
+        };
+        let socket = UdpSocket::bind(self.address)?;
+        let rtcp_socket = match self.rtcp_address {
+            Some(rtcp_address) => {
+                let rtcp_socket = UdpSocket::bind(rtcp_address)?;
+                rtcp_socket.set_nonblocking(true)?;
+                Some(rtcp_socket)
+            }
+            None => None,
+        };
+        if let Some(multicast_group) = self.multicast_group {
+            for socket in std::iter::once(&socket).chain(rtcp_socket.as_ref()) {
+                match multicast_group {
+                    IpAddr::V4(multicast_group) => {
+                        socket.join_multicast_v4(&multicast_group, &Ipv4Addr::UNSPECIFIED)?
+                    }
+                    IpAddr::V6(multicast_group) => socket.join_multicast_v6(&multicast_group, 0)?,
+                }
+            }
+        }
+        let mut network_monitor = NetworkMonitor::new();
+        if let Some((interval, callback)) = self.on_network_statistics {
+            network_monitor = network_monitor.with_callback(interval, callback);
+        }
+        let mut parameter_sets = Vec::new();
+        for parameter_set in &self.parameter_sets {
+            parameter_sets.extend_from_slice(&[0, 0, 0, 1]);
+            parameter_sets.extend_from_slice(parameter_set);
+        }
+        // Wake up regularly to check the interrupt, to handle RTCP while no RTP arrives, and to
+        // release packets from the jitter buffer.
+        if self.interrupt.is_some()
+            || rtcp_socket.is_some()
+            || self.receiver_report_interval.is_some()
+            || self.jitter_buffer.is_some()
+            || network_monitor.is_reporting()
+        {
+            socket.set_read_timeout(Some(RtpReceiver::POLL_INTERVAL))?;
+        }
+        Ok(RtpReceiver {
+            socket,
+            rtcp_socket,
+            codec: self.codec,
+            clock_rate,
+            payload_type: self.payload_type,
+            interrupt: self.interrupt,
+            depacketizer: Depacketizer::new(self.codec),
+            completed: VecDeque::new(),
+            timestamp: None,
+            ssrc: std::collections::hash_map::RandomState::new()
+                .build_hasher()
+                .finish() as u32,
+            tracker: None,
+            sender_report: None,
+            rtp_source: None,
+            rtcp_source: None,
+            receiver_report_interval: self.receiver_report_interval,
+            last_receiver_report: Instant::now(),
+            jitter_buffer: self.jitter_buffer,
+            jitter_queue: self
+                .jitter_buffer
+                .map(|jitter_buffer| JitterQueue::new(jitter_buffer, clock_rate)),
+            retransmission_payload_type: self.retransmission_payload_type,
+            nack_tracker: None,
+            awaiting_keyframe: false,
+            parameter_sets,
+            audio_specific_config: self.audio_specific_config,
+            network_monitor,
+            buf: vec![0; RtpReceiver::MAX_DATAGRAM_SIZE],
+        })
+    }
+}
+
+/// Receives a video or audio stream over RTP and reassembles the RTP payloads into packets.
+///
+/// Every packet holds one access unit: NAL units in Annex B format for H.264 and H.265, a frame
+/// for VP8, VP9 and audio codecs, or a temporal unit for AV1. Timestamps are in the RTP clock,
+/// which is 90 kHz for video, and start at zero. The packets can be fed to a [`Muxer`], or the
+/// receiver can be turned into a [`Reader`] with [`RtpReceiver::into_reader`] so that the stream
+/// can be decoded.
+///
+/// The receiver tracks packet loss and jitter, and periodically sends RTCP receiver reports to the
+/// sender. Sender reports from the sender are used to map timestamps to wall clock time.
+///
+/// # Example
+///
+/// ```ignore
+/// let receiver = RtpReceiver::new("0.0.0.0:5004".parse().unwrap(), RtpCodec::H264).unwrap();
+/// let reader = receiver.into_reader().unwrap();
+/// let stream_index = reader.best_video_stream_index().unwrap();
+/// let decoder =
+///     DecoderSplit::new_with_latency(&reader, stream_index, None, None, LatencyProfile::Low)
+///         .unwrap();
+/// ```
+pub struct RtpReceiver {
+    socket: UdpSocket,
+    rtcp_socket: Option<UdpSocket>,
+    codec: RtpCodec,
+    clock_rate: u32,
+    payload_type: Option<u8>,
+    interrupt: Option<Interrupt>,
+    depacketizer: Depacketizer,
+    // Access units that were completed but not yet returned.
+    completed: VecDeque<AccessUnit>,
+    // RTP timestamp of the previous access unit, and its unwrapped value relative to the first.
+    timestamp: Option<(u32, i64)>,
+    // SSRC of the receiver in receiver reports.
+    ssrc: u32,
+    // Reception statistics of the sender.
+    tracker: Option<ReceptionTracker>,
+    // Last sender report of the sender.
+    sender_report: Option<SenderReport>,
+    // Addresses that RTP and RTCP were last received from.
+    rtp_source: Option<SocketAddr>,
+    rtcp_source: Option<SocketAddr>,
+    receiver_report_interval: Option<Duration>,
+    last_receiver_report: Instant,
+    jitter_buffer: Option<JitterBuffer>,
+    jitter_queue: Option<JitterQueue<(RtpHeader, Vec<u8>)>>,
+    retransmission_payload_type: Option<u8>,
+    // Packets to request from the sender, if retransmission is enabled.
+    nack_tracker: Option<NackTracker>,
+    // Whether access units are dropped until the next keyframe to conceal packet loss.
+    awaiting_keyframe: bool,
+    // Parameter sets in Annex B format to put in front of the first access unit, if any.
+    parameter_sets: Vec<u8>,
+    audio_specific_config: Option<Vec<u8>>,
+    network_monitor: NetworkMonitor,
+    buf: Vec<u8>,
+}
+
+impl RtpReceiver {
+    /// Largest possible UDP datagram.
+    const MAX_DATAGRAM_SIZE: usize = 65536;
+
+    /// How often to check the interrupt and handle RTCP while waiting for packets.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// Default interval between receiver reports.
+    const DEFAULT_RECEIVER_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// Clock rate of video RTP timestamps.
+    const CLOCK_RATE: i32 = 90_000;
+
+    /// Clock rate of Opus RTP timestamps, whatever the sample rate of the stream.
+    const OPUS_CLOCK_RATE: u32 = 48_000;
+
+    /// Create a new [`RtpReceiver`] that accepts packets of any payload type.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Local address to bind the UDP socket to, e.g. `0.0.0.0:5004`.
+    /// * `codec` - Codec of the RTP payload.
+    #[inline]
+    pub fn new(address: SocketAddr, codec: RtpCodec) -> Result<RtpReceiver> {
+        RtpReceiverBuilder::new(address, codec).build()
+    }
+
+    /// Get the local address the receiver is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Get the codec of the RTP payload.
+    pub fn codec(&self) -> RtpCodec {
+        self.codec
+    }
+
+    /// Get the time base of the received packets.
+    pub fn time_base(&self) -> AvRational {
+        AvRational::new(1, self.clock_rate as i32)
+    }
+
+    /// Get the audio specific config of an AAC stream, which decoders need as extradata to decode
+    /// the received packets.
+    pub fn audio_specific_config(&self) -> Option<&[u8]> {
+        self.audio_specific_config.as_deref()
+    }
+
+    /// Receive the next access unit. Blocks until a complete access unit has arrived.
+    ///
+    /// Packets that are not valid RTP packets, RTCP packets and packets with another payload type
+    /// are ignored.
+    pub fn receive(&mut self) -> Result<Packet> {
+        let access_unit = self.receive_access_unit()?;
+        Ok(self.packet_from(access_unit))
+    }
+
+    /// Get statistics about the received stream, such as packet loss and jitter. Returns `None`
+    /// until the first packet has been received.
+    pub fn statistics(&self) -> Option<ReceptionStatistics> {
+        self.tracker.as_ref().map(ReceptionTracker::statistics)
+    }
+
+    /// Get the current delay of the jitter buffer, or `None` if there is no jitter buffer. With
+    /// [`LatePacketPolicy::Adapt`](crate::core::jitter::LatePacketPolicy::Adapt) the delay grows
+    /// beyond the target delay when packets arrive late.
+    pub fn jitter_buffer_delay(&self) -> Option<Duration> {
+        self.jitter_queue.as_ref().map(JitterQueue::delay)
+    }
+
+    /// Get the last RTCP sender report received from the sender.
+    pub fn last_sender_report(&self) -> Option<&SenderReport> {
+        self.sender_report.as_ref()
+    }
+
+    /// Get the amount of data received and the current throughput, along with the packet loss
+    /// and the delay of the jitter buffer. The round trip time is not known to receivers.
+    pub fn network_statistics(&self) -> NetworkStatistics {
+        let reception_statistics = self.statistics();
+        NetworkStatistics {
+            packets_lost: reception_statistics
+                .as_ref()
+                .map(|reception_statistics| reception_statistics.packets_lost),
+            fraction_lost: reception_statistics
+                .map(|reception_statistics| reception_statistics.fraction_lost),
+            buffer_depth: self.jitter_buffer_delay(),
+            ..self.network_monitor.statistics(Instant::now())
+        }
+    }
+
+    /// Map the timestamp of a received packet to the wall clock time of the sender at which it
+    /// was captured. Returns `None` until a sender report has been received.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - Timestamp of a packet returned by [`RtpReceiver::receive`].
+    pub fn wallclock_time(&self, timestamp: Time) -> Option<SystemTime> {
+        let pts = timestamp
+            .aligned_with_rational(self.time_base())
+            .into_value()?;
+        let (previous, previous_pts) = self.timestamp?;
+        let rtp_timestamp = previous.wrapping_add((pts - previous_pts) as u32);
+        let sender_report = self.sender_report.as_ref()?;
+        Some(
+            sender_report
+                .ntp_time_of(rtp_timestamp, self.clock_rate)
+                .into(),
+        )
+    }
+
+    /// Turn the receiver into a [`Reader`] that reads the received stream, so that it can be
+    /// decoded with a [`DecoderSplit`](crate::core::decode::DecoderSplit). The reader is not
+    /// seekable. Stream parameters are detected from the parameter sets in the stream, so
+    /// decoding starts once the sender has sent them. H.264 and H.265 are read as an Annex B byte
+    /// stream, VP8, VP9 and AV1 as an IVF stream, Opus as an Ogg stream, and AAC as an ADTS stream.
+    ///
+    /// AAC streams can only be read if their audio specific config is known, see
+    /// [`RtpReceiverBuilder::with_audio_specific_config`]. Use
+    /// [`Reader::best_audio_stream_index`] to find the stream of an audio receiver.
+    pub fn into_reader(self) -> Result<Reader> {
+        let interrupt = self.interrupt.clone();
+        let mut reader_builder = ReaderBuilder::from_read(AccessUnitSource::new(self)?);
+        if let Some(interrupt) = interrupt {
+            reader_builder = reader_builder.with_interrupt(interrupt);
+        }
+        reader_builder.build()
+    }
+
+    /// Receive the next access unit.
+    fn receive_access_unit(&mut self) -> Result<AccessUnit> {
+        loop {
+            if let Some(mut access_unit) = self.completed.pop_front() {
+                if self.jitter_queue.is_some() && self.conceal_loss(&access_unit) {
+                    continue;
+                }
+                if !self.parameter_sets.is_empty() {
+                    let mut data = std::mem::take(&mut self.parameter_sets);
+                    data.append(&mut access_unit.data);
+                    access_unit.data = data;
+                }
+                return Ok(access_unit);
+            }
+
+            self.receive_rtcp();
+            self.send_receiver_report();
+            self.send_nack();
+            let now = Instant::now();
+            if self.network_monitor.is_due(now) {
+                let statistics = self.network_statistics();
+                self.network_monitor.report(now, &statistics);
+            }
+
+            if let Some(jitter_queue) = self.jitter_queue.as_mut() {
+                while let Some((header, payload)) = jitter_queue.pop(Instant::now()) {
+                    self.completed
+                        .extend(self.depacketizer.push(&header, &payload));
+                }
+                if !self.completed.is_empty() {
+                    continue;
+                }
+                let timeout = jitter_queue
+                    .next_release()
+                    .map(|next_release| next_release.saturating_duration_since(Instant::now()))
+                    .unwrap_or(Self::POLL_INTERVAL)
+                    .clamp(Duration::from_millis(1), Self::POLL_INTERVAL);
+                self.socket.set_read_timeout(Some(timeout))?;
+            }
+
+            let (size, source) = match self.socket.recv_from(&mut self.buf) {
+                Ok(received) => received,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    if self
+                        .interrupt
+                        .as_ref()
+                        .is_some_and(Interrupt::is_interrupted)
+                    {
+                        return Err(Error::Interrupted);
+                    }
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let arrival = Instant::now();
+
+            let Some((header, payload)) = RtpHeader::parse(&self.buf[..size]) else {
+                tracing::debug!("ignoring datagram that is not an RTP packet");
+                continue;
+            };
+            if header.is_rtcp() {
+                let packets = RtcpPacket::parse_compound(&self.buf[..size]);
+                self.handle_rtcp(packets, source, arrival);
+                continue;
+            }
+            self.network_monitor.record(size, arrival);
+            if self.retransmission_payload_type == Some(header.payload_type) {
+                self.handle_retransmission(header, payload, arrival);
+                continue;
+            }
+            if self
+                .payload_type
+                .is_some_and(|payload_type| payload_type != header.payload_type)
+            {
+                continue;
+            }
+            self.rtp_source = Some(source);
+            match self.tracker.as_mut() {
+                Some(tracker) if tracker.ssrc() == header.ssrc => {
+                    tracker.update(header.sequence_number, header.timestamp, arrival)
+                }
+                _ => {
+                    let mut tracker =
+                        ReceptionTracker::new(header.ssrc, header.sequence_number, self.clock_rate);
+                    tracker.update(header.sequence_number, header.timestamp, arrival);
+                    self.tracker = Some(tracker);
+                    // A new sender starts a new sequence.
+                    self.jitter_queue = self
+                        .jitter_buffer
+                        .map(|jitter_buffer| JitterQueue::new(jitter_buffer, self.clock_rate));
+                    self.nack_tracker = self
+                        .jitter_buffer
+                        .filter(|_| self.retransmission_payload_type.is_some())
+                        .map(|jitter_buffer| NackTracker::new(jitter_buffer.max_delay));
+                }
+            }
+            if let Some(nack_tracker) = self.nack_tracker.as_mut() {
+                nack_tracker.update(header.sequence_number, arrival);
+            }
+            match self.jitter_queue.as_mut() {
+                Some(jitter_queue) => jitter_queue.push(
+                    header.sequence_number,
+                    header.timestamp,
+                    arrival,
+                    (header, payload.to_vec()),
+                ),
+                None => self
+                    .completed
+                    .extend(self.depacketizer.push(&header, payload)),
+            }
+        }
+    }
+
+    /// Whether an access unit must be dropped to conceal packet loss. After an access unit that is
+    /// damaged by loss, access units are dropped until the next intact keyframe, since they may
+    /// refer to the damaged one.
+    ///
+    /// # Arguments
+    ///
+    /// * `access_unit` - Access unit to check.
+    fn conceal_loss(&mut self, access_unit: &AccessUnit) -> bool {
+        if access_unit.is_corrupt {
+            self.awaiting_keyframe = true;
+        } else if access_unit.is_key {
+            self.awaiting_keyframe = false;
+        }
+        if self.awaiting_keyframe {
+            tracing::debug!("dropping access unit to conceal packet loss");
+        }
+        self.awaiting_keyframe
+    }
+
+    /// Queue a retransmitted packet (RFC 4588) in the jitter buffer in place of the lost original.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - Header of the retransmission.
+    /// * `payload` - Payload of the retransmission: the original sequence number followed by the
+    ///   original payload.
+    /// * `arrival` - When the retransmission arrived.
+    fn handle_retransmission(&mut self, header: RtpHeader, payload: &[u8], arrival: Instant) {
+        let (Some(tracker), Some(jitter_queue), Some(nack_tracker)) = (
+            self.tracker.as_ref(),
+            self.jitter_queue.as_mut(),
+            self.nack_tracker.as_mut(),
+        ) else {
+            return;
+        };
+        let Some((sequence_number, payload)) = payload.split_first_chunk::<2>() else {
+            tracing::debug!("ignoring RTP retransmission without original sequence number");
+            return;
+        };
+        let original = RtpHeader {
+            payload_type: self.payload_type.unwrap_or(header.payload_type),
+            sequence_number: u16::from_be_bytes(*sequence_number),
+            ssrc: tracker.ssrc(),
+            ..header
+        };
+        nack_tracker.update(original.sequence_number, arrival);
+        jitter_queue.push_retransmission(
+            original.sequence_number,
+            original.timestamp,
+            arrival,
+            (original, payload.to_vec()),
+        );
+    }
+
+    /// Handle the RTCP packets that arrived on the RTCP socket, if there is one.
+    fn receive_rtcp(&mut self) {
+        loop {
+            let Some(rtcp_socket) = self.rtcp_socket.as_ref() else {
+                return;
+            };
+            match rtcp_socket.recv_from(&mut self.buf) {
+                Ok((size, source)) => {
+                    let packets = RtcpPacket::parse_compound(&self.buf[..size]);
+                    self.handle_rtcp(packets, source, Instant::now());
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return,
+                Err(err) => {
+                    tracing::warn!("failed to receive RTCP: {err}");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Keep track of the sender reports of the sender.
+    ///
+    /// # Arguments
+    ///
+    /// * `packets` - Received RTCP packets.
+    /// * `source` - Address the packets were received from.
+    /// * `arrival` - When the packets arrived.
+    fn handle_rtcp(&mut self, packets: Vec<RtcpPacket>, source: SocketAddr, arrival: Instant) {
+        self.rtcp_source = Some(source);
+        for packet in packets {
+            if let RtcpPacket::SenderReport(sender_report) = packet {
+                match self.tracker.as_mut() {
+                    Some(tracker) if tracker.ssrc() == sender_report.ssrc => {
+                        tracker.update_sender_report(&sender_report, arrival)
+                    }
+                    _ => continue,
+                }
+                self.sender_report = Some(sender_report);
+            }
+        }
+    }
+
+    /// Send a receiver report to the sender if one is due.
+    fn send_receiver_report(&mut self) {
+        let Some(receiver_report_interval) = self.receiver_report_interval else {
+            return;
+        };
+        let now = Instant::now();
+        if now.duration_since(self.last_receiver_report) < receiver_report_interval {
+            return;
+        }
+        let Some(destination) = self.rtcp_destination() else {
+            return;
+        };
+        let Some(tracker) = self.tracker.as_mut() else {
+            return;
+        };
+        self.last_receiver_report = now;
+
+        let mut buf = Vec::new();
+        ReceiverReport {
+            ssrc: self.ssrc,
+            report_blocks: vec![tracker.report_block(now)],
+        }
+        .write(&mut buf);
+        rtcp::write_source_description(&mut buf, self.ssrc, &format!("{:08x}", self.ssrc));
+        let socket = self.rtcp_socket.as_ref().unwrap_or(&self.socket);
+        if let Err(err) = socket.send_to(&buf, destination) {
+            tracing::warn!("failed to send RTCP receiver report: {err}");
+        }
+    }
+
+    /// Request the retransmission of lost packets from the sender, if any are due.
+    ///
+    /// The NACK is sent in a compound packet behind an empty receiver report, to the same address
+    /// as receiver reports.
+    fn send_nack(&mut self) {
+        let (Some(nack_tracker), Some(tracker)) = (self.nack_tracker.as_mut(), &self.tracker)
+        else {
+            return;
+        };
+        let lost = nack_tracker.poll(Instant::now());
+        if lost.is_empty() {
+            return;
+        }
+        let media_ssrc = tracker.ssrc();
+        let Some(destination) = self.rtcp_destination() else {
+            return;
+        };
+
+        let mut buf = Vec::new();
+        ReceiverReport {
+            ssrc: self.ssrc,
+            report_blocks: Vec::new(),
+        }
+        .write(&mut buf);
+        rtcp::write_source_description(&mut buf, self.ssrc, &format!("{:08x}", self.ssrc));
+        GenericNack {
+            sender_ssrc: self.ssrc,
+            media_ssrc,
+            lost,
+        }
+        .write(&mut buf);
+        let socket = self.rtcp_socket.as_ref().unwrap_or(&self.socket);
+        if let Err(err) = socket.send_to(&buf, destination) {
+            tracing::warn!("failed to send RTCP NACK: {err}");
+        }
+    }
+
+    /// Address to send RTCP to: the address RTCP was last received from or, if the sender has not
+    /// sent any RTCP, the address RTP is received from (or the port after it, when RTCP is
+    /// received on a separate socket).
+    fn rtcp_destination(&self) -> Option<SocketAddr> {
+        self.rtcp_source.or_else(|| {
+            let mut destination = self.rtp_source?;
+            if self.rtcp_socket.is_some() {
+                destination.set_port(destination.port().wrapping_add(1));
+            }
+            Some(destination)
+        })
+    }
+
+    /// Convert an access unit to a packet, timestamped relative to the first access unit.
+    ///
+    /// # Arguments
+    ///
+    /// * `access_unit` - Access unit to convert.
+    fn packet_from(&mut self, access_unit: AccessUnit) -> Packet {
+        let pts = self.unwrap_timestamp(access_unit.timestamp);
+        let mut packet = AvPacket::copy(&access_unit.data);
+        packet.set_pts(Some(pts));
+        packet.set_dts(None);
+        let mut flags = AvPacketFlags::empty();
+        flags.set(AvPacketFlags::KEY, access_unit.is_key);
+        flags.set(AvPacketFlags::CORRUPT, access_unit.is_corrupt);
+        packet.set_flags(flags);
+        Packet::new(packet, self.time_base())
+    }
+
+    /// Convert an RTP timestamp to a timestamp relative to the first access unit.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - RTP timestamp of the next access unit.
+    fn unwrap_timestamp(&mut self, timestamp: u32) -> i64 {
+        let pts = match self.timestamp {
+            // The difference is interpreted as signed, so that the RTP timestamp can wrap around.
+            Some((previous, previous_pts)) => {
+                previous_pts + timestamp.wrapping_sub(previous) as i32 as i64
+            }
+            None => 0,
+        };
+        self.timestamp = Some((timestamp, pts));
+        pts
+    }
+}
+
+unsafe impl Send for RtpReceiver {}
+unsafe impl Sync for RtpReceiver {}
+
+/// Source that reads the access units of an [`RtpReceiver`] as a byte stream: an Annex B byte
+/// stream for H.264 and H.265, an IVF stream for VP8, VP9 and AV1, an Ogg stream for Opus, or an
+/// ADTS stream for AAC.
+struct AccessUnitSource {
+    receiver: RtpReceiver,
+    // Data that has not been read completely.
+    pending: VecDeque<u8>,
+    // Sequence number of the next Ogg page.
+    page_sequence_number: u32,
+    // Header of ADTS frames, without the frame length.
+    adts_header: Option<[u8; 7]>,
+}
+
+impl AccessUnitSource {
+    /// Create a source for a receiver, starting with the headers of the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver` - Receiver to read access units from.
+    fn new(receiver: RtpReceiver) -> Result<Self> {
+        let adts_header = match receiver.codec {
+            RtpCodec::Aac | RtpCodec::AacLatm => Some(
+                receiver
+                    .audio_specific_config
+                    .as_deref()
+                    .and_then(Self::adts_header)
+                    .ok_or_else(|| Error::InvalidConfiguration {
+                        field: "audio_specific_config",
+                        reason: "AAC streams require an audio specific config of an AAC object \
+                                 type that ADTS can carry"
+                            .to_string(),
+                    })?,
+            ),
+            _ => None,
+        };
+        let mut source = Self {
+            receiver,
+            pending: VecDeque::new(),
+            page_sequence_number: 0,
+            adts_header,
+        };
+        match source.receiver.codec {
+            RtpCodec::Vp8 => source.pending = Self::ivf_header(b"VP80").into(),
+            RtpCodec::Vp9 => source.pending = Self::ivf_header(b"VP90").into(),
+            RtpCodec::Av1 => source.pending = Self::ivf_header(b"AV01").into(),
+            RtpCodec::Opus => {
+                // Identification header (RFC 7845). RTP always signals Opus as stereo.
+                let mut identification = b"OpusHead".to_vec();
+                identification.push(1); // Version
+                identification.push(2); // Channels
+                identification.extend_from_slice(&0u16.to_le_bytes()); // Pre-skip
+                identification.extend_from_slice(&RtpReceiver::OPUS_CLOCK_RATE.to_le_bytes());
+                identification.extend_from_slice(&0i16.to_le_bytes()); // Output gain
+                identification.push(0); // Channel mapping family
+                                        // Comment header without vendor string and comments.
+                let mut comments = b"OpusTags".to_vec();
+                comments.extend_from_slice(&[0; 8]);
+                source.pending = source.ogg_page(0x02, 0, &identification).into();
+                let comments = source.ogg_page(0x00, 0, &comments);
+                source.pending.extend(comments);
+            }
+            _ => {}
+        }
+        Ok(source)
+    }
+
+    /// Create the header of an IVF stream with the time base of RTP video timestamps. The frame
+    /// count and dimensions are left out, since the demuxer does not need them.
+    ///
+    /// # Arguments
+    ///
+    /// * `fourcc` - Codec of the stream.
+    fn ivf_header(fourcc: &[u8; 4]) -> Vec<u8> {
+        let mut header = Vec::with_capacity(32);
+        header.extend_from_slice(b"DKIF");
+        header.extend_from_slice(&0u16.to_le_bytes()); // Version
+        header.extend_from_slice(&32u16.to_le_bytes()); // Header size
+        header.extend_from_slice(fourcc);
+        header.extend_from_slice(&[0; 4]); // Width and height
+        header.extend_from_slice(&(RtpReceiver::CLOCK_RATE as u32).to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+        header.extend_from_slice(&[0; 8]); // Frame count and unused
+        header
+    }
+
+    /// Create the header of an ADTS frame (ISO/IEC 13818-7) without CRC, of which the frame length
+    /// is left zero. Returns `None` if the audio specific config is truncated, or if its object
+    /// type cannot be carried by ADTS.
+    ///
+    /// # Arguments
+    ///
+    /// * `audio_specific_config` - Audio specific config of the stream.
+    fn adts_header(audio_specific_config: &[u8]) -> Option<[u8; 7]> {
+        let &[first, second, ..] = audio_specific_config else {
+            return None;
+        };
+        let object_type = first >> 3;
+        let frequency_index = ((first & 0x07) << 1) | (second >> 7);
+        let channels = (second >> 3) & 0x0f;
+        // The profile takes two bits, and is the object type minus one.
+        if !(1..=4).contains(&object_type) || frequency_index > 12 {
+            return None;
+        }
+        Some([
+            0xff,
+            0xf1,
+            ((object_type - 1) << 6) | (frequency_index << 2) | (channels >> 2),
+            (channels & 0x03) << 6,
+            0x00,
+            0x1f,
+            0xfc,
+        ])
+    }
+
+    /// Create an Ogg page (RFC 3533) that holds a single packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `header_type` - Flags of the page, e.g. `0x02` for the first page of the stream.
+    /// * `granule_position` - Position of the end of the packet, in samples for Opus.
+    /// * `packet` - Packet to put in the page, which must be smaller than 65025 bytes.
+    fn ogg_page(&mut self, header_type: u8, granule_position: u64, packet: &[u8]) -> Vec<u8> {
+        let segments = packet.len() / 255 + 1;
+        let mut page = Vec::with_capacity(27 + segments + packet.len());
+        page.extend_from_slice(b"OggS");
+        page.push(0); // Version
+        page.push(header_type);
+        page.extend_from_slice(&granule_position.to_le_bytes());
+        page.extend_from_slice(&self.receiver.ssrc.to_le_bytes()); // Serial number
+        page.extend_from_slice(&self.page_sequence_number.to_le_bytes());
+        page.extend_from_slice(&[0; 4]); // Checksum
+        page.push(segments as u8);
+        page.extend(std::iter::repeat_n(255, segments - 1));
+        page.push((packet.len() % 255) as u8);
+        page.extend_from_slice(packet);
+        let checksum = ogg_checksum(&page);
+        page[22..26].copy_from_slice(&checksum.to_le_bytes());
+        self.page_sequence_number = self.page_sequence_number.wrapping_add(1);
+        page
+    }
+
+    /// Convert an access unit to the bytes of the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `access_unit` - Access unit to convert.
+    fn stream_data(&mut self, access_unit: AccessUnit) -> Vec<u8> {
+        match self.receiver.codec {
+            RtpCodec::H264 | RtpCodec::H265 => access_unit.data,
+            RtpCodec::Vp8 | RtpCodec::Vp9 | RtpCodec::Av1 => {
+                let pts = self.receiver.unwrap_timestamp(access_unit.timestamp);
+                let mut data = Vec::with_capacity(12 + access_unit.data.len());
+                data.extend_from_slice(&(access_unit.data.len() as u32).to_le_bytes());
+                data.extend_from_slice(&(pts as u64).to_le_bytes());
+                data.extend_from_slice(&access_unit.data);
+                data
+            }
+            RtpCodec::Opus => {
+                let pts = self.receiver.unwrap_timestamp(access_unit.timestamp);
+                let end = pts + opus_packet_samples(&access_unit.data) as i64;
+                self.ogg_page(0x00, end.max(0) as u64, &access_unit.data)
+            }
+            RtpCodec::Aac | RtpCodec::AacLatm => {
+                let frame_length = 7 + access_unit.data.len();
+                let mut data = self.adts_header.unwrap_or_default().to_vec();
+                data[3] |= ((frame_length >> 11) & 0x03) as u8;
+                data[4] = (frame_length >> 3) as u8;
+                data[5] |= ((frame_length & 0x07) << 5) as u8;
+                data.extend_from_slice(&access_unit.data);
+                data
+            }
+        }
+    }
+}
+
+impl std::io::Read for AccessUnitSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.receiver.receive_access_unit() {
+                Ok(access_unit) => self.pending = self.stream_data(access_unit).into(),
+                // The reader ends the stream when it is interrupted.
+                Err(Error::Interrupted) => return Ok(0),
+                Err(err) => return Err(std::io::Error::other(err)),
+            }
+        }
+        std::io::Read::read(&mut self.pending, buf)
+    }
+}
+
+/// Extract the audio specific config from the stream mux config of an `MP4A-LATM` stream
+/// (ISO/IEC 14496-3), in which it is not byte aligned. Only the first two bytes are extracted,
+/// which hold the object type, sample rate and channel configuration of common streams.
+///
+/// # Arguments
+///
+/// * `stream_mux_config` - Stream mux config, as found in the `config` format parameter.
+fn audio_specific_config_from_stream_mux_config(stream_mux_config: &[u8]) -> Option<Vec<u8>> {
+    let &[first, second, third, fourth, ..] = stream_mux_config else {
+        return None;
+    };
+    // Only version 0 has the audio specific config at a fixed position: after the version,
+    // framing, subframe, program and layer fields, which take 15 bits.
+    if first & 0x80 != 0 {
+        return None;
+    }
+    let bits = u32::from_be_bytes([first, second, third, fourth]);
+    Some(((bits >> 1) as u16).to_be_bytes().to_vec())
+}
+
+/// Get the number of samples at 48 kHz in an Opus packet, from its TOC byte (RFC 6716).
+///
+/// # Arguments
+///
+/// * `packet` - Opus packet.
+fn opus_packet_samples(packet: &[u8]) -> u64 {
+    let Some(&toc) = packet.first() else {
+        return 0;
+    };
+    let config = (toc >> 3) as usize;
+    let frame_size = match config {
+        // SILK
+        0..=11 => [480, 960, 1920, 2880][config % 4],
+        // Hybrid
+        12..=15 => [480, 960][config % 2],
+        // CELT
+        _ => [120, 240, 480, 960][config % 4],
+    };
+    let frames = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => packet.get(1).map_or(0, |count| count & 0x3f) as u64,
+    };
+    frame_size * frames
+}
+
+/// Compute the checksum of an Ogg page: a CRC-32 with polynomial `0x04c11db7`, without reflection
+/// or final XOR.
+///
+/// # Arguments
+///
+/// * `page` - Page with its checksum field set to zero.
+fn ogg_checksum(page: &[u8]) -> u32 {
+    page.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| {
+            if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
@@ -0,0 +1,831 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::net::{
+    IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket,
+};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use ffmpeg::media::Type as AvMediaType;
+use ffmpeg::Error as AvError;
+
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::io::Reader;
+use crate::core::packet::Packet;
+use crate::core::rtp::{RtpBuf, RtpMuxer, RtpMuxerBuilder};
+use crate::core::rtsp_message::{track_index, write_interleaved, Request, Response, TransportSpec};
+use crate::core::sdp::SessionDescription;
+use crate::core::stream::StreamInfo;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Build an [`RtspServer`].
+pub struct RtspServerBuilder {
+    address: SocketAddr,
+    path: String,
+    session_timeout: Duration,
+    // RTP muxer of each stream, with the index of the stream in the source.
+    muxers: Vec<(usize, RtpMuxer)>,
+}
+
+impl RtspServerBuilder {
+    /// Create a new [`RtspServerBuilder`].
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Local address to listen on for RTSP connections, e.g. `0.0.0.0:8554`.
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            path: "/stream".to_string(),
+            session_timeout: RtspServer::DEFAULT_SESSION_TIMEOUT,
+            muxers: Vec::new(),
+        }
+    }
+
+    /// Set the path the stream is served at. Defaults to `/stream`, so that clients play
+    /// `rtsp://<host>:<port>/stream`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the stream, starting with `/`.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = path.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set how long a session is kept without requests from its client. The timeout is announced to
+    /// clients, which send keep-alive requests well within it. Defaults to 60 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_timeout` - Time after the last request at which a session ends.
+    pub fn with_session_timeout(mut self, session_timeout: Duration) -> Self {
+        self.session_timeout = session_timeout;
+        self
+    }
+
+    /// Add a stream to serve based on an input stream from a reader.
+    ///
+    /// At least one stream must be added. Supported codecs are those of
+    /// [`RtpMuxer::session_description`].
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_info` - Stream information. Usually this information is retrieved by calling
+    ///   [`Reader::stream_info()`].
+    pub fn with_stream(mut self, stream_info: StreamInfo) -> Result<Self> {
+        let stream_index = stream_info.index;
+        let muxer = RtpMuxerBuilder::new()?.with_stream(stream_info)?.build()?;
+        self.muxers.push((stream_index, muxer));
+        Ok(self)
+    }
+
+    /// Add all video and audio streams of a reader. After calling this, it is safe to serve all
+    /// video and audio packets from the provided reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader to add streams from.
+    pub fn with_streams(mut self, reader: &Reader) -> Result<Self> {
+        for stream in reader.streams() {
+            if matches!(stream.medium, AvMediaType::Video | AvMediaType::Audio) {
+                self = self.with_stream(reader.stream_info(stream.index)?)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Build [`RtspServer`]. The server starts accepting connections right away.
+    pub fn build(self) -> Result<RtspServer> {
+        if self.muxers.is_empty() {
+            return Err(Error::InvalidConfiguration {
+                field: "streams",
+                reason: "at least one stream must be added".to_string(),
+            });
+        }
+        if !self.path.starts_with('/') {
+            return Err(Error::InvalidConfiguration {
+                field: "path",
+                reason: "path must start with '/'".to_string(),
+            });
+        }
+        if self.session_timeout.as_secs() == 0 {
+            return Err(Error::InvalidConfiguration {
+                field: "session_timeout",
+                reason: "session timeout must be at least one second".to_string(),
+            });
+        }
+
+        let unspecified_address: IpAddr = match self.address.ip() {
+            IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            IpAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        // Media is described without address and port, those are negotiated with `SETUP`.
+        let media = self
+            .muxers
+            .iter()
+            .enumerate()
+            .map(|(track_index, (_, muxer))| {
+                let mut session_description =
+                    muxer.session_description(SocketAddr::new(unspecified_address, 0))?;
+                let mut media = session_description.media.remove(0);
+                media.control = Some(format!("trackID={track_index}"));
+                Ok(media)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let session_description = SessionDescription {
+            session_name: "Stream".to_string(),
+            connection_address: unspecified_address,
+            media,
+        };
+
+        let listener = TcpListener::bind(self.address)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let rtp_socket = UdpSocket::bind(SocketAddr::new(self.address.ip(), 0))?;
+        let rtcp_socket = UdpSocket::bind(SocketAddr::new(self.address.ip(), 0))?;
+
+        let state = Arc::new(ServerState {
+            path: self.path,
+            session_timeout: self.session_timeout,
+            session_description: session_description.to_string(),
+            num_tracks: self.muxers.len(),
+            server_ports: (
+                rtp_socket.local_addr()?.port(),
+                rtcp_socket.local_addr()?.port(),
+            ),
+            next_connection_id: AtomicU64::new(0),
+            stop: AtomicBool::new(false),
+            shared: Mutex::new(Shared {
+                sessions: HashMap::new(),
+                connections: HashMap::new(),
+                rtp_info: vec![None; self.muxers.len()],
+            }),
+        });
+        let thread = std::thread::spawn({
+            let state = state.clone();
+            move || accept_connections(listener, state)
+        });
+
+        Ok(RtspServer {
+            muxers: self.muxers,
+            state,
+            rtp_socket,
+            rtcp_socket,
+            local_addr,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// Serves streams to RTSP clients, such as VLC, ffplay or GStreamer, so that this crate can act as
+/// a camera-style source for testing and restreaming.
+///
+/// Clients connect to `rtsp://<host>:<port>/<path>`, set up the streams they want to receive over
+/// UDP or interleaved on the RTSP connection (TCP), and start playing. Packets passed to
+/// [`RtspServer::send`] are packetized into RTP and sent to every playing client. Clients that
+/// join later start receiving from the next packet; the parameter sets of H.264 and H.265 streams
+/// are described in the session description, so decoding starts at the next keyframe.
+///
+/// Sessions end when the client tears them down, closes its RTSP connection, or sends no request
+/// for the session within the session timeout (see [`RtspServerBuilder::with_session_timeout`]).
+/// Connections are handled on background threads, which stop when the server is dropped.
+///
+/// Packets are sent as soon as they are passed in. When serving a file, the caller paces the
+/// packets to real time.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut reader = Reader::new(Path::new("input.mp4")).unwrap();
+/// let mut server = RtspServerBuilder::new("0.0.0.0:8554".parse().unwrap())
+///     .with_streams(&reader)?
+///     .build()?;
+/// // Clients can now play rtsp://<host>:8554/stream.
+/// let stream_index = reader.best_video_stream_index()?;
+/// while let Ok(packet) = reader.read(stream_index) {
+///     server.send(stream_index, packet)?;
+/// }
+/// ```
+pub struct RtspServer {
+    muxers: Vec<(usize, RtpMuxer)>,
+    state: Arc<ServerState>,
+    rtp_socket: UdpSocket,
+    rtcp_socket: UdpSocket,
+    local_addr: SocketAddr,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl RtspServer {
+    /// How often the accept thread checks whether the server was dropped.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// How long sending to a client over TCP may block before the client is disconnected.
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Default time that sessions are kept alive without requests.
+    const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Get the local address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Get the number of sessions that are currently playing.
+    pub fn num_playing_sessions(&self) -> usize {
+        self.state
+            .lock()
+            .sessions
+            .values()
+            .filter(|session| session.playing)
+            .count()
+    }
+
+    /// Send a packet to all playing clients that have set up its stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the stream in the source the packet belongs to.
+    /// * `packet` - Packet to send.
+    pub fn send(&mut self, stream_index: usize, packet: Packet) -> Result<()> {
+        let (track_index, muxer) = self
+            .muxers
+            .iter_mut()
+            .enumerate()
+            .find_map(|(track_index, (source_index, muxer))| {
+                (*source_index == stream_index).then_some((track_index, muxer))
+            })
+            .ok_or(AvError::StreamNotFound)?;
+        // The packet is muxed even without clients, so that the RTP stream stays continuous for
+        // clients that join later.
+        let bufs = muxer.mux(packet)?;
+        let rtp_info = muxer.seq_and_timestamp();
+
+        let transports = {
+            let mut shared = self.state.lock();
+            shared.rtp_info[track_index] = Some(rtp_info);
+            shared
+                .sessions
+                .values()
+                .filter(|session| session.playing)
+                .filter_map(|session| session.transports[track_index].clone())
+                .collect::<Vec<_>>()
+        };
+        // Sending happens without holding the lock, since writing to a TCP client may block.
+        for transport in &transports {
+            for buf in &bufs {
+                let (data, is_rtcp) = match buf {
+                    RtpBuf::Rtp(buf) => (buf, false),
+                    RtpBuf::Rtcp(buf) => (buf, true),
+                };
+                if let Err(err) = self.send_to(transport, data, is_rtcp) {
+                    tracing::warn!("failed to send to RTSP client: {err}");
+                    if let Transport::Interleaved { connection, .. } = transport {
+                        // The connection thread ends the session once the connection is closed.
+                        let _ = lock(connection).shutdown(Shutdown::Both);
+                    }
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send an RTP or RTCP packet over a transport.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - Transport of the stream to the client.
+    /// * `data` - Packet to send.
+    /// * `is_rtcp` - Whether the packet is an RTCP packet.
+    fn send_to(&self, transport: &Transport, data: &[u8], is_rtcp: bool) -> std::io::Result<()> {
+        match transport {
+            Transport::Udp { rtp, rtcp } => {
+                if is_rtcp {
+                    self.rtcp_socket.send_to(data, rtcp)?;
+                } else {
+                    self.rtp_socket.send_to(data, rtp)?;
+                }
+                Ok(())
+            }
+            Transport::Interleaved {
+                connection,
+                channels: (rtp_channel, rtcp_channel),
+            } => {
+                let channel = if is_rtcp { *rtcp_channel } else { *rtp_channel };
+                write_interleaved(&mut *lock(connection), channel, data)
+            }
+        }
+    }
+}
+
+impl Drop for RtspServer {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+        let connections = self
+            .state
+            .lock()
+            .connections
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for connection in connections {
+            let _ = lock(&connection).shutdown(Shutdown::Both);
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+unsafe impl Send for RtspServer {}
+unsafe impl Sync for RtspServer {}
+
+/// State shared between the server and its connection threads.
+struct ServerState {
+    path: String,
+    session_timeout: Duration,
+    session_description: String,
+    num_tracks: usize,
+    server_ports: (u16, u16),
+    next_connection_id: AtomicU64,
+    stop: AtomicBool,
+    shared: Mutex<Shared>,
+}
+
+impl ServerState {
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        lock(&self.shared)
+    }
+
+    /// Keep the sessions that a request applies to alive: the sessions set up on the connection
+    /// the request arrived on, and the session named in the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection_id` - Identifier of the connection the request arrived on.
+    /// * `session_id` - Session named in the request, if any.
+    fn refresh_sessions(&self, connection_id: u64, session_id: Option<&str>) {
+        let now = Instant::now();
+        for (id, session) in self.lock().sessions.iter_mut() {
+            if session.connection_id == connection_id || session_id == Some(id.as_str()) {
+                session.last_request = now;
+            }
+        }
+    }
+
+    /// End the sessions of which the client has not sent a request within the session timeout.
+    fn expire_sessions(&self) {
+        self.lock().sessions.retain(|session_id, session| {
+            let alive = session.last_request.elapsed() < self.session_timeout;
+            if !alive {
+                tracing::debug!("RTSP session {session_id} timed out");
+            }
+            alive
+        });
+    }
+}
+
+/// Mutable state shared between the server and its connection threads.
+struct Shared {
+    // Sessions by session identifier.
+    sessions: HashMap<String, Session>,
+    // Write half of each client connection, by connection identifier.
+    connections: HashMap<u64, Arc<Mutex<TcpStream>>>,
+    // Sequence number of the next packet and RTP timestamp of the last packet of each track.
+    rtp_info: Vec<Option<(u16, u32)>>,
+}
+
+/// RTSP session of a client.
+struct Session {
+    // Connection the session was set up on.
+    connection_id: u64,
+    // Transport of each track, if the client has set it up.
+    transports: Vec<Option<Transport>>,
+    playing: bool,
+    // Time of the last request of the client for this session.
+    last_request: Instant,
+}
+
+/// How the packets of a track are sent to a client.
+#[derive(Clone)]
+enum Transport {
+    Udp {
+        rtp: SocketAddr,
+        rtcp: SocketAddr,
+    },
+    Interleaved {
+        connection: Arc<Mutex<TcpStream>>,
+        channels: (u8, u8),
+    },
+}
+
+/// Accept client connections until the server is dropped, and handle each on its own thread.
+///
+/// # Arguments
+///
+/// * `listener` - Non-blocking listener to accept connections from.
+/// * `state` - Server state.
+fn accept_connections(listener: TcpListener, state: Arc<ServerState>) {
+    while !state.stop.load(Ordering::Relaxed) {
+        state.expire_sessions();
+        match listener.accept() {
+            Ok((stream, peer_addr)) => {
+                tracing::debug!("RTSP client connected from {peer_addr}");
+                let state = state.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = handle_connection(stream, peer_addr, &state) {
+                        tracing::debug!("RTSP connection from {peer_addr} failed: {err}");
+                    }
+                });
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(RtspServer::POLL_INTERVAL);
+            }
+            Err(err) => {
+                tracing::warn!("failed to accept RTSP connection: {err}");
+                std::thread::sleep(RtspServer::POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Handle the requests of a client until it disconnects, then end its sessions.
+///
+/// # Arguments
+///
+/// * `stream` - Connection to the client.
+/// * `peer_addr` - Address of the client.
+/// * `state` - Server state.
+fn handle_connection(stream: TcpStream, peer_addr: SocketAddr, state: &ServerState) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(RtspServer::WRITE_TIMEOUT))?;
+    let connection = Arc::new(Mutex::new(stream.try_clone()?));
+    let connection_id = state.next_connection_id.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut shared = state.lock();
+        if state.stop.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        shared.connections.insert(connection_id, connection.clone());
+    }
+
+    let mut reader = BufReader::new(stream);
+    let result = loop {
+        let request = match Request::read(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
+        };
+        state.refresh_sessions(connection_id, request.session());
+        let response = handle_request(&request, peer_addr, connection_id, &connection, state);
+        if let Err(err) = response.write(request.header("CSeq"), &mut *lock(&connection)) {
+            break Err(err);
+        }
+    };
+
+    let mut shared = state.lock();
+    shared.connections.remove(&connection_id);
+    shared
+        .sessions
+        .retain(|_, session| session.connection_id != connection_id);
+    tracing::debug!("RTSP client {peer_addr} disconnected");
+    Ok(result?)
+}
+
+/// Handle a single request of a client.
+///
+/// # Arguments
+///
+/// * `request` - Request to handle.
+/// * `peer_addr` - Address of the client.
+/// * `connection_id` - Identifier of the connection the request arrived on.
+/// * `connection` - Write half of the connection.
+/// * `state` - Server state.
+fn handle_request(
+    request: &Request,
+    peer_addr: SocketAddr,
+    connection_id: u64,
+    connection: &Arc<Mutex<TcpStream>>,
+    state: &ServerState,
+) -> Response {
+    const PUBLIC: &str = "OPTIONS, DESCRIBE, SETUP, PLAY, PAUSE, TEARDOWN, GET_PARAMETER";
+
+    let path = request.path();
+    let in_path = path == state.path
+        || path
+            .strip_prefix(&state.path)
+            .is_some_and(|rest| rest.starts_with('/'));
+    if request.method != "OPTIONS" && !in_path {
+        return Response::new(404);
+    }
+    let session_header =
+        |session_id: &str| format!("{session_id};timeout={}", state.session_timeout.as_secs());
+
+    match request.method.as_str() {
+        "OPTIONS" => Response::new(200).with_header("Public", PUBLIC),
+        "DESCRIBE" => Response::new(200)
+            .with_header(
+                "Content-Base",
+                format!("{}/", request.uri.trim_end_matches('/')),
+            )
+            .with_body("application/sdp", state.session_description.clone()),
+        "SETUP" => {
+            // A single track may be set up without addressing it explicitly.
+            let track_index = match track_index(path) {
+                Some(track_index) if track_index < state.num_tracks => track_index,
+                None if state.num_tracks == 1 && path == state.path => 0,
+                _ => return Response::new(404),
+            };
+            let Some(transport_spec) = request.header("Transport").and_then(TransportSpec::parse)
+            else {
+                return Response::new(461);
+            };
+            let transport = match transport_spec {
+                TransportSpec::Udp {
+                    client_ports: (rtp_port, rtcp_port),
+                } => Transport::Udp {
+                    rtp: SocketAddr::new(peer_addr.ip(), rtp_port),
+                    rtcp: SocketAddr::new(peer_addr.ip(), rtcp_port),
+                },
+                TransportSpec::Interleaved { channels } => Transport::Interleaved {
+                    connection: connection.clone(),
+                    channels,
+                },
+            };
+
+            let mut shared = state.lock();
+            let session_id = match request.session() {
+                Some(session_id) if shared.sessions.contains_key(session_id) => {
+                    session_id.to_string()
+                }
+                Some(_) => return Response::new(454),
+                None => {
+                    let session_id = (0..4)
+                        .map(|_| format!("{:08x}", ffi::random_seed()))
+                        .collect::<String>();
+                    shared.sessions.insert(
+                        session_id.clone(),
+                        Session {
+                            connection_id,
+                            transports: (0..state.num_tracks).map(|_| None).collect(),
+                            playing: false,
+                            last_request: Instant::now(),
+                        },
+                    );
+                    session_id
+                }
+            };
+            if let Some(session) = shared.sessions.get_mut(&session_id) {
+                session.transports[track_index] = Some(transport);
+            }
+            Response::new(200)
+                .with_header("Transport", transport_spec.to_header(state.server_ports))
+                .with_header("Session", session_header(&session_id))
+        }
+        "PLAY" | "PAUSE" => {
+            let mut guard = state.lock();
+            // Borrow the fields separately, the session is updated while reading the RTP info.
+            let shared = &mut *guard;
+            let Some(session_id) = request.session() else {
+                return Response::new(454);
+            };
+            let Some(session) = shared.sessions.get_mut(session_id) else {
+                return Response::new(454);
+            };
+            if session.transports.iter().all(Option::is_none) {
+                return Response::new(455);
+            }
+            session.playing = request.method == "PLAY";
+            let mut response =
+                Response::new(200).with_header("Session", session_header(session_id));
+            if session.playing {
+                let base_uri = request.uri.trim_end_matches('/');
+                let rtp_info = session
+                    .transports
+                    .iter()
+                    .zip(&shared.rtp_info)
+                    .enumerate()
+                    .filter_map(|(track_index, (transport, rtp_info))| {
+                        transport.as_ref()?;
+                        let (seq, rtptime) = (*rtp_info)?;
+                        Some(format!(
+                            "url={base_uri}/trackID={track_index};seq={seq};rtptime={rtptime}"
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+                response = response.with_header("Range", "npt=now-");
+                if !rtp_info.is_empty() {
+                    response = response.with_header("RTP-Info", rtp_info.join(","));
+                }
+            }
+            response
+        }
+        "TEARDOWN" => {
+            let mut shared = state.lock();
+            match request
+                .session()
+                .and_then(|session_id| shared.sessions.remove(session_id))
+            {
+                Some(_) => Response::new(200),
+                None => Response::new(454),
+            }
+        }
+        // Used by clients as keep-alive.
+        "GET_PARAMETER" | "SET_PARAMETER" => Response::new(200),
+        _ => Response::new(501).with_header("Public", PUBLIC),
+    }
+}
+
+/// Lock a mutex. The state behind the locks is always consistent, so a poisoned lock can be used
+/// safely.
+///
+/// # Arguments
+///
+/// * `mutex` - Mutex to lock.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Read, Write};
+
+    use ffmpeg::util::format::Pixel as AvPixel;
+
+    use super::*;
+    use crate::core::encode::{EncoderBuilder, Settings};
+    use crate::core::frame::RawFrame;
+
+    /// Send a request and read the response, without its body.
+    fn request(client: &mut BufReader<TcpStream>, request: &str) -> Vec<String> {
+        client.get_mut().write_all(request.as_bytes()).unwrap();
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            client.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            lines.push(line);
+        }
+        let content_length = lines
+            .iter()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .map_or(0, |length| length.parse().unwrap());
+        client.read_exact(&mut vec![0; content_length]).unwrap();
+        lines
+    }
+
+    /// Get the value of a header of a response.
+    fn header<'a>(response: &'a [String], name: &str) -> &'a str {
+        response
+            .iter()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+            .unwrap()
+    }
+
+    /// Create a server for a short H.264 file, and return it with the reader of the file.
+    fn server(session_timeout: Duration) -> (RtspServer, Reader, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "rtsp-{}-{}.mp4",
+            std::process::id(),
+            session_timeout.as_secs()
+        ));
+        let mut encoder =
+            EncoderBuilder::new(path.as_path(), Settings::preset_h264_yuv420p(64, 48, false))
+                .build()
+                .unwrap();
+        for index in 0..10 {
+            let mut frame = RawFrame::new(AvPixel::YUV420P, 64, 48);
+            frame.set_pts(Some(index * 1_000_000 / 30));
+            encoder.encode_raw(frame).unwrap();
+        }
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        let reader = Reader::new(path.as_path()).unwrap();
+        let server = RtspServerBuilder::new("127.0.0.1:0".parse().unwrap())
+            .with_session_timeout(session_timeout)
+            .with_streams(&reader)
+            .unwrap()
+            .build()
+            .unwrap();
+        (server, reader, path)
+    }
+
+    #[test]
+    fn test_play_interleaved() {
+        let (mut server, mut reader, path) = server(Duration::from_secs(60));
+        let uri = format!("rtsp://{}/stream", server.local_addr());
+        let mut client = BufReader::new(TcpStream::connect(server.local_addr()).unwrap());
+
+        let response = request(
+            &mut client,
+            &format!("OPTIONS {uri} RTSP/1.0\r\nCSeq: 1\r\n\r\n"),
+        );
+        assert_eq!(response[0], "RTSP/1.0 200 OK");
+        assert_eq!(header(&response, "CSeq"), "1");
+        let response = request(
+            &mut client,
+            &format!("DESCRIBE {uri} RTSP/1.0\r\nCSeq: 2\r\n\r\n"),
+        );
+        assert_eq!(header(&response, "Content-Type"), "application/sdp");
+        let response = request(
+            &mut client,
+            &format!(
+                "SETUP {uri}/trackID=0 RTSP/1.0\r\nCSeq: 3\r\n\
+                 Transport: RTP/AVP/TCP;unicast;interleaved=0-1\r\n\r\n"
+            ),
+        );
+        assert_eq!(response[0], "RTSP/1.0 200 OK");
+        let session = header(&response, "Session");
+        assert!(session.ends_with(";timeout=60"));
+        let session_id = session.split(';').next().unwrap().to_string();
+        assert_eq!(session_id.len(), 32);
+        let response = request(
+            &mut client,
+            &format!("PLAY {uri} RTSP/1.0\r\nCSeq: 4\r\nSession: {session_id}\r\n\r\n"),
+        );
+        assert_eq!(response[0], "RTSP/1.0 200 OK");
+        assert_eq!(server.num_playing_sessions(), 1);
+
+        // Packets arrive interleaved on the RTSP connection.
+        let stream_index = reader.best_video_stream_index().unwrap();
+        let packet = reader.read(stream_index).unwrap();
+        server.send(stream_index, packet).unwrap();
+        let mut rtp_packets = 0;
+        client
+            .get_mut()
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        while client
+            .fill_buf()
+            .is_ok_and(|buf| buf.first() == Some(&b'$'))
+        {
+            let mut interleaved_header = [0; 4];
+            client.read_exact(&mut interleaved_header).unwrap();
+            let size = u16::from_be_bytes([interleaved_header[2], interleaved_header[3]]);
+            let mut data = vec![0; size as usize];
+            client.read_exact(&mut data).unwrap();
+            // RTP and RTCP both have version 2.
+            assert_eq!(data[0] >> 6, 2);
+            if interleaved_header[1] == 0 {
+                rtp_packets += 1;
+            }
+        }
+        assert!(rtp_packets > 0);
+        client.get_mut().set_read_timeout(None).unwrap();
+        let response = request(
+            &mut client,
+            &format!("TEARDOWN {uri} RTSP/1.0\r\nCSeq: 5\r\nSession: {session_id}\r\n\r\n"),
+        );
+        assert_eq!(response[0], "RTSP/1.0 200 OK");
+        assert_eq!(server.num_playing_sessions(), 0);
+
+        drop(server);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_session_expires() {
+        let (server, _reader, path) = server(Duration::from_secs(1));
+        let uri = format!("rtsp://{}/stream", server.local_addr());
+        let mut client = BufReader::new(TcpStream::connect(server.local_addr()).unwrap());
+        let response = request(
+            &mut client,
+            &format!(
+                "SETUP {uri}/trackID=0 RTSP/1.0\r\nCSeq: 1\r\n\
+                 Transport: RTP/AVP;unicast;client_port=5004-5005\r\n\r\n"
+            ),
+        );
+        let session_id = header(&response, "Session")
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        request(
+            &mut client,
+            &format!("PLAY {uri} RTSP/1.0\r\nCSeq: 2\r\nSession: {session_id}\r\n\r\n"),
+        );
+        assert_eq!(server.num_playing_sessions(), 1);
+
+        // Without keep-alives, the session ends even though the connection stays open.
+        std::thread::sleep(Duration::from_millis(1500));
+        assert_eq!(server.num_playing_sessions(), 0);
+        let response = request(
+            &mut client,
+            &format!("PLAY {uri} RTSP/1.0\r\nCSeq: 3\r\nSession: {session_id}\r\n\r\n"),
+        );
+        assert_eq!(response[0], "RTSP/1.0 454 Session Not Found");
+
+        drop(server);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+use std::io::{BufRead, Read, Write};
+
+/// RTSP request sent by a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) uri: String,
+    pub(crate) headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Read the next request from a connection. Returns `None` when the client closed the
+    /// connection. Interleaved binary data sent by the client (RTCP over TCP) and request bodies
+    /// are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Connection to read from.
+    pub(crate) fn read(reader: &mut impl BufRead) -> std::io::Result<Option<Request>> {
+        loop {
+            let Some(&first) = reader.fill_buf()?.first() else {
+                return Ok(None);
+            };
+            if first != b'$' {
+                break;
+            }
+            let mut header = [0; 4];
+            reader.read_exact(&mut header)?;
+            let length = u16::from_be_bytes([header[2], header[3]]) as u64;
+            std::io::copy(&mut reader.take(length), &mut std::io::sink())?;
+        }
+
+        let mut request_line = String::new();
+        // Clients may send empty lines between requests, e.g. as keep-alive.
+        while request_line.trim().is_empty() {
+            request_line.clear();
+            if reader.read_line(&mut request_line)? == 0 {
+                return Ok(None);
+            }
+        }
+        let mut fields = request_line.split_whitespace();
+        let (Some(method), Some(uri), Some(version)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid_data("invalid request line"));
+        };
+        if !version.starts_with("RTSP/") {
+            return Err(invalid_data("unsupported protocol version"));
+        }
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid_data("invalid header line"))?;
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+
+        let request = Request {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            headers,
+        };
+        if let Some(content_length) = request.header("Content-Length") {
+            let content_length = content_length
+                .parse()
+                .map_err(|_| invalid_data("invalid content length"))?;
+            std::io::copy(&mut reader.take(content_length), &mut std::io::sink())?;
+        }
+        Ok(Some(request))
+    }
+
+    /// Get the value of a header.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the header (case insensitive).
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the session identifier of the request, without the session timeout.
+    pub(crate) fn session(&self) -> Option<&str> {
+        self.header("Session")
+            .and_then(|session| session.split(';').next())
+            .map(str::trim)
+    }
+
+    /// Get the path of the request URI, e.g. `/stream/trackID=0` for
+    /// `rtsp://127.0.0.1:8554/stream/trackID=0`.
+    pub(crate) fn path(&self) -> &str {
+        let uri = self
+            .uri
+            .strip_prefix("rtsp://")
+            .or_else(|| self.uri.strip_prefix("rtsps://"));
+        match uri {
+            Some(uri) => uri.find('/').map(|start| &uri[start..]).unwrap_or("/"),
+            None => &self.uri,
+        }
+    }
+}
+
+/// RTSP response to a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    // Content type and body.
+    body: Option<(&'static str, String)>,
+}
+
+impl Response {
+    /// Create a response without headers and body.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - Status code of the response.
+    pub(crate) fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// Add a header to the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the header.
+    /// * `value` - Value of the header.
+    pub(crate) fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Set the body of the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `content_type` - Content type of the body.
+    /// * `body` - Body of the response.
+    pub(crate) fn with_body(mut self, content_type: &'static str, body: String) -> Self {
+        self.body = Some((content_type, body));
+        self
+    }
+
+    /// Write the response to a connection. The response is written at once, so that it is not
+    /// interleaved with RTP data that is sent over the same connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `cseq` - Sequence number of the request that is responded to.
+    /// * `writer` - Connection to write to.
+    pub(crate) fn write(&self, cseq: Option<&str>, writer: &mut impl Write) -> std::io::Result<()> {
+        let mut response = format!("RTSP/1.0 {} {}\r\n", self.status, reason(self.status));
+        if let Some(cseq) = cseq {
+            response.push_str(&format!("CSeq: {cseq}\r\n"));
+        }
+        for (name, value) in &self.headers {
+            response.push_str(&format!("{name}: {value}\r\n"));
+        }
+        match &self.body {
+            Some((content_type, body)) => {
+                response.push_str(&format!("Content-Type: {content_type}\r\n"));
+                response.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+                response.push_str(body);
+            }
+            None => response.push_str("\r\n"),
+        }
+        writer.write_all(response.as_bytes())
+    }
+}
+
+/// Transport requested by a client in a `SETUP` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransportSpec {
+    /// RTP and RTCP over UDP to the given client ports.
+    Udp { client_ports: (u16, u16) },
+    /// RTP and RTCP interleaved on the RTSP connection on the given channels.
+    Interleaved { channels: (u8, u8) },
+}
+
+impl TransportSpec {
+    /// Parse a `Transport` header, and pick the first transport that is supported. Multicast
+    /// transports are not supported.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - Value of the `Transport` header.
+    pub(crate) fn parse(header: &str) -> Option<Self> {
+        header.split(',').find_map(|transport| {
+            let mut parameters = transport.split(';').map(str::trim);
+            let is_tcp = match parameters.next()? {
+                "RTP/AVP" | "RTP/AVP/UDP" => false,
+                "RTP/AVP/TCP" => true,
+                _ => return None,
+            };
+            let mut ports = None;
+            for parameter in parameters {
+                let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+                match name {
+                    "multicast" => return None,
+                    "client_port" if !is_tcp => ports = parse_range(value),
+                    "interleaved" if is_tcp => ports = parse_range(value),
+                    _ => {}
+                }
+            }
+            if is_tcp {
+                // Without explicit channels, the first channels are used.
+                let (rtp, rtcp) = ports.unwrap_or((0, 1));
+                Some(TransportSpec::Interleaved {
+                    channels: (u8::try_from(rtp).ok()?, u8::try_from(rtcp).ok()?),
+                })
+            } else {
+                Some(TransportSpec::Udp {
+                    client_ports: ports?,
+                })
+            }
+        })
+    }
+
+    /// Format the transport for the `Transport` header of the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_ports` - Ports that the server sends RTP and RTCP from over UDP.
+    pub(crate) fn to_header(self, server_ports: (u16, u16)) -> String {
+        match self {
+            TransportSpec::Udp {
+                client_ports: (rtp, rtcp),
+            } => format!(
+                "RTP/AVP;unicast;client_port={rtp}-{rtcp};server_port={}-{}",
+                server_ports.0, server_ports.1
+            ),
+            TransportSpec::Interleaved {
+                channels: (rtp, rtcp),
+            } => format!("RTP/AVP/TCP;unicast;interleaved={rtp}-{rtcp}"),
+        }
+    }
+}
+
+/// Get the index of the track addressed by a request path, e.g. 1 for `/stream/trackID=1`.
+///
+/// # Arguments
+///
+/// * `path` - Path of the request URI.
+pub(crate) fn track_index(path: &str) -> Option<usize> {
+    path.rsplit('/')
+        .next()?
+        .strip_prefix("trackID=")?
+        .parse()
+        .ok()
+}
+
+/// Write RTP or RTCP data interleaved on an RTSP connection.
+///
+/// # Arguments
+///
+/// * `writer` - Connection to write to.
+/// * `channel` - Channel of the data.
+/// * `data` - RTP or RTCP packet.
+pub(crate) fn write_interleaved(
+    writer: &mut impl Write,
+    channel: u8,
+    data: &[u8],
+) -> std::io::Result<()> {
+    let length = u16::try_from(data.len())
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "packet too large"))?;
+    let mut frame = Vec::with_capacity(4 + data.len());
+    frame.push(b'$');
+    frame.push(channel);
+    frame.extend_from_slice(&length.to_be_bytes());
+    frame.extend_from_slice(data);
+    writer.write_all(&frame)
+}
+
+/// Parse a port or channel range such as `5004-5005`. A single value is followed by the next.
+///
+/// # Arguments
+///
+/// * `range` - Range to parse.
+fn parse_range(range: &str) -> Option<(u16, u16)> {
+    match range.split_once('-') {
+        Some((first, second)) => Some((first.parse().ok()?, second.parse().ok()?)),
+        None => {
+            let first: u16 = range.parse().ok()?;
+            Some((first, first.checked_add(1)?))
+        }
+    }
+}
+
+/// Get the reason phrase of a status code.
+///
+/// # Arguments
+///
+/// * `status` - Status code.
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        454 => "Session Not Found",
+        455 => "Method Not Valid in This State",
+        461 => "Unsupported Transport",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        _ => "Unknown",
+    }
+}
+
+fn invalid_data(reason: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_requests() {
+        let mut connection = std::io::Cursor::new(
+            b"OPTIONS rtsp://127.0.0.1:8554/stream RTSP/1.0\r\n\
+              CSeq: 1\r\n\
+              \r\n\
+              $\x01\x00\x03abc\
+              SET_PARAMETER rtsp://127.0.0.1:8554/stream/trackID=1 RTSP/1.0\r\n\
+              CSeq: 2\r\n\
+              Session: 1234abcd;timeout=60\r\n\
+              Content-Length: 5\r\n\
+              \r\n\
+              hello\
+              \r\n"
+                .to_vec(),
+        );
+        let request = Request::read(&mut connection).unwrap().unwrap();
+        assert_eq!(request.method, "OPTIONS");
+        assert_eq!(request.path(), "/stream");
+        assert_eq!(request.header("cseq"), Some("1"));
+        assert_eq!(request.session(), None);
+
+        let request = Request::read(&mut connection).unwrap().unwrap();
+        assert_eq!(request.method, "SET_PARAMETER");
+        assert_eq!(request.path(), "/stream/trackID=1");
+        assert_eq!(track_index(request.path()), Some(1));
+        assert_eq!(request.session(), Some("1234abcd"));
+        assert_eq!(Request::read(&mut connection).unwrap(), None);
+
+        let mut connection = std::io::Cursor::new(b"HELLO\r\n\r\n".to_vec());
+        assert!(Request::read(&mut connection).is_err());
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut buf = Vec::new();
+        Response::new(200)
+            .with_header("Session", "1234abcd")
+            .with_body("application/sdp", "v=0\r\n".to_string())
+            .write(Some("3"), &mut buf)
+            .unwrap();
+        assert_eq!(
+            buf,
+            b"RTSP/1.0 200 OK\r\n\
+              CSeq: 3\r\n\
+              Session: 1234abcd\r\n\
+              Content-Type: application/sdp\r\n\
+              Content-Length: 5\r\n\
+              \r\n\
+              v=0\r\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_transport() {
+        assert_eq!(
+            TransportSpec::parse("RTP/AVP;unicast;client_port=5004-5005"),
+            Some(TransportSpec::Udp {
+                client_ports: (5004, 5005)
+            })
+        );
+        assert_eq!(
+            TransportSpec::parse("RTP/AVP;multicast;port=5004-5005,RTP/AVP/TCP;interleaved=2-3"),
+            Some(TransportSpec::Interleaved { channels: (2, 3) })
+        );
+        assert_eq!(
+            TransportSpec::parse("RTP/AVP/TCP;unicast"),
+            Some(TransportSpec::Interleaved { channels: (0, 1) })
+        );
+        assert_eq!(TransportSpec::parse("RTP/AVP;unicast"), None);
+        assert_eq!(TransportSpec::parse("RAW/RAW/UDP;client_port=5004"), None);
+        assert_eq!(
+            TransportSpec::Udp {
+                client_ports: (5004, 5005)
+            }
+            .to_header((6970, 6971)),
+            "RTP/AVP;unicast;client_port=5004-5005;server_port=6970-6971"
+        );
+    }
+
+    #[test]
+    fn test_write_interleaved() {
+        let mut buf = Vec::new();
+        write_interleaved(&mut buf, 1, b"abc").unwrap();
+        assert_eq!(buf, b"$\x01\x00\x03abc");
+    }
+}
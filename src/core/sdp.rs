@@ -0,0 +1,664 @@
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::core::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Kind of media in a media description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+impl MediaKind {
+    /// Name of the media kind in the `m=` line.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            MediaKind::Video => "video",
+            MediaKind::Audio => "audio",
+        }
+    }
+}
+
+/// Description of a single RTP stream in a session description. More attributes may be added in
+/// the future, so descriptions are created with [`MediaDescription::new`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MediaDescription {
+    /// Kind of media.
+    pub kind: MediaKind,
+    /// Port the RTP packets are sent to.
+    pub port: u16,
+    /// RTP payload type of the stream.
+    pub payload_type: u8,
+    /// Encoding name as registered for RTP, e.g. `H264` or `opus`.
+    pub encoding_name: String,
+    /// Clock rate of the RTP timestamps.
+    pub clock_rate: u32,
+    /// Number of audio channels, if it must be signaled.
+    pub channels: Option<u32>,
+    /// Format specific parameters (the `a=fmtp` line), such as the parameter sets of a video
+    /// stream.
+    pub format_parameters: Vec<(String, String)>,
+    /// Port RTCP is sent to, if it is not the RTP port plus one. Equal to `port` if RTP and RTCP
+    /// share a port (`a=rtcp-mux`).
+    pub rtcp_port: Option<u16>,
+    /// Control URL of the stream (the `a=control` line), relative to the URL of the session. Used
+    /// by RTSP to address the stream.
+    pub control: Option<String>,
+    /// Payload type of retransmissions of the stream (`rtx`, RFC 4588), if the sender retransmits
+    /// packets that receivers request with generic NACKs.
+    pub retransmission_payload_type: Option<u8>,
+}
+
+impl MediaDescription {
+    /// Create a new [`MediaDescription`] without optional attributes.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of media.
+    /// * `port` - Port the RTP packets are sent to.
+    /// * `payload_type` - RTP payload type of the stream.
+    /// * `encoding_name` - Encoding name as registered for RTP, e.g. `H264` or `opus`.
+    /// * `clock_rate` - Clock rate of the RTP timestamps.
+    pub fn new(
+        kind: MediaKind,
+        port: u16,
+        payload_type: u8,
+        encoding_name: impl Into<String>,
+        clock_rate: u32,
+    ) -> Self {
+        Self {
+            kind,
+            port,
+            payload_type,
+            encoding_name: encoding_name.into(),
+            clock_rate,
+            channels: None,
+            format_parameters: Vec::new(),
+            rtcp_port: None,
+            control: None,
+            retransmission_payload_type: None,
+        }
+    }
+
+    /// Get the value of a format specific parameter.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the parameter.
+    pub fn format_parameter(&self, name: &str) -> Option<&str> {
+        self.format_parameters
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Session description (SDP, RFC 4566) of an RTP session. Its text form, produced by `Display`, can
+/// be stored as an `.sdp` file and opened by receivers such as ffplay, VLC or GStreamer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionDescription {
+    /// Name of the session.
+    pub session_name: String,
+    /// Address the RTP packets are sent to.
+    pub connection_address: IpAddr,
+    /// Streams in the session.
+    pub media: Vec<MediaDescription>,
+}
+
+impl fmt::Display for SessionDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let address_type = match self.connection_address {
+            IpAddr::V4(_) => "IP4",
+            IpAddr::V6(_) => "IP6",
+        };
+        // IPv4 multicast addresses must carry a TTL.
+        let ttl = match self.connection_address {
+            IpAddr::V4(address) if address.is_multicast() => "/127",
+            _ => "",
+        };
+        write!(f, "v=0\r\n")?;
+        write!(
+            f,
+            "o=- 0 0 IN {address_type} {}\r\n",
+            self.connection_address
+        )?;
+        write!(f, "s={}\r\n", self.session_name)?;
+        write!(
+            f,
+            "c=IN {address_type} {}{ttl}\r\n",
+            self.connection_address
+        )?;
+        write!(f, "t=0 0\r\n")?;
+        for media in &self.media {
+            // Feedback such as NACKs requires the AVPF profile.
+            match media.retransmission_payload_type {
+                Some(retransmission_payload_type) => write!(
+                    f,
+                    "m={} {} RTP/AVPF {} {retransmission_payload_type}\r\n",
+                    media.kind.as_str(),
+                    media.port,
+                    media.payload_type
+                )?,
+                None => write!(
+                    f,
+                    "m={} {} RTP/AVP {}\r\n",
+                    media.kind.as_str(),
+                    media.port,
+                    media.payload_type
+                )?,
+            }
+            write!(
+                f,
+                "a=rtpmap:{} {}/{}",
+                media.payload_type, media.encoding_name, media.clock_rate
+            )?;
+            if let Some(channels) = media.channels {
+                write!(f, "/{channels}")?;
+            }
+            write!(f, "\r\n")?;
+            if !media.format_parameters.is_empty() {
+                let format_parameters = media
+                    .format_parameters
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                write!(f, "a=fmtp:{} {format_parameters}\r\n", media.payload_type)?;
+            }
+            if let Some(retransmission_payload_type) = media.retransmission_payload_type {
+                write!(
+                    f,
+                    "a=rtpmap:{retransmission_payload_type} rtx/{}\r\n",
+                    media.clock_rate
+                )?;
+                write!(
+                    f,
+                    "a=fmtp:{retransmission_payload_type} apt={}\r\n",
+                    media.payload_type
+                )?;
+                write!(f, "a=rtcp-fb:{} nack\r\n", media.payload_type)?;
+            }
+            match media.rtcp_port {
+                Some(rtcp_port) if rtcp_port == media.port => write!(f, "a=rtcp-mux\r\n")?,
+                Some(rtcp_port) => write!(f, "a=rtcp:{rtcp_port}\r\n")?,
+                None => {}
+            }
+            if let Some(control) = &media.control {
+                write!(f, "a=control:{control}\r\n")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for SessionDescription {
+    type Err = Error;
+
+    /// Parse a session description. Only RTP audio and video streams are kept; other media, and
+    /// attributes that do not describe the first payload type of a stream or its retransmissions,
+    /// are ignored.
+    fn from_str(text: &str) -> Result<Self> {
+        let mut session_name = String::new();
+        let mut connection_address = None;
+        let mut media = Vec::new();
+        let mut in_media_section = false;
+        // Media section that is being parsed, or `None` if it is skipped.
+        let mut section: Option<MediaSection> = None;
+
+        for line in text.lines() {
+            let Some((kind, value)) = line.split_once('=') else {
+                continue;
+            };
+            match kind {
+                "s" if !in_media_section => session_name = value.to_string(),
+                "c" => {
+                    let address = parse_connection_address(value)?;
+                    // The address of the session applies to all media, unless a media section
+                    // has its own. Only one address is kept, so the session address wins.
+                    if in_media_section {
+                        connection_address.get_or_insert(address);
+                    } else {
+                        connection_address = Some(address);
+                    }
+                }
+                "m" => {
+                    in_media_section = true;
+                    media.extend(section.take().and_then(MediaSection::finish));
+                    section = MediaSection::parse(value)?;
+                }
+                "a" => {
+                    if let Some(section) = section.as_mut() {
+                        section.attribute(value)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        media.extend(section.and_then(MediaSection::finish));
+
+        Ok(SessionDescription {
+            session_name,
+            connection_address: connection_address.ok_or_else(|| {
+                Error::InvalidSessionDescription("missing connection address".to_string())
+            })?,
+            media,
+        })
+    }
+}
+
+/// Media section of a session description that is being parsed.
+struct MediaSection {
+    kind: MediaKind,
+    port: u16,
+    payload_type: u8,
+    // Encoding name, clock rate and channels from `a=rtpmap`.
+    rtp_map: Option<(String, u32, Option<u32>)>,
+    format_parameters: Vec<(String, String)>,
+    rtcp_port: Option<u16>,
+    rtcp_mux: bool,
+    control: Option<String>,
+    // Payload types with an `rtx` encoding, and the payload types they retransmit (`apt`).
+    rtx_payload_types: Vec<u8>,
+    associated_payload_types: Vec<(u8, u8)>,
+}
+
+impl MediaSection {
+    /// Parse the `m=` line that starts a media section. Returns `None` for media that is not
+    /// RTP audio or video.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value of the `m=` line.
+    fn parse(value: &str) -> Result<Option<Self>> {
+        let invalid = || Error::InvalidSessionDescription(format!("invalid media line: {value}"));
+        let mut fields = value.split_whitespace();
+        let kind = match fields.next() {
+            Some("video") => MediaKind::Video,
+            Some("audio") => MediaKind::Audio,
+            _ => return Ok(None),
+        };
+        // The port may be followed by the number of ports, e.g. `5004/2`.
+        let port = fields
+            .next()
+            .and_then(|port| port.split('/').next())
+            .and_then(|port| port.parse().ok())
+            .ok_or_else(invalid)?;
+        if !fields.next().ok_or_else(invalid)?.starts_with("RTP/") {
+            return Ok(None);
+        }
+        let payload_type = fields
+            .next()
+            .and_then(|payload_type| payload_type.parse().ok())
+            .ok_or_else(invalid)?;
+        Ok(Some(Self {
+            kind,
+            port,
+            payload_type,
+            rtp_map: None,
+            format_parameters: Vec::new(),
+            rtcp_port: None,
+            rtcp_mux: false,
+            control: None,
+            rtx_payload_types: Vec::new(),
+            associated_payload_types: Vec::new(),
+        }))
+    }
+
+    /// Parse an attribute of the media section.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value of the `a=` line.
+    fn attribute(&mut self, value: &str) -> Result<()> {
+        let invalid = || Error::InvalidSessionDescription(format!("invalid attribute: {value}"));
+        let (name, value) = value.split_once(':').unwrap_or((value, ""));
+        // Attributes of the `rtpmap` and `fmtp` kind start with the payload type they apply to.
+        let for_payload_type = || {
+            value
+                .split_once(' ')
+                .filter(|(payload_type, _)| payload_type.parse() == Ok(self.payload_type))
+                .map(|(_, value)| value.trim())
+        };
+        // Attributes of other payload types may describe retransmissions.
+        let other_payload_type = value
+            .split_once(' ')
+            .and_then(|(payload_type, value)| Some((payload_type.parse::<u8>().ok()?, value)))
+            .filter(|(payload_type, _)| *payload_type != self.payload_type);
+        match name {
+            "rtpmap" => {
+                if let Some((payload_type, value)) = other_payload_type {
+                    if value.trim().to_ascii_lowercase().starts_with("rtx/") {
+                        self.rtx_payload_types.push(payload_type);
+                    }
+                } else if let Some(value) = for_payload_type() {
+                    let mut fields = value.split('/');
+                    let encoding_name = fields.next().ok_or_else(invalid)?.to_string();
+                    let clock_rate = fields
+                        .next()
+                        .and_then(|clock_rate| clock_rate.parse().ok())
+                        .ok_or_else(invalid)?;
+                    let channels = fields.next().and_then(|channels| channels.parse().ok());
+                    self.rtp_map = Some((encoding_name, clock_rate, channels));
+                }
+            }
+            "fmtp" => {
+                if let Some((payload_type, value)) = other_payload_type {
+                    let associated_payload_type = value
+                        .split(';')
+                        .filter_map(|parameter| parameter.trim().split_once('='))
+                        .find(|(key, _)| key.trim() == "apt")
+                        .and_then(|(_, value)| value.trim().parse().ok());
+                    if let Some(associated_payload_type) = associated_payload_type {
+                        self.associated_payload_types
+                            .push((payload_type, associated_payload_type));
+                    }
+                } else if let Some(value) = for_payload_type() {
+                    self.format_parameters = value
+                        .split(';')
+                        .filter_map(|parameter| parameter.trim().split_once('='))
+                        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                        .collect();
+                }
+            }
+            "rtcp" => {
+                let rtcp_port = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|port| port.parse().ok());
+                self.rtcp_port = Some(rtcp_port.ok_or_else(invalid)?);
+            }
+            "rtcp-mux" => self.rtcp_mux = true,
+            "control" => self.control = Some(value.to_string()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Finish the media section. Returns `None` if the encoding of the stream is not known.
+    fn finish(self) -> Option<MediaDescription> {
+        // Static payload types do not need an `a=rtpmap` line.
+        let rtp_map = self.rtp_map.or_else(|| {
+            let encoding_name = match self.payload_type {
+                0 => "PCMU",
+                8 => "PCMA",
+                9 => "G722",
+                _ => return None,
+            };
+            Some((encoding_name.to_string(), 8_000, None))
+        });
+        let Some((encoding_name, clock_rate, channels)) = rtp_map else {
+            tracing::debug!(
+                "ignoring media with unknown payload type {}",
+                self.payload_type
+            );
+            return None;
+        };
+        let retransmission_payload_type = self
+            .associated_payload_types
+            .iter()
+            .find(|(payload_type, associated_payload_type)| {
+                *associated_payload_type == self.payload_type
+                    && self.rtx_payload_types.contains(payload_type)
+            })
+            .map(|(payload_type, _)| *payload_type);
+        Some(MediaDescription {
+            kind: self.kind,
+            port: self.port,
+            payload_type: self.payload_type,
+            encoding_name,
+            clock_rate,
+            channels,
+            format_parameters: self.format_parameters,
+            rtcp_port: if self.rtcp_mux {
+                Some(self.port)
+            } else {
+                self.rtcp_port
+            },
+            control: self.control,
+            retransmission_payload_type,
+        })
+    }
+}
+
+/// Parse the address of a `c=` line, e.g. `IN IP4 239.0.0.1/127`.
+///
+/// # Arguments
+///
+/// * `value` - Value of the `c=` line.
+fn parse_connection_address(value: &str) -> Result<IpAddr> {
+    value
+        .split_whitespace()
+        .nth(2)
+        .and_then(|address| address.split('/').next())
+        .and_then(|address| address.parse().ok())
+        .ok_or_else(|| {
+            Error::InvalidSessionDescription(format!("invalid connection line: {value}"))
+        })
+}
+
+/// Encode bytes as base64 (with padding), as used for parameter sets in SDP.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes to encode.
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | ((*byte as u32) << (16 - 8 * i))
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode base64 (with or without padding). Returns `None` if the text is not valid base64.
+///
+/// # Arguments
+///
+/// * `text` - Text to decode.
+pub(crate) fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let mut bits = 0u32;
+    let mut num_bits = 0;
+    for c in text.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | value as u32;
+        num_bits += 6;
+        if num_bits >= 8 {
+            num_bits -= 8;
+            decoded.push((bits >> num_bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// Encode bytes as lowercase hexadecimal.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes to encode.
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Decode hexadecimal, in either case. Returns `None` if the text is not valid hexadecimal.
+///
+/// # Arguments
+///
+/// * `text` - Text to decode.
+pub(crate) fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(&[0x68, 0xce, 0x3c, 0x80]), "aM48gA==");
+    }
+
+    #[test]
+    fn test_base64_decode() {
+        assert_eq!(base64_decode("Zg==").unwrap(), b"f");
+        assert_eq!(base64_decode("Zm8").unwrap(), b"fo");
+        assert_eq!(base64_decode("aM48gA==").unwrap(), [0x68, 0xce, 0x3c, 0x80]);
+        assert!(base64_decode("Zm9v!").is_none());
+    }
+
+    #[test]
+    fn test_hex_decode() {
+        assert_eq!(hex_decode("1190").unwrap(), [0x11, 0x90]);
+        assert_eq!(hex_decode("40002410").unwrap(), [0x40, 0x00, 0x24, 0x10]);
+        assert_eq!(
+            hex_decode(&hex_encode(&[0xab, 0x01])).unwrap(),
+            [0xab, 0x01]
+        );
+        assert!(hex_decode("119").is_none());
+        assert!(hex_decode("+1").is_none());
+    }
+
+    #[test]
+    fn test_session_description_text() {
+        let session_description = SessionDescription {
+            session_name: "Stream".to_string(),
+            connection_address: "239.0.0.1".parse().unwrap(),
+            media: vec![
+                MediaDescription {
+                    kind: MediaKind::Video,
+                    port: 5004,
+                    payload_type: 96,
+                    encoding_name: "H264".to_string(),
+                    clock_rate: 90_000,
+                    channels: None,
+                    format_parameters: vec![
+                        ("packetization-mode".to_string(), "1".to_string()),
+                        ("profile-level-id".to_string(), "42c01f".to_string()),
+                    ],
+                    rtcp_port: None,
+                    control: None,
+                    retransmission_payload_type: Some(98),
+                },
+                MediaDescription {
+                    kind: MediaKind::Audio,
+                    port: 5006,
+                    payload_type: 97,
+                    encoding_name: "opus".to_string(),
+                    clock_rate: 48_000,
+                    channels: Some(2),
+                    format_parameters: Vec::new(),
+                    rtcp_port: Some(5006),
+                    control: Some("trackID=1".to_string()),
+                    retransmission_payload_type: None,
+                },
+            ],
+        };
+        assert_eq!(
+            session_description.to_string(),
+            "v=0\r\n\
+             o=- 0 0 IN IP4 239.0.0.1\r\n\
+             s=Stream\r\n\
+             c=IN IP4 239.0.0.1/127\r\n\
+             t=0 0\r\n\
+             m=video 5004 RTP/AVPF 96 98\r\n\
+             a=rtpmap:96 H264/90000\r\n\
+             a=fmtp:96 packetization-mode=1; profile-level-id=42c01f\r\n\
+             a=rtpmap:98 rtx/90000\r\n\
+             a=fmtp:98 apt=96\r\n\
+             a=rtcp-fb:96 nack\r\n\
+             m=audio 5006 RTP/AVP 97\r\n\
+             a=rtpmap:97 opus/48000/2\r\n\
+             a=rtcp-mux\r\n\
+             a=control:trackID=1\r\n"
+        );
+        assert_eq!(
+            session_description.media[0].format_parameter("Profile-Level-Id"),
+            Some("42c01f")
+        );
+        assert_eq!(
+            session_description
+                .to_string()
+                .parse::<SessionDescription>()
+                .unwrap(),
+            session_description
+        );
+    }
+
+    #[test]
+    fn test_parse_session_description() {
+        let session_description: SessionDescription = "v=0\n\
+             o=- 0 0 IN IP4 127.0.0.1\n\
+             s=No Name\n\
+             t=0 0\n\
+             m=application 9 UDP/BFCP *\n\
+             a=rtpmap:96 H264/90000\n\
+             m=video 5004 RTP/AVP 96 97\n\
+             c=IN IP4 192.168.1.2\n\
+             a=rtpmap:97 H265/90000\n\
+             a=rtpmap:96 H264/90000\n\
+             a=fmtp:96 packetization-mode=1;sprop-parameter-sets=Z0IAH5WoFAFuQA==,aM48gA==\n\
+             a=rtcp:5010 IN IP4 192.168.1.2\n\
+             m=audio 5006 RTP/AVP 0\n"
+            .parse()
+            .unwrap();
+        assert_eq!(session_description.session_name, "No Name");
+        assert_eq!(
+            session_description.connection_address,
+            "192.168.1.2".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(session_description.media.len(), 2);
+        let video = &session_description.media[0];
+        assert_eq!(video.kind, MediaKind::Video);
+        assert_eq!(video.port, 5004);
+        assert_eq!(video.payload_type, 96);
+        assert_eq!(video.encoding_name, "H264");
+        assert_eq!(video.clock_rate, 90_000);
+        assert_eq!(video.format_parameter("packetization-mode"), Some("1"));
+        assert_eq!(
+            video.format_parameter("sprop-parameter-sets"),
+            Some("Z0IAH5WoFAFuQA==,aM48gA==")
+        );
+        assert_eq!(video.rtcp_port, Some(5010));
+        let audio = &session_description.media[1];
+        assert_eq!(audio.encoding_name, "PCMU");
+        assert_eq!(audio.clock_rate, 8_000);
+
+        assert!("v=0\nm=video 5004 RTP/AVP 96\n"
+            .parse::<SessionDescription>()
+            .is_err());
+        assert!("c=IN IP4 127.0.0.1\nm=video port RTP/AVP 96\n"
+            .parse::<SessionDescription>()
+            .is_err());
+    }
+}
@@ -0,0 +1,182 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::ffi::{AVSEEK_FLAG_ANY, AVSEEK_FLAG_BYTE};
+
+/// Number of `AV_TIME_BASE` units in a millisecond.
+const TIME_BASE_UNITS_PER_MILLISECOND: i64 = 1000;
+
+/// Where to seek to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeekTarget {
+    /// Number of milliseconds from the start of the source.
+    Milliseconds(i64),
+    /// Byte offset in the source. Useful for containers without a reliable index, such as live
+    /// MPEG-TS streams.
+    Byte(i64),
+}
+
+/// Where the reader should land relative to the target.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeekDirection {
+    /// Land on the nearest position within the leeway on either side of the target.
+    Nearest,
+    /// Land on the last position at or before the target. Decoding from there reaches the target
+    /// exactly, which is what frame-accurate scrubbing needs.
+    Backward,
+    /// Land on the first position at or after the target.
+    Forward,
+}
+
+/// Controls how [`Reader::seek_with`](crate::core::io::Reader::seek_with) positions the reader.
+///
+/// The default matches [`Reader::seek`](crate::core::io::Reader::seek): land on a keyframe within
+/// one second of the target, on either side.
+///
+/// # Example
+///
+/// ```ignore
+/// // Scrub an MP4 file: land on the keyframe before the target and decode forward.
+/// reader.seek_with(
+///     SeekTarget::Milliseconds(12_500),
+///     SeekOptions::new().with_direction(SeekDirection::Backward),
+/// )?;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SeekOptions {
+    direction: SeekDirection,
+    any_frame: bool,
+    leeway: i64,
+}
+
+impl SeekOptions {
+    /// Default leeway for [`SeekDirection::Nearest`].
+    const LEEWAY: i64 = 1000;
+
+    /// Create default seek options.
+    pub fn new() -> Self {
+        Self {
+            direction: SeekDirection::Nearest,
+            any_frame: false,
+            leeway: Self::LEEWAY,
+        }
+    }
+
+    /// Set where to land relative to the target.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - Seek direction.
+    pub fn with_direction(mut self, direction: SeekDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Allow landing on non-keyframes. Decoding from a non-keyframe produces corrupt frames until
+    /// the next keyframe, so this is only useful for inspecting packets or for intra-only codecs.
+    ///
+    /// # Arguments
+    ///
+    /// * `any_frame` - Whether or not to allow non-keyframes.
+    pub fn with_any_frame(mut self, any_frame: bool) -> Self {
+        self.any_frame = any_frame;
+        self
+    }
+
+    /// Set how far from the target the reader may land with [`SeekDirection::Nearest`].
+    ///
+    /// # Arguments
+    ///
+    /// * `leeway` - Leeway on either side of the target, in the unit of the target: milliseconds
+    ///   for [`SeekTarget::Milliseconds`] and bytes for [`SeekTarget::Byte`].
+    pub fn with_leeway(mut self, leeway: i64) -> Self {
+        self.leeway = leeway.max(0);
+        self
+    }
+
+    /// Get seek direction.
+    #[inline]
+    pub fn direction(&self) -> SeekDirection {
+        self.direction
+    }
+
+    /// Compute the minimum, target and maximum positions to pass to the backend, in backend units
+    /// (`AV_TIME_BASE` units or bytes).
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Where to seek to.
+    pub(crate) fn range(&self, target: SeekTarget) -> (i64, i64, i64) {
+        let (position, leeway) = match target {
+            SeekTarget::Milliseconds(milliseconds) => (
+                milliseconds.saturating_mul(TIME_BASE_UNITS_PER_MILLISECOND),
+                self.leeway.saturating_mul(TIME_BASE_UNITS_PER_MILLISECOND),
+            ),
+            SeekTarget::Byte(offset) => (offset, self.leeway),
+        };
+        match self.direction {
+            SeekDirection::Nearest => (
+                position.saturating_sub(leeway),
+                position,
+                position.saturating_add(leeway),
+            ),
+            SeekDirection::Backward => (i64::MIN, position, position),
+            SeekDirection::Forward => (position, position, i64::MAX),
+        }
+    }
+
+    /// Compute the flags to pass to the backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Where to seek to.
+    pub(crate) fn flags(&self, target: SeekTarget) -> i32 {
+        let mut flags = 0;
+        if matches!(target, SeekTarget::Byte(_)) {
+            flags |= AVSEEK_FLAG_BYTE;
+        }
+        if self.any_frame {
+            flags |= AVSEEK_FLAG_ANY;
+        }
+        flags
+    }
+}
+
+impl Default for SeekOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_range_matches_leeway() {
+        let (min, position, max) = SeekOptions::new().range(SeekTarget::Milliseconds(5000));
+        assert_eq!(position, 5_000_000);
+        assert_eq!(min, 4_000_000);
+        assert_eq!(max, 6_000_000);
+    }
+
+    #[test]
+    fn test_direction_bounds_range() {
+        let backward = SeekOptions::new().with_direction(SeekDirection::Backward);
+        assert_eq!(
+            backward.range(SeekTarget::Milliseconds(1)),
+            (i64::MIN, 1000, 1000)
+        );
+        let forward = SeekOptions::new().with_direction(SeekDirection::Forward);
+        assert_eq!(forward.range(SeekTarget::Byte(4096)), (4096, 4096, i64::MAX));
+    }
+
+    #[test]
+    fn test_flags() {
+        let options = SeekOptions::new().with_any_frame(true);
+        assert_eq!(options.flags(SeekTarget::Milliseconds(0)), AVSEEK_FLAG_ANY);
+        assert_eq!(
+            options.flags(SeekTarget::Byte(0)),
+            AVSEEK_FLAG_ANY | AVSEEK_FLAG_BYTE
+        );
+    }
+}
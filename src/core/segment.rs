@@ -0,0 +1,554 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::format::context::Output as AvOutput;
+use ffmpeg::media::Type as AvMediaType;
+use ffmpeg::{Error as AvError, Rational as AvRational, Rescale};
+
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::io::private::{self, Output as _, Write as _};
+use crate::core::io::{Write, Writer, WriterBuilder};
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::probe::timestamp_to_duration;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Callback that is invoked when a segment is complete.
+type SegmentCallback = Box<dyn FnMut(&SegmentInfo) + Send>;
+
+/// Information about a segment that was written by a [`SegmentedWriter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentInfo {
+    /// Number of the segment, starting at zero.
+    pub index: usize,
+    /// Where the segment was written to.
+    pub location: Location,
+    /// Duration of the segment.
+    pub duration: Duration,
+    /// Size of the segment in bytes.
+    pub size: u64,
+}
+
+/// Builds a [`SegmentedWriter`].
+pub struct SegmentedWriterBuilder<'a> {
+    template: String,
+    format: Option<&'a str>,
+    options: Option<&'a Options>,
+    max_duration: Option<Duration>,
+    max_size: Option<u64>,
+    on_segment_complete: Option<SegmentCallback>,
+}
+
+impl<'a> SegmentedWriterBuilder<'a> {
+    /// Create a writer that writes segments to files named after `template`.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Filename template with a `printf`-style segment number, e.g.
+    ///   `recording_%05d.mp4`.
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            format: None,
+            options: None,
+            max_duration: None,
+            max_size: None,
+            on_segment_complete: None,
+        }
+    }
+
+    /// Set the container format of the segments. By default it is derived from the template.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Container format to use.
+    pub fn with_format(mut self, format: &'a str) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Specify options for the backend. They are passed on to every segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to output.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Start a new segment once the current segment is at least this long.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_duration` - Duration after which to start a new segment.
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Start a new segment once the current segment has grown to at least this many bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_size` - Size in bytes after which to start a new segment.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Invoke a callback whenever a segment is complete, e.g. to upload it or to delete old
+    /// segments. The callback runs on the thread that writes, so it should not block for long.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - Callback that receives information about the completed segment.
+    pub fn with_on_segment_complete(
+        mut self,
+        callback: impl FnMut(&SegmentInfo) + Send + 'static,
+    ) -> Self {
+        self.on_segment_complete = Some(Box::new(callback));
+        self
+    }
+
+    /// Build a [`SegmentedWriter`].
+    pub fn build(self) -> Result<SegmentedWriter> {
+        if self.max_duration.is_none() && self.max_size.is_none() {
+            return Err(Error::InvalidConfiguration {
+                field: "max_duration",
+                reason: "either a maximum duration or a maximum size is required".to_string(),
+            });
+        }
+        if self.max_duration == Some(Duration::ZERO) || self.max_size == Some(0) {
+            return Err(Error::InvalidConfiguration {
+                field: "max_duration",
+                reason: "segment limits must be greater than zero".to_string(),
+            });
+        }
+        // Validate the template before anything is written.
+        segment_path(&self.template, 0)?;
+
+        Ok(SegmentedWriter {
+            writer: open_segment(&self.template, 0, self.format, self.options)?,
+            template: self.template,
+            format: self.format.map(str::to_string),
+            options: self.options.cloned(),
+            max_duration: self.max_duration,
+            max_size: self.max_size,
+            on_segment_complete: self.on_segment_complete,
+            index: 0,
+            key_stream_index: 0,
+            start: None,
+            elapsed: Duration::ZERO,
+        })
+    }
+}
+
+/// Writes output into a series of files, starting a new file whenever the current one reaches a
+/// maximum duration or size. This is useful for continuous recording, where a single file would
+/// grow without bounds.
+///
+/// New segments start on a keyframe of the first video stream (or on any packet if there is no
+/// video), so that every segment can be played back on its own. The timestamps of each segment
+/// start at zero. Packets of other streams that belong just before that keyframe would get
+/// negative timestamps, so unless the `avoid_negative_ts` option is set, the muxer shifts the
+/// timestamps of such a segment up until they are non-negative.
+///
+/// Use it with a [`Muxer`](crate::core::mux::Muxer) to record packets as they are received.
+///
+/// # Example
+///
+/// ```ignore
+/// let writer = SegmentedWriterBuilder::new("camera_%05d.mp4")
+///     .with_max_duration(Duration::from_secs(600))
+///     .with_on_segment_complete(|segment| println!("finished {}", segment.location))
+///     .build()
+///     .unwrap();
+/// let mut muxer = MuxerBuilder::new(writer)
+///     .with_supported_streams(&reader)
+///     .unwrap()
+///     .build()
+///     .unwrap();
+/// ```
+pub struct SegmentedWriter {
+    writer: Writer,
+    template: String,
+    format: Option<String>,
+    options: Option<Options>,
+    max_duration: Option<Duration>,
+    max_size: Option<u64>,
+    on_segment_complete: Option<SegmentCallback>,
+    index: usize,
+    key_stream_index: usize,
+    // Timestamp and time base of the first packet of the current segment.
+    start: Option<(i64, AvRational)>,
+    // Time between the first and the last packet of the current segment so far.
+    elapsed: Duration,
+}
+
+impl SegmentedWriter {
+    /// Location of the segment that is currently being written.
+    pub fn current_segment(&self) -> &Location {
+        &self.writer.destination
+    }
+
+    /// Number of the segment that is currently being written, starting at zero.
+    pub fn current_segment_index(&self) -> usize {
+        self.index
+    }
+
+    /// Start a new segment if the packet is a suitable point to do so and the current segment has
+    /// reached its limits, then shift the packet so that the segment starts at zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - Packet that is about to be written, in the time base of its output stream.
+    fn prepare(&mut self, packet: &mut AvPacket) -> Result<()> {
+        let stream_index = packet.stream();
+        let mut time_base = self.stream_time_base(stream_index)?;
+        if let Some(timestamp) = packet.dts().or(packet.pts()) {
+            match self.start {
+                None => self.start = Some((timestamp, time_base)),
+                Some((start, start_time_base)) => {
+                    let elapsed = timestamp.rescale(time_base, start_time_base) - start;
+                    // Packets from before the start of the segment do not make it any longer.
+                    if let Some(elapsed) = timestamp_to_duration(elapsed, start_time_base) {
+                        self.elapsed = self.elapsed.max(elapsed);
+                    }
+                    if stream_index == self.key_stream_index
+                        && packet.is_key()
+                        && self.limit_reached()
+                    {
+                        self.roll_over()?;
+                        self.start = Some((timestamp, time_base));
+                        // The new segment may have picked a different time base.
+                        let segment_time_base = self.stream_time_base(stream_index)?;
+                        packet.rescale_ts(time_base, segment_time_base);
+                        time_base = segment_time_base;
+                    }
+                }
+            }
+        }
+
+        if let Some((start, start_time_base)) = self.start {
+            let offset = start.rescale(start_time_base, time_base);
+            packet.set_pts(packet.pts().map(|pts| pts - offset));
+            packet.set_dts(packet.dts().map(|dts| dts - offset));
+        }
+        Ok(())
+    }
+
+    /// Whether or not the current segment has reached its maximum duration or size.
+    fn limit_reached(&self) -> bool {
+        self.max_duration
+            .is_some_and(|max_duration| self.elapsed >= max_duration)
+            || self
+                .max_size
+                .is_some_and(|max_size| ffi::output_position(self.writer.output()) >= max_size)
+    }
+
+    /// Finish the current segment and continue in a new one with the same streams.
+    fn roll_over(&mut self) -> Result<()> {
+        self.writer.write_trailer()?;
+
+        let mut writer = open_segment(
+            &self.template,
+            self.index + 1,
+            self.format.as_deref(),
+            self.options.as_ref(),
+        )?;
+        copy_streams(self.writer.output(), writer.output_mut())?;
+        *writer.header_options_mut() = self.writer.header_options_mut().clone();
+        writer.write_header()?;
+
+        let finished = std::mem::replace(&mut self.writer, writer);
+        let segment = SegmentInfo {
+            index: self.index,
+            location: finished.destination.clone(),
+            duration: self.elapsed,
+            size: ffi::output_position(finished.output()),
+        };
+        // Close the file before anyone gets to see it.
+        drop(finished);
+        self.index += 1;
+        self.elapsed = Duration::ZERO;
+        self.notify(&segment);
+        Ok(())
+    }
+
+    /// Invoke the segment complete callback, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment` - Segment that was completed.
+    fn notify(&mut self, segment: &SegmentInfo) {
+        if let Some(on_segment_complete) = self.on_segment_complete.as_mut() {
+            on_segment_complete(segment);
+        }
+    }
+
+    /// Get the time base of an output stream of the current segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the output stream.
+    fn stream_time_base(&self, stream_index: usize) -> Result<AvRational> {
+        Ok(self
+            .writer
+            .output()
+            .stream(stream_index)
+            .ok_or(AvError::StreamNotFound)?
+            .time_base())
+    }
+}
+
+impl Write for SegmentedWriter {}
+
+impl private::Write for SegmentedWriter {
+    type Out = ();
+
+    fn write_header(&mut self) -> Result<()> {
+        // Segments are cut on keyframes of the first video stream, or on any packet of the first
+        // stream if there is no video.
+        self.key_stream_index = self
+            .writer
+            .output()
+            .streams()
+            .find(|stream| stream.parameters().medium() == AvMediaType::Video)
+            .map(|stream| stream.index())
+            .unwrap_or(0);
+        // The options are carried over to every following segment.
+        let options = self.writer.header_options_mut();
+        if options.get("avoid_negative_ts").is_none() {
+            options.set("avoid_negative_ts", "make_non_negative");
+        }
+        self.writer.write_header()
+    }
+
+    fn write(&mut self, packet: &mut AvPacket) -> Result<()> {
+        self.prepare(packet)?;
+        self.writer.write(packet)
+    }
+
+    fn write_interleaved(&mut self, packet: &mut AvPacket) -> Result<()> {
+        self.prepare(packet)?;
+        self.writer.write_interleaved(packet)
+    }
+
+    fn write_trailer(&mut self) -> Result<()> {
+        self.writer.write_trailer()?;
+        let segment = SegmentInfo {
+            index: self.index,
+            location: self.writer.destination.clone(),
+            duration: self.elapsed,
+            size: ffi::output_position(self.writer.output()),
+        };
+        self.notify(&segment);
+        Ok(())
+    }
+
+    fn header_options_mut(&mut self) -> &mut Options {
+        self.writer.header_options_mut()
+    }
+}
+
+impl private::Output for SegmentedWriter {
+    fn output(&self) -> &AvOutput {
+        self.writer.output()
+    }
+
+    fn output_mut(&mut self) -> &mut AvOutput {
+        self.writer.output_mut()
+    }
+}
+
+unsafe impl Send for SegmentedWriter {}
+unsafe impl Sync for SegmentedWriter {}
+
+/// Open the writer of a segment.
+///
+/// # Arguments
+///
+/// * `template` - Filename template of the segments.
+/// * `index` - Number of the segment.
+/// * `format` - Container format, or `None` to derive it from the filename.
+/// * `options` - Options to pass on to the output.
+fn open_segment(
+    template: &str,
+    index: usize,
+    format: Option<&str>,
+    options: Option<&Options>,
+) -> Result<Writer> {
+    let mut writer_builder = WriterBuilder::new(segment_path(template, index)?);
+    if let Some(format) = format {
+        writer_builder = writer_builder.with_format(format);
+    }
+    if let Some(options) = options {
+        writer_builder = writer_builder.with_options(options);
+    }
+    writer_builder.build()
+}
+
+/// Add streams with the same parameters, time bases and metadata as the streams of `source` to
+/// `destination`, and copy the container metadata.
+///
+/// # Arguments
+///
+/// * `source` - Output to copy streams from.
+/// * `destination` - Output to add streams to.
+fn copy_streams(source: &AvOutput, destination: &mut AvOutput) -> Result<()> {
+    for stream in source.streams() {
+        let parameters = stream.parameters();
+        let mut destination_stream =
+            destination.add_stream(ffmpeg::encoder::find(parameters.id()))?;
+        destination_stream.set_parameters(parameters);
+        destination_stream.set_time_base(stream.time_base());
+        destination_stream.set_metadata(stream.metadata().to_owned());
+    }
+    destination.set_metadata(source.metadata().to_owned());
+    Ok(())
+}
+
+/// Substitute the segment number into a filename template. The template must contain exactly one
+/// `%d` placeholder, optionally zero-padded to a width, e.g. `%05d`. Use `%%` for a literal `%`.
+///
+/// # Arguments
+///
+/// * `template` - Filename template.
+/// * `index` - Segment number.
+fn segment_path(template: &str, index: usize) -> Result<PathBuf> {
+    let invalid = |reason: &str| Error::InvalidConfiguration {
+        field: "template",
+        reason: format!("{reason}: {template}"),
+    };
+    let mut path = String::with_capacity(template.len() + 8);
+    let mut placeholders = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+        if chars.next_if_eq(&'%').is_some() {
+            path.push('%');
+            continue;
+        }
+        let mut width = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            width.push(digit);
+        }
+        if chars.next() != Some('d') {
+            return Err(invalid(
+                "unsupported placeholder in segment filename template",
+            ));
+        }
+        let width = width.parse::<usize>().unwrap_or(0);
+        path.push_str(&format!("{index:0width$}"));
+        placeholders += 1;
+    }
+    if placeholders != 1 {
+        return Err(invalid(
+            "segment filename template needs exactly one %d placeholder",
+        ));
+    }
+    Ok(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ffmpeg::util::format::Pixel as AvPixel;
+
+    use super::*;
+    use crate::core::encode::{EncoderBuilder, Settings};
+    use crate::core::frame::RawFrame;
+    use crate::core::io::Reader;
+    use crate::core::mux::MuxerBuilder;
+
+    #[test]
+    fn test_segment_path() {
+        assert_eq!(
+            segment_path("rec_%05d.mp4", 42).unwrap(),
+            PathBuf::from("rec_00042.mp4")
+        );
+        assert_eq!(
+            segment_path("100%%_%d.ts", 7).unwrap(),
+            PathBuf::from("100%_7.ts")
+        );
+        assert!(segment_path("rec.mp4", 0).is_err());
+        assert!(segment_path("rec_%d_%d.mp4", 0).is_err());
+        assert!(segment_path("rec_%s.mp4", 0).is_err());
+    }
+
+    #[test]
+    fn test_roll_over() {
+        let directory = std::env::temp_dir();
+        let source = directory.join(format!("segment-source-{}.mp4", std::process::id()));
+        // Three seconds at 30 fps, with a keyframe every second.
+        let mut encoder = EncoderBuilder::new(
+            source.as_path(),
+            Settings::preset_h264_yuv420p(64, 48, false),
+        )
+        .with_gop_size(30)
+        .build()
+        .unwrap();
+        for index in 0..90 {
+            let mut frame = RawFrame::new(AvPixel::YUV420P, 64, 48);
+            frame.data_mut(0).fill(index as u8);
+            frame.set_pts(Some(index * 1_000_000 / 30));
+            encoder.encode_raw(frame).unwrap();
+        }
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        let template = directory.join(format!("segment-{}-%d.mp4", std::process::id()));
+        let segments = Arc::new(Mutex::new(Vec::new()));
+        let writer = SegmentedWriterBuilder::new(template.to_str().unwrap())
+            .with_max_duration(Duration::from_secs(1))
+            .with_on_segment_complete({
+                let segments = segments.clone();
+                move |segment| segments.lock().unwrap().push(segment.clone())
+            })
+            .build()
+            .unwrap();
+        let mut reader = Reader::new(source.as_path()).unwrap();
+        let mut muxer = MuxerBuilder::new(writer)
+            .with_streams(&reader)
+            .unwrap()
+            .build()
+            .unwrap();
+        while let Ok((_, packet)) = reader.read_any() {
+            muxer.mux(packet).unwrap();
+        }
+        muxer.finish().unwrap();
+        drop(muxer);
+
+        let segments = segments.lock().unwrap().clone();
+        assert_eq!(segments.len(), 3);
+        for (index, segment) in segments.iter().enumerate() {
+            assert_eq!(segment.index, index);
+            assert!(segment.size > 0);
+            if index < 2 {
+                assert!((segment.duration.as_secs_f64() - 1.0).abs() < 0.05);
+            }
+
+            // Every segment starts with a keyframe, at zero.
+            let Location::File(path) = &segment.location else {
+                panic!("segment is not a file");
+            };
+            let mut segment_reader = Reader::new(path.as_path()).unwrap();
+            let (_, packet) = segment_reader.read_any().unwrap();
+            assert!(packet.is_key());
+            assert!(packet.pts().as_secs_f64().abs() < 0.1);
+            drop(segment_reader);
+            std::fs::remove_file(path).unwrap();
+        }
+        std::fs::remove_file(source).unwrap();
+    }
+}
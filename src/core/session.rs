@@ -0,0 +1,262 @@
+use crate::core::decode::Decoder;
+use crate::core::error::Error;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Snapshot of playback state that can be persisted and restored later, for example to implement
+/// "resume where you left off".
+///
+/// The state is serialized as a small, versioned `key=value` text format through [`Display`] and
+/// [`FromStr`], so it can be stored in a file or any other string storage without extra
+/// dependencies. Unknown keys are ignored when parsing, so that newer versions can add fields.
+///
+/// [`Display`]: std::fmt::Display
+/// [`FromStr`]: std::str::FromStr
+///
+/// # Example
+///
+/// ```ignore
+/// let state = SessionState::new()
+///     .with_position(Duration::from_secs(754))
+///     .with_volume(0.8);
+/// state.save(Path::new("session.txt")).unwrap();
+///
+/// let state = SessionState::load(Path::new("session.txt")).unwrap();
+/// state.restore(&mut decoder).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionState {
+    /// Playback position.
+    pub position: std::time::Duration,
+    /// Index of the selected video stream.
+    pub video_stream_index: Option<usize>,
+    /// Index of the selected audio stream.
+    pub audio_stream_index: Option<usize>,
+    /// Index of the selected subtitle stream.
+    pub subtitle_stream_index: Option<usize>,
+    /// Playback speed, where `1.0` is normal speed.
+    pub speed: f32,
+    /// Volume, where `0.0` is muted and `1.0` is full volume.
+    pub volume: f32,
+    /// Loop region: playback jumps back to the start when it reaches the end.
+    pub loop_region: Option<(std::time::Duration, std::time::Duration)>,
+}
+
+impl SessionState {
+    /// Version of the serialization format.
+    const VERSION: u32 = 1;
+
+    /// Create a state at the start of playback, at normal speed and full volume.
+    pub fn new() -> Self {
+        Self {
+            position: std::time::Duration::ZERO,
+            video_stream_index: None,
+            audio_stream_index: None,
+            subtitle_stream_index: None,
+            speed: 1.0,
+            volume: 1.0,
+            loop_region: None,
+        }
+    }
+
+    /// Set the playback position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Playback position.
+    pub fn with_position(mut self, position: std::time::Duration) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Set the selected video, audio and subtitle streams.
+    ///
+    /// # Arguments
+    ///
+    /// * `video` - Index of video stream.
+    /// * `audio` - Index of audio stream.
+    /// * `subtitle` - Index of subtitle stream.
+    pub fn with_streams(
+        mut self,
+        video: Option<usize>,
+        audio: Option<usize>,
+        subtitle: Option<usize>,
+    ) -> Self {
+        self.video_stream_index = video;
+        self.audio_stream_index = audio;
+        self.subtitle_stream_index = subtitle;
+        self
+    }
+
+    /// Set the playback speed.
+    ///
+    /// # Arguments
+    ///
+    /// * `speed` - Playback speed, where `1.0` is normal speed.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Set the volume.
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - Volume from `0.0` to `1.0`.
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    /// Set the loop region.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Start of loop region.
+    /// * `end` - End of loop region.
+    pub fn with_loop_region(mut self, start: std::time::Duration, end: std::time::Duration) -> Self {
+        self.loop_region = Some((start, end));
+        self
+    }
+
+    /// Seek the decoder to the saved position.
+    ///
+    /// # Arguments
+    ///
+    /// * `decoder` - Decoder to restore position of.
+    pub fn restore(&self, decoder: &mut Decoder) -> Result<()> {
+        decoder.seek(self.position.as_millis() as i64)
+    }
+
+    /// Write the state to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to write to.
+    pub fn save(&self, path: &std::path::Path) -> Result<()> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Read a state from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to read from.
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for SessionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "version={}", Self::VERSION)?;
+        writeln!(f, "position_ms={}", self.position.as_millis())?;
+        if let Some(index) = self.video_stream_index {
+            writeln!(f, "video_stream={index}")?;
+        }
+        if let Some(index) = self.audio_stream_index {
+            writeln!(f, "audio_stream={index}")?;
+        }
+        if let Some(index) = self.subtitle_stream_index {
+            writeln!(f, "subtitle_stream={index}")?;
+        }
+        writeln!(f, "speed={}", self.speed)?;
+        writeln!(f, "volume={}", self.volume)?;
+        if let Some((start, end)) = self.loop_region {
+            writeln!(f, "loop_ms={}-{}", start.as_millis(), end.as_millis())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for SessionState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut state = SessionState::new();
+        let mut version = None;
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("malformed line: {line}")))?;
+            match key {
+                "version" => version = Some(parse::<u32>(key, value)?),
+                "position_ms" => state.position = parse_millis(key, value)?,
+                "video_stream" => state.video_stream_index = Some(parse(key, value)?),
+                "audio_stream" => state.audio_stream_index = Some(parse(key, value)?),
+                "subtitle_stream" => state.subtitle_stream_index = Some(parse(key, value)?),
+                "speed" => state.speed = parse(key, value)?,
+                "volume" => state.volume = parse(key, value)?,
+                "loop_ms" => {
+                    let (start, end) = value
+                        .split_once('-')
+                        .ok_or_else(|| invalid(format!("malformed loop region: {value}")))?;
+                    state.loop_region = Some((parse_millis(key, start)?, parse_millis(key, end)?));
+                }
+                _ => {}
+            }
+        }
+
+        match version {
+            Some(version) if version <= Self::VERSION => Ok(state),
+            Some(version) => Err(invalid(format!("unsupported version: {version}"))),
+            None => Err(invalid("missing version".to_string())),
+        }
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidSessionState(reason)
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("invalid value for {key}: {value}")))
+}
+
+fn parse_millis(key: &str, value: &str) -> Result<std::time::Duration> {
+    parse::<u64>(key, value).map(std::time::Duration::from_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let state = SessionState::new()
+            .with_position(std::time::Duration::from_millis(754_250))
+            .with_streams(Some(0), Some(2), None)
+            .with_speed(1.5)
+            .with_volume(0.25)
+            .with_loop_region(
+                std::time::Duration::from_secs(10),
+                std::time::Duration::from_secs(20),
+            );
+        let parsed: SessionState = state.to_string().parse().unwrap();
+        assert_eq!(parsed, state);
+    }
+
+    #[test]
+    fn test_defaults_for_missing_keys() {
+        let parsed: SessionState = "version=1\nposition_ms=1000\n".parse().unwrap();
+        assert_eq!(parsed.position, std::time::Duration::from_secs(1));
+        assert_eq!(parsed.speed, 1.0);
+        assert_eq!(parsed.loop_region, None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("position_ms=1000".parse::<SessionState>().is_err());
+        assert!("version=99".parse::<SessionState>().is_err());
+        assert!("version=1\nspeed=fast".parse::<SessionState>().is_err());
+    }
+}
@@ -0,0 +1,750 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::collections::{HashMap, HashSet};
+
+use ffmpeg::codec::decoder::Video as AvDecoder;
+use ffmpeg::codec::encoder::video::Encoder as AvEncoder;
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::codec::Context as AvContext;
+use ffmpeg::util::error::EAGAIN;
+use ffmpeg::util::frame::Video as AvFrame;
+use ffmpeg::util::mathematics::rescale::Rescale;
+use ffmpeg::util::picture::Type as AvFrameType;
+use ffmpeg::{Error as AvError, Rational as AvRational};
+
+use crate::core::bitstream::{
+    annexb_to_length_prefixed, length_prefixed, nal_length_size, parameter_sets, with_data,
+};
+use crate::core::codec::{CodecOptions, VideoCodec};
+use crate::core::color::ColorMetadata;
+use crate::core::error::Error;
+use crate::core::ffi;
+use crate::core::io::private::Write;
+use crate::core::io::{Reader, ReaderBuilder, Writer, WriterBuilder};
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::seek::{SeekDirection, SeekOptions, SeekTarget};
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Builds a [`SmartCut`].
+pub struct SmartCutBuilder<'a> {
+    source: Location,
+    destination: Location,
+    start: Time,
+    end: Time,
+    input_options: Option<&'a Options>,
+    output_options: Option<&'a Options>,
+    format: Option<&'a str>,
+    crf: Option<u8>,
+}
+
+impl<'a> SmartCutBuilder<'a> {
+    /// Boundary GOPs are re-encoded at a lower CRF than the codec default, so that they do not
+    /// stand out against the (usually high quality) copied GOPs.
+    const CRF_MARGIN: u8 = 5;
+
+    /// Create a smart cut of `source` from `start` up to (but not including) `end`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to cut from.
+    /// * `destination` - Where to write the clip to.
+    /// * `start` - Start of the clip, relative to the start of the stream.
+    /// * `end` - End of the clip, relative to the start of the stream.
+    pub fn new(
+        source: impl Into<Location>,
+        destination: impl Into<Location>,
+        start: Time,
+        end: Time,
+    ) -> Self {
+        Self {
+            source: source.into(),
+            destination: destination.into(),
+            start,
+            end,
+            input_options: None,
+            output_options: None,
+            format: None,
+            crf: None,
+        }
+    }
+
+    /// Set the input options for the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The input options.
+    pub fn with_input_options(mut self, options: &'a Options) -> Self {
+        self.input_options = Some(options);
+        self
+    }
+
+    /// Set the output options for the destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The output options.
+    pub fn with_output_options(mut self, options: &'a Options) -> Self {
+        self.output_options = Some(options);
+        self
+    }
+
+    /// Set the container format for the destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Container format to use.
+    pub fn with_format(mut self, format: &'a str) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Set the constant rate factor used to re-encode the partial GOPs at the cut points. Defaults
+    /// to a value that is visually close to most sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `crf` - Constant rate factor.
+    pub fn with_crf(mut self, crf: u8) -> Self {
+        self.crf = Some(crf);
+        self
+    }
+
+    /// Build [`SmartCut`]. This scans the keyframes of the source around the cut points to plan
+    /// which parts can be copied.
+    pub fn build(self) -> Result<SmartCut> {
+        let mut reader_builder = ReaderBuilder::new(self.source);
+        if let Some(options) = self.input_options {
+            reader_builder = reader_builder.with_options(options);
+        }
+        let mut reader = reader_builder.build()?;
+
+        let video_stream_index = reader.best_video_stream_index()?;
+        let video_stream = reader
+            .input
+            .stream(video_stream_index)
+            .ok_or(AvError::StreamNotFound)?;
+        let video_time_base = video_stream.time_base();
+        let video_frame_rate = video_stream.avg_frame_rate();
+        let video_codec_id = video_stream.parameters().id();
+        let video_codec = VideoCodec::from_id(video_codec_id)
+            .ok_or_else(|| Error::UnsupportedCodec(format!("{video_codec_id:?}")))?;
+
+        let crf = self
+            .crf
+            .unwrap_or_else(|| video_codec.default_crf().saturating_sub(Self::CRF_MARGIN));
+        CodecOptions {
+            crf: Some(crf),
+            ..Default::default()
+        }
+        .validate(video_codec)?;
+
+        // Cut points are relative to the start of the stream, which is not necessarily zero.
+        let stream_start = match video_stream.start_time() {
+            ffmpeg::ffi::AV_NOPTS_VALUE => 0,
+            start_time => start_time,
+        };
+        let start = self
+            .start
+            .aligned_with_rational(video_time_base)
+            .into_value()
+            .unwrap_or(0)
+            .max(0)
+            + stream_start;
+        let end = self
+            .end
+            .aligned_with_rational(video_time_base)
+            .into_value()
+            .map_or(i64::MAX, |end| end + stream_start);
+        let start_milliseconds =
+            (Time::new(Some(start), video_time_base).as_secs_f64() * 1000.0) as i64;
+
+        let keyframes = scan_keyframes(&mut reader, video_stream_index, start_milliseconds, end)?;
+        let plan = CutPlan::new(
+            &keyframes.iter().map(|&(pts, _)| pts).collect::<Vec<_>>(),
+            start,
+            end,
+        );
+        // Re-encoded packets have no B-frames, so their decoding timestamps equal their
+        // presentation timestamps. They are shifted by the reordering delay of the copied part,
+        // so that decoding timestamps increase across the segment boundaries.
+        let reorder_delay = plan
+            .copy
+            .and_then(|(copy_start, _)| keyframes.iter().find(|&&(pts, _)| pts == copy_start))
+            .map_or(0, |&(pts, dts)| pts - dts);
+
+        let mut writer_builder = WriterBuilder::new(self.destination);
+        if let Some(format) = self.format {
+            writer_builder = writer_builder.with_format(format);
+        }
+        if let Some(options) = self.output_options {
+            writer_builder = writer_builder.with_options(options);
+        }
+        let mut writer = writer_builder.build()?;
+
+        let mut mapping = HashMap::new();
+        for stream in reader.input.streams() {
+            let codec_parameters = stream.parameters();
+            let mut writer_stream = writer
+                .output
+                .add_stream(ffmpeg::encoder::find(codec_parameters.id()))?;
+            writer_stream.set_parameters(codec_parameters);
+            mapping.insert(
+                stream.index(),
+                StreamMapping {
+                    index: writer_stream.index(),
+                    time_base: stream.time_base(),
+                },
+            );
+        }
+
+        let video_writer_stream_index = mapping[&video_stream_index].index;
+        let extradata = ffi::extradata(&writer.output, video_writer_stream_index)?;
+        let bitstream = match nal_length_size(video_codec, extradata) {
+            Some(nal_length_size) => Bitstream::LengthPrefixed {
+                nal_length_size,
+                parameter_sets: length_prefixed(
+                    &parameter_sets(video_codec, extradata),
+                    nal_length_size,
+                ),
+            },
+            None => Bitstream::AsIs,
+        };
+
+        Ok(SmartCut {
+            reader,
+            writer,
+            video_stream_index,
+            video_time_base,
+            video_frame_rate,
+            video_codec,
+            crf,
+            mapping,
+            plan,
+            start_milliseconds,
+            reorder_delay,
+            bitstream,
+        })
+    }
+}
+
+/// Which part of a clip is copied and which parts are re-encoded. All timestamps are in the time
+/// base of the source video stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CutPlan {
+    /// Start of the clip.
+    pub start: i64,
+    /// End of the clip (exclusive).
+    pub end: i64,
+    /// Keyframe-aligned range of the clip that is copied without re-encoding, if any. Everything
+    /// before it is re-encoded up to the first keyframe, and everything after it is re-encoded
+    /// from the last keyframe.
+    pub copy: Option<(i64, i64)>,
+}
+
+impl CutPlan {
+    /// Plan a cut given the keyframe timestamps of the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `keyframes` - Timestamps of the keyframes around the clip.
+    /// * `start` - Start of the clip.
+    /// * `end` - End of the clip (exclusive).
+    pub(crate) fn new(keyframes: &[i64], start: i64, end: i64) -> Self {
+        let inside = keyframes
+            .iter()
+            .copied()
+            .filter(|&keyframe| keyframe >= start && keyframe <= end);
+        let first = inside.clone().min();
+        let last = inside.max();
+        let copy = match (first, last) {
+            (Some(first), Some(last)) if first < last => Some((first, last)),
+            _ => None,
+        };
+        Self { start, end, copy }
+    }
+
+    /// Whether or not the clip starts and ends on keyframes, so that nothing is re-encoded.
+    pub fn is_copy_only(&self) -> bool {
+        self.copy == Some((self.start, self.end))
+    }
+}
+
+/// Summary of a finished [`SmartCut`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct SmartCutSummary {
+    /// Number of video packets copied without re-encoding.
+    pub packets_copied: u64,
+    /// Number of video frames that were re-encoded.
+    pub frames_encoded: u64,
+}
+
+/// Extracts a clip with frame-accurate cut points, without re-encoding all of it.
+///
+/// Whole GOPs inside the clip are copied as-is, and only the partial GOPs at the start and the end
+/// of the clip are decoded and re-encoded. This is orders of magnitude faster than re-encoding the
+/// entire clip, and preserves the original quality of the copied part. All other streams (audio,
+/// subtitles) are copied within the clip boundaries.
+///
+/// The re-encoded parts carry their own in-band parameter sets. When the source stores parameter
+/// sets out-of-band (MP4, Matroska), the parameter sets of the source are repeated in-band at the
+/// start of the copied part so that decoders switch back to them. The source must use closed GOPs
+/// for the copied part to start cleanly.
+///
+/// # Example
+///
+/// ```ignore
+/// let summary = SmartCutBuilder::new(
+///     Path::new("movie.mp4"),
+///     Path::new("clip.mp4"),
+///     Time::from_secs(12.3),
+///     Time::from_secs(47.9),
+/// )
+/// .build()?
+/// .run()?;
+/// ```
+pub struct SmartCut {
+    reader: Reader,
+    writer: Writer,
+    video_stream_index: usize,
+    video_time_base: AvRational,
+    video_frame_rate: AvRational,
+    video_codec: VideoCodec,
+    crf: u8,
+    mapping: HashMap<usize, StreamMapping>,
+    plan: CutPlan,
+    start_milliseconds: i64,
+    // Difference between the presentation and decoding timestamps of the first copied keyframe.
+    reorder_delay: i64,
+    bitstream: Bitstream,
+}
+
+impl SmartCut {
+    /// Get the plan that the cut will execute.
+    #[inline]
+    pub fn plan(&self) -> CutPlan {
+        self.plan
+    }
+
+    /// Run the cut.
+    pub fn run(mut self) -> Result<SmartCutSummary> {
+        self.reader.seek_with(
+            SeekTarget::Milliseconds(self.start_milliseconds),
+            SeekOptions::new().with_direction(SeekDirection::Backward),
+        )?;
+        self.writer.write_header()?;
+
+        let video_stream = self
+            .reader
+            .input
+            .stream(self.video_stream_index)
+            .ok_or(AvError::StreamNotFound)?;
+        let mut decoder_context = AvContext::new();
+        ffi::set_decoder_context_time_base(&mut decoder_context, self.video_time_base);
+        decoder_context.set_parameters(video_stream.parameters())?;
+        let decoder = decoder_context.decoder().video()?;
+
+        let mut state = CutState {
+            decoder,
+            encoder: None,
+            segment: Segment::Head,
+            parameter_sets_pending: true,
+            summary: SmartCutSummary::default(),
+        };
+        let mut open_streams = self.mapping.keys().copied().collect::<HashSet<_>>();
+
+        while !open_streams.is_empty() {
+            let (stream_index, packet) = match self.reader.read_any() {
+                Ok(packet) => packet,
+                Err(Error::ReadExhausted) => break,
+                Err(err) => return Err(err),
+            };
+            if !open_streams.contains(&stream_index) {
+                continue;
+            }
+            let (packet, time_base) = packet.into_inner_parts();
+            let Some(pts) = packet.pts() else {
+                continue;
+            };
+
+            if stream_index != self.video_stream_index {
+                let start = self.plan.start.rescale(self.video_time_base, time_base);
+                let end = self.plan.end.rescale(self.video_time_base, time_base);
+                if pts >= end {
+                    open_streams.remove(&stream_index);
+                } else if pts >= start {
+                    self.write_packet(stream_index, packet, start)?;
+                }
+                continue;
+            }
+
+            if packet.is_key() && pts >= self.plan.end {
+                open_streams.remove(&stream_index);
+                continue;
+            }
+            if packet.is_key() {
+                match self.plan.copy {
+                    Some((copy_start, _)) if pts == copy_start => {
+                        self.finish_segment(&mut state)?;
+                        state.segment = Segment::Copy;
+                    }
+                    Some((_, copy_end)) if pts == copy_end => {
+                        state.segment = Segment::Tail;
+                    }
+                    _ => {}
+                }
+            }
+
+            match state.segment {
+                Segment::Copy => self.copy_video_packet(&mut state, packet)?,
+                Segment::Head | Segment::Tail => {
+                    state.decoder.send_packet(&packet)?;
+                    self.receive_frames(&mut state)?;
+                }
+            }
+        }
+
+        if state.segment != Segment::Copy {
+            self.finish_segment(&mut state)?;
+        }
+        self.writer.write_trailer()?;
+
+        Ok(state.summary)
+    }
+
+    /// Range of timestamps that the current re-encoded segment covers.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment` - Current segment.
+    fn segment_range(&self, segment: Segment) -> (i64, i64) {
+        match (segment, self.plan.copy) {
+            (Segment::Head, Some((copy_start, _))) => (self.plan.start, copy_start),
+            (Segment::Tail, Some((_, copy_end))) => (copy_end, self.plan.end),
+            _ => (self.plan.start, self.plan.end),
+        }
+    }
+
+    /// Receive all available frames from the decoder and encode those inside the current segment.
+    fn receive_frames(&mut self, state: &mut CutState) -> Result<()> {
+        let (segment_start, segment_end) = self.segment_range(state.segment);
+        loop {
+            let mut frame = AvFrame::empty();
+            match state.decoder.receive_frame(&mut frame) {
+                Ok(()) => {}
+                Err(AvError::Eof) => return Ok(()),
+                Err(AvError::Other { errno }) if errno == EAGAIN => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+
+            let Some(pts) = frame.timestamp() else {
+                continue;
+            };
+            if pts < segment_start || pts >= segment_end {
+                continue;
+            }
+
+            if state.encoder.is_none() {
+                state.encoder = Some(self.open_encoder(&state.decoder, &frame)?);
+            }
+            if let Some(encoder) = state.encoder.as_mut() {
+                frame.set_pts(Some(pts));
+                frame.set_kind(AvFrameType::None);
+                encoder.send_frame(&frame)?;
+                state.summary.frames_encoded += 1;
+            }
+            self.write_encoded_packets(state)?;
+        }
+    }
+
+    /// Drain the decoder and the encoder of the current re-encoded segment, and reset the decoder
+    /// so that it can be used for the next segment.
+    fn finish_segment(&mut self, state: &mut CutState) -> Result<()> {
+        state.decoder.send_eof()?;
+        self.receive_frames(state)?;
+        state.decoder.flush();
+        if let Some(encoder) = state.encoder.as_mut() {
+            encoder.send_eof()?;
+        }
+        self.write_encoded_packets(state)?;
+        state.encoder = None;
+        Ok(())
+    }
+
+    /// Open an encoder for a re-encoded segment. Every segment gets its own encoder, so that it
+    /// starts with a keyframe and its own parameter sets.
+    ///
+    /// # Arguments
+    ///
+    /// * `decoder` - Decoder of the source video.
+    /// * `frame` - First frame to encode.
+    fn open_encoder(&self, decoder: &AvDecoder, frame: &AvFrame) -> Result<AvEncoder> {
+        let codec = self
+            .video_codec
+            .find_encoder()
+            .ok_or(AvError::EncoderNotFound)?;
+        let mut encoder = ffi::codec_context_as(&codec)?.encoder().video()?;
+        encoder.set_width(frame.width());
+        encoder.set_height(frame.height());
+        encoder.set_format(frame.format());
+        encoder.set_time_base(self.video_time_base);
+        if self.video_frame_rate.numerator() > 0 {
+            encoder.set_frame_rate(Some(self.video_frame_rate));
+        }
+        ColorMetadata::from_decoder(decoder).apply_to(&mut encoder);
+
+        // Without B-frames the re-encoded timestamps never need reordering, which keeps the
+        // decoding timestamps monotonic across the segment boundaries.
+        let options = CodecOptions {
+            crf: Some(self.crf),
+            max_b_frames: Some(0),
+            ..Default::default()
+        }
+        .apply_to(codec.name(), &Options::default());
+
+        Ok(encoder.open_with(options.to_dict())?)
+    }
+
+    /// Write all packets the encoder of the current segment has available.
+    fn write_encoded_packets(&mut self, state: &mut CutState) -> Result<()> {
+        let Some(encoder) = state.encoder.as_mut() else {
+            return Ok(());
+        };
+        let mut packets = Vec::new();
+        loop {
+            let mut packet = AvPacket::empty();
+            match encoder.receive_packet(&mut packet) {
+                Ok(()) => packets.push(packet),
+                Err(AvError::Eof) => break,
+                Err(AvError::Other { errno }) if errno == EAGAIN => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        for mut packet in packets {
+            packet.set_dts(packet.dts().map(|dts| dts - self.reorder_delay));
+            let packet = match self.bitstream {
+                Bitstream::LengthPrefixed {
+                    nal_length_size, ..
+                } => with_data(
+                    &packet,
+                    &annexb_to_length_prefixed(packet.data().unwrap_or_default(), nal_length_size),
+                ),
+                Bitstream::AsIs => packet,
+            };
+            self.write_packet(self.video_stream_index, packet, self.plan.start)?;
+        }
+        Ok(())
+    }
+
+    /// Copy a source video packet into the clip.
+    fn copy_video_packet(&mut self, state: &mut CutState, packet: AvPacket) -> Result<()> {
+        let packet = match self.bitstream {
+            Bitstream::LengthPrefixed {
+                ref parameter_sets, ..
+            } if state.parameter_sets_pending => {
+                let mut data = parameter_sets.clone();
+                data.extend_from_slice(packet.data().unwrap_or_default());
+                with_data(&packet, &data)
+            }
+            _ => packet,
+        };
+        state.parameter_sets_pending = false;
+        state.summary.packets_copied += 1;
+        self.write_packet(self.video_stream_index, packet, self.plan.start)
+    }
+
+    /// Write a packet of a source stream to the corresponding stream of the clip.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the source stream.
+    /// * `packet` - Packet with timestamps in the time base of the source stream.
+    /// * `start` - Start of the clip in the time base of the source stream.
+    fn write_packet(
+        &mut self,
+        stream_index: usize,
+        mut packet: AvPacket,
+        start: i64,
+    ) -> Result<()> {
+        let mapping = self
+            .mapping
+            .get(&stream_index)
+            .ok_or(AvError::StreamNotFound)?;
+        let writer_stream_time_base = self
+            .writer
+            .output
+            .stream(mapping.index)
+            .ok_or(AvError::StreamNotFound)?
+            .time_base();
+        packet.set_pts(packet.pts().map(|pts| pts - start));
+        packet.set_dts(packet.dts().map(|dts| dts - start));
+        packet.set_stream(mapping.index);
+        packet.set_position(-1);
+        packet.rescale_ts(mapping.time_base, writer_stream_time_base);
+        self.writer.write_interleaved(&mut packet)
+    }
+}
+
+unsafe impl Send for SmartCut {}
+unsafe impl Sync for SmartCut {}
+
+/// Segment of the clip that is currently being produced.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Re-encoded part before the first copied keyframe.
+    Head,
+    /// Copied part.
+    Copy,
+    /// Re-encoded part after the last copied GOP.
+    Tail,
+}
+
+/// Mutable state of a running cut.
+struct CutState {
+    decoder: AvDecoder,
+    encoder: Option<AvEncoder>,
+    segment: Segment,
+    parameter_sets_pending: bool,
+    summary: SmartCutSummary,
+}
+
+/// Where a source stream ends up in the clip.
+struct StreamMapping {
+    index: usize,
+    time_base: AvRational,
+}
+
+/// How video packets are framed in the destination.
+enum Bitstream {
+    /// Packets are written as produced. Used for Annex B sources and codecs without NAL units.
+    AsIs,
+    /// NAL units are prefixed with their length, and parameter sets are stored out-of-band.
+    LengthPrefixed {
+        nal_length_size: usize,
+        parameter_sets: Vec<u8>,
+    },
+}
+
+/// Collect the presentation and decoding timestamps of the video keyframes from the keyframe at
+/// or before the start of the clip up to the first keyframe after the end of the clip.
+///
+/// # Arguments
+///
+/// * `reader` - Reader to scan.
+/// * `video_stream_index` - Index of video stream.
+/// * `start_milliseconds` - Start of the clip.
+/// * `end` - End of the clip in the time base of the video stream.
+fn scan_keyframes(
+    reader: &mut Reader,
+    video_stream_index: usize,
+    start_milliseconds: i64,
+    end: i64,
+) -> Result<Vec<(i64, i64)>> {
+    reader.seek_with(
+        SeekTarget::Milliseconds(start_milliseconds),
+        SeekOptions::new().with_direction(SeekDirection::Backward),
+    )?;
+    let mut keyframes = Vec::new();
+    loop {
+        let packet = match reader.read(video_stream_index) {
+            Ok(packet) => packet,
+            Err(Error::ReadExhausted) => break,
+            Err(err) => return Err(err),
+        };
+        if !packet.is_key() {
+            continue;
+        }
+        let (packet, _) = packet.into_inner_parts();
+        if let Some(pts) = packet.pts() {
+            keyframes.push((pts, packet.dts().unwrap_or(pts)));
+            if pts > end {
+                break;
+            }
+        }
+    }
+    Ok(keyframes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ffmpeg::util::format::Pixel as AvPixel;
+
+    use crate::core::decode::Decoder;
+    use crate::core::encode::{EncoderBuilder, Settings};
+    use crate::core::frame::RawFrame;
+
+    #[test]
+    fn test_run_is_frame_accurate() {
+        let directory = std::env::temp_dir();
+        let source = directory.join(format!("smartcut-source-{}.mp4", std::process::id()));
+        let clip = directory.join(format!("smartcut-clip-{}.mp4", std::process::id()));
+
+        // Three seconds at 30 fps, with a keyframe every 10 frames.
+        let mut encoder = EncoderBuilder::new(
+            source.as_path(),
+            Settings::preset_h264_yuv420p(64, 48, false),
+        )
+        .with_gop_size(10)
+        .with_closed_gop(true)
+        .build()
+        .unwrap();
+        for index in 0..90 {
+            let mut frame = RawFrame::new(AvPixel::YUV420P, 64, 48);
+            frame.data_mut(0).fill((index * 2) as u8);
+            frame.set_pts(Some(index * 1_000_000 / 30));
+            encoder.encode_raw(frame).unwrap();
+        }
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        // Both cut points fall in the middle of a GOP.
+        let cut = SmartCutBuilder::new(
+            source.as_path(),
+            clip.as_path(),
+            Time::from_secs(0.5),
+            Time::from_secs(2.5),
+        )
+        .build()
+        .unwrap();
+        assert!(!cut.plan().is_copy_only());
+        let summary = cut.run().unwrap();
+        assert!(summary.packets_copied > 0);
+        assert!(summary.frames_encoded > 0);
+
+        let mut decoder = Decoder::new(clip.as_path()).unwrap();
+        let frames = decoder.decode_raw_iter().take_while(Result::is_ok).count();
+        assert_eq!(frames, 60);
+        assert_eq!(
+            summary.packets_copied + summary.frames_encoded,
+            frames as u64
+        );
+
+        std::fs::remove_file(source).unwrap();
+        std::fs::remove_file(clip).unwrap();
+    }
+
+    #[test]
+    fn test_plan_copies_whole_gops() {
+        let plan = CutPlan::new(&[0, 100, 200, 300, 400], 50, 350);
+        assert_eq!(plan.copy, Some((100, 300)));
+        assert!(!plan.is_copy_only());
+    }
+
+    #[test]
+    fn test_plan_on_keyframes_is_copy_only() {
+        let plan = CutPlan::new(&[0, 100, 200, 300], 100, 300);
+        assert!(plan.is_copy_only());
+    }
+
+    #[test]
+    fn test_plan_within_single_gop_reencodes() {
+        let plan = CutPlan::new(&[0, 100, 200], 110, 190);
+        assert_eq!(plan.copy, None);
+        let plan = CutPlan::new(&[0, 100, 200], 50, 150);
+        assert_eq!(plan.copy, None);
+    }
+}
@@ -1,6 +1,8 @@
 extern crate ffmpeg_next as ffmpeg;
 
+use ffmpeg::codec::Id as AvCodecId;
 use ffmpeg::codec::Parameters as AvCodecParameters;
+use ffmpeg::media::Type as AvMediaType;
 use ffmpeg::{Error as AvError, Rational as AvRational};
 
 use crate::core::error::Error;
@@ -45,6 +47,18 @@ impl StreamInfo {
         })
     }
 
+    /// Get the kind of media the stream carries.
+    #[inline]
+    pub fn medium(&self) -> AvMediaType {
+        self.codec_parameters.medium()
+    }
+
+    /// Get the codec of the stream.
+    #[inline]
+    pub fn codec_id(&self) -> AvCodecId {
+        self.codec_parameters.id()
+    }
+
     /// Turn information back into parts for usage.
     ///
     /// Note: Consumes stream information object.
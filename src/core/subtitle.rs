@@ -0,0 +1,500 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::codec::decoder::Subtitle as AvSubtitleDecoder;
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::codec::subtitle::Rect as AvRect;
+use ffmpeg::codec::Context as AvContext;
+use ffmpeg::{Error as AvError, Subtitle as AvSubtitle};
+
+use crate::core::error::Error;
+use crate::core::io::{Reader, ReaderBuilder};
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::packet::Packet;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Text subtitle file formats that can be exported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    /// WebVTT (`.vtt`).
+    WebVtt,
+    /// SubRip (`.srt`).
+    Srt,
+}
+
+impl SubtitleFormat {
+    /// Guess the subtitle format from the extension of a path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path with `.vtt` or `.srt` extension.
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        match path
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "vtt" => Some(SubtitleFormat::WebVtt),
+            "srt" => Some(SubtitleFormat::Srt),
+            _ => None,
+        }
+    }
+}
+
+/// A single subtitle cue: text that is shown between `start` and `end`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: Time,
+    pub end: Time,
+    pub text: String,
+}
+
+/// Builds a [`SubtitleExporter`].
+pub struct SubtitleExporterBuilder<'a> {
+    source: Location,
+    options: Option<&'a Options>,
+    stream_index: Option<usize>,
+}
+
+impl<'a> SubtitleExporterBuilder<'a> {
+    /// Create a new exporter builder for the specified source.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read subtitles from.
+    pub fn new(source: impl Into<Location>) -> Self {
+        Self {
+            source: source.into(),
+            options: None,
+            stream_index: None,
+        }
+    }
+
+    /// Specify options for the backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to input.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Select the subtitle stream to export. By default the best subtitle stream is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of subtitle stream in source.
+    pub fn with_stream_index(mut self, stream_index: usize) -> Self {
+        self.stream_index = Some(stream_index);
+        self
+    }
+
+    /// Build [`SubtitleExporter`].
+    pub fn build(self) -> Result<SubtitleExporter> {
+        let mut reader_builder = ReaderBuilder::new(self.source);
+        if let Some(options) = self.options {
+            reader_builder = reader_builder.with_options(options);
+        }
+        let reader = reader_builder.build()?;
+        let stream_index = match self.stream_index {
+            Some(stream_index) => stream_index,
+            None => reader.best_subtitle_stream_index()?,
+        };
+
+        let stream = reader
+            .input
+            .stream(stream_index)
+            .ok_or(AvError::StreamNotFound)?;
+        let decoder = AvContext::from_parameters(stream.parameters())?
+            .decoder()
+            .subtitle()?;
+
+        Ok(SubtitleExporter {
+            reader,
+            stream_index,
+            decoder,
+        })
+    }
+}
+
+/// Converts a text-based subtitle or caption track into standalone WebVTT or SubRip files.
+///
+/// Only text-based subtitles (e.g. `mov_text`, `subrip`, `ass`, `webvtt`, `eia_608`) can be
+/// exported. Cues from bitmap-based subtitles (e.g. PGS or DVB) are skipped.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut exporter = SubtitleExporter::new(Path::new("movie.mkv")).unwrap();
+/// exporter.export(Path::new("movie.vtt")).unwrap();
+/// ```
+pub struct SubtitleExporter {
+    reader: Reader,
+    stream_index: usize,
+    decoder: AvSubtitleDecoder,
+}
+
+impl SubtitleExporter {
+    /// Create an exporter for the best subtitle stream in the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to read subtitles from.
+    #[inline]
+    pub fn new(source: impl Into<Location>) -> Result<Self> {
+        SubtitleExporterBuilder::new(source).build()
+    }
+
+    /// Decode all cues in the subtitle stream.
+    pub fn cues(&mut self) -> Result<Vec<Cue>> {
+        let mut cues = CueList::default();
+        loop {
+            let packet = match self.reader.read(self.stream_index) {
+                Ok(packet) => packet,
+                Err(Error::ReadExhausted) => break,
+                Err(err) => return Err(err),
+            };
+            decode_cues(&mut self.decoder, Some(packet), &mut cues)?;
+        }
+        // Decoders with delay, such as those of closed captions, still hold cues at this point.
+        decode_cues(&mut self.decoder, None, &mut cues)?;
+
+        Ok(cues.finish())
+    }
+
+    /// Export the subtitle stream to a file. The format is derived from the file extension, and
+    /// defaults to WebVTT.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to write to.
+    pub fn export(&mut self, path: &std::path::Path) -> Result<()> {
+        let format = SubtitleFormat::from_path(path).unwrap_or(SubtitleFormat::WebVtt);
+        let cues = self.cues()?;
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        write_cues(&mut file, &cues, format)?;
+        std::io::Write::flush(&mut file)?;
+        Ok(())
+    }
+}
+
+unsafe impl Send for SubtitleExporter {}
+unsafe impl Sync for SubtitleExporter {}
+
+/// How long the last cue is shown if its end time is not known.
+const LAST_CUE_DURATION_SECS: f32 = 5.0;
+
+/// Cues in decoding order. Cues without an end time end where the next cue starts.
+#[derive(Default)]
+struct CueList {
+    cues: Vec<Cue>,
+    // Whether or not the end time of the last cue is still unknown.
+    open_ended: bool,
+}
+
+impl CueList {
+    /// Add a cue.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Start time of the cue.
+    /// * `end` - End time of the cue, or `None` if it is not known.
+    /// * `text` - Text of the cue.
+    fn push(&mut self, start: Time, end: Option<Time>, text: String) {
+        if self.open_ended {
+            if let Some(last) = self.cues.last_mut() {
+                last.end = start;
+            }
+        }
+        self.open_ended = end.is_none();
+        self.cues.push(Cue {
+            start,
+            end: end.unwrap_or(start),
+            text,
+        });
+    }
+
+    /// Get the cues, ending the last cue after a default duration if its end time is not known.
+    fn finish(mut self) -> Vec<Cue> {
+        if self.open_ended {
+            if let Some(last) = self.cues.last_mut() {
+                last.end = last
+                    .start
+                    .aligned_with(Time::from_secs(LAST_CUE_DURATION_SECS))
+                    .add();
+            }
+        }
+        self.cues
+    }
+}
+
+/// Decode a subtitle packet and add the cues it produces. Without a packet, the decoder is
+/// flushed instead.
+///
+/// # Arguments
+///
+/// * `decoder` - Subtitle decoder.
+/// * `packet` - Packet to decode, or `None` to flush the decoder.
+/// * `cues` - Cues to add to.
+fn decode_cues(
+    decoder: &mut AvSubtitleDecoder,
+    packet: Option<Packet>,
+    cues: &mut CueList,
+) -> Result<()> {
+    let Some(packet) = packet else {
+        loop {
+            let mut subtitle = AvSubtitle::new();
+            if !decoder.decode(&AvPacket::empty(), &mut subtitle)? {
+                return Ok(());
+            }
+            // Flushed subtitles carry their own timestamp, in microseconds.
+            let start = Time::new(subtitle.pts(), (1, 1_000_000).into());
+            push_subtitle_cue(&subtitle, start, None, cues);
+        }
+    };
+
+    let packet_start = packet.pts();
+    let packet_duration = packet.duration();
+    let (packet, _) = packet.into_inner_parts();
+
+    let mut subtitle = AvSubtitle::new();
+    if decoder.decode(&packet, &mut subtitle)? {
+        let packet_duration = packet_duration
+            .into_value()
+            .is_some_and(|duration| duration > 0)
+            .then_some(packet_duration);
+        push_subtitle_cue(&subtitle, packet_start, packet_duration, cues);
+    }
+    Ok(())
+}
+
+/// Add the text of a decoded subtitle as a cue, if it has any.
+///
+/// # Arguments
+///
+/// * `subtitle` - Decoded subtitle.
+/// * `base` - Time that the display times of the subtitle are relative to.
+/// * `duration` - Duration of the packet the subtitle was decoded from, if known.
+/// * `cues` - Cues to add to.
+fn push_subtitle_cue(
+    subtitle: &AvSubtitle,
+    base: Time,
+    duration: Option<Time>,
+    cues: &mut CueList,
+) {
+    let text = subtitle_text(subtitle);
+    if text.is_empty() {
+        return;
+    }
+
+    // Display times of the subtitle are in milliseconds relative to the packet.
+    let start = base
+        .aligned_with(Time::new(Some(subtitle.start() as i64), (1, 1000).into()))
+        .add();
+    let end = if subtitle.end() > subtitle.start() {
+        Some(
+            base.aligned_with(Time::new(Some(subtitle.end() as i64), (1, 1000).into()))
+                .add(),
+        )
+    } else {
+        duration.map(|duration| base.aligned_with(duration).add())
+    };
+    cues.push(start, end, text);
+}
+
+/// Write cues in the given subtitle format.
+///
+/// # Arguments
+///
+/// * `out` - Writer to write to.
+/// * `cues` - Cues to write.
+/// * `format` - Subtitle file format.
+pub fn write_cues(
+    out: &mut impl std::io::Write,
+    cues: &[Cue],
+    format: SubtitleFormat,
+) -> std::io::Result<()> {
+    match format {
+        SubtitleFormat::WebVtt => {
+            writeln!(out, "WEBVTT")?;
+            for cue in cues {
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "{} --> {}",
+                    format_timestamp(cue.start, '.'),
+                    format_timestamp(cue.end, '.'),
+                )?;
+                writeln!(out, "{}", cue.text)?;
+            }
+        }
+        SubtitleFormat::Srt => {
+            for (index, cue) in cues.iter().enumerate() {
+                if index > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}", index + 1)?;
+                writeln!(
+                    out,
+                    "{} --> {}",
+                    format_timestamp(cue.start, ','),
+                    format_timestamp(cue.end, ','),
+                )?;
+                writeln!(out, "{}", cue.text)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collect the text of all text-based rects in the subtitle.
+fn subtitle_text(subtitle: &AvSubtitle) -> String {
+    subtitle
+        .rects()
+        .filter_map(|rect| match rect {
+            AvRect::Text(text) => Some(text.get().trim().to_string()),
+            AvRect::Ass(ass) => Some(ass_dialogue_text(ass.get())),
+            AvRect::Bitmap(_) | AvRect::None(_) => None,
+        })
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Extract plain text from an ASS dialogue event as produced by the backend decoders:
+/// `ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text`. Override tags such as
+/// `{\i1}` are removed and line breaks are converted.
+fn ass_dialogue_text(dialogue: &str) -> String {
+    const FIELDS_BEFORE_TEXT: usize = 8;
+
+    let dialogue = dialogue.strip_prefix("Dialogue: ").unwrap_or(dialogue);
+    let text = dialogue
+        .splitn(FIELDS_BEFORE_TEXT + 1, ',')
+        .nth(FIELDS_BEFORE_TEXT)
+        .unwrap_or_default();
+
+    let mut out = String::with_capacity(text.len());
+    let mut in_override = false;
+    for c in text.chars() {
+        match c {
+            '{' => in_override = true,
+            '}' if in_override => in_override = false,
+            _ if !in_override => out.push(c),
+            _ => {}
+        }
+    }
+
+    out.replace("\\N", "\n")
+        .replace("\\n", "\n")
+        .replace("\\h", " ")
+        .trim()
+        .to_string()
+}
+
+/// Format a timestamp as `HH:MM:SS.mmm` (WebVTT) or `HH:MM:SS,mmm` (SubRip).
+fn format_timestamp(time: Time, separator: char) -> String {
+    let total_millis = (time.as_secs_f64().max(0.0) * 1000.0).round() as u64;
+    let millis = total_millis % 1000;
+    let secs = (total_millis / 1000) % 60;
+    let mins = (total_millis / 60_000) % 60;
+    let hours = total_millis / 3_600_000;
+    format!("{hours:02}:{mins:02}:{secs:02}{separator}{millis:03}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(Time::from_secs(0.0), '.'), "00:00:00.000");
+        assert_eq!(
+            format_timestamp(Time::from_secs_f64(3723.456), ','),
+            "01:02:03,456"
+        );
+    }
+
+    #[test]
+    fn test_ass_dialogue_text() {
+        assert_eq!(
+            ass_dialogue_text("0,0,Default,,0,0,0,,{\\i1}Hello{\\i0}\\Nworld, again"),
+            "Hello\nworld, again",
+        );
+    }
+
+    fn subrip_packet(text: &str, pts_millis: i64, duration_millis: i64) -> Packet {
+        let mut packet = AvPacket::copy(text.as_bytes());
+        packet.set_pts(Some(pts_millis));
+        packet.set_duration(duration_millis);
+        Packet::new(packet, (1, 1000).into())
+    }
+
+    #[test]
+    fn test_decode_cues() {
+        let codec = ffmpeg::decoder::find(ffmpeg::codec::Id::SUBRIP).unwrap();
+        let mut decoder = AvContext::new_with_codec(codec)
+            .decoder()
+            .subtitle()
+            .unwrap();
+        let mut cues = CueList::default();
+        for packet in [
+            subrip_packet("One", 1000, 0),
+            subrip_packet("Two", 3000, 1500),
+            subrip_packet("Three", 6000, 0),
+        ] {
+            decode_cues(&mut decoder, Some(packet), &mut cues).unwrap();
+        }
+        decode_cues(&mut decoder, None, &mut cues).unwrap();
+
+        let cues = cues.finish();
+        let times = cues
+            .iter()
+            .map(|cue| (cue.start.as_secs_f64(), cue.end.as_secs_f64()))
+            .collect::<Vec<_>>();
+        let texts = cues.iter().map(|cue| cue.text.as_str()).collect::<Vec<_>>();
+        // Cues without an end time end at the next cue, or after a default duration.
+        assert_eq!(times, vec![(1.0, 3.0), (3.0, 4.5), (6.0, 11.0)]);
+        assert_eq!(texts, vec!["One", "Two", "Three"]);
+    }
+
+    #[test]
+    fn test_write_cues_webvtt() {
+        let cues = vec![Cue {
+            start: Time::from_secs(1.0),
+            end: Time::from_secs(2.5),
+            text: "Hello".to_string(),
+        }];
+        let mut out = Vec::new();
+        write_cues(&mut out, &cues, SubtitleFormat::WebVtt).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.500\nHello\n",
+        );
+    }
+
+    #[test]
+    fn test_write_cues_srt() {
+        let cues = vec![
+            Cue {
+                start: Time::from_secs(1.0),
+                end: Time::from_secs(2.0),
+                text: "One".to_string(),
+            },
+            Cue {
+                start: Time::from_secs(3.0),
+                end: Time::from_secs(4.0),
+                text: "Two".to_string(),
+            },
+        ];
+        let mut out = Vec::new();
+        write_cues(&mut out, &cues, SubtitleFormat::Srt).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\nOne\n\n2\n00:00:03,000 --> 00:00:04,000\nTwo\n",
+        );
+    }
+}
@@ -0,0 +1,516 @@
+use crate::core::decode::{Decoder, DecoderBuilder};
+use crate::core::error::Error;
+use crate::core::frame::RawFrame;
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::resize::Resize;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Playback clock that can be shared between multiple sources so that they stay in lockstep.
+///
+/// The clock is cheap to clone: all clones refer to the same underlying clock, so pausing or
+/// seeking one affects all of them.
+#[derive(Debug, Clone, Default)]
+pub struct MasterClock {
+    state: std::sync::Arc<std::sync::Mutex<ClockState>>,
+}
+
+#[derive(Debug, Default)]
+struct ClockState {
+    /// Position at the moment the clock was last started, paused or seeked.
+    base: std::time::Duration,
+    /// When the clock was last started, or `None` if paused.
+    running_since: Option<std::time::Instant>,
+    /// Incremented on every seek, so that observers can tell a jump from regular playback.
+    seek_count: u64,
+}
+
+impl ClockState {
+    fn position(&self) -> std::time::Duration {
+        match self.running_since {
+            Some(running_since) => self.base + running_since.elapsed(),
+            None => self.base,
+        }
+    }
+}
+
+impl MasterClock {
+    /// Create a new clock. The clock starts paused at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start or resume the clock. Does nothing if the clock is already running.
+    pub fn play(&self) {
+        let mut state = self.lock();
+        if state.running_since.is_none() {
+            state.running_since = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Pause the clock. Does nothing if the clock is already paused.
+    pub fn pause(&self) {
+        let mut state = self.lock();
+        state.base = state.position();
+        state.running_since = None;
+    }
+
+    /// Move the clock to a new position. The clock keeps running if it was running.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position to move to.
+    pub fn seek(&self, position: std::time::Duration) {
+        let mut state = self.lock();
+        state.base = position;
+        state.seek_count += 1;
+        if state.running_since.is_some() {
+            state.running_since = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Current position of the clock.
+    pub fn position(&self) -> std::time::Duration {
+        self.lock().position()
+    }
+
+    /// Whether or not the clock is paused.
+    pub fn is_paused(&self) -> bool {
+        self.lock().running_since.is_none()
+    }
+
+    /// Call `callback` whenever playback crosses one of the given timestamps. Cues are only fired
+    /// by regular playback: seeking over a cue does not fire it, but seeking back before it arms
+    /// it again.
+    ///
+    /// The callback runs on a background thread. Dropping the returned handle stops watching.
+    ///
+    /// # Arguments
+    ///
+    /// * `cues` - Timestamps to watch.
+    /// * `callback` - Called with the cue that was crossed.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let _watch = group.clock().notify_at(&[Time::from_secs(12.5)], |cue| {
+    ///     println!("reached cue point at {cue}");
+    /// });
+    /// ```
+    pub fn notify_at(
+        &self,
+        cues: &[Time],
+        mut callback: impl FnMut(Time) + Send + 'static,
+    ) -> CueWatch {
+        // Longest time to sleep before checking the clock again, bounds how long it takes to notice
+        // that the clock was paused, resumed or seeked.
+        const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(20);
+
+        let mut cues = cues
+            .iter()
+            .map(|&cue| (std::time::Duration::from(cue), cue))
+            .collect::<Vec<_>>();
+        cues.sort_by_key(|&(position, _)| position);
+
+        let clock = self.clone();
+        let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let thread = std::thread::spawn({
+            let stop = stop.clone();
+            move || {
+                let (mut last_position, mut last_seek_count) = clock.position_and_seek_count();
+                while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    let (position, seek_count) = clock.position_and_seek_count();
+                    if seek_count == last_seek_count {
+                        for &(_, cue) in &cues[crossed_cues(&cues, last_position, position)] {
+                            callback(cue);
+                        }
+                    }
+                    last_position = position;
+                    last_seek_count = seek_count;
+
+                    let until_next_cue = cues
+                        .iter()
+                        .find(|&&(cue_position, _)| cue_position > position)
+                        .map(|&(cue_position, _)| cue_position - position);
+                    let sleep = match until_next_cue {
+                        Some(until_next_cue) if !clock.is_paused() => {
+                            until_next_cue.min(POLL_INTERVAL)
+                        }
+                        _ => POLL_INTERVAL,
+                    };
+                    std::thread::sleep(sleep);
+                }
+            }
+        });
+
+        CueWatch {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn position_and_seek_count(&self) -> (std::time::Duration, u64) {
+        let state = self.lock();
+        (state.position(), state.seek_count)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ClockState> {
+        // The state is always consistent, so a poisoned lock can be used safely.
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Handle to cue point notifications created with [`MasterClock::notify_at`]. Notifications stop
+/// when the handle is dropped.
+pub struct CueWatch {
+    stop: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Drop for CueWatch {
+    fn drop(&mut self) {
+        self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Find the cues that lie in `(last_position, position]`.
+///
+/// # Arguments
+///
+/// * `cues` - Cues sorted by position.
+/// * `last_position` - Position at the previous check.
+/// * `position` - Current position.
+fn crossed_cues(
+    cues: &[(std::time::Duration, Time)],
+    last_position: std::time::Duration,
+    position: std::time::Duration,
+) -> std::ops::Range<usize> {
+    if position <= last_position {
+        return 0..0;
+    }
+    let start = cues.partition_point(|&(cue_position, _)| cue_position <= last_position);
+    let end = cues.partition_point(|&(cue_position, _)| cue_position <= position);
+    start..end
+}
+
+/// Builds a [`SyncGroup`].
+pub struct SyncGroupBuilder<'a> {
+    sources: Vec<Location>,
+    options: Option<&'a Options>,
+    resize: Option<Resize>,
+    clock: Option<MasterClock>,
+}
+
+impl<'a> SyncGroupBuilder<'a> {
+    /// Create an empty group builder.
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            options: None,
+            resize: None,
+            clock: None,
+        }
+    }
+
+    /// Add a source to the group, e.g. one camera angle.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to add.
+    pub fn with_source(mut self, source: impl Into<Location>) -> Self {
+        self.sources.push(source.into());
+        self
+    }
+
+    /// Specify options for the backend. The options are used for all sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to input.
+    pub fn with_options(mut self, options: &'a Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Resize the frames of all sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `resize` - Resize to apply.
+    pub fn with_resize(mut self, resize: Resize) -> Self {
+        self.resize = Some(resize);
+        self
+    }
+
+    /// Use an existing clock, for example to keep several groups in sync.
+    ///
+    /// # Arguments
+    ///
+    /// * `clock` - Clock to use.
+    pub fn with_clock(mut self, clock: MasterClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Build [`SyncGroup`]. All sources are opened and their first frame is decoded.
+    pub fn build(self) -> Result<SyncGroup> {
+        let sources = self
+            .sources
+            .into_iter()
+            .map(|source| {
+                let mut decoder_builder = DecoderBuilder::new(source);
+                if let Some(options) = self.options {
+                    decoder_builder = decoder_builder.with_options(options);
+                }
+                if let Some(resize) = self.resize {
+                    decoder_builder = decoder_builder.with_resize(resize);
+                }
+                Ok(SyncSource::new(decoder_builder.build()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut group = SyncGroup {
+            sources,
+            clock: self.clock.unwrap_or_default(),
+        };
+        group.preroll()?;
+        Ok(group)
+    }
+}
+
+impl Default for SyncGroupBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Plays multiple sources (e.g. camera angles) frame-synchronized on a single [`MasterClock`].
+///
+/// Playback starts behind a barrier: every source has decoded its first frame before the clock is
+/// started. Play, pause and seek always apply to all sources at once.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut group = SyncGroupBuilder::new()
+///     .with_source(Path::new("angle1.mp4"))
+///     .with_source(Path::new("angle2.mp4"))
+///     .build()
+///     .unwrap();
+/// group.play();
+/// while !group.is_finished() {
+///     for frame in group.frames().unwrap() {
+///         // Render the frame of each angle, `None` if the source has not started yet.
+///     }
+/// }
+/// ```
+pub struct SyncGroup {
+    sources: Vec<SyncSource>,
+    clock: MasterClock,
+}
+
+impl SyncGroup {
+    /// Get the clock that drives the group.
+    #[inline]
+    pub fn clock(&self) -> &MasterClock {
+        &self.clock
+    }
+
+    /// Number of sources in the group.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Whether or not the group has no sources.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Start or resume playback of all sources.
+    pub fn play(&self) {
+        self.clock.play();
+    }
+
+    /// Pause playback of all sources.
+    pub fn pause(&self) {
+        self.clock.pause();
+    }
+
+    /// Seek all sources to the same position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position to seek to.
+    pub fn seek(&mut self, position: std::time::Duration) -> Result<()> {
+        // Hold the clock at the target until all sources have caught up, so that none of them
+        // starts ahead of the others.
+        let was_paused = self.clock.is_paused();
+        self.clock.pause();
+        self.clock.seek(position);
+
+        for source in self.sources.iter_mut() {
+            source.seek(position)?;
+        }
+        self.preroll()?;
+
+        if !was_paused {
+            self.clock.play();
+        }
+        Ok(())
+    }
+
+    /// Get the frame of each source that should be shown at the current clock position.
+    ///
+    /// # Return value
+    ///
+    /// One entry per source, in the order in which they were added. An entry is `None` if the
+    /// source has no frame for the current position yet.
+    pub fn frames(&mut self) -> Result<Vec<Option<&RawFrame>>> {
+        self.frames_at(self.clock.position())
+    }
+
+    /// Get the frame of each source that should be shown at `position`. Sources only move
+    /// forward; use [`SyncGroup::seek`] to go back.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position to get frames for.
+    pub fn frames_at(&mut self, position: std::time::Duration) -> Result<Vec<Option<&RawFrame>>> {
+        let position = position.as_secs_f64();
+        for source in self.sources.iter_mut() {
+            source.advance_to(position)?;
+        }
+        Ok(self
+            .sources
+            .iter()
+            .map(|source| source.current.as_ref().map(|(_, frame)| frame))
+            .collect())
+    }
+
+    /// Whether or not all sources have run out of frames.
+    pub fn is_finished(&self) -> bool {
+        self.sources
+            .iter()
+            .all(|source| source.exhausted && source.next.is_none())
+    }
+
+    /// Decode the first frame of every source. This is the start barrier of the group.
+    fn preroll(&mut self) -> Result<()> {
+        for source in self.sources.iter_mut() {
+            source.fill_next()?;
+        }
+        Ok(())
+    }
+}
+
+unsafe impl Send for SyncGroup {}
+unsafe impl Sync for SyncGroup {}
+
+/// A single source of a [`SyncGroup`].
+struct SyncSource {
+    decoder: Decoder,
+    /// Frame that is currently shown, with its presentation time in seconds.
+    current: Option<(f64, RawFrame)>,
+    /// Next frame to show, with its presentation time in seconds.
+    next: Option<(f64, RawFrame)>,
+    exhausted: bool,
+}
+
+impl SyncSource {
+    fn new(decoder: Decoder) -> Self {
+        Self {
+            decoder,
+            current: None,
+            next: None,
+            exhausted: false,
+        }
+    }
+
+    /// Seek the decoder and forget about buffered frames.
+    fn seek(&mut self, position: std::time::Duration) -> Result<()> {
+        self.decoder.seek(position.as_millis() as i64)?;
+        self.current = None;
+        self.next = None;
+        self.exhausted = false;
+        Ok(())
+    }
+
+    /// Make sure the next frame is decoded, if there is one.
+    fn fill_next(&mut self) -> Result<()> {
+        if self.next.is_some() || self.exhausted {
+            return Ok(());
+        }
+        match self.decoder.decode_raw() {
+            Ok(frame) => {
+                let time = Time::new(frame.pts(), self.decoder.time_base()).as_secs_f64();
+                self.next = Some((time, frame));
+            }
+            Err(Error::DecodeExhausted) => self.exhausted = true,
+            Err(err) => return Err(err),
+        }
+        Ok(())
+    }
+
+    /// Move forward until the current frame is the last frame at or before `position`.
+    fn advance_to(&mut self, position: f64) -> Result<()> {
+        loop {
+            self.fill_next()?;
+            match self.next {
+                Some((time, _)) if time <= position => {
+                    self.current = self.next.take();
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_starts_paused() {
+        let clock = MasterClock::new();
+        assert!(clock.is_paused());
+        assert_eq!(clock.position(), std::time::Duration::ZERO);
+    }
+
+    #[test]
+    fn test_clock_seek_while_paused() {
+        let clock = MasterClock::new();
+        clock.seek(std::time::Duration::from_secs(5));
+        assert_eq!(clock.position(), std::time::Duration::from_secs(5));
+        assert!(clock.is_paused());
+    }
+
+    #[test]
+    fn test_crossed_cues() {
+        let cues = [1, 2, 2, 5]
+            .map(|secs| (std::time::Duration::from_secs(secs), Time::from_secs(secs as f32)));
+        let secs = std::time::Duration::from_secs;
+        assert_eq!(crossed_cues(&cues, secs(0), secs(1)), 0..1);
+        assert_eq!(crossed_cues(&cues, secs(1), secs(3)), 1..3);
+        assert_eq!(crossed_cues(&cues, secs(3), secs(4)), 3..3);
+        assert_eq!(crossed_cues(&cues, secs(4), secs(2)), 0..0);
+    }
+
+    #[test]
+    fn test_clock_is_shared_between_clones() {
+        let clock = MasterClock::new();
+        let other = clock.clone();
+        clock.play();
+        assert!(!other.is_paused());
+        other.pause();
+        assert!(clock.is_paused());
+    }
+}
@@ -0,0 +1,542 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::time::Instant;
+
+use ffmpeg::util::format::sample::Type as AvSampleType;
+use ffmpeg::util::format::Pixel as AvPixel;
+use ffmpeg::util::format::Sample as AvSampleFormat;
+use ffmpeg::ChannelLayout as AvChannelLayout;
+
+use crate::core::audio::RawAudioFrame;
+use crate::core::audio_convert::AudioFormat;
+use crate::core::encode::Encoder;
+use crate::core::error::Error;
+use crate::core::frame::RawFrame;
+use crate::core::recorder::Recorder;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Synthetic A/V sync test pattern: a white flash in the video and a beep in the audio, both at the
+/// same known timestamps. Play the pattern through a pipeline and feed the output into a
+/// [`SyncAnalyzer`] to measure how far audio and video drift apart.
+///
+/// # Example
+///
+/// ```ignore
+/// let pattern = SyncPattern::new(640, 360);
+/// let settings = Settings::preset_h264_yuv420p(640, 360, false);
+/// let mut recorder = RecorderBuilder::new(Path::new("sync.mp4"), settings)
+///     .with_audio(pattern.audio_format())
+///     .build()
+///     .unwrap();
+/// pattern.record(&mut recorder, Duration::from_secs(10)).unwrap();
+/// recorder.finish().unwrap();
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SyncPattern {
+    width: u32,
+    height: u32,
+    frame_rate: u32,
+    sample_rate: u32,
+    interval: std::time::Duration,
+    flash_duration: std::time::Duration,
+    beep_frequency: f32,
+}
+
+impl SyncPattern {
+    /// Create a pattern with a flash and beep of 100 ms every second, at 30 fps and 48 kHz.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the video.
+    /// * `height` - Height of the video.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            frame_rate: 30,
+            sample_rate: 48_000,
+            interval: std::time::Duration::from_secs(1),
+            flash_duration: std::time::Duration::from_millis(100),
+            beep_frequency: 1_000.0,
+        }
+    }
+
+    /// Set the video frame rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_rate` - Frames per second.
+    pub fn with_frame_rate(mut self, frame_rate: u32) -> Self {
+        self.frame_rate = frame_rate.max(1);
+        self
+    }
+
+    /// Set the audio sample rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Samples per second.
+    pub fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate.max(1);
+        self
+    }
+
+    /// Set the time between two events, and how long each flash and beep lasts.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Time between the start of two events.
+    /// * `flash_duration` - Duration of each flash and beep.
+    pub fn with_timing(
+        mut self,
+        interval: std::time::Duration,
+        flash_duration: std::time::Duration,
+    ) -> Self {
+        self.interval = interval;
+        self.flash_duration = flash_duration.min(interval);
+        self
+    }
+
+    /// Timestamps at which flashes and beeps start, up to `duration`.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Length of the pattern.
+    pub fn event_times(&self, duration: std::time::Duration) -> Vec<std::time::Duration> {
+        (0..)
+            .map(|index| self.interval * index)
+            .take_while(|&time| time < duration)
+            .collect()
+    }
+
+    /// Whether or not an event (flash or beep) is active at `time`.
+    fn is_active(&self, time: std::time::Duration) -> bool {
+        let interval = self.interval.as_nanos().max(1);
+        time.as_nanos() % interval < self.flash_duration.as_nanos()
+    }
+
+    /// Produce the video frame with the given index: white during a flash, black otherwise. The
+    /// frame is in RGB24, and its PTS is the frame index in units of `1 / frame_rate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of frame.
+    pub fn video_frame(&self, index: u64) -> RawFrame {
+        let time = std::time::Duration::from_secs_f64(index as f64 / self.frame_rate as f64);
+        let value = if self.is_active(time) { 255 } else { 0 };
+
+        let mut frame = RawFrame::new(AvPixel::RGB24, self.width, self.height);
+        frame.data_mut(0).fill(value);
+        frame.set_pts(Some(index as i64));
+        frame
+    }
+
+    /// Produce mono audio samples starting at sample `start`: a sine beep during an event, silence
+    /// otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Index of first sample.
+    /// * `count` - Number of samples to produce.
+    pub fn audio_samples(&self, start: u64, count: usize) -> Vec<f32> {
+        (start..start + count as u64)
+            .map(|index| {
+                let secs = index as f64 / self.sample_rate as f64;
+                if self.is_active(std::time::Duration::from_secs_f64(secs)) {
+                    (secs * self.beep_frequency as f64 * std::f64::consts::TAU).sin() as f32 * 0.5
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Encode the video part of the pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoder` - Encoder to encode with. Must have the same size as the pattern.
+    /// * `duration` - Length of the pattern.
+    pub fn encode_video(&self, encoder: &mut Encoder, duration: std::time::Duration) -> Result<()> {
+        let frame_count = (duration.as_secs_f64() * self.frame_rate as f64).ceil() as u64;
+        let pattern_time_base = (1, self.frame_rate as i32).into();
+        for index in 0..frame_count {
+            let mut frame = self.video_frame(index);
+            frame.set_pts(
+                Time::new(Some(index as i64), pattern_time_base)
+                    .aligned_with_rational(encoder.time_base())
+                    .into_value(),
+            );
+            encoder.encode_raw(frame)?;
+        }
+        Ok(())
+    }
+
+    /// Format of the audio part of the pattern: mono 32-bit float samples at the sample rate of the
+    /// pattern. Pass it to [`RecorderBuilder::with_audio`] to record the pattern.
+    ///
+    /// [`RecorderBuilder::with_audio`]: crate::core::recorder::RecorderBuilder::with_audio
+    pub fn audio_format(&self) -> AudioFormat {
+        AudioFormat::new(
+            AvSampleFormat::F32(AvSampleType::Packed),
+            AvChannelLayout::MONO,
+            self.sample_rate,
+        )
+    }
+
+    /// Record the pattern, muxing flashes and beeps into the same file so that both share one
+    /// timeline. Audio is pushed alongside each video frame, so the streams stay interleaved.
+    ///
+    /// # Arguments
+    ///
+    /// * `recorder` - Recorder to record to. Must have the same size as the pattern and be built
+    ///   with audio.
+    /// * `duration` - Length of the pattern.
+    pub fn record(&self, recorder: &mut Recorder, duration: std::time::Duration) -> Result<()> {
+        let origin = Instant::now();
+        let frame_count = (duration.as_secs_f64() * self.frame_rate as f64).ceil() as u64;
+        let sample_count = (duration.as_secs_f64() * self.sample_rate as f64).round() as u64;
+        let mut next_sample = 0;
+        for index in 0..frame_count {
+            let time = std::time::Duration::from_secs_f64(index as f64 / self.frame_rate as f64);
+            recorder.push_video_raw(self.video_frame(index), origin + time)?;
+
+            // Push the audio up to the start of the next frame.
+            let end = (((index + 1) as f64 / self.frame_rate as f64) * self.sample_rate as f64)
+                .round() as u64;
+            let end = end.min(sample_count);
+            if end > next_sample {
+                let time = std::time::Duration::from_secs_f64(
+                    next_sample as f64 / self.sample_rate as f64,
+                );
+                let frame = self.audio_frame(next_sample, (end - next_sample) as usize);
+                recorder.push_audio(frame, origin + time)?;
+                next_sample = end;
+            }
+        }
+        Ok(())
+    }
+
+    /// Produce a frame of audio samples in [`SyncPattern::audio_format`].
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Index of first sample.
+    /// * `count` - Number of samples to produce.
+    fn audio_frame(&self, start: u64, count: usize) -> RawAudioFrame {
+        let mut frame = RawAudioFrame::new(
+            AvSampleFormat::F32(AvSampleType::Packed),
+            count,
+            AvChannelLayout::MONO,
+        );
+        frame.set_rate(self.sample_rate);
+        let data = frame.data_mut(0);
+        for (bytes, sample) in data
+            .chunks_exact_mut(4)
+            .zip(self.audio_samples(start, count))
+        {
+            bytes.copy_from_slice(&sample.to_ne_bytes());
+        }
+        frame
+    }
+}
+
+/// Result of an A/V sync measurement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncReport {
+    /// Offset of each matched event in seconds: audio onset minus video onset. Positive values
+    /// mean that audio lags behind video.
+    pub offsets: Vec<f64>,
+    /// Number of flashes that could not be matched to a beep.
+    pub unmatched: usize,
+}
+
+impl SyncReport {
+    /// Mean offset in seconds, or `None` if no events were matched.
+    pub fn mean_offset(&self) -> Option<f64> {
+        if self.offsets.is_empty() {
+            None
+        } else {
+            Some(self.offsets.iter().sum::<f64>() / self.offsets.len() as f64)
+        }
+    }
+
+    /// Largest absolute offset in seconds, or `None` if no events were matched.
+    pub fn max_abs_offset(&self) -> Option<f64> {
+        self.offsets.iter().map(|offset| offset.abs()).reduce(f64::max)
+    }
+}
+
+/// Measures A/V sync from the output of a pipeline that plays a [`SyncPattern`], by detecting the
+/// onsets of flashes and beeps and pairing them up.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut analyzer = SyncAnalyzer::new();
+/// // For every presented video frame and audio buffer:
+/// analyzer.push_video(presentation_time, &frame);
+/// analyzer.push_audio(presentation_time, &samples, sample_rate);
+/// let report = analyzer.report();
+/// assert!(report.max_abs_offset().unwrap() < 0.045);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SyncAnalyzer {
+    video_onsets: Vec<f64>,
+    audio_onsets: Vec<f64>,
+    video_active: bool,
+    audio: OnsetDetector,
+}
+
+impl SyncAnalyzer {
+    /// Brightness (0 to 255) above which a frame counts as a flash.
+    const FLASH_THRESHOLD: f64 = 128.0;
+    /// Absolute amplitude above which a sample counts as part of a beep.
+    const BEEP_THRESHOLD: f32 = 0.1;
+    /// Maximum distance in seconds between a flash and a beep to be considered the same event.
+    const MAX_PAIR_DISTANCE: f64 = 0.5;
+
+    /// Create a new analyzer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a presented video frame. The frame must be in RGB24, as produced by the decoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - Presentation time of the frame.
+    /// * `frame` - Frame that was presented.
+    pub fn push_video(&mut self, time: Time, frame: &RawFrame) {
+        let brightness = mean_brightness(frame.data(0), frame.stride(0), frame.width() * 3);
+        let active = brightness > Self::FLASH_THRESHOLD;
+        if active && !self.video_active {
+            self.video_onsets.push(time.as_secs_f64());
+        }
+        self.video_active = active;
+    }
+
+    /// Feed presented mono audio samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - Presentation time of the first sample.
+    /// * `samples` - Samples that were presented.
+    /// * `sample_rate` - Sample rate of `samples`.
+    pub fn push_audio(&mut self, time: Time, samples: &[f32], sample_rate: u32) {
+        let start = time.as_secs_f64();
+        for index in self.audio.push(samples, Self::BEEP_THRESHOLD) {
+            self.audio_onsets
+                .push(start + index as f64 / sample_rate.max(1) as f64);
+        }
+    }
+
+    /// Pair flashes with beeps and compute their offsets.
+    pub fn report(&self) -> SyncReport {
+        pair_onsets(
+            &self.video_onsets,
+            &self.audio_onsets,
+            Self::MAX_PAIR_DISTANCE,
+        )
+    }
+}
+
+/// Mean value of the bytes of a plane.
+///
+/// # Arguments
+///
+/// * `data` - Plane data.
+/// * `stride` - Number of bytes per line, including padding.
+/// * `line_size` - Number of bytes per line that contain pixels.
+fn mean_brightness(data: &[u8], stride: usize, line_size: u32) -> f64 {
+    let line_size = (line_size as usize).min(stride);
+    let (sum, count) = data
+        .chunks(stride.max(1))
+        .map(|line| &line[..line_size.min(line.len())])
+        .fold((0u64, 0u64), |(sum, count), line| {
+            (
+                sum + line.iter().map(|&value| value as u64).sum::<u64>(),
+                count + line.len() as u64,
+            )
+        });
+    if count == 0 {
+        0.0
+    } else {
+        sum as f64 / count as f64
+    }
+}
+
+/// Detects the onsets of beeps in a stream of samples. A beep only counts as ended after a run of
+/// silent samples, so that zero crossings of the sine are not mistaken for silence. The state is
+/// kept between buffers, so a beep or a run of silence may span several of them.
+#[derive(Debug, Clone, Default)]
+struct OnsetDetector {
+    // Whether or not a beep is going on.
+    active: bool,
+    // Number of consecutive silent samples seen so far.
+    silent_run: usize,
+}
+
+impl OnsetDetector {
+    /// Number of consecutive silent samples that end a beep.
+    const SILENCE_RUN: usize = 64;
+
+    /// Feed a buffer of samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Samples that follow the previously pushed samples.
+    /// * `threshold` - Absolute amplitude above which a sample counts as part of a beep.
+    ///
+    /// # Return value
+    ///
+    /// Indices into `samples` at which a beep starts.
+    fn push(&mut self, samples: &[f32], threshold: f32) -> Vec<usize> {
+        let mut onsets = Vec::new();
+        for (index, sample) in samples.iter().enumerate() {
+            if sample.abs() > threshold {
+                self.silent_run = 0;
+                if !self.active {
+                    self.active = true;
+                    onsets.push(index);
+                }
+            } else {
+                self.silent_run += 1;
+                if self.active && self.silent_run >= Self::SILENCE_RUN {
+                    self.active = false;
+                }
+            }
+        }
+        onsets
+    }
+}
+
+/// Pair every video onset with the closest audio onset within `max_distance` seconds.
+fn pair_onsets(video: &[f64], audio: &[f64], max_distance: f64) -> SyncReport {
+    let mut offsets = Vec::new();
+    let mut unmatched = 0;
+    for &video_onset in video {
+        let closest = audio
+            .iter()
+            .map(|&audio_onset| audio_onset - video_onset)
+            .filter(|offset| offset.abs() <= max_distance)
+            .reduce(|a, b| if a.abs() <= b.abs() { a } else { b });
+        match closest {
+            Some(offset) => offsets.push(offset),
+            None => unmatched += 1,
+        }
+    }
+    SyncReport { offsets, unmatched }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::encode::Settings;
+    use crate::core::recorder::RecorderBuilder;
+
+    #[test]
+    fn test_event_times() {
+        let pattern = SyncPattern::new(16, 16);
+        assert_eq!(
+            pattern.event_times(std::time::Duration::from_millis(2500)),
+            vec![
+                std::time::Duration::ZERO,
+                std::time::Duration::from_secs(1),
+                std::time::Duration::from_secs(2),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_audio_samples_and_onsets() {
+        let pattern = SyncPattern::new(16, 16).with_sample_rate(8_000);
+        let samples = pattern.audio_samples(0, 16_000);
+        let onsets = OnsetDetector::default().push(&samples, SyncAnalyzer::BEEP_THRESHOLD);
+        assert_eq!(onsets.len(), 2);
+        // Onsets lie within a few samples of the start of each second.
+        assert!(onsets[0] < 8);
+        assert!((8_000..8_008).contains(&onsets[1]));
+    }
+
+    #[test]
+    fn test_onsets_across_buffers() {
+        let pattern = SyncPattern::new(16, 16).with_sample_rate(8_000);
+        let mut detector = OnsetDetector::default();
+        // Small buffers split both the beeps and the silence between them.
+        let onsets = (0..16_000)
+            .step_by(50)
+            .flat_map(|start| {
+                detector
+                    .push(
+                        &pattern.audio_samples(start, 50),
+                        SyncAnalyzer::BEEP_THRESHOLD,
+                    )
+                    .into_iter()
+                    .map(move |index| start as usize + index)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(onsets.len(), 2);
+        assert!(onsets[0] < 8);
+        assert!((8_000..8_008).contains(&onsets[1]));
+
+        // A beep that is interrupted by less than a run of silence stays a single beep.
+        let mut detector = OnsetDetector::default();
+        assert_eq!(detector.push(&[0.5; 10], 0.1), vec![0]);
+        assert!(detector.push(&[0.0; 40], 0.1).is_empty());
+        assert!(detector.push(&[0.0; 20], 0.1).is_empty());
+        assert!(detector.push(&[0.5; 10], 0.1).is_empty());
+        // After a full run of silence, the next beep is a new onset.
+        assert!(detector.push(&[0.0; 64], 0.1).is_empty());
+        assert_eq!(detector.push(&[0.0, 0.5], 0.1), vec![1]);
+    }
+
+    #[test]
+    fn test_record() {
+        let path = std::env::temp_dir().join(format!("syncpattern-{}.mp4", std::process::id()));
+        let pattern = SyncPattern::new(64, 48);
+        let mut recorder =
+            RecorderBuilder::new(path.as_path(), Settings::preset_h264_yuv420p(64, 48, false))
+                .with_audio(pattern.audio_format())
+                .build()
+                .unwrap();
+        pattern
+            .record(&mut recorder, std::time::Duration::from_secs(2))
+            .unwrap();
+        recorder.finish().unwrap();
+        drop(recorder);
+
+        // Flashes and beeps end up in the same file.
+        let input = ffmpeg::format::input(&path).unwrap();
+        let media_types = input
+            .streams()
+            .map(|stream| stream.parameters().medium())
+            .collect::<Vec<_>>();
+        assert_eq!(media_types.len(), 2);
+        assert!(media_types.contains(&ffmpeg::media::Type::Video));
+        assert!(media_types.contains(&ffmpeg::media::Type::Audio));
+        drop(input);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_mean_brightness() {
+        let data = [255, 255, 0, 0, 255, 255, 0, 0];
+        assert_eq!(mean_brightness(&data, 4, 2), 255.0);
+        assert_eq!(mean_brightness(&data, 4, 4), 127.5);
+    }
+
+    #[test]
+    fn test_pair_onsets() {
+        let report = pair_onsets(&[1.0, 2.0, 3.0], &[1.04, 1.98, 10.0], 0.5);
+        assert_eq!(report.unmatched, 1);
+        assert_eq!(report.offsets.len(), 2);
+        assert!((report.offsets[0] - 0.04).abs() < 1e-9);
+        assert!((report.offsets[1] + 0.02).abs() < 1e-9);
+        assert!((report.mean_offset().unwrap() - 0.01).abs() < 1e-9);
+        assert!((report.max_abs_offset().unwrap() - 0.04).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,248 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use ffmpeg::codec::context::Context as AvContext;
+use ffmpeg::ffi::{FF_THREAD_FRAME, FF_THREAD_SLICE};
+
+use crate::core::error::Error;
+use crate::core::ffi;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// How a codec may divide its work between threads.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum CodecThreading {
+    /// Let the codec pick frame and/or slice threading.
+    #[default]
+    Auto,
+    /// Decode or encode multiple frames at once. Adds one frame of latency per thread.
+    Frame,
+    /// Split each frame into slices. Does not add latency, but only helps when the stream has
+    /// multiple slices per frame.
+    Slice,
+}
+
+impl CodecThreading {
+    /// Get the backend thread type flags, or `None` to keep the codec default.
+    fn thread_type(self) -> Option<i32> {
+        match self {
+            CodecThreading::Auto => None,
+            CodecThreading::Frame => Some(FF_THREAD_FRAME),
+            CodecThreading::Slice => Some(FF_THREAD_SLICE),
+        }
+    }
+}
+
+/// Scheduling priority hint for threads.
+///
+/// Priorities can only be lowered, since raising them requires elevated privileges on most
+/// systems.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ThreadPriority {
+    /// Leave the priority as it is.
+    #[default]
+    Normal,
+    /// Slightly below normal priority.
+    Low,
+    /// Only run when the system is otherwise idle.
+    Background,
+}
+
+impl ThreadPriority {
+    /// Get the niceness that corresponds with the priority, or `None` to leave it as it is.
+    fn niceness(self) -> Option<i32> {
+        match self {
+            ThreadPriority::Normal => None,
+            ThreadPriority::Low => Some(5),
+            ThreadPriority::Background => Some(19),
+        }
+    }
+}
+
+/// Limits the threads used by a decoder, encoder or transcoder, so that many of them can share
+/// constrained hardware.
+///
+/// The number of codec threads is honored everywhere. The priority and core affinity are hints:
+/// they are applied on Linux and ignored on other platforms. Threads that the codec spawns
+/// inherit the priority and affinity of the thread that opens it, so apply the budget to the
+/// current thread with [`ThreadBudget::apply_to_current_thread`] before building a decoder or
+/// encoder, or spawn the thread with [`ThreadBudget::spawn`].
+///
+/// # Example
+///
+/// ```ignore
+/// let budget = ThreadBudget::new()
+///     .with_codec_threads(2)
+///     .with_priority(ThreadPriority::Low)
+///     .with_affinity(vec![2, 3]);
+/// let handle = budget.spawn("player-video", {
+///     let budget = budget.clone();
+///     move || {
+///         let mut decoder = DecoderBuilder::new(source).with_thread_budget(budget).build()?;
+///         // ...
+///     }
+/// })?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ThreadBudget {
+    codec_threads: Option<usize>,
+    codec_threading: CodecThreading,
+    priority: ThreadPriority,
+    affinity: Option<Vec<usize>>,
+}
+
+impl ThreadBudget {
+    /// Create a budget that leaves everything at the defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of threads each codec may use.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec_threads` - Number of threads. `0` picks one per core, `1` disables codec
+    ///   threading.
+    pub fn with_codec_threads(mut self, codec_threads: usize) -> Self {
+        self.codec_threads = Some(codec_threads);
+        self
+    }
+
+    /// Set how each codec divides its work between its threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec_threading` - Threading kind.
+    pub fn with_codec_threading(mut self, codec_threading: CodecThreading) -> Self {
+        self.codec_threading = codec_threading;
+        self
+    }
+
+    /// Set the scheduling priority hint.
+    ///
+    /// # Arguments
+    ///
+    /// * `priority` - Thread priority.
+    pub fn with_priority(mut self, priority: ThreadPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Pin threads to a set of cores.
+    ///
+    /// # Arguments
+    ///
+    /// * `cores` - Indices of the cores the threads may run on.
+    pub fn with_affinity(mut self, cores: Vec<usize>) -> Self {
+        self.affinity = Some(cores);
+        self
+    }
+
+    /// Get the number of codec threads, if set.
+    #[inline]
+    pub fn codec_threads(&self) -> Option<usize> {
+        self.codec_threads
+    }
+
+    /// Get the codec threading kind.
+    #[inline]
+    pub fn codec_threading(&self) -> CodecThreading {
+        self.codec_threading
+    }
+
+    /// Get the priority hint.
+    #[inline]
+    pub fn priority(&self) -> ThreadPriority {
+        self.priority
+    }
+
+    /// Get the cores threads are pinned to, if any.
+    #[inline]
+    pub fn affinity(&self) -> Option<&[usize]> {
+        self.affinity.as_deref()
+    }
+
+    /// Apply the priority and core affinity to the calling thread. Does nothing on platforms
+    /// other than Linux.
+    pub fn apply_to_current_thread(&self) -> Result<()> {
+        if let Some(cores) = self.affinity.as_deref() {
+            if cores.is_empty() {
+                return Err(Error::InvalidConfiguration {
+                    field: "affinity",
+                    reason: "core affinity must contain at least one core".to_string(),
+                });
+            }
+            ffi::set_current_thread_affinity(cores)?;
+        }
+        if let Some(niceness) = self.priority.niceness() {
+            ffi::set_current_thread_niceness(niceness)?;
+        }
+        Ok(())
+    }
+
+    /// Spawn a named thread that runs with this budget applied. If the budget cannot be applied,
+    /// the thread still runs and a warning is logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the thread.
+    /// * `f` - Function to run on the thread.
+    pub fn spawn<F, T>(&self, name: impl Into<String>, f: F) -> Result<std::thread::JoinHandle<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let budget = self.clone();
+        let handle = std::thread::Builder::new()
+            .name(name.into())
+            .spawn(move || {
+                budget.apply_to_current_thread_or_warn();
+                f()
+            })?;
+        Ok(handle)
+    }
+
+    /// Apply the budget to the calling thread, logging a warning if that fails.
+    pub(crate) fn apply_to_current_thread_or_warn(&self) {
+        if let Err(err) = self.apply_to_current_thread() {
+            tracing::warn!("failed to apply thread budget: {err}");
+        }
+    }
+
+    /// Apply the codec thread settings to a codec context that has not been opened yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - Codec context.
+    pub(crate) fn apply_to_codec(&self, context: &mut AvContext) {
+        if self.codec_threads.is_some() || self.codec_threading != CodecThreading::Auto {
+            ffi::set_codec_context_threads(
+                context,
+                self.codec_threads,
+                self.codec_threading.thread_type(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_budget_changes_nothing() {
+        let budget = ThreadBudget::new();
+        assert_eq!(budget.codec_threads(), None);
+        assert_eq!(budget.codec_threading().thread_type(), None);
+        assert_eq!(budget.priority().niceness(), None);
+        assert!(budget.apply_to_current_thread().is_ok());
+    }
+
+    #[test]
+    fn test_empty_affinity_is_rejected() {
+        let budget = ThreadBudget::new().with_affinity(Vec::new());
+        assert!(matches!(
+            budget.apply_to_current_thread(),
+            Err(Error::InvalidConfiguration { .. })
+        ));
+    }
+}
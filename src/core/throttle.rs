@@ -0,0 +1,192 @@
+/// Bandwidth limit for simulating slow or unstable networks.
+///
+/// Throttling is applied to every packet read from the source, so that buffering, adaptive
+/// bitrate and reconnect logic can be tested deterministically without external network shaping
+/// tools. Jitter is generated from a seeded pseudo-random number generator, so the same seed will
+/// always produce the same delays.
+///
+/// # Example
+///
+/// ```ignore
+/// let throttle = Throttle::new(512).with_jitter(Duration::from_millis(50));
+/// let reader = ReaderBuilder::new(Path::new("video.mp4"))
+///     .with_throttle(throttle)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Throttle {
+    kbps: u32,
+    jitter: std::time::Duration,
+    seed: u64,
+}
+
+impl Throttle {
+    /// Default seed for the jitter generator.
+    const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+    /// Create a throttle with the given bandwidth and no jitter.
+    ///
+    /// # Arguments
+    ///
+    /// * `kbps` - Bandwidth in kilobits per second. Must be larger than zero.
+    pub fn new(kbps: u32) -> Self {
+        Self {
+            kbps: kbps.max(1),
+            jitter: std::time::Duration::ZERO,
+            seed: Self::DEFAULT_SEED,
+        }
+    }
+
+    /// Add a random delay of up to `jitter` to each packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `jitter` - Maximum extra delay per packet.
+    pub fn with_jitter(mut self, jitter: std::time::Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the seed of the jitter generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed to use.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Get the bandwidth in kilobits per second.
+    #[inline]
+    pub fn kbps(&self) -> u32 {
+        self.kbps
+    }
+
+    /// Get the maximum extra delay per packet.
+    #[inline]
+    pub fn jitter(&self) -> std::time::Duration {
+        self.jitter
+    }
+}
+
+/// Paces reads according to a [`Throttle`] by blocking the caller.
+#[derive(Debug, Clone)]
+pub struct Throttler {
+    throttle: Throttle,
+    start: Option<std::time::Instant>,
+    bytes: u64,
+    rng_state: u64,
+}
+
+impl Throttler {
+    /// Create a new throttler.
+    ///
+    /// # Arguments
+    ///
+    /// * `throttle` - Bandwidth limit to enforce.
+    pub fn new(throttle: Throttle) -> Self {
+        Self {
+            throttle,
+            start: None,
+            bytes: 0,
+            // Xorshift gets stuck on zero.
+            rng_state: throttle.seed.max(1),
+        }
+    }
+
+    /// Get the bandwidth limit.
+    #[inline]
+    pub fn throttle(&self) -> &Throttle {
+        &self.throttle
+    }
+
+    /// Restart pacing, for example after seeking. The jitter sequence is not reset.
+    pub fn reset(&mut self) {
+        self.start = None;
+        self.bytes = 0;
+    }
+
+    /// Account for `bytes` bytes that have been received, and block until they would have been
+    /// transferred at the configured bandwidth.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Number of bytes received.
+    pub fn wait(&mut self, bytes: usize) {
+        let start = *self.start.get_or_insert_with(std::time::Instant::now);
+        self.bytes += bytes as u64;
+
+        let deadline = start + transfer_time(self.bytes, self.throttle.kbps) + self.next_jitter();
+        let now = std::time::Instant::now();
+        if deadline > now {
+            std::thread::sleep(deadline - now);
+        }
+    }
+
+    /// Produce the next jitter delay, between zero and the configured maximum.
+    fn next_jitter(&mut self) -> std::time::Duration {
+        if self.throttle.jitter.is_zero() {
+            return std::time::Duration::ZERO;
+        }
+        let fraction = (xorshift64(&mut self.rng_state) >> 11) as f64 / (1u64 << 53) as f64;
+        self.throttle.jitter.mul_f64(fraction)
+    }
+}
+
+/// Time it takes to transfer `bytes` bytes at `kbps` kilobits per second.
+fn transfer_time(bytes: u64, kbps: u32) -> std::time::Duration {
+    let bits = bytes as u128 * 8;
+    let micros = bits * 1_000 / kbps.max(1) as u128;
+    std::time::Duration::from_micros(micros.min(u64::MAX as u128) as u64)
+}
+
+/// Advance xorshift64 state and return the next value.
+fn xorshift64(state: &mut u64) -> u64 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *state = x;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_time() {
+        // 1000 kbps is 125000 bytes per second.
+        assert_eq!(
+            transfer_time(125_000, 1_000),
+            std::time::Duration::from_secs(1)
+        );
+        assert_eq!(
+            transfer_time(1_000, 8),
+            std::time::Duration::from_secs(1)
+        );
+        assert_eq!(transfer_time(0, 8), std::time::Duration::ZERO);
+    }
+
+    #[test]
+    fn test_jitter_is_deterministic_and_bounded() {
+        let throttle = Throttle::new(1_000)
+            .with_jitter(std::time::Duration::from_millis(50))
+            .with_seed(42);
+        let mut a = Throttler::new(throttle);
+        let mut b = Throttler::new(throttle);
+        for _ in 0..100 {
+            let jitter = a.next_jitter();
+            assert_eq!(jitter, b.next_jitter());
+            assert!(jitter <= std::time::Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn test_no_jitter() {
+        let mut throttler = Throttler::new(Throttle::new(1_000));
+        assert_eq!(throttler.next_jitter(), std::time::Duration::ZERO);
+    }
+}
@@ -0,0 +1,300 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ffmpeg::Rational as AvRational;
+
+use crate::core::burnin::{SubtitleBurner, SubtitleBurnerBuilder};
+use crate::core::color::ColorMetadata;
+use crate::core::decode::{Decoder, DecoderBuilder};
+use crate::core::encode::{Encoder, EncoderBuilder, Pass, Settings};
+use crate::core::error::Error;
+use crate::core::location::Location;
+use crate::core::options::Options;
+use crate::core::resize::Resize;
+use crate::core::time::Time;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Path of the platform null device, which discards everything written to it.
+#[cfg(windows)]
+const NULL_DEVICE: &str = "NUL";
+#[cfg(not(windows))]
+const NULL_DEVICE: &str = "/dev/null";
+
+/// Builds a [`TwoPassTranscoder`].
+pub struct TwoPassTranscoderBuilder<'a> {
+    source: Location,
+    destination: Location,
+    settings: Settings,
+    stats_file: Option<std::path::PathBuf>,
+    input_options: Option<&'a Options>,
+    output_options: Option<&'a Options>,
+    format: Option<&'a str>,
+    burn_in_subtitles: Option<Option<usize>>,
+}
+
+impl<'a> TwoPassTranscoderBuilder<'a> {
+    /// Create a new two-pass transcoder builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Source to decode video from.
+    /// * `destination` - Where to encode to.
+    /// * `settings` - Encoder settings, used for both passes. Set a target bitrate (`b`) in the
+    ///   encoder options to make the most of two-pass encoding.
+    pub fn new(
+        source: impl Into<Location>,
+        destination: impl Into<Location>,
+        settings: Settings,
+    ) -> Self {
+        Self {
+            source: source.into(),
+            destination: destination.into(),
+            settings,
+            stats_file: None,
+            input_options: None,
+            output_options: None,
+            format: None,
+            burn_in_subtitles: None,
+        }
+    }
+
+    /// Set the file to store first pass statistics in. By default a temporary file is used, which
+    /// is removed after transcoding.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats_file` - Path of statistics file.
+    pub fn with_stats_file(mut self, stats_file: impl Into<std::path::PathBuf>) -> Self {
+        self.stats_file = Some(stats_file.into());
+        self
+    }
+
+    /// Set the options for the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to input.
+    pub fn with_input_options(mut self, options: &'a Options) -> Self {
+        self.input_options = Some(options);
+        self
+    }
+
+    /// Set the options for the destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to pass on to output.
+    pub fn with_output_options(mut self, options: &'a Options) -> Self {
+        self.output_options = Some(options);
+        self
+    }
+
+    /// Set the container format of the destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Container format to use.
+    pub fn with_format(mut self, format: &'a str) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Render the best subtitle stream of the source onto the video, producing hardsubbed output.
+    /// See [`SubtitleBurner`] for the subtitle formats that are supported.
+    pub fn with_burned_in_subtitles(mut self) -> Self {
+        self.burn_in_subtitles = Some(None);
+        self
+    }
+
+    /// Render a specific subtitle stream of the source onto the video, producing hardsubbed
+    /// output.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of subtitle stream in source.
+    pub fn with_burned_in_subtitle_stream(mut self, stream_index: usize) -> Self {
+        self.burn_in_subtitles = Some(Some(stream_index));
+        self
+    }
+
+    /// Build [`TwoPassTranscoder`].
+    pub fn build(self) -> Result<TwoPassTranscoder<'a>> {
+        let (stats_file, remove_stats_file) = match self.stats_file {
+            Some(stats_file) => (stats_file, false),
+            None => {
+                // Transcoders that run at the same time in one process must not share a file.
+                static NEXT_STATS_FILE_ID: AtomicU64 = AtomicU64::new(0);
+                let stats_file_id = NEXT_STATS_FILE_ID.fetch_add(1, Ordering::Relaxed);
+                let stats_file_name =
+                    format!("two-pass-{}-{stats_file_id}.log", std::process::id());
+                (std::env::temp_dir().join(stats_file_name), true)
+            }
+        };
+
+        Ok(TwoPassTranscoder {
+            source: self.source,
+            destination: self.destination,
+            settings: self.settings,
+            stats_file,
+            remove_stats_file,
+            input_options: self.input_options,
+            output_options: self.output_options,
+            format: self.format,
+            burn_in_subtitles: self.burn_in_subtitles,
+        })
+    }
+}
+
+/// Transcodes the video stream of a source in two passes. The first pass only analyzes the video
+/// and writes encoder statistics, the second pass uses them to produce the final output.
+///
+/// # Example
+///
+/// ```ignore
+/// let settings = Settings::preset_h264_yuv420p(1280, 720, false);
+/// let mut transcoder = TwoPassTranscoderBuilder::new(
+///     Path::new("input.mp4"),
+///     Path::new("output.mp4"),
+///     settings,
+/// )
+/// .build()
+/// .unwrap();
+/// transcoder.run().unwrap();
+/// ```
+pub struct TwoPassTranscoder<'a> {
+    source: Location,
+    destination: Location,
+    settings: Settings,
+    stats_file: std::path::PathBuf,
+    remove_stats_file: bool,
+    input_options: Option<&'a Options>,
+    output_options: Option<&'a Options>,
+    format: Option<&'a str>,
+    burn_in_subtitles: Option<Option<usize>>,
+}
+
+impl<'a> TwoPassTranscoder<'a> {
+    /// Run both passes.
+    ///
+    /// # Return value
+    ///
+    /// Number of frames encoded in the second pass.
+    pub fn run(&mut self) -> Result<u64> {
+        let result = self
+            .run_pass(Pass::First)
+            .and_then(|_| self.run_pass(Pass::Second));
+        if self.remove_stats_file {
+            let _ = std::fs::remove_file(&self.stats_file);
+        }
+        result
+    }
+
+    /// Run a single pass over the entire source.
+    ///
+    /// # Arguments
+    ///
+    /// * `pass` - Pass to run.
+    fn run_pass(&self, pass: Pass) -> Result<u64> {
+        let mut decoder = self.decoder()?;
+        let mut encoder = self.encoder(pass, self.color_metadata(&decoder))?;
+        // Both passes must see the same frames, so subtitles are burned in during the first pass
+        // as well.
+        let mut subtitle_burner = self.subtitle_burner()?;
+
+        let mut frame_count = 0;
+        loop {
+            let mut frame = match decoder.decode_raw() {
+                Ok(frame) => frame,
+                Err(Error::DecodeExhausted) => break,
+                Err(err) => return Err(err),
+            };
+
+            if let Some(subtitle_burner) = subtitle_burner.as_mut() {
+                frame = subtitle_burner.burn(frame, decoder.time_base())?;
+            }
+            frame.set_pts(
+                Time::new(frame.pts(), decoder.time_base())
+                    .aligned_with_rational(encoder.time_base())
+                    .into_value(),
+            );
+            encoder.encode_raw(frame)?;
+            frame_count += 1;
+        }
+
+        encoder.finish()?;
+        Ok(frame_count)
+    }
+
+    /// Create a decoder that produces frames at the size of the encoder.
+    fn decoder(&self) -> Result<Decoder> {
+        let (width, height) = self.settings.size();
+        let mut decoder_builder =
+            DecoderBuilder::new(self.source.clone()).with_resize(Resize::Exact(width, height));
+        if let Some(options) = self.input_options {
+            decoder_builder = decoder_builder.with_options(options);
+        }
+        decoder_builder.build()
+    }
+
+    /// Create the subtitle burner, if subtitles are to be burned in.
+    fn subtitle_burner(&self) -> Result<Option<SubtitleBurner>> {
+        let Some(stream_index) = self.burn_in_subtitles else {
+            return Ok(None);
+        };
+        let mut subtitle_burner_builder = SubtitleBurnerBuilder::new(self.source.clone());
+        if let Some(stream_index) = stream_index {
+            subtitle_burner_builder = subtitle_burner_builder.with_stream_index(stream_index);
+        }
+        if let Some(options) = self.input_options {
+            subtitle_burner_builder = subtitle_burner_builder.with_options(options);
+        }
+        subtitle_burner_builder.build().map(Some)
+    }
+
+    /// Color metadata to encode with: the metadata from the settings, completed with that of the
+    /// source. The sample aspect ratio of the source is only kept if the video is not resized.
+    ///
+    /// # Arguments
+    ///
+    /// * `decoder` - Decoder of the source.
+    fn color_metadata(&self, decoder: &Decoder) -> ColorMetadata {
+        let mut source_color = decoder.color_metadata();
+        let (width, height) = decoder.size();
+        if self.settings.size() != (width, height) {
+            source_color = source_color.with_sample_aspect_ratio(AvRational::new(0, 1));
+        }
+        self.settings.color_metadata().or(source_color)
+    }
+
+    /// Create the encoder for a pass. The first pass does not produce any output, so it is written
+    /// to the null device using the `null` container format, which discards everything.
+    ///
+    /// # Arguments
+    ///
+    /// * `pass` - Pass to create encoder for.
+    /// * `color` - Color metadata to signal in the output.
+    fn encoder(&self, pass: Pass, color: ColorMetadata) -> Result<Encoder> {
+        let settings = self.settings.clone().with_color_metadata(color);
+        let encoder_builder = match pass {
+            Pass::First => EncoderBuilder::new(Location::File(NULL_DEVICE.into()), settings)
+                .with_format("null"),
+            Pass::Second => {
+                let encoder_builder = EncoderBuilder::new(self.destination.clone(), settings);
+                match self.format {
+                    Some(format) => encoder_builder.with_format(format),
+                    None => encoder_builder,
+                }
+            }
+        };
+        let mut encoder_builder = encoder_builder.with_two_pass(pass, self.stats_file.clone());
+        if let Some(options) = self.output_options {
+            encoder_builder = encoder_builder.with_options(options);
+        }
+        encoder_builder.build()
+    }
+}
+
+unsafe impl Send for TwoPassTranscoder<'_> {}
+unsafe impl Sync for TwoPassTranscoder<'_> {}
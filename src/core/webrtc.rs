@@ -0,0 +1,970 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use ffmpeg::codec::packet::Flags as AvPacketFlags;
+use ffmpeg::codec::packet::Packet as AvPacket;
+use ffmpeg::codec::Id as AvCodecId;
+use ffmpeg::Error as AvError;
+use ffmpeg::Rational as AvRational;
+
+use crate::core::bitstream::{
+    has_parameter_sets, length_prefixed, nal_length_size, parameter_sets, with_data,
+};
+use crate::core::codec::VideoCodec;
+use crate::core::depacketize::{AccessUnit, Depacketizer, RtpCodec, RtpHeader};
+use crate::core::error::Error;
+use crate::core::io::Reader;
+use crate::core::location::Url;
+use crate::core::packet::Packet;
+use crate::core::rtp::{RtpBuf, RtpMuxer, RtpMuxerBuilder};
+use crate::core::sdp::{MediaDescription, MediaKind, SessionDescription};
+use crate::core::stream::StreamInfo;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Direction of the media in a WebRTC session, from the point of view of this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Media is sent to the peer (WHIP).
+    SendOnly,
+    /// Media is received from the peer (WHEP).
+    ReceiveOnly,
+}
+
+impl Direction {
+    /// Name of the direction as used in SDP, e.g. `sendonly`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::SendOnly => "sendonly",
+            Direction::ReceiveOnly => "recvonly",
+        }
+    }
+}
+
+/// Peer connection of a WebRTC stack, such as `webrtc-rs` or `str0m`.
+///
+/// This crate does not implement WebRTC itself. The stack takes care of ICE, DTLS and SRTP (and
+/// RTCP), while this crate provides the RTP packets and the WHIP/WHEP signaling. Implement this
+/// trait on top of the peer connection of the stack.
+pub trait PeerConnection: Send {
+    /// Create an SDP offer with a transceiver for each of the given media, in order and with the
+    /// given payload types and format parameters. The offer must contain all ICE candidates, since
+    /// signaling does not use trickle ICE.
+    ///
+    /// # Arguments
+    ///
+    /// * `media` - Media to offer.
+    /// * `direction` - Direction of all media.
+    fn create_offer(
+        &mut self,
+        media: &[MediaDescription],
+        direction: Direction,
+    ) -> std::io::Result<String>;
+
+    /// Apply the SDP answer of the remote peer and start connecting.
+    ///
+    /// # Arguments
+    ///
+    /// * `answer` - SDP answer.
+    fn set_answer(&mut self, answer: &str) -> std::io::Result<()>;
+
+    /// Send an RTP packet on the transceiver of the media with the given index.
+    ///
+    /// # Arguments
+    ///
+    /// * `media_index` - Index of the media in the offer.
+    /// * `packet` - RTP packet, without encryption.
+    fn write_rtp(&mut self, media_index: usize, packet: &[u8]) -> std::io::Result<()>;
+
+    /// Receive the next RTP packet and the index of the media it belongs to. Blocks until a
+    /// packet arrives.
+    fn read_rtp(&mut self) -> std::io::Result<(usize, Vec<u8>)>;
+}
+
+/// HTTP client for WHIP and WHEP signaling.
+///
+/// This crate does not include an HTTP client, so implement this trait on top of the HTTP client
+/// of the application, e.g. `reqwest` or `ureq`.
+pub trait SignalingClient: Send {
+    /// Send a `POST` request and return the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL to post to.
+    /// * `headers` - Request headers as name and value, including `Content-Type`.
+    /// * `body` - Request body.
+    fn post(
+        &mut self,
+        url: &Url,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> std::io::Result<SignalingResponse>;
+
+    /// Send a `DELETE` request.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL to delete.
+    /// * `headers` - Request headers as name and value.
+    fn delete(&mut self, url: &Url, headers: &[(&str, &str)]) -> std::io::Result<()>;
+}
+
+/// Response to a signaling request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalingResponse {
+    /// HTTP status code.
+    pub status: u16,
+    /// Value of the `Location` header, if any.
+    pub location: Option<String>,
+    /// Response body.
+    pub body: String,
+}
+
+/// Build a [`WhipPublisher`].
+pub struct WhipPublisherBuilder {
+    endpoint: Url,
+    peer_connection: Box<dyn PeerConnection>,
+    signaling_client: Box<dyn SignalingClient>,
+    bearer_token: Option<String>,
+    // RTP muxer of each stream, with the index of the stream in the source and its codec.
+    muxers: Vec<(usize, AvCodecId, RtpMuxer)>,
+}
+
+impl WhipPublisherBuilder {
+    /// Create a new [`WhipPublisherBuilder`].
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - URL of the WHIP endpoint.
+    /// * `peer_connection` - Peer connection of the WebRTC stack.
+    /// * `signaling_client` - HTTP client to signal with.
+    pub fn new(
+        endpoint: Url,
+        peer_connection: impl PeerConnection + 'static,
+        signaling_client: impl SignalingClient + 'static,
+    ) -> Self {
+        Self {
+            endpoint,
+            peer_connection: Box::new(peer_connection),
+            signaling_client: Box::new(signaling_client),
+            bearer_token: None,
+            muxers: Vec::new(),
+        }
+    }
+
+    /// Authenticate with a bearer token, as most WHIP endpoints require.
+    ///
+    /// # Arguments
+    ///
+    /// * `bearer_token` - Token to send in the `Authorization` header.
+    pub fn with_bearer_token(mut self, bearer_token: &str) -> Self {
+        self.bearer_token = Some(bearer_token.to_string());
+        self
+    }
+
+    /// Add a stream to publish based on an input stream from a reader. Only H.264 video and Opus
+    /// audio can be published, since those are the codecs every browser supports. At most one
+    /// video and one audio stream can be added.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_info` - Stream information. Usually this information is retrieved by calling
+    ///   [`Reader::stream_info()`].
+    pub fn with_stream(mut self, stream_info: StreamInfo) -> Result<Self> {
+        let codec_id = stream_info.codec_id();
+        if !matches!(codec_id, AvCodecId::H264 | AvCodecId::OPUS) {
+            return Err(Error::UnsupportedCodec(format!("{codec_id:?}")));
+        }
+        let duplicate = self
+            .muxers
+            .iter()
+            .any(|(_, muxer_codec_id, _)| *muxer_codec_id == codec_id);
+        if duplicate {
+            return Err(Error::InvalidConfiguration {
+                field: "streams",
+                reason: "at most one video and one audio stream can be published".to_string(),
+            });
+        }
+        let stream_index = stream_info.index;
+        let muxer = RtpMuxerBuilder::new()?.with_stream(stream_info)?.build()?;
+        self.muxers.push((stream_index, codec_id, muxer));
+        Ok(self)
+    }
+
+    /// Add the best H.264 video stream and the best Opus audio stream of a reader, if it has them.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Reader to add streams from.
+    pub fn with_streams(mut self, reader: &Reader) -> Result<Self> {
+        for (stream_index, codec_id) in [
+            (reader.best_video_stream_index(), AvCodecId::H264),
+            (reader.best_audio_stream_index(), AvCodecId::OPUS),
+        ] {
+            let Ok(stream_index) = stream_index else {
+                continue;
+            };
+            let stream_info = reader.stream_info(stream_index)?;
+            if stream_info.codec_id() == codec_id {
+                self = self.with_stream(stream_info)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Build [`WhipPublisher`]. Offers the streams to the WHIP endpoint and applies its answer,
+    /// after which the WebRTC stack connects to the endpoint.
+    pub fn build(mut self) -> Result<WhipPublisher> {
+        if self.muxers.is_empty() {
+            return Err(Error::InvalidConfiguration {
+                field: "streams",
+                reason: "at least one stream must be added".to_string(),
+            });
+        }
+        // WebRTC negotiates addresses with ICE, so the media is described without them.
+        let media = self
+            .muxers
+            .iter()
+            .map(|(_, _, muxer)| {
+                let destination = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 9);
+                let mut session_description = muxer.session_description(destination)?;
+                Ok(session_description.media.remove(0))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let offer = self
+            .peer_connection
+            .create_offer(&media, Direction::SendOnly)?;
+        let (resource, answer) = signal(
+            self.signaling_client.as_mut(),
+            &self.endpoint,
+            self.bearer_token.as_deref(),
+            &offer,
+        )?;
+        self.peer_connection.set_answer(&answer)?;
+
+        // Browsers only decode H.264 keyframes that carry the parameter sets in-band.
+        let parameter_sets = self
+            .muxers
+            .iter()
+            .map(|(_, codec_id, muxer)| {
+                if *codec_id != AvCodecId::H264 {
+                    return None;
+                }
+                ParameterSets::from_extradata(muxer.extradata(0).ok()?)
+            })
+            .collect();
+
+        Ok(WhipPublisher {
+            muxers: self.muxers,
+            parameter_sets,
+            peer_connection: self.peer_connection,
+            signaling_client: self.signaling_client,
+            bearer_token: self.bearer_token,
+            resource,
+        })
+    }
+}
+
+/// Publishes H.264 and Opus streams to a WebRTC media server or service with WHIP (WebRTC-HTTP
+/// Ingestion Protocol), so that browsers can watch them with low latency.
+///
+/// Packets passed to [`WhipPublisher::send`] are packetized into RTP and handed to the WebRTC
+/// stack, which encrypts and sends them. The session is ended when the publisher is closed or
+/// dropped.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut publisher = WhipPublisherBuilder::new(endpoint, peer_connection, http_client)
+///     .with_bearer_token("secret")
+///     .with_streams(&reader)?
+///     .build()?;
+/// while let Ok((stream_index, packet)) = reader.read_any() {
+///     publisher.send(stream_index, packet)?;
+/// }
+/// publisher.close()?;
+/// ```
+pub struct WhipPublisher {
+    muxers: Vec<(usize, AvCodecId, RtpMuxer)>,
+    // Parameter sets to put in front of H.264 keyframes, for each stream.
+    parameter_sets: Vec<Option<ParameterSets>>,
+    peer_connection: Box<dyn PeerConnection>,
+    signaling_client: Box<dyn SignalingClient>,
+    bearer_token: Option<String>,
+    // URL of the session resource, which is deleted to end the session.
+    resource: Option<Url>,
+}
+
+impl WhipPublisher {
+    /// Send a packet to the endpoint. Packets of streams that were not added are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_index` - Index of the stream in the source the packet belongs to.
+    /// * `packet` - Packet to send.
+    pub fn send(&mut self, stream_index: usize, packet: Packet) -> Result<()> {
+        let media_index = self
+            .muxers
+            .iter()
+            .position(|(source_index, _, _)| *source_index == stream_index)
+            .ok_or(AvError::StreamNotFound)?;
+        let packet = match &self.parameter_sets[media_index] {
+            Some(parameter_sets) if packet.is_key() => parameter_sets.prepend(packet),
+            _ => packet,
+        };
+        let bufs = self.muxers[media_index].2.mux(packet)?;
+        for buf in bufs {
+            // The WebRTC stack sends its own (encrypted) RTCP.
+            if let RtpBuf::Rtp(buf) = buf {
+                self.peer_connection.write_rtp(media_index, &buf)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// End the session by deleting the session resource at the endpoint.
+    pub fn close(mut self) -> Result<()> {
+        self.end_session()
+    }
+
+    /// Delete the session resource, if it was not deleted yet.
+    fn end_session(&mut self) -> Result<()> {
+        let Some(resource) = self.resource.take() else {
+            return Ok(());
+        };
+        let authorization = authorization(self.bearer_token.as_deref());
+        self.signaling_client
+            .delete(&resource, &headers(None, authorization.as_deref()))?;
+        Ok(())
+    }
+}
+
+impl Drop for WhipPublisher {
+    fn drop(&mut self) {
+        if let Err(err) = self.end_session() {
+            tracing::warn!("failed to end WHIP session: {err}");
+        }
+    }
+}
+
+unsafe impl Send for WhipPublisher {}
+
+/// Parameter sets of an H.264 stream, to put in front of keyframes that do not carry them in-band.
+struct ParameterSets {
+    // Parameter sets in the format of the packets of the stream.
+    data: Vec<u8>,
+    // Size of the NAL unit length prefix of the packets, or `None` for Annex B start codes.
+    nal_length_size: Option<usize>,
+}
+
+impl ParameterSets {
+    /// Get the parameter sets from the extradata of a stream. Returns `None` if it has none.
+    ///
+    /// # Arguments
+    ///
+    /// * `extradata` - Extradata of the stream.
+    fn from_extradata(extradata: &[u8]) -> Option<Self> {
+        let nals = parameter_sets(VideoCodec::H264, extradata);
+        if nals.is_empty() {
+            return None;
+        }
+        let nal_length_size = nal_length_size(VideoCodec::H264, extradata);
+        let data = match nal_length_size {
+            Some(nal_length_size) => length_prefixed(&nals, nal_length_size),
+            None => {
+                let mut data = Vec::new();
+                for nal in &nals {
+                    data.extend_from_slice(&[0, 0, 0, 1]);
+                    data.extend_from_slice(nal);
+                }
+                data
+            }
+        };
+        Some(Self {
+            data,
+            nal_length_size,
+        })
+    }
+
+    /// Put the parameter sets in front of a keyframe, unless it already carries them.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - Keyframe packet.
+    fn prepend(&self, packet: Packet) -> Packet {
+        let (inner, time_base) = packet.into_inner_parts();
+        let data = inner.data().unwrap_or_default();
+        if has_parameter_sets(VideoCodec::H264, data, self.nal_length_size) {
+            return Packet::new(inner, time_base);
+        }
+        let data = [self.data.as_slice(), data].concat();
+        let mut new_inner = with_data(&inner, &data);
+        new_inner.set_stream(inner.stream());
+        Packet::new(new_inner, time_base)
+    }
+}
+
+/// Build a [`WhepPlayer`].
+pub struct WhepPlayerBuilder {
+    endpoint: Url,
+    peer_connection: Box<dyn PeerConnection>,
+    signaling_client: Box<dyn SignalingClient>,
+    bearer_token: Option<String>,
+    video: bool,
+    audio: bool,
+}
+
+impl WhepPlayerBuilder {
+    /// Create a new [`WhepPlayerBuilder`]. By default, both H.264 video and Opus audio are
+    /// requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - URL of the WHEP endpoint.
+    /// * `peer_connection` - Peer connection of the WebRTC stack.
+    /// * `signaling_client` - HTTP client to signal with.
+    pub fn new(
+        endpoint: Url,
+        peer_connection: impl PeerConnection + 'static,
+        signaling_client: impl SignalingClient + 'static,
+    ) -> Self {
+        Self {
+            endpoint,
+            peer_connection: Box::new(peer_connection),
+            signaling_client: Box::new(signaling_client),
+            bearer_token: None,
+            video: true,
+            audio: true,
+        }
+    }
+
+    /// Authenticate with a bearer token.
+    ///
+    /// # Arguments
+    ///
+    /// * `bearer_token` - Token to send in the `Authorization` header.
+    pub fn with_bearer_token(mut self, bearer_token: &str) -> Self {
+        self.bearer_token = Some(bearer_token.to_string());
+        self
+    }
+
+    /// Set whether to request video.
+    ///
+    /// # Arguments
+    ///
+    /// * `video` - Whether to request video.
+    pub fn with_video(mut self, video: bool) -> Self {
+        self.video = video;
+        self
+    }
+
+    /// Set whether to request audio.
+    ///
+    /// # Arguments
+    ///
+    /// * `audio` - Whether to request audio.
+    pub fn with_audio(mut self, audio: bool) -> Self {
+        self.audio = audio;
+        self
+    }
+
+    /// Build [`WhepPlayer`]. Requests the streams from the WHEP endpoint and applies its answer,
+    /// after which the WebRTC stack connects to the endpoint.
+    pub fn build(mut self) -> Result<WhepPlayer> {
+        let mut media = Vec::new();
+        if self.video {
+            media.push(MediaDescription {
+                kind: MediaKind::Video,
+                port: 9,
+                payload_type: WhepPlayer::VIDEO_PAYLOAD_TYPE,
+                encoding_name: "H264".to_string(),
+                clock_rate: WhepPlayer::VIDEO_CLOCK_RATE as u32,
+                channels: None,
+                format_parameters: [
+                    ("level-asymmetry-allowed", "1"),
+                    ("packetization-mode", "1"),
+                    ("profile-level-id", "42e01f"),
+                ]
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+                rtcp_port: Some(9),
+                control: None,
+                retransmission_payload_type: None,
+            });
+        }
+        if self.audio {
+            media.push(MediaDescription {
+                kind: MediaKind::Audio,
+                port: 9,
+                payload_type: WhepPlayer::AUDIO_PAYLOAD_TYPE,
+                encoding_name: "opus".to_string(),
+                clock_rate: WhepPlayer::AUDIO_CLOCK_RATE as u32,
+                channels: Some(2),
+                format_parameters: [("minptime", "10"), ("useinbandfec", "1")]
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                rtcp_port: Some(9),
+                control: None,
+                retransmission_payload_type: None,
+            });
+        }
+        if media.is_empty() {
+            return Err(Error::InvalidConfiguration {
+                field: "media",
+                reason: "video or audio must be requested".to_string(),
+            });
+        }
+
+        let offer = self
+            .peer_connection
+            .create_offer(&media, Direction::ReceiveOnly)?;
+        let (resource, answer) = signal(
+            self.signaling_client.as_mut(),
+            &self.endpoint,
+            self.bearer_token.as_deref(),
+            &offer,
+        )?;
+        self.peer_connection.set_answer(&answer)?;
+
+        Ok(WhepPlayer {
+            media_kinds: media.iter().map(|media| media.kind).collect(),
+            peer_connection: self.peer_connection,
+            signaling_client: self.signaling_client,
+            bearer_token: self.bearer_token,
+            resource,
+            depacketizer: Depacketizer::new(RtpCodec::H264),
+            completed: VecDeque::new(),
+            video_timestamp: None,
+            audio_timestamp: None,
+        })
+    }
+}
+
+/// Plays H.264 and Opus streams from a WebRTC media server or service with WHEP (WebRTC-HTTP
+/// Egress Protocol).
+///
+/// The WebRTC stack receives and decrypts the RTP packets, and the player reassembles them into
+/// packets that can be decoded. Video packets hold one access unit in Annex B format with
+/// timestamps in the 90 kHz RTP clock; audio packets hold one Opus packet with timestamps in the
+/// 48 kHz RTP clock. The timestamps of each stream start at zero. The session is ended when the
+/// player is closed or dropped.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut player = WhepPlayerBuilder::new(endpoint, peer_connection, http_client).build()?;
+/// loop {
+///     let (kind, packet) = player.receive()?;
+///     // Decode the packet.
+/// }
+/// ```
+pub struct WhepPlayer {
+    // Kind of each media in the offer.
+    media_kinds: Vec<MediaKind>,
+    peer_connection: Box<dyn PeerConnection>,
+    signaling_client: Box<dyn SignalingClient>,
+    bearer_token: Option<String>,
+    // URL of the session resource, which is deleted to end the session.
+    resource: Option<Url>,
+    depacketizer: Depacketizer,
+    // Video access units that were completed but not yet returned.
+    completed: VecDeque<AccessUnit>,
+    // RTP timestamp of the previous packet of each stream, and its unwrapped value relative to
+    // the first.
+    video_timestamp: Option<(u32, i64)>,
+    audio_timestamp: Option<(u32, i64)>,
+}
+
+impl WhepPlayer {
+    /// Payload type offered for H.264.
+    const VIDEO_PAYLOAD_TYPE: u8 = 96;
+
+    /// Payload type offered for Opus.
+    const AUDIO_PAYLOAD_TYPE: u8 = 111;
+
+    /// Clock rate of H.264 RTP timestamps.
+    const VIDEO_CLOCK_RATE: i32 = 90_000;
+
+    /// Clock rate of Opus RTP timestamps.
+    const AUDIO_CLOCK_RATE: i32 = 48_000;
+
+    /// Get the time base of the received packets of a kind of media.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of media.
+    pub fn time_base(&self, kind: MediaKind) -> AvRational {
+        match kind {
+            MediaKind::Video => AvRational::new(1, Self::VIDEO_CLOCK_RATE),
+            MediaKind::Audio => AvRational::new(1, Self::AUDIO_CLOCK_RATE),
+        }
+    }
+
+    /// Receive the next packet, and whether it is video or audio. Blocks until a complete packet
+    /// has arrived.
+    pub fn receive(&mut self) -> Result<(MediaKind, Packet)> {
+        loop {
+            if let Some(access_unit) = self.completed.pop_front() {
+                let timestamp = access_unit.timestamp;
+                let mut packet = AvPacket::copy(&access_unit.data);
+                let mut flags = AvPacketFlags::empty();
+                flags.set(AvPacketFlags::KEY, access_unit.is_key);
+                flags.set(AvPacketFlags::CORRUPT, access_unit.is_corrupt);
+                packet.set_flags(flags);
+                return Ok((
+                    MediaKind::Video,
+                    self.timestamped(MediaKind::Video, packet, timestamp),
+                ));
+            }
+
+            let (media_index, buf) = self.peer_connection.read_rtp()?;
+            let Some((header, payload)) = RtpHeader::parse(&buf) else {
+                tracing::debug!("ignoring invalid RTP packet");
+                continue;
+            };
+            match self.media_kinds.get(media_index) {
+                Some(MediaKind::Video) => {
+                    self.completed
+                        .extend(self.depacketizer.push(&header, payload));
+                }
+                Some(MediaKind::Audio) => {
+                    let packet = AvPacket::copy(payload);
+                    return Ok((
+                        MediaKind::Audio,
+                        self.timestamped(MediaKind::Audio, packet, header.timestamp),
+                    ));
+                }
+                None => tracing::debug!("ignoring RTP packet of unknown media {media_index}"),
+            }
+        }
+    }
+
+    /// End the session by deleting the session resource at the endpoint.
+    pub fn close(mut self) -> Result<()> {
+        self.end_session()
+    }
+
+    /// Timestamp a packet relative to the first packet of its stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of media of the packet.
+    /// * `packet` - Packet to timestamp.
+    /// * `timestamp` - RTP timestamp of the packet.
+    fn timestamped(&mut self, kind: MediaKind, mut packet: AvPacket, timestamp: u32) -> Packet {
+        let previous = match kind {
+            MediaKind::Video => &mut self.video_timestamp,
+            MediaKind::Audio => &mut self.audio_timestamp,
+        };
+        let pts = match *previous {
+            // The difference is interpreted as signed, so that the RTP timestamp can wrap around.
+            Some((previous, previous_pts)) => {
+                previous_pts + timestamp.wrapping_sub(previous) as i32 as i64
+            }
+            None => 0,
+        };
+        *previous = Some((timestamp, pts));
+        packet.set_pts(Some(pts));
+        packet.set_dts(None);
+        Packet::new(packet, self.time_base(kind))
+    }
+
+    /// Delete the session resource, if it was not deleted yet.
+    fn end_session(&mut self) -> Result<()> {
+        let Some(resource) = self.resource.take() else {
+            return Ok(());
+        };
+        let authorization = authorization(self.bearer_token.as_deref());
+        self.signaling_client
+            .delete(&resource, &headers(None, authorization.as_deref()))?;
+        Ok(())
+    }
+}
+
+impl Drop for WhepPlayer {
+    fn drop(&mut self) {
+        if let Err(err) = self.end_session() {
+            tracing::warn!("failed to end WHEP session: {err}");
+        }
+    }
+}
+
+unsafe impl Send for WhepPlayer {}
+
+/// Post an SDP offer to a WHIP or WHEP endpoint. Returns the URL of the created session resource
+/// (if the endpoint returned one) and the SDP answer.
+///
+/// # Arguments
+///
+/// * `signaling_client` - HTTP client to signal with.
+/// * `endpoint` - URL of the endpoint.
+/// * `bearer_token` - Token to authenticate with.
+/// * `offer` - SDP offer.
+fn signal(
+    signaling_client: &mut dyn SignalingClient,
+    endpoint: &Url,
+    bearer_token: Option<&str>,
+    offer: &str,
+) -> Result<(Option<Url>, String)> {
+    let authorization = authorization(bearer_token);
+    let response = signaling_client.post(
+        endpoint,
+        &headers(Some("application/sdp"), authorization.as_deref()),
+        offer,
+    )?;
+    if response.status != 201 && response.status != 200 {
+        return Err(Error::SignalingFailed(format!(
+            "endpoint responded with status {}",
+            response.status
+        )));
+    }
+    // The answer must at least be a valid session description.
+    response.body.parse::<SessionDescription>()?;
+    // The location may be relative to the endpoint.
+    let resource = response
+        .location
+        .and_then(|location| endpoint.join(&location).ok());
+    if resource.is_none() {
+        tracing::warn!("endpoint did not return a session resource, the session cannot be ended");
+    }
+    Ok((resource, response.body))
+}
+
+/// Value of the `Authorization` header for a bearer token.
+///
+/// # Arguments
+///
+/// * `bearer_token` - Token to authenticate with.
+fn authorization(bearer_token: Option<&str>) -> Option<String> {
+    bearer_token.map(|bearer_token| format!("Bearer {bearer_token}"))
+}
+
+/// Headers of a signaling request.
+///
+/// # Arguments
+///
+/// * `content_type` - Content type of the body, if there is one.
+/// * `authorization` - Value of the `Authorization` header, if any.
+fn headers<'a>(
+    content_type: Option<&'a str>,
+    authorization: Option<&'a str>,
+) -> Vec<(&'static str, &'a str)> {
+    let mut headers = Vec::new();
+    if let Some(content_type) = content_type {
+        headers.push(("Content-Type", content_type));
+    }
+    if let Some(authorization) = authorization {
+        headers.push(("Authorization", authorization));
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Answer of an endpoint with a single H.264 stream.
+    const ANSWER: &str = "v=0\r\n\
+        o=- 0 0 IN IP4 127.0.0.1\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        m=video 9 UDP/TLS/RTP/SAVPF 96\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=rtpmap:96 H264/90000\r\n";
+
+    /// Signaling client that answers every `POST` with the same response, and records the URLs
+    /// that are deleted.
+    struct MockSignalingClient {
+        response: SignalingResponse,
+        deleted: Arc<Mutex<Vec<Url>>>,
+    }
+
+    impl SignalingClient for MockSignalingClient {
+        fn post(
+            &mut self,
+            _url: &Url,
+            headers: &[(&str, &str)],
+            _body: &str,
+        ) -> std::io::Result<SignalingResponse> {
+            assert!(headers.contains(&("Content-Type", "application/sdp")));
+            Ok(self.response.clone())
+        }
+
+        fn delete(&mut self, url: &Url, _headers: &[(&str, &str)]) -> std::io::Result<()> {
+            self.deleted.lock().unwrap().push(url.clone());
+            Ok(())
+        }
+    }
+
+    /// Peer connection that receives a fixed list of RTP packets.
+    struct MockPeerConnection {
+        received: VecDeque<(usize, Vec<u8>)>,
+    }
+
+    impl PeerConnection for MockPeerConnection {
+        fn create_offer(
+            &mut self,
+            _media: &[MediaDescription],
+            _direction: Direction,
+        ) -> std::io::Result<String> {
+            Ok(String::new())
+        }
+
+        fn set_answer(&mut self, _answer: &str) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn write_rtp(&mut self, _media_index: usize, _packet: &[u8]) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn read_rtp(&mut self) -> std::io::Result<(usize, Vec<u8>)> {
+            self.received
+                .pop_front()
+                .ok_or_else(|| std::io::ErrorKind::UnexpectedEof.into())
+        }
+    }
+
+    fn signaling_client(status: u16, location: Option<&str>, body: &str) -> MockSignalingClient {
+        MockSignalingClient {
+            response: SignalingResponse {
+                status,
+                location: location.map(str::to_string),
+                body: body.to_string(),
+            },
+            deleted: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    fn rtp(sequence_number: u16, timestamp: u32, marker: bool, payload: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        RtpHeader {
+            marker,
+            payload_type: 96,
+            sequence_number,
+            timestamp,
+            ssrc: 1,
+        }
+        .write(&mut buf);
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    fn packet_data(packet: Packet) -> Vec<u8> {
+        let (inner, _) = packet.into_inner_parts();
+        inner.data().unwrap_or_default().to_vec()
+    }
+
+    #[test]
+    fn test_signal_resolves_relative_location() {
+        let endpoint: Url = "https://example.com/whip/endpoint".parse().unwrap();
+        let mut client = signaling_client(201, Some("/whip/session/1"), ANSWER);
+        let (resource, answer) = signal(&mut client, &endpoint, Some("secret"), "offer").unwrap();
+        assert_eq!(
+            resource.unwrap().as_str(),
+            "https://example.com/whip/session/1"
+        );
+        assert_eq!(answer, ANSWER);
+
+        let mut client = signaling_client(200, None, ANSWER);
+        let (resource, _) = signal(&mut client, &endpoint, None, "offer").unwrap();
+        assert_eq!(resource, None);
+    }
+
+    #[test]
+    fn test_signal_rejects_failed_responses() {
+        let endpoint: Url = "https://example.com/whip".parse().unwrap();
+        let mut client = signaling_client(403, None, ANSWER);
+        assert!(matches!(
+            signal(&mut client, &endpoint, None, "offer"),
+            Err(Error::SignalingFailed(_))
+        ));
+        let mut client = signaling_client(201, Some("/session"), "not a session description");
+        assert!(signal(&mut client, &endpoint, None, "offer").is_err());
+    }
+
+    #[test]
+    fn test_whep_player_receive() {
+        let deleted = Arc::new(Mutex::new(Vec::new()));
+        let received = [
+            (0, rtp(1, 1000, false, &[0x67, 0x42])),
+            (0, rtp(2, 1000, true, &[0x65, 0x88])),
+            (1, rtp(7, 500, true, &[0xfc, 0x01])),
+            (0, rtp(3, 4000, true, &[0x41, 0x9a])),
+            (1, rtp(8, 1460, true, &[0xfc, 0x02])),
+        ];
+        let mut player = WhepPlayer {
+            media_kinds: vec![MediaKind::Video, MediaKind::Audio],
+            peer_connection: Box::new(MockPeerConnection {
+                received: received.into_iter().collect(),
+            }),
+            signaling_client: Box::new(MockSignalingClient {
+                response: signaling_client(201, None, ANSWER).response,
+                deleted: deleted.clone(),
+            }),
+            bearer_token: None,
+            resource: Some("https://example.com/whep/session".parse().unwrap()),
+            depacketizer: Depacketizer::new(RtpCodec::H264),
+            completed: VecDeque::new(),
+            video_timestamp: None,
+            audio_timestamp: None,
+        };
+
+        let (kind, packet) = player.receive().unwrap();
+        assert_eq!(kind, MediaKind::Video);
+        assert!(packet.is_key());
+        assert_eq!(packet.pts().into_value(), Some(0));
+        assert_eq!(
+            packet_data(packet),
+            vec![0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x65, 0x88]
+        );
+
+        let (kind, packet) = player.receive().unwrap();
+        assert_eq!(kind, MediaKind::Audio);
+        assert_eq!(packet.pts().into_value(), Some(0));
+        assert_eq!(packet_data(packet), vec![0xfc, 0x01]);
+
+        let (kind, packet) = player.receive().unwrap();
+        assert_eq!(kind, MediaKind::Video);
+        assert!(!packet.is_key());
+        assert_eq!(packet.pts().into_value(), Some(3000));
+
+        let (kind, packet) = player.receive().unwrap();
+        assert_eq!(kind, MediaKind::Audio);
+        assert_eq!(packet.pts().into_value(), Some(960));
+
+        assert!(player.receive().is_err());
+        player.close().unwrap();
+        assert_eq!(deleted.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parameter_sets_are_prepended_once() {
+        let avcc = [
+            1, 0x42, 0, 0x1f, 0xff, 0xe1, 0, 2, 0x67, 0x42, 1, 0, 2, 0x68, 0xce,
+        ];
+        let parameter_sets = ParameterSets::from_extradata(&avcc).unwrap();
+        let time_base = AvRational::new(1, 90_000);
+        let keyframe = |data: &[u8]| {
+            let mut packet = AvPacket::copy(data);
+            packet.set_flags(AvPacketFlags::KEY);
+            Packet::new(packet, time_base)
+        };
+
+        let prepended = parameter_sets.prepend(keyframe(&[0, 0, 0, 2, 0x65, 0x88]));
+        assert_eq!(
+            packet_data(prepended),
+            vec![0, 0, 0, 2, 0x67, 0x42, 0, 0, 0, 2, 0x68, 0xce, 0, 0, 0, 2, 0x65, 0x88]
+        );
+
+        let in_band = [0, 0, 0, 2, 0x67, 0x42, 0, 0, 0, 2, 0x65, 0x88];
+        let unchanged = parameter_sets.prepend(keyframe(&in_band));
+        assert_eq!(packet_data(unchanged), in_band.to_vec());
+    }
+}